@@ -34,6 +34,8 @@ mod wire {
             src_port: 48896,
             dst_port: 80,
             control: TcpControl::Syn,
+            ece: false,
+            cwr: false,
             seq_number: TcpSeqNumber(0x01234567),
             ack_number: None,
             window_len: 0x0123,
@@ -41,6 +43,8 @@ mod wire {
             max_seg_size: None,
             sack_permitted: false,
             sack_ranges: [None, None, None],
+            timestamp: None,
+            md5_signature: None,
             payload: &PAYLOAD_BYTES,
         };
         let mut bytes = vec![0xa5; repr.buffer_len()];
@@ -88,6 +92,7 @@ mod wire {
             next_header: IpProtocol::Tcp,
             payload_len: 100,
             hop_limit: 64,
+            traffic_class: 0,
         };
         let mut bytes = vec![0xa5; repr.buffer_len()];
 
@@ -106,6 +111,7 @@ mod wire {
             next_header: IpProtocol::Tcp,
             payload_len: 100,
             hop_limit: 64,
+            traffic_class: 0,
         };
         let mut bytes = vec![0xa5; repr.buffer_len()];
 
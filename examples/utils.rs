@@ -121,6 +121,18 @@ pub fn add_middleware_options(opts: &mut Options, _free: &mut [&str]) {
         "Chance of corrupting a packet (%)",
         "CHANCE",
     );
+    opts.optopt(
+        "",
+        "duplicate-chance",
+        "Chance of receiving a packet twice (%)",
+        "CHANCE",
+    );
+    opts.optopt(
+        "",
+        "reorder-chance",
+        "Chance of receiving a packet after the next one (%)",
+        "CHANCE",
+    );
     opts.optopt(
         "",
         "size-limit",
@@ -165,6 +177,14 @@ where
         .opt_str("corrupt-chance")
         .map(|s| u8::from_str(&s).unwrap())
         .unwrap_or(0);
+    let duplicate_chance = matches
+        .opt_str("duplicate-chance")
+        .map(|s| u8::from_str(&s).unwrap())
+        .unwrap_or(0);
+    let reorder_chance = matches
+        .opt_str("reorder-chance")
+        .map(|s| u8::from_str(&s).unwrap())
+        .unwrap_or(0);
     let size_limit = matches
         .opt_str("size-limit")
         .map(|s| usize::from_str(&s).unwrap())
@@ -210,6 +230,8 @@ where
     let mut device = FaultInjector::new(device, seed);
     device.set_drop_chance(drop_chance);
     device.set_corrupt_chance(corrupt_chance);
+    device.set_duplicate_chance(duplicate_chance);
+    device.set_reorder_chance(reorder_chance);
     device.set_max_packet_size(size_limit);
     device.set_max_tx_rate(tx_rate_limit);
     device.set_max_rx_rate(rx_rate_limit);
@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use smoltcp::phy::Medium;
+
+fuzz_target!(|data: &[u8]| {
+    smoltcp::fuzz::ingress(Medium::Ppp, data);
+});
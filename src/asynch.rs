@@ -0,0 +1,493 @@
+/*! An executor-agnostic async facade.
+
+A [Stack] owns an interface, its device and a socket set, and provides futures to connect,
+accept, send and receive on TCP sockets. The interface is driven by [Stack::run], which
+polls it and then sleeps until [Interface::poll_at], until a socket is used, or until
+[Stack::wake] is called, whichever comes first.
+
+With the `socket-dns` feature, [Stack::connect_host] also resolves a host name with a DNS
+socket before connecting to it.
+
+Only a [Timer] is needed from the runtime, so the stack can be used with smol, async-std,
+embassy, or any other executor. Devices that cannot wake the driver up when a packet
+arrives should call [Stack::wake] from their receive notification, e.g. an interrupt
+handler or a readiness callback.
+
+```rust,ignore
+let stack = Stack::new(iface, device, sockets);
+let driver = stack.run(MyTimer);
+let app = async {
+    stack.connect(handle, (IpAddress::v4(192, 168, 69, 100), 80), 49500).await?;
+    stack.send(handle, b"GET / HTTP/1.0\r\n\r\n").await?;
+    // ...
+};
+// Run `driver` and `app` concurrently, e.g. with `futures::join!`.
+```
+*/
+
+use core::cell::{Cell, RefCell};
+use core::future::{poll_fn, Future};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use crate::iface::{Interface, SocketHandle, SocketSet};
+use crate::phy::Device;
+#[cfg(feature = "socket-dns")]
+use crate::socket::dns;
+use crate::socket::{tcp, WakerRegistration};
+use crate::time::Instant;
+#[cfg(feature = "socket-dns")]
+use crate::wire::{DnsQueryType, IpAddress};
+use crate::wire::{IpEndpoint, IpListenEndpoint};
+
+/// A source of time and delays, provided by the runtime.
+pub trait Timer {
+    /// The future returned by [delay_until](#tymethod.delay_until).
+    ///
+    /// Timers whose futures are not `Unpin` can return them boxed with `Box::pin`.
+    type Delay: Future<Output = ()> + Unpin;
+
+    /// Return the current time.
+    fn now(&mut self) -> Instant;
+
+    /// Return a future that completes at `deadline`, or right away if it is in the past.
+    fn delay_until(&mut self, deadline: Instant) -> Self::Delay;
+}
+
+/// Error returned by the futures of a [Stack].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The operation is not permitted in the current state of the socket.
+    InvalidState,
+    /// The remote endpoint cannot be reached.
+    Unaddressable,
+    /// The connection was refused or reset by the remote endpoint.
+    ConnectionReset,
+    /// The host name is not a valid DNS name.
+    InvalidName,
+    /// The host name could not be resolved to any address.
+    NameNotFound,
+}
+
+/// The address family tried first when connecting to a host name.
+#[cfg(feature = "socket-dns")]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressPreference {
+    /// Try IPv6 addresses first, as recommended by RFC 6724.
+    #[default]
+    Ipv6,
+    /// Try IPv4 addresses first.
+    Ipv4,
+}
+
+struct Inner<'a, D> {
+    iface: Interface<'a>,
+    device: D,
+    sockets: SocketSet<'a>,
+}
+
+/// An interface, its device and a socket set, shared between a driver and futures.
+pub struct Stack<'a, D> {
+    inner: RefCell<Inner<'a, D>>,
+    driver: RefCell<WakerRegistration>,
+    woken: Cell<bool>,
+}
+
+/// Return whether a socket in this state may still be able to transfer data later.
+fn is_opening(state: tcp::State) -> bool {
+    matches!(state, tcp::State::SynSent | tcp::State::SynReceived)
+}
+
+impl<'a, D> Stack<'a, D>
+where
+    D: Device,
+{
+    /// Create a stack out of an interface, the device it uses, and a socket set.
+    pub fn new(iface: Interface<'a>, device: D, sockets: SocketSet<'a>) -> Stack<'a, D> {
+        Stack {
+            inner: RefCell::new(Inner {
+                iface,
+                device,
+                sockets,
+            }),
+            driver: RefCell::new(WakerRegistration::new()),
+            woken: Cell::new(false),
+        }
+    }
+
+    /// Call `f` with the interface and the socket set, then wake the driver up.
+    ///
+    /// # Panics
+    /// This function panics if called from within `f`.
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Interface<'a>, &mut SocketSet<'a>) -> R,
+    {
+        let result = {
+            let mut inner = self.inner.borrow_mut();
+            let inner = &mut *inner;
+            f(&mut inner.iface, &mut inner.sockets)
+        };
+        self.wake();
+        result
+    }
+
+    /// Wake the driver up, so that it polls the interface again.
+    pub fn wake(&self) {
+        self.woken.set(true);
+        self.driver.borrow_mut().wake();
+    }
+
+    /// Drive the interface forever.
+    ///
+    /// This future never completes, and must be polled concurrently with the futures
+    /// using the sockets.
+    pub async fn run<T: Timer>(&self, mut timer: T) {
+        loop {
+            let poll_at = {
+                let mut inner = self.inner.borrow_mut();
+                let inner = &mut *inner;
+                let timestamp = timer.now();
+                if let Err(err) = inner
+                    .iface
+                    .poll(timestamp, &mut inner.device, &mut inner.sockets)
+                {
+                    net_debug!("poll error: {}", err);
+                }
+                inner.iface.poll_at(timestamp, &inner.sockets)
+            };
+
+            let mut delay = poll_at.map(|poll_at| timer.delay_until(poll_at));
+            poll_fn(|cx| {
+                if self.woken.replace(false) {
+                    return Poll::Ready(());
+                }
+                if let Some(delay) = delay.as_mut() {
+                    if Pin::new(delay).poll(cx).is_ready() {
+                        return Poll::Ready(());
+                    }
+                }
+                self.driver.borrow_mut().register(cx.waker());
+                Poll::Pending
+            })
+            .await
+        }
+    }
+
+    /// Poll `f` with a TCP socket until it is ready, waking the driver up once it is.
+    async fn poll_tcp<F, R>(&self, handle: SocketHandle, mut f: F) -> R
+    where
+        F: FnMut(&mut tcp::Socket<'a>, &mut Context) -> Poll<R>,
+    {
+        let result = poll_fn(|cx| {
+            let mut inner = self.inner.borrow_mut();
+            f(inner.sockets.get_mut::<tcp::Socket>(handle), cx)
+        })
+        .await;
+        self.wake();
+        result
+    }
+
+    /// Connect a TCP socket to a remote endpoint, and wait until the connection is
+    /// established.
+    pub async fn connect<T, U>(
+        &self,
+        handle: SocketHandle,
+        remote: T,
+        local: U,
+    ) -> Result<(), Error>
+    where
+        T: Into<IpEndpoint>,
+        U: Into<IpListenEndpoint>,
+    {
+        self.with(|iface, sockets| {
+            sockets
+                .get_mut::<tcp::Socket>(handle)
+                .connect(iface.context(), remote, local)
+        })
+        .map_err(|err| match err {
+            tcp::ConnectError::InvalidState => Error::InvalidState,
+            tcp::ConnectError::Unaddressable => Error::Unaddressable,
+        })?;
+
+        self.poll_tcp(handle, |socket, cx| match socket.state() {
+            state if is_opening(state) => {
+                socket.register_send_waker(cx.waker());
+                Poll::Pending
+            }
+            tcp::State::Closed | tcp::State::TimeWait => Poll::Ready(Err(Error::ConnectionReset)),
+            _ => Poll::Ready(Ok(())),
+        })
+        .await
+    }
+
+    /// Wait until a listening TCP socket is connected, and return the remote endpoint.
+    pub async fn accept(&self, handle: SocketHandle) -> Result<IpEndpoint, Error> {
+        self.poll_tcp(handle, |socket, cx| match socket.state() {
+            tcp::State::Listen | tcp::State::SynReceived => {
+                socket.register_recv_waker(cx.waker());
+                Poll::Pending
+            }
+            tcp::State::Closed => Poll::Ready(Err(Error::InvalidState)),
+            _ => Poll::Ready(socket.remote_endpoint().ok_or(Error::ConnectionReset)),
+        })
+        .await
+    }
+
+    /// Send data on a TCP socket, and return how much was sent once at least one octet
+    /// could be enqueued.
+    pub async fn send(&self, handle: SocketHandle, data: &[u8]) -> Result<usize, Error> {
+        self.poll_tcp(handle, |socket, cx| {
+            if !socket.may_send() && !is_opening(socket.state()) {
+                return Poll::Ready(Err(Error::ConnectionReset));
+            }
+            match socket.send_slice(data) {
+                Ok(0) if !data.is_empty() => {
+                    socket.register_send_waker(cx.waker());
+                    Poll::Pending
+                }
+                Ok(size) => Poll::Ready(Ok(size)),
+                Err(tcp::SendError::InvalidState) => {
+                    socket.register_send_waker(cx.waker());
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    /// Receive data from a TCP socket, and return how much was received once at least
+    /// one octet is available.
+    ///
+    /// 0 is returned once the remote endpoint has closed the connection.
+    pub async fn recv(&self, handle: SocketHandle, buf: &mut [u8]) -> Result<usize, Error> {
+        self.poll_tcp(handle, |socket, cx| match socket.recv_slice(buf) {
+            Ok(0) if !buf.is_empty() => {
+                socket.register_recv_waker(cx.waker());
+                Poll::Pending
+            }
+            Ok(size) => Poll::Ready(Ok(size)),
+            Err(tcp::RecvError::Finished) => Poll::Ready(Ok(0)),
+            Err(tcp::RecvError::InvalidState) if is_opening(socket.state()) => {
+                socket.register_recv_waker(cx.waker());
+                Poll::Pending
+            }
+            Err(tcp::RecvError::InvalidState) => Poll::Ready(Err(Error::ConnectionReset)),
+        })
+        .await
+    }
+
+    /// Resolve `name` with a DNS socket, and return the addresses of type `query_type`.
+    #[cfg(feature = "socket-dns")]
+    pub async fn resolve(
+        &self,
+        handle: SocketHandle,
+        name: &str,
+        query_type: DnsQueryType,
+    ) -> Result<heapless::Vec<IpAddress, { dns::MAX_ADDRESS_COUNT }>, Error> {
+        let query = self
+            .with(|iface, sockets| {
+                sockets.get_mut::<dns::Socket>(handle).start_query(
+                    iface.context(),
+                    name,
+                    query_type,
+                )
+            })
+            .map_err(|err| match err {
+                dns::StartQueryError::NoFreeSlot => Error::InvalidState,
+                dns::StartQueryError::InvalidName | dns::StartQueryError::NameTooLong => {
+                    Error::InvalidName
+                }
+            })?;
+
+        poll_fn(|cx| {
+            let mut inner = self.inner.borrow_mut();
+            let socket = inner.sockets.get_mut::<dns::Socket>(handle);
+            match socket.get_query_result(query) {
+                Ok(addrs) if addrs.is_empty() => Poll::Ready(Err(Error::NameNotFound)),
+                Ok(addrs) => Poll::Ready(Ok(addrs)),
+                Err(dns::GetQueryResultError::Pending) => {
+                    socket.register_query_waker(query, cx.waker());
+                    Poll::Pending
+                }
+                Err(dns::GetQueryResultError::Failed) => Poll::Ready(Err(Error::NameNotFound)),
+            }
+        })
+        .await
+    }
+
+    /// Resolve `name` with a DNS socket, and connect a TCP socket to `port` on the first
+    /// of its addresses that accepts the connection. Return the connected endpoint.
+    ///
+    /// Both A and AAAA records are queried, for the protocols that are enabled. The
+    /// addresses are then tried one at a time, alternating between the families and
+    /// starting with `preference`, so that a host unreachable over one family is reached
+    /// over the other. While an address is tried, the TCP socket is in the `SynSent`
+    /// state and [remote_endpoint] returns it. Set a [timeout] on the socket so that an
+    /// address that does not answer is given up.
+    ///
+    /// [remote_endpoint]: tcp::Socket::remote_endpoint
+    /// [timeout]: tcp::Socket::set_timeout
+    #[cfg(feature = "socket-dns")]
+    pub async fn connect_host<U>(
+        &self,
+        handle: SocketHandle,
+        dns_handle: SocketHandle,
+        name: &str,
+        port: u16,
+        local: U,
+        preference: AddressPreference,
+    ) -> Result<IpEndpoint, Error>
+    where
+        U: Into<IpListenEndpoint>,
+    {
+        let query_types = match preference {
+            AddressPreference::Ipv6 => [DnsQueryType::Aaaa, DnsQueryType::A],
+            AddressPreference::Ipv4 => [DnsQueryType::A, DnsQueryType::Aaaa],
+        };
+        let mut results = [heapless::Vec::new(), heapless::Vec::new()];
+        let mut error = Error::NameNotFound;
+        for (query_type, result) in query_types.iter().zip(results.iter_mut()) {
+            let enabled = match *query_type {
+                DnsQueryType::A => cfg!(feature = "proto-ipv4"),
+                _ => cfg!(feature = "proto-ipv6"),
+            };
+            if !enabled {
+                continue;
+            }
+            match self.resolve(dns_handle, name, *query_type).await {
+                Ok(addrs) => *result = addrs,
+                Err(Error::NameNotFound) => (),
+                Err(err) => error = err,
+            }
+        }
+
+        let [preferred, fallback] = results;
+        let mut preferred = preferred.into_iter();
+        let mut fallback = fallback.into_iter();
+        let local = local.into();
+        loop {
+            let addr = match preferred.next().or_else(|| fallback.next()) {
+                Some(addr) => addr,
+                None => return Err(error),
+            };
+            // Swap the families, so that the next attempt uses the other one if it can.
+            core::mem::swap(&mut preferred, &mut fallback);
+
+            let remote = IpEndpoint::new(addr, port);
+            match self.connect(handle, remote, local).await {
+                Ok(()) => return Ok(remote),
+                Err(err @ (Error::ConnectionReset | Error::Unaddressable)) => {
+                    net_debug!("connecting to {} failed: {:?}", remote, err);
+                    error = err
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Close the transmit half of a TCP connection.
+    pub fn close(&self, handle: SocketHandle) {
+        self.with(|_, sockets| sockets.get_mut::<tcp::Socket>(handle).close())
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "medium-ip", feature = "proto-ipv4"))]
+mod test {
+    use std::boxed::Box;
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    use super::*;
+    use crate::iface::InterfaceBuilder;
+    use crate::phy::{Loopback, Medium};
+    use crate::wire::{IpAddress, IpCidr};
+
+    /// A delay that completes the second time it is polled.
+    struct Yield(bool);
+
+    impl Future for Yield {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    struct TestTimer;
+
+    impl Timer for TestTimer {
+        type Delay = Yield;
+
+        fn now(&mut self) -> Instant {
+            Instant::now()
+        }
+
+        fn delay_until(&mut self, _deadline: Instant) -> Yield {
+            Yield(false)
+        }
+    }
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    #[test]
+    fn test_tcp() {
+        let mut device = Loopback::new(Medium::Ip);
+        let mut ip_addrs = heapless::Vec::<IpCidr, 5>::new();
+        ip_addrs
+            .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
+            .unwrap();
+        let iface = InterfaceBuilder::new()
+            .ip_addrs(ip_addrs)
+            .finalize(&mut device);
+        let mut sockets = SocketSet::new(vec![]);
+        let socket = || {
+            tcp::Socket::new(
+                tcp::SocketBuffer::new(vec![0; 64]),
+                tcp::SocketBuffer::new(vec![0; 64]),
+            )
+        };
+        let server = sockets.add(socket());
+        let client = sockets.add(socket());
+        sockets.get_mut::<tcp::Socket>(server).listen(80).unwrap();
+        let stack = Stack::new(iface, device, sockets);
+
+        let mut driver = Box::pin(stack.run(TestTimer));
+        let mut app = Box::pin(async {
+            stack
+                .connect(client, (IpAddress::v4(127, 0, 0, 1), 80), 49152)
+                .await
+                .unwrap();
+            assert_eq!(
+                stack.accept(server).await,
+                Ok((IpAddress::v4(127, 0, 0, 1), 49152).into())
+            );
+            assert_eq!(stack.send(client, b"hello").await, Ok(5));
+            let mut buf = [0; 16];
+            assert_eq!(stack.recv(server, &mut buf).await, Ok(5));
+            assert_eq!(&buf[..5], b"hello");
+        });
+
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        for _ in 0..100 {
+            assert!(driver.as_mut().poll(&mut cx).is_pending());
+            if app.as_mut().poll(&mut cx).is_ready() {
+                return;
+            }
+        }
+        panic!("the connection did not make progress");
+    }
+}
@@ -0,0 +1,979 @@
+/*! A BSD sockets compatibility layer.
+
+This module offers the familiar `socket`/`bind`/`listen`/`accept`/`connect`/`send`/`recv`/`select`
+calls on top of a socket set and the interface poll loop, to ease porting application code
+written against BSD sockets or the lwIP sockets API.
+
+A [Stack] owns an interface and its device, and hands out [Fd] descriptors. By default,
+the calls block the way they do on a host operating system: they poll the interface until
+they can complete. A descriptor can be switched to non-blocking mode with
+[Stack::set_nonblocking], in which case the calls return [io::ErrorKind::WouldBlock] instead,
+and [Stack::select] or [Stack::poll] must be used to make progress.
+
+```rust,no_run
+# #[cfg(all(feature = "phy-tuntap_interface", feature = "medium-ip"))]
+# fn main() -> std::io::Result<()> {
+# use smoltcp::iface::InterfaceBuilder;
+# use smoltcp::phy::{Medium, TunTapInterface};
+# use smoltcp::wire::{IpAddress, IpCidr};
+use smoltcp::bsd::{SocketType, Stack};
+
+let mut device = TunTapInterface::new("tun0", Medium::Ip)?;
+let mut ip_addrs = heapless::Vec::<IpCidr, 5>::new();
+ip_addrs
+    .push(IpCidr::new(IpAddress::v4(192, 168, 69, 1), 24))
+    .unwrap();
+let iface = InterfaceBuilder::new()
+    .ip_addrs(ip_addrs)
+    .finalize(&mut device);
+let mut stack = Stack::new(iface, device);
+
+let fd = stack.socket(SocketType::Stream);
+stack.connect(fd, (IpAddress::v4(192, 168, 69, 100), 80).into())?;
+stack.send(fd, b"GET / HTTP/1.0\r\n\r\n")?;
+let mut buf = [0; 1024];
+let len = stack.recv(fd, &mut buf)?;
+stack.close(fd)?;
+# Ok(())
+# }
+# #[cfg(not(all(feature = "phy-tuntap_interface", feature = "medium-ip")))]
+# fn main() {}
+```
+
+Unlike on a host operating system, a listening descriptor can only hold as many pending
+connections as its backlog, since each of them needs a TCP socket with its own buffers.
+*/
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io;
+use std::net::Shutdown;
+use std::thread;
+use std::vec;
+use std::vec::Vec;
+
+use crate::iface::{Interface, SocketHandle, SocketSet};
+use crate::phy::Device;
+use crate::socket::{tcp, udp};
+use crate::time::{Duration, Instant};
+use crate::wire::{IpEndpoint, IpListenEndpoint};
+
+/// The longest time to sleep for while blocking, since the device cannot wake us up.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The range of ports used for sockets that are not bound to a specific port.
+const EPHEMERAL_PORTS: core::ops::RangeInclusive<u16> = 49152..=65535;
+
+/// A socket descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fd(usize);
+
+impl Fd {
+    /// Return the index of the descriptor, which is the lowest one unused when it was created.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for Fd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The type of a socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketType {
+    /// A TCP socket, like `SOCK_STREAM`.
+    Stream,
+    /// A UDP socket, like `SOCK_DGRAM`.
+    Datagram,
+}
+
+/// The sizes of the buffers allocated for new sockets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferSizes {
+    /// The size of the receive buffer of TCP sockets, in octets.
+    pub tcp_rx: usize,
+    /// The size of the transmit buffer of TCP sockets, in octets.
+    pub tcp_tx: usize,
+    /// The number of datagrams each buffer of UDP sockets can hold.
+    pub udp_packets: usize,
+    /// The size of the payload storage of each buffer of UDP sockets, in octets.
+    pub udp_payload: usize,
+}
+
+impl Default for BufferSizes {
+    fn default() -> BufferSizes {
+        BufferSizes {
+            tcp_rx: 4096,
+            tcp_tx: 4096,
+            udp_packets: 8,
+            udp_payload: 4096,
+        }
+    }
+}
+
+/// A set of descriptors, like `fd_set`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FdSet {
+    fds: BTreeSet<Fd>,
+}
+
+impl FdSet {
+    /// Create an empty set.
+    pub fn new() -> FdSet {
+        FdSet::default()
+    }
+
+    /// Add a descriptor to the set, like `FD_SET`.
+    pub fn insert(&mut self, fd: Fd) {
+        self.fds.insert(fd);
+    }
+
+    /// Remove a descriptor from the set, like `FD_CLR`.
+    pub fn remove(&mut self, fd: Fd) {
+        self.fds.remove(&fd);
+    }
+
+    /// Return whether the set contains a descriptor, like `FD_ISSET`.
+    pub fn contains(&self, fd: Fd) -> bool {
+        self.fds.contains(&fd)
+    }
+
+    /// Remove all descriptors from the set, like `FD_ZERO`.
+    pub fn clear(&mut self) {
+        self.fds.clear()
+    }
+
+    /// Return the number of descriptors in the set.
+    pub fn len(&self) -> usize {
+        self.fds.len()
+    }
+
+    /// Return whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.fds.is_empty()
+    }
+
+    /// Iterate over the descriptors in the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = Fd> + '_ {
+        self.fds.iter().copied()
+    }
+}
+
+#[derive(Debug)]
+enum Kind {
+    Tcp(SocketHandle),
+    Listener(Vec<SocketHandle>),
+    Udp {
+        handle: SocketHandle,
+        remote: Option<IpEndpoint>,
+    },
+}
+
+#[derive(Debug)]
+struct Descriptor {
+    kind: Kind,
+    bound: Option<IpListenEndpoint>,
+    /// Whether the outcome of a `connect` that would have blocked is yet to be reported.
+    connecting: bool,
+    nonblocking: bool,
+    timeout: Option<Duration>,
+}
+
+impl Descriptor {
+    fn is_stream(&self) -> bool {
+        !matches!(self.kind, Kind::Udp { .. })
+    }
+}
+
+fn bad_descriptor() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "bad descriptor")
+}
+
+fn invalid_input(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Return whether a listening socket holds a connection that can be accepted.
+fn is_acceptable(socket: &tcp::Socket) -> bool {
+    !matches!(socket.state(), tcp::State::Listen | tcp::State::SynReceived)
+}
+
+/// Return whether a socket in this state may still be able to transfer data later.
+fn is_opening(state: tcp::State) -> bool {
+    matches!(state, tcp::State::SynSent | tcp::State::SynReceived)
+}
+
+/// An interface, its device, and the sockets behind a set of descriptors.
+pub struct Stack<D> {
+    iface: Interface<'static>,
+    device: D,
+    sockets: SocketSet<'static>,
+    descriptors: Vec<Option<Descriptor>>,
+    closing: Vec<SocketHandle>,
+    buffer_sizes: BufferSizes,
+    next_port: u16,
+}
+
+impl<D> Stack<D>
+where
+    D: Device,
+{
+    /// Create a stack out of an interface and the device it uses.
+    pub fn new(iface: Interface<'static>, device: D) -> Stack<D> {
+        Stack {
+            iface,
+            device,
+            sockets: SocketSet::new(vec![]),
+            descriptors: Vec::new(),
+            closing: Vec::new(),
+            buffer_sizes: BufferSizes::default(),
+            next_port: *EPHEMERAL_PORTS.start(),
+        }
+    }
+
+    /// Return a reference to the interface.
+    pub fn iface(&self) -> &Interface<'static> {
+        &self.iface
+    }
+
+    /// Return a mutable reference to the interface.
+    pub fn iface_mut(&mut self) -> &mut Interface<'static> {
+        &mut self.iface
+    }
+
+    /// Return a mutable reference to the device.
+    pub fn device_mut(&mut self) -> &mut D {
+        &mut self.device
+    }
+
+    /// Set the sizes of the buffers allocated for sockets created from now on.
+    pub fn set_buffer_sizes(&mut self, buffer_sizes: BufferSizes) {
+        self.buffer_sizes = buffer_sizes
+    }
+
+    /// Transmit and receive packets, and return whether the state of any socket may have
+    /// changed.
+    ///
+    /// This only needs to be called when all descriptors are non-blocking, and [select]
+    /// is not used.
+    ///
+    /// [select]: #method.select
+    pub fn poll(&mut self) -> bool {
+        let changed = match self
+            .iface
+            .poll(Instant::now(), &mut self.device, &mut self.sockets)
+        {
+            Ok(changed) => changed,
+            Err(err) => {
+                net_debug!("poll error: {}", err);
+                true
+            }
+        };
+
+        // Release the sockets of closed descriptors once their connection is over.
+        let sockets = &mut self.sockets;
+        self.closing.retain(|&handle| {
+            if sockets.get::<tcp::Socket>(handle).state() == tcp::State::Closed {
+                sockets.remove(handle);
+                false
+            } else {
+                true
+            }
+        });
+
+        changed
+    }
+
+    /// Create a socket, and return the lowest unused descriptor for it, like `socket`.
+    pub fn socket(&mut self, socket_type: SocketType) -> Fd {
+        let kind = match socket_type {
+            SocketType::Stream => Kind::Tcp(self.add_tcp_socket()),
+            SocketType::Datagram => {
+                let sizes = self.buffer_sizes;
+                let buffer = || {
+                    udp::PacketBuffer::new(
+                        vec![udp::PacketMetadata::EMPTY; sizes.udp_packets],
+                        vec![0; sizes.udp_payload],
+                    )
+                };
+                Kind::Udp {
+                    handle: self.sockets.add(udp::Socket::new(buffer(), buffer())),
+                    remote: None,
+                }
+            }
+        };
+        self.add_descriptor(kind, None)
+    }
+
+    /// Switch a descriptor to or from non-blocking mode, like `O_NONBLOCK`.
+    pub fn set_nonblocking(&mut self, fd: Fd, nonblocking: bool) -> io::Result<()> {
+        self.descriptor_mut(fd)?.nonblocking = nonblocking;
+        Ok(())
+    }
+
+    /// Set how long blocking calls on a descriptor may wait before failing with
+    /// [io::ErrorKind::TimedOut], like `SO_RCVTIMEO` and `SO_SNDTIMEO`.
+    ///
+    /// `None`, the default, waits forever.
+    pub fn set_timeout(&mut self, fd: Fd, timeout: Option<Duration>) -> io::Result<()> {
+        self.descriptor_mut(fd)?.timeout = timeout;
+        Ok(())
+    }
+
+    /// Assign a local endpoint to a socket, like `bind`.
+    ///
+    /// A port of 0 picks an unused ephemeral port.
+    pub fn bind(&mut self, fd: Fd, endpoint: IpListenEndpoint) -> io::Result<()> {
+        let descriptor = self.descriptor(fd)?;
+        if descriptor.bound.is_some() || matches!(descriptor.kind, Kind::Listener(_)) {
+            return Err(invalid_input("already bound"));
+        }
+        let is_stream = descriptor.is_stream();
+        let endpoint = if endpoint.port == 0 {
+            IpListenEndpoint {
+                port: self.ephemeral_port(is_stream)?,
+                ..endpoint
+            }
+        } else if self.is_port_in_use(is_stream, endpoint) {
+            return Err(io::ErrorKind::AddrInUse.into());
+        } else {
+            endpoint
+        };
+
+        if let Kind::Udp { handle, .. } = self.descriptor(fd)?.kind {
+            self.sockets
+                .get_mut::<udp::Socket>(handle)
+                .bind(endpoint)
+                .map_err(|err| match err {
+                    udp::BindError::InvalidState => invalid_input("already bound"),
+                    udp::BindError::Unaddressable => io::ErrorKind::AddrNotAvailable.into(),
+                })?;
+        }
+        self.descriptor_mut(fd)?.bound = Some(endpoint);
+        Ok(())
+    }
+
+    /// Listen for connections on a bound TCP socket, like `listen`.
+    ///
+    /// Up to `backlog` connections, and at least one, are accepted in the background
+    /// until [accept] is called.
+    ///
+    /// [accept]: #method.accept
+    pub fn listen(&mut self, fd: Fd, backlog: usize) -> io::Result<()> {
+        let descriptor = self.descriptor(fd)?;
+        let (handle, endpoint) = match (&descriptor.kind, descriptor.bound) {
+            (Kind::Tcp(handle), Some(endpoint))
+                if self.sockets.get::<tcp::Socket>(*handle).state() == tcp::State::Closed =>
+            {
+                (*handle, endpoint)
+            }
+            (Kind::Listener(_), _) => return Ok(()),
+            _ => return Err(invalid_input("not a bound stream socket")),
+        };
+
+        let mut handles = vec![handle];
+        handles.extend((1..backlog).map(|_| self.add_tcp_socket()));
+        for &handle in &handles {
+            if let Err(err) = self.sockets.get_mut::<tcp::Socket>(handle).listen(endpoint) {
+                net_debug!("cannot listen on {}: {:?}", endpoint, err);
+                for &handle in &handles[1..] {
+                    self.sockets.remove(handle);
+                }
+                return Err(io::ErrorKind::AddrNotAvailable.into());
+            }
+        }
+        self.descriptor_mut(fd)?.kind = Kind::Listener(handles);
+        Ok(())
+    }
+
+    /// Wait for a connection on a listening socket, and return a new descriptor for it
+    /// together with the remote endpoint, like `accept`.
+    pub fn accept(&mut self, fd: Fd) -> io::Result<(Fd, IpEndpoint)> {
+        let handle = self.block_on(fd, |stack| {
+            let handles = match &stack.descriptor(fd)?.kind {
+                Kind::Listener(handles) => handles,
+                _ => return Err(invalid_input("not a listening socket")),
+            };
+            handles
+                .iter()
+                .position(|&handle| is_acceptable(stack.sockets.get::<tcp::Socket>(handle)))
+                .ok_or_else(|| io::ErrorKind::WouldBlock.into())
+        })?;
+
+        let endpoint = self.descriptor(fd)?.bound.unwrap();
+        let replacement = self.add_tcp_socket();
+        self.sockets
+            .get_mut::<tcp::Socket>(replacement)
+            .listen(endpoint)
+            .expect("endpoint was accepted before");
+        let handle = match &mut self.descriptor_mut(fd)?.kind {
+            Kind::Listener(handles) => core::mem::replace(&mut handles[handle], replacement),
+            _ => unreachable!(),
+        };
+
+        // A connection that was reset before being accepted has no remote endpoint anymore.
+        match self.sockets.get::<tcp::Socket>(handle).remote_endpoint() {
+            Some(remote) => Ok((self.add_descriptor(Kind::Tcp(handle), None), remote)),
+            None => {
+                self.sockets.remove(handle);
+                Err(io::ErrorKind::ConnectionAborted.into())
+            }
+        }
+    }
+
+    /// Connect a socket to a remote endpoint, like `connect`.
+    ///
+    /// For a TCP socket, this waits until the connection is established; in non-blocking
+    /// mode, it returns [io::ErrorKind::WouldBlock] while the connection is in progress,
+    /// and can be called again to find out whether it succeeded. For a UDP socket, this sets
+    /// the remote endpoint used by [send], without filtering received datagrams.
+    ///
+    /// [send]: #method.send
+    pub fn connect(&mut self, fd: Fd, remote: IpEndpoint) -> io::Result<()> {
+        let descriptor = self.descriptor(fd)?;
+        let is_stream = descriptor.is_stream();
+        let bound = descriptor.bound;
+        let connecting = descriptor.connecting;
+        let handle = match descriptor.kind {
+            Kind::Tcp(handle) => Some(handle),
+            Kind::Listener(_) => return Err(invalid_input("socket is listening")),
+            Kind::Udp { .. } => None,
+        };
+        let local = match bound {
+            Some(endpoint) => endpoint,
+            None => IpListenEndpoint::from(self.ephemeral_port(is_stream)?),
+        };
+
+        let handle = match handle {
+            Some(handle) => handle,
+            None => {
+                self.bind_udp(fd, local)?;
+                if let Kind::Udp { remote: r, .. } = &mut self.descriptor_mut(fd)?.kind {
+                    *r = Some(remote);
+                }
+                return Ok(());
+            }
+        };
+
+        let socket = self.sockets.get_mut::<tcp::Socket>(handle);
+        match socket.state() {
+            tcp::State::Closed if !connecting => {
+                socket
+                    .connect(self.iface.context(), remote, local)
+                    .map_err(|err| match err {
+                        tcp::ConnectError::InvalidState => invalid_input("already connected"),
+                        tcp::ConnectError::Unaddressable => {
+                            io::Error::from(io::ErrorKind::AddrNotAvailable)
+                        }
+                    })?;
+                let descriptor = self.descriptor_mut(fd)?;
+                descriptor.bound = Some(local);
+                descriptor.connecting = true;
+            }
+            // Like on Linux, calling `connect` again reports the outcome of a non-blocking
+            // connection attempt once it is known, and only then fails.
+            state if is_opening(state) || connecting => (),
+            _ => return Err(invalid_input("already connected")),
+        }
+
+        let result = self.block_on(fd, |stack| {
+            match stack.sockets.get::<tcp::Socket>(handle).state() {
+                state if is_opening(state) => Err(io::ErrorKind::WouldBlock.into()),
+                tcp::State::Closed => Err(io::ErrorKind::ConnectionRefused.into()),
+                _ => Ok(()),
+            }
+        });
+        if !matches!(&result, Err(err) if err.kind() == io::ErrorKind::WouldBlock) {
+            self.descriptor_mut(fd)?.connecting = false;
+        }
+        result
+    }
+
+    /// Send data on a connected socket, and return how much was sent, like `send`.
+    ///
+    /// For a TCP socket, this waits until at least one octet could be enqueued.
+    pub fn send(&mut self, fd: Fd, data: &[u8]) -> io::Result<usize> {
+        self.send_to_impl(fd, data, None)
+    }
+
+    /// Send a datagram to the given remote endpoint, like `sendto`.
+    ///
+    /// For a TCP socket, the remote endpoint is ignored.
+    pub fn send_to(&mut self, fd: Fd, data: &[u8], remote: IpEndpoint) -> io::Result<usize> {
+        self.send_to_impl(fd, data, Some(remote))
+    }
+
+    /// Receive data from a socket into `buf`, and return how much was received, like `recv`.
+    ///
+    /// For a TCP socket, 0 is returned once the remote endpoint has closed the connection.
+    /// For a UDP socket, the part of a datagram that does not fit in `buf` is discarded.
+    pub fn recv(&mut self, fd: Fd, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv_from(fd, buf).map(|(size, _)| size)
+    }
+
+    /// Receive data from a socket into `buf`, and return how much was received together
+    /// with the remote endpoint, like `recvfrom`.
+    pub fn recv_from(&mut self, fd: Fd, buf: &mut [u8]) -> io::Result<(usize, IpEndpoint)> {
+        self.block_on(fd, |stack| match stack.descriptor(fd)?.kind {
+            Kind::Tcp(handle) => {
+                let socket = stack.sockets.get_mut::<tcp::Socket>(handle);
+                let remote = socket.remote_endpoint();
+                if !socket.can_recv() && (socket.may_recv() || is_opening(socket.state())) {
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+                match (socket.recv_slice(buf), remote) {
+                    (Ok(size), Some(remote)) => Ok((size, remote)),
+                    (Err(tcp::RecvError::Finished), Some(remote)) => Ok((0, remote)),
+                    _ => Err(io::ErrorKind::NotConnected.into()),
+                }
+            }
+            Kind::Listener(_) => Err(io::ErrorKind::NotConnected.into()),
+            Kind::Udp { handle, .. } => {
+                let socket = stack.sockets.get_mut::<udp::Socket>(handle);
+                if !socket.is_open() {
+                    return Err(invalid_input("not bound"));
+                }
+                socket
+                    .recv_slice(buf)
+                    .map_err(|udp::RecvError::Exhausted| io::Error::from(io::ErrorKind::WouldBlock))
+            }
+        })
+    }
+
+    /// Shut down part of a TCP connection, like `shutdown`.
+    ///
+    /// Shutting down the write half closes the transmit half of the connection, while
+    /// shutting down the read half has no effect.
+    pub fn shutdown(&mut self, fd: Fd, how: Shutdown) -> io::Result<()> {
+        match self.descriptor(fd)?.kind {
+            Kind::Tcp(handle) => {
+                if how != Shutdown::Read {
+                    self.sockets.get_mut::<tcp::Socket>(handle).close();
+                }
+                Ok(())
+            }
+            _ => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    /// Release a descriptor, like `close`.
+    ///
+    /// A TCP connection is closed gracefully in the background, and its socket is
+    /// released once it is over. Pending connections of a listening socket are reset.
+    pub fn close(&mut self, fd: Fd) -> io::Result<()> {
+        self.descriptor(fd)?;
+        let descriptor = self.descriptors[fd.0].take().unwrap();
+        match descriptor.kind {
+            Kind::Tcp(handle) => {
+                self.sockets.get_mut::<tcp::Socket>(handle).close();
+                self.closing.push(handle);
+            }
+            Kind::Listener(handles) => {
+                for handle in handles {
+                    self.sockets.get_mut::<tcp::Socket>(handle).abort();
+                    self.closing.push(handle);
+                }
+            }
+            Kind::Udp { handle, .. } => {
+                self.sockets.remove(handle);
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait until some of the descriptors in `readfds` can be read from without blocking,
+    /// or some of the descriptors in `writefds` can be written to without blocking, like
+    /// `select`.
+    ///
+    /// On return, the sets only contain the ready descriptors, and their total size is
+    /// returned. A `timeout` of `None` waits forever, and a zero `timeout` does not wait.
+    ///
+    /// A descriptor is readable when it has received data, or when reading from it would fail,
+    /// and a listening descriptor is readable when it has a connection to be accepted.
+    pub fn select(
+        &mut self,
+        readfds: &mut FdSet,
+        writefds: &mut FdSet,
+        timeout: Option<Duration>,
+    ) -> io::Result<usize> {
+        for fd in readfds.iter().chain(writefds.iter()) {
+            self.descriptor(fd)?;
+        }
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let changed = self.poll();
+            let readable = readfds.fds.iter().any(|&fd| self.is_readable(fd));
+            let writable = writefds.fds.iter().any(|&fd| self.is_writable(fd));
+            if readable || writable || deadline.map_or(false, |d| Instant::now() >= d) {
+                break;
+            }
+            if !changed {
+                self.wait(deadline);
+            }
+        }
+
+        readfds.fds.retain(|&fd| self.is_readable(fd));
+        writefds.fds.retain(|&fd| self.is_writable(fd));
+        Ok(readfds.len() + writefds.len())
+    }
+
+    fn descriptor(&self, fd: Fd) -> io::Result<&Descriptor> {
+        match self.descriptors.get(fd.0) {
+            Some(Some(descriptor)) => Ok(descriptor),
+            _ => Err(bad_descriptor()),
+        }
+    }
+
+    fn descriptor_mut(&mut self, fd: Fd) -> io::Result<&mut Descriptor> {
+        match self.descriptors.get_mut(fd.0) {
+            Some(Some(descriptor)) => Ok(descriptor),
+            _ => Err(bad_descriptor()),
+        }
+    }
+
+    fn add_descriptor(&mut self, kind: Kind, bound: Option<IpListenEndpoint>) -> Fd {
+        let descriptor = Descriptor {
+            kind,
+            bound,
+            connecting: false,
+            nonblocking: false,
+            timeout: None,
+        };
+        match self.descriptors.iter().position(Option::is_none) {
+            Some(index) => {
+                self.descriptors[index] = Some(descriptor);
+                Fd(index)
+            }
+            None => {
+                self.descriptors.push(Some(descriptor));
+                Fd(self.descriptors.len() - 1)
+            }
+        }
+    }
+
+    fn add_tcp_socket(&mut self) -> SocketHandle {
+        let socket = tcp::Socket::new(
+            tcp::SocketBuffer::new(vec![0; self.buffer_sizes.tcp_rx]),
+            tcp::SocketBuffer::new(vec![0; self.buffer_sizes.tcp_tx]),
+        );
+        self.sockets.add(socket)
+    }
+
+    fn bind_udp(&mut self, fd: Fd, endpoint: IpListenEndpoint) -> io::Result<()> {
+        if self.descriptor(fd)?.bound.is_none() {
+            self.bind(fd, endpoint)?;
+        }
+        Ok(())
+    }
+
+    fn is_port_in_use(&self, is_stream: bool, endpoint: IpListenEndpoint) -> bool {
+        self.descriptors.iter().flatten().any(|descriptor| {
+            descriptor.is_stream() == is_stream
+                && descriptor.bound.map_or(false, |bound| {
+                    bound.port == endpoint.port
+                        && (bound.addr.is_none()
+                            || endpoint.addr.is_none()
+                            || bound.addr == endpoint.addr)
+                })
+        })
+    }
+
+    fn ephemeral_port(&mut self, is_stream: bool) -> io::Result<u16> {
+        for _ in EPHEMERAL_PORTS {
+            let port = self.next_port;
+            self.next_port = if port == *EPHEMERAL_PORTS.end() {
+                *EPHEMERAL_PORTS.start()
+            } else {
+                port + 1
+            };
+            if !self.is_port_in_use(is_stream, IpListenEndpoint::from(port)) {
+                return Ok(port);
+            }
+        }
+        Err(io::ErrorKind::AddrInUse.into())
+    }
+
+    fn send_to_impl(
+        &mut self,
+        fd: Fd,
+        data: &[u8],
+        remote: Option<IpEndpoint>,
+    ) -> io::Result<usize> {
+        if let Kind::Udp { .. } = self.descriptor(fd)?.kind {
+            if remote.is_some() {
+                let port = self.ephemeral_port(false)?;
+                self.bind_udp(fd, IpListenEndpoint::from(port))?;
+            }
+        }
+
+        self.block_on(fd, |stack| match stack.descriptor(fd)?.kind {
+            Kind::Tcp(handle) => {
+                let socket = stack.sockets.get_mut::<tcp::Socket>(handle);
+                if socket.can_send() {
+                    socket
+                        .send_slice(data)
+                        .map_err(|tcp::SendError::InvalidState| {
+                            io::Error::from(io::ErrorKind::NotConnected)
+                        })
+                } else if socket.may_send() || is_opening(socket.state()) {
+                    Err(io::ErrorKind::WouldBlock.into())
+                } else if socket.state() == tcp::State::Closed {
+                    Err(io::ErrorKind::NotConnected.into())
+                } else {
+                    Err(io::ErrorKind::BrokenPipe.into())
+                }
+            }
+            Kind::Listener(_) => Err(io::ErrorKind::NotConnected.into()),
+            Kind::Udp {
+                handle,
+                remote: connected,
+            } => {
+                let remote = remote
+                    .or(connected)
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+                let socket = stack.sockets.get_mut::<udp::Socket>(handle);
+                if data.len() > socket.payload_send_capacity() {
+                    return Err(invalid_input("message too long"));
+                }
+                match socket.send_slice(data, remote) {
+                    Ok(()) => Ok(data.len()),
+                    Err(udp::SendError::BufferFull) => Err(io::ErrorKind::WouldBlock.into()),
+                    Err(udp::SendError::Unaddressable) => {
+                        Err(io::ErrorKind::AddrNotAvailable.into())
+                    }
+                }
+            }
+        })
+    }
+
+    fn is_readable(&self, fd: Fd) -> bool {
+        let descriptor = match self.descriptor(fd) {
+            Ok(descriptor) => descriptor,
+            Err(_) => return true,
+        };
+        match &descriptor.kind {
+            Kind::Tcp(handle) => {
+                let socket = self.sockets.get::<tcp::Socket>(*handle);
+                socket.can_recv() || !(socket.may_recv() || is_opening(socket.state()))
+            }
+            Kind::Listener(handles) => handles
+                .iter()
+                .any(|&handle| is_acceptable(self.sockets.get::<tcp::Socket>(handle))),
+            Kind::Udp { handle, .. } => {
+                let socket = self.sockets.get::<udp::Socket>(*handle);
+                socket.can_recv() || !socket.is_open()
+            }
+        }
+    }
+
+    fn is_writable(&self, fd: Fd) -> bool {
+        let descriptor = match self.descriptor(fd) {
+            Ok(descriptor) => descriptor,
+            Err(_) => return true,
+        };
+        match &descriptor.kind {
+            Kind::Tcp(handle) => {
+                let socket = self.sockets.get::<tcp::Socket>(*handle);
+                socket.can_send() || !(socket.may_send() || is_opening(socket.state()))
+            }
+            Kind::Listener(_) => false,
+            Kind::Udp { handle, .. } => self.sockets.get::<udp::Socket>(*handle).can_send(),
+        }
+    }
+
+    /// Call `f` until it does not fail with [io::ErrorKind::WouldBlock], polling the
+    /// interface in between, unless the descriptor is non-blocking.
+    fn block_on<F, R>(&mut self, fd: Fd, mut f: F) -> io::Result<R>
+    where
+        F: FnMut(&mut Self) -> io::Result<R>,
+    {
+        let descriptor = self.descriptor(fd)?;
+        let nonblocking = descriptor.nonblocking;
+        let deadline = descriptor.timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match f(self) {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock && !nonblocking => (),
+                result => return result,
+            }
+            if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            if !self.poll() {
+                self.wait(deadline);
+            }
+        }
+    }
+
+    /// Sleep until the interface needs to be polled, the deadline, or the polling interval,
+    /// whichever comes first.
+    fn wait(&mut self, deadline: Option<Instant>) {
+        let timestamp = Instant::now();
+        let mut delay = POLL_INTERVAL;
+        if let Some(poll_delay) = self.iface.poll_delay(timestamp, &self.sockets) {
+            delay = delay.min(poll_delay);
+        }
+        match deadline {
+            Some(deadline) if deadline <= timestamp => return,
+            Some(deadline) => delay = delay.min(deadline - timestamp),
+            None => (),
+        }
+        thread::sleep(delay.into());
+    }
+}
+
+#[cfg(all(test, feature = "medium-ip", feature = "proto-ipv4"))]
+mod test {
+    use super::*;
+    use crate::iface::InterfaceBuilder;
+    use crate::phy::{Loopback, Medium};
+    use crate::wire::{IpAddress, IpCidr};
+
+    const LOCAL_ADDR: IpAddress = IpAddress::v4(127, 0, 0, 1);
+
+    fn stack() -> Stack<Loopback> {
+        let mut device = Loopback::new(Medium::Ip);
+        let mut ip_addrs = heapless::Vec::<IpCidr, 5>::new();
+        ip_addrs.push(IpCidr::new(LOCAL_ADDR, 8)).unwrap();
+        let iface = InterfaceBuilder::new()
+            .ip_addrs(ip_addrs)
+            .finalize(&mut device);
+        Stack::new(iface, device)
+    }
+
+    #[test]
+    fn test_tcp_connection() {
+        let mut stack = stack();
+        let listener = stack.socket(SocketType::Stream);
+        assert_eq!(listener, Fd(0));
+        stack.bind(listener, 80.into()).unwrap();
+        stack.listen(listener, 2).unwrap();
+
+        let client = stack.socket(SocketType::Stream);
+        stack.connect(client, (LOCAL_ADDR, 80).into()).unwrap();
+        let (server, remote) = stack.accept(listener).unwrap();
+        assert_eq!(server, Fd(2));
+        assert_eq!(remote, (LOCAL_ADDR, *EPHEMERAL_PORTS.start()).into());
+
+        assert_eq!(stack.send(client, b"hello").unwrap(), 5);
+        stack.shutdown(client, Shutdown::Write).unwrap();
+        let mut buf = [0; 16];
+        assert_eq!(stack.recv(server, &mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"hello");
+        assert_eq!(stack.recv(server, &mut buf).unwrap(), 0);
+
+        stack.close(server).unwrap();
+        stack.close(client).unwrap();
+        stack.close(listener).unwrap();
+        for _ in 0..10 {
+            stack.poll();
+        }
+        assert_eq!(stack.socket(SocketType::Stream), Fd(0));
+    }
+
+    #[test]
+    fn test_connection_refused() {
+        let mut stack = stack();
+        let client = stack.socket(SocketType::Stream);
+        assert_eq!(
+            stack
+                .connect(client, (LOCAL_ADDR, 80).into())
+                .map_err(|err| err.kind()),
+            Err(io::ErrorKind::ConnectionRefused)
+        );
+    }
+
+    #[test]
+    fn test_nonblocking_select() {
+        let mut stack = stack();
+        let listener = stack.socket(SocketType::Stream);
+        stack.bind(listener, 80.into()).unwrap();
+        stack.listen(listener, 1).unwrap();
+        stack.set_nonblocking(listener, true).unwrap();
+        assert_eq!(
+            stack.accept(listener).map_err(|err| err.kind()),
+            Err(io::ErrorKind::WouldBlock)
+        );
+
+        let client = stack.socket(SocketType::Stream);
+        stack.set_nonblocking(client, true).unwrap();
+        assert_eq!(
+            stack
+                .connect(client, (LOCAL_ADDR, 80).into())
+                .map_err(|err| err.kind()),
+            Err(io::ErrorKind::WouldBlock)
+        );
+
+        let mut readfds = FdSet::new();
+        readfds.insert(listener);
+        let mut writefds = FdSet::new();
+        writefds.insert(client);
+        assert_eq!(stack.select(&mut readfds, &mut writefds, None).unwrap(), 2);
+        assert!(readfds.contains(listener));
+        assert!(writefds.contains(client));
+        stack.connect(client, (LOCAL_ADDR, 80).into()).unwrap();
+        assert_eq!(
+            stack
+                .connect(client, (LOCAL_ADDR, 80).into())
+                .map_err(|err| err.kind()),
+            Err(io::ErrorKind::InvalidInput)
+        );
+
+        let (server, _) = stack.accept(listener).unwrap();
+        stack.set_nonblocking(server, true).unwrap();
+        let mut buf = [0; 16];
+        assert_eq!(
+            stack.recv(server, &mut buf).map_err(|err| err.kind()),
+            Err(io::ErrorKind::WouldBlock)
+        );
+
+        let mut readfds = FdSet::new();
+        readfds.insert(server);
+        assert_eq!(
+            stack
+                .select(&mut readfds, &mut FdSet::new(), Some(Duration::ZERO))
+                .unwrap(),
+            0
+        );
+        assert!(readfds.is_empty());
+    }
+
+    #[test]
+    fn test_udp() {
+        let mut stack = stack();
+        let server = stack.socket(SocketType::Datagram);
+        stack.bind(server, 53.into()).unwrap();
+        let other = stack.socket(SocketType::Datagram);
+        assert_eq!(
+            stack.bind(other, 53.into()).map_err(|err| err.kind()),
+            Err(io::ErrorKind::AddrInUse)
+        );
+
+        assert_eq!(
+            stack.send(other, b"hello").map_err(|err| err.kind()),
+            Err(io::ErrorKind::NotConnected)
+        );
+        stack.connect(other, (LOCAL_ADDR, 53).into()).unwrap();
+        assert_eq!(stack.send(other, b"hello").unwrap(), 5);
+
+        let mut buf = [0; 16];
+        let (size, remote) = stack.recv_from(server, &mut buf).unwrap();
+        assert_eq!(&buf[..size], b"hello");
+        assert_eq!(remote, (LOCAL_ADDR, *EPHEMERAL_PORTS.start()).into());
+
+        assert_eq!(stack.send_to(server, b"world", remote).unwrap(), 5);
+        assert_eq!(stack.recv(other, &mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"world");
+
+        stack.set_timeout(other, Some(Duration::ZERO)).unwrap();
+        assert_eq!(
+            stack.recv(other, &mut buf).map_err(|err| err.kind()),
+            Err(io::ErrorKind::TimedOut)
+        );
+        stack.close(other).unwrap();
+        assert!(stack.recv(other, &mut buf).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+/*! Implementations of the [embedded-io] traits for TCP sockets.
+
+A [TcpStream] borrows an interface, its device and a socket set, and implements the
+blocking [Read], [Write], [ReadReady] and [WriteReady] traits for one TCP socket in the
+set, polling the interface until the socket can make progress.
+
+With the `embedded-io-async` feature, an [AsyncTcpStream] implements the
+[embedded-io-async] traits instead. It shares the socket set through a [RefCell], and
+waits for the socket to be woken up, so the interface must be polled elsewhere, e.g. by
+another task.
+
+[embedded-io]: https://docs.rs/embedded-io
+[embedded-io-async]: https://docs.rs/embedded-io-async
+*/
+
+#[cfg(feature = "embedded-io-async")]
+use core::cell::RefCell;
+use core::fmt;
+#[cfg(feature = "embedded-io-async")]
+use core::future::poll_fn;
+#[cfg(feature = "embedded-io-async")]
+use core::task::Poll;
+
+use ::embedded_io::{ErrorKind, ErrorType, Read, ReadReady, Write, WriteReady};
+
+use crate::iface::{Interface, SocketHandle, SocketSet};
+use crate::phy::Device;
+use crate::socket::tcp;
+use crate::time::Instant;
+
+/// Error returned by the embedded-io trait implementations.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The connection was reset by the remote endpoint, or was never established.
+    ConnectionReset,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ConnectionReset => write!(f, "connection reset"),
+        }
+    }
+}
+
+impl ::embedded_io::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::ConnectionReset => ErrorKind::ConnectionReset,
+        }
+    }
+}
+
+/// Return whether a socket in this state may still be able to transfer data later.
+fn is_opening(state: tcp::State) -> bool {
+    matches!(state, tcp::State::SynSent | tcp::State::SynReceived)
+}
+
+/// Receive into a non-empty `buf`, or return `None` if this would block.
+fn try_recv(socket: &mut tcp::Socket, buf: &mut [u8]) -> Option<Result<usize, Error>> {
+    match socket.recv_slice(buf) {
+        Ok(0) => None,
+        Ok(size) => Some(Ok(size)),
+        Err(tcp::RecvError::Finished) => Some(Ok(0)),
+        Err(tcp::RecvError::InvalidState) if is_opening(socket.state()) => None,
+        Err(tcp::RecvError::InvalidState) => Some(Err(Error::ConnectionReset)),
+    }
+}
+
+/// Send from a non-empty `buf`, or return `None` if this would block.
+fn try_send(socket: &mut tcp::Socket, buf: &[u8]) -> Option<Result<usize, Error>> {
+    if !socket.may_send() {
+        return if is_opening(socket.state()) {
+            None
+        } else {
+            Some(Err(Error::ConnectionReset))
+        };
+    }
+    match socket.send_slice(buf) {
+        Ok(0) => None,
+        Ok(size) => Some(Ok(size)),
+        Err(tcp::SendError::InvalidState) => Some(Err(Error::ConnectionReset)),
+    }
+}
+
+/// Return whether all sent data was acknowledged, or `None` if it was not yet.
+fn try_flush(socket: &mut tcp::Socket) -> Option<Result<(), Error>> {
+    if socket.send_queue() == 0 {
+        Some(Ok(()))
+    } else if !socket.is_active() {
+        Some(Err(Error::ConnectionReset))
+    } else {
+        None
+    }
+}
+
+fn is_read_ready(socket: &tcp::Socket) -> bool {
+    socket.can_recv() || !(socket.may_recv() || is_opening(socket.state()))
+}
+
+fn is_write_ready(socket: &tcp::Socket) -> bool {
+    socket.can_send() || !(socket.may_send() || is_opening(socket.state()))
+}
+
+/// A TCP socket in a socket set, with blocking reads and writes.
+pub struct TcpStream<'s, 'a, D> {
+    iface: &'s mut Interface<'a>,
+    device: &'s mut D,
+    sockets: &'s mut SocketSet<'a>,
+    handle: SocketHandle,
+    clock: fn() -> Instant,
+}
+
+impl<'s, 'a, D> TcpStream<'s, 'a, D>
+where
+    D: Device,
+{
+    /// Create a stream for the TCP socket `handle`, using `clock` to timestamp polls.
+    pub fn new(
+        iface: &'s mut Interface<'a>,
+        device: &'s mut D,
+        sockets: &'s mut SocketSet<'a>,
+        handle: SocketHandle,
+        clock: fn() -> Instant,
+    ) -> TcpStream<'s, 'a, D> {
+        TcpStream {
+            iface,
+            device,
+            sockets,
+            handle,
+            clock,
+        }
+    }
+
+    /// Return a mutable reference to the underlying socket.
+    pub fn socket_mut(&mut self) -> &mut tcp::Socket<'a> {
+        self.sockets.get_mut::<tcp::Socket>(self.handle)
+    }
+
+    /// Poll the interface until `f` returns `Some`.
+    fn block_on<F, R>(&mut self, mut f: F) -> R
+    where
+        F: FnMut(&mut tcp::Socket<'a>) -> Option<R>,
+    {
+        loop {
+            if let Some(result) = f(self.sockets.get_mut::<tcp::Socket>(self.handle)) {
+                // Transmit whatever the call enqueued right away.
+                self.poll();
+                return result;
+            }
+            self.poll();
+        }
+    }
+
+    fn poll(&mut self) {
+        if let Err(err) = self.iface.poll((self.clock)(), self.device, self.sockets) {
+            net_debug!("poll error: {}", err);
+        }
+    }
+}
+
+impl<'s, 'a, D> ErrorType for TcpStream<'s, 'a, D> {
+    type Error = Error;
+}
+
+impl<'s, 'a, D> Read for TcpStream<'s, 'a, D>
+where
+    D: Device,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.block_on(|socket| try_recv(socket, buf))
+    }
+}
+
+impl<'s, 'a, D> Write for TcpStream<'s, 'a, D>
+where
+    D: Device,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.block_on(|socket| try_send(socket, buf))
+    }
+
+    /// Wait until all data written to the stream was acknowledged by the remote endpoint.
+    fn flush(&mut self) -> Result<(), Error> {
+        self.block_on(try_flush)
+    }
+}
+
+impl<'s, 'a, D> ReadReady for TcpStream<'s, 'a, D>
+where
+    D: Device,
+{
+    fn read_ready(&mut self) -> Result<bool, Error> {
+        self.poll();
+        Ok(is_read_ready(self.socket_mut()))
+    }
+}
+
+impl<'s, 'a, D> WriteReady for TcpStream<'s, 'a, D>
+where
+    D: Device,
+{
+    fn write_ready(&mut self) -> Result<bool, Error> {
+        self.poll();
+        Ok(is_write_ready(self.socket_mut()))
+    }
+}
+
+/// A TCP socket in a shared socket set, with asynchronous reads and writes.
+#[cfg(feature = "embedded-io-async")]
+pub struct AsyncTcpStream<'s, 'a> {
+    sockets: &'s RefCell<SocketSet<'a>>,
+    handle: SocketHandle,
+}
+
+#[cfg(feature = "embedded-io-async")]
+impl<'s, 'a> AsyncTcpStream<'s, 'a> {
+    /// Create a stream for the TCP socket `handle`.
+    pub fn new(
+        sockets: &'s RefCell<SocketSet<'a>>,
+        handle: SocketHandle,
+    ) -> AsyncTcpStream<'s, 'a> {
+        AsyncTcpStream { sockets, handle }
+    }
+
+    /// Call `f` with the underlying socket.
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut tcp::Socket<'a>) -> R,
+    {
+        f(self
+            .sockets
+            .borrow_mut()
+            .get_mut::<tcp::Socket>(self.handle))
+    }
+}
+
+#[cfg(feature = "embedded-io-async")]
+impl<'s, 'a> ErrorType for AsyncTcpStream<'s, 'a> {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-io-async")]
+impl<'s, 'a> ::embedded_io_async::Read for AsyncTcpStream<'s, 'a> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        poll_fn(|cx| {
+            self.with(|socket| match try_recv(socket, buf) {
+                Some(result) => Poll::Ready(result),
+                None => {
+                    socket.register_recv_waker(cx.waker());
+                    Poll::Pending
+                }
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "embedded-io-async")]
+impl<'s, 'a> ::embedded_io_async::Write for AsyncTcpStream<'s, 'a> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        poll_fn(|cx| {
+            self.with(|socket| match try_send(socket, buf) {
+                Some(result) => Poll::Ready(result),
+                None => {
+                    socket.register_send_waker(cx.waker());
+                    Poll::Pending
+                }
+            })
+        })
+        .await
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        poll_fn(|cx| {
+            self.with(|socket| match try_flush(socket) {
+                Some(result) => Poll::Ready(result),
+                None => {
+                    socket.register_send_waker(cx.waker());
+                    Poll::Pending
+                }
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "medium-ip", feature = "proto-ipv4"))]
+mod test {
+    use super::*;
+    use crate::iface::InterfaceBuilder;
+    use crate::phy::{Loopback, Medium};
+    use crate::wire::{IpAddress, IpCidr};
+
+    #[test]
+    fn test_read_write() {
+        let mut device = Loopback::new(Medium::Ip);
+        let mut ip_addrs = heapless::Vec::<IpCidr, 5>::new();
+        ip_addrs
+            .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
+            .unwrap();
+        let mut iface = InterfaceBuilder::new()
+            .ip_addrs(ip_addrs)
+            .finalize(&mut device);
+        let mut sockets = SocketSet::new(vec![]);
+        let socket = || {
+            tcp::Socket::new(
+                tcp::SocketBuffer::new(vec![0; 64]),
+                tcp::SocketBuffer::new(vec![0; 64]),
+            )
+        };
+        let server = sockets.add(socket());
+        let client = sockets.add(socket());
+        sockets.get_mut::<tcp::Socket>(server).listen(80).unwrap();
+        sockets
+            .get_mut::<tcp::Socket>(client)
+            .connect(iface.context(), (IpAddress::v4(127, 0, 0, 1), 80), 49152)
+            .unwrap();
+
+        let mut stream =
+            TcpStream::new(&mut iface, &mut device, &mut sockets, client, Instant::now);
+        assert_eq!(stream.read_ready(), Ok(false));
+        assert_eq!(stream.write(b"hello"), Ok(5));
+        assert_eq!(stream.flush(), Ok(()));
+
+        let mut stream =
+            TcpStream::new(&mut iface, &mut device, &mut sockets, server, Instant::now);
+        assert_eq!(stream.read_ready(), Ok(true));
+        let mut buf = [0; 16];
+        assert_eq!(stream.read(&mut buf), Ok(5));
+        assert_eq!(&buf[..5], b"hello");
+    }
+}
@@ -0,0 +1,732 @@
+/*! A C interface to the networking stack.
+
+This module exports `extern "C"` functions that let firmware written in C create an
+interface on top of a device driven by callbacks, open and use TCP sockets, and poll
+the interface. The types are `#[repr(C)]` so that a header can be generated with
+[cbindgen] using the `cbindgen.toml` configuration at the root of the repository.
+
+All memory is allocated with the global allocator, so the `alloc` feature is required,
+and the firmware must provide an allocator to Rust.
+
+A stack is created with `smoltcp_stack_new`, and must be released with
+`smoltcp_stack_free`. Sockets are identified by the non-negative handles returned by
+`smoltcp_tcp_open`. Functions that can fail return a negative [SmoltcpError] value,
+cast to the return type of the function.
+
+None of the functions are thread safe; a stack must only be used from one thread at a time.
+
+[cbindgen]: https://github.com/mozilla/cbindgen
+*/
+
+#![allow(unsafe_code)]
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::slice;
+
+use crate::iface::{Interface, InterfaceBuilder, SocketHandle, SocketSet};
+use crate::phy::{self, Device, DeviceCapabilities, Medium};
+use crate::socket::{tcp, AnySocket};
+use crate::time::Instant;
+use crate::wire::{IpAddress, IpCidr, Ipv4Address};
+
+/// An error returned by the C interface.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmoltcpError {
+    /// The operation succeeded.
+    Ok = 0,
+    /// A pointer was null, or an argument was out of range.
+    InvalidArgument = -1,
+    /// The handle does not refer to a socket of the expected type.
+    InvalidHandle = -2,
+    /// The operation is not permitted in the current state of the socket.
+    InvalidState = -3,
+    /// An endpoint was unspecified or could not be reached.
+    Unaddressable = -4,
+    /// The remote endpoint has closed the connection, and all data has been received.
+    Finished = -5,
+}
+
+/// The medium of a device, passed as an integer in [SmoltcpDevice::medium].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmoltcpMedium {
+    /// Ethernet frames, requiring a hardware address.
+    Ethernet = 0,
+    /// Bare IP packets.
+    Ip = 1,
+}
+
+/// The state of a TCP socket, see [tcp::State].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmoltcpTcpState {
+    Closed = 0,
+    Listen = 1,
+    SynSent = 2,
+    SynReceived = 3,
+    Established = 4,
+    FinWait1 = 5,
+    FinWait2 = 6,
+    CloseWait = 7,
+    Closing = 8,
+    LastAck = 9,
+    TimeWait = 10,
+}
+
+impl From<tcp::State> for SmoltcpTcpState {
+    fn from(state: tcp::State) -> SmoltcpTcpState {
+        match state {
+            tcp::State::Closed => SmoltcpTcpState::Closed,
+            tcp::State::Listen => SmoltcpTcpState::Listen,
+            tcp::State::SynSent => SmoltcpTcpState::SynSent,
+            tcp::State::SynReceived => SmoltcpTcpState::SynReceived,
+            tcp::State::Established => SmoltcpTcpState::Established,
+            tcp::State::FinWait1 => SmoltcpTcpState::FinWait1,
+            tcp::State::FinWait2 => SmoltcpTcpState::FinWait2,
+            tcp::State::CloseWait => SmoltcpTcpState::CloseWait,
+            tcp::State::Closing => SmoltcpTcpState::Closing,
+            tcp::State::LastAck => SmoltcpTcpState::LastAck,
+            tcp::State::TimeWait => SmoltcpTcpState::TimeWait,
+        }
+    }
+}
+
+/// A device implemented by the firmware.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SmoltcpDevice {
+    /// An opaque pointer passed to the callbacks.
+    pub ctx: *mut c_void,
+    /// Copy one received frame into `buf`, which is `len` bytes long, and return its length,
+    /// or return 0 if no frame is available.
+    pub receive: Option<unsafe extern "C" fn(ctx: *mut c_void, buf: *mut u8, len: usize) -> usize>,
+    /// Transmit the frame in `buf`, which is `len` bytes long.
+    pub transmit: Option<unsafe extern "C" fn(ctx: *mut c_void, buf: *const u8, len: usize)>,
+    /// The largest frame the device can receive or transmit, including the Ethernet header
+    /// if there is one.
+    pub mtu: usize,
+    /// The medium of the device, one of the [SmoltcpMedium] values.
+    ///
+    /// This is a plain integer rather than a `SmoltcpMedium`, since any value can come
+    /// from C, and a Rust enum with an invalid discriminant is undefined behavior.
+    pub medium: u32,
+    /// Whether the device hands the frames it transmits back to the stack; packets from
+    /// the address of the interface are dropped otherwise.
+    pub loopback: bool,
+}
+
+impl SmoltcpDevice {
+    fn medium(&self) -> Option<Medium> {
+        match self.medium {
+            #[cfg(feature = "medium-ethernet")]
+            medium if medium == SmoltcpMedium::Ethernet as u32 => Some(Medium::Ethernet),
+            #[cfg(feature = "medium-ip")]
+            medium if medium == SmoltcpMedium::Ip as u32 => Some(Medium::Ip),
+            _ => None,
+        }
+    }
+}
+
+struct CallbackDevice {
+    ops: SmoltcpDevice,
+    medium: Medium,
+    /// The buffer received frames are copied into, `ops.mtu` bytes long.
+    rx_buffer: Vec<u8>,
+}
+
+#[doc(hidden)]
+pub struct RxToken<'a>(&'a mut [u8]);
+
+impl<'a> phy::RxToken for RxToken<'a> {
+    fn consume<R, F>(self, _timestamp: Instant, f: F) -> crate::Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> crate::Result<R>,
+    {
+        f(self.0)
+    }
+}
+
+#[doc(hidden)]
+pub struct TxToken<'a>(&'a SmoltcpDevice);
+
+impl<'a> phy::TxToken for TxToken<'a> {
+    fn consume<R, F>(self, _timestamp: Instant, len: usize, f: F) -> crate::Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> crate::Result<R>,
+    {
+        let mut buffer = vec![0; len];
+        let result = f(&mut buffer);
+        // Frames that could not be built completely are not sent.
+        if let (Ok(_), Some(transmit)) = (&result, self.0.transmit) {
+            // SAFETY: the firmware promised the callback is valid when creating the stack.
+            unsafe { transmit(self.0.ctx, buffer.as_ptr(), buffer.len()) }
+        }
+        result
+    }
+}
+
+impl Device for CallbackDevice {
+    type RxToken<'a> = RxToken<'a>;
+    type TxToken<'a> = TxToken<'a>;
+
+    fn receive(&mut self) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let receive = self.ops.receive?;
+        let buffer = &mut self.rx_buffer[..];
+        // SAFETY: the firmware promised the callback is valid when creating the stack.
+        let len = unsafe { receive(self.ops.ctx, buffer.as_mut_ptr(), buffer.len()) };
+        if len == 0 {
+            return None;
+        }
+        let len = len.min(buffer.len());
+        Some((RxToken(&mut buffer[..len]), TxToken(&self.ops)))
+    }
+
+    fn transmit(&mut self) -> Option<Self::TxToken<'_>> {
+        Some(TxToken(&self.ops))
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities {
+            medium: self.medium,
+            max_transmission_unit: self.ops.mtu,
+            loopback: self.ops.loopback,
+            ..DeviceCapabilities::default()
+        }
+    }
+}
+
+/// A network stack: an interface, its device, and its sockets.
+pub struct SmoltcpStack {
+    iface: Interface<'static>,
+    device: CallbackDevice,
+    sockets: SocketSet<'static>,
+}
+
+fn tcp_socket<'s>(
+    sockets: &'s mut SocketSet<'static>,
+    handle: usize,
+) -> Result<&'s mut tcp::Socket<'static>, SmoltcpError> {
+    let handle = SocketHandle::from_index(handle);
+    sockets
+        .iter_mut()
+        .find(|(h, _)| *h == handle)
+        .and_then(|(_, socket)| tcp::Socket::downcast_mut(socket))
+        .ok_or(SmoltcpError::InvalidHandle)
+}
+
+unsafe fn ipv4_address(addr: *const u8) -> Option<Ipv4Address> {
+    if addr.is_null() {
+        return None;
+    }
+    Some(Ipv4Address::from_bytes(slice::from_raw_parts(addr, 4)))
+}
+
+fn result(result: Result<(), SmoltcpError>) -> SmoltcpError {
+    match result {
+        Ok(()) => SmoltcpError::Ok,
+        Err(err) => err,
+    }
+}
+
+/// Create a stack using `device`, with the IPv4 address `ip_addr`/`prefix_len`.
+///
+/// `hardware_addr` points to the 6 byte Ethernet address of the interface; it is required
+/// for Ethernet devices, and ignored otherwise.
+///
+/// Returns null if an argument is invalid, or the medium of the device is not supported.
+///
+/// # Safety
+/// `ip_addr` must point to 4 bytes, and `hardware_addr` must be null or point to 6 bytes.
+/// The callbacks of `device` must remain valid until the stack is freed.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_stack_new(
+    device: SmoltcpDevice,
+    hardware_addr: *const u8,
+    ip_addr: *const u8,
+    prefix_len: u8,
+) -> *mut SmoltcpStack {
+    let medium = match device.medium() {
+        Some(medium) => medium,
+        None => return core::ptr::null_mut(),
+    };
+    let ip_addr = match ipv4_address(ip_addr) {
+        Some(addr) if prefix_len <= 32 && (addr.is_unicast() || addr.is_unspecified()) => addr,
+        _ => return core::ptr::null_mut(),
+    };
+
+    let mut ip_addrs = heapless::Vec::new();
+    ip_addrs
+        .push(IpCidr::new(IpAddress::Ipv4(ip_addr), prefix_len))
+        .unwrap();
+    let builder = InterfaceBuilder::new().ip_addrs(ip_addrs);
+
+    #[cfg(feature = "medium-ethernet")]
+    let builder = if medium == Medium::Ethernet {
+        use crate::iface::NeighborCache;
+        use crate::wire::EthernetAddress;
+
+        if hardware_addr.is_null() {
+            return core::ptr::null_mut();
+        }
+        let hardware_addr = EthernetAddress::from_bytes(slice::from_raw_parts(hardware_addr, 6));
+        if !hardware_addr.is_unicast() {
+            return core::ptr::null_mut();
+        }
+        builder
+            .hardware_addr(hardware_addr.into())
+            .neighbor_cache(NeighborCache::new())
+    } else {
+        builder
+    };
+    #[cfg(not(feature = "medium-ethernet"))]
+    let _ = hardware_addr;
+
+    let mut device = CallbackDevice {
+        ops: device,
+        medium,
+        rx_buffer: vec![0; device.mtu],
+    };
+    let iface = builder.finalize(&mut device);
+    Box::into_raw(Box::new(SmoltcpStack {
+        iface,
+        device,
+        sockets: SocketSet::new(vec![]),
+    }))
+}
+
+/// Free a stack and all of its sockets.
+///
+/// # Safety
+/// `stack` must be null or have been returned by `smoltcp_stack_new`, and must not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_stack_free(stack: *mut SmoltcpStack) {
+    if !stack.is_null() {
+        drop(Box::from_raw(stack));
+    }
+}
+
+/// Route packets to destinations outside of the local network through `gateway`,
+/// which points to an IPv4 address.
+///
+/// # Safety
+/// `stack` must be a valid stack, and `gateway` must point to 4 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_stack_set_gateway(
+    stack: *mut SmoltcpStack,
+    gateway: *const u8,
+) -> SmoltcpError {
+    let (stack, gateway) = match (stack.as_mut(), ipv4_address(gateway)) {
+        (Some(stack), Some(gateway)) => (stack, gateway),
+        _ => return SmoltcpError::InvalidArgument,
+    };
+    match stack.iface.routes_mut().add_default_ipv4_route(gateway) {
+        Ok(_) => SmoltcpError::Ok,
+        Err(_) => SmoltcpError::InvalidArgument,
+    }
+}
+
+/// Transmit and receive packets, given the current time in milliseconds.
+///
+/// Returns 1 if the state of any socket may have changed, and 0 otherwise.
+///
+/// # Safety
+/// `stack` must be a valid stack.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_poll(stack: *mut SmoltcpStack, timestamp_ms: i64) -> i32 {
+    let stack = match stack.as_mut() {
+        Some(stack) => stack,
+        None => return SmoltcpError::InvalidArgument as i32,
+    };
+    let SmoltcpStack {
+        iface,
+        device,
+        sockets,
+    } = stack;
+    match iface.poll(Instant::from_millis(timestamp_ms), device, sockets) {
+        Ok(changed) => changed as i32,
+        Err(err) => {
+            net_debug!("poll error: {}", err);
+            1
+        }
+    }
+}
+
+/// Return how many milliseconds may pass before `smoltcp_poll` must be called again,
+/// given the current time in milliseconds, or -1 if it only needs to be called once a
+/// frame has been received.
+///
+/// # Safety
+/// `stack` must be a valid stack.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_poll_delay(stack: *mut SmoltcpStack, timestamp_ms: i64) -> i64 {
+    let stack = match stack.as_mut() {
+        Some(stack) => stack,
+        None => return SmoltcpError::InvalidArgument as i64,
+    };
+    match stack
+        .iface
+        .poll_delay(Instant::from_millis(timestamp_ms), &stack.sockets)
+    {
+        Some(delay) => delay.total_millis() as i64,
+        None => -1,
+    }
+}
+
+/// Open a TCP socket with receive and transmit buffers of the given sizes,
+/// and return its handle.
+///
+/// # Safety
+/// `stack` must be a valid stack.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_tcp_open(
+    stack: *mut SmoltcpStack,
+    rx_size: usize,
+    tx_size: usize,
+) -> isize {
+    let stack = match stack.as_mut() {
+        Some(stack) if rx_size > 0 && tx_size > 0 => stack,
+        _ => return SmoltcpError::InvalidArgument as isize,
+    };
+    let socket = tcp::Socket::new(
+        tcp::SocketBuffer::new(vec![0; rx_size]),
+        tcp::SocketBuffer::new(vec![0; tx_size]),
+    );
+    stack.sockets.add(socket).index() as isize
+}
+
+/// Listen for a connection on the given local port.
+///
+/// # Safety
+/// `stack` must be a valid stack.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_tcp_listen(
+    stack: *mut SmoltcpStack,
+    handle: usize,
+    port: u16,
+) -> SmoltcpError {
+    let stack = match stack.as_mut() {
+        Some(stack) => stack,
+        None => return SmoltcpError::InvalidArgument,
+    };
+    result(tcp_socket(&mut stack.sockets, handle).and_then(|socket| {
+        socket.listen(port).map_err(|err| match err {
+            tcp::ListenError::InvalidState => SmoltcpError::InvalidState,
+            tcp::ListenError::Unaddressable => SmoltcpError::Unaddressable,
+        })
+    }))
+}
+
+/// Connect to the IPv4 address `remote_addr` and port `remote_port`, from the given local port.
+///
+/// The connection is established asynchronously; see `smoltcp_tcp_state`.
+///
+/// # Safety
+/// `stack` must be a valid stack, and `remote_addr` must point to 4 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_tcp_connect(
+    stack: *mut SmoltcpStack,
+    handle: usize,
+    remote_addr: *const u8,
+    remote_port: u16,
+    local_port: u16,
+) -> SmoltcpError {
+    let (stack, remote_addr) = match (stack.as_mut(), ipv4_address(remote_addr)) {
+        (Some(stack), Some(remote_addr)) => (stack, remote_addr),
+        _ => return SmoltcpError::InvalidArgument,
+    };
+    let socket = match tcp_socket(&mut stack.sockets, handle) {
+        Ok(socket) => socket,
+        Err(err) => return err,
+    };
+    result(
+        socket
+            .connect(
+                stack.iface.context(),
+                (IpAddress::Ipv4(remote_addr), remote_port),
+                local_port,
+            )
+            .map_err(|err| match err {
+                tcp::ConnectError::InvalidState => SmoltcpError::InvalidState,
+                tcp::ConnectError::Unaddressable => SmoltcpError::Unaddressable,
+            }),
+    )
+}
+
+/// Return the state of a TCP socket, or a negative error.
+///
+/// # Safety
+/// `stack` must be a valid stack.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_tcp_state(stack: *mut SmoltcpStack, handle: usize) -> i32 {
+    let stack = match stack.as_mut() {
+        Some(stack) => stack,
+        None => return SmoltcpError::InvalidArgument as i32,
+    };
+    match tcp_socket(&mut stack.sockets, handle) {
+        Ok(socket) => SmoltcpTcpState::from(socket.state()) as i32,
+        Err(err) => err as i32,
+    }
+}
+
+/// Enqueue up to `len` bytes from `data` for sending, and return how many were enqueued.
+///
+/// # Safety
+/// `stack` must be a valid stack, and `data` must point to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_tcp_send(
+    stack: *mut SmoltcpStack,
+    handle: usize,
+    data: *const u8,
+    len: usize,
+) -> isize {
+    let stack = match stack.as_mut() {
+        Some(stack) if !data.is_null() => stack,
+        _ => return SmoltcpError::InvalidArgument as isize,
+    };
+    let data = slice::from_raw_parts(data, len);
+    match tcp_socket(&mut stack.sockets, handle) {
+        Ok(socket) => match socket.send_slice(data) {
+            Ok(size) => size as isize,
+            Err(tcp::SendError::InvalidState) => SmoltcpError::InvalidState as isize,
+        },
+        Err(err) => err as isize,
+    }
+}
+
+/// Dequeue up to `len` received bytes into `buf`, and return how many were dequeued.
+///
+/// Returns `Finished` once the remote endpoint has closed the connection and all data has
+/// been received.
+///
+/// # Safety
+/// `stack` must be a valid stack, and `buf` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_tcp_recv(
+    stack: *mut SmoltcpStack,
+    handle: usize,
+    buf: *mut u8,
+    len: usize,
+) -> isize {
+    let stack = match stack.as_mut() {
+        Some(stack) if !buf.is_null() => stack,
+        _ => return SmoltcpError::InvalidArgument as isize,
+    };
+    let buf = slice::from_raw_parts_mut(buf, len);
+    match tcp_socket(&mut stack.sockets, handle) {
+        Ok(socket) => match socket.recv_slice(buf) {
+            Ok(size) => size as isize,
+            Err(tcp::RecvError::InvalidState) => SmoltcpError::InvalidState as isize,
+            Err(tcp::RecvError::Finished) => SmoltcpError::Finished as isize,
+        },
+        Err(err) => err as isize,
+    }
+}
+
+/// Close the transmit half of the connection; data can still be received.
+///
+/// # Safety
+/// `stack` must be a valid stack.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_tcp_close(
+    stack: *mut SmoltcpStack,
+    handle: usize,
+) -> SmoltcpError {
+    let stack = match stack.as_mut() {
+        Some(stack) => stack,
+        None => return SmoltcpError::InvalidArgument,
+    };
+    result(tcp_socket(&mut stack.sockets, handle).map(|socket| socket.close()))
+}
+
+/// Remove a socket from the stack and free its buffers, aborting any connection.
+///
+/// # Safety
+/// `stack` must be a valid stack.
+#[no_mangle]
+pub unsafe extern "C" fn smoltcp_socket_free(
+    stack: *mut SmoltcpStack,
+    handle: usize,
+) -> SmoltcpError {
+    let stack = match stack.as_mut() {
+        Some(stack) => stack,
+        None => return SmoltcpError::InvalidArgument,
+    };
+    let handle = SocketHandle::from_index(handle);
+    if !stack.sockets.iter().any(|(h, _)| h == handle) {
+        return SmoltcpError::InvalidHandle;
+    }
+    stack.sockets.remove(handle);
+    SmoltcpError::Ok
+}
+
+#[cfg(all(test, feature = "medium-ip"))]
+mod test {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    const LOCAL_ADDR: [u8; 4] = [192, 168, 1, 1];
+
+    unsafe extern "C" fn receive(ctx: *mut c_void, buf: *mut u8, len: usize) -> usize {
+        let queue = &mut *(ctx as *mut VecDeque<Vec<u8>>);
+        match queue.pop_front() {
+            Some(frame) => {
+                assert!(frame.len() <= len);
+                slice::from_raw_parts_mut(buf, frame.len()).copy_from_slice(&frame);
+                frame.len()
+            }
+            None => 0,
+        }
+    }
+
+    unsafe extern "C" fn transmit(ctx: *mut c_void, buf: *const u8, len: usize) {
+        let queue = &mut *(ctx as *mut VecDeque<Vec<u8>>);
+        queue.push_back(slice::from_raw_parts(buf, len).to_vec());
+    }
+
+    fn loopback(queue: &mut VecDeque<Vec<u8>>) -> SmoltcpDevice {
+        SmoltcpDevice {
+            ctx: queue as *mut _ as *mut c_void,
+            receive: Some(receive),
+            transmit: Some(transmit),
+            mtu: 1500,
+            medium: SmoltcpMedium::Ip as u32,
+            loopback: true,
+        }
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let mut queue = VecDeque::new();
+        unsafe {
+            let stack = smoltcp_stack_new(
+                loopback(&mut queue),
+                core::ptr::null(),
+                LOCAL_ADDR.as_ptr(),
+                33,
+            );
+            assert!(stack.is_null());
+            let stack = smoltcp_stack_new(
+                SmoltcpDevice {
+                    medium: 42,
+                    ..loopback(&mut queue)
+                },
+                core::ptr::null(),
+                LOCAL_ADDR.as_ptr(),
+                24,
+            );
+            assert!(stack.is_null());
+            let stack = smoltcp_stack_new(
+                loopback(&mut queue),
+                core::ptr::null(),
+                core::ptr::null(),
+                24,
+            );
+            assert!(stack.is_null());
+
+            let stack = smoltcp_stack_new(
+                loopback(&mut queue),
+                core::ptr::null(),
+                LOCAL_ADDR.as_ptr(),
+                24,
+            );
+            assert!(!stack.is_null());
+            assert_eq!(
+                smoltcp_tcp_listen(stack, 0, 80),
+                SmoltcpError::InvalidHandle
+            );
+            assert_eq!(
+                smoltcp_tcp_open(stack, 0, 64),
+                SmoltcpError::InvalidArgument as isize
+            );
+            let handle = smoltcp_tcp_open(stack, 64, 64) as usize;
+            assert_eq!(
+                smoltcp_tcp_listen(stack, handle, 0),
+                SmoltcpError::Unaddressable
+            );
+            assert_eq!(smoltcp_socket_free(stack, handle), SmoltcpError::Ok);
+            assert_eq!(
+                smoltcp_socket_free(stack, handle),
+                SmoltcpError::InvalidHandle
+            );
+            smoltcp_stack_free(stack);
+        }
+    }
+
+    #[test]
+    fn test_transmit_error() {
+        let mut queue = VecDeque::new();
+        let ops = loopback(&mut queue);
+        let result: crate::Result<()> =
+            phy::TxToken::consume(TxToken(&ops), Instant::ZERO, 64, |_| {
+                Err(crate::Error::Exhausted(crate::Layer::Phy))
+            });
+        assert!(result.is_err());
+        // The half-built frame was not transmitted.
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_tcp_connection() {
+        let mut queue = VecDeque::new();
+        unsafe {
+            let stack = smoltcp_stack_new(
+                loopback(&mut queue),
+                core::ptr::null(),
+                LOCAL_ADDR.as_ptr(),
+                24,
+            );
+            let server = smoltcp_tcp_open(stack, 64, 64) as usize;
+            let client = smoltcp_tcp_open(stack, 64, 64) as usize;
+
+            assert_eq!(smoltcp_tcp_listen(stack, server, 80), SmoltcpError::Ok);
+            assert_eq!(
+                smoltcp_tcp_connect(stack, client, LOCAL_ADDR.as_ptr(), 80, 49500),
+                SmoltcpError::Ok
+            );
+            assert_eq!(
+                smoltcp_tcp_state(stack, client),
+                SmoltcpTcpState::SynSent as i32
+            );
+
+            let mut timestamp = 0;
+            let mut poll = |stack| {
+                for _ in 0..10 {
+                    smoltcp_poll(stack, timestamp);
+                    timestamp += 1;
+                }
+            };
+            poll(stack);
+            assert_eq!(
+                smoltcp_tcp_state(stack, client),
+                SmoltcpTcpState::Established as i32
+            );
+            assert_eq!(
+                smoltcp_tcp_state(stack, server),
+                SmoltcpTcpState::Established as i32
+            );
+
+            assert_eq!(smoltcp_tcp_send(stack, client, b"hello".as_ptr(), 5), 5);
+            assert_eq!(smoltcp_tcp_close(stack, client), SmoltcpError::Ok);
+            poll(stack);
+
+            let mut buf = [0; 16];
+            assert_eq!(
+                smoltcp_tcp_recv(stack, server, buf.as_mut_ptr(), buf.len()),
+                5
+            );
+            assert_eq!(&buf[..5], b"hello");
+            assert_eq!(
+                smoltcp_tcp_recv(stack, server, buf.as_mut_ptr(), buf.len()),
+                SmoltcpError::Finished as isize
+            );
+
+            smoltcp_stack_free(stack);
+        }
+    }
+}
@@ -0,0 +1,506 @@
+/*! Entry points for fuzzing the ingress path.
+
+This module feeds arbitrary frames to an interface, through the whole ingress path
+down to the sockets, without any real device: the frames are received from an in-memory
+device that discards whatever the interface sends. The interface has a few sockets bound to
+well known ports, which echo what they receive, so that the frames can drive their state
+machines as well as the parsers.
+
+Everything is deterministic: the interface uses a fixed random seed, and time advances by
+a fixed step for each frame. A crash found by a fuzzer can therefore be reproduced by
+passing the same input to [ingress] again, e.g. in a test.
+
+The fuzz targets in the `fuzz` directory of the repository are built on this module.
+
+```rust
+use smoltcp::fuzz;
+use smoltcp::phy::Medium;
+
+// A single, truncated Ethernet frame.
+# #[cfg(feature = "medium-ethernet")]
+fuzz::ingress(Medium::Ethernet, &[0, 4, 0xff, 0xff, 0xff, 0xff]);
+```
+*/
+
+#[cfg(any(
+    feature = "proto-ipv4-fragmentation",
+    feature = "proto-sixlowpan-fragmentation"
+))]
+use alloc::collections::BTreeMap;
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+use crate::iface::NeighborCache;
+#[cfg(any(
+    feature = "proto-ipv4-fragmentation",
+    feature = "proto-sixlowpan-fragmentation"
+))]
+use crate::iface::ReassemblyBuffer;
+use crate::iface::{Interface, InterfaceBuilder, SocketHandle, SocketSet};
+use crate::phy::{self, Device, DeviceCapabilities, Medium};
+use crate::socket::{tcp, udp};
+use crate::time::{Duration, Instant};
+#[cfg(feature = "medium-ethernet")]
+use crate::wire::EthernetAddress;
+#[cfg(feature = "proto-ipv4")]
+use crate::wire::Ipv4Address;
+#[cfg(not(feature = "proto-ipv4"))]
+use crate::wire::Ipv6Address;
+#[cfg(feature = "medium-ieee802154")]
+use crate::wire::{Ieee802154Address, Ieee802154Pan};
+use crate::wire::{IpAddress, IpCidr};
+#[cfg(feature = "medium-ppp")]
+use crate::wire::{
+    PppControlCode, PppControlPacket, PppControlRepr, PppFrame, PppProtocol, PPP_HEADER_LEN,
+};
+
+/// The port the TCP listening socket and the UDP socket are bound to.
+pub const LOCAL_PORT: u16 = 7;
+
+/// The endpoint the TCP client socket connects to.
+#[cfg(feature = "proto-ipv4")]
+pub const REMOTE_ENDPOINT: (IpAddress, u16) =
+    (IpAddress::Ipv4(Ipv4Address([192, 168, 1, 2])), LOCAL_PORT);
+/// The endpoint the TCP client socket connects to.
+#[cfg(not(feature = "proto-ipv4"))]
+pub const REMOTE_ENDPOINT: (IpAddress, u16) = (
+    IpAddress::Ipv6(Ipv6Address([
+        0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2,
+    ])),
+    LOCAL_PORT,
+);
+
+/// How far time advances for each frame, so that the timers of the interface and
+/// the sockets expire after a few frames.
+const TIME_STEP: Duration = Duration::from_millis(250);
+
+/// Run the frames in `data` through a new [Harness] for the given medium.
+///
+/// Each frame is prefixed by its length, as a big endian 16-bit number. A last frame
+/// shorter than its length is passed as it is.
+pub fn ingress(medium: Medium, data: &[u8]) {
+    let mut harness = Harness::new(medium);
+    for frame in frames(data) {
+        harness.ingress(frame);
+    }
+}
+
+/// Split `data` into length prefixed frames.
+fn frames(mut data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    core::iter::from_fn(move || {
+        if data.len() < 2 {
+            return None;
+        }
+        let len = usize::from(u16::from_be_bytes([data[0], data[1]]));
+        let (frame, rest) = data[2..].split_at(len.min(data.len() - 2));
+        data = rest;
+        Some(frame)
+    })
+}
+
+/// An interface receiving arbitrary frames, with sockets echoing what they receive.
+///
+/// The interface has the addresses 192.168.1.1/24 and fe80::1/64, depending on
+/// the enabled protocols. On PPP, the link is negotiated when the interface is created,
+/// with a peer at 192.168.1.2 assigning it 192.168.1.1, so that the frames reach the IP
+/// layer as well as LCP and IPCP. The interface has the following sockets:
+///
+///  * a TCP socket listening on [LOCAL_PORT], listening again once the connection is closed,
+///  * a TCP socket connecting to [REMOTE_ENDPOINT], connecting again once the connection
+///    is closed,
+///  * a UDP socket bound to [LOCAL_PORT].
+pub struct Harness {
+    iface: Interface<'static>,
+    device: FrameQueue,
+    sockets: SocketSet<'static>,
+    tcp_server: SocketHandle,
+    tcp_client: SocketHandle,
+    udp: SocketHandle,
+    now: Instant,
+}
+
+impl Harness {
+    /// Create an interface for the given medium, and its sockets.
+    pub fn new(medium: Medium) -> Harness {
+        let mut device = FrameQueue {
+            medium,
+            rx_queue: VecDeque::new(),
+            tx_queue: VecDeque::new(),
+        };
+
+        let mut ip_addrs = heapless::Vec::new();
+        #[cfg(feature = "proto-ipv4")]
+        ip_addrs
+            .push(IpCidr::new(IpAddress::v4(192, 168, 1, 1), 24))
+            .unwrap();
+        #[cfg(feature = "proto-ipv6")]
+        ip_addrs
+            .push(IpCidr::new(IpAddress::v6(0xfe80, 0, 0, 0, 0, 0, 0, 1), 64))
+            .unwrap();
+        let builder = InterfaceBuilder::new().ip_addrs(ip_addrs);
+
+        let builder = match medium {
+            #[cfg(feature = "medium-ethernet")]
+            Medium::Ethernet => builder
+                .hardware_addr(EthernetAddress([0x02, 0, 0, 0, 0, 1]).into())
+                .neighbor_cache(NeighborCache::new()),
+            #[cfg(feature = "medium-ip")]
+            Medium::Ip => builder,
+            #[cfg(feature = "medium-ppp")]
+            Medium::Ppp => builder,
+            #[cfg(feature = "medium-ieee802154")]
+            Medium::Ieee802154 => builder
+                .hardware_addr(Ieee802154Address::Extended([0x02, 0, 0, 0, 0, 0, 0, 1]).into())
+                .pan_id(Ieee802154Pan(0xbeef))
+                .neighbor_cache(NeighborCache::new()),
+        };
+
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        let builder = builder
+            .ipv4_reassembly_buffer(ReassemblyBuffer::new(vec![], BTreeMap::new()))
+            .ipv4_fragmentation_buffer(vec![]);
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        let builder = builder
+            .sixlowpan_reassembly_buffer(ReassemblyBuffer::new(vec![], BTreeMap::new()))
+            .sixlowpan_fragmentation_buffer(vec![]);
+
+        let iface = builder.finalize(&mut device);
+
+        let tcp_socket = || {
+            tcp::Socket::new(
+                tcp::SocketBuffer::new(vec![0; 1024]),
+                tcp::SocketBuffer::new(vec![0; 1024]),
+            )
+        };
+        let udp_socket = udp::Socket::new(
+            udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 4], vec![0; 1024]),
+            udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 4], vec![0; 1024]),
+        );
+
+        let mut sockets = SocketSet::new(vec![]);
+        let tcp_server = sockets.add(tcp_socket());
+        let tcp_client = sockets.add(tcp_socket());
+        let udp = sockets.add(udp_socket);
+        sockets
+            .get_mut::<udp::Socket>(udp)
+            .bind(LOCAL_PORT)
+            .unwrap();
+
+        let mut harness = Harness {
+            iface,
+            device,
+            sockets,
+            tcp_server,
+            tcp_client,
+            udp,
+            now: Instant::ZERO,
+        };
+        #[cfg(feature = "medium-ppp")]
+        if medium == Medium::Ppp {
+            harness.negotiate_ppp();
+        }
+        harness.collect();
+        harness
+    }
+
+    /// Negotiate the PPP link, answering the interface as its peer would.
+    #[cfg(feature = "medium-ppp")]
+    fn negotiate_ppp(&mut self) {
+        // The IPCP Configure-Request of the peer, with its address.
+        const PEER_REQUEST: [u8; 6] = [0x03, 0x06, 192, 168, 1, 2];
+        // The Configure-Nak assigning the address of the interface.
+        const ADDRESS_NAK: [u8; 6] = [0x03, 0x06, 192, 168, 1, 1];
+
+        self.device.rx_queue.push_back(ppp_control_frame(
+            PppProtocol::Lcp,
+            PppControlCode::ConfigureRequest,
+            1,
+            &[],
+        ));
+        // Each exchange takes one poll, so a few of them are enough.
+        for _ in 0..8 {
+            let _ = self
+                .iface
+                .poll(self.now, &mut self.device, &mut self.sockets);
+            while let Some(frame) = self.device.tx_queue.pop_front() {
+                let frame = match PppFrame::new_checked(&frame[..]) {
+                    Ok(frame) => frame,
+                    Err(_) => continue,
+                };
+                let repr = match PppControlPacket::new_checked(frame.payload())
+                    .and_then(|packet| PppControlRepr::parse(&packet))
+                {
+                    Ok(repr) if repr.code == PppControlCode::ConfigureRequest => repr,
+                    _ => continue,
+                };
+                // The interface requests the unspecified address until it is assigned one.
+                let answer = match frame.protocol() {
+                    PppProtocol::Ipcp if repr.data.starts_with(&[0x03, 0x06, 0, 0, 0, 0]) => {
+                        self.device.rx_queue.push_back(ppp_control_frame(
+                            PppProtocol::Ipcp,
+                            PppControlCode::ConfigureRequest,
+                            1,
+                            &PEER_REQUEST,
+                        ));
+                        (PppControlCode::ConfigureNak, &ADDRESS_NAK[..])
+                    }
+                    _ => (PppControlCode::ConfigureAck, repr.data),
+                };
+                let answer =
+                    ppp_control_frame(frame.protocol(), answer.0, repr.identifier, answer.1);
+                self.device.rx_queue.push_back(answer);
+            }
+            if self.iface.ppp_ipv4_config().is_some() {
+                break;
+            }
+        }
+        self.device.rx_queue.clear();
+    }
+
+    /// Receive a frame, and let the sockets echo what they received.
+    pub fn ingress(&mut self, frame: &[u8]) {
+        self.now += TIME_STEP;
+        self.device.rx_queue.push_back(frame.to_vec());
+        let _ = self
+            .iface
+            .poll(self.now, &mut self.device, &mut self.sockets);
+        self.collect();
+        let _ = self
+            .iface
+            .poll(self.now, &mut self.device, &mut self.sockets);
+        self.device.tx_queue.clear();
+    }
+
+    /// Echo what the sockets received, and reopen the TCP sockets once they are closed.
+    fn collect(&mut self) {
+        let mut data = [0; 1024];
+
+        let socket = self.sockets.get_mut::<tcp::Socket>(self.tcp_server);
+        if !socket.is_open() {
+            socket.listen(LOCAL_PORT).unwrap();
+        }
+        echo_tcp(socket, &mut data);
+
+        let socket = self.sockets.get_mut::<tcp::Socket>(self.tcp_client);
+        if !socket.is_open() {
+            let _ = socket.connect(self.iface.context(), REMOTE_ENDPOINT, 49152);
+        }
+        echo_tcp(socket, &mut data);
+
+        let socket = self.sockets.get_mut::<udp::Socket>(self.udp);
+        while let Ok((len, endpoint)) = socket.recv_slice(&mut data) {
+            let _ = socket.send_slice(&data[..len], endpoint);
+        }
+    }
+}
+
+/// Return a PPP frame carrying an LCP or IPCP packet.
+#[cfg(feature = "medium-ppp")]
+fn ppp_control_frame(
+    protocol: PppProtocol,
+    code: PppControlCode,
+    identifier: u8,
+    data: &[u8],
+) -> Vec<u8> {
+    let repr = PppControlRepr {
+        code,
+        identifier,
+        data,
+    };
+    let mut bytes = vec![0; PPP_HEADER_LEN + repr.buffer_len()];
+    let mut frame = PppFrame::new_unchecked(&mut bytes[..]);
+    frame.set_protocol(protocol);
+    repr.emit(&mut PppControlPacket::new_unchecked(frame.payload_mut()));
+    bytes
+}
+
+fn echo_tcp(socket: &mut tcp::Socket, data: &mut [u8]) {
+    let len = (socket.send_capacity() - socket.send_queue()).min(data.len());
+    if let Ok(len) = socket.recv_slice(&mut data[..len]) {
+        let _ = socket.send_slice(&data[..len]);
+    }
+    if !socket.may_recv() && socket.may_send() {
+        socket.close();
+    }
+}
+
+/// An in-memory device receiving the queued frames, and queueing the frames it sends
+/// until the harness discards them.
+#[derive(Debug)]
+struct FrameQueue {
+    medium: Medium,
+    rx_queue: VecDeque<Vec<u8>>,
+    tx_queue: VecDeque<Vec<u8>>,
+}
+
+impl Device for FrameQueue {
+    type RxToken<'a> = RxToken;
+    type TxToken<'a> = TxToken<'a>;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities {
+            max_transmission_unit: 1514,
+            medium: self.medium,
+            ..DeviceCapabilities::default()
+        }
+    }
+
+    fn receive(&mut self) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let buffer = self.rx_queue.pop_front()?;
+        Some((RxToken(buffer), TxToken(&mut self.tx_queue)))
+    }
+
+    fn transmit(&mut self) -> Option<Self::TxToken<'_>> {
+        Some(TxToken(&mut self.tx_queue))
+    }
+}
+
+struct RxToken(Vec<u8>);
+
+impl phy::RxToken for RxToken {
+    fn consume<R, F>(mut self, _timestamp: Instant, f: F) -> crate::Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> crate::Result<R>,
+    {
+        f(&mut self.0)
+    }
+}
+
+struct TxToken<'a>(&'a mut VecDeque<Vec<u8>>);
+
+impl<'a> phy::TxToken for TxToken<'a> {
+    fn consume<R, F>(self, _timestamp: Instant, len: usize, f: F) -> crate::Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> crate::Result<R>,
+    {
+        let mut buffer = vec![0; len];
+        let result = f(&mut buffer);
+        self.0.push_back(buffer);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let data = [0, 2, 1, 2, 0, 0, 0, 3, 3];
+        let expected: [&[u8]; 3] = [&[1, 2], &[], &[3]];
+        assert_eq!(frames(&data).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_ingress_udp() {
+        use crate::phy::ChecksumCapabilities;
+        use crate::wire::*;
+
+        let udp_repr = UdpRepr {
+            src_port: 49152,
+            dst_port: LOCAL_PORT,
+        };
+        let ip_repr = Ipv4Repr {
+            src_addr: Ipv4Address([192, 168, 1, 2]),
+            dst_addr: Ipv4Address([192, 168, 1, 1]),
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + 4,
+            hop_limit: 64,
+            traffic_class: 0,
+        };
+        let mut frame = vec![0; 14 + ip_repr.buffer_len() + ip_repr.payload_len];
+        let mut eth = EthernetFrame::new_unchecked(&mut frame[..]);
+        eth.set_dst_addr(EthernetAddress([0x02, 0, 0, 0, 0, 1]));
+        eth.set_src_addr(EthernetAddress([0x02, 0, 0, 0, 0, 2]));
+        eth.set_ethertype(EthernetProtocol::Ipv4);
+        let caps = ChecksumCapabilities::default();
+        let mut ip = Ipv4Packet::new_unchecked(eth.payload_mut());
+        ip_repr.emit(&mut ip, &caps);
+        udp_repr.emit(
+            &mut UdpPacket::new_unchecked(ip.payload_mut()),
+            &ip_repr.src_addr.into(),
+            &ip_repr.dst_addr.into(),
+            4,
+            |buf| buf.copy_from_slice(b"ping"),
+            &caps,
+        );
+
+        let mut harness = Harness::new(Medium::Ethernet);
+        harness.ingress(&frame);
+        // The datagram was received and echoed.
+        let socket = harness.sockets.get_mut::<udp::Socket>(harness.udp);
+        assert_eq!(socket.recv_memory_usage().used, 0);
+        assert!(socket.recv_memory_usage().high_water > 0);
+        assert!(socket.send_memory_usage().high_water > 0);
+
+        // Fuzzing the frame must not panic.
+        for len in 0..frame.len() {
+            harness.ingress(&frame[..len]);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "medium-ppp")]
+    fn test_ingress_ppp() {
+        use crate::iface::PppLinkState;
+        use crate::phy::ChecksumCapabilities;
+        use crate::wire::*;
+
+        let mut harness = Harness::new(Medium::Ppp);
+        assert_eq!(harness.iface.ppp_link_state(), Some(PppLinkState::Opened));
+        assert_eq!(
+            harness.iface.ppp_ipv4_config().map(|config| config.address),
+            Some(Ipv4Address([192, 168, 1, 1]))
+        );
+
+        let udp_repr = UdpRepr {
+            src_port: 49152,
+            dst_port: LOCAL_PORT,
+        };
+        let ip_repr = Ipv4Repr {
+            src_addr: Ipv4Address([192, 168, 1, 2]),
+            dst_addr: Ipv4Address([192, 168, 1, 1]),
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + 4,
+            hop_limit: 64,
+            traffic_class: 0,
+        };
+        let mut frame = vec![0; PPP_HEADER_LEN + ip_repr.buffer_len() + ip_repr.payload_len];
+        let mut ppp = PppFrame::new_unchecked(&mut frame[..]);
+        ppp.set_protocol(PppProtocol::Ipv4);
+        let caps = ChecksumCapabilities::default();
+        let mut ip = Ipv4Packet::new_unchecked(ppp.payload_mut());
+        ip_repr.emit(&mut ip, &caps);
+        udp_repr.emit(
+            &mut UdpPacket::new_unchecked(ip.payload_mut()),
+            &ip_repr.src_addr.into(),
+            &ip_repr.dst_addr.into(),
+            4,
+            |buf| buf.copy_from_slice(b"ping"),
+            &caps,
+        );
+
+        harness.ingress(&frame);
+        // The datagram was received and echoed.
+        let socket = harness.sockets.get_mut::<udp::Socket>(harness.udp);
+        assert_eq!(socket.recv_memory_usage().used, 0);
+        assert!(socket.recv_memory_usage().high_water > 0);
+        assert!(socket.send_memory_usage().high_water > 0);
+
+        // Fuzzing the frame must not panic, nor control packets tearing the link down.
+        for len in 0..frame.len() {
+            harness.ingress(&frame[..len]);
+        }
+        harness.ingress(&ppp_control_frame(
+            PppProtocol::Lcp,
+            PppControlCode::TerminateRequest,
+            2,
+            &[],
+        ));
+        assert_eq!(harness.iface.ppp_link_state(), Some(PppLinkState::Stopped));
+        ingress(Medium::Ppp, &[0, 6, 0xc0, 0x21, 0x01, 0x01, 0x00, 0x08]);
+    }
+}
@@ -0,0 +1,384 @@
+// Heads up! Before working on this file you should read RFC 5227, which specifies
+// IPv4 address conflict detection.
+
+use heapless::Vec;
+
+use crate::rand::Rand;
+use crate::time::{Duration, Instant};
+use crate::wire::{EthernetAddress, Ipv4Address, Ipv4Cidr};
+
+pub const MAX_ACD_ADDR_COUNT: usize = 4;
+pub const MAX_ACD_CONFLICT_COUNT: usize = 4;
+
+// The timing of probes and announcements, see RFC 5227 § 1.1.
+const PROBE_WAIT: Duration = Duration::from_secs(1);
+const PROBE_NUM: u8 = 3;
+const PROBE_MIN: Duration = Duration::from_secs(1);
+const PROBE_MAX: Duration = Duration::from_secs(2);
+const ANNOUNCE_WAIT: Duration = Duration::from_secs(2);
+const ANNOUNCE_NUM: u8 = 2;
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(2);
+const DEFEND_INTERVAL: Duration = Duration::from_secs(10);
+
+/// An IPv4 address the interface abandoned, because another node on the link uses it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Conflict {
+    pub cidr: Ipv4Cidr,
+    /// The hardware address of the other node.
+    pub hardware_addr: EthernetAddress,
+    /// Whether the address was assigned to the interface, rather than still being probed.
+    pub was_assigned: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum State {
+    /// Probing has not started yet.
+    Tentative,
+    /// `sent` probes were sent, and the next one is due at `at`; the address is assigned
+    /// at that time once all of them were sent.
+    Probing { sent: u8, at: Instant },
+    /// The address is assigned, `sent` announcements were sent, and the next one is due at `at`.
+    Announcing { sent: u8, at: Instant },
+    /// The address is assigned, and defended.
+    Assigned,
+}
+
+#[derive(Debug)]
+struct Address {
+    cidr: Ipv4Cidr,
+    state: State,
+    /// When the address was last defended against another node claiming it.
+    defended_at: Option<Instant>,
+}
+
+impl Address {
+    fn is_assigned(&self) -> bool {
+        !matches!(self.state, State::Tentative | State::Probing { .. })
+    }
+}
+
+/// A step of address conflict detection the interface has to carry out.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Action {
+    /// Send an ARP probe for the address.
+    Probe(Ipv4Address),
+    /// Send an ARP announcement for the address, i.e. a gratuitous ARP request.
+    Announce(Ipv4Address),
+    /// Add the address to the interface.
+    Assign(Ipv4Cidr),
+}
+
+/// The state of IPv4 address conflict detection.
+#[derive(Debug)]
+pub(crate) struct Acd {
+    addrs: Vec<Address, MAX_ACD_ADDR_COUNT>,
+    conflicts: Vec<Conflict, MAX_ACD_CONFLICT_COUNT>,
+}
+
+impl Acd {
+    pub(crate) fn new() -> Acd {
+        Acd {
+            addrs: Vec::new(),
+            conflicts: Vec::new(),
+        }
+    }
+
+    /// Start probing `cidr`, which is assigned once no other node claimed it.
+    ///
+    /// Returns `false` if there is no room for the address.
+    pub(crate) fn probe(&mut self, cidr: Ipv4Cidr) -> bool {
+        self.add(cidr, State::Tentative)
+    }
+
+    /// Start announcing and defending `cidr`, which is already assigned.
+    ///
+    /// Returns `false` if there is no room for the address.
+    pub(crate) fn announce(&mut self, cidr: Ipv4Cidr) -> bool {
+        let state = State::Announcing {
+            sent: 0,
+            at: Instant::ZERO,
+        };
+        self.add(cidr, state)
+    }
+
+    fn add(&mut self, cidr: Ipv4Cidr, state: State) -> bool {
+        self.remove(cidr);
+        let addr = Address {
+            cidr,
+            state,
+            defended_at: None,
+        };
+        self.addrs.push(addr).is_ok()
+    }
+
+    /// Stop probing or defending `cidr`.
+    pub(crate) fn remove(&mut self, cidr: Ipv4Cidr) {
+        self.addrs.retain(|addr| addr.cidr != cidr)
+    }
+
+    /// Return the addresses still being probed.
+    pub(crate) fn probed_addrs(&self) -> impl Iterator<Item = Ipv4Cidr> + '_ {
+        self.addrs
+            .iter()
+            .filter(|addr| !addr.is_assigned())
+            .map(|addr| addr.cidr)
+    }
+
+    /// Dequeue the oldest conflict, if any.
+    pub(crate) fn conflict(&mut self) -> Option<Conflict> {
+        if self.conflicts.is_empty() {
+            None
+        } else {
+            Some(self.conflicts.remove(0))
+        }
+    }
+
+    /// Process an ARP packet sent by another node, and return the address to remove from
+    /// the interface if it lost it to that node.
+    pub(crate) fn process_arp(
+        &mut self,
+        source_hardware_addr: EthernetAddress,
+        source_protocol_addr: Ipv4Address,
+        target_protocol_addr: Ipv4Address,
+        timestamp: Instant,
+    ) -> Option<Ipv4Cidr> {
+        let index = self.addrs.iter().position(|addr| {
+            let probe = addr.cidr.address();
+            // The probes of another node for an address being probed are a conflict too.
+            source_protocol_addr == probe
+                || (source_protocol_addr.is_unspecified()
+                    && target_protocol_addr == probe
+                    && !addr.is_assigned())
+        })?;
+
+        let addr = &mut self.addrs[index];
+        let was_assigned = addr.is_assigned();
+        if was_assigned {
+            let defended_recently = addr.defended_at.map_or(false, |defended_at| {
+                timestamp < defended_at + DEFEND_INTERVAL
+            });
+            if !defended_recently {
+                // RFC 5227 § 2.4 (b): defend the address with a single announcement.
+                net_debug!(
+                    "acd: defending {} against {}",
+                    addr.cidr,
+                    source_hardware_addr
+                );
+                addr.defended_at = Some(timestamp);
+                addr.state = State::Announcing {
+                    sent: ANNOUNCE_NUM - 1,
+                    at: timestamp,
+                };
+                return None;
+            }
+        }
+
+        let addr = self.addrs.remove(index);
+        net_debug!("acd: {} is used by {}", addr.cidr, source_hardware_addr);
+        let conflict = Conflict {
+            cidr: addr.cidr,
+            hardware_addr: source_hardware_addr,
+            was_assigned,
+        };
+        if self.conflicts.push(conflict).is_err() {
+            net_debug!("acd: no room for conflict on {}", addr.cidr);
+        }
+        was_assigned.then_some(addr.cidr)
+    }
+
+    /// Return the next step of address conflict detection due at `timestamp`, if any.
+    pub(crate) fn poll(&mut self, timestamp: Instant, rand: &mut Rand) -> Option<Action> {
+        for addr in self.addrs.iter_mut() {
+            match addr.state {
+                State::Tentative => {
+                    addr.state = State::Probing {
+                        sent: 0,
+                        at: timestamp + random_delay(rand, Duration::ZERO, PROBE_WAIT),
+                    };
+                }
+                State::Probing { sent, at } if timestamp >= at => {
+                    if sent == PROBE_NUM {
+                        addr.state = State::Announcing { sent: 0, at };
+                        return Some(Action::Assign(addr.cidr));
+                    }
+                    let delay = if sent + 1 == PROBE_NUM {
+                        ANNOUNCE_WAIT
+                    } else {
+                        random_delay(rand, PROBE_MIN, PROBE_MAX)
+                    };
+                    addr.state = State::Probing {
+                        sent: sent + 1,
+                        at: timestamp + delay,
+                    };
+                    return Some(Action::Probe(addr.cidr.address()));
+                }
+                State::Announcing { sent, at } if timestamp >= at => {
+                    addr.state = if sent + 1 == ANNOUNCE_NUM {
+                        State::Assigned
+                    } else {
+                        State::Announcing {
+                            sent: sent + 1,
+                            at: timestamp + ANNOUNCE_INTERVAL,
+                        }
+                    };
+                    return Some(Action::Announce(addr.cidr.address()));
+                }
+                _ => (),
+            }
+        }
+        None
+    }
+
+    /// Return the time at which [poll](#method.poll) has something to do.
+    pub(crate) fn poll_at(&self) -> Option<Instant> {
+        self.addrs
+            .iter()
+            .filter_map(|addr| match addr.state {
+                State::Tentative => Some(Instant::ZERO),
+                State::Probing { at, .. } | State::Announcing { at, .. } => Some(at),
+                State::Assigned => None,
+            })
+            .min()
+    }
+}
+
+/// Return a random delay between `min` and `max`.
+fn random_delay(rand: &mut Rand, min: Duration, max: Duration) -> Duration {
+    let range = (max - min).total_millis() + 1;
+    min + Duration::from_millis(u64::from(rand.rand_u32()) % range)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const REMOTE_HW_ADDR: EthernetAddress = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x01]);
+
+    fn cidr() -> Ipv4Cidr {
+        Ipv4Cidr::new(Ipv4Address::new(192, 168, 1, 2), 24)
+    }
+
+    /// Poll `acd` every 100 ms until it does something, and return what and when.
+    fn next_action(acd: &mut Acd, timestamp: &mut Instant, rand: &mut Rand) -> Option<Action> {
+        for _ in 0..100 {
+            if let Some(action) = acd.poll(*timestamp, rand) {
+                return Some(action);
+            }
+            *timestamp += Duration::from_millis(100);
+        }
+        None
+    }
+
+    #[test]
+    fn test_probe_and_announce() {
+        let mut acd = Acd::new();
+        let mut rand = Rand::new(1234);
+        let mut timestamp = Instant::ZERO;
+        assert!(acd.probe(cidr()));
+        assert_eq!(acd.poll_at(), Some(Instant::ZERO));
+        assert_eq!(acd.probed_addrs().next(), Some(cidr()));
+
+        let mut probed_at = vec![];
+        for _ in 0..PROBE_NUM {
+            assert_eq!(
+                next_action(&mut acd, &mut timestamp, &mut rand),
+                Some(Action::Probe(cidr().address()))
+            );
+            probed_at.push(timestamp);
+        }
+        assert!(probed_at[0] <= Instant::from_secs(1));
+        for pair in probed_at.windows(2) {
+            let interval = pair[1] - pair[0];
+            assert!(interval >= PROBE_MIN && interval <= PROBE_MAX);
+        }
+
+        assert_eq!(
+            next_action(&mut acd, &mut timestamp, &mut rand),
+            Some(Action::Assign(cidr()))
+        );
+        assert_eq!(timestamp, probed_at[2] + ANNOUNCE_WAIT);
+        assert_eq!(acd.probed_addrs().next(), None);
+        for _ in 0..ANNOUNCE_NUM {
+            assert_eq!(
+                next_action(&mut acd, &mut timestamp, &mut rand),
+                Some(Action::Announce(cidr().address()))
+            );
+        }
+        assert_eq!(acd.poll_at(), None);
+        assert_eq!(acd.conflict(), None);
+    }
+
+    #[test]
+    fn test_conflict_while_probing() {
+        let mut acd = Acd::new();
+        let mut rand = Rand::new(1234);
+        let mut timestamp = Instant::ZERO;
+        acd.probe(cidr());
+        next_action(&mut acd, &mut timestamp, &mut rand);
+
+        // Another node probing for the same address.
+        let removed = acd.process_arp(
+            REMOTE_HW_ADDR,
+            Ipv4Address::UNSPECIFIED,
+            cidr().address(),
+            timestamp,
+        );
+        assert_eq!(removed, None);
+        assert_eq!(
+            acd.conflict(),
+            Some(Conflict {
+                cidr: cidr(),
+                hardware_addr: REMOTE_HW_ADDR,
+                was_assigned: false,
+            })
+        );
+        assert_eq!(acd.conflict(), None);
+        assert_eq!(next_action(&mut acd, &mut timestamp, &mut rand), None);
+    }
+
+    #[test]
+    fn test_defend() {
+        let mut acd = Acd::new();
+        let mut rand = Rand::new(1234);
+        acd.announce(cidr());
+        assert_eq!(
+            acd.poll(Instant::ZERO, &mut rand),
+            Some(Action::Announce(cidr().address()))
+        );
+        assert_eq!(
+            acd.poll(Instant::from_secs(2), &mut rand),
+            Some(Action::Announce(cidr().address()))
+        );
+
+        // The first claim of another node is answered with an announcement.
+        let timestamp = Instant::from_secs(60);
+        let claim = |acd: &mut Acd, timestamp| {
+            acd.process_arp(
+                REMOTE_HW_ADDR,
+                cidr().address(),
+                cidr().address(),
+                timestamp,
+            )
+        };
+        assert_eq!(claim(&mut acd, timestamp), None);
+        assert_eq!(acd.conflict(), None);
+        assert_eq!(
+            acd.poll(timestamp, &mut rand),
+            Some(Action::Announce(cidr().address()))
+        );
+        assert_eq!(acd.poll_at(), None);
+
+        // The next one abandons the address.
+        let timestamp = timestamp + Duration::from_secs(5);
+        assert_eq!(claim(&mut acd, timestamp), Some(cidr()));
+        assert_eq!(
+            acd.conflict(),
+            Some(Conflict {
+                cidr: cidr(),
+                hardware_addr: REMOTE_HW_ADDR,
+                was_assigned: true,
+            })
+        );
+    }
+}
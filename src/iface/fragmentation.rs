@@ -4,7 +4,7 @@ use core::fmt;
 
 use managed::{ManagedMap, ManagedSlice};
 
-use crate::storage::Assembler;
+use crate::storage::{Assembler, MemoryUsage};
 use crate::time::{Duration, Instant};
 use crate::Error;
 use crate::Result;
@@ -261,6 +261,7 @@ impl<'a> PacketAssembler<'a> {
 pub struct PacketAssemblerSet<'a, Key: Eq + Ord + Clone + Copy> {
     packet_buffer: ManagedSlice<'a, PacketAssembler<'a>>,
     index_buffer: ManagedMap<'a, Key, usize>,
+    high_water: usize,
 }
 
 impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
@@ -306,9 +307,32 @@ impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
         Self {
             packet_buffer,
             index_buffer,
+            high_water: 0,
         }
     }
 
+    /// Return the number of bytes of reassembly buffer storage used and committed by the set.
+    ///
+    /// A buffer counts as used while a packet is being reassembled in it.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            used: self.used(),
+            capacity: self.packet_buffer.iter().map(|p| p.buffer.len()).sum(),
+            high_water: self.high_water,
+        }
+    }
+
+    fn used(&self) -> usize {
+        self.index_buffer
+            .iter()
+            .map(|(_, i)| self.packet_buffer[*i].buffer.len())
+            .sum()
+    }
+
+    fn update_high_water(&mut self) {
+        self.high_water = core::cmp::max(self.high_water, self.used());
+    }
+
     /// Reserve a [`PacketAssembler`], which is linked to a specific key.
     /// Returns the reserved fragments assembler.
     ///
@@ -337,9 +361,11 @@ impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
 
         // NOTE(thvdveld): this should not fail because we already checked the available space.
         match self.index_buffer.insert(*key, i) {
-            Ok(_) => Ok(&mut self.packet_buffer[i]),
+            Ok(_) => (),
             Err(_) => unreachable!(),
         }
+        self.update_high_water();
+        Ok(&mut self.packet_buffer[i])
     }
 
     /// Return the first free packet assembler available from the cache.
@@ -378,6 +404,8 @@ impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
     /// - Returns [`Error::PacketAssemblerSetKeyNotFound`] when the `key` was not found.
     /// - Returns [`Error::PacketAssemblerIncomplete`] when the fragments assembler was empty or not fully assembled.
     pub(crate) fn get_assembled_packet(&mut self, key: &K) -> Result<&[u8]> {
+        // Owned buffers only grow once fragments are added, so account for them here too.
+        self.update_high_water();
         if let Some(i) = self.index_buffer.get(key) {
             let p = self.packet_buffer[*i].assemble()?;
             self.index_buffer.remove(key);
@@ -387,6 +415,43 @@ impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
         }
     }
 
+    /// Return the number of packets being reassembled.
+    pub(crate) fn len(&self) -> usize {
+        self.index_buffer.len()
+    }
+
+    /// Return the earliest instant at which a packet assembler expires, if any.
+    pub(crate) fn expires_at(&self) -> Option<Instant> {
+        self.index_buffer
+            .iter()
+            .filter_map(|(_, i)| self.packet_buffer[*i].expires_at().ok())
+            .min()
+    }
+
+    /// Remove a [`PacketAssembler`] that expired at `timestamp`, and return its key, along
+    /// with the data received at the start of the packet.
+    ///
+    /// The data is empty if the first fragment of the packet was not received.
+    pub(crate) fn remove_expired(&mut self, timestamp: Instant) -> Option<(K, &[u8])> {
+        let (key, i) = self
+            .index_buffer
+            .iter()
+            .find(|(_, i)| match self.packet_buffer[**i].expires_at() {
+                Ok(expires_at) => timestamp >= expires_at,
+                Err(_) => true,
+            })
+            .map(|(k, i)| (*k, *i))?;
+        self.index_buffer.remove(&key);
+
+        let packet = &mut self.packet_buffer[i];
+        let front = match &packet.assembler {
+            AssemblerState::Assembling { assembler, .. } => assembler.peek_front().unwrap_or(0),
+            AssemblerState::NotInit => 0,
+        };
+        packet.mark_discarded();
+        Some((key, &packet.buffer[..front]))
+    }
+
     /// Remove all [`PacketAssembler`]s that are marked as discarded.
     pub fn remove_discarded(&mut self) {
         loop {
@@ -600,4 +665,32 @@ mod tests {
             .unwrap();
         set.get_assembled_packet(&key).unwrap();
     }
+
+    #[test]
+    fn packet_assembler_set_memory_usage() {
+        let mut buf_1 = [0u8; 16];
+        let mut buf_2 = [0u8; 16];
+        let mut packet_assembler_cache = [
+            PacketAssembler::new(&mut buf_1[..]),
+            PacketAssembler::new(&mut buf_2[..]),
+        ];
+        let mut packet_index_cache = [None, None];
+
+        let mut set =
+            PacketAssemblerSet::new(&mut packet_assembler_cache[..], &mut packet_index_cache[..]);
+        assert_eq!(set.memory_usage().capacity, 32);
+        assert_eq!(set.memory_usage().used, 0);
+
+        set.reserve_with_key(&Key { id: 0 }).unwrap();
+        set.reserve_with_key(&Key { id: 1 }).unwrap();
+        set.get_packet_assembler_mut(&Key { id: 0 })
+            .unwrap()
+            .start(Some(0), Instant::from_secs(0), 0)
+            .unwrap();
+        set.get_assembled_packet(&Key { id: 0 }).unwrap();
+
+        let usage = set.memory_usage();
+        assert_eq!(usage.used, 16);
+        assert_eq!(usage.high_water, 32);
+    }
 }
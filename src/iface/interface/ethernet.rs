@@ -1,13 +1,18 @@
 use super::check;
+use super::DropReason;
 use super::EthernetPacket;
 use super::FragmentsBuffer;
 use super::InterfaceInner;
 use super::SocketSet;
 
+#[cfg(feature = "socket-ethernet")]
+use crate::phy::Medium;
 use crate::phy::TxToken;
+#[cfg(feature = "socket-ethernet")]
+use crate::socket::{ethernet, AnySocket};
 use crate::wire::*;
-use crate::Error;
 use crate::Result;
+use crate::{Error, Layer};
 
 impl<'i> InterfaceInner<'i> {
     #[cfg(feature = "medium-ethernet")]
@@ -17,13 +22,34 @@ impl<'i> InterfaceInner<'i> {
         frame: &'frame T,
         _fragments: &'frame mut FragmentsBuffer<'i>,
     ) -> Option<EthernetPacket<'frame>> {
-        let eth_frame = check!(EthernetFrame::new_checked(frame));
+        let eth_frame = check!(self, EthernetFrame::new_checked(frame));
+
+        let for_us = eth_frame.dst_addr().is_broadcast()
+            || eth_frame.dst_addr().is_multicast()
+            || HardwareAddress::Ethernet(eth_frame.dst_addr()) == self.hardware_addr.unwrap();
+
+        #[cfg(feature = "socket-ethernet")]
+        let promiscuous = self.promiscuous;
+        #[cfg(not(feature = "socket-ethernet"))]
+        let promiscuous = false;
 
         // Ignore any packets not directed to our hardware address or any of the multicast groups.
-        if !eth_frame.dst_addr().is_broadcast()
-            && !eth_frame.dst_addr().is_multicast()
-            && HardwareAddress::Ethernet(eth_frame.dst_addr()) != self.hardware_addr.unwrap()
-        {
+        if !for_us && !promiscuous {
+            net_trace!("ethernet: ignoring frame to {}", eth_frame.dst_addr());
+            self.drop_packet(DropReason::Filtered);
+            return None;
+        }
+
+        #[cfg(feature = "socket-ethernet")]
+        let handled_by_ethernet_socket = self.ethernet_socket_filter(sockets, &eth_frame);
+        #[cfg(not(feature = "socket-ethernet"))]
+        let handled_by_ethernet_socket = false;
+
+        // In promiscuous mode, frames directed to other hosts are only seen by sockets.
+        if !for_us {
+            if !handled_by_ethernet_socket {
+                self.drop_packet(DropReason::Filtered);
+            }
             return None;
         }
 
@@ -32,7 +58,7 @@ impl<'i> InterfaceInner<'i> {
             EthernetProtocol::Arp => self.process_arp(self.now, &eth_frame),
             #[cfg(feature = "proto-ipv4")]
             EthernetProtocol::Ipv4 => {
-                let ipv4_packet = check!(Ipv4Packet::new_checked(eth_frame.payload()));
+                let ipv4_packet = check!(self, Ipv4Packet::new_checked(eth_frame.payload()));
 
                 #[cfg(feature = "proto-ipv4-fragmentation")]
                 {
@@ -48,15 +74,44 @@ impl<'i> InterfaceInner<'i> {
             }
             #[cfg(feature = "proto-ipv6")]
             EthernetProtocol::Ipv6 => {
-                let ipv6_packet = check!(Ipv6Packet::new_checked(eth_frame.payload()));
+                let ipv6_packet = check!(self, Ipv6Packet::new_checked(eth_frame.payload()));
                 self.process_ipv6(sockets, &ipv6_packet)
                     .map(EthernetPacket::Ip)
             }
+            #[cfg(feature = "proto-lldp")]
+            EthernetProtocol::Lldp if self.lldp.is_some() => self.process_lldp(&eth_frame),
             // Drop all other traffic.
-            _ => None,
+            _ => {
+                if !handled_by_ethernet_socket {
+                    net_trace!("ethernet: unsupported ethertype {}", eth_frame.ethertype());
+                    self.drop_packet(DropReason::Unsupported);
+                }
+                None
+            }
         }
     }
 
+    /// Pass an Ethernet frame to all the Ethernet sockets accepting it, and return
+    /// whether any did.
+    #[cfg(feature = "socket-ethernet")]
+    fn ethernet_socket_filter<T: AsRef<[u8]>>(
+        &mut self,
+        sockets: &mut SocketSet,
+        eth_frame: &EthernetFrame<T>,
+    ) -> bool {
+        let mut handled_by_ethernet_socket = false;
+        for ethernet_socket in sockets
+            .items_mut()
+            .filter_map(|i| ethernet::Socket::downcast_mut(&mut i.socket))
+        {
+            if ethernet_socket.accepts(eth_frame) {
+                ethernet_socket.process(self, eth_frame);
+                handled_by_ethernet_socket = true;
+            }
+        }
+        handled_by_ethernet_socket
+    }
+
     #[cfg(feature = "medium-ethernet")]
     pub(super) fn dispatch_ethernet<Tx, F>(
         &mut self,
@@ -69,20 +124,48 @@ impl<'i> InterfaceInner<'i> {
         F: FnOnce(EthernetFrame<&mut [u8]>),
     {
         let tx_len = EthernetFrame::<&[u8]>::buffer_len(buffer_len);
+        #[cfg(feature = "stats")]
+        self.stats.record_tx(tx_len);
         tx_token.consume(self.now, tx_len, |tx_buffer| {
             debug_assert!(tx_buffer.as_ref().len() == tx_len);
-            let mut frame = EthernetFrame::new_unchecked(tx_buffer);
+            let mut frame = EthernetFrame::new_unchecked(&mut *tx_buffer);
 
             let src_addr = if let Some(HardwareAddress::Ethernet(addr)) = self.hardware_addr {
                 addr
             } else {
-                return Err(Error::Malformed);
+                return Err(Error::Malformed(Layer::Link));
             };
 
             frame.set_src_addr(src_addr);
 
             f(frame);
 
+            let frame = EthernetFrame::new_unchecked(&*tx_buffer);
+            net_trace!(
+                "ethernet: sending {} frame to {}",
+                frame.ethertype(),
+                frame.dst_addr()
+            );
+            Ok(())
+        })
+    }
+
+    /// Send a whole Ethernet frame queued by an Ethernet socket, as it is.
+    #[cfg(feature = "socket-ethernet")]
+    pub(super) fn dispatch_ethernet_frame<Tx: TxToken>(
+        &mut self,
+        tx_token: Tx,
+        frame: &[u8],
+    ) -> Result<()> {
+        if self.caps.medium != Medium::Ethernet {
+            net_debug!("ethernet: cannot send frame on a non-Ethernet interface, dropping");
+            return Ok(());
+        }
+
+        #[cfg(feature = "stats")]
+        self.stats.record_tx(frame.len());
+        tx_token.consume(self.now, frame.len(), |tx_buffer| {
+            tx_buffer.copy_from_slice(frame);
             Ok(())
         })
     }
@@ -0,0 +1,206 @@
+// Heads up! Before working on this file you should read the parts of RFC 1812
+// that discuss forwarding, and RFC 8200 § 3 about the hop limit of IPv6 packets.
+
+use super::icmp_reply_payload_len;
+use super::DropReason;
+use super::Interface;
+use super::InterfaceInner;
+use super::IpPacket;
+
+use crate::storage::PacketBuffer;
+use crate::wire::*;
+
+/// A buffer of IP packets forwarded between interfaces, holding the header and the
+/// payload of each packet.
+///
+/// See [InterfaceBuilder::forwarding_buffers](struct.InterfaceBuilder.html#method.forwarding_buffers).
+pub type ForwardingBuffer<'a> = PacketBuffer<'a, IpRepr>;
+
+/// The buffers of an interface forwarding IP packets.
+pub(crate) struct Forwarding<'a> {
+    /// The packets received by the interface, waiting for [forward] to hand them
+    /// to another interface.
+    pub(super) rx: ForwardingBuffer<'a>,
+    /// The packets handed to the interface by [forward], waiting to be sent.
+    pub(super) tx: ForwardingBuffer<'a>,
+}
+
+/// Forward the IP packets received by each of `interfaces` and addressed to other hosts,
+/// and return the number of packets forwarded.
+///
+/// Each packet is handed to the interface attached to the network of its destination, or
+/// else to the first interface with a route to it, and sent by the next call to
+/// [Interface::poll] of that interface. Only interfaces with forwarding buffers take part,
+/// and packets are never forwarded back to the interface they were received by. Packets
+/// that no interface has a route to, or room for, are dropped.
+///
+/// Directed broadcasts, addressed to the broadcast address of the network of the output
+/// interface, are dropped as well, as required by [RFC 2644]; they would otherwise let
+/// a single packet from a remote host reach every host on that network. See
+/// [Interface::set_forward_directed_broadcasts] to forward them.
+///
+/// [RFC 2644]: https://tools.ietf.org/html/rfc2644
+pub fn forward(interfaces: &mut [&mut Interface<'_>]) -> usize {
+    let mut forwarded = 0;
+    for input in 0..interfaces.len() {
+        // Take the buffers out of the interface, so that the others can be borrowed.
+        let mut forwarding = match interfaces[input].inner.forwarding.take() {
+            Some(forwarding) => forwarding,
+            None => continue,
+        };
+
+        while let Ok((ip_repr, payload)) = forwarding.rx.dequeue() {
+            let dst_addr = ip_repr.dst_addr();
+            let buffer = output_interface(interfaces, &dst_addr).and_then(|output| {
+                let inner = &mut interfaces[output].inner;
+                if inner.is_broadcast(&dst_addr) && !inner.forward_directed_broadcasts {
+                    net_debug!("forwarding: refusing directed broadcast to {}", dst_addr);
+                    return None;
+                }
+                let forwarding = inner.forwarding.as_mut()?;
+                forwarding.tx.enqueue(payload.len(), ip_repr).ok()
+            });
+            match buffer {
+                Some(buffer) => {
+                    buffer.copy_from_slice(payload);
+                    forwarded += 1;
+                }
+                None => {
+                    net_debug!("forwarding: cannot forward packet to {}", dst_addr);
+                    interfaces[input]
+                        .inner
+                        .drop_stats
+                        .record(DropReason::Forwarding);
+                }
+            }
+        }
+
+        interfaces[input].inner.forwarding = Some(forwarding);
+    }
+    forwarded
+}
+
+/// Select the interface to forward a packet to `dst_addr` to, among those whose
+/// forwarding buffers are in place.
+fn output_interface(interfaces: &[&mut Interface<'_>], dst_addr: &IpAddress) -> Option<usize> {
+    let candidates = || {
+        interfaces
+            .iter()
+            .map(|iface| &iface.inner)
+            .enumerate()
+            .filter(|(_, inner)| inner.forwarding.is_some())
+    };
+    candidates()
+        .find(|(_, inner)| inner.in_same_network(dst_addr))
+        .or_else(|| {
+            candidates().find(|(_, inner)| inner.routes.lookup(dst_addr, inner.now).is_some())
+        })
+        .map(|(index, _)| index)
+}
+
+impl<'a> InterfaceInner<'a> {
+    /// Return whether a packet to `dst_addr`, which is not addressed to the interface,
+    /// is forwarded.
+    pub(super) fn is_forwarded(&self, dst_addr: IpAddress) -> bool {
+        // Link-local addresses are only valid on the link the packet was received from.
+        let link_local = match dst_addr {
+            #[cfg(feature = "proto-ipv4")]
+            IpAddress::Ipv4(addr) => addr.is_link_local(),
+            #[cfg(feature = "proto-ipv6")]
+            IpAddress::Ipv6(addr) => addr.is_link_local(),
+        };
+        self.forwarding.is_some() && dst_addr.is_unicast() && !link_local
+    }
+
+    /// Queue a packet for forwarding with its hop limit decremented, or answer it with
+    /// an ICMP time exceeded message if its hop limit is exhausted.
+    pub(super) fn forward_ip<'frame>(
+        &mut self,
+        ip_repr: IpRepr,
+        ip_payload: &'frame [u8],
+    ) -> Option<IpPacket<'frame>> {
+        self.forward_ip_with(ip_repr, ip_payload.len(), ip_payload, |buffer| {
+            buffer.copy_from_slice(ip_payload)
+        })
+    }
+
+    /// Like [forward_ip](#method.forward_ip), for packets whose payload has to be
+    /// emitted by `emit`, such as 6LoWPAN packets with compressed headers.
+    ///
+    /// Only `icmp_data` is quoted in the ICMP time exceeded message.
+    pub(super) fn forward_ip_with<'frame>(
+        &mut self,
+        mut ip_repr: IpRepr,
+        payload_len: usize,
+        icmp_data: &'frame [u8],
+        emit: impl FnOnce(&mut [u8]),
+    ) -> Option<IpPacket<'frame>> {
+        if ip_repr.hop_limit() <= 1 {
+            net_debug!("forwarding: hop limit exhausted");
+            self.drop_packet(DropReason::HopLimit);
+            return self.time_exceeded(ip_repr, icmp_data);
+        }
+
+        // The payload length of reassembled packets is that of their last fragment.
+        ip_repr.set_payload_len(payload_len);
+        match ip_repr {
+            #[cfg(feature = "proto-ipv4")]
+            IpRepr::Ipv4(ref mut repr) => repr.hop_limit -= 1,
+            #[cfg(feature = "proto-ipv6")]
+            IpRepr::Ipv6(ref mut repr) => repr.hop_limit -= 1,
+        }
+
+        match self.forwarding.as_mut()?.rx.enqueue(payload_len, ip_repr) {
+            Ok(buffer) => emit(buffer),
+            Err(_) => {
+                net_debug!("forwarding: buffer full");
+                self.drop_packet(DropReason::Forwarding);
+            }
+        }
+        None
+    }
+
+    /// Return the ICMP time exceeded message answering a packet whose hop limit is
+    /// exhausted, sent from the address of the interface.
+    fn time_exceeded<'frame>(
+        &mut self,
+        ip_repr: IpRepr,
+        icmp_data: &'frame [u8],
+    ) -> Option<IpPacket<'frame>> {
+        match ip_repr {
+            #[cfg(feature = "proto-ipv4")]
+            IpRepr::Ipv4(ipv4_repr) => {
+                let src_addr = self.get_source_address_ipv4(ipv4_repr.src_addr)?;
+                let payload_len =
+                    icmp_reply_payload_len(icmp_data.len(), IPV4_MIN_MTU, ipv4_repr.buffer_len());
+                let icmp_repr = Icmpv4Repr::TimeExceeded {
+                    reason: Icmpv4TimeExceeded::TtlExpired,
+                    header: ipv4_repr,
+                    data: &icmp_data[..payload_len],
+                };
+                // The reply is sent to the source of the packet, from this interface.
+                let reply_to = Ipv4Repr {
+                    dst_addr: src_addr,
+                    ..ipv4_repr
+                };
+                self.icmpv4_reply(reply_to, icmp_repr)
+            }
+            #[cfg(feature = "proto-ipv6")]
+            IpRepr::Ipv6(ipv6_repr) => {
+                let src_addr = self.get_source_address_ipv6(ipv6_repr.src_addr)?;
+                let payload_len =
+                    icmp_reply_payload_len(icmp_data.len(), IPV6_MIN_MTU, ipv6_repr.buffer_len());
+                let icmp_repr = Icmpv6Repr::TimeExceeded {
+                    reason: Icmpv6TimeExceeded::HopLimitExceeded,
+                    header: ipv6_repr,
+                    data: &icmp_data[..payload_len],
+                };
+                let reply_to = Ipv6Repr {
+                    dst_addr: src_addr,
+                    ..ipv6_repr
+                };
+                self.icmpv6_reply(reply_to, icmp_repr)
+            }
+        }
+    }
+}
@@ -1,15 +1,23 @@
 use super::check;
 use super::icmp_reply_payload_len;
+use super::DropReason;
 use super::InterfaceInner;
 use super::IpPacket;
 use super::PacketAssemblerSet;
 use super::SocketSet;
+use super::SourceRoutePolicy;
 
 #[cfg(feature = "proto-igmp")]
 use super::IgmpReportState;
 
 #[cfg(feature = "medium-ethernet")]
 use super::EthernetPacket;
+#[cfg(feature = "medium-ethernet")]
+use super::MAX_IP_ADDR_COUNT;
+#[cfg(feature = "medium-ethernet")]
+use crate::iface::acd::Action as AcdAction;
+#[cfg(feature = "medium-ethernet")]
+use heapless::Vec;
 
 #[cfg(feature = "proto-ipv4-fragmentation")]
 use super::Ipv4OutPacket;
@@ -21,7 +29,7 @@ use crate::socket::icmp;
 use crate::socket::AnySocket;
 
 use crate::phy::{Medium, TxToken};
-use crate::{time::*, wire::*, Error, Result};
+use crate::{time::*, wire::*, Error, Layer, Result};
 
 impl<'a> InterfaceInner<'a> {
     pub(super) fn process_ipv4<'output, 'payload: 'output, T: AsRef<[u8]> + ?Sized>(
@@ -30,50 +38,94 @@ impl<'a> InterfaceInner<'a> {
         ipv4_packet: &Ipv4Packet<&'payload T>,
         _fragments: Option<&'output mut PacketAssemblerSet<'a, Ipv4FragKey>>,
     ) -> Option<IpPacket<'output>> {
-        let ipv4_repr = check!(Ipv4Repr::parse(ipv4_packet, &self.caps.checksum));
-        if !self.is_unicast_v4(ipv4_repr.src_addr) {
-            // Discard packets with non-unicast source addresses.
-            net_debug!("non-unicast source address");
+        let ipv4_repr = check!(self, Ipv4Repr::parse(ipv4_packet, &self.caps.checksum));
+        if self.is_martian_source(ipv4_repr.src_addr.into()) {
+            // Discard packets with source addresses that can not be valid.
+            net_debug!("martian source address");
+            self.drop_packet(DropReason::Martian);
+            return None;
+        }
+
+        if self.source_route_policy != SourceRoutePolicy::Accept
+            && has_source_route(ipv4_packet.options())
+        {
+            net_debug!("source routed packet");
+            self.drop_packet(DropReason::Filtered);
+            if self.source_route_policy == SourceRoutePolicy::Reject {
+                let ip_payload = ipv4_packet.payload();
+                let payload_len =
+                    icmp_reply_payload_len(ip_payload.len(), IPV4_MIN_MTU, ipv4_repr.buffer_len());
+                let icmp_reply_repr = Icmpv4Repr::DstUnreachable {
+                    reason: Icmpv4DstUnreachable::SrcRouteFailed,
+                    header: ipv4_repr,
+                    data: &ip_payload[0..payload_len],
+                };
+                return self.icmpv4_reply(ipv4_repr, icmp_reply_repr);
+            }
             return None;
         }
 
         #[cfg(feature = "proto-ipv4-fragmentation")]
         let ip_payload = {
-            const REASSEMBLY_TIMEOUT: u64 = 90;
-
             let fragments = _fragments.unwrap();
 
             if ipv4_packet.more_frags() || ipv4_packet.frag_offset() != 0 {
                 let key = ipv4_packet.get_key();
 
+                let end = ipv4_packet.frag_offset() as usize + ipv4_packet.payload().len();
+                if end > self.ipv4_reassembly_max_size {
+                    net_debug!("fragmented packet too large");
+                    if let Ok(f) = fragments.get_packet_assembler_mut(&key) {
+                        f.mark_discarded();
+                        fragments.remove_discarded();
+                    }
+                    self.drop_packet(DropReason::Reassembly);
+                    return None;
+                }
+
                 let f = match fragments.get_packet_assembler_mut(&key) {
                     Ok(f) => f,
                     Err(_) => {
+                        if fragments.len() >= self.ipv4_reassembly_max_packets {
+                            net_debug!("too many fragmented packets being reassembled");
+                            self.drop_packet(DropReason::Reassembly);
+                            return None;
+                        }
+
                         let p = match fragments.reserve_with_key(&key) {
                             Ok(p) => p,
                             Err(Error::PacketAssemblerSetFull) => {
                                 net_debug!("No available packet assembler for fragmented packet");
+                                self.drop_packet(DropReason::Reassembly);
                                 return Default::default();
                             }
-                            e => check!(e),
+                            e => check!(self, e, DropReason::Reassembly),
                         };
 
-                        check!(p.start(
-                            None,
-                            self.now + Duration::from_secs(REASSEMBLY_TIMEOUT),
-                            0
-                        ));
-
-                        check!(fragments.get_packet_assembler_mut(&key))
+                        check!(
+                            self,
+                            p.start(None, self.now + self.ipv4_reassembly_timeout, 0),
+                            DropReason::Reassembly
+                        );
+
+                        check!(
+                            self,
+                            fragments.get_packet_assembler_mut(&key),
+                            DropReason::Reassembly
+                        )
                     }
                 };
 
                 if !ipv4_packet.more_frags() {
                     // This is the last fragment, so we know the total size
-                    check!(f.set_total_size(
-                        ipv4_packet.total_len() as usize - ipv4_packet.header_len() as usize
-                            + ipv4_packet.frag_offset() as usize,
-                    ));
+                    check!(
+                        self,
+                        f.set_total_size(
+                            ipv4_packet.total_len() as usize - ipv4_packet.header_len() as usize
+                                + ipv4_packet.frag_offset() as usize,
+                        ),
+                        DropReason::Reassembly
+                    );
                 }
 
                 match f.add(ipv4_packet.payload(), ipv4_packet.frag_offset() as usize) {
@@ -81,16 +133,22 @@ impl<'a> InterfaceInner<'a> {
                         // NOTE: according to the standard, the total length needs to be
                         // recomputed, as well as the checksum. However, we don't really use
                         // the IPv4 header after the packet is reassembled.
-                        check!(fragments.get_assembled_packet(&key))
+                        check!(
+                            self,
+                            fragments.get_assembled_packet(&key),
+                            DropReason::Reassembly
+                        )
                     }
                     Ok(false) => {
                         return None;
                     }
                     Err(Error::PacketAssemblerOverlap) => {
+                        self.drop_packet(DropReason::Reassembly);
                         return None;
                     }
                     Err(e) => {
                         net_debug!("fragmentation error: {}", e);
+                        self.drop_packet(DropReason::Reassembly);
                         return None;
                     }
                 }
@@ -114,7 +172,7 @@ impl<'a> InterfaceInner<'a> {
             if ipv4_repr.next_header == IpProtocol::Udp && self.hardware_addr.is_some() {
                 // First check for source and dest ports, then do `UdpRepr::parse` if they match.
                 // This way we avoid validating the UDP checksum twice for all non-DHCP UDP packets (one here, one in `process_udp`)
-                let udp_packet = check!(UdpPacket::new_checked(ip_payload));
+                let udp_packet = check!(self, UdpPacket::new_checked(ip_payload));
                 if udp_packet.src_port() == DHCP_SERVER_PORT
                     && udp_packet.dst_port() == DHCP_CLIENT_PORT
                 {
@@ -123,12 +181,10 @@ impl<'a> InterfaceInner<'a> {
                         .find_map(|i| dhcpv4::Socket::downcast_mut(&mut i.socket))
                     {
                         let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
-                        let udp_repr = check!(UdpRepr::parse(
-                            &udp_packet,
-                            &src_addr,
-                            &dst_addr,
-                            &self.caps.checksum
-                        ));
+                        let udp_repr = check!(
+                            self,
+                            UdpRepr::parse(&udp_packet, &src_addr, &dst_addr, &self.caps.checksum)
+                        );
                         let udp_payload = udp_packet.payload();
 
                         dhcp_socket.process(self, &ipv4_repr, &udp_repr, udp_payload);
@@ -151,6 +207,18 @@ impl<'a> InterfaceInner<'a> {
                     .lookup(&IpAddress::Ipv4(ipv4_repr.dst_addr), self.now)
                     .map_or(true, |router_addr| !self.has_ip_addr(router_addr))
             {
+                #[cfg(feature = "forwarding")]
+                if self.is_forwarded(ipv4_repr.dst_addr.into()) {
+                    #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+                    if ipv4_packet.more_frags() || ipv4_packet.frag_offset() != 0 {
+                        net_debug!("forwarding: fragments can not be forwarded without reassembly");
+                        self.drop_packet(DropReason::Unsupported);
+                        return None;
+                    }
+                    return self.forward_ip(ip_repr, ip_payload);
+                }
+
+                self.drop_packet(DropReason::Filtered);
                 return None;
             }
         }
@@ -163,13 +231,16 @@ impl<'a> InterfaceInner<'a> {
 
             #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
             IpProtocol::Udp => {
-                let udp_packet = check!(UdpPacket::new_checked(ip_payload));
-                let udp_repr = check!(UdpRepr::parse(
-                    &udp_packet,
-                    &ipv4_repr.src_addr.into(),
-                    &ipv4_repr.dst_addr.into(),
-                    &self.checksum_caps(),
-                ));
+                let udp_packet = check!(self, UdpPacket::new_checked(ip_payload));
+                let udp_repr = check!(
+                    self,
+                    UdpRepr::parse(
+                        &udp_packet,
+                        &ipv4_repr.src_addr.into(),
+                        &ipv4_repr.dst_addr.into(),
+                        &self.checksum_caps(),
+                    )
+                );
 
                 self.process_udp(
                     sockets,
@@ -187,6 +258,8 @@ impl<'a> InterfaceInner<'a> {
             _ if handled_by_raw_socket => None,
 
             _ => {
+                self.drop_packet(DropReason::Unsupported);
+
                 // Send back as much of the original payload as we can.
                 let payload_len =
                     icmp_reply_payload_len(ip_payload.len(), IPV4_MIN_MTU, ipv4_repr.buffer_len());
@@ -206,8 +279,8 @@ impl<'a> InterfaceInner<'a> {
         timestamp: Instant,
         eth_frame: &EthernetFrame<&'frame T>,
     ) -> Option<EthernetPacket<'frame>> {
-        let arp_packet = check!(ArpPacket::new_checked(eth_frame.payload()));
-        let arp_repr = check!(ArpRepr::parse(&arp_packet));
+        let arp_packet = check!(self, ArpPacket::new_checked(eth_frame.payload()));
+        let arp_repr = check!(self, ArpRepr::parse(&arp_packet));
 
         match arp_repr {
             ArpRepr::EthernetIpv4 {
@@ -217,8 +290,45 @@ impl<'a> InterfaceInner<'a> {
                 target_protocol_addr,
                 ..
             } => {
+                let own_hardware_addr = match self.hardware_addr {
+                    Some(HardwareAddress::Ethernet(addr)) => addr,
+                    _ => unreachable!(),
+                };
+
+                if let Some(acd) = self
+                    .acd
+                    .as_mut()
+                    .filter(|_| source_hardware_addr != own_hardware_addr)
+                {
+                    if let Some(cidr) = acd.process_arp(
+                        source_hardware_addr,
+                        source_protocol_addr,
+                        target_protocol_addr,
+                        timestamp,
+                    ) {
+                        self.ip_addrs.retain(|probe| *probe != IpCidr::Ipv4(cidr));
+                    }
+
+                    // Answer the probes of other nodes for our addresses, so that they
+                    // detect the conflict.
+                    if operation == ArpOperation::Request
+                        && source_protocol_addr.is_unspecified()
+                        && source_hardware_addr.is_unicast()
+                        && self.has_ip_addr(target_protocol_addr)
+                    {
+                        return Some(EthernetPacket::Arp(ArpRepr::EthernetIpv4 {
+                            operation: ArpOperation::Reply,
+                            source_hardware_addr: own_hardware_addr,
+                            source_protocol_addr: target_protocol_addr,
+                            target_hardware_addr: source_hardware_addr,
+                            target_protocol_addr: source_protocol_addr,
+                        }));
+                    }
+                }
+
                 // Only process ARP packets for us.
                 if !self.has_ip_addr(target_protocol_addr) {
+                    net_trace!("arp: ignoring packet for {}", target_protocol_addr);
                     return None;
                 }
 
@@ -250,14 +360,9 @@ impl<'a> InterfaceInner<'a> {
                 );
 
                 if operation == ArpOperation::Request {
-                    let src_hardware_addr = match self.hardware_addr {
-                        Some(HardwareAddress::Ethernet(addr)) => addr,
-                        _ => unreachable!(),
-                    };
-
                     Some(EthernetPacket::Arp(ArpRepr::EthernetIpv4 {
                         operation: ArpOperation::Reply,
-                        source_hardware_addr: src_hardware_addr,
+                        source_hardware_addr: own_hardware_addr,
                         source_protocol_addr: target_protocol_addr,
                         target_hardware_addr: source_hardware_addr,
                         target_protocol_addr: source_protocol_addr,
@@ -269,6 +374,89 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
+    /// Update the addresses of the interface with `f`, holding back the IPv4 addresses
+    /// it adds until probing finds no conflict, if address conflict detection is enabled.
+    #[cfg(feature = "medium-ethernet")]
+    pub(super) fn acd_update_ip_addrs<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Vec<IpCidr, MAX_IP_ADDR_COUNT>),
+    {
+        let acd = match self.acd.as_mut() {
+            Some(acd) => acd,
+            None => return f(&mut self.ip_addrs),
+        };
+
+        // Let `f` see the addresses still being probed, since it added them.
+        for cidr in acd.probed_addrs() {
+            if self.ip_addrs.push(IpCidr::Ipv4(cidr)).is_err() {
+                net_debug!("acd: no room for address {}", cidr);
+            }
+        }
+        let old_addrs = self.ip_addrs.clone();
+        f(&mut self.ip_addrs);
+
+        let ipv4_cidr = |cidr: &IpCidr| match *cidr {
+            IpCidr::Ipv4(cidr) => Some(cidr),
+            #[cfg(feature = "proto-ipv6")]
+            IpCidr::Ipv6(_) => None,
+        };
+        for cidr in old_addrs
+            .iter()
+            .filter(|cidr| !self.ip_addrs.contains(cidr))
+        {
+            if let Some(cidr) = ipv4_cidr(cidr) {
+                acd.remove(cidr);
+            }
+        }
+        for cidr in self
+            .ip_addrs
+            .iter()
+            .filter(|cidr| !old_addrs.contains(cidr))
+        {
+            if let Some(cidr) = ipv4_cidr(cidr).filter(|cidr| !cidr.address().is_unspecified()) {
+                if !acd.probe(cidr) {
+                    net_debug!("acd: no room for address {}, assigning it", cidr);
+                }
+            }
+        }
+        self.ip_addrs.retain(|cidr| match ipv4_cidr(cidr) {
+            Some(cidr) => !acd.probed_addrs().any(|probe| probe == cidr),
+            None => true,
+        });
+    }
+
+    /// Advance address conflict detection, and return the next ARP packet it has to send.
+    #[cfg(feature = "medium-ethernet")]
+    pub(super) fn acd_poll(&mut self) -> Option<ArpRepr> {
+        let source_hardware_addr = match self.hardware_addr {
+            Some(HardwareAddress::Ethernet(addr)) => addr,
+            _ => return None,
+        };
+        loop {
+            let (source_protocol_addr, target_protocol_addr) =
+                match self.acd.as_mut()?.poll(self.now, &mut self.rand)? {
+                    AcdAction::Probe(addr) => (Ipv4Address::UNSPECIFIED, addr),
+                    AcdAction::Announce(addr) => (addr, addr),
+                    AcdAction::Assign(cidr) => {
+                        net_debug!("acd: assigned address {}", cidr);
+                        if self.ip_addrs.push(IpCidr::Ipv4(cidr)).is_err() {
+                            net_debug!("acd: no room for address {}", cidr);
+                        }
+                        continue;
+                    }
+                };
+
+            // RFC 5227 § 2.1.1 and § 2.3: probes and announcements are broadcast requests.
+            return Some(ArpRepr::EthernetIpv4 {
+                operation: ArpOperation::Request,
+                source_hardware_addr,
+                source_protocol_addr,
+                target_hardware_addr: EthernetAddress::default(),
+                target_protocol_addr,
+            });
+        }
+    }
+
     /// Host duties of the **IGMPv2** protocol.
     ///
     /// Sets up `igmp_report_state` for responding to IGMP general/specific membership queries.
@@ -280,8 +468,8 @@ impl<'a> InterfaceInner<'a> {
         ipv4_repr: Ipv4Repr,
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
-        let igmp_packet = check!(IgmpPacket::new_checked(ip_payload));
-        let igmp_repr = check!(IgmpRepr::parse(&igmp_packet));
+        let igmp_packet = check!(self, IgmpPacket::new_checked(ip_payload));
+        let igmp_repr = check!(self, IgmpRepr::parse(&igmp_packet));
 
         // FIXME: report membership after a delay
         match igmp_repr {
@@ -341,8 +529,8 @@ impl<'a> InterfaceInner<'a> {
         ip_repr: IpRepr,
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
-        let icmp_packet = check!(Icmpv4Packet::new_checked(ip_payload));
-        let icmp_repr = check!(Icmpv4Repr::parse(&icmp_packet, &self.caps.checksum));
+        let icmp_packet = check!(self, Icmpv4Packet::new_checked(ip_payload));
+        let icmp_repr = check!(self, Icmpv4Repr::parse(&icmp_packet, &self.caps.checksum));
 
         #[cfg(feature = "socket-icmp")]
         let mut handled_by_icmp_socket = false;
@@ -365,7 +553,7 @@ impl<'a> InterfaceInner<'a> {
                 ident,
                 seq_no,
                 data,
-            } => {
+            } if self.should_reply_to_echo(data.len()) => {
                 let icmp_reply_repr = Icmpv4Repr::EchoReply {
                     ident,
                     seq_no,
@@ -392,14 +580,22 @@ impl<'a> InterfaceInner<'a> {
     }
 
     pub(super) fn icmpv4_reply<'frame, 'icmp: 'frame>(
-        &self,
+        &mut self,
         ipv4_repr: Ipv4Repr,
         icmp_repr: Icmpv4Repr<'icmp>,
     ) -> Option<IpPacket<'frame>> {
+        let is_error = matches!(
+            icmp_repr,
+            Icmpv4Repr::DstUnreachable { .. } | Icmpv4Repr::TimeExceeded { .. }
+        );
         if !self.is_unicast_v4(ipv4_repr.src_addr) {
             // Do not send ICMP replies to non-unicast sources
             None
         } else if self.is_unicast_v4(ipv4_repr.dst_addr) {
+            if is_error && self.icmp_error_rate_exceeded() {
+                net_debug!("iface: ICMP error rate limit exceeded");
+                return None;
+            }
             // Reply as normal when src_addr and dst_addr are both unicast
             let ipv4_reply_repr = Ipv4Repr {
                 src_addr: ipv4_repr.dst_addr,
@@ -407,6 +603,7 @@ impl<'a> InterfaceInner<'a> {
                 next_header: IpProtocol::Icmp,
                 payload_len: icmp_repr.buffer_len(),
                 hop_limit: 64,
+                traffic_class: 0,
             };
             Some(IpPacket::Icmpv4((ipv4_reply_repr, icmp_repr)))
         } else if self.is_broadcast_v4(ipv4_repr.dst_addr) {
@@ -420,6 +617,7 @@ impl<'a> InterfaceInner<'a> {
                             next_header: IpProtocol::Icmp,
                             payload_len: icmp_repr.buffer_len(),
                             hop_limit: 64,
+                            traffic_class: 0,
                         };
                         Some(IpPacket::Icmpv4((ipv4_reply_repr, icmp_repr)))
                     }
@@ -464,6 +662,10 @@ impl<'a> InterfaceInner<'a> {
         if matches!(caps.medium, Medium::Ethernet) {
             tx_len += EthernetFrame::<&[u8]>::header_len();
         }
+        #[cfg(feature = "medium-ppp")]
+        if matches!(caps.medium, Medium::Ppp) {
+            tx_len += PPP_HEADER_LEN;
+        }
 
         // Emit function for the Ethernet header.
         #[cfg(feature = "medium-ethernet")]
@@ -473,7 +675,7 @@ impl<'a> InterfaceInner<'a> {
             let src_addr = if let Some(HardwareAddress::Ethernet(addr)) = self.hardware_addr {
                 addr
             } else {
-                return Err(Error::Malformed);
+                return Err(Error::Malformed(Layer::Link));
             };
 
             frame.set_src_addr(src_addr);
@@ -489,6 +691,8 @@ impl<'a> InterfaceInner<'a> {
             Ok(())
         };
 
+        #[cfg(feature = "stats")]
+        self.stats.record_tx(tx_len);
         tx_token.consume(self.now, tx_len, |mut tx_buffer| {
             #[cfg(feature = "medium-ethernet")]
             if matches!(self.caps.medium, Medium::Ethernet) {
@@ -496,6 +700,12 @@ impl<'a> InterfaceInner<'a> {
                 tx_buffer = &mut tx_buffer[EthernetFrame::<&[u8]>::header_len()..];
             }
 
+            #[cfg(feature = "medium-ppp")]
+            if matches!(self.caps.medium, Medium::Ppp) {
+                PppFrame::new_unchecked(&mut *tx_buffer).set_protocol(PppProtocol::Ipv4);
+                tx_buffer = &mut tx_buffer[PPP_HEADER_LEN..];
+            }
+
             let mut packet = Ipv4Packet::new_unchecked(&mut tx_buffer[..repr.buffer_len()]);
             repr.emit(&mut packet, &caps.checksum);
             packet.set_ident(*ident);
@@ -538,6 +748,7 @@ impl<'a> InterfaceInner<'a> {
                 payload_len: igmp_repr.buffer_len(),
                 hop_limit: 1,
                 // [#183](https://github.com/m-labs/smoltcp/issues/183).
+                traffic_class: 0,
             },
             igmp_repr,
         ));
@@ -558,9 +769,34 @@ impl<'a> InterfaceInner<'a> {
                     next_header: IpProtocol::Igmp,
                     payload_len: igmp_repr.buffer_len(),
                     hop_limit: 1,
+                    traffic_class: 0,
                 },
                 igmp_repr,
             ))
         })
     }
 }
+
+/// Check whether IPv4 options contain a loose or strict source route.
+fn has_source_route(mut options: &[u8]) -> bool {
+    const END_OF_LIST: u8 = 0;
+    const NO_OPERATION: u8 = 1;
+    const LOOSE_SOURCE_ROUTE: u8 = 131;
+    const STRICT_SOURCE_ROUTE: u8 = 137;
+
+    while let Some(&kind) = options.first() {
+        match kind {
+            END_OF_LIST => break,
+            NO_OPERATION => options = &options[1..],
+            LOOSE_SOURCE_ROUTE | STRICT_SOURCE_ROUTE => return true,
+            _ => match options.get(1) {
+                Some(&len) if len >= 2 && len as usize <= options.len() => {
+                    options = &options[len as usize..]
+                }
+                // Malformed options can't be skipped reliably.
+                _ => return true,
+            },
+        }
+    }
+    false
+}
@@ -1,12 +1,20 @@
 use super::check;
 use super::icmp_reply_payload_len;
+use super::DropReason;
 use super::InterfaceInner;
 use super::IpPacket;
 use super::SocketSet;
+use super::SourceRoutePolicy;
 
+#[cfg(feature = "medium-ethernet")]
+use crate::iface::slaac::Action as SlaacAction;
+#[cfg(feature = "medium-ethernet")]
+use crate::iface::Route;
 #[cfg(feature = "socket-icmp")]
 use crate::socket::icmp;
 use crate::socket::AnySocket;
+#[cfg(feature = "medium-ethernet")]
+use crate::time::Duration;
 
 use crate::wire::*;
 
@@ -17,16 +25,22 @@ impl<'a> InterfaceInner<'a> {
         sockets: &mut SocketSet,
         ipv6_packet: &Ipv6Packet<&'frame T>,
     ) -> Option<IpPacket<'frame>> {
-        let ipv6_repr = check!(Ipv6Repr::parse(ipv6_packet));
+        let ipv6_repr = check!(self, Ipv6Repr::parse(ipv6_packet));
 
-        if !ipv6_repr.src_addr.is_unicast() {
-            // Discard packets with non-unicast source addresses.
-            net_debug!("non-unicast source address");
+        if self.is_martian_source(ipv6_repr.src_addr.into()) {
+            // Discard packets with source addresses that can not be valid.
+            net_debug!("martian source address");
+            self.drop_packet(DropReason::Martian);
             return None;
         }
 
         let ip_payload = ipv6_packet.payload();
 
+        #[cfg(feature = "forwarding")]
+        if !self.has_ip_addr(ipv6_repr.dst_addr) && self.is_forwarded(ipv6_repr.dst_addr.into()) {
+            return self.forward_ip(ipv6_repr.into(), ip_payload);
+        }
+
         #[cfg(feature = "socket-raw")]
         let handled_by_raw_socket = self.raw_socket_filter(sockets, &ipv6_repr.into(), ip_payload);
         #[cfg(not(feature = "socket-raw"))]
@@ -57,13 +71,16 @@ impl<'a> InterfaceInner<'a> {
 
             #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
             IpProtocol::Udp => {
-                let udp_packet = check!(UdpPacket::new_checked(ip_payload));
-                let udp_repr = check!(UdpRepr::parse(
-                    &udp_packet,
-                    &ipv6_repr.src_addr.into(),
-                    &ipv6_repr.dst_addr.into(),
-                    &self.checksum_caps(),
-                ));
+                let udp_packet = check!(self, UdpPacket::new_checked(ip_payload));
+                let udp_repr = check!(
+                    self,
+                    UdpRepr::parse(
+                        &udp_packet,
+                        &ipv6_repr.src_addr.into(),
+                        &ipv6_repr.dst_addr.into(),
+                        &self.checksum_caps(),
+                    )
+                );
 
                 self.process_udp(
                     sockets,
@@ -82,10 +99,16 @@ impl<'a> InterfaceInner<'a> {
                 self.process_hopbyhop(sockets, ipv6_repr, handled_by_raw_socket, ip_payload)
             }
 
+            IpProtocol::Ipv6Route => {
+                self.process_routing(sockets, ipv6_repr, handled_by_raw_socket, ip_payload)
+            }
+
             #[cfg(feature = "socket-raw")]
             _ if handled_by_raw_socket => None,
 
             _ => {
+                self.drop_packet(DropReason::Unsupported);
+
                 // Send back as much of the original payload as we can.
                 let payload_len =
                     icmp_reply_payload_len(ip_payload.len(), IPV6_MIN_MTU, ipv6_repr.buffer_len());
@@ -108,13 +131,16 @@ impl<'a> InterfaceInner<'a> {
         ip_repr: IpRepr,
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
-        let icmp_packet = check!(Icmpv6Packet::new_checked(ip_payload));
-        let icmp_repr = check!(Icmpv6Repr::parse(
-            &ip_repr.src_addr(),
-            &ip_repr.dst_addr(),
-            &icmp_packet,
-            &self.caps.checksum,
-        ));
+        let icmp_packet = check!(self, Icmpv6Packet::new_checked(ip_payload));
+        let icmp_repr = check!(
+            self,
+            Icmpv6Repr::parse(
+                &ip_repr.src_addr(),
+                &ip_repr.dst_addr(),
+                &icmp_packet,
+                &self.caps.checksum,
+            )
+        );
 
         #[cfg(feature = "socket-icmp")]
         let mut handled_by_icmp_socket = false;
@@ -136,7 +162,7 @@ impl<'a> InterfaceInner<'a> {
                 ident,
                 seq_no,
                 data,
-            } => match ip_repr {
+            } if self.should_reply_to_echo(data.len()) => match ip_repr {
                 IpRepr::Ipv6(ipv6_repr) => {
                     let icmp_reply_repr = Icmpv6Repr::EchoReply {
                         ident,
@@ -180,14 +206,28 @@ impl<'a> InterfaceInner<'a> {
         repr: NdiscRepr<'frame>,
     ) -> Option<IpPacket<'frame>> {
         match repr {
+            #[cfg(feature = "medium-ethernet")]
+            NdiscRepr::RouterAdvert {
+                router_lifetime,
+                prefix_info,
+                ..
+            } if self.slaac.is_some() => {
+                self.process_router_advert(ip_repr, router_lifetime, prefix_info);
+                None
+            }
             NdiscRepr::NeighborAdvert {
                 lladdr,
                 target_addr,
                 flags,
             } => {
+                #[cfg(feature = "medium-ethernet")]
+                if let Some(slaac) = self.slaac.as_mut() {
+                    slaac.process_duplicate(target_addr);
+                }
+
                 let ip_addr = ip_repr.src_addr.into();
                 if let Some(lladdr) = lladdr {
-                    let lladdr = check!(lladdr.parse(self.caps.medium));
+                    let lladdr = check!(self, lladdr.parse(self.caps.medium));
                     if !lladdr.is_unicast() || !target_addr.is_unicast() {
                         return None;
                     }
@@ -212,8 +252,16 @@ impl<'a> InterfaceInner<'a> {
                 lladdr,
                 ..
             } => {
+                // Another node is performing duplicate address detection for the address.
+                #[cfg(feature = "medium-ethernet")]
+                if let Some(slaac) = self.slaac.as_mut() {
+                    if ip_repr.src_addr.is_unspecified() {
+                        slaac.process_duplicate(target_addr);
+                    }
+                }
+
                 if let Some(lladdr) = lladdr {
-                    let lladdr = check!(lladdr.parse(self.caps.medium));
+                    let lladdr = check!(self, lladdr.parse(self.caps.medium));
                     if !lladdr.is_unicast() || !target_addr.is_unicast() {
                         return None;
                     }
@@ -237,6 +285,7 @@ impl<'a> InterfaceInner<'a> {
                         next_header: IpProtocol::Icmpv6,
                         hop_limit: 0xff,
                         payload_len: advert.buffer_len(),
+                        traffic_class: 0,
                     };
                     Some(IpPacket::Icmpv6((ip_repr, advert)))
                 } else {
@@ -247,6 +296,101 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
+    /// Configure addresses and the default route from a router advertisement.
+    #[cfg(feature = "medium-ethernet")]
+    fn process_router_advert(
+        &mut self,
+        ip_repr: Ipv6Repr,
+        router_lifetime: Duration,
+        prefix_info: Option<NdiscPrefixInformation>,
+    ) {
+        // RFC 4861 § 6.1.2: routers advertise from their link-local address.
+        if !ip_repr.src_addr.is_link_local() {
+            return;
+        }
+
+        let router = ip_repr.src_addr;
+        if router_lifetime > Duration::ZERO {
+            let mut route = Route::new_ipv6_gateway(router);
+            route.expires_at = Some(self.now + router_lifetime);
+            self.routes.remove_default_ipv6_route();
+            self.routes.update(|storage| {
+                if storage.push(route).is_err() {
+                    net_debug!("slaac: no room for default route via {}", router);
+                }
+            });
+        } else {
+            // The router is no longer a default router.
+            self.routes.update(|storage| {
+                storage.retain(|route| {
+                    route.cidr.prefix_len() != 0 || route.via_router != IpAddress::Ipv6(router)
+                })
+            });
+        }
+
+        let iid = match self.hardware_addr {
+            Some(HardwareAddress::Ethernet(addr)) => addr.as_eui_64(),
+            _ => return,
+        };
+        if let (Some(info), Some(slaac)) = (prefix_info, self.slaac.as_mut()) {
+            slaac.process_prefix(&info, iid, self.now);
+        }
+    }
+
+    /// Advance address autoconfiguration, and return the next packet it has to send.
+    #[cfg(feature = "medium-ethernet")]
+    pub(super) fn slaac_poll(&mut self) -> Option<IpPacket<'static>> {
+        loop {
+            let (src_addr, dst_addr, repr) = match self.slaac.as_mut()?.poll(self.now)? {
+                SlaacAction::SolicitRouter => {
+                    let src_addr = self.ip_addrs.iter().find_map(|cidr| match cidr {
+                        IpCidr::Ipv6(cidr) if cidr.address().is_link_local() => {
+                            Some(cidr.address())
+                        }
+                        _ => None,
+                    });
+                    // RFC 4861 § 4.1: solicitations from the unspecified address must not
+                    // include the link-layer address.
+                    let repr = NdiscRepr::RouterSolicit {
+                        lladdr: src_addr.and(self.hardware_addr).map(|addr| addr.into()),
+                    };
+                    let src_addr = src_addr.unwrap_or(Ipv6Address::UNSPECIFIED);
+                    (src_addr, Ipv6Address::LINK_LOCAL_ALL_ROUTERS, repr)
+                }
+                SlaacAction::Probe(addr) => {
+                    let repr = NdiscRepr::NeighborSolicit {
+                        target_addr: addr,
+                        lladdr: None,
+                    };
+                    (Ipv6Address::UNSPECIFIED, addr.solicited_node(), repr)
+                }
+                SlaacAction::Assign(cidr) => {
+                    net_debug!("slaac: assigned address {}", cidr);
+                    if self.ip_addrs.push(IpCidr::Ipv6(cidr)).is_err() {
+                        net_debug!("slaac: no room for address {}", cidr);
+                    }
+                    continue;
+                }
+                SlaacAction::Remove(cidr) => {
+                    net_debug!("slaac: address {} expired", cidr);
+                    self.ip_addrs.retain(|probe| *probe != IpCidr::Ipv6(cidr));
+                    continue;
+                }
+            };
+
+            let icmp_repr = Icmpv6Repr::Ndisc(repr);
+            let ip_repr = Ipv6Repr {
+                src_addr,
+                dst_addr,
+                next_header: IpProtocol::Icmpv6,
+                payload_len: icmp_repr.buffer_len(),
+                hop_limit: 0xff,
+                traffic_class: 0,
+            };
+            return Some(IpPacket::Icmpv6((ip_repr, icmp_repr)));
+        }
+    }
+
     #[cfg(feature = "proto-ipv6")]
     pub(super) fn process_hopbyhop<'frame>(
         &mut self,
@@ -255,21 +399,23 @@ impl<'a> InterfaceInner<'a> {
         handled_by_raw_socket: bool,
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
-        let hbh_pkt = check!(Ipv6HopByHopHeader::new_checked(ip_payload));
-        let hbh_repr = check!(Ipv6HopByHopRepr::parse(&hbh_pkt));
+        let hbh_pkt = check!(self, Ipv6HopByHopHeader::new_checked(ip_payload));
+        let hbh_repr = check!(self, Ipv6HopByHopRepr::parse(&hbh_pkt));
         for opt_repr in hbh_repr.options() {
-            let opt_repr = check!(opt_repr);
+            let opt_repr = check!(self, opt_repr);
             match opt_repr {
                 Ipv6OptionRepr::Pad1 | Ipv6OptionRepr::PadN(_) => (),
                 Ipv6OptionRepr::Unknown { type_, .. } => {
                     match Ipv6OptionFailureType::from(type_) {
                         Ipv6OptionFailureType::Skip => (),
                         Ipv6OptionFailureType::Discard => {
+                            self.drop_packet(DropReason::Unsupported);
                             return None;
                         }
                         _ => {
                             // FIXME(dlrobertson): Send an ICMPv6 parameter problem message
                             // here.
+                            self.drop_packet(DropReason::Unsupported);
                             return None;
                         }
                     }
@@ -285,19 +431,77 @@ impl<'a> InterfaceInner<'a> {
         )
     }
 
+    #[cfg(feature = "proto-ipv6")]
+    pub(super) fn process_routing<'frame>(
+        &mut self,
+        sockets: &mut SocketSet,
+        ipv6_repr: Ipv6Repr,
+        handled_by_raw_socket: bool,
+        ip_payload: &'frame [u8],
+    ) -> Option<IpPacket<'frame>> {
+        let routing_header = check!(self, Ipv6RoutingHeader::new_checked(ip_payload));
+
+        let source_routed = routing_header.routing_type() == Ipv6RoutingType::Type0;
+        if source_routed && self.source_route_policy != SourceRoutePolicy::Accept {
+            net_debug!("type 0 routing header");
+            self.drop_packet(DropReason::Filtered);
+            if self.source_route_policy == SourceRoutePolicy::Drop {
+                return None;
+            }
+        } else if routing_header.segments_left() != 0 {
+            // We do not forward packets, so we can't visit the remaining segments.
+            net_debug!("routing header with segments left");
+            self.drop_packet(DropReason::Unsupported);
+        } else {
+            // The routing header is ignored once the packet reached its final destination.
+            let header_len = 8 + routing_header.header_len() as usize * 8;
+            return self.process_nxt_hdr(
+                sockets,
+                ipv6_repr,
+                routing_header.next_header(),
+                handled_by_raw_socket,
+                &ip_payload[header_len..],
+            );
+        }
+
+        // Send back as much of the original payload as we can.
+        let payload_len =
+            icmp_reply_payload_len(ip_payload.len(), IPV6_MIN_MTU, ipv6_repr.buffer_len());
+        let icmp_reply_repr = Icmpv6Repr::ParamProblem {
+            reason: Icmpv6ParamProblem::ErroneousHdrField,
+            // Point at the routing type field of the routing header.
+            pointer: ipv6_repr.buffer_len() as u32 + 2,
+            header: ipv6_repr,
+            data: &ip_payload[0..payload_len],
+        };
+        self.icmpv6_reply(ipv6_repr, icmp_reply_repr)
+    }
+
     #[cfg(feature = "proto-ipv6")]
     pub(super) fn icmpv6_reply<'frame, 'icmp: 'frame>(
-        &self,
+        &mut self,
         ipv6_repr: Ipv6Repr,
         icmp_repr: Icmpv6Repr<'icmp>,
     ) -> Option<IpPacket<'frame>> {
+        let is_error = matches!(
+            icmp_repr,
+            Icmpv6Repr::DstUnreachable { .. }
+                | Icmpv6Repr::PktTooBig { .. }
+                | Icmpv6Repr::TimeExceeded { .. }
+                | Icmpv6Repr::ParamProblem { .. }
+        );
         if ipv6_repr.dst_addr.is_unicast() {
+            if is_error && self.icmp_error_rate_exceeded() {
+                net_debug!("iface: ICMP error rate limit exceeded");
+                return None;
+            }
             let ipv6_reply_repr = Ipv6Repr {
                 src_addr: ipv6_repr.dst_addr,
                 dst_addr: ipv6_repr.src_addr,
                 next_header: IpProtocol::Icmpv6,
                 payload_len: icmp_repr.buffer_len(),
                 hop_limit: 64,
+                traffic_class: 0,
             };
             Some(IpPacket::Icmpv6((ipv6_reply_repr, icmp_repr)))
         } else {
@@ -0,0 +1,61 @@
+use super::check;
+use super::EthernetPacket;
+use super::InterfaceInner;
+
+use crate::phy::TxToken;
+use crate::wire::*;
+use crate::{Error, Layer, Result};
+
+impl<'i> InterfaceInner<'i> {
+    pub(super) fn process_lldp<'frame, T: AsRef<[u8]>>(
+        &mut self,
+        eth_frame: &EthernetFrame<&'frame T>,
+    ) -> Option<EthernetPacket<'frame>> {
+        let lldp_repr = check!(self, LldpRepr::parse(eth_frame.payload()));
+        net_trace!("lldp: received {}", lldp_repr);
+        self.lldp.as_mut()?.process(self.now, &lldp_repr);
+        None
+    }
+
+    /// Forget the LLDP neighbors whose advertisement expired, and return whether
+    /// an advertisement is due.
+    pub(super) fn lldp_poll(&mut self) -> bool {
+        let now = self.now;
+        self.lldp.as_mut().map_or(false, |lldp| lldp.poll(now))
+    }
+
+    pub(super) fn dispatch_lldp<Tx: TxToken>(&mut self, tx_token: Tx) -> Result<()> {
+        let lldp = match self.lldp.as_ref() {
+            Some(lldp) => lldp,
+            None => return Ok(()),
+        };
+        let chassis_id = match self.hardware_addr {
+            Some(HardwareAddress::Ethernet(addr)) => addr,
+            _ => return Err(Error::Malformed(Layer::Link)),
+        };
+        let config = *lldp.config();
+        let lldp_repr = LldpRepr {
+            chassis_id_subtype: LldpChassisIdSubtype::MacAddress,
+            chassis_id: chassis_id.as_bytes(),
+            port_id_subtype: LldpPortIdSubtype::InterfaceName,
+            port_id: config.port_id.as_bytes(),
+            ttl: lldp.ttl(),
+            port_description: config.port_description,
+            system_name: config.system_name,
+            system_description: config.system_description,
+        };
+
+        net_trace!("lldp: sending {}", lldp_repr);
+        self.dispatch_ethernet(tx_token, lldp_repr.buffer_len(), |mut frame| {
+            frame.set_dst_addr(LLDP_MULTICAST_ADDR);
+            frame.set_ethertype(EthernetProtocol::Lldp);
+            lldp_repr.emit(frame.payload_mut());
+        })?;
+
+        let now = self.now;
+        if let Some(lldp) = self.lldp.as_mut() {
+            lldp.sent(now);
+        }
+        Ok(())
+    }
+}
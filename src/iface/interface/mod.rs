@@ -7,6 +7,8 @@ mod tests;
 
 #[cfg(feature = "medium-ethernet")]
 mod ethernet;
+#[cfg(feature = "forwarding")]
+mod forwarding;
 #[cfg(feature = "proto-sixlowpan")]
 mod sixlowpan;
 
@@ -14,30 +16,75 @@ mod sixlowpan;
 mod ipv4;
 #[cfg(feature = "proto-ipv6")]
 mod ipv6;
+#[cfg(feature = "proto-lldp")]
+mod lldp;
+#[cfg(feature = "medium-ppp")]
+mod ppp;
 
 use core::cmp;
 use core::marker::PhantomData;
+#[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+use core::ops::RangeInclusive;
 use heapless::{LinearMap, Vec};
 use managed::ManagedSlice;
 
+#[cfg(feature = "forwarding")]
+use self::forwarding::Forwarding;
+#[cfg(feature = "forwarding")]
+pub use self::forwarding::{forward, ForwardingBuffer};
+#[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+use super::acd::Acd;
 #[cfg(any(feature = "proto-ipv4", feature = "proto-sixlowpan"))]
 use super::fragmentation::PacketAssemblerSet;
+#[cfg(feature = "proto-lldp")]
+use super::lldp::Lldp;
+#[cfg(feature = "medium-ppp")]
+use super::ppp::Ppp;
+#[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+use super::slaac::Slaac;
 use super::socket_set::SocketSet;
+#[cfg(feature = "stats")]
+use super::stats::Stats;
+use super::stats::{DropReason, DropStats};
+#[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+use crate::iface::AcdConflict;
 use crate::iface::Routes;
+#[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+use crate::iface::SlaacAddress;
+#[cfg(feature = "proto-lldp")]
+use crate::iface::{LldpConfig, LldpNeighbor};
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 use crate::iface::{NeighborAnswer, NeighborCache};
-use crate::phy::{ChecksumCapabilities, Device, DeviceCapabilities, Medium, RxToken, TxToken};
+#[cfg(feature = "medium-ppp")]
+use crate::iface::{PppIpv4Config, PppLinkState};
+use crate::phy::{
+    ChecksumCapabilities, Device, DeviceCapabilities, Medium, Priority, RxToken, TxToken,
+};
 use crate::rand::Rand;
 #[cfg(feature = "socket-dns")]
 use crate::socket::dns;
+#[cfg(feature = "socket-mdns")]
+use crate::socket::mdns;
+#[cfg(feature = "socket-sntp")]
+use crate::socket::sntp;
 use crate::socket::*;
-use crate::time::{Duration, Instant};
+#[cfg(any(
+    feature = "medium-ethernet",
+    feature = "medium-ieee802154",
+    feature = "proto-ipv4-fragmentation",
+    feature = "proto-sixlowpan-fragmentation"
+))]
+use crate::storage::MemoryUsage;
+use crate::time::{Duration, Instant, RateLimiter};
 use crate::wire::*;
-use crate::{Error, Result};
+use crate::{Error, Layer, Result};
 
 const MAX_IP_ADDR_COUNT: usize = 5;
 #[cfg(feature = "proto-igmp")]
 const MAX_IPV4_MULTICAST_GROUPS: usize = 4;
+/// The default range of ephemeral ports, i.e. the dynamic ports of RFC 6335.
+#[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+const EPHEMERAL_PORTS: RangeInclusive<u16> = 49152..=65535;
 
 pub(crate) struct FragmentsBuffer<'a> {
     #[cfg(feature = "proto-ipv4-fragmentation")]
@@ -118,6 +165,7 @@ impl<'a> Ipv4OutPacket<'a> {
                 next_header: IpProtocol::Unknown(0),
                 payload_len: 0,
                 hop_limit: 0,
+                traffic_class: 0,
             },
             #[cfg(feature = "medium-ethernet")]
             dst_hardware_addr: EthernetAddress::default(),
@@ -148,6 +196,7 @@ impl<'a> Ipv4OutPacket<'a> {
             next_header: IpProtocol::Unknown(0),
             payload_len: 0,
             hop_limit: 0,
+            traffic_class: 0,
         };
         #[cfg(feature = "medium-ethernet")]
         {
@@ -222,22 +271,111 @@ impl<'a> SixlowpanOutPacket<'a> {
 }
 
 macro_rules! check {
-    ($e:expr) => {
-        match $e {
+    (@drop $iface:expr, $e:expr, $result:expr) => {
+        match $result {
             Ok(x) => x,
-            Err(_) => {
+            Err(reason) => {
                 // concat!/stringify! doesn't work with defmt macros
                 #[cfg(not(feature = "defmt"))]
                 net_trace!(concat!("iface: malformed ", stringify!($e)));
                 #[cfg(feature = "defmt")]
                 net_trace!("iface: malformed");
+                $iface.drop_packet(reason);
                 return Default::default();
             }
         }
     };
+    ($iface:expr, $e:expr) => {
+        check!(@drop $iface, $e, $e.map_err(crate::iface::DropReason::from))
+    };
+    ($iface:expr, $e:expr, $reason:expr) => {
+        check!(@drop $iface, $e, $e.map_err(|_| $reason))
+    };
 }
 use check;
 
+/// What to do with sockets whose local address was removed from an interface.
+///
+/// See [Interface::rebind_sockets].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RebindPolicy {
+    /// Abort TCP connections, and close UDP sockets.
+    ///
+    /// Aborted TCP sockets report it through [is_address_lost].
+    ///
+    /// [is_address_lost]: ../socket/tcp/struct.Socket.html#method.is_address_lost
+    Abort,
+    /// Connect TCP sockets again to the same remote endpoint from a current address,
+    /// discarding any buffered data, and bind UDP sockets to any address.
+    ///
+    /// This is meant for idempotent protocols, which can simply repeat their requests.
+    /// Connections accepted by listening sockets cannot be connected again, and are
+    /// aborted instead.
+    Reconnect,
+}
+
+/// What to do with incoming packets carrying a source route.
+///
+/// Source routes, i.e. the IPv4 loose and strict source route options, and the IPv6 type 0
+/// routing header, let the sender choose the path of the replies, which allows spoofing
+/// the source address of a connection from anywhere on that path.
+///
+/// See [Interface::set_source_route_policy].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SourceRoutePolicy {
+    /// Silently drop the packets.
+    Drop,
+    /// Drop the packets, and answer them with an ICMP error: destination unreachable
+    /// (source route failed) for IPv4, and parameter problem for IPv6.
+    Reject,
+    /// Process the packets as if they carried no source route.
+    Accept,
+}
+
+/// What to do with connection attempts to a TCP endpoint whose listening sockets are all busy.
+///
+/// See [Interface::set_tcp_backlog_policy].
+#[cfg(feature = "socket-tcp")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BacklogPolicy {
+    /// Answer the SYN with a TCP RST, so that the peer gives up right away.
+    Reset,
+    /// Silently drop the SYN, so that the peer retransmits it, possibly once a socket
+    /// listens again.
+    Drop,
+}
+
+/// A limit on the rate of ICMP error messages sent by an interface.
+///
+/// This covers destination unreachable, time exceeded, packet too big and parameter problem
+/// messages, but not echo replies. Errors exceeding the limit are not sent.
+///
+/// See [Interface::set_icmp_error_rate_limit].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct IcmpRateLimit {
+    /// The maximum number of ICMP errors sent within each interval.
+    pub max_errors: u16,
+    /// The length of the interval.
+    pub interval: Duration,
+}
+
+/// How the packets sent by sockets with a given priority are marked.
+///
+/// See [Interface::set_priority_mapping].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PriorityMapping {
+    /// The Differentiated Services Code Point of the packets, unless they have one already.
+    pub dscp: u8,
+    /// The priority the device transmits the packets with, which is also the 802.1Q
+    /// priority code point of the frames tagged by [Vlan](crate::phy::Vlan).
+    pub pcp: Priority,
+}
+
 /// A  network interface.
 ///
 /// The network interface logically owns a number of other data structures; to avoid
@@ -273,6 +411,12 @@ pub struct InterfaceInner<'a> {
     pan_id: Option<Ieee802154Pan>,
     #[cfg(feature = "proto-ipv4-fragmentation")]
     ipv4_id: u16,
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    ipv4_reassembly_timeout: Duration,
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    ipv4_reassembly_max_packets: usize,
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    ipv4_reassembly_max_size: usize,
     #[cfg(feature = "proto-sixlowpan")]
     sixlowpan_address_context: &'a [SixlowpanAddressContext<'a>],
     #[cfg(feature = "proto-sixlowpan-fragmentation")]
@@ -286,6 +430,48 @@ pub struct InterfaceInner<'a> {
     /// When to report for (all or) the next multicast group membership via IGMP
     #[cfg(feature = "proto-igmp")]
     igmp_report_state: IgmpReportState,
+    drop_stats: DropStats,
+    #[cfg(feature = "stats")]
+    stats: Stats,
+    /// The reason the frame currently being processed was dropped, if it was.
+    last_drop: Option<DropReason>,
+    drop_hook: Option<fn(DropReason, &[u8])>,
+    #[cfg(feature = "socket-tcp")]
+    tcp_iss_hook: Option<fn(IpEndpoint, IpEndpoint, Instant) -> TcpSeqNumber>,
+    /// Whether ICMP echo requests are answered by the interface itself.
+    echo_reply: bool,
+    /// The largest amount of data in an echo request that is answered.
+    echo_reply_max_len: Option<usize>,
+    /// Whether frames addressed to other hosts are passed to Ethernet sockets.
+    #[cfg(feature = "socket-ethernet")]
+    promiscuous: bool,
+    source_route_policy: SourceRoutePolicy,
+    /// The marking of the packets of sockets with each priority, indexed by the priority.
+    priority_mappings: [Option<PriorityMapping>; 8],
+    #[cfg(feature = "socket-tcp")]
+    tcp_backlog_policy: BacklogPolicy,
+    #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+    ephemeral_ports: RangeInclusive<u16>,
+    /// The offset in `ephemeral_ports` of the next port to try.
+    #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+    next_ephemeral_port: u32,
+    icmp_error_rate_limit: Option<IcmpRateLimit>,
+    /// The ICMP errors sent within the current rate limiting interval.
+    icmp_error_limiter: RateLimiter,
+    #[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+    slaac: Option<Slaac>,
+    #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+    acd: Option<Acd>,
+    #[cfg(feature = "medium-ppp")]
+    ppp: Option<Ppp>,
+    #[cfg(feature = "forwarding")]
+    forwarding: Option<Forwarding<'a>>,
+    /// Whether packets to the broadcast address of a network of the interface are
+    /// forwarded to it.
+    #[cfg(feature = "forwarding")]
+    forward_directed_broadcasts: bool,
+    #[cfg(feature = "proto-lldp")]
+    lldp: Option<Lldp<'a>>,
 }
 
 /// A builder structure used for creating a network interface.
@@ -310,6 +496,12 @@ pub struct InterfaceBuilder<'a> {
     #[cfg(feature = "proto-ipv4-fragmentation")]
     ipv4_fragments: PacketAssemblerSet<'a, Ipv4FragKey>,
     #[cfg(feature = "proto-ipv4-fragmentation")]
+    ipv4_reassembly_buffer_timeout: Duration,
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    ipv4_reassembly_max_packets: usize,
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    ipv4_reassembly_max_packet_size: usize,
+    #[cfg(feature = "proto-ipv4-fragmentation")]
     ipv4_out_buffer: ManagedSlice<'a, u8>,
 
     #[cfg(feature = "proto-sixlowpan-fragmentation")]
@@ -321,6 +513,9 @@ pub struct InterfaceBuilder<'a> {
 
     #[cfg(feature = "proto-sixlowpan")]
     sixlowpan_address_context: &'a [SixlowpanAddressContext<'a>],
+
+    #[cfg(feature = "forwarding")]
+    forwarding: Option<Forwarding<'a>>,
 }
 
 impl<'a> InterfaceBuilder<'a> {
@@ -388,6 +583,12 @@ let iface = builder.finalize(&mut device);
             #[cfg(feature = "proto-ipv4-fragmentation")]
             ipv4_fragments: PacketAssemblerSet::new(&mut [][..], &mut [][..]),
             #[cfg(feature = "proto-ipv4-fragmentation")]
+            ipv4_reassembly_buffer_timeout: Duration::from_secs(90),
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            ipv4_reassembly_max_packets: u8::MAX as usize,
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            ipv4_reassembly_max_packet_size: u16::MAX as usize,
+            #[cfg(feature = "proto-ipv4-fragmentation")]
             ipv4_out_buffer: ManagedSlice::Borrowed(&mut [][..]),
 
             #[cfg(feature = "proto-sixlowpan-fragmentation")]
@@ -399,6 +600,9 @@ let iface = builder.finalize(&mut device);
 
             #[cfg(feature = "proto-sixlowpan")]
             sixlowpan_address_context: &[],
+
+            #[cfg(feature = "forwarding")]
+            forwarding: None,
         }
     }
 
@@ -517,6 +721,37 @@ let iface = builder.finalize(&mut device);
         self
     }
 
+    /// Set how long the fragments of an IPv4 packet are kept before it is dropped.
+    ///
+    /// When a packet whose first fragment was received expires, an ICMP time exceeded
+    /// (fragment reassembly time exceeded) message is sent to its source.
+    /// The default is 90 seconds.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    pub fn ipv4_reassembly_buffer_timeout(mut self, timeout: Duration) -> Self {
+        self.ipv4_reassembly_buffer_timeout = timeout;
+        self
+    }
+
+    /// Set the largest number of IPv4 packets reassembled at the same time.
+    ///
+    /// Fragments of further packets are dropped. The default, and largest supported value,
+    /// is 255.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    pub fn ipv4_reassembly_max_packets(mut self, count: usize) -> Self {
+        self.ipv4_reassembly_max_packets = count;
+        self
+    }
+
+    /// Set the largest size, in octets, of the payload of a reassembled IPv4 packet.
+    ///
+    /// A packet is dropped as soon as one of its fragments exceeds this size.
+    /// The default is 65535.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    pub fn ipv4_reassembly_max_packet_size(mut self, size: usize) -> Self {
+        self.ipv4_reassembly_max_packet_size = size;
+        self
+    }
+
     /// Set the IPv4 fragments buffer the interface will use.
     #[cfg(feature = "proto-ipv4-fragmentation")]
     pub fn ipv4_fragmentation_buffer<T>(mut self, storage: T) -> Self
@@ -537,6 +772,26 @@ let iface = builder.finalize(&mut device);
         self
     }
 
+    /// Set the buffers of the IP packets forwarded by the interface, which enables
+    /// forwarding.
+    ///
+    /// The packets received by the interface and addressed to another host are queued
+    /// in `rx` with their hop limit decremented, until [forward] hands them to the
+    /// interface with a route to their destination, which queues them in its `tx` buffer
+    /// until they are sent. Packets whose hop limit is exhausted are answered with an
+    /// ICMP time exceeded message instead.
+    ///
+    /// The options of IPv4 packets are not forwarded.
+    #[cfg(feature = "forwarding")]
+    pub fn forwarding_buffers(
+        mut self,
+        rx: ForwardingBuffer<'a>,
+        tx: ForwardingBuffer<'a>,
+    ) -> Self {
+        self.forwarding = Some(Forwarding { rx, tx });
+        self
+    }
+
     /// Set the 6LoWPAN reassembly buffer the interface will use.
     #[cfg(feature = "proto-sixlowpan-fragmentation")]
     pub fn sixlowpan_reassembly_buffer(
@@ -609,6 +864,18 @@ let iface = builder.finalize(&mut device);
                 );
                 (None, None)
             }
+            #[cfg(feature = "medium-ppp")]
+            Medium::Ppp => {
+                assert!(
+                    self.hardware_addr.is_none(),
+                    "hardware_addr is set, but device medium is PPP"
+                );
+                assert!(
+                    self.neighbor_cache.is_none(),
+                    "neighbor_cache is set, but device medium is PPP"
+                );
+                (None, None)
+            }
             #[cfg(feature = "medium-ieee802154")]
             Medium::Ieee802154 => (
                 Some(
@@ -656,6 +923,14 @@ let iface = builder.finalize(&mut device);
             }
         }
 
+        #[cfg(feature = "medium-ppp")]
+        let ppp = (caps.medium == Medium::Ppp).then(|| Ppp::new(rand.rand_u32()));
+
+        // Start at a random port, so that the ports used by the interface do not give
+        // away how long it has been up, nor collide with those used before a reboot.
+        #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+        let next_ephemeral_port = rand.rand_u32();
+
         Interface {
             fragments: FragmentsBuffer {
                 #[cfg(feature = "proto-ipv4-fragmentation")]
@@ -704,9 +979,48 @@ let iface = builder.finalize(&mut device);
                 tag,
                 #[cfg(feature = "proto-ipv4-fragmentation")]
                 ipv4_id,
+                #[cfg(feature = "proto-ipv4-fragmentation")]
+                ipv4_reassembly_timeout: self.ipv4_reassembly_buffer_timeout,
+                #[cfg(feature = "proto-ipv4-fragmentation")]
+                ipv4_reassembly_max_packets: self.ipv4_reassembly_max_packets,
+                #[cfg(feature = "proto-ipv4-fragmentation")]
+                ipv4_reassembly_max_size: self.ipv4_reassembly_max_packet_size,
                 #[cfg(feature = "proto-sixlowpan")]
                 sixlowpan_address_context: &[],
                 rand,
+                drop_stats: DropStats::default(),
+                #[cfg(feature = "stats")]
+                stats: Stats::default(),
+                last_drop: None,
+                drop_hook: None,
+                #[cfg(feature = "socket-tcp")]
+                tcp_iss_hook: None,
+                echo_reply: true,
+                echo_reply_max_len: None,
+                #[cfg(feature = "socket-ethernet")]
+                promiscuous: false,
+                source_route_policy: SourceRoutePolicy::Drop,
+                priority_mappings: [None; 8],
+                #[cfg(feature = "socket-tcp")]
+                tcp_backlog_policy: BacklogPolicy::Reset,
+                #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+                ephemeral_ports: EPHEMERAL_PORTS,
+                #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+                next_ephemeral_port,
+                icmp_error_rate_limit: None,
+                icmp_error_limiter: RateLimiter::new(),
+                #[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+                slaac: None,
+                #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+                acd: None,
+                #[cfg(feature = "medium-ppp")]
+                ppp,
+                #[cfg(feature = "forwarding")]
+                forwarding: self.forwarding,
+                #[cfg(feature = "forwarding")]
+                forward_directed_broadcasts: false,
+                #[cfg(feature = "proto-lldp")]
+                lldp: None,
             },
         }
     }
@@ -738,6 +1052,8 @@ pub(crate) enum IpPacket<'a> {
     Tcp((IpRepr, TcpRepr<'a>)),
     #[cfg(feature = "socket-dhcpv4")]
     Dhcpv4((Ipv4Repr, UdpRepr, DhcpRepr<'a>)),
+    #[cfg(feature = "forwarding")]
+    Forward((IpRepr, &'a [u8])),
 }
 
 impl<'a> IpPacket<'a> {
@@ -757,6 +1073,46 @@ impl<'a> IpPacket<'a> {
             IpPacket::Tcp((ip_repr, _)) => ip_repr.clone(),
             #[cfg(feature = "socket-dhcpv4")]
             IpPacket::Dhcpv4((ipv4_repr, _, _)) => IpRepr::Ipv4(*ipv4_repr),
+            #[cfg(feature = "forwarding")]
+            IpPacket::Forward((ip_repr, _)) => ip_repr.clone(),
+        }
+    }
+
+    /// Set the DSCP of the packet, unless it has one already, keeping its ECN bits.
+    pub(crate) fn set_default_dscp(&mut self, dscp: u8) {
+        let mark = |traffic_class: u8| match traffic_class >> 2 {
+            0 => (dscp << 2) | (traffic_class & 0b11),
+            _ => traffic_class,
+        };
+        match self {
+            #[cfg(feature = "proto-ipv4")]
+            IpPacket::Icmpv4((ipv4_repr, _)) => {
+                ipv4_repr.traffic_class = mark(ipv4_repr.traffic_class)
+            }
+            #[cfg(feature = "proto-igmp")]
+            IpPacket::Igmp((ipv4_repr, _)) => {
+                ipv4_repr.traffic_class = mark(ipv4_repr.traffic_class)
+            }
+            #[cfg(feature = "proto-ipv6")]
+            IpPacket::Icmpv6((ipv6_repr, _)) => {
+                ipv6_repr.traffic_class = mark(ipv6_repr.traffic_class)
+            }
+            #[cfg(feature = "socket-raw")]
+            IpPacket::Raw((ip_repr, _)) => ip_repr.set_traffic_class(mark(ip_repr.traffic_class())),
+            #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
+            IpPacket::Udp((ip_repr, _, _)) => {
+                ip_repr.set_traffic_class(mark(ip_repr.traffic_class()))
+            }
+            #[cfg(feature = "socket-tcp")]
+            IpPacket::Tcp((ip_repr, _)) => ip_repr.set_traffic_class(mark(ip_repr.traffic_class())),
+            #[cfg(feature = "socket-dhcpv4")]
+            IpPacket::Dhcpv4((ipv4_repr, _, _)) => {
+                ipv4_repr.traffic_class = mark(ipv4_repr.traffic_class)
+            }
+            #[cfg(feature = "forwarding")]
+            IpPacket::Forward((ip_repr, _)) => {
+                ip_repr.set_traffic_class(mark(ip_repr.traffic_class()))
+            }
         }
     }
 
@@ -829,6 +1185,8 @@ impl<'a> IpPacket<'a> {
                 |buf| dhcp_repr.emit(&mut DhcpPacket::new_unchecked(buf)).unwrap(),
                 &caps.checksum,
             ),
+            #[cfg(feature = "forwarding")]
+            IpPacket::Forward((_, forwarded_payload)) => payload.copy_from_slice(forwarded_payload),
         }
     }
 }
@@ -934,14 +1292,16 @@ impl<'a> Interface<'a> {
                     .inner
                     .ipv4_multicast_groups
                     .insert(addr, ())
-                    .map_err(|_| Error::Exhausted)?
+                    .map_err(|_| Error::Exhausted(Layer::Network))?
                     .is_some();
                 if is_not_new {
                     Ok(false)
                 } else if let Some(pkt) = self.inner.igmp_report_packet(IgmpVersion::Version2, addr)
                 {
                     // Send initial membership report
-                    let tx_token = device.transmit().ok_or(Error::Exhausted)?;
+                    let tx_token = device
+                        .transmit_with_priority(Priority::NETWORK_CONTROL)
+                        .ok_or(Error::Exhausted(Layer::Phy))?;
                     self.inner.dispatch_ip(tx_token, pkt, None)?;
                     Ok(true)
                 } else {
@@ -950,7 +1310,7 @@ impl<'a> Interface<'a> {
             }
             // Multicast is not yet implemented for other address families
             #[allow(unreachable_patterns)]
-            _ => Err(Error::Unaddressable),
+            _ => Err(Error::Unaddressable(Layer::Network)),
         }
     }
 
@@ -977,7 +1337,9 @@ impl<'a> Interface<'a> {
                     Ok(false)
                 } else if let Some(pkt) = self.inner.igmp_leave_packet(addr) {
                     // Send group leave packet
-                    let tx_token = device.transmit().ok_or(Error::Exhausted)?;
+                    let tx_token = device
+                        .transmit_with_priority(Priority::NETWORK_CONTROL)
+                        .ok_or(Error::Exhausted(Layer::Phy))?;
                     self.inner.dispatch_ip(tx_token, pkt, None)?;
                     Ok(true)
                 } else {
@@ -986,7 +1348,7 @@ impl<'a> Interface<'a> {
             }
             // Multicast is not yet implemented for other address families
             #[allow(unreachable_patterns)]
-            _ => Err(Error::Unaddressable),
+            _ => Err(Error::Unaddressable(Layer::Network)),
         }
     }
 
@@ -1014,9 +1376,15 @@ impl<'a> Interface<'a> {
 
     /// Update the IP addresses of the interface.
     ///
+    /// If address conflict detection is enabled, the IPv4 addresses added are only assigned
+    /// once probed, see [set_acd_enabled](#method.set_acd_enabled).
+    ///
     /// # Panics
     /// This function panics if any of the addresses are not unicast.
     pub fn update_ip_addrs<F: FnOnce(&mut Vec<IpCidr, MAX_IP_ADDR_COUNT>)>(&mut self, f: F) {
+        #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+        self.inner.acd_update_ip_addrs(f);
+        #[cfg(not(all(feature = "proto-ipv4", feature = "medium-ethernet")))]
         f(&mut self.inner.ip_addrs);
         InterfaceInner::flush_cache(&mut self.inner);
         InterfaceInner::check_ip_addrs(&self.inner.ip_addrs)
@@ -1027,6 +1395,36 @@ impl<'a> Interface<'a> {
         self.inner.has_ip_addr(addr)
     }
 
+    /// Handle the sockets whose local address is no longer assigned to the interface,
+    /// and return how many were affected.
+    ///
+    /// This should be called after [update_ip_addrs](#method.update_ip_addrs) removed or
+    /// replaced an address, e.g. when DHCP leased a different one, since connections using
+    /// the removed address would otherwise silently stall.
+    #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+    pub fn rebind_sockets(&mut self, sockets: &mut SocketSet<'_>, policy: RebindPolicy) -> usize {
+        let mut count = 0;
+        #[cfg(feature = "socket-tcp")]
+        for tcp_socket in sockets
+            .items_mut()
+            .filter_map(|i| tcp::Socket::downcast_mut(&mut i.socket))
+        {
+            if tcp_socket.rebind(&mut self.inner, policy) {
+                count += 1;
+            }
+        }
+        #[cfg(feature = "socket-udp")]
+        for udp_socket in sockets
+            .items_mut()
+            .filter_map(|i| udp::Socket::downcast_mut(&mut i.socket))
+        {
+            if udp_socket.rebind(&self.inner, policy) {
+                count += 1;
+            }
+        }
+        count
+    }
+
     /// Get the first IPv4 address of the interface.
     #[cfg(feature = "proto-ipv4")]
     pub fn ipv4_address(&self) -> Option<Ipv4Address> {
@@ -1041,12 +1439,459 @@ impl<'a> Interface<'a> {
         &mut self.inner.routes
     }
 
+    /// Get the counters of incoming packets dropped by the interface.
+    pub fn drop_stats(&self) -> &DropStats {
+        &self.inner.drop_stats
+    }
+
+    /// Reset the counters of incoming packets dropped by the interface.
+    pub fn reset_drop_stats(&mut self) {
+        self.inner.drop_stats = DropStats::default();
+    }
+
+    /// Get the counters of the traffic of the interface.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> &Stats {
+        &self.inner.stats
+    }
+
+    /// Reset the counters of the traffic of the interface.
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&mut self) {
+        self.inner.stats = Stats::default();
+    }
+
+    /// Return the number of bytes of neighbor cache storage used and committed by the interface.
+    ///
+    /// Returns `None` if the medium of the interface does not use a neighbor cache.
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    pub fn neighbor_cache_memory_usage(&self) -> Option<MemoryUsage> {
+        self.inner
+            .neighbor_cache
+            .as_ref()
+            .map(|cache| cache.memory_usage())
+    }
+
+    /// Return the number of bytes of IPv4 reassembly buffer storage used and committed
+    /// by the interface.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    pub fn ipv4_reassembly_memory_usage(&self) -> MemoryUsage {
+        self.fragments.ipv4_fragments.memory_usage()
+    }
+
+    /// Return the number of bytes of 6LoWPAN reassembly buffer storage used and committed
+    /// by the interface.
+    #[cfg(feature = "proto-sixlowpan-fragmentation")]
+    pub fn sixlowpan_reassembly_memory_usage(&self) -> MemoryUsage {
+        self.fragments.sixlowpan_fragments.memory_usage()
+    }
+
+    /// Return whether the interface answers ICMP echo requests.
+    ///
+    /// See also [set_echo_reply](#method.set_echo_reply).
+    pub fn echo_reply(&self) -> bool {
+        self.inner.echo_reply
+    }
+
+    /// Enable or disable answering ICMP echo requests.
+    ///
+    /// When enabled, which is the default, the interface answers echo requests (pings)
+    /// addressed to it by itself, echoing their data, whether or not an ICMP socket is
+    /// bound to them. Disabling it hides the device from ping sweeps; echo requests are
+    /// still delivered to ICMP sockets.
+    pub fn set_echo_reply(&mut self, enabled: bool) {
+        self.inner.echo_reply = enabled
+    }
+
+    /// Return the largest amount of data in an echo request that is answered.
+    ///
+    /// See also [set_echo_reply_max_len](#method.set_echo_reply_max_len).
+    pub fn echo_reply_max_len(&self) -> Option<usize> {
+        self.inner.echo_reply_max_len
+    }
+
+    /// Set the largest amount of data in an echo request that is answered, in octets.
+    ///
+    /// Echo requests with more data are silently ignored, which bounds the amount of
+    /// traffic a single request can cause. By default, requests of any size are answered.
+    pub fn set_echo_reply_max_len(&mut self, max_len: Option<usize>) {
+        self.inner.echo_reply_max_len = max_len
+    }
+
+    /// Return whether directed broadcasts are forwarded to the interface.
+    ///
+    /// See also [set_forward_directed_broadcasts](#method.set_forward_directed_broadcasts).
+    #[cfg(feature = "forwarding")]
+    pub fn forward_directed_broadcasts(&self) -> bool {
+        self.inner.forward_directed_broadcasts
+    }
+
+    /// Enable or disable forwarding directed broadcasts to the interface.
+    ///
+    /// A directed broadcast is a packet from another network addressed to the broadcast
+    /// address of a network of this interface, which [forward] would send to every host
+    /// on it. When disabled, which is the default, such packets are dropped, so that the
+    /// network can not be used to amplify a flood of packets. Enabling it lets remote
+    /// hosts reach the whole network, for example to wake its hosts with Wake-on-LAN.
+    #[cfg(feature = "forwarding")]
+    pub fn set_forward_directed_broadcasts(&mut self, enabled: bool) {
+        self.inner.forward_directed_broadcasts = enabled
+    }
+
+    /// Return whether the interface is in promiscuous mode.
+    ///
+    /// See also [set_promiscuous](#method.set_promiscuous).
+    #[cfg(feature = "socket-ethernet")]
+    pub fn promiscuous(&self) -> bool {
+        self.inner.promiscuous
+    }
+
+    /// Enable or disable promiscuous mode.
+    ///
+    /// In promiscuous mode, the Ethernet frames addressed to other hosts are passed to
+    /// the [Ethernet sockets](../socket/ethernet/struct.Socket.html) instead of being
+    /// dropped, e.g. to implement link layer protocols such as PTP alongside the IP stack.
+    /// They are not processed any further by the interface. The device usually has to be
+    /// put into promiscuous mode as well to receive these frames at all.
+    ///
+    /// Promiscuous mode is disabled by default.
+    #[cfg(feature = "socket-ethernet")]
+    pub fn set_promiscuous(&mut self, enabled: bool) {
+        self.inner.promiscuous = enabled
+    }
+
+    /// Return what is done with incoming packets carrying a source route.
+    ///
+    /// See also [set_source_route_policy](#method.set_source_route_policy).
+    pub fn source_route_policy(&self) -> SourceRoutePolicy {
+        self.inner.source_route_policy
+    }
+
+    /// Set what is done with incoming packets carrying a source route.
+    ///
+    /// By default, they are silently dropped, and counted as
+    /// [DropReason::Filtered](enum.DropReason.html#variant.Filtered).
+    pub fn set_source_route_policy(&mut self, policy: SourceRoutePolicy) {
+        self.inner.source_route_policy = policy
+    }
+
+    /// Return how the packets of sockets with the given priority are marked, if set.
+    ///
+    /// See also [set_priority_mapping](#method.set_priority_mapping).
+    pub fn priority_mapping(&self, priority: Priority) -> Option<PriorityMapping> {
+        self.inner.priority_mapping(priority)
+    }
+
+    /// Set how the packets of sockets with the given priority are marked.
+    ///
+    /// This maps the priority set with
+    /// [SocketSet::set_priority](struct.SocketSet.html#method.set_priority) to a DSCP,
+    /// written into the packets sent with a DSCP of 0, and to the priority the device
+    /// transmits them with, so that the QoS policy is configured once for the interface
+    /// rather than for each socket. By default, no priority is mapped: packets keep their
+    /// DSCP, and are transmitted with the priority of their socket.
+    ///
+    /// # Panics
+    /// This function panics if the priority is greater than 7.
+    pub fn set_priority_mapping(&mut self, priority: Priority, mapping: Option<PriorityMapping>) {
+        assert!(priority.0 <= 7, "priority {} out of range", priority.0);
+        self.inner.priority_mappings[usize::from(priority.0)] = mapping
+    }
+
+    /// Return what is done with connection attempts to a TCP endpoint whose listening
+    /// sockets are all busy.
+    ///
+    /// See also [set_tcp_backlog_policy](#method.set_tcp_backlog_policy).
+    #[cfg(feature = "socket-tcp")]
+    pub fn tcp_backlog_policy(&self) -> BacklogPolicy {
+        self.inner.tcp_backlog_policy
+    }
+
+    /// Set what is done with connection attempts to a TCP endpoint whose listening
+    /// sockets are all busy, i.e. that was listened on by sockets that all have accepted
+    /// a connection since.
+    ///
+    /// By default, they are reset. Either way, they are counted as
+    /// [DropReason::BacklogFull](enum.DropReason.html#variant.BacklogFull), which tells
+    /// whether more sockets should listen on the endpoint.
+    #[cfg(feature = "socket-tcp")]
+    pub fn set_tcp_backlog_policy(&mut self, policy: BacklogPolicy) {
+        self.inner.tcp_backlog_policy = policy
+    }
+
+    /// Return the range of ports handed out by [ephemeral_port](#method.ephemeral_port).
+    #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+    pub fn ephemeral_port_range(&self) -> RangeInclusive<u16> {
+        self.inner.ephemeral_ports.clone()
+    }
+
+    /// Set the range of ports handed out by [ephemeral_port](#method.ephemeral_port).
+    ///
+    /// By default, it is the dynamic port range of RFC 6335, i.e. 49152 to 65535.
+    ///
+    /// # Panics
+    /// This function panics if the range is empty or contains port 0.
+    #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+    pub fn set_ephemeral_port_range(&mut self, range: RangeInclusive<u16>) {
+        if range.is_empty() || *range.start() == 0 {
+            panic!("the ephemeral port range must be non-empty and exclude port 0")
+        }
+        self.inner.ephemeral_ports = range
+    }
+
+    /// Allocate a local port for a socket that is not bound to a specific one, e.g. for
+    /// an outbound TCP connection.
+    ///
+    /// Ports are handed out in turn from the
+    /// [ephemeral port range](#method.set_ephemeral_port_range), skipping those any
+    /// TCP or UDP socket in `sockets` uses, including TCP sockets that are listening,
+    /// connected, or lingering in the TIME-WAIT state. The port is not reserved: it is
+    /// only known to be in use once a socket is bound to, or connected from, it.
+    ///
+    /// Returns `None` if every port in the range is in use.
+    #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+    pub fn ephemeral_port(&mut self, sockets: &SocketSet<'_>) -> Option<u16> {
+        let start = *self.inner.ephemeral_ports.start();
+        let count = u32::from(*self.inner.ephemeral_ports.end() - start) + 1;
+        for _ in 0..count {
+            let port = start + (self.inner.next_ephemeral_port % count) as u16;
+            self.inner.next_ephemeral_port = self.inner.next_ephemeral_port.wrapping_add(1);
+            let in_use = sockets.iter().any(|(_, socket)| match socket {
+                #[cfg(feature = "socket-tcp")]
+                Socket::Tcp(socket) => socket.local_port() == Some(port),
+                #[cfg(feature = "socket-udp")]
+                Socket::Udp(socket) => socket.endpoint().port == port,
+                #[allow(unreachable_patterns)]
+                _ => false,
+            });
+            if !in_use {
+                return Some(port);
+            }
+        }
+        net_debug!("no free ephemeral port");
+        None
+    }
+
+    /// Return the limit on the rate of ICMP error messages sent by the interface.
+    ///
+    /// See also [set_icmp_error_rate_limit](#method.set_icmp_error_rate_limit).
+    pub fn icmp_error_rate_limit(&self) -> Option<IcmpRateLimit> {
+        self.inner.icmp_error_rate_limit
+    }
+
+    /// Set a limit on the rate of ICMP error messages sent by the interface, such as
+    /// the port unreachable messages answering UDP datagrams no socket accepted.
+    ///
+    /// Without a limit, the interface can be used to flood a spoofed source address
+    /// with errors. By default, there is no limit.
+    pub fn set_icmp_error_rate_limit(&mut self, limit: Option<IcmpRateLimit>) {
+        self.inner.icmp_error_rate_limit = limit;
+        self.inner.icmp_error_limiter = RateLimiter::new();
+    }
+
+    /// Return whether IPv6 addresses are configured from router advertisements.
+    ///
+    /// See also [set_slaac_enabled](#method.set_slaac_enabled).
+    #[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+    pub fn slaac_enabled(&self) -> bool {
+        self.inner.slaac.is_some()
+    }
+
+    /// Enable or disable stateless address autoconfiguration (SLAAC, RFC 4862).
+    ///
+    /// When enabled, the interface solicits routers, and forms an address from the
+    /// prefixes they advertise for autoconfiguration and its EUI-64 interface identifier.
+    /// Each address is added to the interface once duplicate address detection succeeds,
+    /// and removed when its valid lifetime expires, after which
+    /// [rebind_sockets](#method.rebind_sockets) should be called. Routers advertising
+    /// a non-zero lifetime also become the default IPv6 route until that lifetime
+    /// expires, replacing any other one.
+    ///
+    /// Disabling it removes the configured addresses. It is disabled by default.
+    #[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+    pub fn set_slaac_enabled(&mut self, enabled: bool) {
+        if enabled {
+            if self.inner.slaac.is_none() {
+                self.inner.slaac = Some(Slaac::new());
+            }
+        } else if let Some(slaac) = self.inner.slaac.take() {
+            for addr in slaac.addrs() {
+                let cidr = IpCidr::Ipv6(addr.cidr);
+                self.inner.ip_addrs.retain(|probe| *probe != cidr);
+            }
+        }
+    }
+
+    /// Return the IPv6 addresses configured from router advertisements, including those
+    /// still undergoing duplicate address detection.
+    #[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+    pub fn slaac_addrs(&self) -> &[SlaacAddress] {
+        match self.inner.slaac {
+            Some(ref slaac) => slaac.addrs(),
+            None => &[],
+        }
+    }
+
+    /// Return whether IPv4 address conflict detection is enabled.
+    ///
+    /// See also [set_acd_enabled](#method.set_acd_enabled).
+    #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+    pub fn acd_enabled(&self) -> bool {
+        self.inner.acd.is_some()
+    }
+
+    /// Enable or disable IPv4 address conflict detection (ACD, RFC 5227).
+    ///
+    /// When enabled, the IPv4 addresses added with [update_ip_addrs](#method.update_ip_addrs),
+    /// be they static or leased over DHCP, are probed with ARP before being assigned, which
+    /// takes 4 to 7 seconds; until then they are left out of [ip_addrs](#method.ip_addrs).
+    /// Assigned addresses are announced with gratuitous ARP, and defended once when another
+    /// node claims them. If it claims them again within 10 seconds, they are removed, after
+    /// which [rebind_sockets](#method.rebind_sockets) should be called.
+    ///
+    /// The addresses lost to another node are reported by
+    /// [acd_conflict](#method.acd_conflict), so that the application can pick another one.
+    ///
+    /// The IPv4 addresses of the interface when it is enabled are announced and defended
+    /// without probing. Disabling it assigns the addresses still being probed right away.
+    /// It is disabled by default.
+    #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+    pub fn set_acd_enabled(&mut self, enabled: bool) {
+        if enabled {
+            if self.inner.acd.is_none() {
+                let mut acd = Acd::new();
+                for cidr in self.inner.ip_addrs.iter() {
+                    match *cidr {
+                        IpCidr::Ipv4(cidr) => {
+                            if !acd.announce(cidr) {
+                                net_debug!("acd: no room for address {}", cidr);
+                            }
+                        }
+                        #[cfg(feature = "proto-ipv6")]
+                        IpCidr::Ipv6(_) => (),
+                    }
+                }
+                self.inner.acd = Some(acd);
+            }
+        } else if let Some(acd) = self.inner.acd.take() {
+            for cidr in acd.probed_addrs() {
+                if self.inner.ip_addrs.push(IpCidr::Ipv4(cidr)).is_err() {
+                    net_debug!("acd: no room for address {}", cidr);
+                }
+            }
+        }
+    }
+
+    /// Dequeue the oldest IPv4 address conflict detected, if any.
+    ///
+    /// See also [set_acd_enabled](#method.set_acd_enabled).
+    #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+    pub fn acd_conflict(&mut self) -> Option<AcdConflict> {
+        self.inner.acd.as_mut().and_then(|acd| acd.conflict())
+    }
+
+    /// Return what the interface advertises over LLDP, or `None` if LLDP is disabled.
+    ///
+    /// See also [set_lldp_config](#method.set_lldp_config).
+    #[cfg(feature = "proto-lldp")]
+    pub fn lldp_config(&self) -> Option<&LldpConfig<'a>> {
+        self.inner.lldp.as_ref().map(|lldp| lldp.config())
+    }
+
+    /// Enable LLDP (IEEE 802.1AB) with the given configuration, or disable it.
+    ///
+    /// When enabled, the interface periodically advertises its hardware address as the
+    /// chassis ID, together with the information in `config`, and remembers the
+    /// neighbors advertising themselves, see [lldp_neighbors](#method.lldp_neighbors).
+    /// A new configuration is advertised right away. Disabling LLDP forgets the
+    /// neighbors; it is disabled by default.
+    ///
+    /// # Panics
+    /// This function panics if LLDP is enabled and the medium is not Ethernet.
+    #[cfg(feature = "proto-lldp")]
+    pub fn set_lldp_config(&mut self, config: Option<LldpConfig<'a>>) {
+        match (config, self.inner.lldp.as_mut()) {
+            (Some(config), Some(lldp)) => lldp.set_config(config),
+            (Some(config), None) => {
+                assert!(self.inner.caps.medium == Medium::Ethernet);
+                self.inner.lldp = Some(Lldp::new(config));
+            }
+            (None, _) => self.inner.lldp = None,
+        }
+    }
+
+    /// Return the neighbors whose LLDP advertisement was received, and has not expired.
+    #[cfg(feature = "proto-lldp")]
+    pub fn lldp_neighbors(&self) -> &[LldpNeighbor] {
+        match self.inner.lldp {
+            Some(ref lldp) => lldp.neighbors(),
+            None => &[],
+        }
+    }
+
+    /// Return the state of the PPP link, or `None` if the device medium is not PPP.
+    #[cfg(feature = "medium-ppp")]
+    pub fn ppp_link_state(&self) -> Option<PppLinkState> {
+        self.inner.ppp.as_ref().map(|ppp| ppp.link_state())
+    }
+
+    /// Return the IPv4 configuration negotiated with IPCP, once the link is opened.
+    ///
+    /// The address is also added to the interface, and a default IPv4 route over the
+    /// link installed, replacing any other one. Both are removed when the link goes down.
+    #[cfg(feature = "medium-ppp")]
+    pub fn ppp_ipv4_config(&self) -> Option<PppIpv4Config> {
+        self.inner.ppp.as_ref().and_then(|ppp| ppp.ipv4_config())
+    }
+
+    /// Restart the negotiation of the PPP link, for example after the modem behind
+    /// the device reconnected. The negotiated address is removed until IPCP opens again.
+    #[cfg(feature = "medium-ppp")]
+    pub fn ppp_restart(&mut self) {
+        if let Some(ppp) = self.inner.ppp.as_mut() {
+            ppp.reset();
+            self.inner.ppp_update_config();
+        }
+    }
+
+    /// Set a function to be called with every incoming frame dropped by the interface.
+    ///
+    /// The function is called after the frame has been processed, with the reason it was
+    /// dropped and the full frame as received from the device.
+    pub fn set_drop_hook(&mut self, hook: Option<fn(DropReason, &[u8])>) {
+        self.inner.drop_hook = hook;
+    }
+
+    /// Set a function generating the initial sequence number of TCP connections.
+    ///
+    /// The function is called with the local and remote endpoints of every new connection,
+    /// and the current time. By default, initial sequence numbers are drawn from the
+    /// pseudorandom generator seeded with [random_seed]; a function implementing
+    /// [RFC 6528] instead, i.e. adding a keyed hash of the endpoints to a clock ticking
+    /// every 4 µs, makes the sequence numbers of different connections unpredictable even
+    /// to an observer of the seed. The key may be kept in a `static`.
+    ///
+    /// [random_seed]: struct.InterfaceBuilder.html#method.random_seed
+    /// [RFC 6528]: https://www.rfc-editor.org/rfc/rfc6528
+    #[cfg(feature = "socket-tcp")]
+    pub fn set_tcp_iss_hook(
+        &mut self,
+        hook: Option<fn(IpEndpoint, IpEndpoint, Instant) -> TcpSeqNumber>,
+    ) {
+        self.inner.set_tcp_iss_hook(hook)
+    }
+
     /// Transmit packets queued in the given sockets, and receive packets queued
     /// in the device.
     ///
+    /// All frames the device has received are processed, and every socket sends
+    /// as many packets as it has ready, e.g. a TCP socket as many segments as the
+    /// window allows, for as long as the device accepts them.
+    ///
     /// This function returns a boolean value indicating whether any packets were
     /// processed or emitted, and thus, whether the readiness of any socket might
-    /// have changed.
+    /// have changed. The sockets that became ready are then reported by
+    /// [SocketSet::readiness].
     ///
     /// # Errors
     /// This method will routinely return errors in response to normal network
@@ -1054,7 +1899,7 @@ impl<'a> Interface<'a> {
     /// These errors are provided as an aid for troubleshooting, and are meant
     /// to be logged and ignored.
     ///
-    /// As a special case, `Err(Error::Unrecognized)` is returned in response to
+    /// As a special case, `Err(Error::Unrecognized(_))` is returned in response to
     /// packets containing any unsupported protocol, option, or form, which is
     /// a very common occurrence and on a production system it should not even
     /// be logged.
@@ -1068,16 +1913,28 @@ impl<'a> Interface<'a> {
         D: Device + ?Sized,
     {
         self.inner.now = timestamp;
+        let _span = net_span!("poll", timestamp = %timestamp);
 
+        sockets.start_readiness();
+        let result = self.poll_sockets(device, sockets);
+        sockets.finish_readiness();
+        result
+    }
+
+    fn poll_sockets<D>(&mut self, device: &mut D, sockets: &mut SocketSet<'_>) -> Result<bool>
+    where
+        D: Device + ?Sized,
+    {
         #[cfg(feature = "proto-ipv4-fragmentation")]
-        self.fragments
-            .ipv4_fragments
-            .remove_when(|frag| Ok(timestamp >= frag.expires_at()?))?;
+        self.ipv4_reassembly_expiry(device);
 
         #[cfg(feature = "proto-sixlowpan-fragmentation")]
-        self.fragments
-            .sixlowpan_fragments
-            .remove_when(|frag| Ok(timestamp >= frag.expires_at()?))?;
+        {
+            let timestamp = self.inner.now;
+            self.fragments
+                .sixlowpan_fragments
+                .remove_when(|frag| Ok(timestamp >= frag.expires_at()?))?;
+        }
 
         #[cfg(feature = "proto-ipv4-fragmentation")]
         match self.ipv4_egress(device) {
@@ -1108,6 +1965,21 @@ impl<'a> Interface<'a> {
             #[cfg(feature = "proto-igmp")]
             self.igmp_egress(device)?;
 
+            #[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+            self.slaac_egress(device)?;
+
+            #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+            self.acd_egress(device)?;
+
+            #[cfg(feature = "medium-ppp")]
+            self.ppp_egress(device)?;
+
+            #[cfg(feature = "forwarding")]
+            self.forwarding_egress(device)?;
+
+            #[cfg(feature = "proto-lldp")]
+            self.lldp_egress(device)?;
+
             if processed_any || emitted_any {
                 readiness_may_have_changed = true;
             } else {
@@ -1134,6 +2006,41 @@ impl<'a> Interface<'a> {
             return Some(Instant::from_millis(0));
         }
 
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        let reassembly_expires_at = self.fragments.ipv4_fragments.expires_at();
+        #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+        let reassembly_expires_at = None;
+
+        #[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+        let slaac_poll_at = self.inner.slaac.as_ref().and_then(|slaac| slaac.poll_at());
+        #[cfg(not(all(feature = "proto-ipv6", feature = "medium-ethernet")))]
+        let slaac_poll_at = None;
+
+        #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+        let acd_poll_at = self.inner.acd.as_ref().and_then(|acd| acd.poll_at());
+        #[cfg(not(all(feature = "proto-ipv4", feature = "medium-ethernet")))]
+        let acd_poll_at = None;
+
+        #[cfg(feature = "medium-ppp")]
+        let ppp_poll_at = self.inner.ppp.as_ref().and_then(|ppp| ppp.poll_at());
+        #[cfg(not(feature = "medium-ppp"))]
+        let ppp_poll_at = None;
+
+        #[cfg(feature = "forwarding")]
+        let forwarding_poll_at = self
+            .inner
+            .forwarding
+            .as_ref()
+            .filter(|forwarding| !forwarding.tx.is_empty())
+            .map(|_| Instant::from_millis(0));
+        #[cfg(not(feature = "forwarding"))]
+        let forwarding_poll_at = None;
+
+        #[cfg(feature = "proto-lldp")]
+        let lldp_poll_at = self.inner.lldp.as_ref().map(|lldp| lldp.poll_at());
+        #[cfg(not(feature = "proto-lldp"))]
+        let lldp_poll_at = None;
+
         let inner = &mut self.inner;
 
         sockets
@@ -1149,6 +2056,12 @@ impl<'a> Interface<'a> {
                     PollAt::Now => Some(Instant::from_millis(0)),
                 }
             })
+            .chain(reassembly_expires_at)
+            .chain(slaac_poll_at)
+            .chain(acd_poll_at)
+            .chain(ppp_poll_at)
+            .chain(forwarding_poll_at)
+            .chain(lldp_poll_at)
             .min()
     }
 
@@ -1181,6 +2094,8 @@ impl<'a> Interface<'a> {
 
         while let Some((rx_token, tx_token)) = device.receive() {
             let res = rx_token.consume(inner.now, |frame| {
+                #[cfg(feature = "stats")]
+                inner.stats.record_rx(frame.len());
                 match inner.caps.medium {
                     #[cfg(feature = "medium-ethernet")]
                     Medium::Ethernet => {
@@ -1211,7 +2126,18 @@ impl<'a> Interface<'a> {
                             }
                         }
                     }
+                    #[cfg(feature = "medium-ppp")]
+                    Medium::Ppp => {
+                        if let Some(packet) = inner.process_ppp(sockets, &frame, _fragments) {
+                            if let Err(err) =
+                                inner.dispatch_ppp(tx_token, packet, Some(_out_packets))
+                            {
+                                net_debug!("Failed to send response: {}", err);
+                            }
+                        }
+                    }
                 }
+                inner.report_drop(frame);
                 processed_any = true;
                 Ok(())
             });
@@ -1228,6 +2154,9 @@ impl<'a> Interface<'a> {
     where
         D: Device + ?Sized,
     {
+        #[cfg(feature = "socket-udp")]
+        self.udp_loop_back(sockets);
+
         let Self {
             inner,
             out_packets: _out_packets,
@@ -1236,7 +2165,7 @@ impl<'a> Interface<'a> {
         let _caps = device.capabilities();
 
         let mut emitted_any = false;
-        for item in sockets.items_mut() {
+        'sockets: for item in sockets.items_mut() {
             if !item
                 .meta
                 .egress_permitted(inner.now, |ip_addr| inner.has_neighbor(&ip_addr))
@@ -1244,91 +2173,186 @@ impl<'a> Interface<'a> {
                 continue;
             }
 
-            let mut neighbor_addr = None;
-            let mut respond = |inner: &mut InterfaceInner, response: IpPacket| {
-                neighbor_addr = Some(response.ip_repr().dst_addr());
-                let t = device.transmit().ok_or_else(|| {
-                    net_debug!("failed to transmit IP: {}", Error::Exhausted);
-                    Error::Exhausted
-                })?;
+            let _span = net_span!("socket", handle = %item.meta.handle);
+            let priority = item.meta.priority;
+
+            // Let the socket send everything it can before moving on to the next one, e.g.
+            // a TCP socket as many segments as its window allows, rather than one packet
+            // per pass over the sockets.
+            loop {
+                let mut neighbor_addr = None;
+                let mut emitted = false;
+                #[allow(unused)] // unused if only Ethernet sockets are enabled
+                let mut respond = |inner: &mut InterfaceInner,
+                                   mut response: IpPacket|
+                 -> Result<()> {
+                    let mapping = priority.and_then(|priority| inner.priority_mapping(priority));
+                    if let Some(mapping) = mapping {
+                        response.set_default_dscp(mapping.dscp);
+                    }
+                    let ip_repr = response.ip_repr();
+                    neighbor_addr = Some(ip_repr.dst_addr());
+                    let priority = match mapping {
+                        Some(mapping) => mapping.pcp,
+                        None => priority
+                            .unwrap_or_else(|| Priority::from_dscp(ip_repr.traffic_class() >> 2)),
+                    };
+                    let t = device.transmit_with_priority(priority).ok_or_else(|| {
+                        net_debug!("failed to transmit IP: {}", Error::Exhausted(Layer::Phy));
+                        Error::Exhausted(Layer::Phy)
+                    })?;
+
+                    #[cfg(any(
+                        feature = "proto-ipv4-fragmentation",
+                        feature = "proto-sixlowpan-fragmentation"
+                    ))]
+                    inner.dispatch_ip(t, response, Some(_out_packets))?;
+
+                    #[cfg(not(any(
+                        feature = "proto-ipv4-fragmentation",
+                        feature = "proto-sixlowpan-fragmentation"
+                    )))]
+                    inner.dispatch_ip(t, response, None)?;
+
+                    emitted = true;
+
+                    Ok(())
+                };
 
+                let result = match &mut item.socket {
+                    #[cfg(feature = "socket-raw")]
+                    Socket::Raw(socket) => socket.dispatch(inner, |inner, response| {
+                        respond(inner, IpPacket::Raw(response))
+                    }),
+                    #[cfg(feature = "socket-icmp")]
+                    Socket::Icmp(socket) => {
+                        socket.dispatch(inner, |inner, response| match response {
+                            #[cfg(feature = "proto-ipv4")]
+                            (IpRepr::Ipv4(ipv4_repr), IcmpRepr::Ipv4(icmpv4_repr)) => {
+                                respond(inner, IpPacket::Icmpv4((ipv4_repr, icmpv4_repr)))
+                            }
+                            #[cfg(feature = "proto-ipv6")]
+                            (IpRepr::Ipv6(ipv6_repr), IcmpRepr::Ipv6(icmpv6_repr)) => {
+                                respond(inner, IpPacket::Icmpv6((ipv6_repr, icmpv6_repr)))
+                            }
+                            #[allow(unreachable_patterns)]
+                            _ => unreachable!(),
+                        })
+                    }
+                    #[cfg(feature = "socket-udp")]
+                    Socket::Udp(socket) => socket.dispatch(inner, |inner, response| {
+                        respond(inner, IpPacket::Udp(response))
+                    }),
+                    #[cfg(feature = "socket-tcp")]
+                    Socket::Tcp(socket) => socket.dispatch(inner, |inner, response| {
+                        respond(inner, IpPacket::Tcp(response))
+                    }),
+                    #[cfg(feature = "socket-dhcpv4")]
+                    Socket::Dhcpv4(socket) => socket.dispatch(inner, |inner, response| {
+                        respond(inner, IpPacket::Dhcpv4(response))
+                    }),
+                    #[cfg(feature = "socket-dns")]
+                    Socket::Dns(ref mut socket) => socket.dispatch(inner, |inner, response| {
+                        respond(inner, IpPacket::Udp(response))
+                    }),
+                    #[cfg(feature = "socket-mdns")]
+                    Socket::Mdns(ref mut socket) => socket.dispatch(inner, |inner, response| {
+                        respond(inner, IpPacket::Udp(response))
+                    }),
+                    #[cfg(feature = "socket-sntp")]
+                    Socket::Sntp(ref mut socket) => socket.dispatch(inner, |inner, response| {
+                        respond(inner, IpPacket::Udp(response))
+                    }),
+                    #[cfg(feature = "socket-ethernet")]
+                    Socket::Ethernet(socket) => socket.dispatch(inner, |inner, frame| {
+                        let t = device
+                            .transmit_with_priority(priority.unwrap_or_default())
+                            .ok_or_else(|| {
+                                net_debug!(
+                                    "failed to transmit Ethernet frame: {}",
+                                    Error::Exhausted(Layer::Phy)
+                                );
+                                Error::Exhausted(Layer::Phy)
+                            })?;
+                        inner.dispatch_ethernet_frame(t, frame)?;
+                        emitted = true;
+                        Ok(())
+                    }),
+                };
+                emitted_any |= emitted;
+
+                match result {
+                    Err(Error::Exhausted(_)) => break 'sockets, // Device buffer full.
+                    Err(Error::Unaddressable(_)) => {
+                        // `NeighborCache` already takes care of rate limiting the neighbor
+                        // discovery requests from the socket. However, without an additional
+                        // rate limiting mechanism, we would spin on every socket that has yet
+                        // to discover its neighbor.
+                        item.meta.neighbor_missing(
+                            inner.now,
+                            neighbor_addr.expect("non-IP response packet"),
+                        );
+                        break 'sockets;
+                    }
+                    Err(err) => {
+                        net_debug!(
+                            "{}: cannot dispatch egress packet: {}",
+                            item.meta.handle,
+                            err
+                        );
+                        break;
+                    }
+                    Ok(()) if !emitted => break,
+                    Ok(()) => {}
+                }
+
+                // The rest of a fragmented packet has to be sent first.
                 #[cfg(any(
                     feature = "proto-ipv4-fragmentation",
                     feature = "proto-sixlowpan-fragmentation"
                 ))]
-                inner.dispatch_ip(t, response, Some(_out_packets))?;
-
-                #[cfg(not(any(
-                    feature = "proto-ipv4-fragmentation",
-                    feature = "proto-sixlowpan-fragmentation"
-                )))]
-                inner.dispatch_ip(t, response, None)?;
-
-                emitted_any = true;
+                if !_out_packets.all_transmitted() {
+                    break 'sockets;
+                }
+            }
+        }
+        emitted_any
+    }
 
-                Ok(())
+    /// Loop the multicast datagrams about to be sent by UDP sockets back to the socket
+    /// that would receive them from the network, see [udp::Socket::set_multicast_loop].
+    #[cfg(feature = "socket-udp")]
+    fn udp_loop_back(&mut self, sockets: &mut SocketSet<'_>) {
+        let inner = &mut self.inner;
+        sockets.for_each_with_others(|item, others| {
+            let sender = match udp::Socket::downcast_mut(&mut item.socket) {
+                Some(socket) => socket,
+                None => return,
             };
-
-            let result = match &mut item.socket {
-                #[cfg(feature = "socket-raw")]
-                Socket::Raw(socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Raw(response))
-                }),
-                #[cfg(feature = "socket-icmp")]
-                Socket::Icmp(socket) => socket.dispatch(inner, |inner, response| match response {
-                    #[cfg(feature = "proto-ipv4")]
-                    (IpRepr::Ipv4(ipv4_repr), IcmpRepr::Ipv4(icmpv4_repr)) => {
-                        respond(inner, IpPacket::Icmpv4((ipv4_repr, icmpv4_repr)))
-                    }
-                    #[cfg(feature = "proto-ipv6")]
-                    (IpRepr::Ipv6(ipv6_repr), IcmpRepr::Ipv6(icmpv6_repr)) => {
-                        respond(inner, IpPacket::Icmpv6((ipv6_repr, icmpv6_repr)))
+            sender.loop_back(inner, |inner, ip_repr, udp_repr, payload, specificity| {
+                // Prefer the most specific socket, as for datagrams from the network, and
+                // the sender over other sockets that are as specific.
+                let mut best: Option<&mut udp::Socket> = None;
+                for udp_socket in others.filter_map(|i| udp::Socket::downcast_mut(&mut i.socket)) {
+                    let udp_specificity = udp_socket.endpoint().specificity();
+                    if udp_socket.accepts(inner, ip_repr, udp_repr)
+                        && specificity.map_or(true, |specificity| udp_specificity > specificity)
+                        && best
+                            .as_ref()
+                            .map_or(true, |best| udp_specificity > best.endpoint().specificity())
+                    {
+                        best = Some(udp_socket);
                     }
-                    #[allow(unreachable_patterns)]
-                    _ => unreachable!(),
-                }),
-                #[cfg(feature = "socket-udp")]
-                Socket::Udp(socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Udp(response))
-                }),
-                #[cfg(feature = "socket-tcp")]
-                Socket::Tcp(socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Tcp(response))
-                }),
-                #[cfg(feature = "socket-dhcpv4")]
-                Socket::Dhcpv4(socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Dhcpv4(response))
-                }),
-                #[cfg(feature = "socket-dns")]
-                Socket::Dns(ref mut socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Udp(response))
-                }),
-            };
-
-            match result {
-                Err(Error::Exhausted) => break, // Device buffer full.
-                Err(Error::Unaddressable) => {
-                    // `NeighborCache` already takes care of rate limiting the neighbor discovery
-                    // requests from the socket. However, without an additional rate limiting
-                    // mechanism, we would spin on every socket that has yet to discover its
-                    // neighbor.
-                    item.meta.neighbor_missing(
-                        inner.now,
-                        neighbor_addr.expect("non-IP response packet"),
-                    );
-                    break;
                 }
-                Err(err) => {
-                    net_debug!(
-                        "{}: cannot dispatch egress packet: {}",
-                        item.meta.handle,
-                        err
-                    );
+                match best {
+                    Some(udp_socket) => {
+                        udp_socket.process(inner, ip_repr, udp_repr, payload);
+                        true
+                    }
+                    None => false,
                 }
-                Ok(()) => {}
-            }
-        }
-        emitted_any
+            });
+        });
     }
 
     /// Depending on `igmp_report_state` and the therein contained
@@ -1346,7 +2370,9 @@ impl<'a> Interface<'a> {
             } if self.inner.now >= timeout => {
                 if let Some(pkt) = self.inner.igmp_report_packet(version, group) {
                     // Send initial membership report
-                    let tx_token = device.transmit().ok_or(Error::Exhausted)?;
+                    let tx_token = device
+                        .transmit_with_priority(Priority::NETWORK_CONTROL)
+                        .ok_or(Error::Exhausted(Layer::Phy))?;
                     self.inner.dispatch_ip(tx_token, pkt, None)?;
                 }
 
@@ -1370,7 +2396,9 @@ impl<'a> Interface<'a> {
                     Some(addr) => {
                         if let Some(pkt) = self.inner.igmp_report_packet(version, addr) {
                             // Send initial membership report
-                            let tx_token = device.transmit().ok_or(Error::Exhausted)?;
+                            let tx_token = device
+                                .transmit_with_priority(Priority::NETWORK_CONTROL)
+                                .ok_or(Error::Exhausted(Layer::Phy))?;
                             self.inner.dispatch_ip(tx_token, pkt, None)?;
                         }
 
@@ -1394,6 +2422,163 @@ impl<'a> Interface<'a> {
         }
     }
 
+    /// Carry out the steps of address autoconfiguration that are due, and send the
+    /// router and neighbor solicitations they need.
+    #[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+    fn slaac_egress<D>(&mut self, device: &mut D) -> Result<bool>
+    where
+        D: Device + ?Sized,
+    {
+        let mut emitted_any = false;
+        while let Some(pkt) = self.inner.slaac_poll() {
+            let tx_token = device
+                .transmit_with_priority(Priority::NETWORK_CONTROL)
+                .ok_or(Error::Exhausted(Layer::Phy))?;
+            self.inner.dispatch_ip(tx_token, pkt, None)?;
+            emitted_any = true;
+        }
+        Ok(emitted_any)
+    }
+
+    /// Carry out the steps of address conflict detection that are due, and send the
+    /// ARP probes and announcements they need.
+    #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+    fn acd_egress<D>(&mut self, device: &mut D) -> Result<bool>
+    where
+        D: Device + ?Sized,
+    {
+        let mut emitted_any = false;
+        while let Some(arp_repr) = self.inner.acd_poll() {
+            let tx_token = device
+                .transmit_with_priority(Priority::NETWORK_CONTROL)
+                .ok_or(Error::Exhausted(Layer::Phy))?;
+            self.inner
+                .dispatch_ethernet(tx_token, arp_repr.buffer_len(), |mut frame| {
+                    frame.set_dst_addr(EthernetAddress::BROADCAST);
+                    frame.set_ethertype(EthernetProtocol::Arp);
+
+                    arp_repr.emit(&mut ArpPacket::new_unchecked(frame.payload_mut()))
+                })?;
+            emitted_any = true;
+        }
+        Ok(emitted_any)
+    }
+
+    /// Send the LCP and IPCP packets due to negotiate the PPP link.
+    #[cfg(feature = "medium-ppp")]
+    fn ppp_egress<D>(&mut self, device: &mut D) -> Result<bool>
+    where
+        D: Device + ?Sized,
+    {
+        let mut emitted_any = false;
+        while let Some(message) = self.inner.ppp_poll() {
+            let tx_token = device
+                .transmit_with_priority(Priority::NETWORK_CONTROL)
+                .ok_or(Error::Exhausted(Layer::Phy))?;
+            self.inner.dispatch_ppp_control(tx_token, &message)?;
+            emitted_any = true;
+        }
+        Ok(emitted_any)
+    }
+
+    /// Send the IP packets forwarded to the interface.
+    #[cfg(feature = "forwarding")]
+    fn forwarding_egress<D>(&mut self, device: &mut D) -> Result<bool>
+    where
+        D: Device + ?Sized,
+    {
+        // Take the buffers out of the interface, since the packets are dispatched from them.
+        let mut forwarding = match self.inner.forwarding.take() {
+            Some(forwarding) => forwarding,
+            None => return Ok(false),
+        };
+
+        let mut result = Ok(false);
+        while !forwarding.tx.is_empty() {
+            let tx_token = match device.transmit() {
+                Some(tx_token) => tx_token,
+                None => {
+                    result = Err(Error::Exhausted(Layer::Phy));
+                    break;
+                }
+            };
+            let packet = match forwarding.tx.dequeue() {
+                Ok((ip_repr, payload)) => IpPacket::Forward((ip_repr, payload)),
+                Err(_) => break,
+            };
+            if let Err(err) = self
+                .inner
+                .dispatch_ip(tx_token, packet, Some(&mut self.out_packets))
+            {
+                net_debug!("forwarding: failed to send packet: {}", err);
+            }
+            result = Ok(true);
+        }
+
+        self.inner.forwarding = Some(forwarding);
+        result
+    }
+
+    /// Send an LLDP advertisement if one is due.
+    #[cfg(feature = "proto-lldp")]
+    fn lldp_egress<D>(&mut self, device: &mut D) -> Result<bool>
+    where
+        D: Device + ?Sized,
+    {
+        if !self.inner.lldp_poll() {
+            return Ok(false);
+        }
+        let tx_token = device
+            .transmit_with_priority(Priority::NETWORK_CONTROL)
+            .ok_or(Error::Exhausted(Layer::Phy))?;
+        self.inner.dispatch_lldp(tx_token)?;
+        Ok(true)
+    }
+
+    /// Drop the IPv4 packets whose reassembly timed out, and send an ICMP time exceeded
+    /// message for those whose first fragment was received.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    fn ipv4_reassembly_expiry<D>(&mut self, device: &mut D)
+    where
+        D: Device + ?Sized,
+    {
+        let now = self.inner.now;
+        while let Some((key, data)) = self.fragments.ipv4_fragments.remove_expired(now) {
+            net_debug!("IPv4 reassembly timed out");
+            self.inner.drop_stats.record(DropReason::Reassembly);
+            // RFC 792: no time exceeded message is sent unless fragment zero is available.
+            if data.is_empty() {
+                continue;
+            }
+
+            // The header of the first fragment is not kept, so rebuild it from the key.
+            let ipv4_repr = Ipv4Repr {
+                src_addr: key.src_addr,
+                dst_addr: key.dst_addr,
+                next_header: key.protocol,
+                payload_len: data.len(),
+                hop_limit: 64,
+                traffic_class: 0,
+            };
+            let payload_len =
+                icmp_reply_payload_len(data.len(), IPV4_MIN_MTU, ipv4_repr.buffer_len());
+            let icmp_reply_repr = Icmpv4Repr::TimeExceeded {
+                reason: Icmpv4TimeExceeded::FragExpired,
+                header: ipv4_repr,
+                data: &data[..payload_len],
+            };
+            if let Some(packet) = self.inner.icmpv4_reply(ipv4_repr, icmp_reply_repr) {
+                let result = match device.transmit() {
+                    Some(tx_token) => self.inner.dispatch_ip(tx_token, packet, None),
+                    None => Err(Error::Exhausted(Layer::Phy)),
+                };
+                if let Err(err) = result {
+                    net_debug!("failed to send time exceeded: {}", err);
+                }
+            }
+        }
+    }
+
     /// Process fragments that still need to be sent for IPv4 packets.
     ///
     /// This function returns a boolean value indicating whether any packets were
@@ -1424,7 +2609,7 @@ impl<'a> Interface<'a> {
                 Some(tx_token) => self
                     .inner
                     .dispatch_ipv4_out_packet(tx_token, &mut self.out_packets.ipv4_out_packet),
-                None => Err(Error::Exhausted),
+                None => Err(Error::Exhausted(Layer::Phy)),
             }
             .map(|_| true)
         } else {
@@ -1463,7 +2648,7 @@ impl<'a> Interface<'a> {
                     tx_token,
                     &mut self.out_packets.sixlowpan_out_packet,
                 ),
-                None => Err(Error::Exhausted),
+                None => Err(Error::Exhausted(Layer::Phy)),
             }
             .map(|_| true)
         } else {
@@ -1499,6 +2684,32 @@ impl<'a> InterfaceInner<'a> {
         &mut self.rand
     }
 
+    #[cfg(feature = "socket-tcp")]
+    pub(crate) fn set_tcp_iss_hook(
+        &mut self,
+        hook: Option<fn(IpEndpoint, IpEndpoint, Instant) -> TcpSeqNumber>,
+    ) {
+        self.tcp_iss_hook = hook;
+    }
+
+    /// Return the initial sequence number chosen by the user for a TCP connection, if any.
+    #[cfg(feature = "socket-tcp")]
+    pub(crate) fn tcp_iss(&self, local: IpEndpoint, remote: IpEndpoint) -> Option<TcpSeqNumber> {
+        self.tcp_iss_hook.map(|hook| hook(local, remote, self.now))
+    }
+
+    #[allow(unused)]
+    pub(crate) fn ip_addrs(&self) -> &[IpCidr] {
+        self.ip_addrs.as_ref()
+    }
+
+    fn priority_mapping(&self, priority: Priority) -> Option<PriorityMapping> {
+        self.priority_mappings
+            .get(usize::from(priority.0))
+            .copied()
+            .flatten()
+    }
+
     #[allow(unused)] // unused depending on which sockets are enabled
     pub(crate) fn get_source_address(&mut self, dst_addr: IpAddress) -> Option<IpAddress> {
         let v = dst_addr.version();
@@ -1567,6 +2778,7 @@ impl<'a> InterfaceInner<'a> {
                 max_transmission_unit: 1514,
                 #[cfg(not(feature = "medium-ethernet"))]
                 max_transmission_unit: 1500,
+                loopback: false,
             },
             now: Instant::from_millis_const(0),
 
@@ -1599,6 +2811,12 @@ impl<'a> InterfaceInner<'a> {
 
             #[cfg(feature = "proto-ipv4-fragmentation")]
             ipv4_id: 1,
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            ipv4_reassembly_timeout: Duration::from_secs(90),
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            ipv4_reassembly_max_packets: u8::MAX as usize,
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            ipv4_reassembly_max_size: u16::MAX as usize,
 
             #[cfg(feature = "medium-ethernet")]
             hardware_addr: Some(crate::wire::HardwareAddress::Ethernet(
@@ -1618,6 +2836,40 @@ impl<'a> InterfaceInner<'a> {
             igmp_report_state: IgmpReportState::Inactive,
             #[cfg(feature = "proto-igmp")]
             ipv4_multicast_groups: LinearMap::new(),
+
+            drop_stats: DropStats::default(),
+            #[cfg(feature = "stats")]
+            stats: Stats::default(),
+            last_drop: None,
+            drop_hook: None,
+            #[cfg(feature = "socket-tcp")]
+            tcp_iss_hook: None,
+            echo_reply: true,
+            echo_reply_max_len: None,
+            #[cfg(feature = "socket-ethernet")]
+            promiscuous: false,
+            source_route_policy: SourceRoutePolicy::Drop,
+            priority_mappings: [None; 8],
+            #[cfg(feature = "socket-tcp")]
+            tcp_backlog_policy: BacklogPolicy::Reset,
+            #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+            ephemeral_ports: EPHEMERAL_PORTS,
+            #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+            next_ephemeral_port: 0,
+            icmp_error_rate_limit: None,
+            icmp_error_limiter: RateLimiter::new(),
+            #[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+            slaac: None,
+            #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+            acd: None,
+            #[cfg(feature = "medium-ppp")]
+            ppp: None,
+            #[cfg(feature = "forwarding")]
+            forwarding: None,
+            #[cfg(feature = "forwarding")]
+            forward_directed_broadcasts: false,
+            #[cfg(feature = "proto-lldp")]
+            lldp: None,
         }
     }
 
@@ -1682,8 +2934,16 @@ impl<'a> InterfaceInner<'a> {
         })
     }
 
+    /// Check whether an echo request with `len` octets of data should be answered.
+    fn should_reply_to_echo(&self, len: usize) -> bool {
+        self.echo_reply
+            && self
+                .echo_reply_max_len
+                .map_or(true, |max_len| len <= max_len)
+    }
+
     /// Check whether the interface has the given IP address assigned.
-    fn has_ip_addr<T: Into<IpAddress>>(&self, addr: T) -> bool {
+    pub(crate) fn has_ip_addr<T: Into<IpAddress>>(&self, addr: T) -> bool {
         let addr = addr.into();
         self.ip_addrs.iter().any(|probe| probe.address() == addr)
     }
@@ -1724,7 +2984,7 @@ impl<'a> InterfaceInner<'a> {
         match IpVersion::of_packet(ip_payload.as_ref()) {
             #[cfg(feature = "proto-ipv4")]
             Ok(IpVersion::Ipv4) => {
-                let ipv4_packet = check!(Ipv4Packet::new_checked(ip_payload));
+                let ipv4_packet = check!(self, Ipv4Packet::new_checked(ip_payload));
 
                 #[cfg(feature = "proto-ipv4-fragmentation")]
                 {
@@ -1738,11 +2998,14 @@ impl<'a> InterfaceInner<'a> {
             }
             #[cfg(feature = "proto-ipv6")]
             Ok(IpVersion::Ipv6) => {
-                let ipv6_packet = check!(Ipv6Packet::new_checked(ip_payload));
+                let ipv6_packet = check!(self, Ipv6Packet::new_checked(ip_payload));
                 self.process_ipv6(sockets, &ipv6_packet)
             }
             // Drop all other traffic.
-            _ => None,
+            _ => {
+                self.drop_packet(DropReason::Unsupported);
+                None
+            }
         }
     }
 
@@ -1788,12 +3051,80 @@ impl<'a> InterfaceInner<'a> {
         address.is_broadcast() || self.is_subnet_broadcast(address)
     }
 
+    /// Checks if an address is broadcast, taking into account subnet broadcast addresses
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn is_broadcast(&self, address: &IpAddress) -> bool {
+        match *address {
+            #[cfg(feature = "proto-ipv4")]
+            IpAddress::Ipv4(address) => self.is_broadcast_v4(address),
+            #[cfg(feature = "proto-ipv6")]
+            IpAddress::Ipv6(_) => false,
+        }
+    }
+
     /// Checks if an ipv4 address is unicast, taking into account subnet broadcast addresses
     #[cfg(feature = "proto-ipv4")]
     fn is_unicast_v4(&self, address: Ipv4Address) -> bool {
         address.is_unicast() && !self.is_subnet_broadcast(address)
     }
 
+    /// Checks if an address is a loopback address.
+    fn is_loopback(address: IpAddress) -> bool {
+        match address {
+            #[cfg(feature = "proto-ipv4")]
+            IpAddress::Ipv4(address) => address.is_loopback(),
+            #[cfg(feature = "proto-ipv6")]
+            IpAddress::Ipv6(address) => address.is_loopback(),
+        }
+    }
+
+    /// Checks if an address can not be the source of an incoming packet.
+    ///
+    /// Besides non-unicast addresses, this rejects loopback addresses and the addresses
+    /// of the interface itself, unless the device is a loopback one and so legitimately
+    /// hands the interface its own packets.
+    fn is_martian_source(&self, address: IpAddress) -> bool {
+        let unicast = match address {
+            #[cfg(feature = "proto-ipv4")]
+            IpAddress::Ipv4(address) => self.is_unicast_v4(address),
+            #[cfg(feature = "proto-ipv6")]
+            IpAddress::Ipv6(address) => address.is_unicast(),
+        };
+        if !unicast {
+            return true;
+        }
+        let own = Self::is_loopback(address) || self.has_ip_addr(address);
+        own && !self.caps.loopback
+    }
+
+    /// Account for the incoming packet being processed as dropped.
+    pub(crate) fn drop_packet(&mut self, reason: DropReason) {
+        net_trace!("iface: dropped packet ({})", reason);
+        self.drop_stats.record(reason);
+        self.last_drop = Some(reason);
+    }
+
+    /// Return whether sending an ICMP error message now would exceed the rate limit,
+    /// and count the message otherwise.
+    pub(super) fn icmp_error_rate_exceeded(&mut self) -> bool {
+        match self.icmp_error_rate_limit {
+            Some(limit) => {
+                self.icmp_error_limiter
+                    .exceeded(self.now, limit.max_errors, limit.interval)
+            }
+            None => false,
+        }
+    }
+
+    /// Hand the frame that was just processed to the drop hook, if it was dropped.
+    fn report_drop(&mut self, frame: &[u8]) {
+        if let Some(reason) = self.last_drop.take() {
+            if let Some(hook) = self.drop_hook {
+                hook(reason, frame)
+            }
+        }
+    }
+
     #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
     fn process_udp<'frame>(
         &mut self,
@@ -1805,16 +3136,38 @@ impl<'a> InterfaceInner<'a> {
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
         #[cfg(feature = "socket-udp")]
-        for udp_socket in sockets
-            .items_mut()
-            .filter_map(|i| udp::Socket::downcast_mut(&mut i.socket))
         {
-            if udp_socket.accepts(self, &ip_repr, &udp_repr) {
+            // Prefer a socket bound to the destination address over one bound to the port only.
+            let mut best: Option<&mut udp::Socket> = None;
+            for udp_socket in sockets
+                .items_mut()
+                .filter_map(|i| udp::Socket::downcast_mut(&mut i.socket))
+            {
+                if udp_socket.accepts(self, &ip_repr, &udp_repr)
+                    && best.as_ref().map_or(true, |best| {
+                        udp_socket.endpoint().specificity() > best.endpoint().specificity()
+                    })
+                {
+                    best = Some(udp_socket);
+                }
+            }
+            if let Some(udp_socket) = best {
                 udp_socket.process(self, &ip_repr, &udp_repr, udp_payload);
                 return None;
             }
         }
 
+        #[cfg(feature = "socket-mdns")]
+        for mdns_socket in sockets
+            .items_mut()
+            .filter_map(|i| mdns::Socket::downcast_mut(&mut i.socket))
+        {
+            if mdns_socket.accepts(&udp_repr) {
+                mdns_socket.process(self, &ip_repr, &udp_repr, udp_payload);
+                return None;
+            }
+        }
+
         #[cfg(feature = "socket-dns")]
         for dns_socket in sockets
             .items_mut()
@@ -1826,6 +3179,21 @@ impl<'a> InterfaceInner<'a> {
             }
         }
 
+        #[cfg(feature = "socket-sntp")]
+        for sntp_socket in sockets
+            .items_mut()
+            .filter_map(|i| sntp::Socket::downcast_mut(&mut i.socket))
+        {
+            if sntp_socket.accepts(&ip_repr, &udp_repr) {
+                sntp_socket.process(self, &ip_repr, &udp_repr, udp_payload);
+                return None;
+            }
+        }
+
+        if !handled_by_raw_socket {
+            self.drop_packet(DropReason::NoSocket);
+        }
+
         // The packet wasn't handled by a socket, send an ICMP port unreachable packet.
         match ip_repr {
             #[cfg(feature = "proto-ipv4")]
@@ -1865,24 +3233,64 @@ impl<'a> InterfaceInner<'a> {
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
         let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
-        let tcp_packet = check!(TcpPacket::new_checked(ip_payload));
-        let tcp_repr = check!(TcpRepr::parse(
-            &tcp_packet,
-            &src_addr,
-            &dst_addr,
-            &self.caps.checksum
-        ));
+        let tcp_packet = check!(self, TcpPacket::new_checked(ip_payload));
+        let tcp_repr = check!(
+            self,
+            TcpRepr::parse(&tcp_packet, &src_addr, &dst_addr, &self.caps.checksum)
+        );
 
+        // Prefer a connection over a listening socket, so that e.g. a retransmitted SYN
+        // reaches the connection it already opened rather than opening another one, and
+        // a socket listening on the destination address over one listening on the port only.
+        let mut best: Option<&mut tcp::Socket> = None;
         for tcp_socket in sockets
             .items_mut()
             .filter_map(|i| tcp::Socket::downcast_mut(&mut i.socket))
         {
-            if tcp_socket.accepts(self, &ip_repr, &tcp_repr) {
-                return tcp_socket
-                    .process(self, &ip_repr, &tcp_repr)
-                    .map(IpPacket::Tcp);
+            if tcp_socket.accepts(self, &ip_repr, &tcp_repr)
+                && best
+                    .as_ref()
+                    .map_or(true, |best| tcp_socket.specificity() > best.specificity())
+            {
+                best = Some(tcp_socket);
             }
         }
+        if let Some(tcp_socket) = best {
+            // RFC 2385 § 2.0: segments failing the MD5 signature check are silently dropped.
+            #[cfg(feature = "socket-tcp-md5")]
+            if !tcp_socket.md5_verify(&ip_repr, &tcp_repr, &tcp_packet) {
+                net_debug!("tcp: missing or wrong MD5 signature");
+                self.drop_packet(DropReason::Filtered);
+                return None;
+            }
+            return tcp_socket
+                .process(self, &ip_repr, &tcp_repr)
+                .map(IpPacket::Tcp);
+        }
+
+        // A connection attempt to an endpoint that sockets listened on, which are all
+        // busy with a connection now.
+        let backlog_full = tcp_repr.control == TcpControl::Syn
+            && tcp_repr.ack_number.is_none()
+            && sockets
+                .items()
+                .filter_map(|i| tcp::Socket::downcast(&i.socket))
+                .any(|tcp_socket| tcp_socket.listens_on(&ip_repr, &tcp_repr));
+        if backlog_full {
+            net_debug!(
+                "tcp: no socket left listening on port {}",
+                tcp_repr.dst_port
+            );
+            self.drop_packet(DropReason::BacklogFull);
+            return match self.tcp_backlog_policy {
+                BacklogPolicy::Reset => {
+                    Some(IpPacket::Tcp(tcp::Socket::rst_reply(&ip_repr, &tcp_repr)))
+                }
+                BacklogPolicy::Drop => None,
+            };
+        }
+
+        self.drop_packet(DropReason::NoSocket);
 
         if tcp_repr.control == TcpControl::Rst {
             // Never reply to a TCP RST packet with another TCP RST packet.
@@ -1938,7 +3346,7 @@ impl<'a> InterfaceInner<'a> {
         // Route via a router.
         match self.routes.lookup(addr, timestamp) {
             Some(router_addr) => Ok(router_addr),
-            None => Err(Error::Unaddressable),
+            None => Err(Error::Unaddressable(Layer::Network)),
         }
     }
 
@@ -1961,6 +3369,8 @@ impl<'a> InterfaceInner<'a> {
                     .found(),
                 #[cfg(feature = "medium-ip")]
                 Medium::Ip => true,
+                #[cfg(feature = "medium-ppp")]
+                Medium::Ppp => true,
             },
             Err(_) => false,
         }
@@ -1984,31 +3394,26 @@ impl<'a> InterfaceInner<'a> {
                 Medium::Ieee802154 => HardwareAddress::Ieee802154(Ieee802154Address::BROADCAST),
                 #[cfg(feature = "medium-ip")]
                 Medium::Ip => unreachable!(),
+                #[cfg(feature = "medium-ppp")]
+                Medium::Ppp => unreachable!(),
             };
 
             return Ok((hardware_addr, tx_token));
         }
 
         if dst_addr.is_multicast() {
-            let b = dst_addr.as_bytes();
             let hardware_addr = match *dst_addr {
                 #[cfg(feature = "proto-ipv4")]
-                IpAddress::Ipv4(_addr) => {
-                    HardwareAddress::Ethernet(EthernetAddress::from_bytes(&[
-                        0x01,
-                        0x00,
-                        0x5e,
-                        b[1] & 0x7F,
-                        b[2],
-                        b[3],
-                    ]))
+                IpAddress::Ipv4(addr) => {
+                    HardwareAddress::Ethernet(EthernetAddress::from_ipv4_multicast(addr))
                 }
                 #[cfg(feature = "proto-ipv6")]
-                IpAddress::Ipv6(_addr) => match self.caps.medium {
+                #[cfg_attr(not(feature = "medium-ethernet"), allow(unused_variables))]
+                IpAddress::Ipv6(addr) => match self.caps.medium {
                     #[cfg(feature = "medium-ethernet")]
-                    Medium::Ethernet => HardwareAddress::Ethernet(EthernetAddress::from_bytes(&[
-                        0x33, 0x33, b[12], b[13], b[14], b[15],
-                    ])),
+                    Medium::Ethernet => {
+                        HardwareAddress::Ethernet(EthernetAddress::from_ipv6_multicast(addr))
+                    }
                     #[cfg(feature = "medium-ieee802154")]
                     Medium::Ieee802154 => {
                         // Not sure if this is correct
@@ -2016,6 +3421,8 @@ impl<'a> InterfaceInner<'a> {
                     }
                     #[cfg(feature = "medium-ip")]
                     Medium::Ip => unreachable!(),
+                    #[cfg(feature = "medium-ppp")]
+                    Medium::Ppp => unreachable!(),
                 },
             };
 
@@ -2031,9 +3438,11 @@ impl<'a> InterfaceInner<'a> {
             .lookup(&dst_addr, self.now)
         {
             NeighborAnswer::Found(hardware_addr) => return Ok((hardware_addr, tx_token)),
-            NeighborAnswer::RateLimited => return Err(Error::Unaddressable),
+            NeighborAnswer::RateLimited => return Err(Error::Unaddressable(Layer::Link)),
             _ => (), // XXX
         }
+        #[cfg(feature = "stats")]
+        self.stats.record_neighbor_miss();
 
         match (src_addr, dst_addr) {
             #[cfg(feature = "proto-ipv4")]
@@ -2046,7 +3455,7 @@ impl<'a> InterfaceInner<'a> {
                     if let Some(HardwareAddress::Ethernet(addr)) = self.hardware_addr {
                         addr
                     } else {
-                        return Err(Error::Malformed);
+                        return Err(Error::Malformed(Layer::Link));
                     };
 
                 let arp_repr = ArpRepr::EthernetIpv4 {
@@ -2084,6 +3493,7 @@ impl<'a> InterfaceInner<'a> {
                         next_header: IpProtocol::Icmpv6,
                         payload_len: solicit.buffer_len(),
                         hop_limit: 0xff,
+                        traffic_class: 0,
                     },
                     solicit,
                 ));
@@ -2096,7 +3506,7 @@ impl<'a> InterfaceInner<'a> {
         }
         // The request got dispatched, limit the rate on the cache.
         self.neighbor_cache.as_mut().unwrap().limit_rate(self.now);
-        Err(Error::Unaddressable)
+        Err(Error::Unaddressable(Layer::Link))
     }
 
     fn flush_cache(&mut self) {
@@ -2147,6 +3557,12 @@ impl<'a> InterfaceInner<'a> {
             total_len = EthernetFrame::<&[u8]>::buffer_len(total_len);
         }
 
+        // Add the size of the protocol field if the medium is PPP.
+        #[cfg(feature = "medium-ppp")]
+        if matches!(self.caps.medium, Medium::Ppp) {
+            total_len += PPP_HEADER_LEN;
+        }
+
         // If the medium is Ethernet, then we need to retrieve the destination hardware address.
         #[cfg(feature = "medium-ethernet")]
         let (dst_hardware_addr, tx_token) = match self.caps.medium {
@@ -2172,7 +3588,7 @@ impl<'a> InterfaceInner<'a> {
             let src_addr = if let Some(HardwareAddress::Ethernet(addr)) = self.hardware_addr {
                 addr
             } else {
-                return Err(Error::Malformed);
+                return Err(Error::Malformed(Layer::Link));
             };
 
             frame.set_src_addr(src_addr);
@@ -2188,6 +3604,18 @@ impl<'a> InterfaceInner<'a> {
             Ok(())
         };
 
+        // Emit function for the PPP header.
+        #[cfg(feature = "medium-ppp")]
+        let emit_ppp = |repr: &IpRepr, tx_buffer: &mut [u8]| {
+            let mut frame = PppFrame::new_unchecked(tx_buffer);
+            match repr.version() {
+                #[cfg(feature = "proto-ipv4")]
+                IpVersion::Ipv4 => frame.set_protocol(PppProtocol::Ipv4),
+                #[cfg(feature = "proto-ipv6")]
+                IpVersion::Ipv6 => frame.set_protocol(PppProtocol::Ipv6),
+            }
+        };
+
         // Emit function for the IP header and payload.
         let emit_ip = |repr: &IpRepr, mut tx_buffer: &mut [u8]| {
             repr.emit(&mut tx_buffer, &self.caps.checksum);
@@ -2229,7 +3657,7 @@ impl<'a> InterfaceInner<'a> {
                                 "Fragmentation buffer is too small, at least {} needed",
                                 first_frag_ip_len
                             );
-                            return Err(Error::Exhausted);
+                            return Err(Error::Exhausted(Layer::Network));
                         }
 
                         #[cfg(feature = "medium-ethernet")]
@@ -2264,6 +3692,8 @@ impl<'a> InterfaceInner<'a> {
                         }
 
                         // Transmit the first packet.
+                        #[cfg(feature = "stats")]
+                        self.stats.record_tx(tx_len);
                         tx_token.consume(self.now, tx_len, |mut tx_buffer| {
                             #[cfg(feature = "medium-ethernet")]
                             if matches!(self.caps.medium, Medium::Ethernet) {
@@ -2271,6 +3701,12 @@ impl<'a> InterfaceInner<'a> {
                                 tx_buffer = &mut tx_buffer[EthernetFrame::<&[u8]>::header_len()..];
                             }
 
+                            #[cfg(feature = "medium-ppp")]
+                            if matches!(self.caps.medium, Medium::Ppp) {
+                                emit_ppp(&ip_repr, tx_buffer);
+                                tx_buffer = &mut tx_buffer[PPP_HEADER_LEN..];
+                            }
+
                             // Change the offset for the next packet.
                             *frag_offset = (first_frag_ip_len - ip_header_len) as u16;
 
@@ -2289,6 +3725,8 @@ impl<'a> InterfaceInner<'a> {
                     }
                 } else {
                     // No fragmentation is required.
+                    #[cfg(feature = "stats")]
+                    self.stats.record_tx(total_len);
                     tx_token.consume(self.now, total_len, |mut tx_buffer| {
                         #[cfg(feature = "medium-ethernet")]
                         if matches!(self.caps.medium, Medium::Ethernet) {
@@ -2296,6 +3734,12 @@ impl<'a> InterfaceInner<'a> {
                             tx_buffer = &mut tx_buffer[EthernetFrame::<&[u8]>::header_len()..];
                         }
 
+                        #[cfg(feature = "medium-ppp")]
+                        if matches!(self.caps.medium, Medium::Ppp) {
+                            emit_ppp(&ip_repr, tx_buffer);
+                            tx_buffer = &mut tx_buffer[PPP_HEADER_LEN..];
+                        }
+
                         emit_ip(&ip_repr, tx_buffer);
                         Ok(())
                     })
@@ -2303,16 +3747,26 @@ impl<'a> InterfaceInner<'a> {
             }
             // We don't support IPv6 fragmentation yet.
             #[cfg(feature = "proto-ipv6")]
-            IpRepr::Ipv6(_) => tx_token.consume(self.now, total_len, |mut tx_buffer| {
-                #[cfg(feature = "medium-ethernet")]
-                if matches!(self.caps.medium, Medium::Ethernet) {
-                    emit_ethernet(&ip_repr, tx_buffer)?;
-                    tx_buffer = &mut tx_buffer[EthernetFrame::<&[u8]>::header_len()..];
-                }
+            IpRepr::Ipv6(_) => {
+                #[cfg(feature = "stats")]
+                self.stats.record_tx(total_len);
+                tx_token.consume(self.now, total_len, |mut tx_buffer| {
+                    #[cfg(feature = "medium-ethernet")]
+                    if matches!(self.caps.medium, Medium::Ethernet) {
+                        emit_ethernet(&ip_repr, tx_buffer)?;
+                        tx_buffer = &mut tx_buffer[EthernetFrame::<&[u8]>::header_len()..];
+                    }
 
-                emit_ip(&ip_repr, tx_buffer);
-                Ok(())
-            }),
+                    #[cfg(feature = "medium-ppp")]
+                    if matches!(self.caps.medium, Medium::Ppp) {
+                        emit_ppp(&ip_repr, tx_buffer);
+                        tx_buffer = &mut tx_buffer[PPP_HEADER_LEN..];
+                    }
+
+                    emit_ip(&ip_repr, tx_buffer);
+                    Ok(())
+                })
+            }
         }
     }
 }
@@ -0,0 +1,146 @@
+use super::check;
+use super::DropReason;
+use super::FragmentsBuffer;
+use super::InterfaceInner;
+use super::IpPacket;
+use super::OutPackets;
+use super::SocketSet;
+
+use crate::iface::ppp::Message;
+use crate::phy::TxToken;
+use crate::wire::*;
+use crate::Result;
+
+/// A packet to send over a PPP link.
+#[derive(Debug, PartialEq)]
+pub(crate) enum PppPacket<'a> {
+    Ip(IpPacket<'a>),
+    Control(Message),
+}
+
+impl<'i> InterfaceInner<'i> {
+    pub(super) fn process_ppp<'frame, T: AsRef<[u8]>>(
+        &mut self,
+        sockets: &mut SocketSet,
+        frame: &'frame T,
+        _fragments: &'frame mut FragmentsBuffer<'i>,
+    ) -> Option<PppPacket<'frame>> {
+        let mut frame = frame.as_ref();
+        // Some devices keep the address and control fields, whose values are fixed.
+        if frame.starts_with(&[0xff, 0x03]) {
+            frame = &frame[2..];
+        }
+        let ppp_frame = check!(self, PppFrame::new_checked(frame));
+
+        match ppp_frame.protocol() {
+            PppProtocol::Ipv4 => {
+                // RFC 1661 § 3.4: network-layer packets are discarded until IPCP is opened.
+                if !self.ppp.as_ref()?.ipv4_opened() {
+                    self.drop_packet(DropReason::Filtered);
+                    return None;
+                }
+                let ipv4_packet = check!(self, Ipv4Packet::new_checked(ppp_frame.payload()));
+
+                #[cfg(feature = "proto-ipv4-fragmentation")]
+                {
+                    self.process_ipv4(sockets, &ipv4_packet, Some(&mut _fragments.ipv4_fragments))
+                        .map(PppPacket::Ip)
+                }
+
+                #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+                {
+                    self.process_ipv4(sockets, &ipv4_packet, None)
+                        .map(PppPacket::Ip)
+                }
+            }
+            protocol @ (PppProtocol::Lcp | PppProtocol::Ipcp) => {
+                let packet = check!(self, PppControlPacket::new_checked(ppp_frame.payload()));
+                let repr = check!(self, PppControlRepr::parse(&packet));
+                net_trace!("ppp: received {} {}", protocol, repr);
+                let answer = self.ppp.as_mut()?.process(self.now, protocol, &repr);
+                self.ppp_update_config();
+                answer.map(PppPacket::Control)
+            }
+            // Reject everything else, including IPv6 since IPV6CP is not supported.
+            protocol => {
+                net_debug!("ppp: rejecting protocol {}", protocol);
+                self.drop_packet(DropReason::Unsupported);
+                self.ppp
+                    .as_mut()?
+                    .protocol_reject(protocol, ppp_frame.payload())
+                    .map(PppPacket::Control)
+            }
+        }
+    }
+
+    /// Advance the negotiation of the link, and return the next control packet to send.
+    pub(super) fn ppp_poll(&mut self) -> Option<Message> {
+        let message = self.ppp.as_mut()?.poll(self.now);
+        self.ppp_update_config();
+        message
+    }
+
+    /// Add the address negotiated with IPCP to the interface, together with a default
+    /// route over the link, or remove them once the link goes down.
+    pub(super) fn ppp_update_config(&mut self) {
+        let ppp = match self.ppp.as_mut() {
+            Some(ppp) => ppp,
+            None => return,
+        };
+        let config = ppp.ipv4_config();
+        if ppp.assigned == config.as_ref().map(|config| config.address) {
+            return;
+        }
+
+        if let Some(addr) = ppp.assigned.take() {
+            net_debug!("ppp: removing address {}", addr);
+            self.ip_addrs
+                .retain(|cidr| *cidr != IpCidr::new(IpAddress::Ipv4(addr), 32));
+            self.routes.remove_default_ipv4_route();
+        }
+        if let Some(config) = config {
+            net_debug!("ppp: assigned address {}", config.address);
+            let cidr = IpCidr::new(IpAddress::Ipv4(config.address), 32);
+            if self.ip_addrs.push(cidr).is_err() {
+                net_debug!("ppp: no room for address {}", config.address);
+            }
+            // The link is point-to-point, so any gateway address reaches the peer.
+            let gateway = config.peer_address.unwrap_or(config.address);
+            if self.routes.add_default_ipv4_route(gateway).is_err() {
+                net_debug!("ppp: no room for default route via {}", gateway);
+            }
+            ppp.assigned = Some(config.address);
+        }
+    }
+
+    pub(super) fn dispatch_ppp<Tx: TxToken>(
+        &mut self,
+        tx_token: Tx,
+        packet: PppPacket,
+        out_packet: Option<&mut OutPackets<'_>>,
+    ) -> Result<()> {
+        match packet {
+            PppPacket::Ip(packet) => self.dispatch_ip(tx_token, packet, out_packet),
+            PppPacket::Control(message) => self.dispatch_ppp_control(tx_token, &message),
+        }
+    }
+
+    pub(super) fn dispatch_ppp_control<Tx: TxToken>(
+        &mut self,
+        tx_token: Tx,
+        message: &Message,
+    ) -> Result<()> {
+        let repr = message.repr();
+        net_trace!("ppp: sending {} {}", message.protocol, repr);
+
+        let tx_len = PPP_HEADER_LEN + repr.buffer_len();
+        #[cfg(feature = "stats")]
+        self.stats.record_tx(tx_len);
+        tx_token.consume(self.now, tx_len, |tx_buffer| {
+            let mut frame = PppFrame::new_unchecked(tx_buffer);
+            frame.set_protocol(message.protocol);
+            repr.emit(&mut PppControlPacket::new_unchecked(frame.payload_mut()));
+            Ok(())
+        })
+    }
+}
@@ -1,4 +1,5 @@
 use super::check;
+use super::DropReason;
 use super::FragmentsBuffer;
 use super::InterfaceInner;
 use super::IpPacket;
@@ -9,11 +10,13 @@ use super::SocketSet;
 #[cfg(feature = "proto-sixlowpan-fragmentation")]
 use super::SixlowpanOutPacket;
 
+#[cfg(feature = "forwarding")]
+use crate::phy::ChecksumCapabilities;
 use crate::phy::TxToken;
 use crate::time::*;
 use crate::wire::*;
-use crate::Error;
 use crate::Result;
+use crate::{Error, Layer};
 
 impl<'a> InterfaceInner<'a> {
     #[cfg(feature = "medium-ieee802154")]
@@ -23,10 +26,11 @@ impl<'a> InterfaceInner<'a> {
         sixlowpan_payload: &'payload T,
         _fragments: &'output mut FragmentsBuffer<'a>,
     ) -> Option<IpPacket<'output>> {
-        let ieee802154_frame = check!(Ieee802154Frame::new_checked(sixlowpan_payload));
-        let ieee802154_repr = check!(Ieee802154Repr::parse(&ieee802154_frame));
+        let ieee802154_frame = check!(self, Ieee802154Frame::new_checked(sixlowpan_payload));
+        let ieee802154_repr = check!(self, Ieee802154Repr::parse(&ieee802154_frame));
 
         if ieee802154_repr.frame_type != Ieee802154FrameType::Data {
+            self.drop_packet(DropReason::Unsupported);
             return None;
         }
 
@@ -41,6 +45,7 @@ impl<'a> InterfaceInner<'a> {
                 "IEEE802.15.4: dropping {:?} because not our PAN id (or not broadcast)",
                 ieee802154_repr
             );
+            self.drop_packet(DropReason::Filtered);
             return None;
         }
 
@@ -78,10 +83,11 @@ impl<'a> InterfaceInner<'a> {
             Duration,
         )>,
     ) -> Option<IpPacket<'output>> {
-        let payload = match check!(SixlowpanPacket::dispatch(payload)) {
+        let payload = match check!(self, SixlowpanPacket::dispatch(payload)) {
             #[cfg(not(feature = "proto-sixlowpan-fragmentation"))]
             SixlowpanPacket::FragmentHeader => {
                 net_debug!("Fragmentation is not supported, use the `proto-sixlowpan-fragmentation` feature to add support.");
+                self.drop_packet(DropReason::Unsupported);
                 return None;
             }
             #[cfg(feature = "proto-sixlowpan-fragmentation")]
@@ -96,13 +102,16 @@ impl<'a> InterfaceInner<'a> {
 
         // At this point we should have a valid 6LoWPAN packet.
         // The first header needs to be an IPHC header.
-        let iphc_packet = check!(SixlowpanIphcPacket::new_checked(payload));
-        let iphc_repr = check!(SixlowpanIphcRepr::parse(
-            &iphc_packet,
-            ieee802154_repr.src_addr,
-            ieee802154_repr.dst_addr,
-            self.sixlowpan_address_context,
-        ));
+        let iphc_packet = check!(self, SixlowpanIphcPacket::new_checked(payload));
+        let iphc_repr = check!(
+            self,
+            SixlowpanIphcRepr::parse(
+                &iphc_packet,
+                ieee802154_repr.src_addr,
+                ieee802154_repr.dst_addr,
+                self.sixlowpan_address_context,
+            )
+        );
 
         let payload = iphc_packet.payload();
         let mut ipv6_repr = Ipv6Repr {
@@ -111,11 +120,17 @@ impl<'a> InterfaceInner<'a> {
             hop_limit: iphc_repr.hop_limit,
             next_header: IpProtocol::Unknown(0),
             payload_len: 40,
+            traffic_class: 0,
         };
 
+        #[cfg(feature = "forwarding")]
+        if !self.has_ip_addr(ipv6_repr.dst_addr) && self.is_forwarded(ipv6_repr.dst_addr.into()) {
+            return self.forward_sixlowpan(ipv6_repr, &iphc_repr, payload);
+        }
+
         match iphc_repr.next_header {
             SixlowpanNextHeader::Compressed => {
-                match check!(SixlowpanNhcPacket::dispatch(payload)) {
+                match check!(self, SixlowpanNhcPacket::dispatch(payload)) {
                     SixlowpanNhcPacket::ExtHeader => {
                         net_debug!("Extension headers are currently not supported for 6LoWPAN");
                         None
@@ -127,16 +142,19 @@ impl<'a> InterfaceInner<'a> {
                     }
                     #[cfg(feature = "socket-udp")]
                     SixlowpanNhcPacket::UdpHeader => {
-                        let udp_packet = check!(SixlowpanUdpNhcPacket::new_checked(payload));
+                        let udp_packet = check!(self, SixlowpanUdpNhcPacket::new_checked(payload));
                         ipv6_repr.next_header = IpProtocol::Udp;
                         ipv6_repr.payload_len += 8 + udp_packet.payload().len();
 
-                        let udp_repr = check!(SixlowpanUdpNhcRepr::parse(
-                            &udp_packet,
-                            &iphc_repr.src_addr,
-                            &iphc_repr.dst_addr,
-                            &self.checksum_caps(),
-                        ));
+                        let udp_repr = check!(
+                            self,
+                            SixlowpanUdpNhcRepr::parse(
+                                &udp_packet,
+                                &iphc_repr.src_addr,
+                                &iphc_repr.dst_addr,
+                                &self.checksum_caps(),
+                            )
+                        );
 
                         self.process_udp(
                             sockets,
@@ -168,6 +186,65 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
+    /// Forward a 6LoWPAN packet, with its compressed headers expanded.
+    #[cfg(feature = "forwarding")]
+    fn forward_sixlowpan<'output>(
+        &mut self,
+        mut ipv6_repr: Ipv6Repr,
+        iphc_repr: &SixlowpanIphcRepr,
+        payload: &'output [u8],
+    ) -> Option<IpPacket<'output>> {
+        match iphc_repr.next_header {
+            SixlowpanNextHeader::Uncompressed(next_header) => {
+                ipv6_repr.next_header = next_header;
+                self.forward_ip(IpRepr::Ipv6(ipv6_repr), payload)
+            }
+            SixlowpanNextHeader::Compressed => {
+                match check!(self, SixlowpanNhcPacket::dispatch(payload)) {
+                    SixlowpanNhcPacket::ExtHeader => {
+                        net_debug!("Extension headers are currently not supported for 6LoWPAN");
+                        self.drop_packet(DropReason::Unsupported);
+                        None
+                    }
+                    SixlowpanNhcPacket::UdpHeader => {
+                        let udp_packet = check!(self, SixlowpanUdpNhcPacket::new_checked(payload));
+                        let udp_repr = check!(
+                            self,
+                            SixlowpanUdpNhcRepr::parse(
+                                &udp_packet,
+                                &iphc_repr.src_addr,
+                                &iphc_repr.dst_addr,
+                                &self.checksum_caps(),
+                            )
+                        )
+                        .0;
+                        let udp_payload = udp_packet.payload();
+                        ipv6_repr.next_header = IpProtocol::Udp;
+
+                        let src_addr = IpAddress::Ipv6(ipv6_repr.src_addr);
+                        let dst_addr = IpAddress::Ipv6(ipv6_repr.dst_addr);
+                        // The UDP header is not quoted in ICMP errors, since it is compressed.
+                        self.forward_ip_with(
+                            IpRepr::Ipv6(ipv6_repr),
+                            udp_repr.header_len() + udp_payload.len(),
+                            &[],
+                            |buffer| {
+                                udp_repr.emit(
+                                    &mut UdpPacket::new_unchecked(buffer),
+                                    &src_addr,
+                                    &dst_addr,
+                                    udp_payload.len(),
+                                    |buf| buf.copy_from_slice(udp_payload),
+                                    &ChecksumCapabilities::default(),
+                                )
+                            },
+                        )
+                    }
+                }
+            }
+        }
+    }
+
     #[cfg(feature = "proto-sixlowpan-fragmentation")]
     fn process_sixlowpan_fragment<'output, 'payload: 'output, T: AsRef<[u8]> + ?Sized>(
         &mut self,
@@ -182,7 +259,11 @@ impl<'a> InterfaceInner<'a> {
 
         // We have a fragment header, which means we cannot process the 6LoWPAN packet,
         // unless we have a complete one after processing this fragment.
-        let frag = check!(SixlowpanFragPacket::new_checked(payload));
+        let frag = check!(
+            self,
+            SixlowpanFragPacket::new_checked(payload),
+            DropReason::Malformed
+        );
 
         // The key specifies to which 6LoWPAN fragment it belongs too.
         // It is based on the link layer addresses, the tag and the size.
@@ -199,13 +280,16 @@ impl<'a> InterfaceInner<'a> {
             // compression of the IP header and when UDP is used (because the UDP header
             // can also be compressed). Other headers are not compressed by 6LoWPAN.
 
-            let iphc = check!(SixlowpanIphcPacket::new_checked(frag.payload()));
-            let iphc_repr = check!(SixlowpanIphcRepr::parse(
-                &iphc,
-                ieee802154_repr.src_addr,
-                ieee802154_repr.dst_addr,
-                self.sixlowpan_address_context,
-            ));
+            let iphc = check!(self, SixlowpanIphcPacket::new_checked(frag.payload()));
+            let iphc_repr = check!(
+                self,
+                SixlowpanIphcRepr::parse(
+                    &iphc,
+                    ieee802154_repr.src_addr,
+                    ieee802154_repr.dst_addr,
+                    self.sixlowpan_address_context,
+                )
+            );
 
             // The uncompressed header size always starts with 40, since this is the size
             // of a IPv6 header.
@@ -216,14 +300,15 @@ impl<'a> InterfaceInner<'a> {
             // compressed by 6LoWPAN. We currently don't support extension headers yet.
             match iphc_repr.next_header {
                 SixlowpanNextHeader::Compressed => {
-                    match check!(SixlowpanNhcPacket::dispatch(iphc.payload())) {
+                    match check!(self, SixlowpanNhcPacket::dispatch(iphc.payload())) {
                         SixlowpanNhcPacket::ExtHeader => {
                             net_debug!("6LoWPAN: extension headers not supported");
+                            self.drop_packet(DropReason::Unsupported);
                             return None;
                         }
                         SixlowpanNhcPacket::UdpHeader => {
                             let udp_packet =
-                                check!(SixlowpanUdpNhcPacket::new_checked(iphc.payload()));
+                                check!(self, SixlowpanUdpNhcPacket::new_checked(iphc.payload()));
 
                             uncompressed_header_size += 8;
                             compressed_header_size +=
@@ -243,22 +328,31 @@ impl<'a> InterfaceInner<'a> {
                 Ok(frag) => frag,
                 Err(Error::PacketAssemblerSetFull) => {
                     net_debug!("No available packet assembler for fragmented packet");
+                    self.drop_packet(DropReason::Reassembly);
                     return Default::default();
                 }
-                e => check!(e),
+                e => check!(self, e, DropReason::Reassembly),
             };
 
-            check!(frag_slot.start(
-                Some(
-                    frag.datagram_size() as usize - uncompressed_header_size
-                        + compressed_header_size
+            check!(
+                self,
+                frag_slot.start(
+                    Some(
+                        frag.datagram_size() as usize - uncompressed_header_size
+                            + compressed_header_size
+                    ),
+                    self.now + timeout,
+                    -((uncompressed_header_size - compressed_header_size) as isize),
                 ),
-                self.now + timeout,
-                -((uncompressed_header_size - compressed_header_size) as isize),
-            ));
+                DropReason::Reassembly
+            );
         }
 
-        let frags = check!(fragments.get_packet_assembler_mut(&key));
+        let frags = check!(
+            self,
+            fragments.get_packet_assembler_mut(&key),
+            DropReason::Reassembly
+        );
 
         net_trace!("6LoWPAN: received packet fragment");
 
@@ -275,9 +369,13 @@ impl<'a> InterfaceInner<'a> {
             Err(Error::PacketAssemblerOverlap) => {
                 net_trace!("6LoWPAN: overlap in packet");
                 frags.mark_discarded();
+                self.drop_packet(DropReason::Reassembly);
+                None
+            }
+            Err(_) => {
+                self.drop_packet(DropReason::Reassembly);
                 None
             }
-            Err(_) => None,
         }
     }
 
@@ -293,10 +391,10 @@ impl<'a> InterfaceInner<'a> {
         // Whenever this packet is to big to fit in the IEEE802.15.4 packet, then we need to
         // fragment it.
         let ll_src_a = self.hardware_addr.map_or_else(
-            || Err(Error::Malformed),
+            || Err(Error::Malformed(Layer::Link)),
             |addr| match addr {
                 HardwareAddress::Ieee802154(addr) => Ok(addr),
-                _ => Err(Error::Malformed),
+                _ => Err(Error::Malformed(Layer::Link)),
             },
         )?;
 
@@ -305,7 +403,7 @@ impl<'a> InterfaceInner<'a> {
         let (src_addr, dst_addr) = match (ip_repr.src_addr(), ip_repr.dst_addr()) {
             (IpAddress::Ipv6(src_addr), IpAddress::Ipv6(dst_addr)) => (src_addr, dst_addr),
             #[allow(unreachable_patterns)]
-            _ => return Err(Error::Unaddressable),
+            _ => return Err(Error::Unaddressable(Layer::Network)),
         };
 
         // Create the IEEE802.15.4 header.
@@ -335,8 +433,12 @@ impl<'a> InterfaceInner<'a> {
                 IpPacket::Tcp(_) => SixlowpanNextHeader::Uncompressed(IpProtocol::Tcp),
                 #[cfg(feature = "socket-udp")]
                 IpPacket::Udp(_) => SixlowpanNextHeader::Compressed,
+                #[cfg(feature = "forwarding")]
+                IpPacket::Forward((ip_repr, _)) => {
+                    SixlowpanNextHeader::Uncompressed(ip_repr.next_header())
+                }
                 #[allow(unreachable_patterns)]
-                _ => return Err(Error::Unrecognized),
+                _ => return Err(Error::Unrecognized(Layer::Network)),
             },
             hop_limit: ip_repr.hop_limit(),
             ecn: None,
@@ -368,7 +470,11 @@ impl<'a> InterfaceInner<'a> {
             IpPacket::Icmpv6((_, icmp_repr)) => {
                 total_size += icmp_repr.buffer_len();
             }
-            _ => return Err(Error::Unrecognized),
+            #[cfg(feature = "forwarding")]
+            IpPacket::Forward((_, payload)) => {
+                total_size += payload.len();
+            }
+            _ => return Err(Error::Unrecognized(Layer::Network)),
         }
 
         let ieee_len = ieee_repr.buffer_len();
@@ -404,7 +510,7 @@ impl<'a> InterfaceInner<'a> {
                                 "6LoWPAN: Fragmentation buffer is too small, at least {} needed",
                                 total_size
                             );
-                            return Err(Error::Exhausted);
+                            return Err(Error::Exhausted(Layer::Network));
                         }
                     }
                     #[cfg(feature = "alloc")]
@@ -458,7 +564,11 @@ impl<'a> InterfaceInner<'a> {
                             &self.caps.checksum,
                         );
                     }
-                    _ => return Err(Error::Unrecognized),
+                    #[cfg(feature = "forwarding")]
+                    IpPacket::Forward((_, payload)) => {
+                        b[..payload.len()].copy_from_slice(payload);
+                    }
+                    _ => return Err(Error::Unrecognized(Layer::Network)),
                 }
 
                 *packet_len = total_size;
@@ -499,6 +609,9 @@ impl<'a> InterfaceInner<'a> {
                 *sent_bytes = frag1_size;
                 *datagram_offset = frag1_size + header_diff;
 
+                #[cfg(feature = "stats")]
+                self.stats
+                    .record_tx(ieee_len + frag1.buffer_len() + frag1_size);
                 tx_token.consume(
                     self.now,
                     ieee_len + frag1.buffer_len() + frag1_size,
@@ -531,6 +644,8 @@ impl<'a> InterfaceInner<'a> {
             }
         } else {
             // We don't need fragmentation, so we emit everything to the TX token.
+            #[cfg(feature = "stats")]
+            self.stats.record_tx(total_size + ieee_len);
             tx_token.consume(self.now, total_size + ieee_len, |mut tx_buf| {
                 let mut ieee_packet = Ieee802154Frame::new_unchecked(&mut tx_buf[..ieee_len]);
                 ieee_repr.emit(&mut ieee_packet);
@@ -579,7 +694,11 @@ impl<'a> InterfaceInner<'a> {
                             &self.caps.checksum,
                         );
                     }
-                    _ => return Err(Error::Unrecognized),
+                    #[cfg(feature = "forwarding")]
+                    IpPacket::Forward((_, payload)) => {
+                        tx_buf[..payload.len()].copy_from_slice(payload);
+                    }
+                    _ => return Err(Error::Unrecognized(Layer::Network)),
                 }
                 Ok(())
             })
@@ -633,6 +752,9 @@ impl<'a> InterfaceInner<'a> {
         let ieee_len = ieee_repr.buffer_len();
         let frag_size = (*packet_len - *sent_bytes).min(*fragn_size);
 
+        #[cfg(feature = "stats")]
+        self.stats
+            .record_tx(ieee_repr.buffer_len() + fragn.buffer_len() + frag_size);
         tx_token.consume(
             self.now,
             ieee_repr.buffer_len() + fragn.buffer_len() + frag_size,
@@ -1,5 +1,12 @@
 use std::collections::BTreeMap;
-#[cfg(feature = "proto-igmp")]
+#[cfg(any(
+    feature = "proto-igmp",
+    feature = "proto-lldp",
+    feature = "medium-ppp",
+    all(feature = "forwarding", feature = "medium-ip", feature = "proto-ipv4"),
+    all(feature = "medium-ethernet", feature = "socket-ethernet"),
+    all(feature = "socket-udp", feature = "proto-ipv4")
+))]
 use std::vec::Vec;
 
 use super::*;
@@ -10,7 +17,7 @@ use crate::iface::NeighborCache;
 use crate::phy::{ChecksumCapabilities, Loopback};
 #[cfg(feature = "proto-igmp")]
 use crate::time::Instant;
-use crate::{Error, Result};
+use crate::{Error, Layer, Result};
 
 #[allow(unused)]
 fn fill_slice(s: &mut [u8], val: u8) {
@@ -34,6 +41,8 @@ fn create<'a>(medium: Medium) -> (Interface<'a>, SocketSet<'a>, Loopback) {
         Medium::Ip => create_ip(),
         #[cfg(feature = "medium-ieee802154")]
         Medium::Ieee802154 => create_ieee802154(),
+        #[cfg(feature = "medium-ppp")]
+        Medium::Ppp => create_ppp(),
     }
 }
 
@@ -68,6 +77,22 @@ fn create_ip<'a>() -> (Interface<'a>, SocketSet<'a>, Loopback) {
     (iface, SocketSet::new(vec![]), device)
 }
 
+#[cfg(feature = "medium-ppp")]
+fn create_ppp<'a>() -> (Interface<'a>, SocketSet<'a>, Loopback) {
+    // The address is negotiated with IPCP.
+    let mut device = Loopback::new(Medium::Ppp);
+    let iface_builder = InterfaceBuilder::new();
+
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    let iface_builder = iface_builder
+        .ipv4_reassembly_buffer(PacketAssemblerSet::new(vec![], BTreeMap::new()))
+        .ipv4_fragmentation_buffer(vec![]);
+
+    let iface = iface_builder.finalize(&mut device);
+
+    (iface, SocketSet::new(vec![]), device)
+}
+
 #[cfg(feature = "medium-ethernet")]
 fn create_ethernet<'a>() -> (Interface<'a>, SocketSet<'a>, Loopback) {
     // Create a basic device
@@ -135,7 +160,19 @@ fn create_ieee802154<'a>() -> (Interface<'a>, SocketSet<'a>, Loopback) {
     (iface, SocketSet::new(vec![]), device)
 }
 
-#[cfg(feature = "proto-igmp")]
+#[cfg(any(
+    feature = "proto-igmp",
+    feature = "proto-lldp",
+    feature = "medium-ppp",
+    all(feature = "forwarding", feature = "medium-ip", feature = "proto-ipv4"),
+    all(feature = "medium-ethernet", feature = "socket-ethernet"),
+    all(feature = "socket-udp", feature = "proto-ipv4", feature = "medium-ip"),
+    all(
+        feature = "socket-udp",
+        feature = "proto-ipv4",
+        feature = "medium-ethernet"
+    )
+))]
 fn recv_all(device: &mut Loopback, timestamp: Instant) -> Vec<Vec<u8>> {
     let mut pkts = Vec::new();
     while let Some((rx, _tx)) = device.receive() {
@@ -157,7 +194,7 @@ impl TxToken for MockTxToken {
     where
         F: FnOnce(&mut [u8]) -> Result<R>,
     {
-        Err(Error::Unaddressable)
+        Err(Error::Unaddressable(Layer::Link))
     }
 }
 
@@ -185,6 +222,7 @@ fn test_no_icmp_no_unicast_ipv4() {
         next_header: IpProtocol::Unknown(0x0c),
         payload_len: 0,
         hop_limit: 0x40,
+        traffic_class: 0,
     });
 
     let mut bytes = vec![0u8; 54];
@@ -224,6 +262,7 @@ fn test_no_icmp_no_unicast_ipv6() {
         next_header: IpProtocol::Unknown(0x0c),
         payload_len: 0,
         hop_limit: 0x40,
+        traffic_class: 0,
     });
 
     let mut bytes = vec![0u8; 54];
@@ -249,6 +288,7 @@ fn test_icmp_error_no_payload() {
         next_header: IpProtocol::Unknown(0x0c),
         payload_len: 0,
         hop_limit: 0x40,
+        traffic_class: 0,
     });
 
     let mut bytes = vec![0u8; 34];
@@ -265,6 +305,7 @@ fn test_icmp_error_no_payload() {
             next_header: IpProtocol::Unknown(12),
             payload_len: 0,
             hop_limit: 64,
+            traffic_class: 0,
         },
         data: &NO_BYTES,
     };
@@ -276,6 +317,7 @@ fn test_icmp_error_no_payload() {
             next_header: IpProtocol::Icmp,
             payload_len: icmp_repr.buffer_len(),
             hop_limit: 64,
+            traffic_class: 0,
         },
         icmp_repr,
     ));
@@ -300,6 +342,216 @@ fn test_icmp_error_no_payload() {
     );
 }
 
+#[test]
+#[cfg(feature = "proto-ipv4")]
+fn test_source_route_policy() {
+    let (mut iface, mut sockets, _device) = create(MEDIUM);
+
+    let repr = Ipv4Repr {
+        src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+        dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+        next_header: IpProtocol::Unknown(0x0c),
+        payload_len: 0,
+        hop_limit: 0x40,
+        traffic_class: 0,
+    };
+
+    // A loose source route option, followed by the end of the option list.
+    let mut bytes = vec![0u8; 24];
+    repr.emit(
+        &mut Ipv4Packet::new_unchecked(&mut bytes),
+        &ChecksumCapabilities::default(),
+    );
+    bytes[20..24].copy_from_slice(&[0x83, 3, 4, 0]);
+    let mut packet = Ipv4Packet::new_unchecked(&mut bytes);
+    packet.set_header_len(24);
+    packet.set_total_len(24);
+    packet.fill_checksum();
+    let frame = Ipv4Packet::new_unchecked(&bytes);
+
+    #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+    assert_eq!(iface.inner.process_ipv4(&mut sockets, &frame, None), None);
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    assert_eq!(
+        iface.inner.process_ipv4(
+            &mut sockets,
+            &frame,
+            Some(&mut iface.fragments.ipv4_fragments)
+        ),
+        None
+    );
+    assert_eq!(iface.drop_stats().count(DropReason::Filtered), 1);
+
+    let icmp_repr = Icmpv4Repr::DstUnreachable {
+        reason: Icmpv4DstUnreachable::SrcRouteFailed,
+        header: repr,
+        data: &[],
+    };
+    let expected_repr = IpPacket::Icmpv4((
+        Ipv4Repr {
+            src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+            dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+            next_header: IpProtocol::Icmp,
+            payload_len: icmp_repr.buffer_len(),
+            hop_limit: 64,
+            traffic_class: 0,
+        },
+        icmp_repr,
+    ));
+
+    iface.set_source_route_policy(SourceRoutePolicy::Reject);
+    #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+    assert_eq!(
+        iface.inner.process_ipv4(&mut sockets, &frame, None),
+        Some(expected_repr)
+    );
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    assert_eq!(
+        iface.inner.process_ipv4(
+            &mut sockets,
+            &frame,
+            Some(&mut iface.fragments.ipv4_fragments)
+        ),
+        Some(expected_repr)
+    );
+}
+
+#[test]
+#[cfg(feature = "proto-ipv4")]
+fn test_martian_source() {
+    let (mut iface, mut sockets, _device) = create(MEDIUM);
+    // Pretend the device is not a loopback one.
+    iface.inner.caps.loopback = false;
+    iface.update_ip_addrs(|addrs| {
+        addrs.clear();
+        addrs
+            .push(IpCidr::new(IpAddress::v4(192, 168, 1, 1), 24))
+            .unwrap();
+    });
+
+    let martian = |iface: &Interface, addr: Ipv4Address| iface.inner.is_martian_source(addr.into());
+    assert!(martian(&iface, Ipv4Address::UNSPECIFIED));
+    assert!(martian(&iface, Ipv4Address::BROADCAST));
+    assert!(martian(&iface, Ipv4Address([192, 168, 1, 255])));
+    assert!(martian(&iface, Ipv4Address([224, 0, 0, 1])));
+    assert!(martian(&iface, Ipv4Address([127, 0, 0, 1])));
+    assert!(martian(&iface, Ipv4Address([192, 168, 1, 1])));
+    assert!(!martian(&iface, Ipv4Address([192, 168, 1, 2])));
+
+    let repr = IpRepr::Ipv4(Ipv4Repr {
+        src_addr: Ipv4Address([192, 168, 1, 1]),
+        dst_addr: Ipv4Address([192, 168, 1, 1]),
+        next_header: IpProtocol::Unknown(0x0c),
+        payload_len: 0,
+        hop_limit: 0x40,
+        traffic_class: 0,
+    });
+    let mut bytes = vec![0u8; 20];
+    repr.emit(&mut bytes, &ChecksumCapabilities::default());
+    let frame = Ipv4Packet::new_unchecked(&bytes);
+
+    #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+    assert_eq!(iface.inner.process_ipv4(&mut sockets, &frame, None), None);
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    assert_eq!(
+        iface.inner.process_ipv4(
+            &mut sockets,
+            &frame,
+            Some(&mut iface.fragments.ipv4_fragments)
+        ),
+        None
+    );
+    assert_eq!(iface.drop_stats().count(DropReason::Martian), 1);
+
+    // A loopback address alone does not make the interface receive its own packets...
+    iface.update_ip_addrs(|addrs| {
+        addrs
+            .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
+            .unwrap();
+    });
+    assert!(martian(&iface, Ipv4Address([192, 168, 1, 1])));
+
+    // ... but a loopback device does.
+    iface.inner.caps.loopback = true;
+    assert!(!martian(&iface, Ipv4Address([127, 0, 0, 1])));
+    assert!(!martian(&iface, Ipv4Address([192, 168, 1, 1])));
+    assert!(martian(&iface, Ipv4Address([224, 0, 0, 1])));
+}
+
+#[test]
+#[cfg(feature = "proto-ipv4")]
+fn test_drop_bad_checksum() {
+    let (mut iface, mut sockets, _device) = create(MEDIUM);
+
+    let repr = IpRepr::Ipv4(Ipv4Repr {
+        src_addr: Ipv4Address([192, 168, 1, 2]),
+        dst_addr: Ipv4Address([192, 168, 1, 1]),
+        next_header: IpProtocol::Unknown(0x0c),
+        payload_len: 0,
+        hop_limit: 0x40,
+        traffic_class: 0,
+    });
+    let mut bytes = vec![0u8; 20];
+    repr.emit(&mut bytes, &ChecksumCapabilities::default());
+    // Corrupt the header checksum.
+    bytes[10] ^= 0xff;
+    let frame = Ipv4Packet::new_unchecked(&bytes);
+
+    #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+    assert_eq!(iface.inner.process_ipv4(&mut sockets, &frame, None), None);
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    assert_eq!(
+        iface.inner.process_ipv4(
+            &mut sockets,
+            &frame,
+            Some(&mut iface.fragments.ipv4_fragments)
+        ),
+        None
+    );
+    assert_eq!(iface.drop_stats().count(DropReason::Checksum), 1);
+    assert_eq!(iface.drop_stats().count(DropReason::Malformed), 0);
+}
+
+#[test]
+#[cfg(feature = "proto-ipv4")]
+fn test_deliver_zero_ttl() {
+    let (mut iface, mut sockets, _device) = create(MEDIUM);
+
+    // The TTL of datagrams addressed to the host is not checked, see RFC 1122 § 3.2.1.7;
+    // this one gets the usual answer to an unknown protocol.
+    let repr = IpRepr::Ipv4(Ipv4Repr {
+        src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+        dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+        next_header: IpProtocol::Unknown(0x0c),
+        payload_len: 0,
+        hop_limit: 0,
+        traffic_class: 0,
+    });
+    let mut bytes = vec![0u8; 20];
+    repr.emit(&mut bytes, &ChecksumCapabilities::default());
+    let frame = Ipv4Packet::new_unchecked(&bytes);
+
+    #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+    let reply = iface.inner.process_ipv4(&mut sockets, &frame, None);
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    let reply = iface.inner.process_ipv4(
+        &mut sockets,
+        &frame,
+        Some(&mut iface.fragments.ipv4_fragments),
+    );
+    assert!(matches!(
+        reply,
+        Some(IpPacket::Icmpv4((
+            _,
+            Icmpv4Repr::DstUnreachable {
+                reason: Icmpv4DstUnreachable::ProtoUnreachable,
+                ..
+            }
+        )))
+    ));
+    assert_eq!(iface.drop_stats().count(DropReason::HopLimit), 0);
+}
+
 #[test]
 #[cfg(feature = "proto-ipv4")]
 fn test_local_subnet_broadcasts() {
@@ -378,6 +630,7 @@ fn test_icmp_error_port_unreachable() {
         next_header: IpProtocol::Udp,
         payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
         hop_limit: 64,
+        traffic_class: 0,
     });
 
     // Emit the representations to a packet
@@ -402,6 +655,7 @@ fn test_icmp_error_port_unreachable() {
             next_header: IpProtocol::Udp,
             payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
             hop_limit: 64,
+            traffic_class: 0,
         },
         data,
     };
@@ -412,6 +666,7 @@ fn test_icmp_error_port_unreachable() {
             next_header: IpProtocol::Icmp,
             payload_len: icmp_repr.buffer_len(),
             hop_limit: 64,
+            traffic_class: 0,
         },
         icmp_repr,
     ));
@@ -431,6 +686,7 @@ fn test_icmp_error_port_unreachable() {
         next_header: IpProtocol::Udp,
         payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
         hop_limit: 64,
+        traffic_class: 0,
     });
 
     // Emit the representations to a packet
@@ -459,6 +715,67 @@ fn test_icmp_error_port_unreachable() {
     );
 }
 
+#[test]
+#[cfg(all(feature = "socket-udp", feature = "proto-ipv4"))]
+fn test_icmp_error_rate_limit() {
+    static UDP_PAYLOAD: [u8; 5] = [0x48, 0x65, 0x6c, 0x6c, 0x6f];
+    let (mut iface, mut sockets, _device) = create(MEDIUM);
+    iface.set_icmp_error_rate_limit(Some(IcmpRateLimit {
+        max_errors: 2,
+        interval: Duration::from_secs(1),
+    }));
+
+    let udp_repr = UdpRepr {
+        src_port: 67,
+        dst_port: 68,
+    };
+    let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+        src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+        dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+        next_header: IpProtocol::Udp,
+        payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
+        hop_limit: 64,
+        traffic_class: 0,
+    });
+
+    let mut udp_bytes = vec![0u8; udp_repr.header_len() + UDP_PAYLOAD.len()];
+    udp_repr.emit(
+        &mut UdpPacket::new_unchecked(&mut udp_bytes),
+        &ip_repr.src_addr(),
+        &ip_repr.dst_addr(),
+        UDP_PAYLOAD.len(),
+        |buf| buf.copy_from_slice(&UDP_PAYLOAD),
+        &ChecksumCapabilities::default(),
+    );
+
+    let mut port_unreachable = |iface: &mut Interface, secs| {
+        iface.inner.now = Instant::from_secs(secs);
+        iface
+            .inner
+            .process_udp(
+                &mut sockets,
+                ip_repr.clone(),
+                udp_repr,
+                false,
+                &UDP_PAYLOAD,
+                &udp_bytes,
+            )
+            .is_some()
+    };
+
+    // Only the first two errors of each interval are sent.
+    assert!(port_unreachable(&mut iface, 10));
+    assert!(port_unreachable(&mut iface, 10));
+    assert!(!port_unreachable(&mut iface, 10));
+    assert!(port_unreachable(&mut iface, 11));
+
+    // Without a limit, every error is sent.
+    iface.set_icmp_error_rate_limit(None);
+    for _ in 0..4 {
+        assert!(port_unreachable(&mut iface, 11));
+    }
+}
+
 #[test]
 #[cfg(feature = "socket-udp")]
 fn test_handle_udp_broadcast() {
@@ -495,6 +812,7 @@ fn test_handle_udp_broadcast() {
         next_header: IpProtocol::Udp,
         payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
         hop_limit: 0x40,
+        traffic_class: 0,
     });
     #[cfg(all(not(feature = "proto-ipv6"), feature = "proto-ipv4"))]
     let ip_repr = IpRepr::Ipv4(Ipv4Repr {
@@ -503,6 +821,7 @@ fn test_handle_udp_broadcast() {
         next_header: IpProtocol::Udp,
         payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
         hop_limit: 0x40,
+        traffic_class: 0,
     });
 
     // Bind the socket to port 68
@@ -544,68 +863,643 @@ fn test_handle_udp_broadcast() {
 }
 
 #[test]
-#[cfg(feature = "proto-ipv4")]
-fn test_handle_ipv4_broadcast() {
-    use crate::wire::{Icmpv4Packet, Icmpv4Repr, Ipv4Packet};
+#[cfg(all(feature = "socket-tcp", feature = "proto-ipv4"))]
+fn test_handle_tcp_listeners_on_same_port() {
+    fn syn(iface: &mut Interface, sockets: &mut SocketSet, src_port: u16) -> Option<TcpControl> {
+        let src_addr = IpAddress::v4(127, 0, 0, 2);
+        let dst_addr = IpAddress::v4(127, 0, 0, 1);
+        let tcp_repr = TcpRepr {
+            src_port,
+            dst_port: 80,
+            control: TcpControl::Syn,
+            ece: false,
+            cwr: false,
+            seq_number: TcpSeqNumber(1000),
+            ack_number: None,
+            window_len: 256,
+            window_scale: None,
+            max_seg_size: None,
+            sack_permitted: false,
+            sack_ranges: [None; 3],
+            timestamp: None,
+            md5_signature: None,
+            payload: &[],
+        };
+        let mut bytes = vec![0; tcp_repr.buffer_len()];
+        tcp_repr.emit(
+            &mut TcpPacket::new_unchecked(&mut bytes[..]),
+            &src_addr,
+            &dst_addr,
+            &ChecksumCapabilities::default(),
+        );
+        let ip_repr = IpRepr::new(src_addr, dst_addr, IpProtocol::Tcp, bytes.len(), 64);
+        match iface.inner.process_tcp(sockets, ip_repr, &bytes) {
+            Some(IpPacket::Tcp((_, reply))) => Some(reply.control),
+            _ => None,
+        }
+    }
 
     let (mut iface, mut sockets, _device) = create(MEDIUM);
 
-    let our_ipv4_addr = iface.ipv4_address().unwrap();
-    let src_ipv4_addr = Ipv4Address([127, 0, 0, 2]);
-
-    // ICMPv4 echo request
-    let icmpv4_data: [u8; 4] = [0xaa, 0x00, 0x00, 0xff];
-    let icmpv4_repr = Icmpv4Repr::EchoRequest {
-        ident: 0x1234,
-        seq_no: 0xabcd,
-        data: &icmpv4_data,
+    let socket = || {
+        tcp::Socket::new(
+            tcp::SocketBuffer::new(vec![0; 64]),
+            tcp::SocketBuffer::new(vec![0; 64]),
+        )
     };
+    let first = sockets.add(socket());
+    let second = sockets.add(socket());
+    for handle in [first, second] {
+        assert_eq!(sockets.get_mut::<tcp::Socket>(handle).listen(80), Ok(()));
+    }
 
-    // Send to IPv4 broadcast address
-    let ipv4_repr = Ipv4Repr {
-        src_addr: src_ipv4_addr,
-        dst_addr: Ipv4Address::BROADCAST,
-        next_header: IpProtocol::Icmp,
-        hop_limit: 64,
-        payload_len: icmpv4_repr.buffer_len(),
+    let assert_connections = |sockets: &SocketSet| {
+        for (handle, port) in [(first, 1000), (second, 1001)] {
+            let socket = sockets.get::<tcp::Socket>(handle);
+            assert_eq!(socket.state(), tcp::State::SynReceived);
+            assert_eq!(
+                socket.remote_endpoint().map(|endpoint| endpoint.port),
+                Some(port)
+            );
+        }
     };
 
-    // Emit to ip frame
-    let mut bytes = vec![0u8; ipv4_repr.buffer_len() + icmpv4_repr.buffer_len()];
-    let frame = {
-        ipv4_repr.emit(
-            &mut Ipv4Packet::new_unchecked(&mut bytes),
-            &ChecksumCapabilities::default(),
-        );
-        icmpv4_repr.emit(
-            &mut Icmpv4Packet::new_unchecked(&mut bytes[ipv4_repr.buffer_len()..]),
-            &ChecksumCapabilities::default(),
-        );
-        Ipv4Packet::new_unchecked(&bytes)
-    };
+    // Each connection attempt is taken by another socket listening on the port; the
+    // SYN-ACKs are only sent when the sockets are dispatched...
+    assert_eq!(syn(&mut iface, &mut sockets, 1000), None);
+    assert_eq!(syn(&mut iface, &mut sockets, 1001), None);
+    assert_connections(&sockets);
+
+    // ... a retransmitted SYN reaches the connection it already opened...
+    assert_eq!(syn(&mut iface, &mut sockets, 1000), None);
+    assert_connections(&sockets);
+
+    // ... and attempts are reset once no socket is left listening...
+    assert_eq!(syn(&mut iface, &mut sockets, 1002), Some(TcpControl::Rst));
+    assert_eq!(iface.drop_stats().count(DropReason::BacklogFull), 1);
+
+    // ... or dropped, so that the peer retries later.
+    iface.set_tcp_backlog_policy(BacklogPolicy::Drop);
+    assert_eq!(syn(&mut iface, &mut sockets, 1003), None);
+    assert_eq!(iface.drop_stats().count(DropReason::BacklogFull), 2);
+    assert_eq!(iface.drop_stats().count(DropReason::NoSocket), 0);
+}
 
-    // Expected ICMPv4 echo reply
-    let expected_icmpv4_repr = Icmpv4Repr::EchoReply {
-        ident: 0x1234,
-        seq_no: 0xabcd,
-        data: &icmpv4_data,
-    };
-    let expected_ipv4_repr = Ipv4Repr {
-        src_addr: our_ipv4_addr,
-        dst_addr: src_ipv4_addr,
-        next_header: IpProtocol::Icmp,
-        hop_limit: 64,
-        payload_len: expected_icmpv4_repr.buffer_len(),
+#[test]
+#[cfg(all(
+    feature = "socket-tcp",
+    feature = "socket-udp",
+    feature = "proto-ipv4",
+    any(feature = "medium-ethernet", feature = "medium-ip")
+))]
+fn test_ephemeral_port() {
+    let (mut iface, mut sockets, _device) = create(MEDIUM);
+    iface.set_ephemeral_port_range(50000..=50002);
+
+    let tcp_socket = || {
+        tcp::Socket::new(
+            tcp::SocketBuffer::new(vec![0; 64]),
+            tcp::SocketBuffer::new(vec![0; 64]),
+        )
     };
-    let expected_packet = IpPacket::Icmpv4((expected_ipv4_repr, expected_icmpv4_repr));
+    let client = sockets.add(tcp_socket());
+    let listener = sockets.add(tcp_socket());
+    let udp = sockets.add(udp::Socket::new(
+        udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]),
+        udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]),
+    ));
 
-    #[cfg(not(feature = "proto-ipv4-fragmentation"))]
-    assert_eq!(
-        iface.inner.process_ipv4(&mut sockets, &frame, None),
-        Some(expected_packet)
-    );
+    // Ports are handed out in turn...
+    let first = iface.ephemeral_port(&sockets).unwrap();
+    let second = iface.ephemeral_port(&sockets).unwrap();
+    assert!(iface.ephemeral_port_range().contains(&first));
+    assert!(iface.ephemeral_port_range().contains(&second));
+    assert_ne!(first, second);
+
+    // ... skipping those that sockets use...
+    sockets
+        .get_mut::<tcp::Socket>(client)
+        .connect(iface.context(), (IpAddress::v4(127, 0, 0, 2), 80), first)
+        .unwrap();
+    sockets.get_mut::<udp::Socket>(udp).bind(second).unwrap();
+    let third = iface.ephemeral_port(&sockets).unwrap();
+    assert!(third != first && third != second);
+    assert_eq!(iface.ephemeral_port(&sockets), Some(third));
+
+    // ... until none is left.
+    sockets
+        .get_mut::<tcp::Socket>(listener)
+        .listen(third)
+        .unwrap();
+    assert_eq!(iface.ephemeral_port(&sockets), None);
+}
 
-    #[cfg(feature = "proto-ipv4-fragmentation")]
+#[test]
+#[cfg(all(feature = "socket-tcp", feature = "proto-ipv4", feature = "medium-ip"))]
+fn test_tcp_burst() {
+    let (mut iface, mut sockets, mut device) = create_ip();
+    // Keep segments small so that the data below takes several of them.
+    iface.inner.caps.max_transmission_unit = 576;
+
+    let tcp_socket = || {
+        tcp::Socket::new(
+            tcp::SocketBuffer::new(vec![0; 8192]),
+            tcp::SocketBuffer::new(vec![0; 8192]),
+        )
+    };
+    let client = sockets.add(tcp_socket());
+    let server = sockets.add(tcp_socket());
+    sockets.get_mut::<tcp::Socket>(server).listen(80).unwrap();
+    sockets
+        .get_mut::<tcp::Socket>(client)
+        .connect(iface.context(), (IpAddress::v4(127, 0, 0, 1), 80), 49152)
+        .unwrap();
+    for _ in 0..3 {
+        iface
+            .poll(Instant::ZERO, &mut device, &mut sockets)
+            .unwrap();
+    }
+    assert_eq!(
+        sockets.get::<tcp::Socket>(client).state(),
+        tcp::State::Established
+    );
+
+    let socket = sockets.get_mut::<tcp::Socket>(client);
+    socket.set_nagle_enabled(false);
+    socket.send_slice(&[0xaa; 4096]).unwrap();
+
+    // All of the data leaves in a single pass over the sockets, which takes at
+    // least 8 segments of at most 536 octets.
+    assert!(iface.socket_egress(&mut device, &mut sockets));
+    let mut segments = 0;
+    while let Some((rx_token, _)) = device.receive() {
+        rx_token.consume(Instant::ZERO, |_| Ok(())).unwrap();
+        segments += 1;
+    }
+    assert!(segments >= 8);
+}
+
+#[test]
+#[cfg(all(feature = "socket-udp", feature = "proto-ipv4", feature = "medium-ip"))]
+fn test_udp_burst() {
+    // A device with a limited number of transmit buffers.
+    struct LimitedDevice {
+        inner: Loopback,
+        tx_budget: usize,
+    }
+
+    impl Device for LimitedDevice {
+        type RxToken<'a> = <Loopback as Device>::RxToken<'a>;
+        type TxToken<'a> = <Loopback as Device>::TxToken<'a>;
+
+        fn receive(&mut self) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+            self.inner.receive()
+        }
+
+        fn transmit(&mut self) -> Option<Self::TxToken<'_>> {
+            if self.tx_budget == 0 {
+                return None;
+            }
+            self.tx_budget -= 1;
+            self.inner.transmit()
+        }
+
+        fn capabilities(&self) -> DeviceCapabilities {
+            self.inner.capabilities()
+        }
+    }
+
+    let (mut iface, mut sockets, device) = create_ip();
+    let mut device = LimitedDevice {
+        inner: device,
+        tx_budget: 2,
+    };
+
+    let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+    let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 3], vec![0; 30]);
+    let handle = sockets.add(udp::Socket::new(rx_buffer, tx_buffer));
+    let socket = sockets.get_mut::<udp::Socket>(handle);
+    socket.bind(68).unwrap();
+    for _ in 0..3 {
+        socket
+            .send_slice(b"data", (IpAddress::v4(127, 0, 0, 1), 67).into())
+            .unwrap();
+    }
+
+    // The queued datagrams leave back to back, until the device runs out of buffers...
+    assert!(iface.socket_egress(&mut device, &mut sockets));
+    assert_eq!(recv_all(&mut device.inner, Instant::ZERO).len(), 2);
+
+    // ...and the rest leaves in the next pass.
+    device.tx_budget = 2;
+    assert!(iface.socket_egress(&mut device, &mut sockets));
+    assert_eq!(recv_all(&mut device.inner, Instant::ZERO).len(), 1);
+    assert!(!iface.socket_egress(&mut device, &mut sockets));
+}
+
+#[test]
+#[cfg(all(feature = "socket-tcp-md5", feature = "proto-ipv4"))]
+fn test_tcp_md5_signature() {
+    fn syn(
+        iface: &mut Interface,
+        sockets: &mut SocketSet,
+        key: Option<&[u8]>,
+    ) -> Option<TcpControl> {
+        let src_addr = IpAddress::v4(127, 0, 0, 2);
+        let dst_addr = IpAddress::v4(127, 0, 0, 1);
+        let mut tcp_repr = TcpRepr {
+            src_port: 1000,
+            dst_port: 80,
+            control: TcpControl::Syn,
+            ece: false,
+            cwr: false,
+            seq_number: TcpSeqNumber(1000),
+            ack_number: None,
+            window_len: 256,
+            window_scale: None,
+            max_seg_size: None,
+            sack_permitted: false,
+            sack_ranges: [None; 3],
+            timestamp: None,
+            md5_signature: None,
+            payload: &[],
+        };
+        if let Some(key) = key {
+            tcp_repr.md5_signature = Some([0; 16]);
+            tcp_repr.md5_signature =
+                Some(tcp_repr.compute_md5_signature(&src_addr, &dst_addr, key));
+        }
+        let mut bytes = vec![0; tcp_repr.buffer_len()];
+        tcp_repr.emit(
+            &mut TcpPacket::new_unchecked(&mut bytes[..]),
+            &src_addr,
+            &dst_addr,
+            &ChecksumCapabilities::default(),
+        );
+        let ip_repr = IpRepr::new(src_addr, dst_addr, IpProtocol::Tcp, bytes.len(), 64);
+        match iface.inner.process_tcp(sockets, ip_repr, &bytes) {
+            Some(IpPacket::Tcp((_, reply))) => Some(reply.control),
+            _ => None,
+        }
+    }
+
+    let (mut iface, mut sockets, _device) = create(MEDIUM);
+
+    let mut socket = tcp::Socket::new(
+        tcp::SocketBuffer::new(vec![0; 64]),
+        tcp::SocketBuffer::new(vec![0; 64]),
+    );
+    socket.set_md5_key(Some(b"secret"));
+    let handle = sockets.add(socket);
+    assert_eq!(sockets.get_mut::<tcp::Socket>(handle).listen(80), Ok(()));
+
+    // Unsigned segments, and segments signed with another key, are silently dropped...
+    assert_eq!(syn(&mut iface, &mut sockets, None), None);
+    assert_eq!(syn(&mut iface, &mut sockets, Some(b"wrong")), None);
+    assert_eq!(iface.drop_stats().count(DropReason::Filtered), 2);
+    assert_eq!(
+        sockets.get::<tcp::Socket>(handle).state(),
+        tcp::State::Listen
+    );
+
+    // ... while those signed with the key are accepted; the SYN-ACK is only sent when
+    // the socket is dispatched.
+    assert_eq!(syn(&mut iface, &mut sockets, Some(b"secret")), None);
+    assert_eq!(iface.drop_stats().count(DropReason::Filtered), 2);
+    assert_eq!(
+        sockets.get::<tcp::Socket>(handle).state(),
+        tcp::State::SynReceived
+    );
+}
+
+#[test]
+#[cfg(all(feature = "socket-udp", feature = "proto-ipv4"))]
+fn test_handle_udp_most_specific_socket() {
+    static UDP_PAYLOAD: [u8; 5] = [0x48, 0x65, 0x6c, 0x6c, 0x6f];
+
+    let (mut iface, mut sockets, _device) = create(MEDIUM);
+
+    let socket = || {
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        udp::Socket::new(rx_buffer, tx_buffer)
+    };
+    let any_handle = sockets.add(socket());
+    let specific_handle = sockets.add(socket());
+
+    let dst_ip = Ipv4Address::new(0x7f, 0x00, 0x00, 0x01);
+    assert_eq!(sockets.get_mut::<udp::Socket>(any_handle).bind(68), Ok(()));
+    assert_eq!(
+        sockets
+            .get_mut::<udp::Socket>(specific_handle)
+            .bind((dst_ip, 68)),
+        Ok(())
+    );
+
+    let udp_repr = UdpRepr {
+        src_port: 67,
+        dst_port: 68,
+    };
+    let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+        src_addr: Ipv4Address::new(0x7f, 0x00, 0x00, 0x02),
+        dst_addr: dst_ip,
+        next_header: IpProtocol::Udp,
+        payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
+        hop_limit: 0x40,
+        traffic_class: 0,
+    });
+
+    let mut udp_bytes = vec![0u8; 13];
+    let mut packet = UdpPacket::new_unchecked(&mut udp_bytes);
+    udp_repr.emit(
+        &mut packet,
+        &ip_repr.src_addr(),
+        &ip_repr.dst_addr(),
+        UDP_PAYLOAD.len(),
+        |buf| buf.copy_from_slice(&UDP_PAYLOAD),
+        &ChecksumCapabilities::default(),
+    );
+
+    // The packet is handled by the socket bound to the address, although it was added last.
+    assert_eq!(
+        iface.inner.process_udp(
+            &mut sockets,
+            ip_repr,
+            udp_repr,
+            false,
+            &UDP_PAYLOAD,
+            packet.into_inner(),
+        ),
+        None
+    );
+    assert!(!sockets.get_mut::<udp::Socket>(any_handle).can_recv());
+    assert!(sockets.get_mut::<udp::Socket>(specific_handle).can_recv());
+}
+
+#[test]
+#[cfg(all(
+    feature = "socket-udp",
+    feature = "proto-ipv4",
+    any(feature = "medium-ethernet", feature = "medium-ip")
+))]
+fn test_transmit_priority() {
+    struct PriorityDevice {
+        inner: Loopback,
+        priorities: Vec<Priority>,
+    }
+
+    impl Device for PriorityDevice {
+        type RxToken<'a> = <Loopback as Device>::RxToken<'a>;
+        type TxToken<'a> = <Loopback as Device>::TxToken<'a>;
+
+        fn receive(&mut self) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+            self.inner.receive()
+        }
+
+        fn transmit(&mut self) -> Option<Self::TxToken<'_>> {
+            self.transmit_with_priority(Priority::BEST_EFFORT)
+        }
+
+        fn transmit_with_priority(&mut self, priority: Priority) -> Option<Self::TxToken<'_>> {
+            self.priorities.push(priority);
+            self.inner.transmit()
+        }
+
+        fn capabilities(&self) -> DeviceCapabilities {
+            self.inner.capabilities()
+        }
+    }
+
+    let (mut iface, mut sockets, device) = create(MEDIUM);
+    let mut device = PriorityDevice {
+        inner: device,
+        priorities: Vec::new(),
+    };
+
+    let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+    let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 2], vec![0; 30]);
+    let handle = sockets.add(udp::Socket::new(rx_buffer, tx_buffer));
+    let socket = sockets.get_mut::<udp::Socket>(handle);
+    socket.bind(68).unwrap();
+    let mut metadata = udp::UdpMetadata::from(IpEndpoint::new(Ipv4Address::BROADCAST.into(), 67));
+    socket.send_slice(b"bulk", metadata.endpoint).unwrap();
+    // Expedited forwarding.
+    metadata.dscp = 46;
+    socket.send_with_metadata(4, metadata).unwrap();
+
+    // The priority of a packet is derived from its DSCP; both datagrams leave in the
+    // same pass over the sockets...
+    assert!(iface.socket_egress(&mut device, &mut sockets));
+    assert_eq!(device.priorities, [Priority::BEST_EFFORT, Priority(5)]);
+    assert!(!iface.socket_egress(&mut device, &mut sockets));
+
+    // ...unless the priority of the socket is set.
+    device.priorities.clear();
+    sockets.set_priority(handle, Some(Priority(6)));
+    assert_eq!(sockets.priority(handle), Some(Priority(6)));
+    let socket = sockets.get_mut::<udp::Socket>(handle);
+    socket.send_with_metadata(4, metadata).unwrap();
+    assert!(iface.socket_egress(&mut device, &mut sockets));
+    assert_eq!(device.priorities, [Priority(6)]);
+}
+
+#[test]
+#[cfg(all(
+    feature = "socket-udp",
+    feature = "proto-ipv4",
+    feature = "medium-ethernet"
+))]
+fn test_priority_mapping() {
+    use crate::phy::{Transformer, Vlan};
+
+    let (mut iface, mut sockets, device) = create(Medium::Ethernet);
+    let mut device = Transformer::new(device, Vlan::new(VlanTag::new(10)));
+
+    let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+    let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 2], vec![0; 30]);
+    let handle = sockets.add(udp::Socket::new(rx_buffer, tx_buffer));
+    sockets.set_priority(handle, Some(Priority(3)));
+    let mapping = PriorityMapping {
+        // Assured forwarding, class 3.
+        dscp: 26,
+        pcp: Priority(4),
+    };
+    iface.set_priority_mapping(Priority(3), Some(mapping));
+    assert_eq!(iface.priority_mapping(Priority(3)), Some(mapping));
+    assert_eq!(iface.priority_mapping(Priority(2)), None);
+
+    let socket = sockets.get_mut::<udp::Socket>(handle);
+    socket.bind(68).unwrap();
+    let mut metadata = udp::UdpMetadata::from(IpEndpoint::new(Ipv4Address::BROADCAST.into(), 67));
+    socket.send_slice(b"bulk", metadata.endpoint).unwrap();
+    // Expedited forwarding.
+    metadata.dscp = 46;
+    socket.send_with_metadata(4, metadata).unwrap();
+    assert!(iface.socket_egress(&mut device, &mut sockets));
+
+    // Packets without a DSCP are marked with the DSCP of the mapping, and both are
+    // tagged with its priority code point.
+    let frames = recv_all(device.get_mut(), Instant::ZERO);
+    let dscps: Vec<_> = frames
+        .iter()
+        .map(|frame| {
+            let frame = EthernetFrame::new_checked(&frame[..]).unwrap();
+            assert_eq!(frame.ethertype(), EthernetProtocol::Vlan);
+            let packet = VlanPacket::new_checked(frame.payload()).unwrap();
+            assert_eq!(packet.vlan_id(), 10);
+            assert_eq!(packet.priority(), 4);
+            Ipv4Packet::new_checked(packet.payload()).unwrap().dscp()
+        })
+        .collect();
+    assert_eq!(dscps, [26, 46]);
+}
+
+#[test]
+#[cfg(all(feature = "socket-udp", feature = "proto-igmp"))]
+fn test_udp_multicast_loop() {
+    let (mut iface, mut sockets, mut device) = create(MEDIUM);
+
+    let socket = || {
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        udp::Socket::new(rx_buffer, tx_buffer)
+    };
+    let sender_handle = sockets.add(socket());
+    let group_handle = sockets.add(socket());
+
+    let group = Ipv4Address::MULTICAST_ALL_SYSTEMS;
+    let sender = sockets.get_mut::<udp::Socket>(sender_handle);
+    assert_eq!(sender.bind(5353), Ok(()));
+    sender.set_multicast_loop(true);
+    sender.send_slice(b"Hello", (group, 5353).into()).unwrap();
+    assert_eq!(
+        sockets
+            .get_mut::<udp::Socket>(group_handle)
+            .bind((group, 5353)),
+        Ok(())
+    );
+
+    // The datagram is sent, and received by the socket bound to the group rather than
+    // by the sender, like a datagram from the network.
+    assert!(iface.socket_egress(&mut device, &mut sockets));
+    assert_eq!(recv_all(&mut device, Instant::ZERO).len(), 1);
+    assert!(!sockets.get_mut::<udp::Socket>(sender_handle).can_recv());
+    let (payload, endpoint) = sockets.get_mut::<udp::Socket>(group_handle).recv().unwrap();
+    assert_eq!(payload, b"Hello");
+    assert_eq!(endpoint.port, 5353);
+}
+
+#[test]
+#[cfg(all(feature = "socket-udp", feature = "medium-ip", feature = "proto-ipv4"))]
+fn test_socket_readiness() {
+    let (mut iface, mut sockets, mut device) = create_ip();
+
+    let socket = || {
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        udp::Socket::new(rx_buffer, tx_buffer)
+    };
+    let client_handle = sockets.add(socket());
+    let server_handle = sockets.add(socket());
+    assert_eq!(
+        sockets.get_mut::<udp::Socket>(server_handle).bind(67),
+        Ok(())
+    );
+    let client = sockets.get_mut::<udp::Socket>(client_handle);
+    assert_eq!(client.bind(68), Ok(()));
+    client
+        .send_slice(b"Hello", (IpAddress::v4(127, 0, 0, 1), 67).into())
+        .unwrap();
+    assert!(!sockets.get::<udp::Socket>(client_handle).can_send());
+
+    // The client can send again once its datagram left, and the server can receive it;
+    // the server could already send before the poll, which is not reported again.
+    assert_eq!(
+        iface.poll(Instant::ZERO, &mut device, &mut sockets),
+        Ok(true)
+    );
+    assert_eq!(
+        sockets.readiness().collect::<Vec<_>>(),
+        [
+            (client_handle, Readiness::WRITABLE),
+            (server_handle, Readiness::READABLE)
+        ]
+    );
+
+    // Nothing happens during the next poll.
+    assert_eq!(
+        iface.poll(Instant::ZERO, &mut device, &mut sockets),
+        Ok(false)
+    );
+    assert_eq!(sockets.readiness().count(), 0);
+    assert!(sockets.get::<udp::Socket>(server_handle).can_recv());
+}
+
+#[test]
+#[cfg(feature = "proto-ipv4")]
+fn test_handle_ipv4_broadcast() {
+    use crate::wire::{Icmpv4Packet, Icmpv4Repr, Ipv4Packet};
+
+    let (mut iface, mut sockets, _device) = create(MEDIUM);
+
+    let our_ipv4_addr = iface.ipv4_address().unwrap();
+    let src_ipv4_addr = Ipv4Address([127, 0, 0, 2]);
+
+    // ICMPv4 echo request
+    let icmpv4_data: [u8; 4] = [0xaa, 0x00, 0x00, 0xff];
+    let icmpv4_repr = Icmpv4Repr::EchoRequest {
+        ident: 0x1234,
+        seq_no: 0xabcd,
+        data: &icmpv4_data,
+    };
+
+    // Send to IPv4 broadcast address
+    let ipv4_repr = Ipv4Repr {
+        src_addr: src_ipv4_addr,
+        dst_addr: Ipv4Address::BROADCAST,
+        next_header: IpProtocol::Icmp,
+        hop_limit: 64,
+        payload_len: icmpv4_repr.buffer_len(),
+        traffic_class: 0,
+    };
+
+    // Emit to ip frame
+    let mut bytes = vec![0u8; ipv4_repr.buffer_len() + icmpv4_repr.buffer_len()];
+    let frame = {
+        ipv4_repr.emit(
+            &mut Ipv4Packet::new_unchecked(&mut bytes),
+            &ChecksumCapabilities::default(),
+        );
+        icmpv4_repr.emit(
+            &mut Icmpv4Packet::new_unchecked(&mut bytes[ipv4_repr.buffer_len()..]),
+            &ChecksumCapabilities::default(),
+        );
+        Ipv4Packet::new_unchecked(&bytes)
+    };
+
+    // Expected ICMPv4 echo reply
+    let expected_icmpv4_repr = Icmpv4Repr::EchoReply {
+        ident: 0x1234,
+        seq_no: 0xabcd,
+        data: &icmpv4_data,
+    };
+    let expected_ipv4_repr = Ipv4Repr {
+        src_addr: our_ipv4_addr,
+        dst_addr: src_ipv4_addr,
+        next_header: IpProtocol::Icmp,
+        hop_limit: 64,
+        payload_len: expected_icmpv4_repr.buffer_len(),
+        traffic_class: 0,
+    };
+    let expected_packet = IpPacket::Icmpv4((expected_ipv4_repr, expected_icmpv4_repr));
+
+    #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+    assert_eq!(
+        iface.inner.process_ipv4(&mut sockets, &frame, None),
+        Some(expected_packet)
+    );
+
+    #[cfg(feature = "proto-ipv4-fragmentation")]
     assert_eq!(
         iface.inner.process_ipv4(
             &mut sockets,
@@ -665,6 +1559,7 @@ fn test_icmp_reply_size() {
         next_header: IpProtocol::Udp,
         hop_limit: 64,
         payload_len: udp_repr.header_len() + MAX_PAYLOAD_LEN,
+        traffic_class: 0,
     };
     #[cfg(feature = "proto-ipv6")]
     let ip_repr = Ipv6Repr {
@@ -673,6 +1568,7 @@ fn test_icmp_reply_size() {
         next_header: IpProtocol::Udp,
         hop_limit: 64,
         payload_len: udp_repr.header_len() + MAX_PAYLOAD_LEN,
+        traffic_class: 0,
     };
     let payload = packet.into_inner();
 
@@ -690,6 +1586,7 @@ fn test_icmp_reply_size() {
         next_header: IpProtocol::Icmpv6,
         hop_limit: 64,
         payload_len: expected_icmp_repr.buffer_len(),
+        traffic_class: 0,
     };
     #[cfg(all(feature = "proto-ipv4", not(feature = "proto-ipv6")))]
     let expected_icmp_repr = Icmpv4Repr::DstUnreachable {
@@ -704,6 +1601,7 @@ fn test_icmp_reply_size() {
         next_header: IpProtocol::Icmp,
         hop_limit: 64,
         payload_len: expected_icmp_repr.buffer_len(),
+        traffic_class: 0,
     };
 
     // The expected packet does not exceed the IPV4_MIN_MTU
@@ -819,6 +1717,7 @@ fn test_handle_valid_ndisc_request() {
         next_header: IpProtocol::Icmpv6,
         hop_limit: 0xff,
         payload_len: solicit.buffer_len(),
+        traffic_class: 0,
     });
 
     let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
@@ -845,6 +1744,7 @@ fn test_handle_valid_ndisc_request() {
         next_header: IpProtocol::Icmpv6,
         hop_limit: 0xff,
         payload_len: icmpv6_expected.buffer_len(),
+        traffic_class: 0,
     };
 
     // Ensure an Neighbor Solicitation triggers a Neighbor Advertisement
@@ -909,7 +1809,7 @@ fn test_handle_other_arp_request() {
             &IpAddress::Ipv4(Ipv4Address([0x7f, 0x00, 0x00, 0x01])),
             &IpAddress::Ipv4(remote_ip_addr)
         ),
-        Err(Error::Unaddressable)
+        Err(Error::Unaddressable(Layer::Link))
     );
 }
 
@@ -982,6 +1882,86 @@ fn test_arp_flush_after_update_ip() {
     assert!(!iface.inner.has_neighbor(&IpAddress::Ipv4(remote_ip_addr)));
 }
 
+#[test]
+#[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+fn test_acd() {
+    let (mut iface, mut sockets, mut device) = create_ethernet();
+    iface.set_acd_enabled(true);
+
+    let local_hw_addr = EthernetAddress([0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
+    let cidr = Ipv4Cidr::new(Ipv4Address([192, 168, 1, 2]), 24);
+    iface.update_ip_addrs(|addrs| addrs.push(IpCidr::Ipv4(cidr)).unwrap());
+    assert!(!iface.has_ip_addr(cidr.address()));
+
+    // The probes looped back by the device do not conflict with the address.
+    for millis in (0..10_000).step_by(100) {
+        let timestamp = Instant::from_millis(millis);
+        iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+    }
+    assert!(iface.has_ip_addr(cidr.address()));
+    assert_eq!(iface.poll_at(Instant::from_secs(10), &sockets), None);
+
+    let mut arp_frame = |iface: &mut Interface, repr: ArpRepr| {
+        let mut eth_bytes = vec![0u8; 42];
+        let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+        frame.set_dst_addr(EthernetAddress::BROADCAST);
+        frame.set_src_addr(remote_hw_addr);
+        frame.set_ethertype(EthernetProtocol::Arp);
+        repr.emit(&mut ArpPacket::new_unchecked(frame.payload_mut()));
+        iface
+            .inner
+            .process_ethernet(&mut sockets, &eth_bytes, &mut iface.fragments)
+            .map(|packet| match packet {
+                EthernetPacket::Arp(repr) => repr,
+                _ => unreachable!(),
+            })
+    };
+
+    // The probes of other nodes are answered.
+    assert_eq!(
+        arp_frame(
+            &mut iface,
+            ArpRepr::EthernetIpv4 {
+                operation: ArpOperation::Request,
+                source_hardware_addr: remote_hw_addr,
+                source_protocol_addr: Ipv4Address::UNSPECIFIED,
+                target_hardware_addr: EthernetAddress::default(),
+                target_protocol_addr: cidr.address(),
+            }
+        ),
+        Some(ArpRepr::EthernetIpv4 {
+            operation: ArpOperation::Reply,
+            source_hardware_addr: local_hw_addr,
+            source_protocol_addr: cidr.address(),
+            target_hardware_addr: remote_hw_addr,
+            target_protocol_addr: Ipv4Address::UNSPECIFIED,
+        })
+    );
+
+    // The address is defended once, and given up when claimed again.
+    let claim = ArpRepr::EthernetIpv4 {
+        operation: ArpOperation::Request,
+        source_hardware_addr: remote_hw_addr,
+        source_protocol_addr: cidr.address(),
+        target_hardware_addr: EthernetAddress::default(),
+        target_protocol_addr: cidr.address(),
+    };
+    arp_frame(&mut iface, claim);
+    assert!(iface.has_ip_addr(cidr.address()));
+    assert_eq!(iface.acd_conflict(), None);
+    arp_frame(&mut iface, claim);
+    assert!(!iface.has_ip_addr(cidr.address()));
+    assert_eq!(
+        iface.acd_conflict(),
+        Some(AcdConflict {
+            cidr,
+            hardware_addr: remote_hw_addr,
+            was_assigned: true,
+        })
+    );
+}
+
 #[test]
 #[cfg(all(feature = "socket-icmp", feature = "proto-ipv4"))]
 fn test_icmpv4_socket() {
@@ -1021,67 +2001,381 @@ fn test_icmpv4_socket() {
         next_header: IpProtocol::Icmp,
         payload_len: 24,
         hop_limit: 64,
+        traffic_class: 0,
     };
     let ip_repr = IpRepr::Ipv4(ipv4_repr);
 
-    // Open a socket and ensure the packet is handled due to the listening
-    // socket.
-    assert!(!sockets.get_mut::<icmp::Socket>(socket_handle).can_recv());
+    // Open a socket and ensure the packet is handled due to the listening
+    // socket.
+    assert!(!sockets.get_mut::<icmp::Socket>(socket_handle).can_recv());
+
+    // Confirm we still get EchoReply from `smoltcp` even with the ICMP socket listening
+    let echo_reply = Icmpv4Repr::EchoReply {
+        ident,
+        seq_no,
+        data: echo_data,
+    };
+    let ipv4_reply = Ipv4Repr {
+        src_addr: ipv4_repr.dst_addr,
+        dst_addr: ipv4_repr.src_addr,
+        ..ipv4_repr
+    };
+    assert_eq!(
+        iface.inner.process_icmpv4(&mut sockets, ip_repr, icmp_data),
+        Some(IpPacket::Icmpv4((ipv4_reply, echo_reply)))
+    );
+
+    let socket = sockets.get_mut::<icmp::Socket>(socket_handle);
+    assert!(socket.can_recv());
+    assert_eq!(
+        socket.recv(),
+        Ok((
+            icmp_data,
+            IpAddress::Ipv4(Ipv4Address::new(0x7f, 0x00, 0x00, 0x02))
+        ))
+    );
+}
+
+#[test]
+#[cfg(feature = "proto-ipv4")]
+fn test_icmpv4_echo_reply_disabled() {
+    use crate::wire::Icmpv4Packet;
+
+    let (mut iface, mut sockets, _device) = create(MEDIUM);
+
+    let echo_data = &[0xff; 16];
+    let mut bytes = [0xff; 24];
+    let mut packet = Icmpv4Packet::new_unchecked(&mut bytes[..]);
+    let echo_repr = Icmpv4Repr::EchoRequest {
+        ident: 0x1234,
+        seq_no: 0x5432,
+        data: echo_data,
+    };
+    echo_repr.emit(&mut packet, &ChecksumCapabilities::default());
+    let icmp_data = &*packet.into_inner();
+
+    let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+        src_addr: Ipv4Address::new(0x7f, 0x00, 0x00, 0x02),
+        dst_addr: Ipv4Address::new(0x7f, 0x00, 0x00, 0x01),
+        next_header: IpProtocol::Icmp,
+        payload_len: 24,
+        hop_limit: 64,
+        traffic_class: 0,
+    });
+
+    iface.set_echo_reply_max_len(Some(15));
+    assert_eq!(
+        iface
+            .inner
+            .process_icmpv4(&mut sockets, ip_repr.clone(), icmp_data),
+        None
+    );
+
+    iface.set_echo_reply_max_len(Some(16));
+    assert!(iface
+        .inner
+        .process_icmpv4(&mut sockets, ip_repr.clone(), icmp_data)
+        .is_some());
+
+    iface.set_echo_reply(false);
+    assert_eq!(
+        iface.inner.process_icmpv4(&mut sockets, ip_repr, icmp_data),
+        None
+    );
+}
+
+#[test]
+#[cfg(feature = "proto-ipv6")]
+fn test_solicited_node_addrs() {
+    let (mut iface, _, _device) = create(MEDIUM);
+    let mut new_addrs = heapless::Vec::<IpCidr, MAX_IP_ADDR_COUNT>::new();
+    new_addrs
+        .push(IpCidr::new(IpAddress::v6(0xfe80, 0, 0, 0, 1, 2, 0, 2), 64))
+        .unwrap();
+    new_addrs
+        .push(IpCidr::new(
+            IpAddress::v6(0xfe80, 0, 0, 0, 3, 4, 0, 0xffff),
+            64,
+        ))
+        .unwrap();
+    iface.update_ip_addrs(|addrs| {
+        new_addrs.extend(addrs.to_vec());
+        *addrs = new_addrs;
+    });
+    assert!(iface
+        .inner
+        .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0x0002)));
+    assert!(iface
+        .inner
+        .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0xffff)));
+    assert!(!iface
+        .inner
+        .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0x0003)));
+}
+
+#[test]
+#[cfg(all(feature = "medium-ethernet", feature = "proto-ipv6"))]
+fn test_slaac() {
+    use crate::iface::SlaacAddressState;
+
+    let (mut iface, _, _device) = create_ethernet();
+    let router = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 0x1234);
+    let addr = Ipv6Address::new(0x2001, 0xdb8, 0, 1, 0x0200, 0x00ff, 0xfe00, 0);
+    iface.set_slaac_enabled(true);
+
+    let ndisc_packet = |src_addr, dst_addr, repr| {
+        let icmp_repr = Icmpv6Repr::Ndisc(repr);
+        let ip_repr = Ipv6Repr {
+            src_addr,
+            dst_addr,
+            next_header: IpProtocol::Icmpv6,
+            payload_len: icmp_repr.buffer_len(),
+            hop_limit: 0xff,
+            traffic_class: 0,
+        };
+        Some(IpPacket::Icmpv6((ip_repr, icmp_repr)))
+    };
+    let router_advert = NdiscRepr::RouterAdvert {
+        hop_limit: 64,
+        flags: NdiscRouterFlags::empty(),
+        router_lifetime: Duration::from_secs(1800),
+        reachable_time: Duration::ZERO,
+        retrans_time: Duration::ZERO,
+        lladdr: None,
+        mtu: None,
+        prefix_info: Some(NdiscPrefixInformation {
+            prefix_len: 64,
+            flags: NdiscPrefixInfoFlags::ON_LINK | NdiscPrefixInfoFlags::ADDRCONF,
+            valid_lifetime: Duration::from_secs(3600),
+            preferred_lifetime: Duration::from_secs(1800),
+            prefix: Ipv6Address::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 0),
+        }),
+    };
+    let ra_ip_repr = Ipv6Repr {
+        src_addr: router,
+        dst_addr: Ipv6Address::LINK_LOCAL_ALL_NODES,
+        next_header: IpProtocol::Icmpv6,
+        payload_len: 64,
+        hop_limit: 0xff,
+        traffic_class: 0,
+    };
+    let probe = NdiscRepr::NeighborSolicit {
+        target_addr: addr,
+        lladdr: None,
+    };
+
+    // Without a link-local address, routers are solicited from the unspecified address.
+    assert_eq!(
+        iface.inner.slaac_poll(),
+        ndisc_packet(
+            Ipv6Address::UNSPECIFIED,
+            Ipv6Address::LINK_LOCAL_ALL_ROUTERS,
+            NdiscRepr::RouterSolicit { lladdr: None }
+        )
+    );
+    assert_eq!(iface.inner.slaac_poll(), None);
+
+    assert_eq!(iface.inner.process_ndisc(ra_ip_repr, router_advert), None);
+    assert_eq!(
+        iface.inner.routes.lookup(
+            &IpAddress::v6(0x2001, 0xdb8, 0, 2, 0, 0, 0, 1),
+            iface.inner.now
+        ),
+        Some(router.into())
+    );
+    assert_eq!(
+        iface.inner.slaac_poll(),
+        ndisc_packet(Ipv6Address::UNSPECIFIED, addr.solicited_node(), probe)
+    );
+
+    // Another node answers the probe, so the address is abandoned.
+    let advert = NdiscRepr::NeighborAdvert {
+        flags: NdiscNeighborFlags::OVERRIDE,
+        target_addr: addr,
+        lladdr: None,
+    };
+    let na_ip_repr = Ipv6Repr {
+        src_addr: addr,
+        ..ra_ip_repr
+    };
+    assert_eq!(iface.inner.process_ndisc(na_ip_repr, advert), None);
+    assert!(iface.slaac_addrs().is_empty());
+
+    // Nobody answers the second probe, so the address is assigned.
+    assert_eq!(iface.inner.process_ndisc(ra_ip_repr, router_advert), None);
+    assert_eq!(
+        iface.inner.slaac_poll(),
+        ndisc_packet(Ipv6Address::UNSPECIFIED, addr.solicited_node(), probe)
+    );
+    iface.inner.now = Instant::from_secs(1);
+    assert_eq!(iface.inner.slaac_poll(), None);
+    assert!(iface.has_ip_addr(addr));
+    assert_eq!(iface.slaac_addrs()[0].state, SlaacAddressState::Assigned);
+
+    // Until its valid lifetime expires.
+    iface.inner.now = Instant::from_secs(3600);
+    assert_eq!(iface.inner.slaac_poll(), None);
+    assert!(!iface.has_ip_addr(addr));
+    assert!(iface.slaac_addrs().is_empty());
+}
+
+#[test]
+#[cfg(feature = "medium-ppp")]
+fn test_ppp() {
+    use super::ppp::PppPacket;
+    use crate::iface::ppp::Message;
+    use crate::iface::{PppIpv4Config, PppLinkState};
+
+    let (mut iface, mut sockets, mut device) = create(Medium::Ppp);
+    let addr = Ipv4Address::new(10, 0, 0, 2);
+    let peer_addr = Ipv4Address::new(10, 0, 0, 1);
+    let dns_server = Ipv4Address::new(10, 0, 0, 53);
+
+    let control_frame = |protocol, code, identifier, data: &[u8]| {
+        let repr = PppControlRepr {
+            code,
+            identifier,
+            data,
+        };
+        let mut bytes = vec![0; PPP_HEADER_LEN + repr.buffer_len()];
+        let mut frame = PppFrame::new_unchecked(&mut bytes);
+        frame.set_protocol(protocol);
+        repr.emit(&mut PppControlPacket::new_unchecked(frame.payload_mut()));
+        bytes
+    };
+    let ack = |protocol, code, identifier, data: &[u8]| {
+        let mut message = Message {
+            protocol,
+            code,
+            identifier,
+            data: heapless::Vec::new(),
+        };
+        message.data.extend_from_slice(data).unwrap();
+        Some(PppPacket::Control(message))
+    };
+
+    // The interface starts negotiating the link on its own.
+    assert_eq!(iface.ppp_link_state(), Some(PppLinkState::Establishing));
+    assert_eq!(
+        iface.poll(Instant::ZERO, &mut device, &mut sockets),
+        Ok(false)
+    );
+    let frames = recv_all(&mut device, Instant::ZERO);
+    assert_eq!(frames.len(), 1);
+    let frame = PppFrame::new_checked(&frames[0][..]).unwrap();
+    assert_eq!(frame.protocol(), PppProtocol::Lcp);
+    let packet = PppControlPacket::new_checked(frame.payload()).unwrap();
+    let request = PppControlRepr::parse(&packet).unwrap();
+    assert_eq!(request.code, PppControlCode::ConfigureRequest);
+
+    let lcp_ack = control_frame(
+        PppProtocol::Lcp,
+        PppControlCode::ConfigureAck,
+        request.identifier,
+        request.data,
+    );
+    assert_eq!(
+        iface
+            .inner
+            .process_ppp(&mut sockets, &lcp_ack, &mut iface.fragments),
+        None
+    );
+    let lcp_request = control_frame(PppProtocol::Lcp, PppControlCode::ConfigureRequest, 1, &[]);
+    assert_eq!(
+        iface
+            .inner
+            .process_ppp(&mut sockets, &lcp_request, &mut iface.fragments),
+        ack(PppProtocol::Lcp, PppControlCode::ConfigureAck, 1, &[])
+    );
+    assert_eq!(iface.ppp_link_state(), Some(PppLinkState::Negotiating));
+
+    // IPv4 packets are discarded until IPCP is opened.
+    let ipv4_frame = [0x00, 0x21, 0x45, 0x00, 0x00, 0x14];
+    assert_eq!(
+        iface
+            .inner
+            .process_ppp(&mut sockets, &ipv4_frame, &mut iface.fragments),
+        None
+    );
+    assert_eq!(iface.drop_stats().count(DropReason::Filtered), 1);
+
+    // The peer assigns the address with a Configure-Nak.
+    let request = iface.inner.ppp_poll().unwrap();
+    assert_eq!(request.protocol, PppProtocol::Ipcp);
+    assert_eq!(&request.data[..6], &[0x03, 0x06, 0, 0, 0, 0]);
+    let mut nak = vec![0x03, 0x06];
+    nak.extend_from_slice(addr.as_bytes());
+    nak.extend_from_slice(&[0x81, 0x06]);
+    nak.extend_from_slice(dns_server.as_bytes());
+    let ipcp_nak = control_frame(
+        PppProtocol::Ipcp,
+        PppControlCode::ConfigureNak,
+        request.identifier,
+        &nak,
+    );
+    assert_eq!(
+        iface
+            .inner
+            .process_ppp(&mut sockets, &ipcp_nak, &mut iface.fragments),
+        None
+    );
+    let mut options = vec![0x03, 0x06];
+    options.extend_from_slice(peer_addr.as_bytes());
+    let ipcp_request = control_frame(
+        PppProtocol::Ipcp,
+        PppControlCode::ConfigureRequest,
+        1,
+        &options,
+    );
+    assert_eq!(
+        iface
+            .inner
+            .process_ppp(&mut sockets, &ipcp_request, &mut iface.fragments),
+        ack(PppProtocol::Ipcp, PppControlCode::ConfigureAck, 1, &options)
+    );
 
-    // Confirm we still get EchoReply from `smoltcp` even with the ICMP socket listening
-    let echo_reply = Icmpv4Repr::EchoReply {
-        ident,
-        seq_no,
-        data: echo_data,
-    };
-    let ipv4_reply = Ipv4Repr {
-        src_addr: ipv4_repr.dst_addr,
-        dst_addr: ipv4_repr.src_addr,
-        ..ipv4_repr
-    };
+    let request = iface.inner.ppp_poll().unwrap();
+    assert_eq!(&request.data[..6], &nak[..6]);
+    let ipcp_ack = control_frame(
+        PppProtocol::Ipcp,
+        PppControlCode::ConfigureAck,
+        request.identifier,
+        &request.data,
+    );
     assert_eq!(
-        iface.inner.process_icmpv4(&mut sockets, ip_repr, icmp_data),
-        Some(IpPacket::Icmpv4((ipv4_reply, echo_reply)))
+        iface
+            .inner
+            .process_ppp(&mut sockets, &ipcp_ack, &mut iface.fragments),
+        None
     );
 
-    let socket = sockets.get_mut::<icmp::Socket>(socket_handle);
-    assert!(socket.can_recv());
+    // The negotiated address and a default route over the link are configured.
+    assert_eq!(iface.ppp_link_state(), Some(PppLinkState::Opened));
     assert_eq!(
-        socket.recv(),
-        Ok((
-            icmp_data,
-            IpAddress::Ipv4(Ipv4Address::new(0x7f, 0x00, 0x00, 0x02))
-        ))
+        iface.ppp_ipv4_config(),
+        Some(PppIpv4Config {
+            address: addr,
+            peer_address: Some(peer_addr),
+            dns_servers: heapless::Vec::from_slice(&[dns_server]).unwrap(),
+        })
+    );
+    assert!(iface.has_ip_addr(addr));
+    assert_eq!(
+        iface
+            .inner
+            .route(&IpAddress::v4(192, 0, 2, 1), Instant::ZERO),
+        Ok(IpAddress::Ipv4(peer_addr))
     );
-}
 
-#[test]
-#[cfg(feature = "proto-ipv6")]
-fn test_solicited_node_addrs() {
-    let (mut iface, _, _device) = create(MEDIUM);
-    let mut new_addrs = heapless::Vec::<IpCidr, MAX_IP_ADDR_COUNT>::new();
-    new_addrs
-        .push(IpCidr::new(IpAddress::v6(0xfe80, 0, 0, 0, 1, 2, 0, 2), 64))
-        .unwrap();
-    new_addrs
-        .push(IpCidr::new(
-            IpAddress::v6(0xfe80, 0, 0, 0, 3, 4, 0, 0xffff),
-            64,
-        ))
-        .unwrap();
-    iface.update_ip_addrs(|addrs| {
-        new_addrs.extend(addrs.to_vec());
-        *addrs = new_addrs;
-    });
-    assert!(iface
-        .inner
-        .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0x0002)));
+    // Restarting the negotiation removes them.
+    iface.ppp_restart();
+    assert_eq!(iface.ppp_link_state(), Some(PppLinkState::Establishing));
+    assert_eq!(iface.ppp_ipv4_config(), None);
+    assert!(!iface.has_ip_addr(addr));
     assert!(iface
         .inner
-        .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0xffff)));
-    assert!(!iface
-        .inner
-        .has_solicited_node(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff00, 0x0003)));
+        .route(&IpAddress::v4(192, 0, 2, 1), Instant::ZERO)
+        .is_err());
 }
 
 #[test]
@@ -1099,6 +2393,7 @@ fn test_icmpv6_nxthdr_unknown() {
         next_header: IpProtocol::HopByHop,
         payload_len: 12,
         hop_limit: 0x40,
+        traffic_class: 0,
     };
 
     let mut bytes = vec![0; 52];
@@ -1137,6 +2432,7 @@ fn test_icmpv6_nxthdr_unknown() {
         next_header: IpProtocol::Icmpv6,
         payload_len: reply_icmp_repr.buffer_len(),
         hop_limit: 0x40,
+        traffic_class: 0,
     };
 
     // Ensure the unknown next header causes a ICMPv6 Parameter Problem
@@ -1166,6 +2462,11 @@ fn test_handle_igmp() {
                     Medium::Ip => Ipv4Packet::new_checked(&frame[..]).ok()?,
                     #[cfg(feature = "medium-ieee802154")]
                     Medium::Ieee802154 => todo!(),
+                    #[cfg(feature = "medium-ppp")]
+                    Medium::Ppp => {
+                        let ppp_frame = PppFrame::new_checked(&frame[..]).ok()?;
+                        Ipv4Packet::new_checked(ppp_frame.payload()).ok()?
+                    }
                 };
                 let ipv4_repr = Ipv4Repr::parse(&ipv4_packet, checksum_caps).ok()?;
                 let ip_payload = ipv4_packet.payload();
@@ -1288,6 +2589,7 @@ fn test_raw_socket_no_reply() {
         next_header: IpProtocol::Udp,
         hop_limit: 64,
         payload_len: udp_repr.header_len() + PAYLOAD_LEN,
+        traffic_class: 0,
     };
 
     // Emit to frame
@@ -1379,6 +2681,7 @@ fn test_raw_socket_with_udp_socket() {
         next_header: IpProtocol::Udp,
         hop_limit: 64,
         payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
+        traffic_class: 0,
     };
 
     // Emit to frame
@@ -1560,6 +2863,7 @@ fn test_echo_request_sixlowpan_128_bytes() {
                 next_header: IpProtocol::Icmpv6,
                 payload_len: 136,
                 hop_limit: 64,
+                traffic_class: 0,
             },
             Icmpv6Repr::EchoReply {
                 ident: 39,
@@ -1736,6 +3040,7 @@ fn test_sixlowpan_udp_with_fragmentation() {
                     next_header: IpProtocol::Udp,
                     payload_len: udp_data.len(),
                     hop_limit: 64,
+                    traffic_class: 0,
                 }),
                 UdpRepr {
                     src_port: 1234,
@@ -1777,3 +3082,509 @@ fn test_sixlowpan_udp_with_fragmentation() {
         ]
     );
 }
+
+#[test]
+#[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+fn test_drop_stats() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static HOOK_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    fn hook(reason: DropReason, frame: &[u8]) {
+        match reason {
+            DropReason::Filtered => assert_eq!(frame.len(), 64),
+            DropReason::Truncated => assert_eq!(frame.len(), 4),
+            _ => unreachable!(),
+        }
+        HOOK_CALLS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    let (mut iface, mut sockets, mut device) = create_ethernet();
+    iface.set_drop_hook(Some(hook));
+
+    // An Ethernet frame directed to some other host.
+    let mut frame = vec![0u8; 64];
+    {
+        let mut eth_frame = EthernetFrame::new_unchecked(&mut frame[..]);
+        eth_frame.set_dst_addr(EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]));
+        eth_frame.set_src_addr(EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x02]));
+        eth_frame.set_ethertype(EthernetProtocol::Ipv4);
+    }
+    device.queue.push_back(frame);
+
+    // A frame too short to hold an Ethernet header.
+    device.queue.push_back(vec![0u8; 4]);
+
+    iface
+        .poll(Instant::from_millis(0), &mut device, &mut sockets)
+        .unwrap();
+
+    assert_eq!(iface.drop_stats().count(DropReason::Filtered), 1);
+    assert_eq!(iface.drop_stats().count(DropReason::Truncated), 1);
+    assert_eq!(iface.drop_stats().total(), 2);
+    assert_eq!(HOOK_CALLS.load(Ordering::SeqCst), 2);
+
+    iface.reset_drop_stats();
+    assert_eq!(iface.drop_stats().total(), 0);
+}
+
+#[test]
+#[cfg(all(feature = "stats", feature = "medium-ethernet", feature = "proto-ipv4"))]
+fn test_stats() {
+    let (mut iface, mut sockets, mut device) = create_ethernet();
+
+    // Dropped frames are counted as received too.
+    device.queue.push_back(vec![0u8; 4]);
+    iface
+        .poll(Instant::from_millis(0), &mut device, &mut sockets)
+        .unwrap();
+    assert_eq!(iface.stats().rx_packets, 1);
+    assert_eq!(iface.stats().rx_bytes, 4);
+    assert_eq!(iface.stats().tx_packets, 0);
+
+    // Resolving an address missing from the neighbor cache sends an ARP request.
+    assert_eq!(
+        iface.inner.lookup_hardware_addr(
+            MockTxToken,
+            &IpAddress::v4(127, 0, 0, 1),
+            &IpAddress::v4(127, 0, 0, 3)
+        ),
+        Err(Error::Unaddressable(Layer::Link))
+    );
+    assert_eq!(iface.stats().neighbor_misses, 1);
+    assert_eq!(iface.stats().tx_packets, 1);
+    assert_eq!(iface.stats().tx_bytes, 42);
+
+    iface.reset_stats();
+    assert_eq!(*iface.stats(), Stats::default());
+}
+
+#[test]
+#[cfg(all(feature = "medium-ip", feature = "proto-ipv4-fragmentation"))]
+fn test_ipv4_reassembly_limits() {
+    let (mut iface, mut sockets, mut device) = create_ip();
+    iface.inner.ipv4_reassembly_max_packets = 1;
+    iface.inner.ipv4_reassembly_max_size = 32;
+
+    let payload = [0xa5; 16];
+    let fragment = |ident: u16, frag_offset: u16| {
+        let repr = Ipv4Repr {
+            src_addr: Ipv4Address([127, 0, 0, 2]),
+            dst_addr: Ipv4Address([127, 0, 0, 1]),
+            next_header: IpProtocol::Udp,
+            payload_len: payload.len(),
+            hop_limit: 64,
+            traffic_class: 0,
+        };
+        let mut bytes = vec![0; repr.buffer_len() + payload.len()];
+        let mut packet = Ipv4Packet::new_unchecked(&mut bytes[..]);
+        repr.emit(&mut packet, &ChecksumCapabilities::default());
+        packet.set_ident(ident);
+        packet.set_dont_frag(false);
+        packet.set_more_frags(true);
+        packet.set_frag_offset(frag_offset);
+        packet.payload_mut().copy_from_slice(&payload);
+        packet.fill_checksum();
+        bytes
+    };
+
+    iface.inner.now = Instant::from_secs(0);
+    for (ident, frag_offset) in [(1, 0), (2, 0), (1, 24)] {
+        let bytes = fragment(ident, frag_offset);
+        let packet = Ipv4Packet::new_unchecked(&bytes[..]);
+        assert_eq!(
+            iface.inner.process_ipv4(
+                &mut sockets,
+                &packet,
+                Some(&mut iface.fragments.ipv4_fragments)
+            ),
+            None
+        );
+    }
+    // The second packet exceeded the number of reassemblies, and the third fragment
+    // exceeded the packet size, which also dropped the first one.
+    assert_eq!(iface.drop_stats().count(DropReason::Reassembly), 2);
+    assert_eq!(iface.fragments.ipv4_fragments.len(), 0);
+
+    let bytes = fragment(3, 0);
+    let packet = Ipv4Packet::new_unchecked(&bytes[..]);
+    iface.inner.process_ipv4(
+        &mut sockets,
+        &packet,
+        Some(&mut iface.fragments.ipv4_fragments),
+    );
+    assert_eq!(
+        iface.poll_at(Instant::from_secs(0), &sockets),
+        Some(Instant::from_secs(90))
+    );
+
+    iface.inner.now = Instant::from_secs(90);
+    iface.ipv4_reassembly_expiry(&mut device);
+    assert_eq!(iface.fragments.ipv4_fragments.len(), 0);
+    assert_eq!(iface.drop_stats().count(DropReason::Reassembly), 3);
+
+    let reply = device.queue.pop_front().unwrap();
+    let ipv4_packet = Ipv4Packet::new_checked(&reply[..]).unwrap();
+    assert_eq!(ipv4_packet.dst_addr(), Ipv4Address([127, 0, 0, 2]));
+    let icmp_packet = Icmpv4Packet::new_checked(ipv4_packet.payload()).unwrap();
+    match Icmpv4Repr::parse(&icmp_packet, &ChecksumCapabilities::default()).unwrap() {
+        Icmpv4Repr::TimeExceeded { reason, data, .. } => {
+            assert_eq!(reason, Icmpv4TimeExceeded::FragExpired);
+            assert_eq!(data, &payload[..]);
+        }
+        repr => panic!("unexpected ICMP message {repr:?}"),
+    }
+}
+
+#[cfg(all(feature = "forwarding", feature = "medium-ip", feature = "proto-ipv4"))]
+fn create_router<'a>(cidr: IpCidr) -> (Interface<'a>, Loopback) {
+    use crate::iface::ForwardingBuffer;
+    use crate::storage::PacketMetadata;
+
+    let mut device = Loopback::new(Medium::Ip);
+    let mut ip_addrs = heapless::Vec::<IpCidr, MAX_IP_ADDR_COUNT>::new();
+    ip_addrs.push(cidr).unwrap();
+    let buffer = || ForwardingBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 1500]);
+
+    let iface_builder = InterfaceBuilder::new()
+        .ip_addrs(ip_addrs)
+        .forwarding_buffers(buffer(), buffer());
+
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    let iface_builder = iface_builder
+        .ipv4_reassembly_buffer(PacketAssemblerSet::new(vec![], BTreeMap::new()))
+        .ipv4_fragmentation_buffer(vec![]);
+
+    (iface_builder.finalize(&mut device), device)
+}
+
+#[test]
+#[cfg(all(feature = "forwarding", feature = "medium-ip", feature = "proto-ipv4"))]
+fn test_forwarding() {
+    use crate::iface::forward;
+
+    let (mut iface_a, mut device_a) = create_router(IpCidr::new(IpAddress::v4(192, 168, 1, 1), 24));
+    let (mut iface_b, mut device_b) = create_router(IpCidr::new(IpAddress::v4(10, 0, 0, 1), 24));
+    let mut sockets = SocketSet::new(vec![]);
+
+    let payload = [0xaa; 8];
+    let packet = |dst_addr, hop_limit| {
+        let repr = Ipv4Repr {
+            src_addr: Ipv4Address::new(192, 168, 1, 2),
+            dst_addr,
+            next_header: IpProtocol::Unknown(0xfd),
+            payload_len: payload.len(),
+            hop_limit,
+            traffic_class: 0,
+        };
+        let mut bytes = vec![0u8; repr.buffer_len() + payload.len()];
+        repr.emit(
+            &mut Ipv4Packet::new_unchecked(&mut bytes),
+            &ChecksumCapabilities::default(),
+        );
+        bytes[repr.buffer_len()..].copy_from_slice(&payload);
+        (repr, bytes)
+    };
+
+    // A packet to the network of the other interface is handed to it, and sent
+    // with its hop limit decremented.
+    let (repr, bytes) = packet(Ipv4Address::new(10, 0, 0, 2), 64);
+    let tx_token = device_a.transmit().unwrap();
+    tx_token
+        .consume(Instant::ZERO, bytes.len(), |buffer| {
+            buffer.copy_from_slice(&bytes);
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(
+        iface_a.poll(Instant::ZERO, &mut device_a, &mut sockets),
+        Ok(true)
+    );
+    assert!(recv_all(&mut device_a, Instant::ZERO).is_empty());
+
+    assert_eq!(forward(&mut [&mut iface_a, &mut iface_b]), 1);
+    assert_eq!(
+        iface_b.poll_at(Instant::ZERO, &sockets),
+        Some(Instant::ZERO)
+    );
+    assert_eq!(
+        iface_b.poll(Instant::ZERO, &mut device_b, &mut sockets),
+        Ok(false)
+    );
+    let frames = recv_all(&mut device_b, Instant::ZERO);
+    assert_eq!(frames.len(), 1);
+    let forwarded = Ipv4Packet::new_checked(&frames[0][..]).unwrap();
+    assert_eq!(
+        Ipv4Repr::parse(&forwarded, &ChecksumCapabilities::default()),
+        Ok(Ipv4Repr {
+            hop_limit: 63,
+            ..repr
+        })
+    );
+    assert_eq!(forwarded.payload(), &payload[..]);
+
+    // A packet whose hop limit is exhausted is answered with a time exceeded message.
+    let (repr, bytes) = packet(Ipv4Address::new(10, 0, 0, 2), 1);
+    let frame = Ipv4Packet::new_unchecked(&bytes);
+    let icmp_repr = Icmpv4Repr::TimeExceeded {
+        reason: Icmpv4TimeExceeded::TtlExpired,
+        header: repr,
+        data: &payload,
+    };
+    let expected = IpPacket::Icmpv4((
+        Ipv4Repr {
+            src_addr: Ipv4Address::new(192, 168, 1, 1),
+            dst_addr: repr.src_addr,
+            next_header: IpProtocol::Icmp,
+            payload_len: icmp_repr.buffer_len(),
+            hop_limit: 64,
+            traffic_class: 0,
+        },
+        icmp_repr,
+    ));
+
+    #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+    assert_eq!(
+        iface_a.inner.process_ipv4(&mut sockets, &frame, None),
+        Some(expected)
+    );
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    assert_eq!(
+        iface_a.inner.process_ipv4(
+            &mut sockets,
+            &frame,
+            Some(&mut iface_a.fragments.ipv4_fragments)
+        ),
+        Some(expected)
+    );
+    assert_eq!(iface_a.drop_stats().count(DropReason::HopLimit), 1);
+
+    // A packet no interface has a route to is dropped.
+    let (_, bytes) = packet(Ipv4Address::new(172, 16, 0, 1), 64);
+    let frame = Ipv4Packet::new_unchecked(&bytes);
+
+    #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+    assert_eq!(iface_a.inner.process_ipv4(&mut sockets, &frame, None), None);
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    assert_eq!(
+        iface_a.inner.process_ipv4(
+            &mut sockets,
+            &frame,
+            Some(&mut iface_a.fragments.ipv4_fragments)
+        ),
+        None
+    );
+    assert_eq!(forward(&mut [&mut iface_a, &mut iface_b]), 0);
+    assert_eq!(iface_a.drop_stats().count(DropReason::Forwarding), 1);
+}
+
+#[test]
+#[cfg(all(feature = "forwarding", feature = "medium-ip", feature = "proto-ipv4"))]
+fn test_forwarding_directed_broadcast() {
+    use crate::iface::forward;
+
+    let (mut iface_a, mut device_a) = create_router(IpCidr::new(IpAddress::v4(192, 168, 1, 1), 24));
+    let (mut iface_b, mut device_b) = create_router(IpCidr::new(IpAddress::v4(10, 0, 0, 1), 24));
+    let mut sockets = SocketSet::new(vec![]);
+
+    let receive = |iface: &mut Interface, device: &mut Loopback, dst_addr| {
+        let payload = [0xaa; 8];
+        let repr = Ipv4Repr {
+            src_addr: Ipv4Address::new(192, 168, 1, 2),
+            dst_addr,
+            next_header: IpProtocol::Unknown(0xfd),
+            payload_len: payload.len(),
+            hop_limit: 64,
+            traffic_class: 0,
+        };
+        let mut bytes = vec![0u8; repr.buffer_len() + payload.len()];
+        repr.emit(
+            &mut Ipv4Packet::new_unchecked(&mut bytes),
+            &ChecksumCapabilities::default(),
+        );
+        bytes[repr.buffer_len()..].copy_from_slice(&payload);
+
+        let tx_token = device.transmit().unwrap();
+        tx_token
+            .consume(Instant::ZERO, bytes.len(), |buffer| {
+                buffer.copy_from_slice(&bytes);
+                Ok(())
+            })
+            .unwrap();
+        iface
+            .poll(Instant::ZERO, device, &mut SocketSet::new(vec![]))
+            .unwrap();
+    };
+
+    // A broadcast to the network of the other interface is not forwarded.
+    receive(&mut iface_a, &mut device_a, Ipv4Address::new(10, 0, 0, 255));
+    assert_eq!(forward(&mut [&mut iface_a, &mut iface_b]), 0);
+    assert_eq!(iface_a.drop_stats().count(DropReason::Forwarding), 1);
+
+    // Neither is a limited broadcast.
+    receive(&mut iface_a, &mut device_a, Ipv4Address::BROADCAST);
+    assert_eq!(forward(&mut [&mut iface_a, &mut iface_b]), 0);
+
+    iface_b
+        .poll(Instant::ZERO, &mut device_b, &mut sockets)
+        .unwrap();
+    assert!(recv_all(&mut device_b, Instant::ZERO).is_empty());
+
+    // Unless the output interface allows it.
+    iface_b.set_forward_directed_broadcasts(true);
+    receive(&mut iface_a, &mut device_a, Ipv4Address::new(10, 0, 0, 255));
+    assert_eq!(forward(&mut [&mut iface_a, &mut iface_b]), 1);
+    iface_b
+        .poll(Instant::ZERO, &mut device_b, &mut sockets)
+        .unwrap();
+    let frames = recv_all(&mut device_b, Instant::ZERO);
+    assert_eq!(frames.len(), 1);
+    let forwarded = Ipv4Packet::new_checked(&frames[0][..]).unwrap();
+    assert_eq!(forwarded.dst_addr(), Ipv4Address::new(10, 0, 0, 255));
+}
+
+#[test]
+#[cfg(all(feature = "medium-ethernet", feature = "socket-ethernet"))]
+fn test_ethernet_socket_promiscuous() {
+    use crate::socket::ethernet;
+
+    let (mut iface, mut sockets, mut device) = create_ethernet();
+
+    let rx_buffer =
+        ethernet::PacketBuffer::new(vec![ethernet::PacketMetadata::EMPTY; 2], vec![0; 128]);
+    let tx_buffer =
+        ethernet::PacketBuffer::new(vec![ethernet::PacketMetadata::EMPTY; 1], vec![0; 64]);
+    let socket = ethernet::Socket::new(
+        Some(EthernetProtocol::Unknown(0x88f7)),
+        rx_buffer,
+        tx_buffer,
+    );
+    let socket_handle = sockets.add(socket);
+
+    let frame = |dst_addr| {
+        let mut bytes = vec![0u8; 14 + 4];
+        let mut frame = EthernetFrame::new_unchecked(&mut bytes[..]);
+        frame.set_dst_addr(dst_addr);
+        frame.set_src_addr(EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x01]));
+        frame.set_ethertype(EthernetProtocol::Unknown(0x88f7));
+        frame
+            .payload_mut()
+            .copy_from_slice(&[0xaa, 0x00, 0x00, 0xff]);
+        bytes
+    };
+    let other_host = frame(EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x02]));
+    let this_host = frame(EthernetAddress::default());
+
+    // Frames addressed to other hosts are dropped, unless the interface is promiscuous.
+    assert_eq!(
+        iface
+            .inner
+            .process_ethernet(&mut sockets, &other_host, &mut iface.fragments),
+        None
+    );
+    assert!(!sockets
+        .get_mut::<ethernet::Socket>(socket_handle)
+        .can_recv());
+    assert_eq!(iface.drop_stats().count(DropReason::Filtered), 1);
+
+    iface.set_promiscuous(true);
+    assert_eq!(
+        iface
+            .inner
+            .process_ethernet(&mut sockets, &other_host, &mut iface.fragments),
+        None
+    );
+    assert_eq!(
+        iface
+            .inner
+            .process_ethernet(&mut sockets, &this_host, &mut iface.fragments),
+        None
+    );
+    assert_eq!(iface.drop_stats().count(DropReason::Filtered), 1);
+    assert_eq!(iface.drop_stats().count(DropReason::Unsupported), 0);
+
+    let socket = sockets.get_mut::<ethernet::Socket>(socket_handle);
+    assert_eq!(socket.recv(), Ok(&other_host[..]));
+    assert_eq!(socket.recv(), Ok(&this_host[..]));
+
+    // Frames are sent as they are.
+    socket.send_slice(&other_host).unwrap();
+    assert!(iface.socket_egress(&mut device, &mut sockets));
+    assert_eq!(recv_all(&mut device, Instant::ZERO), vec![other_host]);
+}
+
+#[test]
+#[cfg(feature = "proto-lldp")]
+fn test_lldp() {
+    use crate::iface::LldpConfig;
+
+    let (mut iface, mut sockets, mut device) = create_ethernet();
+    iface.set_lldp_config(Some(LldpConfig {
+        system_name: Some("smoltcp"),
+        ..LldpConfig::new("eth0")
+    }));
+
+    // The configuration is advertised right away, and then every 30 seconds.
+    assert_eq!(iface.lldp_egress(&mut device), Ok(true));
+    assert_eq!(iface.lldp_egress(&mut device), Ok(false));
+
+    let frames = recv_all(&mut device, Instant::ZERO);
+    assert_eq!(frames.len(), 1);
+    let frame = EthernetFrame::new_checked(&frames[0][..]).unwrap();
+    assert_eq!(frame.dst_addr(), LLDP_MULTICAST_ADDR);
+    assert_eq!(frame.ethertype(), EthernetProtocol::Lldp);
+    assert_eq!(
+        LldpRepr::parse(frame.payload()),
+        Ok(LldpRepr {
+            chassis_id_subtype: LldpChassisIdSubtype::MacAddress,
+            chassis_id: EthernetAddress::default().as_bytes(),
+            port_id_subtype: LldpPortIdSubtype::InterfaceName,
+            port_id: b"eth0",
+            ttl: 120,
+            port_description: None,
+            system_name: Some("smoltcp"),
+            system_description: None,
+        })
+    );
+
+    // Neighbors are remembered until they shut down.
+    let neighbor_frame = |ttl| {
+        let repr = LldpRepr {
+            chassis_id_subtype: LldpChassisIdSubtype::MacAddress,
+            chassis_id: &[0x52, 0x54, 0x00, 0x00, 0x00, 0x01],
+            port_id_subtype: LldpPortIdSubtype::InterfaceName,
+            port_id: b"ge-0/0/1",
+            ttl,
+            port_description: None,
+            system_name: Some("switch"),
+            system_description: None,
+        };
+        let mut bytes = vec![0u8; 14 + repr.buffer_len()];
+        let mut frame = EthernetFrame::new_unchecked(&mut bytes[..]);
+        frame.set_dst_addr(LLDP_MULTICAST_ADDR);
+        frame.set_src_addr(EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x01]));
+        frame.set_ethertype(EthernetProtocol::Lldp);
+        repr.emit(frame.payload_mut());
+        bytes
+    };
+
+    assert_eq!(
+        iface
+            .inner
+            .process_ethernet(&mut sockets, &neighbor_frame(120), &mut iface.fragments),
+        None
+    );
+    let neighbors = iface.lldp_neighbors();
+    assert_eq!(neighbors.len(), 1);
+    assert_eq!(&neighbors[0].port_id[..], b"ge-0/0/1");
+    assert_eq!(neighbors[0].system_name.as_deref(), Some("switch"));
+    assert_eq!(neighbors[0].expires_at, Instant::from_secs(120));
+
+    assert_eq!(
+        iface
+            .inner
+            .process_ethernet(&mut sockets, &neighbor_frame(0), &mut iface.fragments),
+        None
+    );
+    assert!(iface.lldp_neighbors().is_empty());
+}
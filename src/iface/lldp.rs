@@ -0,0 +1,241 @@
+// Heads up! Before working on this file you should read IEEE 802.1AB § 9, which
+// describes the timers of the transmit and receive state machines.
+
+use heapless::{String, Vec};
+
+use crate::time::{Duration, Instant};
+use crate::wire::{LldpChassisIdSubtype, LldpPortIdSubtype, LldpRepr};
+
+/// The largest number of neighbors remembered.
+pub const MAX_NEIGHBOR_COUNT: usize = 4;
+
+/// The largest chassis ID or port ID remembered for a neighbor; longer ones are truncated.
+pub const MAX_ID_LEN: usize = 32;
+
+/// The largest text remembered for a neighbor; longer ones are truncated.
+pub const MAX_TEXT_LEN: usize = 64;
+
+/// The number of transmit intervals the advertised information stays valid for,
+/// i.e. `msgTxHold` from IEEE 802.1AB § 9.2.5.
+const TX_HOLD: u32 = 4;
+
+/// What the interface advertises over LLDP.
+///
+/// The chassis ID is always the hardware address of the interface.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config<'a> {
+    /// The name of the interface, advertised as the port ID.
+    pub port_id: &'a str,
+    pub port_description: Option<&'a str>,
+    pub system_name: Option<&'a str>,
+    pub system_description: Option<&'a str>,
+    /// How often the information is advertised. It is valid for four times as long.
+    pub tx_interval: Duration,
+}
+
+impl<'a> Config<'a> {
+    /// Create a configuration advertising the port named `port_id` every 30 seconds,
+    /// without any optional information.
+    pub const fn new(port_id: &'a str) -> Config<'a> {
+        Config {
+            port_id,
+            port_description: None,
+            system_name: None,
+            system_description: None,
+            tx_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A neighbor whose advertisement was received over LLDP.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Neighbor {
+    pub chassis_id_subtype: LldpChassisIdSubtype,
+    pub chassis_id: Vec<u8, MAX_ID_LEN>,
+    pub port_id_subtype: LldpPortIdSubtype,
+    pub port_id: Vec<u8, MAX_ID_LEN>,
+    pub port_description: Option<String<MAX_TEXT_LEN>>,
+    pub system_name: Option<String<MAX_TEXT_LEN>>,
+    /// When the advertisement expires, unless it is renewed.
+    pub expires_at: Instant,
+}
+
+impl Neighbor {
+    fn new(timestamp: Instant, repr: &LldpRepr) -> Neighbor {
+        Neighbor {
+            chassis_id_subtype: repr.chassis_id_subtype,
+            chassis_id: truncated_id(repr.chassis_id),
+            port_id_subtype: repr.port_id_subtype,
+            port_id: truncated_id(repr.port_id),
+            port_description: repr.port_description.map(truncated_text),
+            system_name: repr.system_name.map(truncated_text),
+            expires_at: timestamp + Duration::from_secs(repr.ttl.into()),
+        }
+    }
+
+    /// Query whether the advertisement comes from this neighbor, which LLDP identifies
+    /// by its chassis ID and port ID.
+    fn is_source_of(&self, repr: &LldpRepr) -> bool {
+        self.chassis_id_subtype == repr.chassis_id_subtype
+            && self.chassis_id == truncated_id(repr.chassis_id)
+            && self.port_id_subtype == repr.port_id_subtype
+            && self.port_id == truncated_id(repr.port_id)
+    }
+}
+
+fn truncated_id(id: &[u8]) -> Vec<u8, MAX_ID_LEN> {
+    Vec::from_slice(&id[..id.len().min(MAX_ID_LEN)]).unwrap()
+}
+
+fn truncated_text(text: &str) -> String<MAX_TEXT_LEN> {
+    let mut truncated = String::new();
+    for c in text.chars() {
+        if truncated.push(c).is_err() {
+            break;
+        }
+    }
+    truncated
+}
+
+/// The state of LLDP on an interface.
+#[derive(Debug)]
+pub(crate) struct Lldp<'a> {
+    config: Config<'a>,
+    /// When to send the next advertisement.
+    next_tx: Instant,
+    neighbors: Vec<Neighbor, MAX_NEIGHBOR_COUNT>,
+}
+
+impl<'a> Lldp<'a> {
+    pub(crate) fn new(config: Config<'a>) -> Lldp<'a> {
+        Lldp {
+            config,
+            next_tx: Instant::ZERO,
+            neighbors: Vec::new(),
+        }
+    }
+
+    pub(crate) fn config(&self) -> &Config<'a> {
+        &self.config
+    }
+
+    /// Replace the configuration, and advertise it right away.
+    pub(crate) fn set_config(&mut self, config: Config<'a>) {
+        self.config = config;
+        self.next_tx = Instant::ZERO;
+    }
+
+    pub(crate) fn neighbors(&self) -> &[Neighbor] {
+        &self.neighbors
+    }
+
+    /// Return how long the advertised information is valid, in seconds.
+    pub(crate) fn ttl(&self) -> u16 {
+        let ttl = self.config.tx_interval.secs() * u64::from(TX_HOLD);
+        ttl.min(u64::from(u16::MAX)) as u16
+    }
+
+    /// Forget the neighbors whose advertisement expired, and return whether an
+    /// advertisement is due.
+    pub(crate) fn poll(&mut self, timestamp: Instant) -> bool {
+        self.neighbors
+            .retain(|neighbor| neighbor.expires_at > timestamp);
+        timestamp >= self.next_tx
+    }
+
+    /// Schedule the next advertisement after one was sent.
+    pub(crate) fn sent(&mut self, timestamp: Instant) {
+        self.next_tx = timestamp + self.config.tx_interval;
+    }
+
+    pub(crate) fn poll_at(&self) -> Instant {
+        self.neighbors
+            .iter()
+            .map(|neighbor| neighbor.expires_at)
+            .fold(self.next_tx, Instant::min)
+    }
+
+    /// Remember the neighbor sending an advertisement, or forget it if the advertisement
+    /// has a TTL of zero.
+    pub(crate) fn process(&mut self, timestamp: Instant, repr: &LldpRepr) {
+        let index = self
+            .neighbors
+            .iter()
+            .position(|neighbor| neighbor.is_source_of(repr));
+        match index {
+            Some(index) if repr.ttl == 0 => {
+                net_debug!("lldp: neighbor {} shut down", repr);
+                self.neighbors.swap_remove(index);
+            }
+            Some(index) => self.neighbors[index] = Neighbor::new(timestamp, repr),
+            None if repr.ttl == 0 => (),
+            None => {
+                net_debug!("lldp: new neighbor {}", repr);
+                if self.neighbors.push(Neighbor::new(timestamp, repr)).is_err() {
+                    net_debug!("lldp: no room for neighbor");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn repr(ttl: u16) -> LldpRepr<'static> {
+        LldpRepr {
+            chassis_id_subtype: LldpChassisIdSubtype::MacAddress,
+            chassis_id: &[0x02, 0x02, 0x02, 0x02, 0x02, 0x02],
+            port_id_subtype: LldpPortIdSubtype::InterfaceName,
+            port_id: b"eth0",
+            ttl,
+            port_description: None,
+            system_name: Some("switch"),
+            system_description: None,
+        }
+    }
+
+    #[test]
+    fn test_schedule() {
+        let mut lldp = Lldp::new(Config::new("eth0"));
+        assert_eq!(lldp.ttl(), 120);
+        assert!(lldp.poll(Instant::from_secs(1)));
+        lldp.sent(Instant::from_secs(1));
+        assert!(!lldp.poll(Instant::from_secs(30)));
+        assert_eq!(lldp.poll_at(), Instant::from_secs(31));
+        assert!(lldp.poll(Instant::from_secs(31)));
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let mut lldp = Lldp::new(Config::new("eth0"));
+        lldp.sent(Instant::from_secs(0));
+
+        lldp.process(Instant::from_secs(0), &repr(10));
+        lldp.process(Instant::from_secs(5), &repr(10));
+        assert_eq!(lldp.neighbors().len(), 1);
+        assert_eq!(lldp.neighbors()[0].system_name.as_deref(), Some("switch"));
+        assert_eq!(lldp.poll_at(), Instant::from_secs(15));
+
+        // The neighbor is forgotten once its advertisement expires.
+        assert!(!lldp.poll(Instant::from_secs(14)));
+        assert_eq!(lldp.neighbors().len(), 1);
+        lldp.poll(Instant::from_secs(15));
+        assert!(lldp.neighbors().is_empty());
+
+        // Or right away when it shuts down.
+        lldp.process(Instant::from_secs(16), &repr(10));
+        lldp.process(Instant::from_secs(17), &repr(0));
+        assert!(lldp.neighbors().is_empty());
+    }
+
+    #[test]
+    fn test_truncated_text() {
+        let text = "é".repeat(MAX_TEXT_LEN);
+        assert_eq!(truncated_text(&text).len(), MAX_TEXT_LEN);
+        assert_eq!(truncated_id(&[0; 40]).len(), MAX_ID_LEN);
+    }
+}
@@ -4,25 +4,58 @@ The `iface` module deals with the *network interfaces*. It filters incoming fram
 provides lookup and caching of hardware addresses, and handles management packets.
 */
 
+#[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+mod acd;
 #[cfg(any(feature = "proto-ipv4", feature = "proto-sixlowpan"))]
 mod fragmentation;
 mod interface;
+#[cfg(feature = "proto-lldp")]
+mod lldp;
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 mod neighbor;
+#[cfg(feature = "medium-ppp")]
+mod ppp;
 mod route;
+#[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+mod slaac;
 mod socket_meta;
 mod socket_set;
+mod stats;
 
+#[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+pub use self::acd::Conflict as AcdConflict;
+#[cfg(feature = "proto-lldp")]
+pub use self::lldp::{
+    Config as LldpConfig, Neighbor as LldpNeighbor, MAX_ID_LEN as LLDP_MAX_ID_LEN,
+    MAX_NEIGHBOR_COUNT as LLDP_MAX_NEIGHBOR_COUNT, MAX_TEXT_LEN as LLDP_MAX_TEXT_LEN,
+};
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 pub(crate) use self::neighbor::Answer as NeighborAnswer;
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 pub use self::neighbor::Cache as NeighborCache;
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 pub use self::neighbor::Neighbor;
+#[cfg(feature = "medium-ppp")]
+pub use self::ppp::{
+    Ipv4Config as PppIpv4Config, LinkState as PppLinkState,
+    MAX_DNS_SERVER_COUNT as PPP_MAX_DNS_SERVER_COUNT,
+};
 pub use self::route::{Route, Routes};
-pub use socket_set::{SocketHandle, SocketSet, SocketStorage};
+#[cfg(all(feature = "proto-ipv6", feature = "medium-ethernet"))]
+pub use self::slaac::{Address as SlaacAddress, AddressState as SlaacAddressState};
+pub use socket_set::{ReadinessIter, SocketHandle, SocketSet, SocketStorage};
+#[cfg(feature = "stats")]
+pub use stats::Stats;
+pub use stats::{DropReason, DropStats};
 
 #[cfg(any(feature = "proto-ipv4", feature = "proto-sixlowpan"))]
 pub use self::fragmentation::{PacketAssembler, PacketAssemblerSet as ReassemblyBuffer};
 
-pub use self::interface::{Interface, InterfaceBuilder, InterfaceInner as Context};
+#[cfg(feature = "socket-tcp")]
+pub use self::interface::BacklogPolicy;
+#[cfg(feature = "forwarding")]
+pub use self::interface::{forward, ForwardingBuffer};
+pub use self::interface::{
+    IcmpRateLimit, Interface, InterfaceBuilder, InterfaceInner as Context, PriorityMapping,
+    RebindPolicy, SourceRoutePolicy,
+};
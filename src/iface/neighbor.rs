@@ -3,6 +3,7 @@
 
 use heapless::LinearMap;
 
+use crate::storage::MemoryUsage;
 use crate::time::{Duration, Instant};
 use crate::wire::{HardwareAddress, IpAddress};
 
@@ -50,6 +51,7 @@ impl Answer {
 pub struct Cache {
     storage: LinearMap<IpAddress, Neighbor, NEIGHBOR_CACHE_SIZE>,
     silent_until: Instant,
+    high_water: usize,
 }
 
 impl Cache {
@@ -64,6 +66,17 @@ impl Cache {
         Self {
             storage: LinearMap::new(),
             silent_until: Instant::from_millis(0),
+            high_water: 0,
+        }
+    }
+
+    /// Return the number of bytes of storage used and committed by the cache entries.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let size = core::mem::size_of::<(IpAddress, Neighbor)>();
+        MemoryUsage {
+            used: self.storage.len() * size,
+            capacity: NEIGHBOR_CACHE_SIZE * size,
+            high_water: self.high_water * size,
         }
     }
 
@@ -119,6 +132,7 @@ impl Cache {
                 }
             }
         }
+        self.high_water = core::cmp::max(self.high_water, self.storage.len());
     }
 
     pub(crate) fn lookup(&self, protocol_addr: &IpAddress, timestamp: Instant) -> Answer {
@@ -132,6 +146,7 @@ impl Cache {
             if timestamp < expires_at {
                 return Answer::Found(hardware_addr);
             }
+            net_trace!("expired {} => {}", protocol_addr, hardware_addr);
         }
 
         if timestamp < self.silent_until {
@@ -146,6 +161,7 @@ impl Cache {
     }
 
     pub(crate) fn flush(&mut self) {
+        net_trace!("flushed {} entries", self.storage.len());
         self.storage.clear()
     }
 }
@@ -294,4 +310,19 @@ mod test {
             .lookup(&MOCK_IP_ADDR_1, Instant::from_millis(0))
             .found());
     }
+
+    #[test]
+    fn test_memory_usage() {
+        let mut cache = Cache::new();
+        let size = core::mem::size_of::<(IpAddress, Neighbor)>();
+        assert_eq!(cache.memory_usage().capacity, NEIGHBOR_CACHE_SIZE * size);
+
+        cache.fill(MOCK_IP_ADDR_1, HADDR_A, Instant::from_millis(0));
+        cache.fill(MOCK_IP_ADDR_2, HADDR_B, Instant::from_millis(0));
+        cache.flush();
+        cache.fill(MOCK_IP_ADDR_1, HADDR_A, Instant::from_millis(0));
+        let usage = cache.memory_usage();
+        assert_eq!(usage.used, size);
+        assert_eq!(usage.high_water, 2 * size);
+    }
 }
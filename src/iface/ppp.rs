@@ -0,0 +1,781 @@
+// Heads up! Before working on this file you should read RFC 1661, which describes LCP
+// and the option negotiation automaton shared by the PPP control protocols, and
+// RFC 1332 and RFC 1877 for IPCP.
+
+use heapless::Vec;
+
+use crate::time::{Duration, Instant};
+use crate::wire::{
+    Ipv4Address, PppControlCode, PppControlOption, PppControlOptionsIterator, PppControlRepr,
+    PppIpcpOption, PppLcpOption, PppProtocol, PPP_CONTROL_HEADER_LEN,
+};
+
+/// The largest number of DNS servers negotiated with IPCP.
+pub const MAX_DNS_SERVER_COUNT: usize = 2;
+
+/// The largest data field of the control packets sent by the interface. Longer
+/// Configure-Requests are ignored, and longer Echo-Requests are answered truncated.
+pub(crate) const MAX_CONTROL_DATA_LEN: usize = 128;
+
+/// How long to wait for an answer to a Configure-Request, see RFC 1661 § 4.6.
+const RESTART_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How many Configure-Requests to send without an answer before giving up,
+/// i.e. `Max-Configure` from RFC 1661 § 4.6.
+const MAX_CONFIGURE: u8 = 10;
+
+/// The state of a PPP link.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LinkState {
+    /// LCP is negotiating the configuration of the link.
+    Establishing,
+    /// The link is established, and IPCP is negotiating the IPv4 configuration.
+    Negotiating,
+    /// The IPv4 configuration is negotiated, and the link carries IPv4 packets.
+    Opened,
+    /// The negotiation failed, or the peer terminated the link.
+    Stopped,
+}
+
+/// The IPv4 configuration negotiated with IPCP.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ipv4Config {
+    /// The address of the interface, assigned by the peer.
+    pub address: Ipv4Address,
+    /// The address of the peer, if it sent it.
+    pub peer_address: Option<Ipv4Address>,
+    /// The DNS servers sent by the peer.
+    pub dns_servers: Vec<Ipv4Address, MAX_DNS_SERVER_COUNT>,
+}
+
+/// A control packet the interface has to send.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Message {
+    pub protocol: PppProtocol,
+    pub code: PppControlCode,
+    pub identifier: u8,
+    pub data: Vec<u8, MAX_CONTROL_DATA_LEN>,
+}
+
+impl Message {
+    fn new(protocol: PppProtocol, code: PppControlCode, identifier: u8) -> Message {
+        Message {
+            protocol,
+            code,
+            identifier,
+            data: Vec::new(),
+        }
+    }
+
+    pub(crate) fn repr(&self) -> PppControlRepr<'_> {
+        PppControlRepr {
+            code: self.code,
+            identifier: self.identifier,
+            data: &self.data,
+        }
+    }
+
+    /// Append `data`, truncated to the room left.
+    fn push_data(&mut self, data: &[u8]) {
+        let len = data.len().min(self.data.capacity() - self.data.len());
+        self.data.extend_from_slice(&data[..len]).unwrap();
+    }
+
+    fn push_option(&mut self, option: PppControlOption) {
+        let len = self.data.len();
+        self.data.resize(len + option.buffer_len(), 0).unwrap();
+        option.emit(&mut self.data[len..]);
+    }
+}
+
+/// The states of the option negotiation automaton of RFC 1661 § 4.2.
+///
+/// The Closed, Closing and Stopping states are not needed, since the interface never
+/// closes the link itself, and goes directly to Stopped when the peer does.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum State {
+    /// Waiting for the layer below to come up.
+    Starting,
+    ReqSent,
+    AckRcvd,
+    AckSent,
+    Opened,
+    Stopped,
+}
+
+/// The option negotiation automaton of a control protocol.
+///
+/// Only tracks the state; the options are handled by [Ppp].
+#[derive(Debug)]
+struct Automaton {
+    state: State,
+    /// The identifier of the last Configure-Request sent.
+    identifier: u8,
+    /// When to send the next Configure-Request, in the ReqSent, AckRcvd and AckSent states.
+    retry_at: Instant,
+    /// How many Configure-Requests are left to send before giving up.
+    retries: u8,
+}
+
+impl Automaton {
+    const fn new() -> Automaton {
+        Automaton {
+            state: State::Starting,
+            identifier: 0,
+            retry_at: Instant::ZERO,
+            retries: 0,
+        }
+    }
+
+    /// The layer below came up; start negotiating.
+    fn up(&mut self, now: Instant) {
+        self.state = State::ReqSent;
+        self.send_request(now);
+    }
+
+    /// The layer below went down.
+    fn down(&mut self) {
+        self.state = State::Starting;
+    }
+
+    /// Give up negotiating, e.g. because the peer does not support the protocol.
+    fn stop(&mut self) {
+        if self.state != State::Starting {
+            self.state = State::Stopped;
+        }
+    }
+
+    fn is_opened(&self) -> bool {
+        self.state == State::Opened
+    }
+
+    /// Send a Configure-Request right away, and reset the restart counter.
+    fn send_request(&mut self, now: Instant) {
+        self.retries = MAX_CONFIGURE;
+        self.retry_at = now;
+    }
+
+    /// Return the identifier of the Configure-Request to send, if one is due.
+    fn poll(&mut self, now: Instant) -> Option<u8> {
+        match self.state {
+            State::ReqSent | State::AckRcvd | State::AckSent if now >= self.retry_at => (),
+            _ => return None,
+        }
+        if self.retries == 0 {
+            net_debug!("ppp: negotiation timed out");
+            self.state = State::Stopped;
+            return None;
+        }
+
+        self.retries -= 1;
+        self.identifier = self.identifier.wrapping_add(1);
+        self.retry_at = now + RESTART_TIMEOUT;
+        if self.state == State::AckRcvd {
+            self.state = State::ReqSent;
+        }
+        Some(self.identifier)
+    }
+
+    fn poll_at(&self) -> Option<Instant> {
+        match self.state {
+            State::ReqSent | State::AckRcvd | State::AckSent => Some(self.retry_at),
+            _ => None,
+        }
+    }
+
+    /// Handle a Configure-Request of the peer, and return whether to answer it.
+    fn recv_configure_request(&mut self, now: Instant, acceptable: bool) -> bool {
+        self.state = match (self.state, acceptable) {
+            (State::Starting, _) => return false,
+            (State::Opened | State::Stopped, true) => {
+                self.send_request(now);
+                State::AckSent
+            }
+            (State::Opened | State::Stopped, false) => {
+                self.send_request(now);
+                State::ReqSent
+            }
+            (State::ReqSent | State::AckSent, true) => State::AckSent,
+            (State::AckRcvd, true) => State::Opened,
+            (State::AckSent, false) => State::ReqSent,
+            (state, false) => state,
+        };
+        true
+    }
+
+    /// Query whether a Configure-Ack, Configure-Nak or Configure-Reject with `identifier`
+    /// answers the last Configure-Request.
+    fn is_answer(&self, identifier: u8) -> bool {
+        matches!(
+            self.state,
+            State::ReqSent | State::AckRcvd | State::AckSent | State::Opened
+        ) && identifier == self.identifier
+    }
+
+    fn recv_configure_ack(&mut self, now: Instant, identifier: u8) {
+        if !self.is_answer(identifier) {
+            return;
+        }
+        self.state = match self.state {
+            State::ReqSent => {
+                self.retries = MAX_CONFIGURE;
+                State::AckRcvd
+            }
+            State::AckSent => State::Opened,
+            _ => {
+                self.send_request(now);
+                State::ReqSent
+            }
+        };
+    }
+
+    /// Handle a Configure-Nak or Configure-Reject, once the options were updated.
+    fn recv_configure_nak(&mut self, now: Instant) {
+        self.send_request(now);
+        if matches!(self.state, State::AckRcvd | State::Opened) {
+            self.state = State::ReqSent;
+        }
+    }
+
+    fn recv_terminate_request(&mut self) {
+        self.state = match self.state {
+            State::Starting => State::Starting,
+            State::Opened | State::Stopped => State::Stopped,
+            State::ReqSent | State::AckRcvd | State::AckSent => State::ReqSent,
+        };
+    }
+}
+
+/// The state of a PPP link: LCP, and IPCP on top of it.
+#[derive(Debug)]
+pub(crate) struct Ppp {
+    lcp: Automaton,
+    ipcp: Automaton,
+    magic_number: u32,
+    /// Whether to send the magic number, unless the peer rejected it.
+    send_magic_number: bool,
+    /// The address to request, unspecified until the peer assigns one.
+    address: Ipv4Address,
+    /// The DNS servers to request, unspecified until the peer sends them, and
+    /// `None` if the peer rejected the option.
+    dns_servers: [Option<Ipv4Address>; MAX_DNS_SERVER_COUNT],
+    peer_address: Option<Ipv4Address>,
+    /// The identifier of the last Code-Reject or Protocol-Reject sent.
+    reject_identifier: u8,
+    /// The address added to the interface.
+    pub(crate) assigned: Option<Ipv4Address>,
+}
+
+impl Ppp {
+    pub(crate) fn new(magic_number: u32) -> Ppp {
+        let mut lcp = Automaton::new();
+        lcp.up(Instant::ZERO);
+        Ppp {
+            lcp,
+            ipcp: Automaton::new(),
+            magic_number,
+            send_magic_number: true,
+            address: Ipv4Address::UNSPECIFIED,
+            dns_servers: [Some(Ipv4Address::UNSPECIFIED); MAX_DNS_SERVER_COUNT],
+            peer_address: None,
+            reject_identifier: 0,
+            assigned: None,
+        }
+    }
+
+    /// Negotiate the link again from the start.
+    pub(crate) fn reset(&mut self) {
+        net_debug!("ppp: reset");
+        let assigned = self.assigned;
+        *self = Ppp::new(self.magic_number);
+        self.assigned = assigned;
+    }
+
+    pub(crate) fn link_state(&self) -> LinkState {
+        match (self.lcp.state, self.ipcp.state) {
+            (State::Stopped, _) | (State::Opened, State::Stopped) => LinkState::Stopped,
+            (State::Opened, State::Opened) => LinkState::Opened,
+            (State::Opened, _) => LinkState::Negotiating,
+            _ => LinkState::Establishing,
+        }
+    }
+
+    pub(crate) fn ipv4_config(&self) -> Option<Ipv4Config> {
+        if !self.ipcp.is_opened() || self.address.is_unspecified() {
+            return None;
+        }
+        Some(Ipv4Config {
+            address: self.address,
+            peer_address: self.peer_address,
+            dns_servers: self
+                .dns_servers
+                .iter()
+                .flatten()
+                .filter(|addr| !addr.is_unspecified())
+                .copied()
+                .collect(),
+        })
+    }
+
+    /// Query whether the link carries IPv4 packets.
+    pub(crate) fn ipv4_opened(&self) -> bool {
+        self.ipcp.is_opened()
+    }
+
+    pub(crate) fn poll_at(&self) -> Option<Instant> {
+        match (self.lcp.poll_at(), self.ipcp.poll_at()) {
+            (Some(lcp), Some(ipcp)) => Some(lcp.min(ipcp)),
+            (lcp, ipcp) => lcp.or(ipcp),
+        }
+    }
+
+    /// Return the next Configure-Request to send, if one is due.
+    pub(crate) fn poll(&mut self, now: Instant) -> Option<Message> {
+        let lcp_opened = self.lcp.is_opened();
+        let identifier = self.lcp.poll(now);
+        self.lcp_changed(now, lcp_opened);
+        if let Some(identifier) = identifier {
+            let mut message = Message::new(
+                PppProtocol::Lcp,
+                PppControlCode::ConfigureRequest,
+                identifier,
+            );
+            if self.send_magic_number {
+                message.push_option(PppControlOption {
+                    kind: PppLcpOption::MagicNumber.into(),
+                    data: &self.magic_number.to_be_bytes(),
+                });
+            }
+            return Some(message);
+        }
+
+        let identifier = self.ipcp.poll(now)?;
+        let mut message = Message::new(
+            PppProtocol::Ipcp,
+            PppControlCode::ConfigureRequest,
+            identifier,
+        );
+        message.push_option(PppControlOption {
+            kind: PppIpcpOption::IpAddress.into(),
+            data: self.address.as_bytes(),
+        });
+        for (kind, addr) in DNS_OPTIONS.iter().zip(self.dns_servers.iter()) {
+            if let Some(addr) = addr {
+                message.push_option(PppControlOption {
+                    kind: (*kind).into(),
+                    data: addr.as_bytes(),
+                });
+            }
+        }
+        Some(message)
+    }
+
+    /// Process an LCP or IPCP packet, and return the answer to send.
+    pub(crate) fn process(
+        &mut self,
+        now: Instant,
+        protocol: PppProtocol,
+        repr: &PppControlRepr,
+    ) -> Option<Message> {
+        match protocol {
+            PppProtocol::Lcp => {
+                let lcp_opened = self.lcp.is_opened();
+                let answer = self.process_lcp(now, repr);
+                self.lcp_changed(now, lcp_opened);
+                answer
+            }
+            PppProtocol::Ipcp if self.lcp.is_opened() => self.process_ipcp(now, repr),
+            _ => None,
+        }
+    }
+
+    /// Return an LCP Protocol-Reject for a packet of an unsupported `protocol`.
+    pub(crate) fn protocol_reject(
+        &mut self,
+        protocol: PppProtocol,
+        payload: &[u8],
+    ) -> Option<Message> {
+        // RFC 1661 § 5.7: Protocol-Rejects are only sent in the Opened state.
+        if !self.lcp.is_opened() {
+            return None;
+        }
+        self.reject_identifier = self.reject_identifier.wrapping_add(1);
+        let mut message = Message::new(
+            PppProtocol::Lcp,
+            PppControlCode::ProtocolReject,
+            self.reject_identifier,
+        );
+        message.push_data(&u16::from(protocol).to_be_bytes());
+        message.push_data(payload);
+        Some(message)
+    }
+
+    /// Return a Code-Reject for a packet with an unknown code.
+    fn code_reject(&mut self, protocol: PppProtocol, repr: &PppControlRepr) -> Message {
+        self.reject_identifier = self.reject_identifier.wrapping_add(1);
+        let mut message =
+            Message::new(protocol, PppControlCode::CodeReject, self.reject_identifier);
+        let mut header = [0; PPP_CONTROL_HEADER_LEN];
+        header[0] = repr.code.into();
+        header[1] = repr.identifier;
+        header[2..].copy_from_slice(&(repr.buffer_len() as u16).to_be_bytes());
+        message.push_data(&header);
+        message.push_data(repr.data);
+        message
+    }
+
+    /// Start or stop IPCP when LCP enters or leaves the Opened state.
+    fn lcp_changed(&mut self, now: Instant, was_opened: bool) {
+        match (was_opened, self.lcp.is_opened()) {
+            (false, true) => {
+                net_debug!("ppp: link established");
+                self.ipcp.up(now)
+            }
+            (true, false) => {
+                net_debug!("ppp: link down");
+                self.ipcp.down()
+            }
+            _ => (),
+        }
+    }
+
+    fn process_lcp(&mut self, now: Instant, repr: &PppControlRepr) -> Option<Message> {
+        match repr.code {
+            PppControlCode::ConfigureRequest => {
+                let (acceptable, answer) =
+                    answer_configure_request(PppProtocol::Lcp, repr, |option| {
+                        match PppLcpOption::from(option.kind) {
+                            PppLcpOption::Mru => option.data.len() == 2,
+                            // The device takes care of the HDLC-like framing.
+                            PppLcpOption::Accm => option.data.len() == 4,
+                            PppLcpOption::MagicNumber => option.data.len() == 4,
+                            // Authentication, and compression of the address, control
+                            // and protocol fields are not supported.
+                            _ => false,
+                        }
+                    })?;
+                self.lcp
+                    .recv_configure_request(now, acceptable)
+                    .then_some(answer)
+            }
+            PppControlCode::ConfigureAck => {
+                self.lcp.recv_configure_ack(now, repr.identifier);
+                None
+            }
+            PppControlCode::ConfigureNak | PppControlCode::ConfigureReject
+                if self.lcp.is_answer(repr.identifier) =>
+            {
+                for option in PppControlOptionsIterator::new(repr.data).flatten() {
+                    if PppLcpOption::from(option.kind) != PppLcpOption::MagicNumber {
+                        continue;
+                    }
+                    if repr.code == PppControlCode::ConfigureReject {
+                        self.send_magic_number = false;
+                    } else if option.data.len() == 4 {
+                        let mut bytes = [0; 4];
+                        bytes.copy_from_slice(option.data);
+                        self.magic_number = u32::from_be_bytes(bytes);
+                    }
+                }
+                self.lcp.recv_configure_nak(now);
+                None
+            }
+            PppControlCode::TerminateRequest => {
+                net_debug!("ppp: peer terminated the link");
+                self.lcp.recv_terminate_request();
+                Some(Message::new(
+                    PppProtocol::Lcp,
+                    PppControlCode::TerminateAck,
+                    repr.identifier,
+                ))
+            }
+            PppControlCode::ProtocolReject => {
+                if repr.data.get(..2) == Some(&u16::from(PppProtocol::Ipcp).to_be_bytes()[..]) {
+                    net_debug!("ppp: peer rejected IPCP");
+                    self.ipcp.stop();
+                }
+                None
+            }
+            PppControlCode::EchoRequest if self.lcp.is_opened() => {
+                let mut answer =
+                    Message::new(PppProtocol::Lcp, PppControlCode::EchoReply, repr.identifier);
+                answer.push_data(&self.magic_number.to_be_bytes());
+                answer.push_data(repr.data.get(4..).unwrap_or(&[]));
+                Some(answer)
+            }
+            PppControlCode::Unknown(_) => Some(self.code_reject(PppProtocol::Lcp, repr)),
+            _ => None,
+        }
+    }
+
+    fn process_ipcp(&mut self, now: Instant, repr: &PppControlRepr) -> Option<Message> {
+        match repr.code {
+            PppControlCode::ConfigureRequest => {
+                let mut peer_address = None;
+                let (acceptable, answer) =
+                    answer_configure_request(PppProtocol::Ipcp, repr, |option| {
+                        match PppIpcpOption::from(option.kind) {
+                            PppIpcpOption::IpAddress if option.data.len() == 4 => {
+                                peer_address = Some(Ipv4Address::from_bytes(option.data));
+                                true
+                            }
+                            _ => false,
+                        }
+                    })?;
+                if acceptable {
+                    self.peer_address = peer_address.filter(|addr| !addr.is_unspecified());
+                }
+                self.ipcp
+                    .recv_configure_request(now, acceptable)
+                    .then_some(answer)
+            }
+            PppControlCode::ConfigureAck => {
+                self.ipcp.recv_configure_ack(now, repr.identifier);
+                if self.ipcp.is_opened() {
+                    net_debug!("ppp: IPCP opened, address {}", self.address);
+                }
+                None
+            }
+            PppControlCode::ConfigureNak | PppControlCode::ConfigureReject
+                if self.ipcp.is_answer(repr.identifier) =>
+            {
+                let reject = repr.code == PppControlCode::ConfigureReject;
+                for option in PppControlOptionsIterator::new(repr.data).flatten() {
+                    let kind = PppIpcpOption::from(option.kind);
+                    let value = match option.data.len() {
+                        4 => Ipv4Address::from_bytes(option.data),
+                        _ => continue,
+                    };
+                    if kind == PppIpcpOption::IpAddress {
+                        if reject {
+                            // Without an address from the peer, there is nothing to negotiate.
+                            net_debug!("ppp: peer did not assign an address");
+                            self.ipcp.stop();
+                            return None;
+                        }
+                        self.address = value;
+                    } else if let Some(index) = DNS_OPTIONS.iter().position(|k| *k == kind) {
+                        let server = &mut self.dns_servers[index];
+                        if server.is_some() {
+                            *server = (!reject).then_some(value);
+                        }
+                    }
+                }
+                self.ipcp.recv_configure_nak(now);
+                None
+            }
+            PppControlCode::TerminateRequest => {
+                self.ipcp.recv_terminate_request();
+                Some(Message::new(
+                    PppProtocol::Ipcp,
+                    PppControlCode::TerminateAck,
+                    repr.identifier,
+                ))
+            }
+            PppControlCode::Unknown(_) => Some(self.code_reject(PppProtocol::Ipcp, repr)),
+            _ => None,
+        }
+    }
+}
+
+/// The IPCP options carrying the DNS servers, in order.
+const DNS_OPTIONS: [PppIpcpOption; MAX_DNS_SERVER_COUNT] =
+    [PppIpcpOption::PrimaryDns, PppIpcpOption::SecondaryDns];
+
+/// Check the options of a Configure-Request with `acceptable`, and return whether all
+/// of them are, together with the Configure-Ack or Configure-Reject answering it.
+///
+/// Returns `None` if the request is malformed, or too long to answer.
+fn answer_configure_request(
+    protocol: PppProtocol,
+    repr: &PppControlRepr,
+    mut acceptable: impl FnMut(&PppControlOption) -> bool,
+) -> Option<(bool, Message)> {
+    if repr.data.len() > MAX_CONTROL_DATA_LEN {
+        return None;
+    }
+    let mut rejected = Message::new(protocol, PppControlCode::ConfigureReject, repr.identifier);
+    for option in PppControlOptionsIterator::new(repr.data) {
+        let option = option.ok()?;
+        if !acceptable(&option) {
+            rejected.push_option(option);
+        }
+    }
+
+    if rejected.data.is_empty() {
+        let mut answer = Message::new(protocol, PppControlCode::ConfigureAck, repr.identifier);
+        answer.push_data(repr.data);
+        Some((true, answer))
+    } else {
+        Some((false, rejected))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MAGIC_NUMBER: u32 = 0x1234_5678;
+
+    fn configure(code: PppControlCode, identifier: u8, options: &[u8]) -> PppControlRepr<'_> {
+        PppControlRepr {
+            code,
+            identifier,
+            data: options,
+        }
+    }
+
+    /// Bring LCP to the Opened state, and return the time.
+    fn open_lcp(ppp: &mut Ppp) -> Instant {
+        let now = Instant::from_millis(0);
+        let request = ppp.poll(now).unwrap();
+        assert_eq!(request.protocol, PppProtocol::Lcp);
+        assert_eq!(request.code, PppControlCode::ConfigureRequest);
+        assert_eq!(&request.data[..], &[0x05, 0x06, 0x12, 0x34, 0x56, 0x78]);
+
+        let ack = configure(
+            PppControlCode::ConfigureAck,
+            request.identifier,
+            &request.data,
+        );
+        assert_eq!(ppp.process(now, PppProtocol::Lcp, &ack), None);
+        assert_eq!(ppp.link_state(), LinkState::Establishing);
+
+        let options = [0x01, 0x04, 0x05, 0xdc, 0x05, 0x06, 0xca, 0xfe, 0xba, 0xbe];
+        let peer_request = configure(PppControlCode::ConfigureRequest, 7, &options);
+        let answer = ppp.process(now, PppProtocol::Lcp, &peer_request).unwrap();
+        assert_eq!(answer.code, PppControlCode::ConfigureAck);
+        assert_eq!(answer.identifier, 7);
+        assert_eq!(&answer.data[..], &options[..]);
+        assert_eq!(ppp.link_state(), LinkState::Negotiating);
+        now
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let mut ppp = Ppp::new(MAGIC_NUMBER);
+        let now = open_lcp(&mut ppp);
+
+        // IPCP asks for an address and DNS servers.
+        let request = ppp.poll(now).unwrap();
+        assert_eq!(request.protocol, PppProtocol::Ipcp);
+        assert_eq!(
+            &request.data[..],
+            &[3, 6, 0, 0, 0, 0, 129, 6, 0, 0, 0, 0, 131, 6, 0, 0, 0, 0]
+        );
+
+        // The peer assigns an address and a single DNS server.
+        let nak = [3, 6, 10, 0, 0, 2, 129, 6, 10, 0, 0, 53];
+        let nak = configure(PppControlCode::ConfigureNak, request.identifier, &nak);
+        assert_eq!(ppp.process(now, PppProtocol::Ipcp, &nak), None);
+        let request = ppp.poll(now).unwrap();
+        let reject = [131, 6, 0, 0, 0, 0];
+        let reject = configure(PppControlCode::ConfigureReject, request.identifier, &reject);
+        assert_eq!(ppp.process(now, PppProtocol::Ipcp, &reject), None);
+
+        let request = ppp.poll(now).unwrap();
+        assert_eq!(
+            &request.data[..],
+            &[3, 6, 10, 0, 0, 2, 129, 6, 10, 0, 0, 53]
+        );
+        let ack = configure(
+            PppControlCode::ConfigureAck,
+            request.identifier,
+            &request.data,
+        );
+        assert_eq!(ppp.process(now, PppProtocol::Ipcp, &ack), None);
+
+        let peer_request = configure(PppControlCode::ConfigureRequest, 1, &[3, 6, 10, 0, 0, 1]);
+        let answer = ppp.process(now, PppProtocol::Ipcp, &peer_request).unwrap();
+        assert_eq!(answer.code, PppControlCode::ConfigureAck);
+        assert_eq!(ppp.link_state(), LinkState::Opened);
+        assert_eq!(ppp.poll(now), None);
+        assert_eq!(ppp.poll_at(), None);
+
+        let config = ppp.ipv4_config().unwrap();
+        assert_eq!(config.address, Ipv4Address::new(10, 0, 0, 2));
+        assert_eq!(config.peer_address, Some(Ipv4Address::new(10, 0, 0, 1)));
+        assert_eq!(&config.dns_servers[..], &[Ipv4Address::new(10, 0, 0, 53)]);
+    }
+
+    #[test]
+    fn test_reject_options() {
+        let mut ppp = Ppp::new(MAGIC_NUMBER);
+        let now = Instant::from_millis(0);
+        ppp.poll(now).unwrap();
+
+        // Authentication and protocol field compression are rejected.
+        let options = [
+            0x03, 0x04, 0xc0, 0x23, 0x05, 0x06, 0xca, 0xfe, 0xba, 0xbe, 0x07, 0x02,
+        ];
+        let peer_request = configure(PppControlCode::ConfigureRequest, 1, &options);
+        let answer = ppp.process(now, PppProtocol::Lcp, &peer_request).unwrap();
+        assert_eq!(answer.code, PppControlCode::ConfigureReject);
+        assert_eq!(&answer.data[..], &[0x03, 0x04, 0xc0, 0x23, 0x07, 0x02]);
+
+        // A malformed request is not answered.
+        let peer_request = configure(PppControlCode::ConfigureRequest, 2, &[0x05, 0x01]);
+        assert_eq!(ppp.process(now, PppProtocol::Lcp, &peer_request), None);
+
+        // Unknown codes are rejected.
+        let unknown = configure(PppControlCode::Unknown(42), 3, &[0xaa]);
+        let answer = ppp.process(now, PppProtocol::Lcp, &unknown).unwrap();
+        assert_eq!(answer.code, PppControlCode::CodeReject);
+        assert_eq!(&answer.data[..], &[42, 3, 0, 5, 0xaa]);
+    }
+
+    #[test]
+    fn test_retransmit() {
+        let mut ppp = Ppp::new(MAGIC_NUMBER);
+        let mut now = Instant::from_millis(0);
+        let mut identifiers = std::vec::Vec::new();
+        while let Some(request) = ppp.poll(now) {
+            identifiers.push(request.identifier);
+            assert_eq!(ppp.poll(now), None);
+            assert_eq!(ppp.poll_at(), Some(now + RESTART_TIMEOUT));
+            now += RESTART_TIMEOUT;
+        }
+        assert_eq!(
+            identifiers,
+            (1..=MAX_CONFIGURE).collect::<std::vec::Vec<_>>()
+        );
+        assert_eq!(ppp.link_state(), LinkState::Stopped);
+        assert_eq!(ppp.poll_at(), None);
+
+        // A Configure-Request of the peer restarts the negotiation.
+        let peer_request = configure(PppControlCode::ConfigureRequest, 1, &[]);
+        let answer = ppp.process(now, PppProtocol::Lcp, &peer_request).unwrap();
+        assert_eq!(answer.code, PppControlCode::ConfigureAck);
+        assert!(ppp.poll(now).is_some());
+    }
+
+    #[test]
+    fn test_terminate() {
+        let mut ppp = Ppp::new(MAGIC_NUMBER);
+        let now = open_lcp(&mut ppp);
+
+        let echo = configure(
+            PppControlCode::EchoRequest,
+            9,
+            &[0xca, 0xfe, 0xba, 0xbe, 0x01],
+        );
+        let answer = ppp.process(now, PppProtocol::Lcp, &echo).unwrap();
+        assert_eq!(answer.code, PppControlCode::EchoReply);
+        assert_eq!(answer.identifier, 9);
+        assert_eq!(&answer.data[..], &[0x12, 0x34, 0x56, 0x78, 0x01]);
+
+        let terminate = configure(PppControlCode::TerminateRequest, 4, &[]);
+        let answer = ppp.process(now, PppProtocol::Lcp, &terminate).unwrap();
+        assert_eq!(answer.code, PppControlCode::TerminateAck);
+        assert_eq!(answer.identifier, 4);
+        assert_eq!(ppp.link_state(), LinkState::Stopped);
+        assert_eq!(ppp.poll(now), None);
+
+        // IPCP packets are ignored until the link is established again.
+        let ipcp = configure(PppControlCode::ConfigureRequest, 1, &[3, 6, 10, 0, 0, 1]);
+        assert_eq!(ppp.process(now, PppProtocol::Ipcp, &ipcp), None);
+    }
+}
@@ -6,7 +6,7 @@ use crate::wire::{IpAddress, IpCidr};
 use crate::wire::{Ipv4Address, Ipv4Cidr};
 #[cfg(feature = "proto-ipv6")]
 use crate::wire::{Ipv6Address, Ipv6Cidr};
-use crate::{Error, Result};
+use crate::{Error, Layer, Result};
 
 pub const MAX_ROUTE_COUNT: usize = 4;
 
@@ -79,7 +79,7 @@ impl Routes {
         let old = self.remove_default_ipv4_route();
         self.storage
             .push(Route::new_ipv4_gateway(gateway))
-            .map_err(|_| Error::Exhausted)?;
+            .map_err(|_| Error::Exhausted(Layer::Network))?;
         Ok(old)
     }
 
@@ -91,7 +91,7 @@ impl Routes {
         let old = self.remove_default_ipv6_route();
         self.storage
             .push(Route::new_ipv6_gateway(gateway))
-            .map_err(|_| Error::Exhausted)?;
+            .map_err(|_| Error::Exhausted(Layer::Network))?;
         Ok(old)
     }
 
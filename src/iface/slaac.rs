@@ -0,0 +1,319 @@
+// Heads up! Before working on this file you should read, at least,
+// RFC 4862, and the parts of RFC 4861 that discuss router advertisements.
+
+use heapless::Vec;
+
+use crate::time::{Duration, Instant};
+use crate::wire::{Ipv6Address, Ipv6Cidr, NdiscPrefixInfoFlags, NdiscPrefixInformation};
+
+pub const MAX_SLAAC_ADDR_COUNT: usize = 2;
+
+/// How long to wait for an answer to the neighbor solicitation sent for duplicate
+/// address detection, i.e. `RetransTimer` from RFC 4861 § 10.
+const DAD_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The valid lifetime below which prefix information cannot shorten the lifetime of
+/// an address, see RFC 4862 § 5.5.3 (e).
+const MIN_VALID_LIFETIME: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// The lifetime meaning "forever" in prefix information options.
+const INFINITE_LIFETIME: Duration = Duration::from_secs(0xffff_ffff);
+
+/// The state of an address configured from router advertisements.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressState {
+    /// Duplicate address detection has not started yet.
+    Tentative,
+    /// A neighbor solicitation was sent for the address, which is assigned unless
+    /// another node answers it before the given time.
+    Probing { until: Instant },
+    /// The address is assigned to the interface.
+    Assigned,
+}
+
+/// An address configured from router advertisements.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Address {
+    pub cidr: Ipv6Cidr,
+    pub state: AddressState,
+    /// `None` means "forever".
+    pub preferred_until: Option<Instant>,
+    /// `None` means "forever".
+    pub valid_until: Option<Instant>,
+}
+
+impl Address {
+    /// Query whether the preferred lifetime of the address has passed, in which case
+    /// it should not be used for new connections.
+    pub fn is_deprecated(&self, timestamp: Instant) -> bool {
+        match self.preferred_until {
+            Some(preferred_until) => timestamp >= preferred_until,
+            None => false,
+        }
+    }
+}
+
+/// A step of address autoconfiguration the interface has to carry out.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Action {
+    /// Send a router solicitation.
+    SolicitRouter,
+    /// Send a neighbor solicitation for duplicate address detection.
+    Probe(Ipv6Address),
+    /// Add the address to the interface.
+    Assign(Ipv6Cidr),
+    /// Remove the address from the interface.
+    Remove(Ipv6Cidr),
+}
+
+/// The state of stateless address autoconfiguration.
+#[derive(Debug)]
+pub(crate) struct Slaac {
+    addrs: Vec<Address, MAX_SLAAC_ADDR_COUNT>,
+    router_solicited: bool,
+}
+
+impl Slaac {
+    pub(crate) fn new() -> Slaac {
+        Slaac {
+            addrs: Vec::new(),
+            router_solicited: false,
+        }
+    }
+
+    pub(crate) fn addrs(&self) -> &[Address] {
+        &self.addrs
+    }
+
+    /// Process the prefix information of a router advertisement, forming the address
+    /// from the prefix and the interface identifier `iid`.
+    pub(crate) fn process_prefix(
+        &mut self,
+        info: &NdiscPrefixInformation,
+        iid: [u8; 8],
+        timestamp: Instant,
+    ) {
+        if !info.flags.contains(NdiscPrefixInfoFlags::ADDRCONF)
+            || info.prefix.is_link_local()
+            || info.preferred_lifetime > info.valid_lifetime
+            || info.prefix_len != 64
+        {
+            return;
+        }
+
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&info.prefix.as_bytes()[..8]);
+        bytes[8..].copy_from_slice(&iid);
+        let cidr = Ipv6Cidr::new(Ipv6Address::from_bytes(&bytes), info.prefix_len);
+
+        let expiry = |lifetime: Duration| {
+            if lifetime == INFINITE_LIFETIME {
+                None
+            } else {
+                Some(timestamp + lifetime)
+            }
+        };
+
+        if let Some(addr) = self.addrs.iter_mut().find(|addr| addr.cidr == cidr) {
+            addr.preferred_until = expiry(info.preferred_lifetime);
+
+            // Do not let a spoofed advertisement expire the address right away.
+            let remaining = match addr.valid_until {
+                Some(valid_until) if valid_until > timestamp => valid_until - timestamp,
+                Some(_) => Duration::ZERO,
+                None => INFINITE_LIFETIME,
+            };
+            if info.valid_lifetime > MIN_VALID_LIFETIME || info.valid_lifetime > remaining {
+                addr.valid_until = expiry(info.valid_lifetime);
+            } else if remaining > MIN_VALID_LIFETIME {
+                addr.valid_until = Some(timestamp + MIN_VALID_LIFETIME);
+            }
+            return;
+        }
+
+        if info.valid_lifetime == Duration::ZERO {
+            return;
+        }
+        let addr = Address {
+            cidr,
+            state: AddressState::Tentative,
+            preferred_until: expiry(info.preferred_lifetime),
+            valid_until: expiry(info.valid_lifetime),
+        };
+        if self.addrs.push(addr).is_err() {
+            net_debug!("slaac: no room for address {}", cidr);
+        }
+    }
+
+    /// Abandon `addr` if it is still undergoing duplicate address detection, since
+    /// another node uses or tries to use it.
+    pub(crate) fn process_duplicate(&mut self, addr: Ipv6Address) {
+        if let Some(index) = self
+            .addrs
+            .iter()
+            .position(|probe| probe.cidr.address() == addr && probe.state != AddressState::Assigned)
+        {
+            net_debug!("slaac: duplicate address {}", addr);
+            self.addrs.remove(index);
+        }
+    }
+
+    /// Return the next step of address autoconfiguration due at `timestamp`, if any.
+    pub(crate) fn poll(&mut self, timestamp: Instant) -> Option<Action> {
+        if !self.router_solicited {
+            self.router_solicited = true;
+            return Some(Action::SolicitRouter);
+        }
+
+        let mut index = 0;
+        while index < self.addrs.len() {
+            let expired = self.addrs[index]
+                .valid_until
+                .map_or(false, |valid_until| timestamp >= valid_until);
+            if expired {
+                let addr = self.addrs.remove(index);
+                if addr.state == AddressState::Assigned {
+                    return Some(Action::Remove(addr.cidr));
+                }
+                continue;
+            }
+
+            let addr = &mut self.addrs[index];
+            match addr.state {
+                AddressState::Tentative => {
+                    addr.state = AddressState::Probing {
+                        until: timestamp + DAD_TIMEOUT,
+                    };
+                    return Some(Action::Probe(addr.cidr.address()));
+                }
+                AddressState::Probing { until } if timestamp >= until => {
+                    addr.state = AddressState::Assigned;
+                    return Some(Action::Assign(addr.cidr));
+                }
+                _ => index += 1,
+            }
+        }
+        None
+    }
+
+    /// Return the time at which [poll](#method.poll) has something to do.
+    pub(crate) fn poll_at(&self) -> Option<Instant> {
+        if !self.router_solicited {
+            return Some(Instant::ZERO);
+        }
+
+        self.addrs
+            .iter()
+            .filter_map(|addr| {
+                let state_at = match addr.state {
+                    AddressState::Tentative => Some(Instant::ZERO),
+                    AddressState::Probing { until } => Some(until),
+                    AddressState::Assigned => None,
+                };
+                match (state_at, addr.valid_until) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                }
+            })
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const IID: [u8; 8] = [0x00, 0x00, 0x00, 0xff, 0xfe, 0x00, 0x00, 0x01];
+
+    fn prefix_info(valid: u64, preferred: u64) -> NdiscPrefixInformation {
+        NdiscPrefixInformation {
+            prefix_len: 64,
+            flags: NdiscPrefixInfoFlags::ON_LINK | NdiscPrefixInfoFlags::ADDRCONF,
+            valid_lifetime: Duration::from_secs(valid),
+            preferred_lifetime: Duration::from_secs(preferred),
+            prefix: Ipv6Address::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 0),
+        }
+    }
+
+    fn cidr() -> Ipv6Cidr {
+        Ipv6Cidr::new(
+            Ipv6Address::new(0x2001, 0xdb8, 0, 1, 0, 0xff, 0xfe00, 1),
+            64,
+        )
+    }
+
+    #[test]
+    fn test_autoconfiguration() {
+        let mut slaac = Slaac::new();
+        assert_eq!(slaac.poll_at(), Some(Instant::ZERO));
+        assert_eq!(slaac.poll(Instant::ZERO), Some(Action::SolicitRouter));
+        assert_eq!(slaac.poll(Instant::ZERO), None);
+        assert_eq!(slaac.poll_at(), None);
+
+        slaac.process_prefix(&prefix_info(3600, 1800), IID, Instant::from_secs(10));
+        assert_eq!(slaac.addrs()[0].state, AddressState::Tentative);
+        assert_eq!(
+            slaac.poll(Instant::from_secs(10)),
+            Some(Action::Probe(cidr().address()))
+        );
+        assert_eq!(slaac.poll_at(), Some(Instant::from_secs(11)));
+        assert_eq!(slaac.poll(Instant::from_secs(10)), None);
+        assert_eq!(
+            slaac.poll(Instant::from_secs(11)),
+            Some(Action::Assign(cidr()))
+        );
+
+        let addr = slaac.addrs()[0];
+        assert!(!addr.is_deprecated(Instant::from_secs(1809)));
+        assert!(addr.is_deprecated(Instant::from_secs(1810)));
+        assert_eq!(slaac.poll_at(), Some(Instant::from_secs(3610)));
+        assert_eq!(
+            slaac.poll(Instant::from_secs(3610)),
+            Some(Action::Remove(cidr()))
+        );
+        assert!(slaac.addrs().is_empty());
+    }
+
+    #[test]
+    fn test_duplicate() {
+        let mut slaac = Slaac::new();
+        slaac.poll(Instant::ZERO);
+        slaac.process_prefix(&prefix_info(3600, 1800), IID, Instant::ZERO);
+        slaac.poll(Instant::ZERO);
+        slaac.process_duplicate(cidr().address());
+        assert!(slaac.addrs().is_empty());
+        assert_eq!(slaac.poll(Instant::from_secs(1)), None);
+    }
+
+    #[test]
+    fn test_ignored_prefix() {
+        let mut slaac = Slaac::new();
+        let mut info = prefix_info(3600, 1800);
+        info.flags = NdiscPrefixInfoFlags::ON_LINK;
+        slaac.process_prefix(&info, IID, Instant::ZERO);
+        slaac.process_prefix(&prefix_info(3600, 7200), IID, Instant::ZERO);
+        slaac.process_prefix(&prefix_info(0, 0), IID, Instant::ZERO);
+        assert!(slaac.addrs().is_empty());
+    }
+
+    #[test]
+    fn test_valid_lifetime_update() {
+        let mut slaac = Slaac::new();
+        slaac.process_prefix(&prefix_info(86400, 3600), IID, Instant::ZERO);
+
+        // A short valid lifetime only lowers the remaining one to two hours.
+        slaac.process_prefix(&prefix_info(60, 0), IID, Instant::ZERO);
+        assert_eq!(slaac.addrs()[0].valid_until, Some(Instant::from_secs(7200)));
+        assert_eq!(slaac.addrs()[0].preferred_until, Some(Instant::ZERO));
+
+        // And does nothing once less than two hours remain.
+        slaac.process_prefix(&prefix_info(60, 0), IID, Instant::from_secs(3600));
+        assert_eq!(slaac.addrs()[0].valid_until, Some(Instant::from_secs(7200)));
+
+        slaac.process_prefix(&prefix_info(0xffff_ffff, 0xffff_ffff), IID, Instant::ZERO);
+        assert_eq!(slaac.addrs()[0].valid_until, None);
+        assert_eq!(slaac.addrs()[0].preferred_until, None);
+    }
+}
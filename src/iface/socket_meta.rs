@@ -1,6 +1,7 @@
 use super::SocketHandle;
 use crate::{
-    socket::PollAt,
+    phy::Priority,
+    socket::{PollAt, Readiness},
     time::{Duration, Instant},
     wire::IpAddress,
 };
@@ -41,6 +42,12 @@ pub(crate) struct Meta {
     pub(crate) handle: SocketHandle,
     /// See [NeighborState](struct.NeighborState.html).
     neighbor_state: NeighborState,
+    /// The priority of the packets sent by this socket, if it does not depend on their DSCP.
+    pub(crate) priority: Option<Priority>,
+    /// The readiness of the socket when the interface started polling it.
+    pub(crate) readiness: Readiness,
+    /// The readiness the socket gained while the interface polled it last.
+    pub(crate) readiness_gained: Readiness,
 }
 
 impl Meta {
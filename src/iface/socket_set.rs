@@ -2,7 +2,9 @@ use core::fmt;
 use managed::ManagedSlice;
 
 use super::socket_meta::Meta;
-use crate::socket::{AnySocket, Socket};
+use crate::phy::Priority;
+use crate::socket::{AnySocket, Readiness, Socket};
+use crate::storage::MemoryUsage;
 
 /// Opaque struct with space for storing one socket.
 ///
@@ -29,12 +31,43 @@ pub(crate) struct Item<'a> {
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct SocketHandle(usize);
 
+#[cfg(feature = "ffi")]
+impl SocketHandle {
+    pub(crate) fn from_index(index: usize) -> SocketHandle {
+        SocketHandle(index)
+    }
+
+    pub(crate) fn index(self) -> usize {
+        self.0
+    }
+}
+
 impl fmt::Display for SocketHandle {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "#{}", self.0)
     }
 }
 
+/// An iterator over the sockets that became ready during the last poll.
+///
+/// This struct is created by [SocketSet::readiness].
+#[derive(Debug)]
+pub struct ReadinessIter<'s, 'a> {
+    sockets: core::slice::Iter<'s, SocketStorage<'a>>,
+}
+
+impl<'s, 'a> Iterator for ReadinessIter<'s, 'a> {
+    type Item = (SocketHandle, Readiness);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.sockets
+            .by_ref()
+            .filter_map(|x| x.inner.as_ref())
+            .find(|i| !i.meta.readiness_gained.is_empty())
+            .map(|i| (i.meta.handle, i.meta.readiness_gained))
+    }
+}
+
 /// An extensible set of sockets.
 ///
 /// The lifetime `'a` is used when storing a `Socket<'a>`.
@@ -115,6 +148,34 @@ impl<'a> SocketSet<'a> {
         }
     }
 
+    /// Return the priority of the packets sent by a socket, if it was set.
+    ///
+    /// See also the [set_priority](#method.set_priority) method.
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    pub fn priority(&self, handle: SocketHandle) -> Option<Priority> {
+        match self.sockets[handle.0].inner.as_ref() {
+            Some(item) => item.meta.priority,
+            None => panic!("handle does not refer to a valid socket"),
+        }
+    }
+
+    /// Set the priority the device transmits the packets sent by a socket with, see
+    /// [Device::transmit_with_priority](crate::phy::Device::transmit_with_priority).
+    ///
+    /// By default, the priority of a packet is derived from its DSCP, see
+    /// [Priority::from_dscp].
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    pub fn set_priority(&mut self, handle: SocketHandle, priority: Option<Priority>) {
+        match self.sockets[handle.0].inner.as_mut() {
+            Some(item) => item.meta.priority = priority,
+            None => panic!("handle does not refer to a valid socket"),
+        }
+    }
+
     /// Remove a socket from the set, without changing its state.
     ///
     /// # Panics
@@ -137,6 +198,43 @@ impl<'a> SocketSet<'a> {
         self.items_mut().map(|i| (i.meta.handle, &mut i.socket))
     }
 
+    /// Return the sockets that became readable, writable or closed during the last call to
+    /// [Interface::poll](crate::iface::Interface::poll), along with what they became
+    /// ready for, so that only those need to be serviced afterwards.
+    ///
+    /// Readiness the sockets already had before the poll is not reported again, even if
+    /// it was not acted upon; use [Socket::readiness](crate::socket::Socket::readiness) to
+    /// query it.
+    pub fn readiness(&self) -> ReadinessIter<'_, 'a> {
+        ReadinessIter {
+            sockets: self.sockets.iter(),
+        }
+    }
+
+    /// Record the readiness of every socket before the interface polls them.
+    pub(crate) fn start_readiness(&mut self) {
+        for item in self.items_mut() {
+            item.meta.readiness = item.socket.readiness();
+            item.meta.readiness_gained = Readiness::empty();
+        }
+    }
+
+    /// Record the readiness every socket gained since `start_readiness` was called.
+    pub(crate) fn finish_readiness(&mut self) {
+        for item in self.items_mut() {
+            item.meta.readiness_gained = item.socket.readiness() - item.meta.readiness;
+        }
+    }
+
+    /// Return the number of bytes of buffer storage used and committed by all sockets in the set.
+    ///
+    /// The high-water mark is the sum of the high-water marks of the individual sockets.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.items().fold(MemoryUsage::default(), |acc, i| {
+            acc + i.socket.memory_usage()
+        })
+    }
+
     /// Iterate every socket in this set.
     pub(crate) fn items(&self) -> impl Iterator<Item = &Item<'a>> + '_ {
         self.sockets.iter().filter_map(|x| x.inner.as_ref())
@@ -146,4 +244,25 @@ impl<'a> SocketSet<'a> {
     pub(crate) fn items_mut(&mut self) -> impl Iterator<Item = &mut Item<'a>> + '_ {
         self.sockets.iter_mut().filter_map(|x| x.inner.as_mut())
     }
+
+    /// Call `f` with every socket in this set, along with an iterator over all the other
+    /// sockets, e.g. to pass them a packet sent by the socket.
+    #[cfg(feature = "socket-udp")]
+    pub(crate) fn for_each_with_others<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Item<'a>, &mut dyn Iterator<Item = &mut Item<'a>>),
+    {
+        for index in 0..self.sockets.len() {
+            let (before, rest) = self.sockets.split_at_mut(index);
+            if let Some((slot, after)) = rest.split_first_mut() {
+                if let Some(item) = slot.inner.as_mut() {
+                    let mut others = before
+                        .iter_mut()
+                        .chain(after.iter_mut())
+                        .filter_map(|x| x.inner.as_mut());
+                    f(item, &mut others);
+                }
+            }
+        }
+    }
 }
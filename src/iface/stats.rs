@@ -0,0 +1,170 @@
+use core::fmt;
+
+use crate::wire;
+
+/// The reason an incoming packet was dropped by the interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DropReason {
+    /// The packet had a field with an invalid value, or could not be parsed.
+    Malformed,
+    /// The packet was shorter than its headers or its length fields say.
+    Truncated,
+    /// The packet had an invalid checksum.
+    Checksum,
+    /// The packet was not addressed to this interface, or was rejected by a policy.
+    Filtered,
+    /// The packet uses a protocol, or a protocol feature, that is not supported.
+    Unsupported,
+    /// No socket accepted the packet.
+    NoSocket,
+    /// The packet was a fragment that could not be reassembled.
+    Reassembly,
+    /// The packet was addressed to another host, and its hop limit (TTL) was exhausted
+    /// before it could be forwarded.
+    HopLimit,
+    /// The packet had a source address that can not be valid, such as a multicast,
+    /// unspecified or loopback address, or an address of this interface.
+    Martian,
+    /// The packet was addressed to another host, and could not be forwarded because
+    /// no interface had a route to its destination or room for it.
+    Forwarding,
+    /// The packet was a TCP SYN for an endpoint whose listening sockets were all busy
+    /// with a connection.
+    BacklogFull,
+}
+
+impl DropReason {
+    const COUNT: usize = 11;
+
+    fn index(self) -> usize {
+        match self {
+            DropReason::Malformed => 0,
+            DropReason::Truncated => 1,
+            DropReason::Checksum => 2,
+            DropReason::Filtered => 3,
+            DropReason::Unsupported => 4,
+            DropReason::NoSocket => 5,
+            DropReason::Reassembly => 6,
+            DropReason::HopLimit => 7,
+            DropReason::Martian => 8,
+            DropReason::Forwarding => 9,
+            DropReason::BacklogFull => 10,
+        }
+    }
+}
+
+impl From<wire::Error> for DropReason {
+    fn from(err: wire::Error) -> Self {
+        match err {
+            wire::Error::Truncated => DropReason::Truncated,
+            wire::Error::Checksum => DropReason::Checksum,
+            wire::Error::Malformed => DropReason::Malformed,
+        }
+    }
+}
+
+impl fmt::Display for DropReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DropReason::Malformed => write!(f, "malformed"),
+            DropReason::Truncated => write!(f, "truncated"),
+            DropReason::Checksum => write!(f, "bad checksum"),
+            DropReason::Filtered => write!(f, "filtered"),
+            DropReason::Unsupported => write!(f, "unsupported"),
+            DropReason::NoSocket => write!(f, "no socket"),
+            DropReason::Reassembly => write!(f, "reassembly failed"),
+            DropReason::HopLimit => write!(f, "hop limit exceeded"),
+            DropReason::Martian => write!(f, "martian source"),
+            DropReason::Forwarding => write!(f, "not forwarded"),
+            DropReason::BacklogFull => write!(f, "backlog full"),
+        }
+    }
+}
+
+/// Counters of incoming packets dropped by an interface, bucketed by [DropReason].
+///
+/// The counters wrap around on overflow.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DropStats {
+    counts: [u32; DropReason::COUNT],
+}
+
+impl DropStats {
+    /// Return the number of packets dropped for the given reason.
+    pub fn count(&self, reason: DropReason) -> u32 {
+        self.counts[reason.index()]
+    }
+
+    /// Return the number of packets dropped for any reason.
+    pub fn total(&self) -> u32 {
+        self.counts
+            .iter()
+            .fold(0, |acc, count| acc.wrapping_add(*count))
+    }
+
+    pub(crate) fn record(&mut self, reason: DropReason) {
+        let count = &mut self.counts[reason.index()];
+        *count = count.wrapping_add(1);
+    }
+}
+
+/// Counters of the traffic of an interface.
+///
+/// Packets are counted at the device, i.e. including their link layer header, and including
+/// the packets sent and received by the interface itself, such as ARP or NDISC packets.
+/// Incoming packets that are dropped are counted as received, and bucketed by reason
+/// in [DropStats], which also counts checksum errors. The counters wrap around on overflow.
+#[cfg(feature = "stats")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stats {
+    /// The number of received packets.
+    pub rx_packets: u32,
+    /// The number of octets in received packets.
+    pub rx_bytes: u32,
+    /// The number of packets handed to the device for transmission.
+    pub tx_packets: u32,
+    /// The number of octets in packets handed to the device for transmission.
+    pub tx_bytes: u32,
+    /// The number of times the hardware address of a destination was not in the neighbor
+    /// cache, and had to be requested with ARP or NDISC.
+    pub neighbor_misses: u32,
+}
+
+#[cfg(feature = "stats")]
+impl Stats {
+    pub(crate) fn record_rx(&mut self, len: usize) {
+        self.rx_packets = self.rx_packets.wrapping_add(1);
+        self.rx_bytes = self.rx_bytes.wrapping_add(len as u32);
+    }
+
+    pub(crate) fn record_tx(&mut self, len: usize) {
+        self.tx_packets = self.tx_packets.wrapping_add(1);
+        self.tx_bytes = self.tx_bytes.wrapping_add(len as u32);
+    }
+
+    pub(crate) fn record_neighbor_miss(&mut self) {
+        self.neighbor_misses = self.neighbor_misses.wrapping_add(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut stats = DropStats::default();
+        assert_eq!(stats.total(), 0);
+
+        stats.record(DropReason::Malformed);
+        stats.record(DropReason::Malformed);
+        stats.record(DropReason::NoSocket);
+        assert_eq!(stats.count(DropReason::Malformed), 2);
+        assert_eq!(stats.count(DropReason::NoSocket), 1);
+        assert_eq!(stats.count(DropReason::Filtered), 0);
+        assert_eq!(stats.total(), 3);
+    }
+}
@@ -107,9 +107,12 @@ compile_error!("You must enable at least one of the following features: proto-ip
         feature = "socket-icmp",
         feature = "socket-dhcpv4",
         feature = "socket-dns",
+        feature = "socket-mdns",
+        feature = "socket-sntp",
+        feature = "socket-ethernet",
     ))
 ))]
-compile_error!("If you enable the socket feature, you must enable at least one of the following features: socket-raw, socket-udp, socket-tcp, socket-icmp, socket-dhcpv4, socket-dns");
+compile_error!("If you enable the socket feature, you must enable at least one of the following features: socket-raw, socket-udp, socket-tcp, socket-icmp, socket-dhcpv4, socket-dns, socket-mdns, socket-sntp, socket-ethernet");
 
 #[cfg(all(
     feature = "socket",
@@ -131,33 +134,88 @@ mod macros;
 mod parsers;
 mod rand;
 
+#[cfg(all(
+    feature = "async",
+    feature = "socket-tcp",
+    any(
+        feature = "medium-ethernet",
+        feature = "medium-ip",
+        feature = "medium-ieee802154"
+    )
+))]
+pub mod asynch;
+#[cfg(feature = "bsd")]
+pub mod bsd;
+#[cfg(all(
+    feature = "embedded-io",
+    any(
+        feature = "medium-ethernet",
+        feature = "medium-ip",
+        feature = "medium-ieee802154"
+    )
+))]
+pub mod embedded_io;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(all(
+    feature = "fuzz",
+    any(
+        feature = "medium-ethernet",
+        feature = "medium-ip",
+        feature = "medium-ieee802154"
+    )
+))]
+pub mod fuzz;
 #[cfg(any(
     feature = "medium-ethernet",
     feature = "medium-ip",
     feature = "medium-ieee802154"
 ))]
 pub mod iface;
+#[cfg(all(
+    feature = "embedded-nal",
+    any(
+        feature = "medium-ethernet",
+        feature = "medium-ip",
+        feature = "medium-ieee802154"
+    )
+))]
+pub mod nal;
 
 pub mod phy;
 #[cfg(feature = "socket")]
 pub mod socket;
 pub mod storage;
+#[cfg(all(
+    feature = "tftp",
+    any(
+        feature = "medium-ethernet",
+        feature = "medium-ip",
+        feature = "medium-ieee802154"
+    )
+))]
+pub mod tftp;
 pub mod time;
+#[cfg(all(feature = "tokio", unix))]
+pub mod tokio;
 pub mod wire;
 
 /// The error type for the networking stack.
+///
+/// Errors that can be raised by several layers of the stack carry the [Layer] that
+/// raised them.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// An operation cannot proceed because a buffer is empty or full.
-    Exhausted,
+    Exhausted(Layer),
     /// An operation is not permitted in the current state.
-    Illegal,
+    Illegal(Layer),
     /// An endpoint or address of a remote host could not be translated to a lower level address.
     /// E.g. there was no an Ethernet address corresponding to an IPv4 address in the ARP cache,
     /// or a TCP connection attempt was made to an unspecified endpoint.
-    Unaddressable,
+    Unaddressable(Layer),
 
     /// The operation is finished.
     /// E.g. when reading from a TCP socket, there's no more data to read because the remote
@@ -166,21 +224,21 @@ pub enum Error {
 
     /// An incoming packet could not be parsed because some of its fields were out of bounds
     /// of the received data.
-    Truncated,
+    Truncated(Layer),
     /// An incoming packet had an incorrect checksum and was dropped.
-    Checksum,
+    Checksum(Layer),
     /// An incoming packet could not be recognized and was dropped.
     /// E.g. an Ethernet packet with an unknown EtherType.
-    Unrecognized,
+    Unrecognized(Layer),
     /// An incoming IP packet has been split into several IP fragments and was dropped,
     /// since IP reassembly is not supported.
     Fragmented,
     /// An incoming packet was recognized but was self-contradictory.
     /// E.g. a TCP packet with both SYN and FIN flags set.
-    Malformed,
+    Malformed(Layer),
     /// An incoming packet was recognized but contradicted internal state.
     /// E.g. a TCP packet addressed to a socket that doesn't exist.
-    Dropped,
+    Dropped(Layer),
     /// An incoming fragment arrived too late.
     ReassemblyTimeout,
 
@@ -203,7 +261,7 @@ pub enum Error {
 
     /// An incoming packet was recognized but some parts are not supported by smoltcp.
     /// E.g. some bit configuration in a packet header is not supported, but is defined in an RFC.
-    NotSupported,
+    NotSupported(Layer),
 }
 
 #[cfg(feature = "std")]
@@ -215,16 +273,16 @@ pub type Result<T> = core::result::Result<T, Error>;
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Error::Exhausted => write!(f, "buffer space exhausted"),
-            Error::Illegal => write!(f, "illegal operation"),
-            Error::Unaddressable => write!(f, "unaddressable destination"),
+            Error::Exhausted(_) => write!(f, "buffer space exhausted"),
+            Error::Illegal(_) => write!(f, "illegal operation"),
+            Error::Unaddressable(_) => write!(f, "unaddressable destination"),
             Error::Finished => write!(f, "operation finished"),
-            Error::Truncated => write!(f, "truncated packet"),
-            Error::Checksum => write!(f, "checksum error"),
-            Error::Unrecognized => write!(f, "unrecognized packet"),
+            Error::Truncated(_) => write!(f, "truncated packet"),
+            Error::Checksum(_) => write!(f, "checksum error"),
+            Error::Unrecognized(_) => write!(f, "unrecognized packet"),
             Error::Fragmented => write!(f, "fragmented packet"),
-            Error::Malformed => write!(f, "malformed packet"),
-            Error::Dropped => write!(f, "dropped by socket"),
+            Error::Malformed(_) => write!(f, "malformed packet"),
+            Error::Dropped(_) => write!(f, "dropped by socket"),
             Error::ReassemblyTimeout => write!(f, "incoming fragment arrived too late"),
             Error::PacketAssemblerNotInit => write!(f, "packet assembler was not initialized"),
             Error::PacketAssemblerBufferTooSmall => {
@@ -242,13 +300,198 @@ impl fmt::Display for Error {
             Error::PacketAssemblerSetKeyNotFound => {
                 write!(f, "packet assembler set does not find key")
             }
-            Error::NotSupported => write!(f, "not supported by smoltcp"),
+            Error::NotSupported(_) => write!(f, "not supported by smoltcp"),
         }
     }
 }
 
-impl From<wire::Error> for Error {
-    fn from(_: wire::Error) -> Self {
-        Error::Malformed
+/// The broad cause of an [Error].
+///
+/// This allows applications to tell apart errors that are caused by misusing the API from
+/// errors caused by running out of storage, and from errors caused by garbage on the wire,
+/// without having to match on every variant of [Error].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ErrorCategory {
+    /// The operation was not valid in the current state, or was given invalid arguments.
+    Usage,
+    /// A buffer, cache or assembler was out of space.
+    Resource,
+    /// A received packet was truncated, corrupted, unrecognized or otherwise unacceptable.
+    Wire,
+}
+
+/// The layer of the network stack an [Error] belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Layer {
+    /// The physical device, e.g. a full transmit queue.
+    Phy,
+    /// The link layer, e.g. Ethernet, IEEE 802.15.4 or ARP.
+    Link,
+    /// The network layer, e.g. IPv4, IPv6 or fragment reassembly.
+    Network,
+    /// The transport layer, e.g. TCP or UDP.
+    Transport,
+    /// The protocols built on top of the transport layer, e.g. DHCP or DNS.
+    Application,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Layer::Phy => write!(f, "phy"),
+            Layer::Link => write!(f, "link"),
+            Layer::Network => write!(f, "network"),
+            Layer::Transport => write!(f, "transport"),
+            Layer::Application => write!(f, "application"),
+        }
+    }
+}
+
+impl Error {
+    /// Create the error raised by `layer` for a packet that `wire` failed to parse.
+    #[allow(unused)] // unused depending on which media are enabled
+    pub(crate) fn wire(layer: Layer, err: wire::Error) -> Error {
+        match err {
+            wire::Error::Truncated => Error::Truncated(layer),
+            wire::Error::Checksum => Error::Checksum(layer),
+            wire::Error::Malformed => Error::Malformed(layer),
+        }
+    }
+
+    /// Return the broad cause of this error.
+    pub fn category(&self) -> ErrorCategory {
+        match *self {
+            Error::Illegal(_) | Error::Unaddressable(_) | Error::Finished => ErrorCategory::Usage,
+            Error::Exhausted(_)
+            | Error::PacketAssemblerNotInit
+            | Error::PacketAssemblerBufferTooSmall
+            | Error::PacketAssemblerTooManyHoles
+            | Error::PacketAssemblerSetFull => ErrorCategory::Resource,
+            Error::Truncated(_)
+            | Error::Checksum(_)
+            | Error::Unrecognized(_)
+            | Error::Fragmented
+            | Error::Malformed(_)
+            | Error::Dropped(_)
+            | Error::ReassemblyTimeout
+            | Error::PacketAssemblerIncomplete
+            | Error::PacketAssemblerOverlap
+            | Error::PacketAssemblerSetKeyNotFound
+            | Error::NotSupported(_) => ErrorCategory::Wire,
+        }
+    }
+
+    /// Return the layer of the network stack that raised this error.
+    pub fn layer(&self) -> Layer {
+        match *self {
+            Error::Exhausted(layer)
+            | Error::Illegal(layer)
+            | Error::Unaddressable(layer)
+            | Error::Truncated(layer)
+            | Error::Checksum(layer)
+            | Error::Unrecognized(layer)
+            | Error::Malformed(layer)
+            | Error::Dropped(layer)
+            | Error::NotSupported(layer) => layer,
+            Error::Finished => Layer::Transport,
+            Error::Fragmented
+            | Error::ReassemblyTimeout
+            | Error::PacketAssemblerNotInit
+            | Error::PacketAssemblerBufferTooSmall
+            | Error::PacketAssemblerIncomplete
+            | Error::PacketAssemblerTooManyHoles
+            | Error::PacketAssemblerOverlap
+            | Error::PacketAssemblerSetFull
+            | Error::PacketAssemblerSetKeyNotFound => Layer::Network,
+        }
+    }
+
+    /// Return `true` if the error was caused by the contents of a received packet,
+    /// rather than by the application or by a lack of storage.
+    pub fn is_wire_error(&self) -> bool {
+        self.category() == ErrorCategory::Wire
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_category() {
+        assert_eq!(
+            Error::Illegal(Layer::Transport).category(),
+            ErrorCategory::Usage
+        );
+        assert_eq!(
+            Error::Unaddressable(Layer::Link).category(),
+            ErrorCategory::Usage
+        );
+        assert_eq!(Error::Finished.category(), ErrorCategory::Usage);
+        assert_eq!(
+            Error::Exhausted(Layer::Phy).category(),
+            ErrorCategory::Resource
+        );
+        assert_eq!(
+            Error::PacketAssemblerSetFull.category(),
+            ErrorCategory::Resource
+        );
+        assert_eq!(
+            Error::Truncated(Layer::Link).category(),
+            ErrorCategory::Wire
+        );
+        assert_eq!(Error::Dropped(Layer::Link).category(), ErrorCategory::Wire);
+        assert_eq!(
+            Error::PacketAssemblerOverlap.category(),
+            ErrorCategory::Wire
+        );
+    }
+
+    #[test]
+    fn test_layer() {
+        // The layer is the one that raised the error, not a property of its kind.
+        assert_eq!(Error::Unaddressable(Layer::Link).layer(), Layer::Link);
+        assert_eq!(Error::Unaddressable(Layer::Network).layer(), Layer::Network);
+        assert_eq!(
+            Error::Unaddressable(Layer::Transport).layer(),
+            Layer::Transport
+        );
+        assert_eq!(Error::Exhausted(Layer::Phy).layer(), Layer::Phy);
+        assert_eq!(Error::Malformed(Layer::Link).layer(), Layer::Link);
+        assert_eq!(Error::Finished.layer(), Layer::Transport);
+        assert_eq!(Error::ReassemblyTimeout.layer(), Layer::Network);
+    }
+
+    #[test]
+    fn test_is_wire_error() {
+        assert!(Error::Checksum(Layer::Network).is_wire_error());
+        assert!(Error::Unrecognized(Layer::Network).is_wire_error());
+        assert!(Error::Fragmented.is_wire_error());
+        assert!(!Error::Exhausted(Layer::Transport).is_wire_error());
+        assert!(!Error::Illegal(Layer::Application).is_wire_error());
+    }
+
+    #[test]
+    fn test_wire() {
+        let err = Error::wire(Layer::Link, wire::Error::Truncated);
+        assert_eq!(err, Error::Truncated(Layer::Link));
+        assert!(err.is_wire_error());
+        assert_eq!(
+            Error::wire(Layer::Network, wire::Error::Checksum).layer(),
+            Layer::Network
+        );
+        assert_eq!(
+            Error::wire(Layer::Transport, wire::Error::Malformed),
+            Error::Malformed(Layer::Transport)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "socket-tcp")]
+    fn test_socket_error_layer() {
+        let err: Error = socket::tcp::ConnectError::Unaddressable.into();
+        assert_eq!(err.layer(), Layer::Transport);
     }
 }
@@ -31,6 +31,23 @@ macro_rules! net_debug {
     ($($arg:expr),*) => (net_log!(debug, $($arg),*));
 }
 
+/// Enter a `tracing` span for the remainder of the enclosing scope.
+///
+/// Expands to `()` when the `tracing` feature is disabled, so the fields are never evaluated.
+#[cfg(feature = "tracing")]
+#[allow(unused_macros)]
+macro_rules! net_span {
+    ($name:expr, $($fields:tt)*) => { tracing::trace_span!($name, $($fields)*).entered() };
+}
+
+#[cfg(not(feature = "tracing"))]
+#[allow(unused_macros)]
+macro_rules! net_span {
+    ($($args:tt)*) => {
+        ()
+    };
+}
+
 macro_rules! enum_with_unknown {
     (
         $( #[$enum_attr:meta] )*
@@ -43,6 +60,7 @@ macro_rules! enum_with_unknown {
     ) => {
         #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
         #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         $( #[$enum_attr] )*
         pub enum $name {
             $(
@@ -71,3 +89,47 @@ macro_rules! enum_with_unknown {
         }
     }
 }
+
+/// Implement `Serialize` and `Deserialize` for a type through its `Display` and `FromStr`
+/// implementations, so that e.g. addresses are written as `"192.168.1.1"`.
+#[cfg(feature = "serde")]
+macro_rules! serde_via_str {
+    ($name:ty, $expecting:expr) => {
+        impl serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                struct Visitor;
+
+                impl<'de> serde::de::Visitor<'de> for Visitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                        f.write_str($expecting)
+                    }
+
+                    fn visit_str<E>(self, value: &str) -> ::core::result::Result<$name, E>
+                    where
+                        E: serde::de::Error,
+                    {
+                        value
+                            .parse()
+                            .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(value), &self))
+                    }
+                }
+
+                deserializer.deserialize_str(Visitor)
+            }
+        }
+    };
+}
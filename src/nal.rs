@@ -0,0 +1,556 @@
+/*! Implementations of the [embedded-nal] traits.
+
+This module provides a [Stack] implementing [TcpClientStack], [TcpFullStack],
+[UdpClientStack] and [UdpFullStack] on top of an interface, its device and a socket set,
+so that drivers and application crates written against embedded-nal work without
+custom glue.
+
+Since embedded-nal sockets are created on demand, but this stack does not allocate,
+the sockets are provided up front with [Stack::add_tcp_socket] and [Stack::add_udp_socket],
+and handed out by the `socket` methods. The interface is polled on every call, using
+the clock passed to [Stack::new] as the source of timestamps.
+
+[embedded-nal]: https://docs.rs/embedded-nal
+*/
+
+use embedded_nal::{
+    nb, IpAddr, SocketAddr, TcpClientStack, TcpFullStack, UdpClientStack, UdpFullStack,
+};
+use heapless::Vec;
+
+use crate::iface::{Interface, SocketHandle, SocketSet};
+use crate::phy::Device;
+use crate::socket::{tcp, udp};
+use crate::time::Instant;
+#[cfg(feature = "proto-ipv4")]
+use crate::wire::Ipv4Address;
+#[cfg(feature = "proto-ipv6")]
+use crate::wire::Ipv6Address;
+use crate::wire::{IpAddress, IpEndpoint};
+
+/// Error returned by the embedded-nal trait implementations of [Stack].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// All sockets of the requested type are in use, there is no room for another one,
+    /// or no ephemeral port is left for a socket that is not bound to a specific port.
+    NoSockets,
+    /// The operation is not permitted in the current state of the socket.
+    InvalidState,
+    /// The remote endpoint uses an IP version that is not enabled, or cannot be reached.
+    Unaddressable,
+    /// The connection was refused, reset or closed by the remote endpoint.
+    ConnectionReset,
+}
+
+/// A TCP socket handed out by a [Stack].
+#[derive(Debug)]
+pub struct TcpSocket {
+    handle: SocketHandle,
+    local_port: Option<u16>,
+    connecting: bool,
+}
+
+impl TcpSocket {
+    /// Return the handle of the underlying socket in the socket set.
+    pub fn handle(&self) -> SocketHandle {
+        self.handle
+    }
+}
+
+/// A UDP socket handed out by a [Stack].
+#[derive(Debug)]
+pub struct UdpSocket {
+    handle: SocketHandle,
+    remote: Option<IpEndpoint>,
+}
+
+impl UdpSocket {
+    /// Return the handle of the underlying socket in the socket set.
+    pub fn handle(&self) -> SocketHandle {
+        self.handle
+    }
+}
+
+fn to_endpoint(addr: SocketAddr) -> Result<IpEndpoint, Error> {
+    let ip = match addr.ip() {
+        #[cfg(feature = "proto-ipv4")]
+        IpAddr::V4(ip) => IpAddress::Ipv4(Ipv4Address(ip.octets())),
+        #[cfg(feature = "proto-ipv6")]
+        IpAddr::V6(ip) => IpAddress::Ipv6(Ipv6Address(ip.octets())),
+        #[allow(unreachable_patterns)]
+        _ => return Err(Error::Unaddressable),
+    };
+    Ok(IpEndpoint::new(ip, addr.port()))
+}
+
+fn from_endpoint(endpoint: IpEndpoint) -> SocketAddr {
+    let ip = match endpoint.addr {
+        #[cfg(feature = "proto-ipv4")]
+        IpAddress::Ipv4(addr) => IpAddr::V4(addr.0.into()),
+        #[cfg(feature = "proto-ipv6")]
+        IpAddress::Ipv6(addr) => IpAddr::V6(addr.0.into()),
+    };
+    SocketAddr::new(ip, endpoint.port)
+}
+
+/// An interface, its device, and up to `N` TCP and `N` UDP sockets to hand out.
+pub struct Stack<'a, D, const N: usize> {
+    iface: Interface<'a>,
+    device: D,
+    sockets: SocketSet<'a>,
+    clock: fn() -> Instant,
+    tcp_free: Vec<SocketHandle, N>,
+    udp_free: Vec<SocketHandle, N>,
+}
+
+impl<'a, D, const N: usize> Stack<'a, D, N>
+where
+    D: Device,
+{
+    /// Create a stack out of an interface, the device it uses, and a socket set,
+    /// using `clock` to timestamp polls.
+    pub fn new(
+        iface: Interface<'a>,
+        device: D,
+        sockets: SocketSet<'a>,
+        clock: fn() -> Instant,
+    ) -> Stack<'a, D, N> {
+        Stack {
+            iface,
+            device,
+            sockets,
+            clock,
+            tcp_free: Vec::new(),
+            udp_free: Vec::new(),
+        }
+    }
+
+    /// Return a reference to the interface.
+    pub fn iface(&self) -> &Interface<'a> {
+        &self.iface
+    }
+
+    /// Return a mutable reference to the interface.
+    pub fn iface_mut(&mut self) -> &mut Interface<'a> {
+        &mut self.iface
+    }
+
+    /// Return a mutable reference to the socket set.
+    pub fn sockets_mut(&mut self) -> &mut SocketSet<'a> {
+        &mut self.sockets
+    }
+
+    /// Add a TCP socket to the ones handed out by [TcpClientStack::socket].
+    ///
+    /// Returns `Err(Error::NoSockets)` if `N` TCP sockets were already added.
+    pub fn add_tcp_socket(&mut self, socket: tcp::Socket<'a>) -> Result<SocketHandle, Error> {
+        if self.tcp_free.is_full() {
+            return Err(Error::NoSockets);
+        }
+        let handle = self.sockets.add(socket);
+        self.tcp_free.push(handle).unwrap();
+        Ok(handle)
+    }
+
+    /// Add a UDP socket to the ones handed out by [UdpClientStack::socket].
+    ///
+    /// Returns `Err(Error::NoSockets)` if `N` UDP sockets were already added.
+    pub fn add_udp_socket(&mut self, socket: udp::Socket<'a>) -> Result<SocketHandle, Error> {
+        if self.udp_free.is_full() {
+            return Err(Error::NoSockets);
+        }
+        let handle = self.sockets.add(socket);
+        self.udp_free.push(handle).unwrap();
+        Ok(handle)
+    }
+
+    /// Transmit and receive packets, and return whether the state of any socket may have
+    /// changed.
+    pub fn poll(&mut self) -> bool {
+        match self
+            .iface
+            .poll((self.clock)(), &mut self.device, &mut self.sockets)
+        {
+            Ok(changed) => changed,
+            Err(err) => {
+                net_debug!("poll error: {}", err);
+                true
+            }
+        }
+    }
+
+    fn ephemeral_port(&mut self) -> Result<u16, Error> {
+        self.iface
+            .ephemeral_port(&self.sockets)
+            .ok_or(Error::NoSockets)
+    }
+
+    /// Take a free TCP socket that has finished closing.
+    fn take_tcp_socket(&mut self) -> Result<SocketHandle, Error> {
+        let sockets = &self.sockets;
+        let index = self
+            .tcp_free
+            .iter()
+            .position(|&handle| sockets.get::<tcp::Socket>(handle).state() == tcp::State::Closed)
+            .ok_or(Error::NoSockets)?;
+        Ok(self.tcp_free.swap_remove(index))
+    }
+}
+
+impl<'a, D, const N: usize> TcpClientStack for Stack<'a, D, N>
+where
+    D: Device,
+{
+    type TcpSocket = TcpSocket;
+    type Error = Error;
+
+    fn socket(&mut self) -> Result<TcpSocket, Error> {
+        Ok(TcpSocket {
+            handle: self.take_tcp_socket()?,
+            local_port: None,
+            connecting: false,
+        })
+    }
+
+    fn connect(&mut self, socket: &mut TcpSocket, remote: SocketAddr) -> nb::Result<(), Error> {
+        self.poll();
+        match self.sockets.get::<tcp::Socket>(socket.handle).state() {
+            tcp::State::Closed if socket.connecting => {
+                socket.connecting = false;
+                Err(Error::ConnectionReset.into())
+            }
+            tcp::State::Closed => {
+                let remote = to_endpoint(remote)?;
+                let local_port = match socket.local_port {
+                    Some(port) => port,
+                    None => self.ephemeral_port()?,
+                };
+                self.sockets
+                    .get_mut::<tcp::Socket>(socket.handle)
+                    .connect(self.iface.context(), remote, local_port)
+                    .map_err(|err| match err {
+                        tcp::ConnectError::InvalidState => Error::InvalidState,
+                        tcp::ConnectError::Unaddressable => Error::Unaddressable,
+                    })?;
+                socket.connecting = true;
+                self.poll();
+                Err(nb::Error::WouldBlock)
+            }
+            tcp::State::SynSent | tcp::State::SynReceived => Err(nb::Error::WouldBlock),
+            tcp::State::Established => {
+                socket.connecting = false;
+                Ok(())
+            }
+            _ => Err(Error::InvalidState.into()),
+        }
+    }
+
+    fn is_connected(&mut self, socket: &TcpSocket) -> Result<bool, Error> {
+        self.poll();
+        Ok(self.sockets.get::<tcp::Socket>(socket.handle).may_send())
+    }
+
+    fn send(&mut self, socket: &mut TcpSocket, buffer: &[u8]) -> nb::Result<usize, Error> {
+        self.poll();
+        let tcp_socket = self.sockets.get_mut::<tcp::Socket>(socket.handle);
+        if !tcp_socket.may_send() {
+            return match tcp_socket.state() {
+                tcp::State::SynSent | tcp::State::SynReceived => Err(nb::Error::WouldBlock),
+                _ => Err(Error::ConnectionReset.into()),
+            };
+        }
+        match tcp_socket.send_slice(buffer) {
+            Ok(0) if !buffer.is_empty() => Err(nb::Error::WouldBlock),
+            Ok(size) => {
+                self.poll();
+                Ok(size)
+            }
+            Err(tcp::SendError::InvalidState) => Err(Error::InvalidState.into()),
+        }
+    }
+
+    fn receive(&mut self, socket: &mut TcpSocket, buffer: &mut [u8]) -> nb::Result<usize, Error> {
+        self.poll();
+        let tcp_socket = self.sockets.get_mut::<tcp::Socket>(socket.handle);
+        match tcp_socket.recv_slice(buffer) {
+            Ok(0) if !buffer.is_empty() => Err(nb::Error::WouldBlock),
+            Ok(size) => Ok(size),
+            Err(tcp::RecvError::Finished) => Ok(0),
+            Err(tcp::RecvError::InvalidState) => match tcp_socket.state() {
+                tcp::State::SynSent | tcp::State::SynReceived => Err(nb::Error::WouldBlock),
+                _ => Err(Error::ConnectionReset.into()),
+            },
+        }
+    }
+
+    fn close(&mut self, socket: TcpSocket) -> Result<(), Error> {
+        self.sockets.get_mut::<tcp::Socket>(socket.handle).close();
+        // The socket came from the free list, so there is always room for it.
+        self.tcp_free.push(socket.handle).unwrap();
+        self.poll();
+        Ok(())
+    }
+}
+
+impl<'a, D, const N: usize> TcpFullStack for Stack<'a, D, N>
+where
+    D: Device,
+{
+    fn bind(&mut self, socket: &mut TcpSocket, local_port: u16) -> Result<(), Error> {
+        if self.sockets.get::<tcp::Socket>(socket.handle).state() != tcp::State::Closed {
+            return Err(Error::InvalidState);
+        }
+        socket.local_port = Some(local_port);
+        Ok(())
+    }
+
+    fn listen(&mut self, socket: &mut TcpSocket) -> Result<(), Error> {
+        let local_port = socket.local_port.ok_or(Error::InvalidState)?;
+        self.sockets
+            .get_mut::<tcp::Socket>(socket.handle)
+            .listen(local_port)
+            .map_err(|err| match err {
+                tcp::ListenError::InvalidState => Error::InvalidState,
+                tcp::ListenError::Unaddressable => Error::Unaddressable,
+            })
+    }
+
+    /// Accept a connection on a listening socket.
+    ///
+    /// The connection is handed out as a new socket, while the listening socket is moved
+    /// to a free socket, which fails with `Error::NoSockets` if there is none left.
+    fn accept(&mut self, socket: &mut TcpSocket) -> nb::Result<(TcpSocket, SocketAddr), Error> {
+        self.poll();
+        let local_port = socket.local_port.ok_or(Error::InvalidState)?;
+        let remote = match self.sockets.get::<tcp::Socket>(socket.handle).state() {
+            tcp::State::Listen | tcp::State::SynReceived => return Err(nb::Error::WouldBlock),
+            tcp::State::Closed => return Err(Error::InvalidState.into()),
+            _ => self
+                .sockets
+                .get::<tcp::Socket>(socket.handle)
+                .remote_endpoint()
+                .ok_or(Error::ConnectionReset)?,
+        };
+
+        let listener = self.take_tcp_socket()?;
+        self.sockets
+            .get_mut::<tcp::Socket>(listener)
+            .listen(local_port)
+            .map_err(|_| Error::InvalidState)?;
+        let connection = TcpSocket {
+            handle: core::mem::replace(&mut socket.handle, listener),
+            local_port: Some(local_port),
+            connecting: false,
+        };
+        Ok((connection, from_endpoint(remote)))
+    }
+}
+
+impl<'a, D, const N: usize> UdpClientStack for Stack<'a, D, N>
+where
+    D: Device,
+{
+    type UdpSocket = UdpSocket;
+    type Error = Error;
+
+    fn socket(&mut self) -> Result<UdpSocket, Error> {
+        Ok(UdpSocket {
+            handle: self.udp_free.pop().ok_or(Error::NoSockets)?,
+            remote: None,
+        })
+    }
+
+    fn connect(&mut self, socket: &mut UdpSocket, remote: SocketAddr) -> Result<(), Error> {
+        let remote = to_endpoint(remote)?;
+        if !self.sockets.get::<udp::Socket>(socket.handle).is_open() {
+            let local_port = self.ephemeral_port()?;
+            UdpFullStack::bind(self, socket, local_port)?;
+        }
+        socket.remote = Some(remote);
+        Ok(())
+    }
+
+    fn send(&mut self, socket: &mut UdpSocket, buffer: &[u8]) -> nb::Result<(), Error> {
+        let remote = socket.remote.ok_or(Error::InvalidState)?;
+        match self
+            .sockets
+            .get_mut::<udp::Socket>(socket.handle)
+            .send_slice(buffer, remote)
+        {
+            Ok(()) => {
+                self.poll();
+                Ok(())
+            }
+            Err(udp::SendError::BufferFull) => {
+                self.poll();
+                Err(nb::Error::WouldBlock)
+            }
+            Err(udp::SendError::Unaddressable) => Err(Error::Unaddressable.into()),
+        }
+    }
+
+    fn receive(
+        &mut self,
+        socket: &mut UdpSocket,
+        buffer: &mut [u8],
+    ) -> nb::Result<(usize, SocketAddr), Error> {
+        self.poll();
+        let udp_socket = self.sockets.get_mut::<udp::Socket>(socket.handle);
+        if !udp_socket.is_open() {
+            return Err(Error::InvalidState.into());
+        }
+        match udp_socket.recv_slice(buffer) {
+            Ok((size, remote)) => Ok((size, from_endpoint(remote))),
+            Err(udp::RecvError::Exhausted) => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    fn close(&mut self, socket: UdpSocket) -> Result<(), Error> {
+        self.sockets.get_mut::<udp::Socket>(socket.handle).close();
+        // The socket came from the free list, so there is always room for it.
+        self.udp_free.push(socket.handle).unwrap();
+        Ok(())
+    }
+}
+
+impl<'a, D, const N: usize> UdpFullStack for Stack<'a, D, N>
+where
+    D: Device,
+{
+    fn bind(&mut self, socket: &mut UdpSocket, local_port: u16) -> Result<(), Error> {
+        self.sockets
+            .get_mut::<udp::Socket>(socket.handle)
+            .bind(local_port)
+            .map_err(|err| match err {
+                udp::BindError::InvalidState => Error::InvalidState,
+                udp::BindError::Unaddressable => Error::Unaddressable,
+            })
+    }
+
+    fn send_to(
+        &mut self,
+        socket: &mut UdpSocket,
+        remote: SocketAddr,
+        buffer: &[u8],
+    ) -> nb::Result<(), Error> {
+        if !self.sockets.get::<udp::Socket>(socket.handle).is_open() {
+            let local_port = self.ephemeral_port()?;
+            UdpFullStack::bind(self, socket, local_port)?;
+        }
+        let remote = to_endpoint(remote)?;
+        match self
+            .sockets
+            .get_mut::<udp::Socket>(socket.handle)
+            .send_slice(buffer, remote)
+        {
+            Ok(()) => {
+                self.poll();
+                Ok(())
+            }
+            Err(udp::SendError::BufferFull) => {
+                self.poll();
+                Err(nb::Error::WouldBlock)
+            }
+            Err(udp::SendError::Unaddressable) => Err(Error::Unaddressable.into()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "medium-ip", feature = "proto-ipv4"))]
+mod test {
+    use super::*;
+    use crate::iface::InterfaceBuilder;
+    use crate::phy::{Loopback, Medium};
+    use crate::wire::IpCidr;
+
+    fn stack() -> Stack<'static, Loopback, 3> {
+        let mut device = Loopback::new(Medium::Ip);
+        let mut ip_addrs = heapless::Vec::<IpCidr, 5>::new();
+        ip_addrs
+            .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
+            .unwrap();
+        let iface = InterfaceBuilder::new()
+            .ip_addrs(ip_addrs)
+            .finalize(&mut device);
+        let mut stack = Stack::new(iface, device, SocketSet::new(vec![]), Instant::now);
+        for _ in 0..3 {
+            let tcp_socket = tcp::Socket::new(
+                tcp::SocketBuffer::new(vec![0; 64]),
+                tcp::SocketBuffer::new(vec![0; 64]),
+            );
+            stack.add_tcp_socket(tcp_socket).unwrap();
+            let udp_socket = udp::Socket::new(
+                udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 1], vec![0; 64]),
+                udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 1], vec![0; 64]),
+            );
+            stack.add_udp_socket(udp_socket).unwrap();
+        }
+        stack
+    }
+
+    fn local(port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4([127, 0, 0, 1].into()), port)
+    }
+
+    #[test]
+    fn test_tcp() {
+        let mut stack = stack();
+        let mut listener = TcpClientStack::socket(&mut stack).unwrap();
+        TcpFullStack::bind(&mut stack, &mut listener, 80).unwrap();
+        TcpFullStack::listen(&mut stack, &mut listener).unwrap();
+
+        let mut client = TcpClientStack::socket(&mut stack).unwrap();
+        nb::block!(TcpClientStack::connect(&mut stack, &mut client, local(80))).unwrap();
+        let (mut server, remote) = nb::block!(stack.accept(&mut listener)).unwrap();
+        let client_endpoint = stack
+            .sockets
+            .get::<tcp::Socket>(client.handle)
+            .local_endpoint()
+            .unwrap();
+        assert_eq!(remote, from_endpoint(client_endpoint));
+        assert_eq!(
+            TcpClientStack::socket(&mut stack).unwrap_err(),
+            Error::NoSockets
+        );
+
+        assert_eq!(
+            nb::block!(TcpClientStack::send(&mut stack, &mut client, b"hello")),
+            Ok(5)
+        );
+        let mut buf = [0; 16];
+        assert_eq!(
+            nb::block!(TcpClientStack::receive(&mut stack, &mut server, &mut buf)),
+            Ok(5)
+        );
+        assert_eq!(&buf[..5], b"hello");
+    }
+
+    #[test]
+    fn test_udp() {
+        let mut stack = stack();
+        let mut server = UdpClientStack::socket(&mut stack).unwrap();
+        UdpFullStack::bind(&mut stack, &mut server, 53).unwrap();
+
+        let mut client = UdpClientStack::socket(&mut stack).unwrap();
+        UdpClientStack::connect(&mut stack, &mut client, local(53)).unwrap();
+        nb::block!(UdpClientStack::send(&mut stack, &mut client, b"hello")).unwrap();
+
+        let mut buf = [0; 16];
+        let (size, remote) =
+            nb::block!(UdpClientStack::receive(&mut stack, &mut server, &mut buf)).unwrap();
+        assert_eq!(&buf[..size], b"hello");
+        let client_port = stack
+            .sockets
+            .get::<udp::Socket>(client.handle)
+            .endpoint()
+            .port;
+        assert_eq!(remote, local(client_port));
+
+        nb::block!(stack.send_to(&mut server, remote, b"world")).unwrap();
+        assert_eq!(
+            nb::block!(UdpClientStack::receive(&mut stack, &mut client, &mut buf)),
+            Ok((5, local(53)))
+        );
+    }
+}
@@ -8,7 +8,7 @@ use core::str::FromStr;
 
 #[cfg(feature = "medium-ethernet")]
 use crate::wire::EthernetAddress;
-use crate::wire::{IpAddress, IpCidr, IpEndpoint};
+use crate::wire::{IpAddress, IpCidr, IpEndpoint, IpListenEndpoint};
 #[cfg(feature = "proto-ipv4")]
 use crate::wire::{Ipv4Address, Ipv4Cidr};
 #[cfg(feature = "proto-ipv6")]
@@ -471,11 +471,34 @@ impl FromStr for IpCidr {
 impl FromStr for IpEndpoint {
     type Err = ();
 
+    /// Parse a string representation of an IP endpoint.
     fn from_str(s: &str) -> Result<IpEndpoint> {
         Parser::new(s).until_eof(|p| p.accept_ip_endpoint())
     }
 }
 
+impl FromStr for IpListenEndpoint {
+    type Err = ();
+
+    /// Parse a string representation of an IP listen endpoint, where `*` stands for
+    /// any address.
+    fn from_str(s: &str) -> Result<IpListenEndpoint> {
+        Parser::new(s).until_eof(|p| {
+            if p.lookahead_char(b'*') {
+                p.accept_char(b'*')?;
+                p.accept_char(b':')?;
+                let port = p.accept_number(5, 65535, false)?;
+                Ok(IpListenEndpoint {
+                    addr: None,
+                    port: port as u16,
+                })
+            } else {
+                p.accept_ip_endpoint().map(IpListenEndpoint::from)
+            }
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -757,4 +780,37 @@ mod test {
             })
         );
     }
+
+    #[test]
+    fn test_listen_endpoint() {
+        assert_eq!(IpListenEndpoint::from_str(""), Err(()));
+        assert_eq!(IpListenEndpoint::from_str("*"), Err(()));
+        assert_eq!(
+            IpListenEndpoint::from_str("*:80"),
+            Ok(IpListenEndpoint::from(80))
+        );
+        #[cfg(feature = "proto-ipv4")]
+        assert_eq!(
+            IpListenEndpoint::from_str("127.0.0.1:80"),
+            Ok(IpListenEndpoint::from((IpAddress::v4(127, 0, 0, 1), 80)))
+        );
+    }
+
+    #[test]
+    fn test_endpoint_display() {
+        #[cfg(feature = "proto-ipv4")]
+        for s in ["192.168.1.10:8080", "*:80"] {
+            assert_eq!(format!("{}", IpListenEndpoint::from_str(s).unwrap()), s);
+        }
+        #[cfg(feature = "proto-ipv4")]
+        assert_eq!(
+            format!("{}", IpEndpoint::from_str("192.168.1.10:8080").unwrap()),
+            "192.168.1.10:8080"
+        );
+        #[cfg(feature = "proto-ipv6")]
+        assert_eq!(
+            format!("{}", IpEndpoint::from_str("[fe80::1]:8080").unwrap()),
+            "[fe80::1]:8080"
+        );
+    }
 }
@@ -1,8 +1,8 @@
 use core::cell::RefCell;
 
-use crate::phy::{self, Device, DeviceCapabilities};
+use crate::phy::{self, Device, DeviceCapabilities, Priority};
 use crate::time::{Duration, Instant};
-use crate::{Error, Result};
+use crate::{Error, Layer, Result};
 
 // We use our own RNG to stay compatible with #![no_std].
 // The use of the RNG below has a slight bias, but it doesn't matter.
@@ -23,6 +23,8 @@ const MTU: usize = 1536;
 struct Config {
     corrupt_pct: u8,
     drop_pct: u8,
+    duplicate_pct: u8,
+    reorder_pct: u8,
     max_size: usize,
     max_tx_rate: u64,
     max_rx_rate: u64,
@@ -88,16 +90,42 @@ impl State {
     }
 }
 
+/// A received packet held back, to be received again or after another one.
+#[derive(Debug)]
+struct Held {
+    buffer: [u8; MTU],
+    len: usize,
+    /// Whether the packet is released only once another packet is received.
+    after_next: bool,
+}
+
+impl Held {
+    fn new(packet: &[u8], after_next: bool) -> Held {
+        let mut buffer = [0; MTU];
+        buffer[..packet.len()].copy_from_slice(packet);
+        Held {
+            buffer,
+            len: packet.len(),
+            after_next,
+        }
+    }
+}
+
 /// A fault injector device.
 ///
 /// A fault injector is a device that alters packets traversing through it to simulate
-/// adverse network conditions (such as random packet loss or corruption), or software
-/// or hardware limitations (such as a limited number or size of usable network buffers).
+/// adverse network conditions (such as random packet loss, corruption, duplication or
+/// reordering), or software or hardware limitations (such as a limited number or size
+/// of usable network buffers).
+///
+/// Duplicated and reordered packets are held back in the fault injector, one at a time.
+/// A reordered packet is only received after the next packet from the underlying device.
 #[derive(Debug)]
 pub struct FaultInjector<D: Device> {
     inner: D,
     state: RefCell<State>,
     config: Config,
+    held: RefCell<Option<Held>>,
 }
 
 impl<D: Device> FaultInjector<D> {
@@ -113,6 +141,7 @@ impl<D: Device> FaultInjector<D> {
             inner,
             state: RefCell::new(state),
             config: Config::default(),
+            held: RefCell::new(None),
         }
     }
 
@@ -131,6 +160,16 @@ impl<D: Device> FaultInjector<D> {
         self.config.drop_pct
     }
 
+    /// Return the probability of receiving a packet twice, in percents.
+    pub fn duplicate_chance(&self) -> u8 {
+        self.config.duplicate_pct
+    }
+
+    /// Return the probability of receiving a packet after the next one, in percents.
+    pub fn reorder_chance(&self) -> u8 {
+        self.config.reorder_pct
+    }
+
     /// Return the maximum packet size, in octets.
     pub fn max_packet_size(&self) -> usize {
         self.config.max_size
@@ -173,6 +212,28 @@ impl<D: Device> FaultInjector<D> {
         self.config.drop_pct = pct
     }
 
+    /// Set the probability of receiving a packet twice, in percents.
+    ///
+    /// # Panics
+    /// This function panics if the probability is not between 0% and 100%.
+    pub fn set_duplicate_chance(&mut self, pct: u8) {
+        if pct > 100 {
+            panic!("percentage out of range")
+        }
+        self.config.duplicate_pct = pct
+    }
+
+    /// Set the probability of receiving a packet after the next one, in percents.
+    ///
+    /// # Panics
+    /// This function panics if the probability is not between 0% and 100%.
+    pub fn set_reorder_chance(&mut self, pct: u8) {
+        if pct > 100 {
+            panic!("percentage out of range")
+        }
+        self.config.reorder_pct = pct
+    }
+
     /// Set the maximum packet size, in octets.
     pub fn set_max_packet_size(&mut self, size: usize) {
         self.config.max_size = size
@@ -216,12 +277,36 @@ impl<D: Device> Device for FaultInjector<D> {
             ref mut inner,
             ref state,
             config,
+            ref held,
         } = self;
+        let released = held
+            .borrow()
+            .as_ref()
+            .map_or(false, |packet| !packet.after_next);
+        if released {
+            return inner.transmit().map(|tx_token| {
+                let rx = RxToken {
+                    state,
+                    config,
+                    token: None,
+                    held,
+                    corrupt: [0; MTU],
+                };
+                let tx = TxToken {
+                    state,
+                    config,
+                    token: tx_token,
+                    junk: [0; MTU],
+                };
+                (rx, tx)
+            });
+        }
         inner.receive().map(|(rx_token, tx_token)| {
             let rx = RxToken {
                 state,
                 config,
-                token: rx_token,
+                token: Some(rx_token),
+                held,
                 corrupt: [0; MTU],
             };
             let tx = TxToken {
@@ -235,12 +320,17 @@ impl<D: Device> Device for FaultInjector<D> {
     }
 
     fn transmit(&mut self) -> Option<Self::TxToken<'_>> {
+        self.transmit_with_priority(Priority::BEST_EFFORT)
+    }
+
+    fn transmit_with_priority(&mut self, priority: Priority) -> Option<Self::TxToken<'_>> {
         let &mut Self {
             ref mut inner,
             ref state,
             config,
+            ..
         } = self;
-        inner.transmit().map(|token| TxToken {
+        inner.transmit_with_priority(priority).map(|token| TxToken {
             state,
             config,
             token,
@@ -253,7 +343,9 @@ impl<D: Device> Device for FaultInjector<D> {
 pub struct RxToken<'a, Rx: phy::RxToken> {
     state: &'a RefCell<State>,
     config: Config,
-    token: Rx,
+    /// The token of the underlying device, or `None` to receive the held packet.
+    token: Option<Rx>,
+    held: &'a RefCell<Option<Held>>,
     corrupt: [u8; MTU],
 }
 
@@ -262,29 +354,53 @@ impl<'a, Rx: phy::RxToken> phy::RxToken for RxToken<'a, Rx> {
     where
         F: FnOnce(&mut [u8]) -> Result<R>,
     {
-        if self.state.borrow_mut().maybe(self.config.drop_pct) {
-            net_trace!("rx: randomly dropping a packet");
-            return Err(Error::Exhausted);
-        }
-        if !self
-            .state
-            .borrow_mut()
-            .maybe_receive(&self.config, timestamp)
-        {
-            net_trace!("rx: dropping a packet because of rate limiting");
-            return Err(Error::Exhausted);
-        }
         let Self {
             token,
             config,
             state,
+            held,
             mut corrupt,
         } = self;
+        let token = match token {
+            Some(token) => token,
+            None => {
+                let mut packet = held.borrow_mut().take().unwrap();
+                net_trace!("rx: receiving a held back packet");
+                return f(&mut packet.buffer[..packet.len]);
+            }
+        };
+
+        if state.borrow_mut().maybe(config.drop_pct) {
+            net_trace!("rx: randomly dropping a packet");
+            return Err(Error::Exhausted(Layer::Phy));
+        }
+        if !state.borrow_mut().maybe_receive(&config, timestamp) {
+            net_trace!("rx: dropping a packet because of rate limiting");
+            return Err(Error::Exhausted(Layer::Phy));
+        }
         token.consume(timestamp, |buffer| {
             if config.max_size > 0 && buffer.as_ref().len() > config.max_size {
                 net_trace!("rx: dropping a packet that is too large");
-                return Err(Error::Exhausted);
+                return Err(Error::Exhausted(Layer::Phy));
+            }
+            let mut held = held.borrow_mut();
+            match *held {
+                // This packet overtakes the held one, which is received next.
+                Some(ref mut packet) => packet.after_next = false,
+                None if buffer.len() > MTU => (),
+                None if state.borrow_mut().maybe(config.reorder_pct) => {
+                    net_trace!("rx: randomly reordering a packet");
+                    *held = Some(Held::new(buffer, true));
+                    return Err(Error::Exhausted(Layer::Phy));
+                }
+                None if state.borrow_mut().maybe(config.duplicate_pct) => {
+                    net_trace!("rx: randomly duplicating a packet");
+                    *held = Some(Held::new(buffer, false));
+                }
+                None => (),
             }
+            drop(held);
+
             if state.borrow_mut().maybe(config.corrupt_pct) {
                 net_trace!("rx: randomly corrupting a packet");
                 let mut corrupt = &mut corrupt[..buffer.len()];
@@ -1,4 +1,4 @@
-use crate::phy::{self, Device, DeviceCapabilities};
+use crate::phy::{self, Device, DeviceCapabilities, Priority};
 use crate::time::Instant;
 use crate::Result;
 
@@ -82,12 +82,16 @@ where
     }
 
     fn transmit(&mut self) -> Option<Self::TxToken<'_>> {
+        self.transmit_with_priority(Priority::BEST_EFFORT)
+    }
+
+    fn transmit_with_priority(&mut self, priority: Priority) -> Option<Self::TxToken<'_>> {
         let &mut Self {
             ref mut inner,
             fuzz_rx: _,
             ref fuzz_tx,
         } = self;
-        inner.transmit().map(|token| TxToken {
+        inner.transmit_with_priority(priority).map(|token| TxToken {
             fuzzer: fuzz_tx,
             token: token,
         })
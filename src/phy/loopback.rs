@@ -34,6 +34,7 @@ impl Device for Loopback {
         DeviceCapabilities {
             max_transmission_unit: 65535,
             medium: self.medium,
+            loopback: true,
             ..DeviceCapabilities::default()
         }
     }
@@ -0,0 +1,89 @@
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+use ::mio::event::Source;
+use ::mio::unix::SourceFd;
+use ::mio::{Events, Interest, Poll, Registry, Token};
+
+#[cfg(any(
+    feature = "medium-ethernet",
+    feature = "medium-ip",
+    feature = "medium-ieee802154"
+))]
+use crate::iface::{Interface, SocketSet};
+#[cfg(feature = "phy-raw_socket")]
+use crate::phy::RawSocket;
+#[cfg(all(
+    feature = "phy-tuntap_interface",
+    any(target_os = "linux", target_os = "android")
+))]
+use crate::phy::TunTapInterface;
+#[cfg(any(
+    feature = "medium-ethernet",
+    feature = "medium-ip",
+    feature = "medium-ieee802154"
+))]
+use crate::time::Instant;
+
+macro_rules! impl_source_fd {
+    ($ty:ty) => {
+        impl Source for $ty {
+            fn register(
+                &mut self,
+                registry: &Registry,
+                token: Token,
+                interests: Interest,
+            ) -> io::Result<()> {
+                SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+            }
+
+            fn reregister(
+                &mut self,
+                registry: &Registry,
+                token: Token,
+                interests: Interest,
+            ) -> io::Result<()> {
+                SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+            }
+
+            fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+                SourceFd(&self.as_raw_fd()).deregister(registry)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "phy-raw_socket")]
+impl_source_fd!(RawSocket);
+#[cfg(all(
+    feature = "phy-tuntap_interface",
+    any(target_os = "linux", target_os = "android")
+))]
+impl_source_fd!(TunTapInterface);
+
+/// Wait for events on `poll`, but no longer than until `iface` needs to be polled again.
+///
+/// This is the [mio] counterpart of [wait](fn.wait.html): register the device with `poll`
+/// using [Interest::READABLE], then call [Interface::poll] and this function in a loop.
+/// Since mio notifications are edge-triggered, the device must be drained on every
+/// iteration, which [Interface::poll] already does.
+///
+/// [mio]: https://docs.rs/mio
+#[cfg(any(
+    feature = "medium-ethernet",
+    feature = "medium-ip",
+    feature = "medium-ieee802154"
+))]
+pub fn wait_mio(
+    poll: &mut Poll,
+    events: &mut Events,
+    iface: &mut Interface<'_>,
+    sockets: &SocketSet<'_>,
+    timestamp: Instant,
+) -> io::Result<()> {
+    let timeout = iface.poll_delay(timestamp, sockets).map(Into::into);
+    match poll.poll(events, timeout) {
+        Err(err) if err.kind() == io::ErrorKind::Interrupted => Ok(()),
+        result => result,
+    }
+}
@@ -4,12 +4,15 @@ The `phy` module deals with the *network devices*. It provides a trait
 for transmitting and receiving frames, [Device](trait.Device.html)
 and implementations of it:
 
-  * the [_loopback_](struct.Loopback.html), for zero dependency testing;
+  * the [_loopback_](struct.Loopback.html), for zero dependency testing, and
+    [StaticLoopback](struct.StaticLoopback.html), which does not need an allocator;
   * _middleware_ [Tracer](struct.Tracer.html) and
-    [FaultInjector](struct.FaultInjector.html), to facilitate debugging;
+    [FaultInjector](struct.FaultInjector.html), to facilitate debugging,
+    and [Transformer](struct.Transformer.html), to encapsulate packets for tunnels or
+    [tag frames](struct.Vlan.html) with a VLAN;
   * _adapters_ [RawSocket](struct.RawSocket.html) and
     [TunTapInterface](struct.TunTapInterface.html), to transmit and receive frames
-    on the host OS.
+    on the host OS; with the `mio` feature, these implement `mio::event::Source`.
 */
 #![cfg_attr(
     feature = "medium-ethernet",
@@ -102,15 +105,21 @@ mod fault_injector;
 mod fuzz_injector;
 #[cfg(feature = "alloc")]
 mod loopback;
+#[cfg(all(feature = "mio", unix))]
+mod mio;
 mod pcap_writer;
 #[cfg(all(feature = "phy-raw_socket", unix))]
 mod raw_socket;
+mod static_loopback;
 mod tracer;
+mod transformer;
 #[cfg(all(
     feature = "phy-tuntap_interface",
     any(target_os = "linux", target_os = "android")
 ))]
 mod tuntap_interface;
+#[cfg(feature = "medium-ethernet")]
+mod vlan;
 
 #[cfg(all(
     any(feature = "phy-raw_socket", feature = "phy-tuntap_interface"),
@@ -122,15 +131,29 @@ pub use self::fault_injector::FaultInjector;
 pub use self::fuzz_injector::{FuzzInjector, Fuzzer};
 #[cfg(feature = "alloc")]
 pub use self::loopback::Loopback;
+#[cfg(all(
+    feature = "mio",
+    unix,
+    any(
+        feature = "medium-ethernet",
+        feature = "medium-ip",
+        feature = "medium-ieee802154"
+    )
+))]
+pub use self::mio::wait_mio;
 pub use self::pcap_writer::{PcapLinkType, PcapMode, PcapSink, PcapWriter};
 #[cfg(all(feature = "phy-raw_socket", unix))]
 pub use self::raw_socket::RawSocket;
+pub use self::static_loopback::StaticLoopback;
 pub use self::tracer::Tracer;
+pub use self::transformer::{Transform, Transformer};
 #[cfg(all(
     feature = "phy-tuntap_interface",
     any(target_os = "linux", target_os = "android")
 ))]
 pub use self::tuntap_interface::TunTapInterface;
+#[cfg(feature = "medium-ethernet")]
+pub use self::vlan::Vlan;
 
 /// A description of checksum behavior for a particular protocol.
 #[derive(Debug, Clone, Copy)]
@@ -243,6 +266,13 @@ pub struct DeviceCapabilities {
     /// If the network device is capable of verifying or computing checksums for some protocols,
     /// it can request that the stack not do so in software to improve performance.
     pub checksum: ChecksumCapabilities,
+
+    /// Whether the device hands the packets it transmits back to the same interface,
+    /// e.g. [Loopback](crate::phy::Loopback).
+    ///
+    /// Packets from the addresses of the interface are only accepted from such devices,
+    /// and dropped as martians otherwise.
+    pub loopback: bool,
 }
 
 impl DeviceCapabilities {
@@ -254,6 +284,8 @@ impl DeviceCapabilities {
             }
             #[cfg(feature = "medium-ip")]
             Medium::Ip => self.max_transmission_unit,
+            #[cfg(feature = "medium-ppp")]
+            Medium::Ppp => self.max_transmission_unit - crate::wire::PPP_HEADER_LEN,
             #[cfg(feature = "medium-ieee802154")]
             Medium::Ieee802154 => self.max_transmission_unit, // TODO(thvdveld): what is the MTU for Medium::IEEE802
         }
@@ -278,6 +310,14 @@ pub enum Medium {
     #[cfg(feature = "medium-ip")]
     Ip,
 
+    /// PPP medium. Devices of this type send and receive PPP frames, consisting of
+    /// the protocol field and the information field, without the HDLC-like framing.
+    /// The interface negotiates the link with LCP, and its IPv4 address with IPCP.
+    ///
+    /// Examples of devices of this type are cellular modems and other serial links.
+    #[cfg(feature = "medium-ppp")]
+    Ppp,
+
     #[cfg(feature = "medium-ieee802154")]
     Ieee802154,
 }
@@ -303,6 +343,30 @@ impl Default for Medium {
     }
 }
 
+/// The priority of a packet to be transmitted.
+///
+/// Priorities range from 0, best effort, to 7, network control, like the priority code
+/// points of IEEE 802.1Q. Devices with several transmit queues can use them so that
+/// time-critical packets are not held up behind bulk traffic, see
+/// [Device::transmit_with_priority].
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Priority(pub u8);
+
+impl Priority {
+    /// The priority of bulk traffic, and of packets without a DSCP.
+    pub const BEST_EFFORT: Priority = Priority(0);
+    /// The priority of the control messages sent by the interface itself, such as IGMP
+    /// reports, neighbor solicitations and LLDP advertisements.
+    pub const NETWORK_CONTROL: Priority = Priority(7);
+
+    /// Return the priority of a packet sent with the given Differentiated Services Code Point,
+    /// i.e. its class selector.
+    pub const fn from_dscp(dscp: u8) -> Priority {
+        Priority((dscp >> 3) & 0b111)
+    }
+}
+
 /// An interface for sending and receiving raw network frames.
 ///
 /// The interface is based on _tokens_, which are types that allow to receive/transmit a
@@ -325,8 +389,25 @@ pub trait Device {
     fn receive(&mut self) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)>;
 
     /// Construct a transmit token.
+    ///
+    /// A device with several transmit queues uses the best effort queue.
     fn transmit(&mut self) -> Option<Self::TxToken<'_>>;
 
+    /// Construct a transmit token for a packet of the given priority.
+    ///
+    /// A device with several transmit queues can pick one from the priority, so that
+    /// time-critical packets, e.g. PTP or control messages, bypass the bulk traffic queued
+    /// in the driver. By default, the priority is ignored and all the packets share the
+    /// queue of [transmit](#tymethod.transmit).
+    ///
+    /// The interface uses this method for the packets sent by sockets and the control
+    /// messages it sends by itself; the replies to received packets use the transmit
+    /// token returned along with the receive token.
+    fn transmit_with_priority(&mut self, priority: Priority) -> Option<Self::TxToken<'_>> {
+        let _ = priority;
+        self.transmit()
+    }
+
     /// Get a description of device capabilities.
     fn capabilities(&self) -> DeviceCapabilities;
 }
@@ -4,7 +4,7 @@ use phy::Medium;
 #[cfg(feature = "std")]
 use std::io::Write;
 
-use crate::phy::{self, Device, DeviceCapabilities};
+use crate::phy::{self, Device, DeviceCapabilities, Priority};
 use crate::time::Instant;
 use crate::Result;
 
@@ -13,6 +13,8 @@ enum_with_unknown! {
     pub enum PcapLinkType(u32) {
         /// Ethernet frames
         Ethernet =   1,
+        /// PPP frames
+        Ppp      =   9,
         /// IPv4 or IPv6 packets (depending on the version field)
         Ip       = 101,
         /// IEEE 802.15.4 packets with FCS included.
@@ -137,6 +139,8 @@ impl<D: Device, S: PcapSink> PcapWriter<D, S> {
             Medium::Ethernet => PcapLinkType::Ethernet,
             #[cfg(feature = "medium-ieee802154")]
             Medium::Ieee802154 => PcapLinkType::Ieee802154WithFcs,
+            #[cfg(feature = "medium-ppp")]
+            Medium::Ppp => PcapLinkType::Ppp,
         };
         sink.global_header(link_type);
         PcapWriter {
@@ -196,10 +200,14 @@ where
     }
 
     fn transmit(&mut self) -> Option<Self::TxToken<'_>> {
+        self.transmit_with_priority(Priority::BEST_EFFORT)
+    }
+
+    fn transmit_with_priority(&mut self, priority: Priority) -> Option<Self::TxToken<'_>> {
         let sink = &self.sink;
         let mode = self.mode;
         self.lower
-            .transmit()
+            .transmit_with_priority(priority)
             .map(move |token| TxToken { token, sink, mode })
     }
 }
@@ -123,7 +123,9 @@ impl phy::TxToken for TxToken {
         let result = f(&mut buffer);
         match lower.send(&buffer[..]) {
             Ok(_) => result,
-            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Err(crate::Error::Exhausted),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                Err(crate::Error::Exhausted(crate::Layer::Phy))
+            }
             Err(err) => panic!("{}", err),
         }
     }
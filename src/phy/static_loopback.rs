@@ -0,0 +1,99 @@
+use heapless::{Deque, Vec};
+
+use crate::phy::{self, Device, DeviceCapabilities, Medium};
+use crate::time::Instant;
+use crate::{Error, Layer, Result};
+
+/// A loopback device that does not need an allocator.
+///
+/// Up to `N` packets of at most `MTU` octets each are queued in the device itself.
+/// While the queue is full, the device cannot transmit.
+#[derive(Debug)]
+pub struct StaticLoopback<const N: usize, const MTU: usize> {
+    queue: Deque<Vec<u8, MTU>, N>,
+    medium: Medium,
+}
+
+#[allow(clippy::new_without_default)]
+impl<const N: usize, const MTU: usize> StaticLoopback<N, MTU> {
+    /// Creates a loopback device.
+    ///
+    /// Every packet transmitted through this device will be received through it
+    /// in FIFO order.
+    pub fn new(medium: Medium) -> StaticLoopback<N, MTU> {
+        StaticLoopback {
+            queue: Deque::new(),
+            medium,
+        }
+    }
+}
+
+impl<const N: usize, const MTU: usize> Device for StaticLoopback<N, MTU> {
+    type RxToken<'a> = RxToken<MTU>;
+    type TxToken<'a> = TxToken<'a, N, MTU>;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities {
+            max_transmission_unit: MTU,
+            medium: self.medium,
+            loopback: true,
+            ..DeviceCapabilities::default()
+        }
+    }
+
+    fn receive(&mut self) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        self.queue.pop_front().map(move |buffer| {
+            let rx = RxToken { buffer };
+            let tx = TxToken {
+                queue: &mut self.queue,
+            };
+            (rx, tx)
+        })
+    }
+
+    fn transmit(&mut self) -> Option<Self::TxToken<'_>> {
+        if self.queue.is_full() {
+            return None;
+        }
+        Some(TxToken {
+            queue: &mut self.queue,
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct RxToken<const MTU: usize> {
+    buffer: Vec<u8, MTU>,
+}
+
+impl<const MTU: usize> phy::RxToken for RxToken<MTU> {
+    fn consume<R, F>(mut self, _timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        f(&mut self.buffer)
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct TxToken<'a, const N: usize, const MTU: usize> {
+    queue: &'a mut Deque<Vec<u8, MTU>, N>,
+}
+
+impl<'a, const N: usize, const MTU: usize> phy::TxToken for TxToken<'a, N, MTU> {
+    fn consume<R, F>(self, _timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let mut buffer = Vec::new();
+        buffer
+            .resize(len, 0)
+            .map_err(|_| Error::Exhausted(Layer::Phy))?;
+        let result = f(&mut buffer);
+        self.queue
+            .push_back(buffer)
+            .map_err(|_| Error::Exhausted(Layer::Phy))?;
+        result
+    }
+}
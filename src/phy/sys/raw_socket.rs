@@ -25,6 +25,15 @@ impl RawSocketDesc {
             Medium::Ip => imp::ETH_P_ALL,
             #[cfg(feature = "medium-ieee802154")]
             Medium::Ieee802154 => imp::ETH_P_IEEE802154,
+            // Packet sockets on a PPP interface of the host carry bare IP packets, and its
+            // link control protocols are run by the kernel rather than by the interface.
+            #[cfg(feature = "medium-ppp")]
+            Medium::Ppp => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "raw sockets do not support the PPP medium",
+                ))
+            }
         };
 
         let lower = unsafe {
@@ -3,6 +3,14 @@ use crate::{phy::Medium, wire::EthernetFrame};
 use std::io;
 use std::os::unix::io::{AsRawFd, RawFd};
 
+#[cfg(feature = "medium-ppp")]
+fn ppp_unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "TUN/TAP interfaces do not support the PPP medium",
+    )
+}
+
 #[derive(Debug)]
 pub struct TunTapInterfaceDesc {
     lower: libc::c_int,
@@ -44,6 +52,10 @@ impl TunTapInterfaceDesc {
             Medium::Ethernet => imp::IFF_TAP,
             #[cfg(feature = "medium-ieee802154")]
             Medium::Ieee802154 => todo!(),
+            // TUN devices carry bare IP packets, without the PPP header and the link
+            // control protocols that the interface expects.
+            #[cfg(feature = "medium-ppp")]
+            Medium::Ppp => return Err(ppp_unsupported()),
         };
         self.ifreq.ifr_data = mode | imp::IFF_NO_PI;
         ifreq_ioctl(self.lower, &mut self.ifreq, imp::TUNSETIFF).map(|_| ())
@@ -76,6 +88,8 @@ impl TunTapInterfaceDesc {
             Medium::Ethernet => ip_mtu + EthernetFrame::<&[u8]>::header_len(),
             #[cfg(feature = "medium-ieee802154")]
             Medium::Ieee802154 => todo!(),
+            #[cfg(feature = "medium-ppp")]
+            Medium::Ppp => return Err(ppp_unsupported()),
         };
 
         Ok(mtu)
@@ -1,6 +1,6 @@
 use core::fmt;
 
-use crate::phy::{self, Device, DeviceCapabilities, Medium};
+use crate::phy::{self, Device, DeviceCapabilities, Medium, Priority};
 use crate::time::Instant;
 use crate::{
     wire::pretty_print::{PrettyIndent, PrettyPrint},
@@ -79,16 +79,22 @@ impl<D: Device> Device for Tracer<D> {
     }
 
     fn transmit(&mut self) -> Option<Self::TxToken<'_>> {
+        self.transmit_with_priority(Priority::BEST_EFFORT)
+    }
+
+    fn transmit_with_priority(&mut self, priority: Priority) -> Option<Self::TxToken<'_>> {
         let &mut Self {
             ref mut inner,
             writer,
         } = self;
         let medium = inner.capabilities().medium;
-        inner.transmit().map(|tx_token| TxToken {
-            token: tx_token,
-            medium,
-            writer,
-        })
+        inner
+            .transmit_with_priority(priority)
+            .map(|tx_token| TxToken {
+                token: tx_token,
+                medium,
+                writer,
+            })
     }
 }
 
@@ -193,6 +199,10 @@ impl<'a> fmt::Display for Packet<'a> {
             },
             #[cfg(feature = "medium-ieee802154")]
             Medium::Ieee802154 => Ok(()), // XXX
+            #[cfg(feature = "medium-ppp")]
+            Medium::Ppp => {
+                crate::wire::PppFrame::<&'static [u8]>::pretty_print(&self.buffer, f, &mut indent)
+            }
         }
     }
 }
@@ -0,0 +1,214 @@
+use core::cell::RefCell;
+
+use crate::phy::{self, Device, DeviceCapabilities, Priority};
+use crate::time::Instant;
+use crate::Result;
+
+/// An encapsulation applied to every packet traversing a [Transformer].
+///
+/// On transmit, the packet built by the interface is placed after `header_len()` free
+/// octets and followed by `trailer_len(len)` free octets, and [encapsulate] fills them in,
+/// possibly transforming the packet in place, e.g. encrypting it. On receive, [decapsulate]
+/// returns the packet to hand to the interface, which is usually a part of the received one.
+///
+/// This can be used to build GRE, IPsec ESP or WireGuard-like tunnels: with a device using
+/// [Medium::Ip](enum.Medium.html#variant.Ip), the transform sees entire IP packets.
+///
+/// [encapsulate]: #tymethod.encapsulate
+/// [decapsulate]: #tymethod.decapsulate
+pub trait Transform {
+    /// Return the number of octets added in front of each packet.
+    fn header_len(&self) -> usize;
+
+    /// Return the number of octets added after a packet of `len` octets, including padding.
+    fn trailer_len(&self, len: usize) -> usize {
+        let _ = len;
+        0
+    }
+
+    /// Return the largest number of octets added after any packet.
+    ///
+    /// Together with the header length, this is subtracted from the MTU of the device.
+    fn max_trailer_len(&self) -> usize {
+        0
+    }
+
+    /// Encapsulate the packet in the middle of `buffer` in place.
+    ///
+    /// Returning an error drops the packet.
+    fn encapsulate(&mut self, timestamp: Instant, buffer: &mut [u8]) -> Result<()>;
+
+    /// Encapsulate a packet transmitted with the given priority, see
+    /// [Device::transmit_with_priority].
+    ///
+    /// The default implementation ignores the priority, and calls
+    /// [encapsulate](#tymethod.encapsulate).
+    fn encapsulate_with_priority(
+        &mut self,
+        timestamp: Instant,
+        priority: Priority,
+        buffer: &mut [u8],
+    ) -> Result<()> {
+        let _ = priority;
+        self.encapsulate(timestamp, buffer)
+    }
+
+    /// Decapsulate a received packet in place, and return the part of `buffer` containing
+    /// the original packet.
+    ///
+    /// Returning an error drops the packet, e.g. if it fails authentication, or is
+    /// a control message consumed by the transform itself.
+    fn decapsulate<'b>(&mut self, timestamp: Instant, buffer: &'b mut [u8])
+        -> Result<&'b mut [u8]>;
+}
+
+/// A transformer device.
+///
+/// A transformer is a device that encapsulates all transmitted packets and decapsulates
+/// all received packets using a [Transform], and then passes them to another device.
+/// The MTU it reports is reduced by the overhead of the encapsulation.
+#[derive(Debug)]
+pub struct Transformer<D: Device, T: Transform> {
+    inner: D,
+    transform: RefCell<T>,
+}
+
+impl<D: Device, T: Transform> Transformer<D, T> {
+    /// Create a transformer device.
+    pub fn new(inner: D, transform: T) -> Transformer<D, T> {
+        Transformer {
+            inner,
+            transform: RefCell::new(transform),
+        }
+    }
+
+    /// Get a reference to the underlying device.
+    ///
+    /// Even if the device offers reading through a standard reference, it is inadvisable to
+    /// directly read from the device as doing so will circumvent the transform.
+    pub fn get_ref(&self) -> &D {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying device.
+    ///
+    /// It is inadvisable to directly read from the device as doing so will circumvent
+    /// the transform.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Get a mutable reference to the transform, e.g. to rotate its keys.
+    pub fn transform_mut(&mut self) -> &mut T {
+        self.transform.get_mut()
+    }
+
+    /// Return the underlying device and transform, consuming the transformer.
+    pub fn into_inner(self) -> (D, T) {
+        (self.inner, self.transform.into_inner())
+    }
+}
+
+impl<D: Device, T: Transform> Device for Transformer<D, T> {
+    type RxToken<'a>
+        = RxToken<'a, D::RxToken<'a>, T>
+    where
+        Self: 'a;
+    type TxToken<'a>
+        = TxToken<'a, D::TxToken<'a>, T>
+    where
+        Self: 'a;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = self.inner.capabilities();
+        let transform = self.transform.borrow();
+        let overhead = transform.header_len() + transform.max_trailer_len();
+        caps.max_transmission_unit = caps.max_transmission_unit.saturating_sub(overhead);
+        caps
+    }
+
+    fn receive(&mut self) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let &mut Self {
+            ref mut inner,
+            ref transform,
+        } = self;
+        inner.receive().map(|(rx_token, tx_token)| {
+            let rx = RxToken {
+                token: rx_token,
+                transform,
+            };
+            let tx = TxToken {
+                token: tx_token,
+                transform,
+                priority: Priority::BEST_EFFORT,
+            };
+            (rx, tx)
+        })
+    }
+
+    fn transmit(&mut self) -> Option<Self::TxToken<'_>> {
+        self.transmit_with_priority(Priority::BEST_EFFORT)
+    }
+
+    fn transmit_with_priority(&mut self, priority: Priority) -> Option<Self::TxToken<'_>> {
+        let &mut Self {
+            ref mut inner,
+            ref transform,
+        } = self;
+        inner.transmit_with_priority(priority).map(|token| TxToken {
+            token,
+            transform,
+            priority,
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct RxToken<'a, Rx: phy::RxToken, T: Transform> {
+    token: Rx,
+    transform: &'a RefCell<T>,
+}
+
+impl<'a, Rx: phy::RxToken, T: Transform> phy::RxToken for RxToken<'a, Rx, T> {
+    fn consume<R, F>(self, timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let Self { token, transform } = self;
+        token.consume(timestamp, |buffer| {
+            let packet = transform.borrow_mut().decapsulate(timestamp, buffer)?;
+            f(packet)
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct TxToken<'a, Tx: phy::TxToken, T: Transform> {
+    token: Tx,
+    transform: &'a RefCell<T>,
+    priority: Priority,
+}
+
+impl<'a, Tx: phy::TxToken, T: Transform> phy::TxToken for TxToken<'a, Tx, T> {
+    fn consume<R, F>(self, timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let Self {
+            token,
+            transform,
+            priority,
+        } = self;
+        let (header_len, trailer_len) = {
+            let transform = transform.borrow();
+            (transform.header_len(), transform.trailer_len(len))
+        };
+        token.consume(timestamp, header_len + len + trailer_len, |buffer| {
+            let result = f(&mut buffer[header_len..header_len + len])?;
+            transform
+                .borrow_mut()
+                .encapsulate_with_priority(timestamp, priority, buffer)?;
+            Ok(result)
+        })
+    }
+}
@@ -0,0 +1,97 @@
+use crate::phy::{Priority, Transform};
+use crate::time::Instant;
+use crate::wire::{EthernetFrame, EthernetProtocol, VlanPacket, VlanTag, VLAN_HEADER_LEN};
+use crate::{Error, Layer, Result};
+
+/// An IEEE 802.1Q tagging transform.
+///
+/// Used with a [Transformer](struct.Transformer.html) around a device using
+/// [Medium::Ethernet](enum.Medium.html#variant.Ethernet), it tags all transmitted frames
+/// with a VLAN tag, and drops all received frames that are untagged or tagged for another
+/// VLAN, stripping the tag from the rest. Without it, frames are sent and received untagged.
+///
+/// Frames transmitted with a priority other than best effort, see
+/// [Device::transmit_with_priority](trait.Device.html#method.transmit_with_priority),
+/// carry it as the priority code point of their tag, instead of that of the configured tag.
+///
+/// The MTU of the transformer is reduced by the length of the tag, since many devices
+/// do not accept frames larger than the standard Ethernet MTU.
+#[derive(Debug, Clone, Copy)]
+pub struct Vlan {
+    tag: VlanTag,
+}
+
+impl Vlan {
+    /// Create a transform tagging frames with `tag`.
+    pub fn new(tag: VlanTag) -> Vlan {
+        Vlan { tag }
+    }
+
+    /// Return the tag of transmitted frames.
+    pub fn tag(&self) -> VlanTag {
+        self.tag
+    }
+
+    /// Set the tag of transmitted frames, and the VLAN of accepted frames.
+    pub fn set_tag(&mut self, tag: VlanTag) {
+        self.tag = tag
+    }
+}
+
+impl Transform for Vlan {
+    fn header_len(&self) -> usize {
+        VLAN_HEADER_LEN
+    }
+
+    fn encapsulate(&mut self, timestamp: Instant, buffer: &mut [u8]) -> Result<()> {
+        self.encapsulate_with_priority(timestamp, Priority::BEST_EFFORT, buffer)
+    }
+
+    fn encapsulate_with_priority(
+        &mut self,
+        _timestamp: Instant,
+        priority: Priority,
+        buffer: &mut [u8],
+    ) -> Result<()> {
+        // The frame was built after the space reserved for the tag; move the addresses
+        // in front of it, so that the original EtherType ends up in the 802.1Q header.
+        let addrs_len = EthernetFrame::<&[u8]>::header_len() - 2;
+        if buffer.len() < VLAN_HEADER_LEN + addrs_len + 2 {
+            return Err(Error::Truncated(Layer::Link));
+        }
+        buffer.copy_within(VLAN_HEADER_LEN..VLAN_HEADER_LEN + addrs_len, 0);
+
+        let mut frame = EthernetFrame::new_unchecked(&mut *buffer);
+        frame.set_ethertype(EthernetProtocol::Vlan);
+        let mut packet = VlanPacket::new_unchecked(frame.payload_mut());
+        let mut tag = self.tag;
+        if priority != Priority::BEST_EFFORT {
+            tag.priority = priority.0.min(7);
+        }
+        tag.emit(&mut packet);
+        Ok(())
+    }
+
+    fn decapsulate<'b>(
+        &mut self,
+        _timestamp: Instant,
+        buffer: &'b mut [u8],
+    ) -> Result<&'b mut [u8]> {
+        let wire_error = |err| Error::wire(Layer::Link, err);
+        let frame = EthernetFrame::new_checked(&*buffer).map_err(wire_error)?;
+        if frame.ethertype() != EthernetProtocol::Vlan {
+            net_trace!("vlan: dropping untagged frame");
+            return Err(Error::Dropped(Layer::Link));
+        }
+        let packet = VlanPacket::new_checked(frame.payload()).map_err(wire_error)?;
+        let tag = VlanTag::parse(&packet).map_err(wire_error)?;
+        if tag.vlan_id != self.tag.vlan_id {
+            net_trace!("vlan: dropping frame for VLAN {}", tag.vlan_id);
+            return Err(Error::Dropped(Layer::Link));
+        }
+
+        let addrs_len = EthernetFrame::<&[u8]>::header_len() - 2;
+        buffer.copy_within(..addrs_len, VLAN_HEADER_LEN);
+        Ok(&mut buffer[VLAN_HEADER_LEN..])
+    }
+}
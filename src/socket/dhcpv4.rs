@@ -83,6 +83,10 @@ struct RenewState {
     /// to renew this lease with the DHCP server.
     /// Must be less or equal than `expires_at`.
     renew_at: Instant,
+    /// Rebind timer. When reached, we will broadcast our renewal
+    /// requests, so that any DHCP server can extend this lease.
+    /// Must be less or equal than `expires_at`.
+    rebind_at: Instant,
     /// Expiration timer. When reached, this lease is no longer valid, so it must be
     /// thrown away and the ethernet interface deconfigured.
     expires_at: Instant,
@@ -336,12 +340,13 @@ impl<'a> Socket<'a> {
                 });
             }
             (ClientState::Requesting(state), DhcpMessageType::Ack) => {
-                if let Some((config, renew_at, expires_at)) =
+                if let Some((config, renew_at, rebind_at, expires_at)) =
                     Self::parse_ack(cx.now(), &dhcp_repr, self.max_lease_duration, state.server)
                 {
                     self.state = ClientState::Renewing(RenewState {
                         config,
                         renew_at,
+                        rebind_at,
                         expires_at,
                     });
                     self.config_changed();
@@ -353,13 +358,20 @@ impl<'a> Socket<'a> {
                 }
             }
             (ClientState::Renewing(state), DhcpMessageType::Ack) => {
-                if let Some((config, renew_at, expires_at)) = Self::parse_ack(
-                    cx.now(),
-                    &dhcp_repr,
-                    self.max_lease_duration,
-                    state.config.server,
-                ) {
+                // While rebinding, any server may extend the lease.
+                let server = if cx.now() >= state.rebind_at {
+                    ServerInfo {
+                        address: src_ip,
+                        identifier: server_identifier,
+                    }
+                } else {
+                    state.config.server
+                };
+                if let Some((config, renew_at, rebind_at, expires_at)) =
+                    Self::parse_ack(cx.now(), &dhcp_repr, self.max_lease_duration, server)
+                {
                     state.renew_at = renew_at;
+                    state.rebind_at = rebind_at;
                     state.expires_at = expires_at;
                     // The `receive_packet_buffer` field isn't populated until
                     // the client asks for the state, but receiving any packet
@@ -395,7 +407,7 @@ impl<'a> Socket<'a> {
         dhcp_repr: &DhcpRepr,
         max_lease_duration: Option<Duration>,
         server: ServerInfo,
-    ) -> Option<(Config<'static>, Instant, Instant)> {
+    ) -> Option<(Config<'static>, Instant, Instant, Instant)> {
         let subnet_mask = match dhcp_repr.subnet_mask {
             Some(subnet_mask) => subnet_mask,
             None => {
@@ -448,26 +460,26 @@ impl<'a> Socket<'a> {
             packet: None,
         };
 
-        // Set renew time as per RFC 2131:
-        // The renew time (T1) can be specified by the server using option 58:
+        // Set renew and rebind times as per RFC 2131:
+        // The rebind time (T2) can be specified by the server using option 59,
+        // and defaults to 0.875 times the lease duration.
+        let rebind_duration = dhcp_repr
+            .rebind_duration
+            .map(|d| Duration::from_secs(d as u64))
+            .unwrap_or(lease_duration * 7 / 8)
+            .min(lease_duration);
+        // The renew time (T1) can be specified by the server using option 58,
+        // and defaults to half the lease duration.
         let renew_duration = dhcp_repr
             .renew_duration
             .map(|d| Duration::from_secs(d as u64))
-            // Since we don't follow the REBINDING part of the spec, when no
-            // explicit T1 time is given, we will also consider the rebinding
-            // time if it is given and less than the default.
-            .or_else(|| {
-                dhcp_repr
-                    .rebind_duration
-                    .map(|d| Duration::from_secs(d as u64).min(lease_duration / 2))
-            })
-            // Otherwise, we use the default T1 time, which is half the lease
-            // duration.
-            .unwrap_or(lease_duration / 2);
+            .unwrap_or(lease_duration / 2)
+            .min(rebind_duration);
         let renew_at = now + renew_duration;
+        let rebind_at = now + rebind_duration;
         let expires_at = now + lease_duration;
 
-        Some((config, renew_at, expires_at))
+        Some((config, renew_at, rebind_at, expires_at))
     }
 
     #[cfg(not(test))]
@@ -538,6 +550,7 @@ impl<'a> Socket<'a> {
             next_header: IpProtocol::Udp,
             payload_len: 0, // filled right before emit
             hop_limit: 64,
+            traffic_class: 0,
         };
 
         match &mut self.state {
@@ -603,8 +616,14 @@ impl<'a> Socket<'a> {
                     return Ok(());
                 }
 
+                // Once T2 is reached, we are REBINDING: the lease can be extended
+                // by any server, so the request is broadcast.
+                let rebinding = cx.now() >= state.rebind_at;
+
                 ipv4_repr.src_addr = state.config.address.address();
-                ipv4_repr.dst_addr = state.config.server.address;
+                if !rebinding {
+                    ipv4_repr.dst_addr = state.config.server.address;
+                }
                 dhcp_repr.message_type = DhcpMessageType::Request;
                 dhcp_repr.client_ip = state.config.address.address();
 
@@ -617,11 +636,17 @@ impl<'a> Socket<'a> {
                 // of the remaining time until T2 (in RENEWING state) and one-half of
                 // the remaining lease time (in REBINDING state), down to a minimum of
                 // 60 seconds, before retransmitting the DHCPREQUEST message.
-                state.renew_at = cx.now()
+                let next_timer = if rebinding {
+                    state.expires_at
+                } else {
+                    state.rebind_at
+                };
+                state.renew_at = (cx.now()
                     + self
                         .retry_config
                         .min_renew_timeout
-                        .max((state.expires_at - cx.now()) / 2);
+                        .max((next_timer - cx.now()) / 2))
+                .min(next_timer);
 
                 self.transaction_id = next_transaction_id;
                 Ok(())
@@ -801,6 +826,7 @@ mod test {
 
     const MY_IP: Ipv4Address = Ipv4Address([192, 168, 1, 42]);
     const SERVER_IP: Ipv4Address = Ipv4Address([192, 168, 1, 1]);
+    const OTHER_SERVER_IP: Ipv4Address = Ipv4Address([192, 168, 1, 2]);
     const DNS_IP_1: Ipv4Address = Ipv4Address([1, 1, 1, 1]);
     const DNS_IP_2: Ipv4Address = Ipv4Address([1, 1, 1, 2]);
     const DNS_IP_3: Ipv4Address = Ipv4Address([1, 1, 1, 3]);
@@ -816,6 +842,7 @@ mod test {
         next_header: IpProtocol::Udp,
         payload_len: 0,
         hop_limit: 64,
+        traffic_class: 0,
     };
 
     const IP_SERVER_BROADCAST: Ipv4Repr = Ipv4Repr {
@@ -824,6 +851,7 @@ mod test {
         next_header: IpProtocol::Udp,
         payload_len: 0,
         hop_limit: 64,
+        traffic_class: 0,
     };
 
     const IP_RECV: Ipv4Repr = Ipv4Repr {
@@ -832,6 +860,7 @@ mod test {
         next_header: IpProtocol::Udp,
         payload_len: 0,
         hop_limit: 64,
+        traffic_class: 0,
     };
 
     const IP_SEND: Ipv4Repr = Ipv4Repr {
@@ -840,6 +869,16 @@ mod test {
         next_header: IpProtocol::Udp,
         payload_len: 0,
         hop_limit: 64,
+        traffic_class: 0,
+    };
+
+    const IP_REBIND: Ipv4Repr = Ipv4Repr {
+        src_addr: MY_IP,
+        dst_addr: Ipv4Address::BROADCAST,
+        next_header: IpProtocol::Udp,
+        payload_len: 0,
+        hop_limit: 64,
+        traffic_class: 0,
     };
 
     const UDP_SEND: UdpRepr = UdpRepr {
@@ -971,6 +1010,7 @@ mod test {
                 packet: None,
             },
             renew_at: Instant::from_secs(500),
+            rebind_at: Instant::from_secs(875),
             expires_at: Instant::from_secs(1000),
         });
 
@@ -1006,6 +1046,7 @@ mod test {
         match &s.state {
             ClientState::Renewing(r) => {
                 assert_eq!(r.renew_at, Instant::from_secs(500));
+                assert_eq!(r.rebind_at, Instant::from_secs(875));
                 assert_eq!(r.expires_at, Instant::from_secs(1000));
             }
             _ => panic!("Invalid state"),
@@ -1122,30 +1163,71 @@ mod test {
 
         recv!(s, []);
         recv!(s, time 500_000, [(IP_SEND, UDP_SEND, DHCP_RENEW)]);
-        recv!(s, time 749_000, []);
-        recv!(s, time 750_000, [(IP_SEND, UDP_SEND, DHCP_RENEW)]);
-        recv!(s, time 874_000, []);
-        recv!(s, time 875_000, [(IP_SEND, UDP_SEND, DHCP_RENEW)]);
+        recv!(s, time 687_000, []);
+        recv!(s, time 687_500, [(IP_SEND, UDP_SEND, DHCP_RENEW)]);
+        recv!(s, time 781_000, []);
+        recv!(s, time 781_250, [(IP_SEND, UDP_SEND, DHCP_RENEW)]);
+        recv!(s, time 841_000, []);
+        recv!(s, time 841_250, [(IP_SEND, UDP_SEND, DHCP_RENEW)]);
 
         // check it still works
-        send!(s, time 875_000, (IP_RECV, UDP_RECV, dhcp_ack()));
+        send!(s, time 850_000, (IP_RECV, UDP_RECV, dhcp_ack()));
         match &s.state {
             ClientState::Renewing(r) => {
                 // NOW the expiration gets bumped
-                assert_eq!(r.renew_at, Instant::from_secs(875 + 500));
-                assert_eq!(r.expires_at, Instant::from_secs(875 + 1000));
+                assert_eq!(r.renew_at, Instant::from_secs(850 + 500));
+                assert_eq!(r.rebind_at, Instant::from_secs(850 + 875));
+                assert_eq!(r.expires_at, Instant::from_secs(850 + 1000));
             }
             _ => panic!("Invalid state"),
         }
     }
 
+    #[test]
+    fn test_rebind() {
+        let mut s = socket_bound();
+
+        recv!(s, time 500_000, [(IP_SEND, UDP_SEND, DHCP_RENEW)]);
+        recv!(s, time 875_000, [(IP_REBIND, UDP_SEND, DHCP_RENEW)]);
+        recv!(s, time 937_000, []);
+        recv!(s, time 937_500, [(IP_REBIND, UDP_SEND, DHCP_RENEW)]);
+
+        // Another server extends the lease.
+        let ip_recv = Ipv4Repr {
+            src_addr: OTHER_SERVER_IP,
+            ..IP_RECV
+        };
+        let ack = DhcpRepr {
+            server_ip: OTHER_SERVER_IP,
+            server_identifier: Some(OTHER_SERVER_IP),
+            ..dhcp_ack()
+        };
+        send!(s, time 950_000, (ip_recv, UDP_RECV, ack));
+        match &s.state {
+            ClientState::Renewing(r) => {
+                assert_eq!(r.config.server.address, OTHER_SERVER_IP);
+                assert_eq!(r.renew_at, Instant::from_secs(950 + 500));
+                assert_eq!(r.rebind_at, Instant::from_secs(950 + 875));
+                assert_eq!(r.expires_at, Instant::from_secs(950 + 1000));
+            }
+            _ => panic!("Invalid state"),
+        }
+
+        // Later renewals are sent to it.
+        let ip_send = Ipv4Repr {
+            dst_addr: OTHER_SERVER_IP,
+            ..IP_SEND
+        };
+        recv!(s, time 1_450_000, [(ip_send, UDP_SEND, DHCP_RENEW)]);
+    }
+
     #[test]
     fn test_renew_timeout() {
         let mut s = socket_bound();
 
         recv!(s, []);
         recv!(s, time 500_000, [(IP_SEND, UDP_SEND, DHCP_RENEW)]);
-        recv!(s, time 999_000, [(IP_SEND, UDP_SEND, DHCP_RENEW)]);
+        recv!(s, time 999_000, [(IP_REBIND, UDP_SEND, DHCP_RENEW)]);
         recv!(s, time 1_000_000, [(IP_BROADCAST, UDP_SEND, DHCP_DISCOVER)]);
         match &s.state {
             ClientState::Discovering(_) => {}
@@ -1,3 +1,4 @@
+use core::fmt;
 #[cfg(feature = "async")]
 use core::task::Waker;
 
@@ -39,6 +40,29 @@ pub enum StartQueryError {
     NameTooLong,
 }
 
+impl fmt::Display for StartQueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StartQueryError::NoFreeSlot => write!(f, "no free query slot"),
+            StartQueryError::InvalidName => write!(f, "invalid name"),
+            StartQueryError::NameTooLong => write!(f, "name too long"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StartQueryError {}
+
+impl From<StartQueryError> for crate::Error {
+    fn from(err: StartQueryError) -> Self {
+        match err {
+            StartQueryError::NoFreeSlot => crate::Error::Exhausted(crate::Layer::Application),
+            StartQueryError::InvalidName => crate::Error::Illegal(crate::Layer::Application),
+            StartQueryError::NameTooLong => crate::Error::Illegal(crate::Layer::Application),
+        }
+    }
+}
+
 /// Error returned by [`Socket::get_query_result`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -49,6 +73,27 @@ pub enum GetQueryResultError {
     Failed,
 }
 
+impl fmt::Display for GetQueryResultError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GetQueryResultError::Pending => write!(f, "query pending"),
+            GetQueryResultError::Failed => write!(f, "query failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for GetQueryResultError {}
+
+impl From<GetQueryResultError> for crate::Error {
+    fn from(err: GetQueryResultError) -> Self {
+        match err {
+            GetQueryResultError::Pending => crate::Error::Exhausted(crate::Layer::Application),
+            GetQueryResultError::Failed => crate::Error::Unaddressable(crate::Layer::Application),
+        }
+    }
+}
+
 /// State for an in-progress DNS query.
 ///
 /// The only reason this struct is public is to allow the socket state
@@ -666,12 +711,14 @@ fn copy_name<'a, const N: usize>(
 
     for label in name {
         let label = label?;
-        dest.push(label.len() as u8).map_err(|_| wire::Error)?;
-        dest.extend_from_slice(label).map_err(|_| wire::Error)?;
+        dest.push(label.len() as u8)
+            .map_err(|_| wire::Error::Malformed)?;
+        dest.extend_from_slice(label)
+            .map_err(|_| wire::Error::Malformed)?;
     }
 
     // Write terminator 0x00
-    dest.push(0).map_err(|_| wire::Error)?;
+    dest.push(0).map_err(|_| wire::Error::Malformed)?;
 
     Ok(())
 }
@@ -0,0 +1,426 @@
+use core::cmp::min;
+use core::fmt;
+#[cfg(feature = "async")]
+use core::task::Waker;
+
+use crate::iface::Context;
+use crate::socket::PollAt;
+#[cfg(feature = "async")]
+use crate::socket::WakerRegistration;
+
+use crate::storage::{Empty, MemoryUsage};
+use crate::wire::{EthernetFrame, EthernetProtocol};
+
+/// Error returned by [`Socket::send`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SendError {
+    BufferFull,
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendError::BufferFull => write!(f, "buffer full"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SendError {}
+
+impl From<SendError> for crate::Error {
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::BufferFull => crate::Error::Exhausted(crate::Layer::Link),
+        }
+    }
+}
+
+/// Error returned by [`Socket::recv`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecvError {
+    Exhausted,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecvError::Exhausted => write!(f, "buffer empty"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RecvError {}
+
+impl From<RecvError> for crate::Error {
+    fn from(err: RecvError) -> Self {
+        match err {
+            RecvError::Exhausted => crate::Error::Exhausted(crate::Layer::Link),
+        }
+    }
+}
+
+/// An Ethernet frame metadata.
+pub type PacketMetadata = crate::storage::PacketMetadata<()>;
+
+/// An Ethernet frame ring buffer.
+pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, ()>;
+
+/// A raw Ethernet socket.
+///
+/// An Ethernet socket receives whole Ethernet frames of a specific EtherType, or of any
+/// EtherType, and sends whole Ethernet frames as they are, for protocols the interface
+/// does not implement itself. Received frames are still processed by the interface.
+///
+/// The socket receives the frames addressed to the interface, broadcast or multicast,
+/// and, if the interface is [promiscuous], those addressed to other hosts too.
+///
+/// [promiscuous]: ../../iface/struct.Interface.html#method.set_promiscuous
+#[derive(Debug)]
+pub struct Socket<'a> {
+    ethertype: Option<EthernetProtocol>,
+    rx_buffer: PacketBuffer<'a>,
+    tx_buffer: PacketBuffer<'a>,
+    #[cfg(feature = "async")]
+    rx_waker: WakerRegistration,
+    #[cfg(feature = "async")]
+    tx_waker: WakerRegistration,
+}
+
+impl<'a> Socket<'a> {
+    /// Create an Ethernet socket receiving the frames of the given EtherType, or of any
+    /// EtherType if `ethertype` is `None`, with the given buffers.
+    pub fn new(
+        ethertype: Option<EthernetProtocol>,
+        rx_buffer: PacketBuffer<'a>,
+        tx_buffer: PacketBuffer<'a>,
+    ) -> Socket<'a> {
+        Socket {
+            ethertype,
+            rx_buffer,
+            tx_buffer,
+            #[cfg(feature = "async")]
+            rx_waker: WakerRegistration::new(),
+            #[cfg(feature = "async")]
+            tx_waker: WakerRegistration::new(),
+        }
+    }
+
+    /// Register a waker for receive operations.
+    ///
+    /// The waker is woken on state changes that might affect the return value
+    /// of `recv` method calls, such as receiving data, or the socket closing.
+    ///
+    /// Notes:
+    ///
+    /// - Only one waker can be registered at a time. If another waker was previously registered,
+    ///   it is overwritten and will no longer be woken.
+    /// - The Waker is woken only once. Once woken, you must register it again to receive more wakes.
+    /// - "Spurious wakes" are allowed: a wake doesn't guarantee the result of `recv` has
+    ///   necessarily changed.
+    #[cfg(feature = "async")]
+    pub fn register_recv_waker(&mut self, waker: &Waker) {
+        self.rx_waker.register(waker)
+    }
+
+    /// Register a waker for send operations.
+    ///
+    /// The waker is woken on state changes that might affect the return value
+    /// of `send` method calls, such as space becoming available in the transmit
+    /// buffer, or the socket closing.
+    ///
+    /// Notes:
+    ///
+    /// - Only one waker can be registered at a time. If another waker was previously registered,
+    ///   it is overwritten and will no longer be woken.
+    /// - The Waker is woken only once. Once woken, you must register it again to receive more wakes.
+    /// - "Spurious wakes" are allowed: a wake doesn't guarantee the result of `send` has
+    ///   necessarily changed.
+    #[cfg(feature = "async")]
+    pub fn register_send_waker(&mut self, waker: &Waker) {
+        self.tx_waker.register(waker)
+    }
+
+    /// Return the EtherType the socket receives frames of, or `None` if it receives
+    /// frames of any EtherType.
+    #[inline]
+    pub fn ethertype(&self) -> Option<EthernetProtocol> {
+        self.ethertype
+    }
+
+    /// Check whether the transmit buffer is full.
+    #[inline]
+    pub fn can_send(&self) -> bool {
+        !self.tx_buffer.is_full()
+    }
+
+    /// Check whether the receive buffer is not empty.
+    #[inline]
+    pub fn can_recv(&self) -> bool {
+        !self.rx_buffer.is_empty()
+    }
+
+    /// Return the maximum number frames the socket can receive.
+    #[inline]
+    pub fn packet_recv_capacity(&self) -> usize {
+        self.rx_buffer.packet_capacity()
+    }
+
+    /// Return the maximum number frames the socket can transmit.
+    #[inline]
+    pub fn packet_send_capacity(&self) -> usize {
+        self.tx_buffer.packet_capacity()
+    }
+
+    /// Return the maximum number of bytes inside the recv buffer.
+    #[inline]
+    pub fn payload_recv_capacity(&self) -> usize {
+        self.rx_buffer.payload_capacity()
+    }
+
+    /// Return the maximum number of bytes inside the transmit buffer.
+    #[inline]
+    pub fn payload_send_capacity(&self) -> usize {
+        self.tx_buffer.payload_capacity()
+    }
+
+    /// Return the number of bytes of receive buffer storage used and committed by the socket.
+    #[inline]
+    pub fn recv_memory_usage(&self) -> MemoryUsage {
+        self.rx_buffer.memory_usage()
+    }
+
+    /// Return the number of bytes of transmit buffer storage used and committed by the socket.
+    #[inline]
+    pub fn send_memory_usage(&self) -> MemoryUsage {
+        self.tx_buffer.memory_usage()
+    }
+
+    /// Enqueue a frame to send, and return a pointer to it.
+    ///
+    /// The buffer has to be filled with a whole Ethernet frame, including its source
+    /// address, which is sent as it is. Frames shorter than an Ethernet header are
+    /// silently dropped.
+    ///
+    /// This function returns `Err(SendError::BufferFull)` if the transmit buffer is full,
+    /// or if there is not enough transmit buffer capacity to ever send this frame.
+    pub fn send(&mut self, size: usize) -> Result<&mut [u8], SendError> {
+        let frame_buf = self
+            .tx_buffer
+            .enqueue(size, ())
+            .map_err(|_| SendError::BufferFull)?;
+
+        net_trace!("ethernet: buffer to send {} octets", frame_buf.len());
+        Ok(frame_buf)
+    }
+
+    /// Enqueue a frame to be send and pass the buffer to the provided closure.
+    /// The closure then returns the size of the data written into the buffer.
+    ///
+    /// Also see [send](#method.send).
+    pub fn send_with<F>(&mut self, max_size: usize, f: F) -> Result<usize, SendError>
+    where
+        F: FnOnce(&mut [u8]) -> usize,
+    {
+        let size = self
+            .tx_buffer
+            .enqueue_with_infallible(max_size, (), f)
+            .map_err(|_| SendError::BufferFull)?;
+
+        net_trace!("ethernet: buffer to send {} octets", size);
+        Ok(size)
+    }
+
+    /// Enqueue a frame to send, and fill it from a slice.
+    ///
+    /// See also [send](#method.send).
+    pub fn send_slice(&mut self, data: &[u8]) -> Result<(), SendError> {
+        self.send(data.len())?.copy_from_slice(data);
+        Ok(())
+    }
+
+    /// Dequeue a frame, and return a pointer to it.
+    ///
+    /// This function returns `Err(RecvError::Exhausted)` if the receive buffer is empty.
+    pub fn recv(&mut self) -> Result<&[u8], RecvError> {
+        let ((), frame_buf) = self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
+
+        net_trace!("ethernet: receive {} buffered octets", frame_buf.len());
+        Ok(frame_buf)
+    }
+
+    /// Dequeue a frame, and copy it into the given slice.
+    ///
+    /// See also [recv](#method.recv).
+    pub fn recv_slice(&mut self, data: &mut [u8]) -> Result<usize, RecvError> {
+        let buffer = self.recv()?;
+        let length = min(data.len(), buffer.len());
+        data[..length].copy_from_slice(&buffer[..length]);
+        Ok(length)
+    }
+
+    pub(crate) fn accepts<T: AsRef<[u8]>>(&self, frame: &EthernetFrame<T>) -> bool {
+        match self.ethertype {
+            Some(ethertype) => frame.ethertype() == ethertype,
+            None => true,
+        }
+    }
+
+    pub(crate) fn process<T: AsRef<[u8]>>(&mut self, _cx: &mut Context, frame: &EthernetFrame<T>) {
+        debug_assert!(self.accepts(frame));
+
+        let frame = frame.as_ref();
+        net_trace!("ethernet: receiving {} octets", frame.len());
+
+        match self.rx_buffer.enqueue(frame.len(), ()) {
+            Ok(buf) => buf.copy_from_slice(frame),
+            Err(_) => net_trace!("ethernet: buffer full, dropped incoming frame"),
+        }
+
+        #[cfg(feature = "async")]
+        self.rx_waker.wake();
+    }
+
+    pub(crate) fn dispatch<F, E>(&mut self, cx: &mut Context, emit: F) -> Result<(), E>
+    where
+        F: FnOnce(&mut Context, &[u8]) -> Result<(), E>,
+    {
+        let res = self.tx_buffer.dequeue_with(|&mut (), buffer| {
+            if EthernetFrame::new_checked(&*buffer).is_err() {
+                net_trace!("ethernet: malformed frame in queue, dropping.");
+                return Ok(());
+            }
+            net_trace!("ethernet: sending");
+            emit(cx, buffer)
+        });
+        match res {
+            Err(Empty) => Ok(()),
+            Ok(Err(e)) => Err(e),
+            Ok(Ok(())) => {
+                #[cfg(feature = "async")]
+                self.tx_waker.wake();
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn poll_at(&self, _cx: &mut Context) -> PollAt {
+        if self.tx_buffer.is_empty() {
+            PollAt::Ingress
+        } else {
+            PollAt::Now
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Error, Layer};
+
+    const FRAME_BYTES: [u8; 18] = [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x88, 0xcc, 0xaa,
+        0x00, 0x00, 0xff,
+    ];
+
+    fn buffer(packets: usize) -> PacketBuffer<'static> {
+        PacketBuffer::new(vec![PacketMetadata::EMPTY; packets], vec![0; 48 * packets])
+    }
+
+    fn socket(
+        rx_buffer: PacketBuffer<'static>,
+        tx_buffer: PacketBuffer<'static>,
+    ) -> Socket<'static> {
+        Socket::new(Some(EthernetProtocol::Lldp), rx_buffer, tx_buffer)
+    }
+
+    #[test]
+    fn test_send_truncated() {
+        let mut socket = socket(buffer(0), buffer(1));
+        assert_eq!(socket.send_slice(&[0; 56][..]), Err(SendError::BufferFull));
+    }
+
+    #[test]
+    fn test_send_dispatch() {
+        let mut socket = socket(buffer(0), buffer(1));
+        let mut cx = Context::mock();
+
+        assert!(socket.can_send());
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, _| unreachable!()),
+            Ok::<_, Error>(())
+        );
+
+        assert_eq!(socket.send_slice(&FRAME_BYTES[..]), Ok(()));
+        assert_eq!(socket.send_slice(b""), Err(SendError::BufferFull));
+        assert!(!socket.can_send());
+
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, frame| {
+                assert_eq!(frame, &FRAME_BYTES[..]);
+                Err(Error::Exhausted(Layer::Phy))
+            }),
+            Err(Error::Exhausted(Layer::Phy))
+        );
+        assert!(!socket.can_send());
+
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, frame| {
+                assert_eq!(frame, &FRAME_BYTES[..]);
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+        assert!(socket.can_send());
+    }
+
+    #[test]
+    fn test_send_truncated_frame() {
+        let mut socket = socket(buffer(0), buffer(1));
+        let mut cx = Context::mock();
+
+        assert_eq!(socket.send_slice(&FRAME_BYTES[..10]), Ok(()));
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, _| unreachable!()),
+            Ok::<_, Error>(())
+        );
+        assert!(socket.can_send());
+    }
+
+    #[test]
+    fn test_recv_process() {
+        let mut socket = socket(buffer(1), buffer(0));
+        let mut cx = Context::mock();
+        assert!(!socket.can_recv());
+        assert_eq!(socket.recv(), Err(RecvError::Exhausted));
+
+        let frame = EthernetFrame::new_unchecked(&FRAME_BYTES[..]);
+        assert!(socket.accepts(&frame));
+        socket.process(&mut cx, &frame);
+        assert!(socket.can_recv());
+
+        // The buffer is full, so this frame is dropped.
+        socket.process(&mut cx, &frame);
+        assert_eq!(socket.recv(), Ok(&FRAME_BYTES[..]));
+        assert!(!socket.can_recv());
+    }
+
+    #[test]
+    fn test_accepts_ethertype() {
+        let mut other = FRAME_BYTES;
+        EthernetFrame::new_unchecked(&mut other[..]).set_ethertype(EthernetProtocol::Arp);
+        let other = EthernetFrame::new_unchecked(&other[..]);
+        let frame = EthernetFrame::new_unchecked(&FRAME_BYTES[..]);
+
+        let socket = socket(buffer(1), buffer(0));
+        assert!(socket.accepts(&frame));
+        assert!(!socket.accepts(&other));
+
+        let socket = Socket::new(None, buffer(1), buffer(0));
+        assert!(socket.accepts(&frame));
+        assert!(socket.accepts(&other));
+    }
+}
@@ -0,0 +1,316 @@
+/*! Adapters exposing sockets as [futures] streams and sinks.
+
+A [UdpFramed] turns a UDP socket into a [Stream] of received datagrams and a [Sink]
+of datagrams to send, each paired with the remote endpoint:
+
+```rust
+# use std::cell::RefCell;
+# use std::rc::Rc;
+# use smoltcp::iface::SocketSet;
+# use smoltcp::socket::udp;
+use smoltcp::socket::framed::UdpFramed;
+
+let udp_socket = udp::Socket::new(
+    udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 4], vec![0; 1024]),
+    udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 4], vec![0; 1024]),
+);
+let mut sockets = SocketSet::new(vec![]);
+let handle = sockets.add(udp_socket);
+let sockets = Rc::new(RefCell::new(sockets));
+
+let framed = UdpFramed::new(sockets.clone(), handle);
+```
+
+The adapters only move data in and out of the socket buffers; the interface must
+still be polled for the datagrams to be sent and received.
+
+[futures]: https://crates.io/crates/futures
+*/
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_sink::Sink;
+
+use crate::iface::{SocketHandle, SocketSet};
+use crate::socket::udp;
+use crate::wire::IpEndpoint;
+
+/// Shared access to a [SocketSet].
+pub trait SharedSockets<'a> {
+    /// Call `f` with the socket set.
+    fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut SocketSet<'a>) -> R;
+}
+
+impl<'a> SharedSockets<'a> for Rc<RefCell<SocketSet<'a>>> {
+    fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut SocketSet<'a>) -> R,
+    {
+        f(&mut self.borrow_mut())
+    }
+}
+
+impl<'a> SharedSockets<'a> for &RefCell<SocketSet<'a>> {
+    fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut SocketSet<'a>) -> R,
+    {
+        f(&mut self.borrow_mut())
+    }
+}
+
+/// A UDP socket usable as a [Stream] and [Sink] of `(IpEndpoint, Bytes)` datagrams.
+///
+/// The stream ends once the socket is closed. The sink keeps at most one datagram
+/// that did not fit in the transmit buffer yet; flushing or closing the sink only waits
+/// until it has been enqueued in the socket, not until it has been sent, and never
+/// closes the socket.
+pub struct UdpFramed<S> {
+    sockets: S,
+    handle: SocketHandle,
+    pending: Option<(IpEndpoint, Bytes)>,
+}
+
+impl<S> UdpFramed<S> {
+    /// Create an adapter for the UDP socket with the given handle.
+    ///
+    /// The socket must be bound to be able to send and receive datagrams.
+    pub fn new(sockets: S, handle: SocketHandle) -> UdpFramed<S> {
+        UdpFramed {
+            sockets,
+            handle,
+            pending: None,
+        }
+    }
+
+    /// Return the handle of the underlying socket.
+    pub fn handle(&self) -> SocketHandle {
+        self.handle
+    }
+
+    /// Consume the adapter, returning the shared socket set.
+    ///
+    /// A datagram that was not enqueued in the socket yet is discarded.
+    pub fn into_inner(self) -> S {
+        self.sockets
+    }
+
+    fn poll_send_pending<'a>(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), udp::SendError>>
+    where
+        S: SharedSockets<'a>,
+    {
+        let (endpoint, data) = match &self.pending {
+            Some(pending) => pending,
+            None => return Poll::Ready(Ok(())),
+        };
+
+        let result = self.sockets.with(|sockets| {
+            let socket = sockets.get_mut::<udp::Socket>(self.handle);
+            if data.len() > socket.payload_send_capacity() {
+                // Waiting will not help, the datagram can never fit.
+                return Poll::Ready(Err(udp::SendError::BufferFull));
+            }
+            match socket.send_slice(data, *endpoint) {
+                Err(udp::SendError::BufferFull) => {
+                    socket.register_send_waker(cx.waker());
+                    Poll::Pending
+                }
+                result => Poll::Ready(result),
+            }
+        });
+        if result.is_ready() {
+            self.pending = None;
+        }
+        result
+    }
+}
+
+impl<'a, S> Stream for UdpFramed<S>
+where
+    S: SharedSockets<'a> + Unpin,
+{
+    type Item = (IpEndpoint, Bytes);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.sockets.with(|sockets| {
+            let socket = sockets.get_mut::<udp::Socket>(self.handle);
+            if !socket.can_recv() {
+                if !socket.is_open() {
+                    return Poll::Ready(None);
+                }
+                socket.register_recv_waker(cx.waker());
+                return Poll::Pending;
+            }
+            match socket.recv() {
+                Ok((data, endpoint)) => Poll::Ready(Some((endpoint, Bytes::copy_from_slice(data)))),
+                Err(udp::RecvError::Exhausted) => unreachable!(),
+            }
+        })
+    }
+}
+
+impl<'a, S> Sink<(IpEndpoint, Bytes)> for UdpFramed<S>
+where
+    S: SharedSockets<'a> + Unpin,
+{
+    type Error = udp::SendError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: (IpEndpoint, Bytes)) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        debug_assert!(
+            this.pending.is_none(),
+            "start_send called without poll_ready"
+        );
+        this.pending = Some(item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+}
+
+#[cfg(all(test, feature = "medium-ip", feature = "proto-ipv4"))]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    use super::*;
+    use crate::iface::{Interface, InterfaceBuilder};
+    use crate::phy::{Loopback, Medium};
+    use crate::time::Instant;
+    use crate::wire::{IpAddress, IpCidr};
+
+    const LOCAL_ADDR: IpAddress = IpAddress::v4(127, 0, 0, 1);
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn udp_socket(port: u16) -> udp::Socket<'static> {
+        let mut socket = udp::Socket::new(
+            udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 2], vec![0; 16]),
+            udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 2], vec![0; 16]),
+        );
+        socket.bind(port).unwrap();
+        socket
+    }
+
+    fn setup() -> (
+        Interface<'static>,
+        Loopback,
+        Rc<RefCell<SocketSet<'static>>>,
+        SocketHandle,
+        SocketHandle,
+    ) {
+        let mut device = Loopback::new(Medium::Ip);
+        let mut ip_addrs = heapless::Vec::<IpCidr, 5>::new();
+        ip_addrs.push(IpCidr::new(LOCAL_ADDR, 8)).unwrap();
+        let iface = InterfaceBuilder::new()
+            .ip_addrs(ip_addrs)
+            .finalize(&mut device);
+
+        let mut sockets = SocketSet::new(vec![]);
+        let handle_a = sockets.add(udp_socket(1000));
+        let handle_b = sockets.add(udp_socket(2000));
+        (
+            iface,
+            device,
+            Rc::new(RefCell::new(sockets)),
+            handle_a,
+            handle_b,
+        )
+    }
+
+    #[test]
+    fn test_send_recv() {
+        let (mut iface, mut device, sockets, handle_a, handle_b) = setup();
+        let mut sink = UdpFramed::new(sockets.clone(), handle_a);
+        let mut stream = UdpFramed::new(&*sockets, handle_b);
+
+        let wakes = Arc::new(CountingWaker::default());
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Pending);
+
+        let item = ((LOCAL_ADDR, 2000).into(), Bytes::from_static(b"hello"));
+        assert_eq!(Pin::new(&mut sink).poll_ready(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(Pin::new(&mut sink).start_send(item), Ok(()));
+        assert_eq!(Pin::new(&mut sink).poll_flush(&mut cx), Poll::Ready(Ok(())));
+
+        iface
+            .poll(
+                Instant::from_millis(0),
+                &mut device,
+                &mut sockets.borrow_mut(),
+            )
+            .unwrap();
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(Some((
+                (LOCAL_ADDR, 1000).into(),
+                Bytes::from_static(b"hello")
+            )))
+        );
+
+        sockets
+            .borrow_mut()
+            .get_mut::<udp::Socket>(handle_b)
+            .close();
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Ready(None));
+    }
+
+    #[test]
+    fn test_send_full() {
+        let (_iface, _device, sockets, handle_a, _handle_b) = setup();
+        let mut sink = UdpFramed::new(sockets, handle_a);
+
+        let wakes = Arc::new(CountingWaker::default());
+        let waker = Waker::from(wakes);
+        let mut cx = Context::from_waker(&waker);
+
+        let endpoint = (LOCAL_ADDR, 2000).into();
+        let data = Bytes::from_static(&[0; 12]);
+        assert_eq!(
+            Pin::new(&mut sink).start_send((endpoint, data.clone())),
+            Ok(())
+        );
+        assert_eq!(Pin::new(&mut sink).poll_ready(&mut cx), Poll::Ready(Ok(())));
+
+        // The second datagram does not fit until the first one has been sent.
+        assert_eq!(Pin::new(&mut sink).start_send((endpoint, data)), Ok(()));
+        assert_eq!(Pin::new(&mut sink).poll_ready(&mut cx), Poll::Pending);
+
+        // This one never fits.
+        let mut sink = UdpFramed::new(sink.into_inner(), handle_a);
+        let data = Bytes::from_static(&[0; 17]);
+        assert_eq!(Pin::new(&mut sink).start_send((endpoint, data)), Ok(()));
+        assert_eq!(
+            Pin::new(&mut sink).poll_flush(&mut cx),
+            Poll::Ready(Err(udp::SendError::BufferFull))
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use core::cmp;
+use core::fmt;
 #[cfg(feature = "async")]
 use core::task::Waker;
 
@@ -7,7 +8,7 @@ use crate::phy::ChecksumCapabilities;
 use crate::socket::WakerRegistration;
 use crate::socket::{Context, PollAt};
 
-use crate::storage::Empty;
+use crate::storage::{Empty, MemoryUsage};
 use crate::wire::IcmpRepr;
 #[cfg(feature = "proto-ipv4")]
 use crate::wire::{Icmpv4Packet, Icmpv4Repr, Ipv4Repr};
@@ -24,6 +25,27 @@ pub enum BindError {
     Unaddressable,
 }
 
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BindError::InvalidState => write!(f, "invalid state"),
+            BindError::Unaddressable => write!(f, "unaddressable destination"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BindError {}
+
+impl From<BindError> for crate::Error {
+    fn from(err: BindError) -> Self {
+        match err {
+            BindError::InvalidState => crate::Error::Illegal(crate::Layer::Network),
+            BindError::Unaddressable => crate::Error::Unaddressable(crate::Layer::Network),
+        }
+    }
+}
+
 /// Error returned by [`Socket::send`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -32,6 +54,27 @@ pub enum SendError {
     BufferFull,
 }
 
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendError::Unaddressable => write!(f, "unaddressable destination"),
+            SendError::BufferFull => write!(f, "buffer full"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SendError {}
+
+impl From<SendError> for crate::Error {
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::Unaddressable => crate::Error::Unaddressable(crate::Layer::Network),
+            SendError::BufferFull => crate::Error::Exhausted(crate::Layer::Network),
+        }
+    }
+}
+
 /// Error returned by [`Socket::recv`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -39,6 +82,25 @@ pub enum RecvError {
     Exhausted,
 }
 
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecvError::Exhausted => write!(f, "buffer empty"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RecvError {}
+
+impl From<RecvError> for crate::Error {
+    fn from(err: RecvError) -> Self {
+        match err {
+            RecvError::Exhausted => crate::Error::Exhausted(crate::Layer::Network),
+        }
+    }
+}
+
 /// Type of endpoint to bind the ICMP socket to. See [IcmpSocket::bind] for
 /// more details.
 ///
@@ -67,11 +129,30 @@ impl Default for Endpoint {
     }
 }
 
+/// Metadata of an ICMP packet.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct IcmpMetadata {
+    /// The remote address the packet was received from or is sent to.
+    pub addr: IpAddress,
+    /// The time-to-live (IPv4) or hop limit (IPv6) of a received packet.
+    ///
+    /// This is 0 for packets to be sent, which use the hop limit of the socket.
+    pub hop_limit: u8,
+}
+
+impl From<IpAddress> for IcmpMetadata {
+    fn from(addr: IpAddress) -> IcmpMetadata {
+        IcmpMetadata { addr, hop_limit: 0 }
+    }
+}
+
 /// An ICMP packet metadata.
-pub type PacketMetadata = crate::storage::PacketMetadata<IpAddress>;
+pub type PacketMetadata = crate::storage::PacketMetadata<IcmpMetadata>;
 
 /// An ICMP packet ring buffer.
-pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, IpAddress>;
+pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, IcmpMetadata>;
 
 /// A ICMP socket
 ///
@@ -284,6 +365,18 @@ impl<'a> Socket<'a> {
         self.tx_buffer.payload_capacity()
     }
 
+    /// Return the number of bytes of receive buffer storage used and committed by the socket.
+    #[inline]
+    pub fn recv_memory_usage(&self) -> MemoryUsage {
+        self.rx_buffer.memory_usage()
+    }
+
+    /// Return the number of bytes of transmit buffer storage used and committed by the socket.
+    #[inline]
+    pub fn send_memory_usage(&self) -> MemoryUsage {
+        self.tx_buffer.memory_usage()
+    }
+
     /// Check whether the socket is open.
     #[inline]
     pub fn is_open(&self) -> bool {
@@ -303,7 +396,7 @@ impl<'a> Socket<'a> {
 
         let packet_buf = self
             .tx_buffer
-            .enqueue(size, endpoint)
+            .enqueue(size, endpoint.into())
             .map_err(|_| SendError::BufferFull)?;
 
         net_trace!("icmp:{}: buffer to send {} octets", endpoint, size);
@@ -330,7 +423,7 @@ impl<'a> Socket<'a> {
 
         let size = self
             .tx_buffer
-            .enqueue_with_infallible(max_size, endpoint, f)
+            .enqueue_with_infallible(max_size, endpoint.into(), f)
             .map_err(|_| SendError::BufferFull)?;
 
         net_trace!("icmp:{}: buffer to send {} octets", endpoint, size);
@@ -351,14 +444,24 @@ impl<'a> Socket<'a> {
     ///
     /// This function returns `Err(Error::Exhausted)` if the receive buffer is empty.
     pub fn recv(&mut self) -> Result<(&[u8], IpAddress), RecvError> {
-        let (endpoint, packet_buf) = self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
+        let (packet_buf, metadata) = self.recv_with_metadata()?;
+        Ok((packet_buf, metadata.addr))
+    }
+
+    /// Dequeue a packet received from a remote endpoint, and return its metadata as well
+    /// as a pointer to the payload.
+    ///
+    /// Besides the remote address, the metadata includes the hop limit the packet was
+    /// received with. See also [recv](#method.recv).
+    pub fn recv_with_metadata(&mut self) -> Result<(&[u8], IcmpMetadata), RecvError> {
+        let (metadata, packet_buf) = self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
 
         net_trace!(
             "icmp:{}: receive {} buffered octets",
-            endpoint,
+            metadata.addr,
             packet_buf.len()
         );
-        Ok((packet_buf, endpoint))
+        Ok((packet_buf, metadata))
     }
 
     /// Dequeue a packet received from a remote endpoint, copy the payload into the given slice,
@@ -444,15 +547,17 @@ impl<'a> Socket<'a> {
     }
 
     pub(crate) fn process(&mut self, _cx: &mut Context, ip_repr: &IpRepr, icmp_repr: &IcmpRepr) {
+        let metadata = IcmpMetadata {
+            addr: ip_repr.src_addr(),
+            hop_limit: ip_repr.hop_limit(),
+        };
+
         match *icmp_repr {
             #[cfg(feature = "proto-ipv4")]
             IcmpRepr::Ipv4(ref icmp_repr) => {
                 net_trace!("icmp: receiving {} octets", icmp_repr.buffer_len());
 
-                match self
-                    .rx_buffer
-                    .enqueue(icmp_repr.buffer_len(), ip_repr.src_addr())
-                {
+                match self.rx_buffer.enqueue(icmp_repr.buffer_len(), metadata) {
                     Ok(packet_buf) => {
                         icmp_repr.emit(
                             &mut Icmpv4Packet::new_unchecked(packet_buf),
@@ -466,10 +571,7 @@ impl<'a> Socket<'a> {
             IcmpRepr::Ipv6(ref icmp_repr) => {
                 net_trace!("icmp: receiving {} octets", icmp_repr.buffer_len());
 
-                match self
-                    .rx_buffer
-                    .enqueue(icmp_repr.buffer_len(), ip_repr.src_addr())
-                {
+                match self.rx_buffer.enqueue(icmp_repr.buffer_len(), metadata) {
                     Ok(packet_buf) => icmp_repr.emit(
                         &ip_repr.src_addr(),
                         &ip_repr.dst_addr(),
@@ -490,13 +592,14 @@ impl<'a> Socket<'a> {
         F: FnOnce(&mut Context, (IpRepr, IcmpRepr)) -> Result<(), E>,
     {
         let hop_limit = self.hop_limit.unwrap_or(64);
-        let res = self.tx_buffer.dequeue_with(|remote_endpoint, packet_buf| {
+        let res = self.tx_buffer.dequeue_with(|metadata, packet_buf| {
+            let remote_endpoint = metadata.addr;
             net_trace!(
                 "icmp:{}: sending {} octets",
                 remote_endpoint,
                 packet_buf.len()
             );
-            match *remote_endpoint {
+            match remote_endpoint {
                 #[cfg(feature = "proto-ipv4")]
                 IpAddress::Ipv4(dst_addr) => {
                     let src_addr = match cx.get_source_address_ipv4(dst_addr) {
@@ -526,6 +629,7 @@ impl<'a> Socket<'a> {
                         next_header: IpProtocol::Icmp,
                         payload_len: repr.buffer_len(),
                         hop_limit: hop_limit,
+                        traffic_class: 0,
                     });
                     emit(cx, (ip_repr, IcmpRepr::Ipv4(repr)))
                 }
@@ -563,6 +667,7 @@ impl<'a> Socket<'a> {
                         next_header: IpProtocol::Icmpv6,
                         payload_len: repr.buffer_len(),
                         hop_limit: hop_limit,
+                        traffic_class: 0,
                     });
                     emit(cx, (ip_repr, IcmpRepr::Ipv6(repr)))
                 }
@@ -619,7 +724,7 @@ mod tests_common {
 mod test_ipv4 {
     use super::tests_common::*;
     use crate::wire::{Icmpv4DstUnreachable, IpEndpoint, Ipv4Address};
-    use crate::Error;
+    use crate::{Error, Layer};
 
     const REMOTE_IPV4: Ipv4Address = Ipv4Address([192, 168, 1, 2]);
     const LOCAL_IPV4: Ipv4Address = Ipv4Address([192, 168, 1, 1]);
@@ -640,6 +745,7 @@ mod test_ipv4 {
         next_header: IpProtocol::Icmp,
         payload_len: 24,
         hop_limit: 0x40,
+        traffic_class: 0,
     });
 
     static REMOTE_IPV4_REPR: IpRepr = IpRepr::Ipv4(Ipv4Repr {
@@ -648,6 +754,7 @@ mod test_ipv4 {
         next_header: IpProtocol::Icmp,
         payload_len: 24,
         hop_limit: 0x40,
+        traffic_class: 0,
     });
 
     #[test]
@@ -696,9 +803,9 @@ mod test_ipv4 {
             socket.dispatch(&mut cx, |_, (ip_repr, icmp_repr)| {
                 assert_eq!(ip_repr, LOCAL_IPV4_REPR);
                 assert_eq!(icmp_repr, ECHOV4_REPR.into());
-                Err(Error::Unaddressable)
+                Err(Error::Unaddressable(Layer::Link))
             }),
-            Err(Error::Unaddressable)
+            Err(Error::Unaddressable(Layer::Link))
         );
         // buffer is not taken off of the tx queue due to the error
         assert!(!socket.can_send());
@@ -741,6 +848,7 @@ mod test_ipv4 {
                         next_header: IpProtocol::Icmp,
                         payload_len: ECHOV4_REPR.buffer_len(),
                         hop_limit: 0x2a,
+                        traffic_class: 0,
                     })
                 );
                 Ok::<_, Error>(())
@@ -776,6 +884,18 @@ mod test_ipv4 {
         assert!(!socket.can_recv());
     }
 
+    #[test]
+    fn test_recv_with_metadata() {
+        let mut socket = socket(buffer(1), buffer(1));
+        let mut cx = Context::mock();
+        assert_eq!(socket.bind(Endpoint::Ident(0x1234)), Ok(()));
+
+        socket.process(&mut cx, &REMOTE_IPV4_REPR, &ECHOV4_REPR.into());
+        let (_, metadata) = socket.recv_with_metadata().unwrap();
+        assert_eq!(metadata.addr, REMOTE_IPV4.into());
+        assert_eq!(metadata.hop_limit, 0x40);
+    }
+
     #[test]
     fn test_accept_bad_id() {
         let mut socket = socket(buffer(1), buffer(1));
@@ -826,6 +946,7 @@ mod test_ipv4 {
                 next_header: IpProtocol::Icmp,
                 payload_len: 12,
                 hop_limit: 0x40,
+                traffic_class: 0,
             },
             data: data,
         };
@@ -835,6 +956,7 @@ mod test_ipv4 {
             next_header: IpProtocol::Icmp,
             payload_len: icmp_repr.buffer_len(),
             hop_limit: 0x40,
+            traffic_class: 0,
         });
 
         assert!(!socket.can_recv());
@@ -861,7 +983,7 @@ mod test_ipv6 {
     use super::tests_common::*;
 
     use crate::wire::{Icmpv6DstUnreachable, IpEndpoint, Ipv6Address};
-    use crate::Error;
+    use crate::{Error, Layer};
 
     const REMOTE_IPV6: Ipv6Address =
         Ipv6Address([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
@@ -883,6 +1005,7 @@ mod test_ipv6 {
         next_header: IpProtocol::Icmpv6,
         payload_len: 24,
         hop_limit: 0x40,
+        traffic_class: 0,
     });
 
     static REMOTE_IPV6_REPR: IpRepr = IpRepr::Ipv6(Ipv6Repr {
@@ -891,6 +1014,7 @@ mod test_ipv6 {
         next_header: IpProtocol::Icmpv6,
         payload_len: 24,
         hop_limit: 0x40,
+        traffic_class: 0,
     });
 
     #[test]
@@ -944,9 +1068,9 @@ mod test_ipv6 {
             socket.dispatch(&mut cx, |_, (ip_repr, icmp_repr)| {
                 assert_eq!(ip_repr, LOCAL_IPV6_REPR);
                 assert_eq!(icmp_repr, ECHOV6_REPR.into());
-                Err(Error::Unaddressable)
+                Err(Error::Unaddressable(Layer::Link))
             }),
-            Err(Error::Unaddressable)
+            Err(Error::Unaddressable(Layer::Link))
         );
         // buffer is not taken off of the tx queue due to the error
         assert!(!socket.can_send());
@@ -994,6 +1118,7 @@ mod test_ipv6 {
                         next_header: IpProtocol::Icmpv6,
                         payload_len: ECHOV6_REPR.buffer_len(),
                         hop_limit: 0x2a,
+                        traffic_class: 0,
                     })
                 );
                 Ok::<_, Error>(())
@@ -1089,6 +1214,7 @@ mod test_ipv6 {
                 next_header: IpProtocol::Icmpv6,
                 payload_len: 12,
                 hop_limit: 0x40,
+                traffic_class: 0,
             },
             data: data,
         };
@@ -1098,6 +1224,7 @@ mod test_ipv6 {
             next_header: IpProtocol::Icmpv6,
             payload_len: icmp_repr.buffer_len(),
             hop_limit: 0x40,
+            traffic_class: 0,
         });
 
         assert!(!socket.can_recv());
@@ -0,0 +1,1115 @@
+// Heads up! Before working on this file you should read, at least,
+// RFC 6762 § 5, 6 and 8, and RFC 6763 § 4 to 6.
+
+use byteorder::{ByteOrder, NetworkEndian};
+use core::iter;
+
+use heapless::{String, Vec};
+
+use crate::socket::{Context, PollAt};
+use crate::time::{Duration, Instant};
+use crate::wire::dns::{
+    Flags, Opcode, Packet, Question, Record, Type, CLASS_IN, CLASS_MDNS_FLAG, HEADER_LEN,
+};
+#[cfg(feature = "proto-ipv4")]
+use crate::wire::Ipv4Address;
+#[cfg(feature = "proto-ipv6")]
+use crate::wire::Ipv6Address;
+use crate::wire::{self, IpAddress, IpProtocol, IpRepr, UdpRepr};
+
+/// The UDP port of multicast DNS.
+pub const MDNS_PORT: u16 = 5353;
+
+/// The IPv4 group of multicast DNS.
+#[cfg(feature = "proto-ipv4")]
+pub const MDNS_IPV4_GROUP: Ipv4Address = Ipv4Address([224, 0, 0, 251]);
+
+/// The IPv6 group of multicast DNS.
+#[cfg(feature = "proto-ipv6")]
+pub const MDNS_IPV6_GROUP: Ipv6Address = Ipv6Address([
+    0xff, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfb,
+]);
+
+/// The groups probes and announcements are sent to.
+const GROUPS: &[IpAddress] = &[
+    #[cfg(feature = "proto-ipv4")]
+    IpAddress::Ipv4(MDNS_IPV4_GROUP),
+    #[cfg(feature = "proto-ipv6")]
+    IpAddress::Ipv6(MDNS_IPV6_GROUP),
+];
+
+const MAX_LABEL_LEN: usize = 63;
+const MAX_TXT_ENTRY_LEN: usize = 255;
+const MAX_ANSWER_COUNT: usize = 16;
+
+/// The TTL of the records containing the host name, see RFC 6762 § 10.
+const HOST_TTL: u32 = 120;
+/// The TTL of the other records.
+const SERVICE_TTL: u32 = 4500;
+/// The TTL of the records of legacy unicast responses, see RFC 6762 § 6.7.
+const LEGACY_TTL: u32 = 10;
+
+const PROBE_COUNT: u8 = 3;
+const PROBE_INTERVAL: Duration = Duration::from_millis(250);
+const ANNOUNCE_COUNT: u8 = 2;
+const ANNOUNCE_INTERVAL: Duration = Duration::from_millis(1_000);
+
+/// A service advertised through DNS-based service discovery.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Service<'a> {
+    /// The instance name, such as `"Kitchen printer"`.
+    pub instance: &'a str,
+    /// The service type, such as `"_ipp._tcp"`.
+    pub service_type: &'a str,
+    /// The port the service is provided on.
+    pub port: u16,
+    /// The `key=value` entries of the TXT record.
+    pub txt: &'a [&'a str],
+}
+
+/// The state of an mDNS responder.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum State {
+    /// Checking that no other host on the link uses the names of the responder.
+    Probing,
+    /// Announcing the records of the responder. Queries are answered.
+    Announcing,
+    /// Answering queries.
+    Running,
+    /// Another host uses one of the names of the responder. Queries are not answered
+    /// until the responder is given another host name.
+    Conflict,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Phase {
+    /// Probing starts after a random delay, so that hosts powered on at once do not
+    /// probe at once, see RFC 6762 § 8.1.
+    Start,
+    Probing,
+    Announcing,
+    Running,
+    Conflict,
+}
+
+/// A name owned by the responder.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum Name {
+    /// `<hostname>.local`
+    Host,
+    /// `<service type>.local`, which is shared with the other hosts providing the service.
+    Service(usize),
+    /// `<instance>.<service type>.local`
+    Instance(usize),
+}
+
+/// A set of records of the responder.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum Answer {
+    /// The IPv4 addresses of the interface.
+    A,
+    /// The IPv6 addresses of the interface.
+    Aaaa,
+    /// The instance of a service.
+    Ptr(usize),
+    /// The host and port of a service instance.
+    Srv(usize),
+    /// The TXT record of a service instance.
+    Txt(usize),
+}
+
+impl Answer {
+    fn name(self) -> Name {
+        match self {
+            Answer::A | Answer::Aaaa => Name::Host,
+            Answer::Ptr(index) => Name::Service(index),
+            Answer::Srv(index) | Answer::Txt(index) => Name::Instance(index),
+        }
+    }
+
+    fn type_(self) -> Type {
+        match self {
+            Answer::A => Type::A,
+            Answer::Aaaa => Type::Aaaa,
+            Answer::Ptr(_) => Type::Ptr,
+            Answer::Srv(_) => Type::Srv,
+            Answer::Txt(_) => Type::Txt,
+        }
+    }
+
+    /// Return the records that should be sent along with this one, as additional records,
+    /// so that the querier does not have to ask for them, see RFC 6763 § 12.
+    fn additionals(self) -> [Option<Answer>; 4] {
+        match self {
+            Answer::Ptr(index) => [
+                Some(Answer::Srv(index)),
+                Some(Answer::Txt(index)),
+                Some(Answer::A),
+                Some(Answer::Aaaa),
+            ],
+            Answer::Srv(_) => [Some(Answer::A), Some(Answer::Aaaa), None, None],
+            _ => [None; 4],
+        }
+    }
+}
+
+/// A response to the queries received since the socket was last dispatched.
+#[derive(Debug)]
+struct Response {
+    addr: IpAddress,
+    port: u16,
+    /// The transaction ID and the question of a legacy unicast query, which are repeated
+    /// in the response.
+    legacy: Option<(u16, Name, Type)>,
+    answers: Vec<Answer, MAX_ANSWER_COUNT>,
+    additionals: Vec<Answer, MAX_ANSWER_COUNT>,
+}
+
+impl Response {
+    fn push(&mut self, answer: Answer) {
+        if self.answers.contains(&answer) {
+            return;
+        }
+        if self.answers.push(answer).is_err() {
+            net_trace!("mdns: too many answers, ignoring {:?}", answer);
+        }
+        if let Some(index) = self.additionals.iter().position(|&a| a == answer) {
+            self.additionals.swap_remove(index);
+        }
+        for additional in answer.additionals().into_iter().flatten() {
+            if !self.answers.contains(&additional) && !self.additionals.contains(&additional) {
+                // Additional records are optional.
+                let _ = self.additionals.push(additional);
+            }
+        }
+    }
+}
+
+/// A multicast DNS responder.
+///
+/// The responder answers queries for the addresses of the interface under the name
+/// `<hostname>.local`, and advertises services through DNS-based service discovery
+/// (RFC 6763). Before answering queries, it probes for the names it owns, and announces
+/// its records, as described in RFC 6762 § 8.
+///
+/// The interface must be a member of [MDNS_IPV4_GROUP] to receive queries over IPv4,
+/// see [Interface::join_multicast_group](crate::iface::Interface::join_multicast_group).
+///
+/// Conflicts are only detected while probing, in which case the responder stops in the
+/// [Conflict](State::Conflict) state and should be given another host name.
+#[derive(Debug)]
+pub struct Socket<'a> {
+    hostname: String<MAX_LABEL_LEN>,
+    services: &'a [Service<'a>],
+
+    phase: Phase,
+    /// The number of probes or announcements sent in the current phase.
+    count: u8,
+    /// When to send the next probe or announcement.
+    at: Instant,
+    /// The groups the current probe or announcement has yet to be sent to, as a bit mask
+    /// of indices into `GROUPS`.
+    pending: u8,
+
+    response: Option<Response>,
+}
+
+impl<'a> Socket<'a> {
+    /// Create an mDNS responder for `hostname`, which is a single label such as
+    /// `"printer"`, advertising `services`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hostname`, a service instance name, or a label of a service type is empty
+    /// or longer than 63 octets, or if a TXT entry is longer than 255 octets.
+    pub fn new(hostname: &str, services: &'a [Service<'a>]) -> Socket<'a> {
+        for service in services {
+            check_label(service.instance);
+            service.service_type.split('.').for_each(check_label);
+            for entry in service.txt {
+                if entry.len() > MAX_TXT_ENTRY_LEN {
+                    panic!("TXT entry {:?} is too long", entry)
+                }
+            }
+        }
+
+        Socket {
+            hostname: host_label(hostname),
+            services,
+            phase: Phase::Start,
+            count: 0,
+            at: Instant::ZERO,
+            pending: 0,
+            response: None,
+        }
+    }
+
+    /// Return the host name of the responder.
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /// Set the host name of the responder, and probe for its names again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hostname` is empty or longer than 63 octets.
+    pub fn set_hostname(&mut self, hostname: &str) {
+        self.hostname = host_label(hostname);
+        self.reset();
+    }
+
+    /// Return the services advertised by the responder.
+    pub fn services(&self) -> &'a [Service<'a>] {
+        self.services
+    }
+
+    /// Return the state of the responder.
+    pub fn state(&self) -> State {
+        match self.phase {
+            Phase::Start | Phase::Probing => State::Probing,
+            Phase::Announcing => State::Announcing,
+            Phase::Running => State::Running,
+            Phase::Conflict => State::Conflict,
+        }
+    }
+
+    /// Probe for the names of the responder and announce its records again.
+    ///
+    /// This should be done when the addresses of the interface change.
+    pub fn reset(&mut self) {
+        net_trace!("mdns: reset");
+        self.phase = Phase::Start;
+        self.count = 0;
+        self.pending = 0;
+        self.response = None;
+    }
+
+    /// Return the labels of one of the names of the responder.
+    fn labels(&self, name: Name) -> impl Iterator<Item = &[u8]> + '_ {
+        let (instance, service_type) = match name {
+            Name::Host => (Some(self.hostname.as_bytes()), None),
+            Name::Service(index) => (None, Some(self.services[index].service_type)),
+            Name::Instance(index) => {
+                let service = &self.services[index];
+                (
+                    Some(service.instance.as_bytes()),
+                    Some(service.service_type),
+                )
+            }
+        };
+        instance
+            .into_iter()
+            .chain(
+                service_type
+                    .into_iter()
+                    .flat_map(|t| t.split('.').map(str::as_bytes)),
+            )
+            .chain(iter::once(&b"local"[..]))
+    }
+
+    /// Return the names that must be unique on the link.
+    fn unique_names(&self) -> impl Iterator<Item = Name> {
+        iter::once(Name::Host).chain((0..self.services.len()).map(Name::Instance))
+    }
+
+    /// Return all the records of the responder.
+    fn answers(&self) -> impl Iterator<Item = Answer> {
+        [Answer::A, Answer::Aaaa].into_iter().chain(
+            (0..self.services.len())
+                .flat_map(|index| [Answer::Ptr(index), Answer::Srv(index), Answer::Txt(index)]),
+        )
+    }
+
+    pub(crate) fn accepts(&self, udp_repr: &UdpRepr) -> bool {
+        udp_repr.dst_port == MDNS_PORT
+    }
+
+    pub(crate) fn process(
+        &mut self,
+        _cx: &mut Context,
+        ip_repr: &IpRepr,
+        udp_repr: &UdpRepr,
+        payload: &[u8],
+    ) {
+        debug_assert!(self.accepts(udp_repr));
+
+        let packet = match Packet::new_checked(payload) {
+            Ok(packet) => packet,
+            Err(_) => {
+                net_trace!("mdns: packet malformed");
+                return;
+            }
+        };
+        if packet.opcode() != Opcode::Query {
+            net_trace!("mdns: unwanted opcode {:?}", packet.opcode());
+            return;
+        }
+
+        if packet.flags().contains(Flags::RESPONSE) {
+            self.process_response(&packet)
+        } else {
+            self.process_query(ip_repr, udp_repr, &packet)
+        }
+    }
+
+    fn process_response(&mut self, packet: &Packet<&[u8]>) {
+        if self.phase != Phase::Probing {
+            return;
+        }
+
+        let mut payload = packet.payload();
+        for _ in 0..packet.question_count() {
+            match Question::parse(payload) {
+                Ok((rest, _)) => payload = rest,
+                Err(_) => {
+                    net_trace!("mdns: question malformed");
+                    return;
+                }
+            }
+        }
+
+        let record_count = packet.answer_record_count() as usize
+            + packet.authority_record_count() as usize
+            + packet.additional_record_count() as usize;
+        for _ in 0..record_count {
+            let (rest, record) = match Record::parse(payload) {
+                Ok(x) => x,
+                Err(_) => {
+                    net_trace!("mdns: record malformed");
+                    return;
+                }
+            };
+            payload = rest;
+
+            for name in self.unique_names() {
+                if eq_name(packet.parse_name(record.name), self.labels(name)) {
+                    net_debug!("mdns: another host uses the name {:?}", name);
+                    self.phase = Phase::Conflict;
+                    self.pending = 0;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn process_query(&mut self, ip_repr: &IpRepr, udp_repr: &UdpRepr, packet: &Packet<&[u8]>) {
+        if !matches!(self.phase, Phase::Announcing | Phase::Running) {
+            return;
+        }
+
+        // Queries sent from another port come from simple resolvers, which expect
+        // a conventional unicast response, see RFC 6762 § 6.7.
+        let legacy_query = udp_repr.src_port != MDNS_PORT;
+        let mut unicast = legacy_query;
+        let mut legacy = None;
+        let mut answers: Vec<Answer, MAX_ANSWER_COUNT> = Vec::new();
+
+        let mut payload = packet.payload();
+        for _ in 0..packet.question_count() {
+            let (rest, question) = match Question::parse(payload) {
+                Ok(x) => x,
+                Err(_) => {
+                    net_trace!("mdns: question malformed");
+                    return;
+                }
+            };
+            let class = NetworkEndian::read_u16(&payload[question.name.len() + 2..]);
+            payload = rest;
+
+            for answer in self.answers() {
+                if (question.type_ == Type::Any || question.type_ == answer.type_())
+                    && !answers.contains(&answer)
+                    && eq_name(packet.parse_name(question.name), self.labels(answer.name()))
+                {
+                    if answers.push(answer).is_err() {
+                        net_trace!("mdns: too many answers, ignoring {:?}", answer);
+                    }
+                    unicast |= class & CLASS_MDNS_FLAG != 0;
+                    if legacy_query && legacy.is_none() {
+                        legacy = Some((packet.transaction_id(), answer.name(), question.type_));
+                    }
+                }
+            }
+        }
+        if answers.is_empty() {
+            return;
+        }
+
+        let (addr, port) = if unicast {
+            (ip_repr.src_addr(), udp_repr.src_port)
+        } else {
+            let version = ip_repr.src_addr().version();
+            match GROUPS.iter().find(|group| group.version() == version) {
+                Some(&group) => (group, MDNS_PORT),
+                None => return,
+            }
+        };
+
+        // Answers to several queries are sent at once if they go to the same destination.
+        match &mut self.response {
+            Some(response)
+                if response.addr == addr
+                    && response.port == port
+                    && response.legacy.is_none()
+                    && legacy.is_none() =>
+            {
+                answers.into_iter().for_each(|answer| response.push(answer))
+            }
+            Some(_) => net_trace!("mdns: response pending, ignoring query"),
+            None => {
+                let mut response = Response {
+                    addr,
+                    port,
+                    legacy,
+                    answers: Vec::new(),
+                    additionals: Vec::new(),
+                };
+                answers.into_iter().for_each(|answer| response.push(answer));
+                self.response = Some(response);
+            }
+        }
+    }
+
+    pub(crate) fn dispatch<F, E>(&mut self, cx: &mut Context, emit: F) -> Result<(), E>
+    where
+        F: FnOnce(&mut Context, (IpRepr, UdpRepr, &[u8])) -> Result<(), E>,
+    {
+        let mut buffer = [0u8; 512];
+
+        if let Some(response) = self.response.take() {
+            let len = match self.emit_response(cx, &response, &mut buffer) {
+                Some(len) => len,
+                None => return Ok(()),
+            };
+            let result = send(cx, response.addr, response.port, &buffer[..len], emit);
+            if result.is_err() {
+                self.response = Some(response);
+            }
+            return result;
+        }
+
+        let now = cx.now();
+        match self.phase {
+            Phase::Start => {
+                let delay = cx.rand().rand_u16() as u64 % PROBE_INTERVAL.total_millis();
+                self.phase = Phase::Probing;
+                self.count = 0;
+                self.at = now + Duration::from_millis(delay);
+                return Ok(());
+            }
+            Phase::Running | Phase::Conflict => return Ok(()),
+            _ if now < self.at => return Ok(()),
+            Phase::Probing if self.count == PROBE_COUNT => {
+                net_debug!("mdns: no conflict, announcing");
+                self.phase = Phase::Announcing;
+                self.count = 0;
+            }
+            Phase::Announcing if self.count == ANNOUNCE_COUNT => {
+                self.phase = Phase::Running;
+                return Ok(());
+            }
+            Phase::Probing | Phase::Announcing => {}
+        }
+
+        let interval = match self.phase {
+            Phase::Probing => PROBE_INTERVAL,
+            _ => ANNOUNCE_INTERVAL,
+        };
+        if self.pending == 0 {
+            for (index, group) in GROUPS.iter().enumerate() {
+                if cx.get_source_address(*group).is_some() {
+                    self.pending |= 1 << index;
+                }
+            }
+            if self.pending == 0 {
+                net_trace!("mdns: no address to send from");
+                self.at = now + interval;
+                return Ok(());
+            }
+        }
+
+        let index = self.pending.trailing_zeros() as usize;
+        let len = match self.phase {
+            Phase::Probing => self.emit_probe(cx, &mut buffer),
+            _ => self.emit_announcement(cx, &mut buffer),
+        };
+        send(cx, GROUPS[index], MDNS_PORT, &buffer[..len], emit)?;
+
+        self.pending &= !(1 << index);
+        if self.pending == 0 {
+            self.count += 1;
+            self.at = now + interval;
+        }
+        Ok(())
+    }
+
+    /// Emit a probe query into `buffer`, returning its length.
+    fn emit_probe(&self, cx: &Context, buffer: &mut [u8]) -> usize {
+        // Ask for unicast responses to the first probe, see RFC 6762 § 8.1.
+        let class = if self.count == 0 {
+            CLASS_IN | CLASS_MDNS_FLAG
+        } else {
+            CLASS_IN
+        };
+
+        let mut writer = Writer::new(buffer);
+        let mut question_count = 0;
+        for name in self.unique_names() {
+            let start = writer.len;
+            let result = writer
+                .name(self.labels(name))
+                .and_then(|_| writer.u16(Type::Any.into()))
+                .and_then(|_| writer.u16(class));
+            if result.is_none() {
+                writer.len = start;
+                break;
+            }
+            question_count += 1;
+        }
+
+        // The authority section holds the records the responder proposes to use.
+        let mut authority_count = 0;
+        for answer in self.answers() {
+            if !matches!(answer, Answer::Ptr(_)) {
+                authority_count += self.emit_answer(cx, &mut writer, answer, false, u32::MAX);
+            }
+        }
+
+        let len = writer.len;
+        let mut packet = Packet::new_unchecked(&mut buffer[..len]);
+        packet.set_opcode(Opcode::Query);
+        packet.set_question_count(question_count);
+        packet.set_authority_record_count(authority_count);
+        len
+    }
+
+    /// Emit an unsolicited response with all the records into `buffer`, returning
+    /// its length.
+    fn emit_announcement(&self, cx: &Context, buffer: &mut [u8]) -> usize {
+        let mut writer = Writer::new(buffer);
+        let mut answer_count = 0;
+        for answer in self.answers() {
+            answer_count += self.emit_answer(cx, &mut writer, answer, true, u32::MAX);
+        }
+
+        let len = writer.len;
+        let mut packet = Packet::new_unchecked(&mut buffer[..len]);
+        packet.set_flags(Flags::RESPONSE | Flags::AUTHORITATIVE);
+        packet.set_opcode(Opcode::Query);
+        packet.set_answer_record_count(answer_count);
+        len
+    }
+
+    /// Emit `response` into `buffer`, returning its length, or `None` if no record
+    /// answers the queries.
+    fn emit_response(&self, cx: &Context, response: &Response, buffer: &mut [u8]) -> Option<usize> {
+        let mut writer = Writer::new(buffer);
+        let (transaction_id, question_count, ttl) = match response.legacy {
+            Some((transaction_id, name, type_)) => {
+                writer.name(self.labels(name))?;
+                writer.u16(type_.into())?;
+                writer.u16(CLASS_IN)?;
+                (transaction_id, 1, LEGACY_TTL)
+            }
+            None => (0, 0, u32::MAX),
+        };
+        let cache_flush = response.legacy.is_none();
+
+        let mut answer_count = 0;
+        for &answer in response.answers.iter() {
+            answer_count += self.emit_answer(cx, &mut writer, answer, cache_flush, ttl);
+        }
+        if answer_count == 0 {
+            return None;
+        }
+        let mut additional_count = 0;
+        for &answer in response.additionals.iter() {
+            additional_count += self.emit_answer(cx, &mut writer, answer, cache_flush, ttl);
+        }
+
+        let len = writer.len;
+        let mut packet = Packet::new_unchecked(&mut buffer[..len]);
+        packet.set_transaction_id(transaction_id);
+        packet.set_flags(Flags::RESPONSE | Flags::AUTHORITATIVE);
+        packet.set_opcode(Opcode::Query);
+        packet.set_question_count(question_count);
+        packet.set_answer_record_count(answer_count);
+        packet.set_additional_record_count(additional_count);
+        Some(len)
+    }
+
+    /// Emit the records of `answer`, returning how many were emitted. The records are
+    /// left out if they do not fit.
+    fn emit_answer(
+        &self,
+        cx: &Context,
+        writer: &mut Writer,
+        answer: Answer,
+        cache_flush: bool,
+        max_ttl: u32,
+    ) -> u16 {
+        let start = writer.len;
+        match self.emit_records(cx, writer, answer, cache_flush, max_ttl) {
+            Some(count) => count,
+            None => {
+                net_trace!("mdns: no room for {:?}", answer);
+                writer.len = start;
+                0
+            }
+        }
+    }
+
+    fn emit_records(
+        &self,
+        cx: &Context,
+        writer: &mut Writer,
+        answer: Answer,
+        cache_flush: bool,
+        max_ttl: u32,
+    ) -> Option<u16> {
+        // Only the records of unique names may flush the caches of other hosts.
+        let class = match answer {
+            Answer::Ptr(_) => CLASS_IN,
+            _ if cache_flush => CLASS_IN | CLASS_MDNS_FLAG,
+            _ => CLASS_IN,
+        };
+        let ttl = match answer {
+            Answer::A | Answer::Aaaa | Answer::Srv(_) => HOST_TTL,
+            Answer::Ptr(_) | Answer::Txt(_) => SERVICE_TTL,
+        }
+        .min(max_ttl);
+        let labels = || self.labels(answer.name());
+
+        match answer {
+            Answer::A | Answer::Aaaa => {
+                let mut count = 0;
+                for cidr in cx.ip_addrs() {
+                    let mut data = [0; 16];
+                    let len = match (answer, cidr.address()) {
+                        #[cfg(feature = "proto-ipv4")]
+                        (Answer::A, IpAddress::Ipv4(addr)) => {
+                            data[..4].copy_from_slice(addr.as_bytes());
+                            4
+                        }
+                        #[cfg(feature = "proto-ipv6")]
+                        (Answer::Aaaa, IpAddress::Ipv6(addr)) => {
+                            data.copy_from_slice(addr.as_bytes());
+                            16
+                        }
+                        _ => continue,
+                    };
+                    writer.record(labels(), answer.type_(), class, ttl, |writer| {
+                        writer.put(&data[..len])
+                    })?;
+                    count += 1;
+                }
+                Some(count)
+            }
+            Answer::Ptr(index) => {
+                writer.record(labels(), Type::Ptr, class, ttl, |writer| {
+                    writer.name(self.labels(Name::Instance(index)))
+                })?;
+                Some(1)
+            }
+            Answer::Srv(index) => {
+                writer.record(labels(), Type::Srv, class, ttl, |writer| {
+                    // The priority and the weight.
+                    writer.u16(0)?;
+                    writer.u16(0)?;
+                    writer.u16(self.services[index].port)?;
+                    writer.name(self.labels(Name::Host))
+                })?;
+                Some(1)
+            }
+            Answer::Txt(index) => {
+                writer.record(labels(), Type::Txt, class, ttl, |writer| {
+                    let txt = self.services[index].txt;
+                    // A TXT record holds at least one string, see RFC 6763 § 6.1.
+                    if txt.is_empty() {
+                        return writer.put(&[0]);
+                    }
+                    for entry in txt {
+                        writer.put(&[entry.len() as u8])?;
+                        writer.put(entry.as_bytes())?;
+                    }
+                    Some(())
+                })?;
+                Some(1)
+            }
+        }
+    }
+
+    pub(crate) fn poll_at(&self, _cx: &Context) -> PollAt {
+        if self.response.is_some() {
+            return PollAt::Now;
+        }
+        match self.phase {
+            Phase::Start => PollAt::Now,
+            Phase::Probing | Phase::Announcing => PollAt::Time(self.at),
+            Phase::Running | Phase::Conflict => PollAt::Ingress,
+        }
+    }
+}
+
+fn send<F, E>(
+    cx: &mut Context,
+    dst_addr: IpAddress,
+    dst_port: u16,
+    payload: &[u8],
+    emit: F,
+) -> Result<(), E>
+where
+    F: FnOnce(&mut Context, (IpRepr, UdpRepr, &[u8])) -> Result<(), E>,
+{
+    let src_addr = match cx.get_source_address(dst_addr) {
+        Some(addr) => addr,
+        None => {
+            net_trace!("mdns: no source address for {}", dst_addr);
+            return Ok(());
+        }
+    };
+    let udp_repr = UdpRepr {
+        src_port: MDNS_PORT,
+        dst_port,
+    };
+    // Packets are sent with the maximum hop limit, so that receivers can tell they were
+    // sent on the link, see RFC 6762 § 11.
+    let ip_repr = IpRepr::new(
+        src_addr,
+        dst_addr,
+        IpProtocol::Udp,
+        udp_repr.header_len() + payload.len(),
+        255,
+    );
+
+    net_trace!(
+        "mdns: sending {} octets to {}:{}",
+        payload.len(),
+        dst_addr,
+        dst_port
+    );
+    emit(cx, (ip_repr, udp_repr, payload))
+}
+
+fn check_label(label: &str) {
+    if label.is_empty() || label.len() > MAX_LABEL_LEN {
+        panic!("invalid DNS label {:?}", label)
+    }
+}
+
+fn host_label(hostname: &str) -> String<MAX_LABEL_LEN> {
+    check_label(hostname);
+    let mut label = String::new();
+    label.push_str(hostname).unwrap();
+    label
+}
+
+/// Compare a name of a packet with a name of the responder, ignoring case.
+fn eq_name<'b, 'c>(
+    mut name: impl Iterator<Item = wire::Result<&'b [u8]>>,
+    mut labels: impl Iterator<Item = &'c [u8]>,
+) -> bool {
+    loop {
+        match (name.next(), labels.next()) {
+            (None, None) => return true,
+            (Some(Ok(a)), Some(b)) if a.eq_ignore_ascii_case(b) => {}
+            _ => return false,
+        }
+    }
+}
+
+/// A cursor writing the sections following the header of a DNS packet.
+///
+/// Names are written without compression.
+struct Writer<'b> {
+    buffer: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> Writer<'b> {
+    fn new(buffer: &'b mut [u8]) -> Writer<'b> {
+        Writer {
+            buffer,
+            len: HEADER_LEN,
+        }
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> Option<()> {
+        let end = self.len + bytes.len();
+        self.buffer.get_mut(self.len..end)?.copy_from_slice(bytes);
+        self.len = end;
+        Some(())
+    }
+
+    fn u16(&mut self, value: u16) -> Option<()> {
+        self.put(&value.to_be_bytes())
+    }
+
+    fn u32(&mut self, value: u32) -> Option<()> {
+        self.put(&value.to_be_bytes())
+    }
+
+    fn name<'c>(&mut self, labels: impl Iterator<Item = &'c [u8]>) -> Option<()> {
+        for label in labels {
+            self.put(&[label.len() as u8])?;
+            self.put(label)?;
+        }
+        self.put(&[0])
+    }
+
+    fn record<'c>(
+        &mut self,
+        name: impl Iterator<Item = &'c [u8]>,
+        type_: Type,
+        class: u16,
+        ttl: u32,
+        data: impl FnOnce(&mut Self) -> Option<()>,
+    ) -> Option<()> {
+        self.name(name)?;
+        self.u16(type_.into())?;
+        self.u16(class)?;
+        self.u32(ttl)?;
+        let data_len_at = self.len;
+        self.u16(0)?;
+        data(self)?;
+        let data_len = self.len - data_len_at - 2;
+        NetworkEndian::write_u16(&mut self.buffer[data_len_at..], data_len as u16);
+        Some(())
+    }
+}
+
+#[cfg(feature = "proto-ipv4")] // tests assume ipv4
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wire::dns::{RecordData, Repr};
+    use std::vec::Vec;
+
+    const NAME: &[u8] = b"\x06device\x05local\x00";
+    const SERVICE_NAME: &[u8] = b"\x05_http\x04_tcp\x05local\x00";
+    const REMOTE_ADDR: IpAddress = IpAddress::Ipv4(Ipv4Address([192, 168, 1, 2]));
+
+    static SERVICES: [Service; 1] = [Service {
+        instance: "Web",
+        service_type: "_http._tcp",
+        port: 80,
+        txt: &["path=/"],
+    }];
+
+    fn dispatch(socket: &mut Socket, cx: &mut Context) -> Vec<(IpRepr, Vec<u8>)> {
+        let mut packets = Vec::new();
+        loop {
+            let mut sent = None;
+            socket
+                .dispatch(cx, |_, (ip_repr, udp_repr, payload)| {
+                    assert_eq!(udp_repr.src_port, MDNS_PORT);
+                    sent = Some((ip_repr, payload.to_vec()));
+                    Ok::<_, ()>(())
+                })
+                .unwrap();
+            match sent {
+                Some(packet) => packets.push(packet),
+                None => return packets,
+            }
+        }
+    }
+
+    fn records(bytes: &[u8]) -> Vec<(Type, u32)> {
+        let packet = Packet::new_checked(bytes).unwrap();
+        let mut payload = packet.payload();
+        for _ in 0..packet.question_count() {
+            payload = Question::parse(payload).unwrap().0;
+        }
+        let mut records = Vec::new();
+        while !payload.is_empty() {
+            let (rest, record) = Record::parse(payload).unwrap();
+            let type_ = match record.data {
+                RecordData::A(_) => Type::A,
+                #[cfg(feature = "proto-ipv6")]
+                RecordData::Aaaa(_) => Type::Aaaa,
+                RecordData::Cname(_) => Type::Cname,
+                RecordData::Other(type_, _) => type_,
+            };
+            records.push((type_, record.ttl));
+            payload = rest;
+        }
+        records
+    }
+
+    fn query(
+        socket: &mut Socket,
+        cx: &mut Context,
+        src_port: u16,
+        name: &[u8],
+        type_: Type,
+    ) -> Vec<(IpRepr, Vec<u8>)> {
+        let repr = Repr {
+            transaction_id: 0x1234,
+            opcode: Opcode::Query,
+            flags: Flags::empty(),
+            question: Question { name, type_ },
+        };
+        let mut bytes = vec![0; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes));
+
+        let udp_repr = UdpRepr {
+            src_port,
+            dst_port: MDNS_PORT,
+        };
+        let ip_repr = IpRepr::new(
+            REMOTE_ADDR,
+            MDNS_IPV4_GROUP.into(),
+            IpProtocol::Udp,
+            udp_repr.header_len() + bytes.len(),
+            255,
+        );
+        socket.process(cx, &ip_repr, &udp_repr, &bytes);
+        dispatch(socket, cx)
+    }
+
+    fn running_socket(cx: &mut Context) -> Socket<'static> {
+        let mut socket = Socket::new("device", &SERVICES[..]);
+        for ms in [0, 250, 500, 750, 1000, 2000, 3000] {
+            cx.set_now(Instant::from_millis(ms));
+            dispatch(&mut socket, cx);
+        }
+        assert_eq!(socket.state(), State::Running);
+        socket
+    }
+
+    #[test]
+    fn test_probe_and_announce() {
+        let mut cx = Context::mock();
+        let mut socket = Socket::new("device", &SERVICES[..]);
+        assert_eq!(socket.poll_at(&cx), PollAt::Now);
+        assert!(dispatch(&mut socket, &mut cx).is_empty());
+        assert_eq!(socket.state(), State::Probing);
+
+        for ms in [250, 500, 750] {
+            cx.set_now(Instant::from_millis(ms));
+            let probes = dispatch(&mut socket, &mut cx);
+            assert_eq!(probes.len(), GROUPS.len());
+            let packet = Packet::new_checked(&probes[0].1[..]).unwrap();
+            assert_eq!(probes[0].0.dst_addr(), MDNS_IPV4_GROUP.into());
+            assert!(!packet.flags().contains(Flags::RESPONSE));
+            assert_eq!(packet.question_count(), 2);
+            assert_eq!(packet.authority_record_count() as usize, GROUPS.len() + 2);
+            assert_eq!(
+                socket.poll_at(&cx),
+                PollAt::Time(Instant::from_millis(ms + 250))
+            );
+        }
+
+        for ms in [1000, 2000] {
+            cx.set_now(Instant::from_millis(ms));
+            let announcements = dispatch(&mut socket, &mut cx);
+            assert_eq!(socket.state(), State::Announcing);
+            assert_eq!(announcements.len(), GROUPS.len());
+            let packet = Packet::new_checked(&announcements[0].1[..]).unwrap();
+            assert!(packet.flags().contains(Flags::RESPONSE));
+            assert_eq!(packet.answer_record_count() as usize, GROUPS.len() + 3);
+        }
+
+        cx.set_now(Instant::from_millis(3000));
+        assert!(dispatch(&mut socket, &mut cx).is_empty());
+        assert_eq!(socket.state(), State::Running);
+        assert_eq!(socket.poll_at(&cx), PollAt::Ingress);
+    }
+
+    #[test]
+    fn test_probe_conflict() {
+        let mut cx = Context::mock();
+        let mut socket = Socket::new("device", &SERVICES[..]);
+        dispatch(&mut socket, &mut cx);
+        cx.set_now(Instant::from_millis(250));
+        dispatch(&mut socket, &mut cx);
+
+        // A response with an A record for DEVICE.local, from another host.
+        let mut bytes = vec![
+            0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+        ];
+        bytes.extend_from_slice(b"\x06DEVICE\x05local\x00");
+        bytes.extend_from_slice(&[0x00, 0x01, 0x80, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x04]);
+        bytes.extend_from_slice(&[192, 168, 1, 2]);
+        let udp_repr = UdpRepr {
+            src_port: MDNS_PORT,
+            dst_port: MDNS_PORT,
+        };
+        let ip_repr = IpRepr::new(
+            REMOTE_ADDR,
+            MDNS_IPV4_GROUP.into(),
+            IpProtocol::Udp,
+            udp_repr.header_len() + bytes.len(),
+            255,
+        );
+        socket.process(&mut cx, &ip_repr, &udp_repr, &bytes);
+        assert_eq!(socket.state(), State::Conflict);
+        cx.set_now(Instant::from_millis(500));
+        assert!(dispatch(&mut socket, &mut cx).is_empty());
+
+        socket.set_hostname("device-2");
+        assert_eq!(socket.state(), State::Probing);
+    }
+
+    #[test]
+    fn test_answer_query() {
+        let mut cx = Context::mock();
+        let mut socket = running_socket(&mut cx);
+
+        let responses = query(&mut socket, &mut cx, MDNS_PORT, NAME, Type::A);
+        assert_eq!(responses.len(), 1);
+        let (ip_repr, bytes) = &responses[0];
+        assert_eq!(ip_repr.dst_addr(), MDNS_IPV4_GROUP.into());
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(packet.transaction_id(), 0);
+        assert_eq!(packet.question_count(), 0);
+        assert_eq!(packet.answer_record_count(), 1);
+        assert_eq!(records(bytes), vec![(Type::A, HOST_TTL)]);
+
+        // Names are compared ignoring case, and queries for other names are ignored.
+        let responses = query(
+            &mut socket,
+            &mut cx,
+            MDNS_PORT,
+            b"\x06DEVICE\x05LOCAL\x00",
+            Type::A,
+        );
+        assert_eq!(responses.len(), 1);
+        let responses = query(
+            &mut socket,
+            &mut cx,
+            MDNS_PORT,
+            b"\x05other\x05local\x00",
+            Type::A,
+        );
+        assert!(responses.is_empty());
+    }
+
+    #[test]
+    fn test_answer_legacy_query() {
+        let mut cx = Context::mock();
+        let mut socket = running_socket(&mut cx);
+
+        let responses = query(&mut socket, &mut cx, 49500, SERVICE_NAME, Type::Ptr);
+        assert_eq!(responses.len(), 1);
+        let (ip_repr, bytes) = &responses[0];
+        assert_eq!(ip_repr.dst_addr(), REMOTE_ADDR);
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(packet.transaction_id(), 0x1234);
+        assert_eq!(packet.question_count(), 1);
+        assert_eq!(packet.answer_record_count(), 1);
+        assert_eq!(
+            records(bytes)[..3],
+            [
+                (Type::Ptr, LEGACY_TTL),
+                (Type::Srv, LEGACY_TTL),
+                (Type::Txt, LEGACY_TTL)
+            ]
+        );
+    }
+}
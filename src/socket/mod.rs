@@ -11,25 +11,40 @@ The interface implemented by this module uses explicit buffering: you decide on
 size for a buffer, allocate it, and let the networking stack use it.
 */
 
+use bitflags::bitflags;
+
 use crate::iface::Context;
+use crate::storage::MemoryUsage;
 use crate::time::Instant;
 
 #[cfg(feature = "socket-dhcpv4")]
 pub mod dhcpv4;
 #[cfg(feature = "socket-dns")]
 pub mod dns;
+#[cfg(feature = "socket-ethernet")]
+pub mod ethernet;
+#[cfg(feature = "futures")]
+pub mod framed;
 #[cfg(feature = "socket-icmp")]
 pub mod icmp;
+#[cfg(feature = "socket-mdns")]
+pub mod mdns;
 #[cfg(feature = "socket-raw")]
 pub mod raw;
+#[cfg(feature = "socket-sntp")]
+pub mod sntp;
 #[cfg(feature = "socket-tcp")]
 pub mod tcp;
 #[cfg(feature = "socket-udp")]
 pub mod udp;
 
+#[cfg(feature = "stats")]
+mod stats;
 #[cfg(feature = "async")]
 mod waker;
 
+#[cfg(feature = "stats")]
+pub use self::stats::Stats;
 #[cfg(feature = "async")]
 pub(crate) use self::waker::WakerRegistration;
 
@@ -45,9 +60,26 @@ pub(crate) enum PollAt {
     Ingress,
 }
 
+bitflags! {
+    /// What a socket is ready for, see [Socket::readiness] and
+    /// [SocketSet::readiness](crate::iface::SocketSet::readiness).
+    #[derive(Default)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct Readiness: u8 {
+        /// Data can be received from the socket.
+        const READABLE = 0b001;
+        /// Data can be sent with the socket.
+        const WRITABLE = 0b010;
+        /// The socket is closed, or, for TCP sockets, the remote endpoint closed or
+        /// reset the connection.
+        const CLOSED   = 0b100;
+    }
+}
+
 /// A network socket.
 ///
-/// This enumeration abstracts the various types of sockets based on the IP protocol.
+/// This enumeration abstracts the various types of sockets based on the IP protocol,
+/// and the raw Ethernet sockets.
 /// To downcast a `Socket` value to a concrete socket, use the [AnySocket] trait,
 /// e.g. to get `udp::Socket`, call `udp::Socket::downcast(socket)`.
 ///
@@ -56,6 +88,8 @@ pub(crate) enum PollAt {
 /// [AnySocket]: trait.AnySocket.html
 /// [SocketSet::get]: struct.SocketSet.html#method.get
 #[derive(Debug)]
+// The history of TCP sockets is kept inline, like their other state.
+#[cfg_attr(feature = "socket-tcp-history", allow(clippy::large_enum_variant))]
 pub enum Socket<'a> {
     #[cfg(feature = "socket-raw")]
     Raw(raw::Socket<'a>),
@@ -69,6 +103,12 @@ pub enum Socket<'a> {
     Dhcpv4(dhcpv4::Socket<'a>),
     #[cfg(feature = "socket-dns")]
     Dns(dns::Socket<'a>),
+    #[cfg(feature = "socket-mdns")]
+    Mdns(mdns::Socket<'a>),
+    #[cfg(feature = "socket-sntp")]
+    Sntp(sntp::Socket),
+    #[cfg(feature = "socket-ethernet")]
+    Ethernet(ethernet::Socket<'a>),
 }
 
 impl<'a> Socket<'a> {
@@ -86,6 +126,79 @@ impl<'a> Socket<'a> {
             Socket::Dhcpv4(s) => s.poll_at(cx),
             #[cfg(feature = "socket-dns")]
             Socket::Dns(s) => s.poll_at(cx),
+            #[cfg(feature = "socket-mdns")]
+            Socket::Mdns(s) => s.poll_at(cx),
+            #[cfg(feature = "socket-sntp")]
+            Socket::Sntp(s) => s.poll_at(cx),
+            #[cfg(feature = "socket-ethernet")]
+            Socket::Ethernet(s) => s.poll_at(cx),
+        }
+    }
+
+    /// Return the number of bytes of receive and transmit buffer storage used and
+    /// committed by the socket.
+    ///
+    /// DHCP, DNS, mDNS and SNTP sockets do not have packet buffers, and always report zero.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        match self {
+            #[cfg(feature = "socket-raw")]
+            Socket::Raw(s) => s.recv_memory_usage() + s.send_memory_usage(),
+            #[cfg(feature = "socket-icmp")]
+            Socket::Icmp(s) => s.recv_memory_usage() + s.send_memory_usage(),
+            #[cfg(feature = "socket-udp")]
+            Socket::Udp(s) => s.recv_memory_usage() + s.send_memory_usage(),
+            #[cfg(feature = "socket-tcp")]
+            Socket::Tcp(s) => s.recv_memory_usage() + s.send_memory_usage(),
+            #[cfg(feature = "socket-dhcpv4")]
+            Socket::Dhcpv4(_) => MemoryUsage::default(),
+            #[cfg(feature = "socket-dns")]
+            Socket::Dns(_) => MemoryUsage::default(),
+            #[cfg(feature = "socket-mdns")]
+            Socket::Mdns(_) => MemoryUsage::default(),
+            #[cfg(feature = "socket-sntp")]
+            Socket::Sntp(_) => MemoryUsage::default(),
+            #[cfg(feature = "socket-ethernet")]
+            Socket::Ethernet(s) => s.recv_memory_usage() + s.send_memory_usage(),
+        }
+    }
+
+    /// Return what the socket is ready for.
+    ///
+    /// DHCP, DNS, mDNS and SNTP sockets are never reported ready; raw and Ethernet
+    /// sockets are never closed.
+    pub fn readiness(&self) -> Readiness {
+        #[allow(unused)] // unused depending on which sockets are enabled
+        fn flags(can_recv: bool, can_send: bool, closed: bool) -> Readiness {
+            let mut readiness = Readiness::empty();
+            readiness.set(Readiness::READABLE, can_recv);
+            readiness.set(Readiness::WRITABLE, can_send);
+            readiness.set(Readiness::CLOSED, closed);
+            readiness
+        }
+
+        match self {
+            #[cfg(feature = "socket-raw")]
+            Socket::Raw(s) => flags(s.can_recv(), s.can_send(), false),
+            #[cfg(feature = "socket-icmp")]
+            Socket::Icmp(s) => flags(s.can_recv(), s.can_send(), !s.is_open()),
+            #[cfg(feature = "socket-udp")]
+            Socket::Udp(s) => flags(s.can_recv(), s.can_send(), !s.is_open()),
+            #[cfg(feature = "socket-tcp")]
+            Socket::Tcp(s) => {
+                let closed = matches!(
+                    s.state(),
+                    tcp::State::Closed
+                        | tcp::State::CloseWait
+                        | tcp::State::Closing
+                        | tcp::State::LastAck
+                        | tcp::State::TimeWait
+                );
+                flags(s.can_recv(), s.can_send(), closed)
+            }
+            #[cfg(feature = "socket-ethernet")]
+            Socket::Ethernet(s) => flags(s.can_recv(), s.can_send(), false),
+            #[allow(unreachable_patterns)]
+            _ => Readiness::empty(),
         }
     }
 }
@@ -139,3 +252,9 @@ from_socket!(tcp::Socket<'a>, Tcp);
 from_socket!(dhcpv4::Socket<'a>, Dhcpv4);
 #[cfg(feature = "socket-dns")]
 from_socket!(dns::Socket<'a>, Dns);
+#[cfg(feature = "socket-mdns")]
+from_socket!(mdns::Socket<'a>, Mdns);
+#[cfg(feature = "socket-sntp")]
+from_socket!(sntp::Socket, Sntp);
+#[cfg(feature = "socket-ethernet")]
+from_socket!(ethernet::Socket<'a>, Ethernet);
@@ -1,4 +1,5 @@
 use core::cmp::min;
+use core::fmt;
 #[cfg(feature = "async")]
 use core::task::Waker;
 
@@ -7,8 +8,8 @@ use crate::socket::PollAt;
 #[cfg(feature = "async")]
 use crate::socket::WakerRegistration;
 
-use crate::storage::Empty;
-use crate::wire::{IpProtocol, IpRepr, IpVersion};
+use crate::storage::{Empty, MemoryUsage};
+use crate::wire::{IpAddress, IpProtocol, IpRepr, IpVersion};
 #[cfg(feature = "proto-ipv4")]
 use crate::wire::{Ipv4Packet, Ipv4Repr};
 #[cfg(feature = "proto-ipv6")]
@@ -22,6 +23,27 @@ pub enum BindError {
     Unaddressable,
 }
 
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BindError::InvalidState => write!(f, "invalid state"),
+            BindError::Unaddressable => write!(f, "unaddressable destination"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BindError {}
+
+impl From<BindError> for crate::Error {
+    fn from(err: BindError) -> Self {
+        match err {
+            BindError::InvalidState => crate::Error::Illegal(crate::Layer::Network),
+            BindError::Unaddressable => crate::Error::Unaddressable(crate::Layer::Network),
+        }
+    }
+}
+
 /// Error returned by [`Socket::send`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -29,6 +51,25 @@ pub enum SendError {
     BufferFull,
 }
 
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendError::BufferFull => write!(f, "buffer full"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SendError {}
+
+impl From<SendError> for crate::Error {
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::BufferFull => crate::Error::Exhausted(crate::Layer::Network),
+        }
+    }
+}
+
 /// Error returned by [`Socket::recv`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -36,6 +77,25 @@ pub enum RecvError {
     Exhausted,
 }
 
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecvError::Exhausted => write!(f, "buffer empty"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RecvError {}
+
+impl From<RecvError> for crate::Error {
+    fn from(err: RecvError) -> Self {
+        match err {
+            RecvError::Exhausted => crate::Error::Exhausted(crate::Layer::Network),
+        }
+    }
+}
+
 /// A UDP packet metadata.
 pub type PacketMetadata = crate::storage::PacketMetadata<()>;
 
@@ -44,12 +104,13 @@ pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, ()>;
 
 /// A raw IP socket.
 ///
-/// A raw socket is bound to a specific IP protocol, and owns
-/// transmit and receive packet buffers.
+/// A raw socket is bound to a specific IP protocol, and optionally to a local address,
+/// and owns transmit and receive packet buffers.
 #[derive(Debug)]
 pub struct Socket<'a> {
     ip_version: IpVersion,
     ip_protocol: IpProtocol,
+    local_address: Option<IpAddress>,
     rx_buffer: PacketBuffer<'a>,
     tx_buffer: PacketBuffer<'a>,
     #[cfg(feature = "async")]
@@ -70,6 +131,7 @@ impl<'a> Socket<'a> {
         Socket {
             ip_version,
             ip_protocol,
+            local_address: None,
             rx_buffer,
             tx_buffer,
             #[cfg(feature = "async")]
@@ -126,6 +188,30 @@ impl<'a> Socket<'a> {
         self.ip_protocol
     }
 
+    /// Return the local address the socket is bound to, if any.
+    #[inline]
+    pub fn local_address(&self) -> Option<IpAddress> {
+        self.local_address
+    }
+
+    /// Bind the socket to the given local address.
+    ///
+    /// Once bound, the socket only receives packets sent to `addr`.
+    /// This function returns `Err(BindError::InvalidState)` if the socket is already bound,
+    /// and `Err(BindError::Unaddressable)` if `addr` is unspecified or of another
+    /// IP version than the socket.
+    pub fn bind(&mut self, addr: IpAddress) -> Result<(), BindError> {
+        if addr.is_unspecified() || addr.version() != self.ip_version {
+            return Err(BindError::Unaddressable);
+        }
+        if self.local_address.is_some() {
+            return Err(BindError::InvalidState);
+        }
+
+        self.local_address = Some(addr);
+        Ok(())
+    }
+
     /// Check whether the transmit buffer is full.
     #[inline]
     pub fn can_send(&self) -> bool {
@@ -162,6 +248,18 @@ impl<'a> Socket<'a> {
         self.tx_buffer.payload_capacity()
     }
 
+    /// Return the number of bytes of receive buffer storage used and committed by the socket.
+    #[inline]
+    pub fn recv_memory_usage(&self) -> MemoryUsage {
+        self.rx_buffer.memory_usage()
+    }
+
+    /// Return the number of bytes of transmit buffer storage used and committed by the socket.
+    #[inline]
+    pub fn send_memory_usage(&self) -> MemoryUsage {
+        self.tx_buffer.memory_usage()
+    }
+
     /// Enqueue a packet to send, and return a pointer to its payload.
     ///
     /// This function returns `Err(Error::Exhausted)` if the transmit buffer is full,
@@ -254,6 +352,11 @@ impl<'a> Socket<'a> {
         if ip_repr.next_header() != self.ip_protocol {
             return false;
         }
+        if let Some(local_address) = self.local_address {
+            if ip_repr.dst_addr() != local_address {
+                return false;
+            }
+        }
 
         true
     }
@@ -387,7 +490,7 @@ mod test {
     use crate::wire::{Ipv4Address, Ipv4Repr};
     #[cfg(feature = "proto-ipv6")]
     use crate::wire::{Ipv6Address, Ipv6Repr};
-    use crate::Error;
+    use crate::{Error, Layer};
 
     fn buffer(packets: usize) -> PacketBuffer<'static> {
         PacketBuffer::new(vec![PacketMetadata::EMPTY; packets], vec![0; 48 * packets])
@@ -416,6 +519,7 @@ mod test {
             next_header: IpProtocol::Unknown(IP_PROTO),
             payload_len: 4,
             hop_limit: 64,
+            traffic_class: 0,
         });
         pub const PACKET_BYTES: [u8; 24] = [
             0x45, 0x00, 0x00, 0x18, 0x00, 0x00, 0x40, 0x00, 0x40, 0x3f, 0x00, 0x00, 0x0a, 0x00,
@@ -453,6 +557,7 @@ mod test {
             next_header: IpProtocol::Unknown(IP_PROTO),
             payload_len: 4,
             hop_limit: 64,
+            traffic_class: 0,
         });
 
         pub const PACKET_BYTES: [u8; 44] = [
@@ -495,9 +600,9 @@ mod test {
                         socket.dispatch(&mut cx, |_, (ip_repr, ip_payload)| {
                             assert_eq!(ip_repr, $hdr);
                             assert_eq!(ip_payload, &$payload);
-                            Err(Error::Unaddressable)
+                            Err(Error::Unaddressable(Layer::Link))
                         }),
-                        Err(Error::Unaddressable)
+                        Err(Error::Unaddressable(Layer::Link))
                     );
                     assert!(!socket.can_send());
 
@@ -664,6 +769,36 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_bind() {
+        let mut socket = ipv4_locals::socket(buffer(1), buffer(0));
+        assert_eq!(
+            socket.bind(Ipv4Address::UNSPECIFIED.into()),
+            Err(BindError::Unaddressable)
+        );
+        #[cfg(feature = "proto-ipv6")]
+        assert_eq!(
+            socket.bind(Ipv6Address::LOOPBACK.into()),
+            Err(BindError::Unaddressable)
+        );
+
+        assert_eq!(socket.bind(Ipv4Address([10, 0, 0, 3]).into()), Ok(()));
+        assert_eq!(
+            socket.local_address(),
+            Some(Ipv4Address([10, 0, 0, 3]).into())
+        );
+        assert!(!socket.accepts(&ipv4_locals::HEADER_REPR));
+        assert_eq!(
+            socket.bind(Ipv4Address([10, 0, 0, 2]).into()),
+            Err(BindError::InvalidState)
+        );
+
+        let mut socket = ipv4_locals::socket(buffer(1), buffer(0));
+        assert_eq!(socket.bind(Ipv4Address([10, 0, 0, 2]).into()), Ok(()));
+        assert!(socket.accepts(&ipv4_locals::HEADER_REPR));
+    }
+
     #[test]
     fn test_doesnt_accept_wrong_proto() {
         #[cfg(feature = "proto-ipv4")]
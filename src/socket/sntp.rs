@@ -0,0 +1,541 @@
+#[cfg(feature = "async")]
+use core::task::Waker;
+
+use crate::socket::{Context, PollAt};
+use crate::time::{Duration, Instant};
+use crate::wire::{
+    IpAddress, IpProtocol, IpRepr, NtpTimestamp, SntpLeapIndicator, SntpMode, SntpPacket, SntpRepr,
+    UdpRepr, SNTP_HEADER_LEN, SNTP_SERVER_PORT,
+};
+
+#[cfg(feature = "async")]
+use super::WakerRegistration;
+
+const NTP_VERSION: u8 = 4;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1024);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(1);
+const INITIAL_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_TIMEOUT: Duration = Duration::from_secs(64);
+
+/// The result of an exchange with the NTP server.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Synchronization {
+    /// The offset of the server clock from the local clock, in microseconds.
+    ///
+    /// The local clock is the one of the timestamps passed to `Interface::poll`,
+    /// and the server clock counts the time elapsed since the Unix epoch.
+    pub offset: i64,
+    /// The round-trip delay of the exchange, excluding the processing time of the server.
+    pub delay: Duration,
+    /// The stratum of the server, i.e. its distance from a reference clock.
+    pub stratum: u8,
+}
+
+impl Synchronization {
+    /// Return the time elapsed since the Unix epoch at `timestamp`, in microseconds.
+    pub fn unix_micros(&self, timestamp: Instant) -> i64 {
+        timestamp.total_micros() + self.offset
+    }
+}
+
+/// Return value for the `sntp::Socket::poll` function
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// The server answered a request.
+    Synchronized(Synchronization),
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum State {
+    /// Waiting to send the next request.
+    Idle { send_at: Instant },
+    /// Waiting for the response to a request.
+    Requesting {
+        /// The UDP port the request was sent from.
+        port: u16,
+        /// The transmit timestamp of the request, which the server echoes back.
+        xmit_timestamp: NtpTimestamp,
+        sent_at: Instant,
+        /// When to give up waiting, and send another request.
+        retry_at: Instant,
+        timeout: Duration,
+    },
+}
+
+/// Simple Network Time Protocol client socket.
+///
+/// The socket periodically asks an NTP server for the time, as described in RFC 4330.
+/// You must query the result with `.poll()` after every call to `Interface::poll()`.
+#[derive(Debug)]
+pub struct Socket {
+    server: IpAddress,
+    state: State,
+    /// How long to wait between successful exchanges.
+    poll_interval: Duration,
+    /// The longest round-trip delay of an exchange to be trusted.
+    max_delay: Duration,
+    /// The result of the last exchange, cleared by the `poll` function.
+    event: Option<Event>,
+
+    /// Waker registration
+    #[cfg(feature = "async")]
+    waker: WakerRegistration,
+}
+
+impl Socket {
+    /// Create an SNTP socket, asking `server` for the time.
+    pub fn new(server: IpAddress) -> Socket {
+        Socket {
+            server,
+            state: State::Idle {
+                send_at: Instant::ZERO,
+            },
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            max_delay: DEFAULT_MAX_DELAY,
+            event: None,
+            #[cfg(feature = "async")]
+            waker: WakerRegistration::new(),
+        }
+    }
+
+    /// Return the address of the NTP server.
+    pub fn server(&self) -> IpAddress {
+        self.server
+    }
+
+    /// Set the address of the NTP server, and ask it for the time right away.
+    pub fn set_server(&mut self, server: IpAddress) {
+        self.server = server;
+        self.reset();
+    }
+
+    /// Return the interval between successful exchanges.
+    ///
+    /// See also the [set_poll_interval](#method.set_poll_interval) method.
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Set the interval between successful exchanges.
+    ///
+    /// The default interval is 1024 seconds. Public servers should not be asked for
+    /// the time more often than every 15 seconds, see RFC 4330 § 10.
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Return the longest round-trip delay of a trusted exchange.
+    ///
+    /// See also the [set_max_delay](#method.set_max_delay) method.
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    /// Set the longest round-trip delay of a trusted exchange.
+    ///
+    /// The error of the offset is at most half of the round-trip delay, so responses
+    /// taking longer to arrive are ignored. The default delay is one second.
+    pub fn set_max_delay(&mut self, delay: Duration) {
+        self.max_delay = delay;
+    }
+
+    /// Reset state, and ask the server for the time right away.
+    pub fn reset(&mut self) {
+        net_trace!("SNTP reset");
+        self.state = State::Idle {
+            send_at: Instant::ZERO,
+        };
+    }
+
+    /// Query the socket for the result of the last exchange with the server.
+    ///
+    /// Each result is returned once.
+    pub fn poll(&mut self) -> Option<Event> {
+        self.event.take()
+    }
+
+    /// Register a waker.
+    ///
+    /// The waker is woken when the server answers a request, which makes `poll`
+    /// return an event.
+    ///
+    /// Notes:
+    ///
+    /// - Only one waker can be registered at a time. If another waker was previously registered,
+    ///   it is overwritten and will no longer be woken.
+    /// - The Waker is woken only once. Once woken, you must register it again to receive more wakes.
+    #[cfg(feature = "async")]
+    pub fn register_waker(&mut self, waker: &Waker) {
+        self.waker.register(waker)
+    }
+
+    pub(crate) fn accepts(&self, ip_repr: &IpRepr, udp_repr: &UdpRepr) -> bool {
+        match self.state {
+            State::Requesting { port, .. } => {
+                udp_repr.src_port == SNTP_SERVER_PORT
+                    && udp_repr.dst_port == port
+                    && ip_repr.src_addr() == self.server
+            }
+            State::Idle { .. } => false,
+        }
+    }
+
+    pub(crate) fn process(
+        &mut self,
+        cx: &mut Context,
+        ip_repr: &IpRepr,
+        udp_repr: &UdpRepr,
+        payload: &[u8],
+    ) {
+        debug_assert!(self.accepts(ip_repr, udp_repr));
+
+        let (xmit_timestamp, sent_at) = match self.state {
+            State::Requesting {
+                xmit_timestamp,
+                sent_at,
+                ..
+            } => (xmit_timestamp, sent_at),
+            State::Idle { .. } => return,
+        };
+
+        let repr = match SntpPacket::new_checked(payload).and_then(|p| SntpRepr::parse(&p)) {
+            Ok(repr) => repr,
+            Err(e) => {
+                net_debug!("SNTP invalid pkt from {}: {:?}", ip_repr.src_addr(), e);
+                return;
+            }
+        };
+        net_debug!("SNTP recv from {}: {}", ip_repr.src_addr(), repr);
+
+        if repr.orig_timestamp != xmit_timestamp {
+            net_debug!("SNTP ignoring response to another request");
+            return;
+        }
+        if repr.mode != SntpMode::Server {
+            net_debug!("SNTP ignoring response with mode {:?}", repr.mode);
+            return;
+        }
+        if repr.stratum == 0 {
+            // A "kiss-o'-death", with which the server asks to be left alone, see RFC 4330 § 8.
+            net_debug!("SNTP kiss-o'-death: {:?}", repr.ref_id);
+            self.state = State::Idle {
+                send_at: cx.now() + self.poll_interval,
+            };
+            return;
+        }
+        if repr.stratum > 15
+            || repr.leap_indicator == SntpLeapIndicator::Unsynchronized
+            || !(3..=4).contains(&repr.version)
+            || repr.xmit_timestamp == NtpTimestamp::ZERO
+        {
+            net_debug!("SNTP ignoring response from unsynchronized server");
+            return;
+        }
+
+        // The timestamps of the request and the response are taken with the local clock,
+        // the others with the clock of the server, see RFC 4330 § 5.
+        let t1 = sent_at.total_micros();
+        let t2 = repr.recv_timestamp.unix_micros();
+        let t3 = repr.xmit_timestamp.unix_micros();
+        let t4 = cx.now().total_micros();
+
+        let delay = (t4 - t1) - (t3 - t2);
+        if delay < 0 || delay as u64 > self.max_delay.total_micros() {
+            net_debug!("SNTP ignoring response with round-trip delay {}us", delay);
+            return;
+        }
+
+        self.event = Some(Event::Synchronized(Synchronization {
+            offset: ((t2 - t1) + (t3 - t4)) / 2,
+            delay: Duration::from_micros(delay as u64),
+            stratum: repr.stratum,
+        }));
+        #[cfg(feature = "async")]
+        self.waker.wake();
+
+        self.state = State::Idle {
+            send_at: cx.now() + self.poll_interval,
+        };
+    }
+
+    pub(crate) fn dispatch<F, E>(&mut self, cx: &mut Context, emit: F) -> Result<(), E>
+    where
+        F: FnOnce(&mut Context, (IpRepr, UdpRepr, &[u8])) -> Result<(), E>,
+    {
+        let (send_at, timeout) = match self.state {
+            State::Idle { send_at } => (send_at, INITIAL_TIMEOUT),
+            State::Requesting {
+                retry_at, timeout, ..
+            } => (retry_at, (timeout * 2).min(MAX_TIMEOUT)),
+        };
+        if cx.now() < send_at {
+            return Ok(());
+        }
+
+        let src_addr = match cx.get_source_address(self.server) {
+            Some(src_addr) => src_addr,
+            None => {
+                net_trace!("SNTP no source address for {}", self.server);
+                self.state = State::Idle {
+                    send_at: cx.now() + INITIAL_TIMEOUT,
+                };
+                return Ok(());
+            }
+        };
+
+        // The transmit timestamp does not need to be the current time, since the server
+        // only echoes it back. A random one makes forged responses harder to pass off.
+        let xmit_timestamp = NtpTimestamp {
+            seconds: cx.rand().rand_u32(),
+            fraction: cx.rand().rand_u32(),
+        };
+        let repr = SntpRepr {
+            leap_indicator: SntpLeapIndicator::NoWarning,
+            version: NTP_VERSION,
+            mode: SntpMode::Client,
+            stratum: 0,
+            poll: 0,
+            precision: 0,
+            root_delay: 0,
+            root_dispersion: 0,
+            ref_id: [0; 4],
+            ref_timestamp: NtpTimestamp::ZERO,
+            orig_timestamp: NtpTimestamp::ZERO,
+            recv_timestamp: NtpTimestamp::ZERO,
+            xmit_timestamp,
+        };
+        let mut payload = [0; SNTP_HEADER_LEN];
+        repr.emit(&mut SntpPacket::new_unchecked(&mut payload[..]));
+
+        let port = cx.rand().rand_source_port();
+        let udp_repr = UdpRepr {
+            src_port: port,
+            dst_port: SNTP_SERVER_PORT,
+        };
+        let ip_repr = IpRepr::new(
+            src_addr,
+            self.server,
+            IpProtocol::Udp,
+            udp_repr.header_len() + payload.len(),
+            64,
+        );
+
+        net_debug!("SNTP send to {}: {}", self.server, repr);
+        emit(cx, (ip_repr, udp_repr, &payload))?;
+
+        self.state = State::Requesting {
+            port,
+            xmit_timestamp,
+            sent_at: cx.now(),
+            retry_at: cx.now() + timeout,
+            timeout,
+        };
+        Ok(())
+    }
+
+    pub(crate) fn poll_at(&self, _cx: &Context) -> PollAt {
+        match self.state {
+            State::Idle { send_at } => PollAt::Time(send_at),
+            State::Requesting { retry_at, .. } => PollAt::Time(retry_at),
+        }
+    }
+}
+
+#[cfg(feature = "proto-ipv4")] // tests assume ipv4
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wire::Ipv4Address;
+    use std::vec::Vec;
+
+    const SERVER_ADDR: IpAddress = IpAddress::Ipv4(Ipv4Address([192, 168, 1, 2]));
+
+    /// The Unix time at `Instant::ZERO` according to the server.
+    const OFFSET: i64 = 1_669_852_800_000_000;
+
+    fn dispatch(socket: &mut Socket, cx: &mut Context, now: Instant) -> Vec<(UdpRepr, SntpRepr)> {
+        cx.set_now(now);
+        let mut sent = Vec::new();
+        socket
+            .dispatch(cx, |_, (ip_repr, udp_repr, payload)| {
+                assert_eq!(ip_repr.dst_addr(), SERVER_ADDR);
+                let packet = SntpPacket::new_checked(payload).unwrap();
+                sent.push((udp_repr, SntpRepr::parse(&packet).unwrap()));
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        sent
+    }
+
+    /// Answer `request` with a response taking `delay` in each direction, from a server
+    /// whose clock is `OFFSET` ahead of the local one.
+    fn respond(
+        socket: &mut Socket,
+        cx: &mut Context,
+        (udp_repr, request): (UdpRepr, SntpRepr),
+        sent_at: Instant,
+        delay: Duration,
+        f: impl FnOnce(&mut SntpRepr),
+    ) {
+        let server_time = |at: Instant| NtpTimestamp::from_unix_micros(at.total_micros() + OFFSET);
+        let mut repr = SntpRepr {
+            leap_indicator: SntpLeapIndicator::NoWarning,
+            version: NTP_VERSION,
+            mode: SntpMode::Server,
+            stratum: 2,
+            poll: 0,
+            precision: -20,
+            root_delay: 0,
+            root_dispersion: 0,
+            ref_id: [192, 168, 1, 254],
+            ref_timestamp: server_time(Instant::ZERO),
+            orig_timestamp: request.xmit_timestamp,
+            recv_timestamp: server_time(sent_at + delay),
+            xmit_timestamp: server_time(sent_at + delay + Duration::from_millis(10)),
+        };
+        f(&mut repr);
+
+        let mut payload = [0; SNTP_HEADER_LEN];
+        repr.emit(&mut SntpPacket::new_unchecked(&mut payload[..]));
+        let udp_repr = UdpRepr {
+            src_port: SNTP_SERVER_PORT,
+            dst_port: udp_repr.src_port,
+        };
+        let ip_repr = IpRepr::new(
+            SERVER_ADDR,
+            IpAddress::v4(192, 168, 1, 1),
+            IpProtocol::Udp,
+            udp_repr.header_len() + payload.len(),
+            64,
+        );
+        cx.set_now(sent_at + delay * 2 + Duration::from_millis(10));
+        assert!(socket.accepts(&ip_repr, &udp_repr));
+        socket.process(cx, &ip_repr, &udp_repr, &payload);
+    }
+
+    #[test]
+    fn test_synchronize() {
+        let mut cx = Context::mock();
+        let mut socket = Socket::new(SERVER_ADDR);
+        assert_eq!(socket.poll_at(&cx), PollAt::Time(Instant::ZERO));
+
+        let sent_at = Instant::from_secs(10);
+        let mut sent = dispatch(&mut socket, &mut cx, sent_at);
+        assert_eq!(sent.len(), 1);
+        let (udp_repr, request) = sent[0];
+        assert_eq!(udp_repr.dst_port, SNTP_SERVER_PORT);
+        assert_eq!(request.mode, SntpMode::Client);
+        assert_eq!(request.version, 4);
+        assert_ne!(request.xmit_timestamp, NtpTimestamp::ZERO);
+
+        let delay = Duration::from_millis(45);
+        respond(
+            &mut socket,
+            &mut cx,
+            sent.pop().unwrap(),
+            sent_at,
+            delay,
+            |_| (),
+        );
+        let sync = match socket.poll() {
+            Some(Event::Synchronized(sync)) => sync,
+            None => panic!("no event"),
+        };
+        assert_eq!(sync.offset, OFFSET);
+        assert_eq!(sync.delay, Duration::from_millis(90));
+        assert_eq!(sync.stratum, 2);
+        assert_eq!(sync.unix_micros(Instant::from_secs(1)), OFFSET + 1_000_000);
+        assert_eq!(socket.poll(), None);
+
+        // The server is asked again after the poll interval.
+        let next_at = Instant::from_millis(10_100) + DEFAULT_POLL_INTERVAL;
+        assert_eq!(socket.poll_at(&cx), PollAt::Time(next_at));
+        assert!(dispatch(&mut socket, &mut cx, next_at - Duration::from_millis(1)).is_empty());
+        assert_eq!(dispatch(&mut socket, &mut cx, next_at).len(), 1);
+    }
+
+    #[test]
+    fn test_retry() {
+        let mut cx = Context::mock();
+        let mut socket = Socket::new(SERVER_ADDR);
+
+        let first = dispatch(&mut socket, &mut cx, Instant::ZERO);
+        assert_eq!(socket.poll_at(&cx), PollAt::Time(Instant::from_secs(2)));
+        let second = dispatch(&mut socket, &mut cx, Instant::from_secs(2));
+        assert_eq!(socket.poll_at(&cx), PollAt::Time(Instant::from_secs(6)));
+        assert_ne!(first[0].1.xmit_timestamp, second[0].1.xmit_timestamp);
+        let third = dispatch(&mut socket, &mut cx, Instant::from_secs(6));
+        assert_eq!(socket.poll_at(&cx), PollAt::Time(Instant::from_secs(14)));
+
+        // A late response to an earlier request is not accepted.
+        let udp_repr = UdpRepr {
+            src_port: SNTP_SERVER_PORT,
+            dst_port: third[0].0.src_port,
+        };
+        let ip_repr = IpRepr::new(
+            SERVER_ADDR,
+            IpAddress::v4(192, 168, 1, 1),
+            IpProtocol::Udp,
+            udp_repr.header_len() + SNTP_HEADER_LEN,
+            64,
+        );
+        let mut payload = [0; SNTP_HEADER_LEN];
+        let mut late = second[0].1;
+        late.mode = SntpMode::Server;
+        late.stratum = 2;
+        late.orig_timestamp = second[0].1.xmit_timestamp;
+        late.emit(&mut SntpPacket::new_unchecked(&mut payload[..]));
+        socket.process(&mut cx, &ip_repr, &udp_repr, &payload);
+        assert_eq!(socket.poll(), None);
+    }
+
+    #[test]
+    fn test_ignore_bad_responses() {
+        let mut cx = Context::mock();
+        let mut socket = Socket::new(SERVER_ADDR);
+        let sent_at = Instant::from_secs(10);
+        let request = dispatch(&mut socket, &mut cx, sent_at)[0];
+
+        // Too slow.
+        respond(
+            &mut socket,
+            &mut cx,
+            request,
+            sent_at,
+            Duration::from_millis(600),
+            |_| (),
+        );
+        // Not synchronized.
+        respond(
+            &mut socket,
+            &mut cx,
+            request,
+            sent_at,
+            Duration::from_millis(45),
+            |repr| repr.leap_indicator = SntpLeapIndicator::Unsynchronized,
+        );
+        assert_eq!(socket.poll(), None);
+
+        // A kiss-o'-death makes the socket wait for the poll interval.
+        respond(
+            &mut socket,
+            &mut cx,
+            request,
+            sent_at,
+            Duration::from_millis(45),
+            |repr| {
+                repr.stratum = 0;
+                repr.ref_id = *b"RATE";
+            },
+        );
+        assert_eq!(socket.poll(), None);
+        assert_eq!(
+            socket.poll_at(&cx),
+            PollAt::Time(Instant::from_millis(10_100) + DEFAULT_POLL_INTERVAL)
+        );
+    }
+}
@@ -0,0 +1,37 @@
+/// Counters of the traffic of a socket.
+///
+/// Packets are counted when they are processed by or dispatched from the socket, and their
+/// length is the length of their payload, i.e. not including the IP and transport headers.
+/// The counters wrap around on overflow.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stats {
+    /// The number of received packets.
+    pub rx_packets: u32,
+    /// The number of payload octets in received packets.
+    pub rx_bytes: u32,
+    /// The number of transmitted packets.
+    pub tx_packets: u32,
+    /// The number of payload octets in transmitted packets.
+    pub tx_bytes: u32,
+    /// The number of times data was retransmitted, after a retransmission timeout or
+    /// duplicate acknowledgements. Only counted by TCP sockets.
+    pub retransmissions: u32,
+}
+
+impl Stats {
+    pub(crate) fn record_rx(&mut self, len: usize) {
+        self.rx_packets = self.rx_packets.wrapping_add(1);
+        self.rx_bytes = self.rx_bytes.wrapping_add(len as u32);
+    }
+
+    pub(crate) fn record_tx(&mut self, len: usize) {
+        self.tx_packets = self.tx_packets.wrapping_add(1);
+        self.tx_bytes = self.tx_bytes.wrapping_add(len as u32);
+    }
+
+    #[cfg(feature = "socket-tcp")]
+    pub(crate) fn record_retransmission(&mut self) {
+        self.retransmissions = self.retransmissions.wrapping_add(1);
+    }
+}
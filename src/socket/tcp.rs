@@ -6,12 +6,19 @@ use core::fmt::Display;
 #[cfg(feature = "async")]
 use core::task::Waker;
 use core::{cmp, fmt, mem};
+#[cfg(feature = "socket-tcp-history")]
+use heapless::HistoryBuffer;
 
+use crate::iface::RebindPolicy;
+#[cfg(feature = "stats")]
+use crate::socket::Stats;
 #[cfg(feature = "async")]
 use crate::socket::WakerRegistration;
 use crate::socket::{Context, PollAt};
-use crate::storage::{Assembler, RingBuffer};
-use crate::time::{Duration, Instant};
+use crate::storage::{Assembler, MemoryUsage, RingBuffer};
+use crate::time::{Duration, Instant, RateLimiter};
+#[cfg(feature = "socket-tcp-md5")]
+use crate::wire::TcpPacket;
 use crate::wire::{
     IpAddress, IpEndpoint, IpListenEndpoint, IpProtocol, IpRepr, TcpControl, TcpRepr, TcpSeqNumber,
     TCP_HEADER_LEN,
@@ -21,6 +28,8 @@ macro_rules! tcp_trace {
     ($($arg:expr),*) => (net_log!(trace, $($arg),*));
 }
 
+mod congestion;
+
 /// Error returned by [`Socket::listen`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -29,6 +38,27 @@ pub enum ListenError {
     Unaddressable,
 }
 
+impl fmt::Display for ListenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ListenError::InvalidState => write!(f, "invalid state"),
+            ListenError::Unaddressable => write!(f, "unaddressable destination"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ListenError {}
+
+impl From<ListenError> for crate::Error {
+    fn from(err: ListenError) -> Self {
+        match err {
+            ListenError::InvalidState => crate::Error::Illegal(crate::Layer::Transport),
+            ListenError::Unaddressable => crate::Error::Unaddressable(crate::Layer::Transport),
+        }
+    }
+}
+
 /// Error returned by [`Socket::connect`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -37,6 +67,27 @@ pub enum ConnectError {
     Unaddressable,
 }
 
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConnectError::InvalidState => write!(f, "invalid state"),
+            ConnectError::Unaddressable => write!(f, "unaddressable destination"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConnectError {}
+
+impl From<ConnectError> for crate::Error {
+    fn from(err: ConnectError) -> Self {
+        match err {
+            ConnectError::InvalidState => crate::Error::Illegal(crate::Layer::Transport),
+            ConnectError::Unaddressable => crate::Error::Unaddressable(crate::Layer::Transport),
+        }
+    }
+}
+
 /// Error returned by [`Socket::send`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -44,6 +95,25 @@ pub enum SendError {
     InvalidState,
 }
 
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendError::InvalidState => write!(f, "invalid state"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SendError {}
+
+impl From<SendError> for crate::Error {
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::InvalidState => crate::Error::Illegal(crate::Layer::Transport),
+        }
+    }
+}
+
 /// Error returned by [`Socket::recv`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -52,6 +122,27 @@ pub enum RecvError {
     Finished,
 }
 
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecvError::InvalidState => write!(f, "invalid state"),
+            RecvError::Finished => write!(f, "connection finished"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RecvError {}
+
+impl From<RecvError> for crate::Error {
+    fn from(err: RecvError) -> Self {
+        match err {
+            RecvError::InvalidState => crate::Error::Illegal(crate::Layer::Transport),
+            RecvError::Finished => crate::Error::Finished,
+        }
+    }
+}
+
 /// A TCP socket ring buffer.
 pub type SocketBuffer<'a> = RingBuffer<'a, u8>;
 
@@ -211,6 +302,10 @@ enum Timer {
         delay: Duration,
     },
     FastRetransmit,
+    ZeroWindowProbe {
+        expires_at: Instant,
+        delay: Duration,
+    },
     Close {
         expires_at: Instant,
     },
@@ -245,6 +340,13 @@ impl Timer {
         }
     }
 
+    fn should_zero_window_probe(&self, timestamp: Instant) -> bool {
+        match *self {
+            Timer::ZeroWindowProbe { expires_at, .. } if timestamp >= expires_at => true,
+            _ => false,
+        }
+    }
+
     fn should_close(&self, timestamp: Instant) -> bool {
         match *self {
             Timer::Close { expires_at } if timestamp >= expires_at => true,
@@ -262,11 +364,16 @@ impl Timer {
             } => PollAt::Ingress,
             Timer::Retransmit { expires_at, .. } => PollAt::Time(expires_at),
             Timer::FastRetransmit => PollAt::Now,
+            Timer::ZeroWindowProbe { expires_at, .. } => PollAt::Time(expires_at),
             Timer::Close { expires_at } => PollAt::Time(expires_at),
         }
     }
 
     fn set_for_idle(&mut self, timestamp: Instant, interval: Option<Duration>) {
+        // Zero window probes go on until the window opens, see `clear_zero_window_probe`.
+        if let Timer::ZeroWindowProbe { .. } = *self {
+            return;
+        }
         *self = Timer::Idle {
             keep_alive_at: interval.map(|interval| timestamp + interval),
         }
@@ -294,7 +401,7 @@ impl Timer {
 
     fn set_for_retransmit(&mut self, timestamp: Instant, delay: Duration) {
         match *self {
-            Timer::Idle { .. } | Timer::FastRetransmit { .. } => {
+            Timer::Idle { .. } | Timer::FastRetransmit | Timer::ZeroWindowProbe { .. } => {
                 *self = Timer::Retransmit {
                     expires_at: timestamp + delay,
                     delay,
@@ -315,6 +422,35 @@ impl Timer {
         *self = Timer::FastRetransmit
     }
 
+    fn set_for_zero_window_probe(&mut self, timestamp: Instant, delay: Duration) {
+        if let Timer::ZeroWindowProbe { .. } = *self {
+            return;
+        }
+        *self = Timer::ZeroWindowProbe {
+            expires_at: timestamp + delay,
+            delay,
+        }
+    }
+
+    fn rewind_zero_window_probe(&mut self, timestamp: Instant) {
+        if let Timer::ZeroWindowProbe {
+            ref mut expires_at,
+            ref mut delay,
+        } = *self
+        {
+            *delay = (*delay * 2).min(Duration::from_millis(RTTE_MAX_RTO as u64));
+            *expires_at = timestamp + *delay;
+        }
+    }
+
+    fn clear_zero_window_probe(&mut self, timestamp: Instant, interval: Option<Duration>) {
+        if let Timer::ZeroWindowProbe { .. } = *self {
+            *self = Timer::Idle {
+                keep_alive_at: interval.map(|interval| timestamp + interval),
+            }
+        }
+    }
+
     fn set_for_close(&mut self, timestamp: Instant) {
         *self = Timer::Close {
             expires_at: timestamp + CLOSE_DELAY,
@@ -336,6 +472,86 @@ enum AckDelayTimer {
     Immediate,
 }
 
+/// The receive window initially advertised with auto-tuning, if the buffer is large enough.
+const RX_AUTOTUNE_INITIAL_WINDOW: usize = 10 * DEFAULT_MSS;
+
+/// State of receive window auto-tuning.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct RxAutotune {
+    /// Largest offset past the beginning of the receive buffer that may be advertised.
+    limit: usize,
+    /// Start of the current measurement, and the sequence number received up to then.
+    since: Option<(Instant, TcpSeqNumber)>,
+}
+
+impl RxAutotune {
+    fn new(limit: usize) -> RxAutotune {
+        RxAutotune { limit, since: None }
+    }
+}
+
+/// The number of events kept in the history of a TCP socket.
+#[cfg(feature = "socket-tcp-history")]
+pub const HISTORY_LEN: usize = 16;
+
+/// A significant event in the life of a TCP socket.
+#[cfg(feature = "socket-tcp-history")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HistoryEvent {
+    /// The socket moved from one state to another.
+    State { from: State, to: State },
+    /// Unacknowledged data is being retransmitted, starting at the given sequence number.
+    Retransmit { seq: TcpSeqNumber },
+    /// The remote endpoint advertised a different window size.
+    RemoteWindow { len: usize },
+    /// No packets have been received within the timeout, and the connection was aborted.
+    Timeout,
+}
+
+/// An entry in the history of a TCP socket.
+#[cfg(feature = "socket-tcp-history")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HistoryEntry {
+    /// The time the event happened at.
+    ///
+    /// Events caused by calling methods on the socket are stamped with the time
+    /// the socket was last processed or dispatched.
+    pub timestamp: Instant,
+    pub event: HistoryEvent,
+}
+
+/// A limit on the rate of connection attempts accepted by a listening socket.
+///
+/// See [Socket::set_syn_rate_limit].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SynRateLimit {
+    /// The maximum number of SYN packets accepted within each interval.
+    pub max_syns: u16,
+    /// The length of the interval.
+    pub interval: Duration,
+    /// Whether excess SYN packets are answered with an RST packet instead of being dropped.
+    pub reset: bool,
+}
+
+/// A congestion control algorithm.
+///
+/// See [Socket::set_congestion_control].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum CongestionControl {
+    /// No congestion control; as much data is sent as the remote window allows.
+    None,
+    /// Reno, as described in RFC 5681.
+    Reno,
+    /// CUBIC, as described in RFC 8312.
+    #[cfg(feature = "socket-tcp-cubic")]
+    Cubic,
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 struct Tuple {
@@ -354,7 +570,9 @@ impl Display for Tuple {
 /// A TCP socket may passively listen for connections or actively connect to another endpoint.
 /// Note that, for listening sockets, there is no "backlog"; to be able to simultaneously
 /// accept several connections, as many sockets must be allocated, or any new connection
-/// attempts will be reset.
+/// attempts will be reset (or dropped, see [Interface::set_tcp_backlog_policy]).
+///
+/// [Interface::set_tcp_backlog_policy]: ../../iface/struct.Interface.html#method.set_tcp_backlog_policy
 #[derive(Debug)]
 pub struct Socket<'a> {
     state: State,
@@ -368,6 +586,31 @@ pub struct Socket<'a> {
     timeout: Option<Duration>,
     /// Interval at which keep-alive packets will be sent.
     keep_alive: Option<Duration>,
+    /// Idle time before the first keep-alive packet is sent, if different from the interval.
+    keep_alive_idle: Option<Duration>,
+    /// Number of unanswered keep-alive packets after which the connection is aborted.
+    keep_alive_probes: Option<u8>,
+    /// Number of keep-alive packets sent since the last packet was received.
+    keep_alive_probes_sent: u8,
+    /// Interval after which, if no packets are received in the SYN-RECEIVED state,
+    /// the connection attempt is abandoned.
+    syn_received_timeout: Option<Duration>,
+    /// Number of SYN|ACK retransmissions after which the connection attempt is abandoned.
+    syn_ack_retries: Option<u8>,
+    /// Number of SYN|ACK retransmissions in the current SYN-RECEIVED state.
+    syn_ack_retries_sent: u8,
+    /// Interval after which, if no packets are received in the FIN-WAIT-2 state,
+    /// the connection is aborted.
+    fin_wait_2_timeout: Option<Duration>,
+    /// Interval during which a handshake is only completed by a packet carrying data.
+    defer_accept: Option<Duration>,
+    /// The connection was aborted because its local address was removed from the interface.
+    address_lost: bool,
+    /// Limit on the rate of SYN packets accepted in the LISTEN state.
+    syn_rate_limit: Option<SynRateLimit>,
+    congestion_controller: congestion::AnyController,
+    /// The SYN packets accepted within the current rate limiting interval.
+    syn_rate_limiter: RateLimiter,
     /// The time-to-live (IPv4) or hop limit (IPv6) value used in outgoing packets.
     hop_limit: Option<u8>,
     /// Address passed to listen(). Listen address is set when listen() is called and
@@ -399,6 +642,24 @@ pub struct Socket<'a> {
     remote_win_scale: Option<u8>,
     /// Whether or not the remote supports selective ACK as described in RFC 2018.
     remote_has_sack: bool,
+    /// Whether or not the timestamps option of RFC 7323 is in use on the connection.
+    remote_has_timestamp: bool,
+    /// The timestamp value to echo to the remote, i.e. `TS.Recent` from RFC 7323.
+    remote_last_tsval: u32,
+    /// Whether or not ECN, as described in RFC 3168, is in use on the connection.
+    remote_has_ecn: bool,
+    /// A congestion experienced mark was received, and is echoed to the remote until
+    /// it signals that it reduced its congestion window.
+    ecn_echo: bool,
+    /// The congestion window was reduced after an ECN echo, which is signalled to the remote
+    /// with the next segment of new data.
+    ecn_cwr: bool,
+    /// The last sequence number sent when the congestion window was reduced after an ECN
+    /// echo. Further echoes only reduce it again once that is acknowledged.
+    ecn_recover: Option<TcpSeqNumber>,
+    /// The end of the sequence space sent so far. Segments before it are retransmissions,
+    /// which are not ECN-capable.
+    ecn_high_seq: Option<TcpSeqNumber>,
     /// The maximum number of data octets that the remote side may receive.
     remote_mss: usize,
     /// The timestamp of the last packet received.
@@ -410,6 +671,9 @@ pub struct Socket<'a> {
     /// The number of packets received directly after
     /// each other which have the same ACK number.
     local_rx_dup_acks: u8,
+    /// The octets beyond local_seq_no reported as received by the selective ACK blocks
+    /// of the last packet received, which need not be retransmitted.
+    remote_sacked: [Option<(TcpSeqNumber, TcpSeqNumber)>; 3],
 
     /// Duration for Delayed ACK. If None no ACKs will be delayed.
     ack_delay: Option<Duration>,
@@ -423,6 +687,35 @@ pub struct Socket<'a> {
     /// Nagle's Algorithm enabled.
     nagle: bool,
 
+    /// Whether the timestamps option is offered to the remote.
+    timestamps: bool,
+    /// Whether ECN is offered to the remote, or accepted when it offers it.
+    ecn: bool,
+    /// Partial segments are held back until uncorked.
+    cork: bool,
+    /// Receive window auto-tuning state, if enabled.
+    rx_autotune: Option<RxAutotune>,
+    /// Low and high watermarks of the transmit buffer occupancy.
+    tx_watermarks: Option<(usize, usize)>,
+    /// The transmit buffer reached its high watermark, and not yet drained to the low one.
+    tx_throttled: bool,
+    /// The length of the contiguous free space handed out by the last call to
+    /// `send_reserve`, or zero once it was committed or overwritten by another send.
+    tx_reserved: usize,
+    /// The key signing and verifying every segment with the MD5 signature option.
+    #[cfg(feature = "socket-tcp-md5")]
+    md5_key: Option<&'a [u8]>,
+
+    /// The most recent significant events, oldest first.
+    #[cfg(feature = "socket-tcp-history")]
+    history: HistoryBuffer<HistoryEntry, HISTORY_LEN>,
+    /// The last timestamp seen in `process` or `dispatch`, used to stamp history entries.
+    #[cfg(feature = "socket-tcp-history")]
+    history_now: Instant,
+
+    #[cfg(feature = "stats")]
+    stats: Stats,
+
     #[cfg(feature = "async")]
     rx_waker: WakerRegistration,
     #[cfg(feature = "async")]
@@ -431,6 +724,11 @@ pub struct Socket<'a> {
 
 const DEFAULT_MSS: usize = 536;
 
+/// The ECN field of ECN-capable packets, i.e. the ECT(0) codepoint of RFC 3168.
+const ECN_ECT0: u8 = 0b10;
+/// The ECN field of packets marked by a router experiencing congestion.
+const ECN_CE: u8 = 0b11;
+
 impl<'a> Socket<'a> {
     #[allow(unused_comparisons)] // small usize platforms always pass rx_capacity check
     /// Create a socket using the given buffers.
@@ -460,6 +758,18 @@ impl<'a> Socket<'a> {
             rx_fin_received: false,
             timeout: None,
             keep_alive: None,
+            keep_alive_idle: None,
+            keep_alive_probes: None,
+            keep_alive_probes_sent: 0,
+            syn_received_timeout: None,
+            syn_ack_retries: None,
+            syn_ack_retries_sent: 0,
+            fin_wait_2_timeout: None,
+            defer_accept: None,
+            address_lost: false,
+            syn_rate_limit: None,
+            congestion_controller: congestion::AnyController::new(CongestionControl::None),
+            syn_rate_limiter: RateLimiter::new(),
             hop_limit: None,
             listen_endpoint: IpListenEndpoint::default(),
             tuple: None,
@@ -472,15 +782,40 @@ impl<'a> Socket<'a> {
             remote_win_shift: rx_cap_log2.saturating_sub(16) as u8,
             remote_win_scale: None,
             remote_has_sack: false,
+            remote_has_timestamp: false,
+            remote_last_tsval: 0,
+            remote_has_ecn: false,
+            ecn_echo: false,
+            ecn_cwr: false,
+            ecn_recover: None,
+            ecn_high_seq: None,
             remote_mss: DEFAULT_MSS,
             remote_last_ts: None,
             local_rx_last_ack: None,
             local_rx_last_seq: None,
             local_rx_dup_acks: 0,
+            remote_sacked: [None; 3],
             ack_delay: Some(ACK_DELAY_DEFAULT),
             ack_delay_timer: AckDelayTimer::Idle,
             challenge_ack_timer: Instant::from_secs(0),
             nagle: true,
+            timestamps: false,
+            ecn: false,
+            cork: false,
+            rx_autotune: None,
+            tx_watermarks: None,
+            tx_throttled: false,
+            tx_reserved: 0,
+            #[cfg(feature = "socket-tcp-md5")]
+            md5_key: None,
+
+            #[cfg(feature = "socket-tcp-history")]
+            history: HistoryBuffer::new(),
+            #[cfg(feature = "socket-tcp-history")]
+            history_now: Instant::from_secs(0),
+
+            #[cfg(feature = "stats")]
+            stats: Stats::default(),
 
             #[cfg(feature = "async")]
             rx_waker: WakerRegistration::new(),
@@ -545,6 +880,101 @@ impl<'a> Socket<'a> {
         self.nagle
     }
 
+    /// Return whether the timestamps option is offered to the remote.
+    ///
+    /// See also the [set_timestamps_enabled](#method.set_timestamps_enabled) method.
+    pub fn timestamps_enabled(&self) -> bool {
+        self.timestamps
+    }
+
+    /// Return whether ECN is offered to the remote.
+    ///
+    /// See also the [set_ecn_enabled](#method.set_ecn_enabled) method.
+    pub fn ecn_enabled(&self) -> bool {
+        self.ecn
+    }
+
+    /// Return the key of the MD5 signature option.
+    ///
+    /// See also the [set_md5_key](#method.set_md5_key) method.
+    #[cfg(feature = "socket-tcp-md5")]
+    pub fn md5_key(&self) -> Option<&'a [u8]> {
+        self.md5_key
+    }
+
+    /// Return whether the socket is corked.
+    ///
+    /// See also the [set_corked](#method.set_corked) method.
+    pub fn is_corked(&self) -> bool {
+        self.cork
+    }
+
+    /// Return whether receive window auto-tuning is enabled.
+    ///
+    /// See also the [set_recv_window_autotune](#method.set_recv_window_autotune) method.
+    pub fn recv_window_autotune(&self) -> bool {
+        self.rx_autotune.is_some()
+    }
+
+    /// Enable or disable receive window auto-tuning.
+    ///
+    /// By default, it is disabled, and the whole free space in the receive buffer is
+    /// advertised. When enabled, the advertised window starts at ten default-sized segments,
+    /// and doubles whenever the remote endpoint fills more than half of it within a round-trip
+    /// time, up to the receive buffer capacity. This keeps slow or idle connections from
+    /// having a large amount of data in flight towards them.
+    ///
+    /// Enabling auto-tuning on a connected socket takes effect on the next connection,
+    /// as the advertised window must not shrink.
+    pub fn set_recv_window_autotune(&mut self, enabled: bool) {
+        self.rx_autotune = match (enabled, self.rx_autotune) {
+            (false, _) => None,
+            (true, Some(autotune)) => Some(autotune),
+            (true, None) => {
+                let limit = match self.state {
+                    State::Closed | State::Listen => {
+                        cmp::min(RX_AUTOTUNE_INITIAL_WINDOW, self.rx_buffer.capacity())
+                    }
+                    _ => self.rx_buffer.capacity(),
+                };
+                Some(RxAutotune::new(limit))
+            }
+        }
+    }
+
+    /// Return the receive window to advertise, before scaling.
+    fn rx_window(&self) -> usize {
+        match self.rx_autotune {
+            Some(RxAutotune { limit, .. }) => cmp::min(
+                self.rx_buffer.window(),
+                limit.saturating_sub(self.rx_buffer.len()),
+            ),
+            None => self.rx_buffer.window(),
+        }
+    }
+
+    /// Grow the receive window if the remote endpoint sent more than half of it within
+    /// a round-trip time.
+    fn autotune_rx_window(&mut self, timestamp: Instant) {
+        let received = self.remote_seq_no + self.rx_buffer.len();
+        let capacity = self.rx_buffer.capacity();
+        let rtt = Duration::from_millis(self.rtte.rtt as u64);
+        if let Some(ref mut autotune) = self.rx_autotune {
+            match autotune.since {
+                Some((since, _)) if timestamp < since + rtt => {}
+                Some((_, since_seq)) => {
+                    let delivered = received - since_seq;
+                    if delivered * 2 > autotune.limit && autotune.limit < capacity {
+                        autotune.limit = cmp::min(delivered * 2, capacity);
+                        tcp_trace!("rx window: growing to {}", autotune.limit);
+                    }
+                    autotune.since = Some((timestamp, received));
+                }
+                None => autotune.since = Some((timestamp, received)),
+            }
+        }
+    }
+
     /// Return the current window field value, including scaling according to RFC 1323.
     ///
     /// Used in internal calculations as well as packet generation.
@@ -552,7 +982,7 @@ impl<'a> Socket<'a> {
     #[inline]
     fn scaled_window(&self) -> u16 {
         cmp::min(
-            self.rx_buffer.window() >> self.remote_win_shift as usize,
+            self.rx_window() >> self.remote_win_shift as usize,
             (1 << 16) - 1,
         ) as u16
     }
@@ -572,6 +1002,148 @@ impl<'a> Socket<'a> {
         self.timeout = duration
     }
 
+    /// Return the SYN-RECEIVED timeout duration.
+    ///
+    /// See also the [set_syn_received_timeout](#method.set_syn_received_timeout) method.
+    pub fn syn_received_timeout(&self) -> Option<Duration> {
+        self.syn_received_timeout
+    }
+
+    /// Set the SYN-RECEIVED timeout duration.
+    ///
+    /// A listening socket with this duration set abandons a connection attempt, and returns
+    /// to the LISTEN state, if the remote endpoint does not complete the handshake within
+    /// this duration after its last packet, e.g. because it vanished after sending a SYN.
+    ///
+    /// By default, no timeout is set, and the SYN|ACK is retransmitted indefinitely unless
+    /// the general [timeout](#method.set_timeout) expires.
+    pub fn set_syn_received_timeout(&mut self, duration: Option<Duration>) {
+        self.syn_received_timeout = duration
+    }
+
+    /// Return the maximum number of SYN|ACK retransmissions.
+    ///
+    /// See also the [set_syn_ack_retries](#method.set_syn_ack_retries) method.
+    pub fn syn_ack_retries(&self) -> Option<u8> {
+        self.syn_ack_retries
+    }
+
+    /// Set the maximum number of SYN|ACK retransmissions.
+    ///
+    /// In the SYN-RECEIVED state, the SYN|ACK is retransmitted with an exponential backoff
+    /// until the remote endpoint acknowledges it. A socket with this count set abandons the
+    /// connection attempt after retransmitting the SYN|ACK this many times without an answer,
+    /// and returns to the LISTEN state. This is similar to Linux's `tcp_synack_retries`.
+    ///
+    /// By default, no count is set, and the SYN|ACK is retransmitted indefinitely unless
+    /// a timeout expires.
+    pub fn set_syn_ack_retries(&mut self, retries: Option<u8>) {
+        self.syn_ack_retries = retries
+    }
+
+    /// Return the FIN-WAIT-2 timeout duration.
+    ///
+    /// See also the [set_fin_wait_2_timeout](#method.set_fin_wait_2_timeout) method.
+    pub fn fin_wait_2_timeout(&self) -> Option<Duration> {
+        self.fin_wait_2_timeout
+    }
+
+    /// Set the FIN-WAIT-2 timeout duration.
+    ///
+    /// A socket with this duration set aborts the connection if, after it was
+    /// [closed](#method.close) and the remote endpoint acknowledged it, the remote endpoint
+    /// sends nothing within this duration, e.g. because it vanished without closing its half
+    /// of the connection. This is similar to Linux's `tcp_fin_timeout`.
+    ///
+    /// By default, no timeout is set, and the socket may stay in the FIN-WAIT-2 state
+    /// indefinitely.
+    pub fn set_fin_wait_2_timeout(&mut self, duration: Option<Duration>) {
+        self.fin_wait_2_timeout = duration
+    }
+
+    /// Return the deferred accept duration.
+    ///
+    /// See also the [set_defer_accept](#method.set_defer_accept) method.
+    pub fn defer_accept(&self) -> Option<Duration> {
+        self.defer_accept
+    }
+
+    /// Set the deferred accept duration.
+    ///
+    /// A listening socket with this duration set stays in the SYN-RECEIVED state, and thus
+    /// does not become [active](#method.is_active), until the remote endpoint sends data,
+    /// instead of as soon as it acknowledges the SYN|ACK. Once this duration has elapsed
+    /// since the last packet from the remote endpoint, an acknowledgement without data
+    /// completes the handshake again. This is similar to Linux's TCP_DEFER_ACCEPT flag,
+    /// and keeps request/response servers from being tied up by idle connections;
+    /// see also [set_syn_received_timeout](#method.set_syn_received_timeout).
+    ///
+    /// By default, accepting is not deferred.
+    pub fn set_defer_accept(&mut self, duration: Option<Duration>) {
+        self.defer_accept = duration
+    }
+
+    /// Return the limit on the rate of connection attempts.
+    ///
+    /// See also the [set_syn_rate_limit](#method.set_syn_rate_limit) method.
+    pub fn syn_rate_limit(&self) -> Option<SynRateLimit> {
+        self.syn_rate_limit
+    }
+
+    /// Set the limit on the rate of connection attempts.
+    ///
+    /// A listening socket with a limit set accepts at most `max_syns` SYN packets within
+    /// each interval, and drops or resets the excess ones. Each socket listening on the same
+    /// endpoint counts the SYN packets it accepts separately. This protects a device from
+    /// connection storms, which would otherwise keep its sockets cycling through
+    /// the SYN-RECEIVED state.
+    ///
+    /// By default, no limit is set.
+    pub fn set_syn_rate_limit(&mut self, limit: Option<SynRateLimit>) {
+        self.syn_rate_limit = limit
+    }
+
+    /// Return the congestion control algorithm.
+    ///
+    /// See also the [set_congestion_control](#method.set_congestion_control) method.
+    pub fn congestion_control(&self) -> CongestionControl {
+        self.congestion_controller.algorithm()
+    }
+
+    /// Set the congestion control algorithm.
+    ///
+    /// Congestion control limits the amount of data in flight to a congestion window,
+    /// which grows as data is acknowledged and shrinks when segments are lost, so that
+    /// a connection does not overwhelm a slow link by sending a full remote window at once.
+    ///
+    /// By default, there is no congestion control. Setting the algorithm restarts it
+    /// from the initial window, so it is best done before opening a connection.
+    pub fn set_congestion_control(&mut self, algorithm: CongestionControl) {
+        self.congestion_controller = congestion::AnyController::new(algorithm);
+        self.congestion_controller
+            .inner_mut()
+            .set_mss(self.remote_mss);
+    }
+
+    /// Count a SYN packet against the rate limit, and return whether it exceeds it.
+    fn syn_rate_exceeded(&mut self, timestamp: Instant) -> bool {
+        match self.syn_rate_limit {
+            Some(limit) => {
+                self.syn_rate_limiter
+                    .exceeded(timestamp, limit.max_syns, limit.interval)
+            }
+            None => false,
+        }
+    }
+
+    fn accept_deferred(&self, timestamp: Instant) -> bool {
+        match (self.defer_accept, self.remote_last_ts) {
+            (Some(defer), Some(remote_last_ts)) => timestamp < remote_last_ts + defer,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
     /// Set the ACK delay duration.
     ///
     /// By default, the ACK delay is set to 10ms.
@@ -595,6 +1167,63 @@ impl<'a> Socket<'a> {
         self.nagle = enabled
     }
 
+    /// Enable or disable the timestamps option.
+    ///
+    /// By default, it is disabled. When enabled, the option is offered when opening a
+    /// connection, and if the remote supports it, every segment carries a timestamp in
+    /// milliseconds, echoed by the remote. As described in RFC 7323, this allows measuring
+    /// the round-trip time with every acknowledgement, including those of retransmitted
+    /// segments, and protects against old duplicate segments once sequence numbers wrap
+    /// around (PAWS).
+    ///
+    /// Changing this setting only affects connections opened afterwards.
+    pub fn set_timestamps_enabled(&mut self, enabled: bool) {
+        self.timestamps = enabled
+    }
+
+    /// Enable or disable Explicit Congestion Notification.
+    ///
+    /// By default, it is disabled. When enabled, ECN is negotiated when opening or accepting
+    /// a connection, as described in RFC 3168. If the remote supports it, new data is sent
+    /// with the ECT(0) codepoint in the IP header, so that routers using active queue
+    /// management mark it instead of dropping it when congested; the remote echoes these
+    /// marks back, and the congestion window is reduced as if a segment was lost, but
+    /// without any retransmission. Marks received from the remote are echoed the same way.
+    ///
+    /// Changing this setting only affects connections opened afterwards.
+    pub fn set_ecn_enabled(&mut self, enabled: bool) {
+        self.ecn = enabled
+    }
+
+    /// Set the key of the MD5 signature option, or `None` to stop using it.
+    ///
+    /// By default, there is no key. With a key, every segment sent carries the MD5 signature
+    /// option of RFC 2385, computed with the key, and segments received without the option or
+    /// with a wrong signature are silently dropped, and counted as
+    /// [DropReason::Filtered](../../iface/enum.DropReason.html#variant.Filtered). Both ends of
+    /// the connection must use the same key, which is typically required by BGP peers.
+    ///
+    /// The option takes 18 of the 40 octets available for options, so fewer SACK blocks
+    /// are sent along with it.
+    #[cfg(feature = "socket-tcp-md5")]
+    pub fn set_md5_key(&mut self, key: Option<&'a [u8]>) {
+        self.md5_key = key
+    }
+
+    /// Cork or uncork the socket.
+    ///
+    /// By default, the socket is not corked. This is equivalent to Linux's TCP_CORK flag,
+    /// without its 200 ms ceiling.
+    ///
+    /// While corked, segments smaller than MSS are never sent, regardless of
+    /// [Nagle's Algorithm](#method.set_nagle_enabled); only full segments, and the rest of
+    /// the data once the connection is [closed](#method.close), are. This allows assembling
+    /// e.g. a response from several small writes, and uncorking the socket to send it in
+    /// as few segments as possible.
+    pub fn set_corked(&mut self, corked: bool) {
+        self.cork = corked
+    }
+
     /// Return the keep-alive interval.
     ///
     /// See also the [set_keep_alive](#method.set_keep_alive) method.
@@ -613,7 +1242,8 @@ impl<'a> Socket<'a> {
     ///   * The remote endpoint has crashed and does not answer.
     ///
     /// The keep-alive functionality together with the timeout functionality allows to react
-    /// to these error conditions.
+    /// to these error conditions. See also [set_keep_alive_idle](#method.set_keep_alive_idle)
+    /// and [set_keep_alive_probes](#method.set_keep_alive_probes).
     pub fn set_keep_alive(&mut self, interval: Option<Duration>) {
         self.keep_alive = interval;
         if self.keep_alive.is_some() {
@@ -623,6 +1253,49 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Return the idle time before the first keep-alive packet.
+    ///
+    /// See also the [set_keep_alive_idle](#method.set_keep_alive_idle) method.
+    pub fn keep_alive_idle(&self) -> Option<Duration> {
+        self.keep_alive_idle
+    }
+
+    /// Set the idle time before the first keep-alive packet.
+    ///
+    /// When keep-alive is enabled with [set_keep_alive](#method.set_keep_alive), the first
+    /// keep-alive packet is sent after the connection has been idle for this duration, and
+    /// the following ones at the keep-alive interval until the remote endpoint answers.
+    /// If `None`, which is the default, the keep-alive interval is used.
+    ///
+    /// E.g. a long idle time and a short interval detect dead peers without generating
+    /// traffic on healthy idle connections, while a short idle time keeps NAT mappings alive.
+    pub fn set_keep_alive_idle(&mut self, idle: Option<Duration>) {
+        self.keep_alive_idle = idle
+    }
+
+    /// Return the number of unanswered keep-alive packets after which the connection
+    /// is aborted.
+    ///
+    /// See also the [set_keep_alive_probes](#method.set_keep_alive_probes) method.
+    pub fn keep_alive_probes(&self) -> Option<u8> {
+        self.keep_alive_probes
+    }
+
+    /// Set the number of unanswered keep-alive packets after which the connection is aborted.
+    ///
+    /// The connection is aborted one keep-alive interval after the last unanswered packet.
+    /// If `None`, which is the default, keep-alive packets are sent indefinitely, and only
+    /// the [timeout](#method.set_timeout) may abort the connection.
+    pub fn set_keep_alive_probes(&mut self, probes: Option<u8>) {
+        self.keep_alive_probes = probes
+    }
+
+    /// Return the duration after which an idle connection sends its first keep-alive packet.
+    fn keep_alive_first(&self) -> Option<Duration> {
+        self.keep_alive
+            .map(|interval| self.keep_alive_idle.unwrap_or(interval))
+    }
+
     /// Return the time-to-live (IPv4) or hop limit (IPv6) value used in outgoing packets.
     ///
     /// See also the [set_hop_limit](#method.set_hop_limit) method
@@ -662,6 +1335,16 @@ impl<'a> Socket<'a> {
         Some(self.tuple?.remote)
     }
 
+    /// Return the local port the socket is listening on or connected from, or None if
+    /// it is closed. The port is held until the TIME-WAIT state is over.
+    pub(crate) fn local_port(&self) -> Option<u16> {
+        match self.state {
+            State::Closed => None,
+            State::Listen => Some(self.listen_endpoint.port),
+            _ => Some(self.tuple?.local.port),
+        }
+    }
+
     /// Return the connection state, in terms of the TCP state machine.
     #[inline]
     pub fn state(&self) -> State {
@@ -691,6 +1374,23 @@ impl<'a> Socket<'a> {
         self.remote_win_shift = rx_cap_log2.saturating_sub(16) as u8;
         self.remote_mss = DEFAULT_MSS;
         self.remote_last_ts = None;
+        self.remote_has_timestamp = false;
+        self.remote_last_tsval = 0;
+        self.remote_has_ecn = false;
+        self.ecn_echo = false;
+        self.ecn_cwr = false;
+        self.ecn_recover = None;
+        self.ecn_high_seq = None;
+        self.congestion_controller =
+            congestion::AnyController::new(self.congestion_controller.algorithm());
+        self.remote_sacked = [None; 3];
+        self.keep_alive_probes_sent = 0;
+        if self.rx_autotune.is_some() {
+            let limit = cmp::min(RX_AUTOTUNE_INITIAL_WINDOW, self.rx_buffer.capacity());
+            self.rx_autotune = Some(RxAutotune::new(limit));
+        }
+        self.tx_throttled = false;
+        self.tx_reserved = 0;
         self.ack_delay_timer = AckDelayTimer::Idle;
         self.challenge_ack_timer = Instant::from_secs(0);
 
@@ -720,6 +1420,7 @@ impl<'a> Socket<'a> {
         }
 
         self.reset();
+        self.address_lost = false;
         self.listen_endpoint = local_endpoint;
         self.tuple = None;
         self.set_state(State::Listen);
@@ -728,8 +1429,9 @@ impl<'a> Socket<'a> {
 
     /// Connect to a given endpoint.
     ///
-    /// The local port must be provided explicitly. Assuming `fn get_ephemeral_port() -> u16`
-    /// allocates a port between 49152 and 65535, a connection may be established as follows:
+    /// The local port must be provided explicitly. An unused one can be allocated with
+    /// [Interface::ephemeral_port](crate::iface::Interface::ephemeral_port), so that a
+    /// connection may be established as follows:
     ///
     /// ```rust
     /// # #[cfg(all(
@@ -738,17 +1440,14 @@ impl<'a> Socket<'a> {
     /// # ))]
     /// # {
     /// # use smoltcp::socket::tcp::{Socket, SocketBuffer};
-    /// # use smoltcp::iface::{InterfaceBuilder, NeighborCache};
+    /// # use smoltcp::iface::{InterfaceBuilder, NeighborCache, SocketSet};
     /// # use smoltcp::wire::{HardwareAddress, EthernetAddress, IpAddress, IpCidr};
     /// #
-    /// # fn get_ephemeral_port() -> u16 {
-    /// #     49152
-    /// # }
-    /// #
-    /// # let mut socket = Socket::new(
+    /// # let mut sockets = SocketSet::new(vec![]);
+    /// # let handle = sockets.add(Socket::new(
     /// #     SocketBuffer::new(vec![0; 1200]),
     /// #     SocketBuffer::new(vec![0; 1200])
-    /// # );
+    /// # ));
     /// #
     /// # let mut ip_addrs = heapless::Vec::<IpCidr, 5>::new();
     /// # ip_addrs
@@ -762,10 +1461,12 @@ impl<'a> Socket<'a> {
     /// #     .ip_addrs(ip_addrs)
     /// #     .finalize(&mut device);
     /// #
+    /// let local_port = iface.ephemeral_port(&sockets).unwrap();
+    /// let socket = sockets.get_mut::<Socket>(handle);
     /// socket.connect(
     ///     iface.context(),
     ///     (IpAddress::v4(10, 0, 0, 1), 80),
-    ///     get_ephemeral_port()
+    ///     local_port
     /// ).unwrap();
     /// # }
     /// ```
@@ -791,7 +1492,7 @@ impl<'a> Socket<'a> {
         if self.is_open() {
             return Err(ConnectError::InvalidState);
         }
-        if remote_endpoint.port == 0 || remote_endpoint.addr.is_unspecified() {
+        if !remote_endpoint.is_specified() {
             return Err(ConnectError::Unaddressable);
         }
         if local_endpoint.port == 0 {
@@ -819,18 +1520,24 @@ impl<'a> Socket<'a> {
         }
 
         self.reset();
+        self.address_lost = false;
         self.tuple = Some(Tuple {
             local: local_endpoint,
             remote: remote_endpoint,
         });
         self.set_state(State::SynSent);
 
-        let seq = Self::random_seq_no(cx);
+        let seq = Self::initial_seq_no(cx, local_endpoint, remote_endpoint);
         self.local_seq_no = seq;
         self.remote_last_seq = seq;
         Ok(())
     }
 
+    fn initial_seq_no(cx: &mut Context, local: IpEndpoint, remote: IpEndpoint) -> TcpSeqNumber {
+        cx.tcp_iss(local, remote)
+            .unwrap_or_else(|| Self::random_seq_no(cx))
+    }
+
     #[cfg(test)]
     fn random_seq_no(_cx: &mut Context) -> TcpSeqNumber {
         TcpSeqNumber(10000)
@@ -880,6 +1587,44 @@ impl<'a> Socket<'a> {
         self.set_state(State::Closed);
     }
 
+    /// Return whether the connection was aborted because its local address was removed
+    /// from the interface.
+    ///
+    /// This is reset when the socket is connected or starts listening again.
+    /// See [Interface::rebind_sockets](../../iface/struct.Interface.html#method.rebind_sockets).
+    pub fn is_address_lost(&self) -> bool {
+        self.address_lost
+    }
+
+    /// Handle the removal of the local address of the connection from the interface,
+    /// and return whether the socket was affected.
+    pub(crate) fn rebind(&mut self, cx: &mut Context, policy: RebindPolicy) -> bool {
+        let tuple = match self.tuple {
+            Some(tuple) if !cx.has_ip_addr(tuple.local.addr) => tuple,
+            _ => return false,
+        };
+        net_debug!("local address {} was removed", tuple.local.addr);
+
+        // The connection cannot continue from another address, and a reset packet sent from
+        // the removed address would likely not reach the remote endpoint, so just forget it.
+        let passive = self.listen_endpoint.port != 0;
+        if passive && self.state == State::SynReceived {
+            self.tuple = None;
+            self.set_state(State::Listen);
+            return true;
+        }
+        self.set_state(State::Closed);
+        self.reset();
+        if policy == RebindPolicy::Reconnect
+            && !passive
+            && self.connect(cx, tuple.remote, tuple.local.port).is_ok()
+        {
+            return true;
+        }
+        self.address_lost = true;
+        true
+    }
+
     /// Return whether the socket is passively listening for incoming connections.
     ///
     /// In terms of the TCP state machine, the socket must be in the `LISTEN` state.
@@ -973,13 +1718,48 @@ impl<'a> Socket<'a> {
 
     /// Check whether the transmit half of the full-duplex connection is open
     /// (see [may_send](#method.may_send)), and the transmit buffer is not full.
+    ///
+    /// If [watermarks](#method.set_send_watermarks) are set, this also checks that the
+    /// transmit buffer has drained below the low watermark since it last reached
+    /// the high one.
     #[inline]
     pub fn can_send(&self) -> bool {
         if !self.may_send() {
             return false;
         }
 
-        !self.tx_buffer.is_full()
+        !self.tx_buffer.is_full() && !self.tx_throttled
+    }
+
+    /// Return the low and high watermarks of the transmit buffer.
+    ///
+    /// See also the [set_send_watermarks](#method.set_send_watermarks) method.
+    pub fn send_watermarks(&self) -> Option<(usize, usize)> {
+        self.tx_watermarks
+    }
+
+    /// Set the low and high watermarks of the transmit buffer, in octets.
+    ///
+    /// Once the amount of octets queued in the transmit buffer reaches the high watermark,
+    /// [can_send](#method.can_send) returns false, and the task registered with
+    /// [register_send_waker](#method.register_send_waker) is not woken up, until enough
+    /// octets were acknowledged for it to drop to the low watermark. This allows producing
+    /// data in batches while the previous ones are in flight. Watermarks do not limit
+    /// the amount of octets that [send](#method.send) may enqueue.
+    ///
+    /// By default, no watermarks are set.
+    ///
+    /// # Panics
+    /// This function panics if the low watermark is greater than the high one.
+    pub fn set_send_watermarks(&mut self, watermarks: Option<(usize, usize)>) {
+        if let Some((low, high)) = watermarks {
+            assert!(low <= high, "low watermark greater than high watermark");
+        }
+        self.tx_watermarks = watermarks;
+        self.tx_throttled = match watermarks {
+            Some((_, high)) => self.tx_buffer.len() >= high,
+            None => false,
+        };
     }
 
     /// Return the maximum number of bytes inside the recv buffer.
@@ -994,6 +1774,33 @@ impl<'a> Socket<'a> {
         self.tx_buffer.capacity()
     }
 
+    /// Return the number of bytes of receive buffer storage used and committed by the socket.
+    #[inline]
+    pub fn recv_memory_usage(&self) -> MemoryUsage {
+        self.rx_buffer.memory_usage()
+    }
+
+    /// Return the number of bytes of transmit buffer storage used and committed by the socket.
+    #[inline]
+    pub fn send_memory_usage(&self) -> MemoryUsage {
+        self.tx_buffer.memory_usage()
+    }
+
+    /// Return the counters of the traffic of the socket.
+    ///
+    /// The counters are kept when the socket is reset, aborted, or reused for another
+    /// connection.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Reset the counters of the traffic of the socket.
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+
     /// Check whether the receive half of the full-duplex connection buffer is open
     /// (see [may_recv](#method.may_recv)), and the receive buffer is not empty.
     #[inline]
@@ -1012,6 +1819,8 @@ impl<'a> Socket<'a> {
         if !self.may_send() {
             return Err(SendError::InvalidState);
         }
+        // Enqueueing octets uses up, or moves, the space of a pending reservation.
+        self.tx_reserved = 0;
 
         // The connection might have been idle for a long time, and so remote_last_ts
         // would be far in the past. Unless we clear it here, we'll abort the connection
@@ -1020,14 +1829,21 @@ impl<'a> Socket<'a> {
             self.remote_last_ts = None
         }
 
-        let _old_length = self.tx_buffer.len();
+        let old_length = self.tx_buffer.len();
         let (size, result) = f(&mut self.tx_buffer);
+        // `f` may return a borrow of the transmit buffer, so its new length is
+        // computed rather than read back.
+        if let Some((_, high)) = self.tx_watermarks {
+            if old_length + size >= high {
+                self.tx_throttled = true;
+            }
+        }
         if size > 0 {
             #[cfg(any(test, feature = "verbose"))]
             tcp_trace!(
                 "tx buffer: enqueueing {} octets (now {})",
                 size,
-                _old_length + size
+                old_length + size
             );
         }
         Ok(result)
@@ -1045,9 +1861,51 @@ impl<'a> Socket<'a> {
         self.send_impl(|tx_buffer| tx_buffer.enqueue_many_with(f))
     }
 
-    /// Enqueue a sequence of octets to be sent, and fill it from a slice.
+    /// Return up to `size` contiguous octets of free space in the transmit buffer, to be
+    /// filled in place and enqueued later with [send_commit](#method.send_commit).
     ///
-    /// This function returns the amount of octets actually enqueued, which is limited
+    /// Nothing is enqueued until `send_commit` is called, so a reservation is rolled back
+    /// by not committing it. The returned slice may be shorter than `size` if the free space
+    /// wraps around the end of the buffer; however, if the transmit buffer is empty, it is
+    /// rewound first, so that a reservation may span its whole capacity. This allows e.g.
+    /// encrypting a TLS record directly into the transmit buffer.
+    ///
+    /// This function returns `Err(SendError::InvalidState)` if the transmit half of
+    /// the connection is not open; see [may_send](#method.may_send).
+    pub fn send_reserve(&mut self, size: usize) -> Result<&mut [u8], SendError> {
+        if !self.may_send() {
+            return Err(SendError::InvalidState);
+        }
+
+        if self.tx_buffer.is_empty() {
+            self.tx_buffer.clear();
+        }
+        let reserved = self.tx_buffer.get_unallocated(0, size);
+        self.tx_reserved = reserved.len();
+        Ok(reserved)
+    }
+
+    /// Enqueue the first `size` octets of the space returned by
+    /// [send_reserve](#method.send_reserve), and return the amount of octets actually
+    /// enqueued, which is limited by the length of that space.
+    ///
+    /// Any other call enqueueing octets in between cancels the reservation, in which case
+    /// nothing is enqueued.
+    ///
+    /// This function returns `Err(SendError::InvalidState)` if the transmit half of
+    /// the connection is not open; see [may_send](#method.may_send).
+    pub fn send_commit(&mut self, size: usize) -> Result<usize, SendError> {
+        let reserved = self.tx_reserved;
+        self.send_impl(|tx_buffer| {
+            let size = cmp::min(size, reserved);
+            tx_buffer.enqueue_unallocated(size);
+            (size, size)
+        })
+    }
+
+    /// Enqueue a sequence of octets to be sent, and fill it from a slice.
+    ///
+    /// This function returns the amount of octets actually enqueued, which is limited
     /// by the amount of free space in the transmit buffer; down to zero.
     ///
     /// See also [send](#method.send).
@@ -1148,6 +2006,30 @@ impl<'a> Socket<'a> {
         Ok(buffer.len())
     }
 
+    /// Dequeue up to `size` received octets, e.g. after looking at them with
+    /// [peek](#method.peek), and return the amount of octets actually dequeued.
+    ///
+    /// Together with `peek`, this allows parsing a record in place, and only removing it
+    /// from the receive buffer once it was processed.
+    ///
+    /// This function otherwise behaves identically to [recv](#method.recv).
+    pub fn recv_consume(&mut self, size: usize) -> Result<usize, RecvError> {
+        self.recv_impl(|rx_buffer| {
+            let size = cmp::min(size, rx_buffer.len());
+            rx_buffer.dequeue_allocated(size);
+            (size, size)
+        })
+    }
+
+    /// Check whether at least `size` octets can be dequeued from the receive buffer,
+    /// e.g. a whole TLS record whose length was read from its header.
+    ///
+    /// Note that the octets may not be contiguous; see [peek_slice](#method.peek_slice).
+    #[inline]
+    pub fn can_recv_len(&self, size: usize) -> bool {
+        self.may_recv() && self.rx_buffer.len() >= size
+    }
+
     /// Return the amount of octets queued in the transmit buffer.
     ///
     /// Note that the Berkeley sockets interface does not have an equivalent of this API.
@@ -1167,6 +2049,13 @@ impl<'a> Socket<'a> {
     fn set_state(&mut self, state: State) {
         if self.state != state {
             tcp_trace!("state={}=>{}", self.state, state);
+            #[cfg(feature = "socket-tcp-history")]
+            self.record(HistoryEvent::State {
+                from: self.state,
+                to: state,
+            });
+            #[cfg(feature = "tracing")]
+            tracing::debug!(from = %self.state, to = %state, "state transition");
         }
 
         self.state = state;
@@ -1181,11 +2070,36 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Return the most recent significant events of this socket, oldest first.
+    ///
+    /// At most [HISTORY_LEN] events are kept. The history is preserved when the socket
+    /// is closed or reused for another connection; see [clear_history](#method.clear_history).
+    #[cfg(feature = "socket-tcp-history")]
+    pub fn history(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.history.oldest_ordered()
+    }
+
+    /// Forget all events recorded in the history of this socket.
+    #[cfg(feature = "socket-tcp-history")]
+    pub fn clear_history(&mut self) {
+        self.history.clear()
+    }
+
+    #[cfg(feature = "socket-tcp-history")]
+    fn record(&mut self, event: HistoryEvent) {
+        self.history.write(HistoryEntry {
+            timestamp: self.history_now,
+            event,
+        })
+    }
+
     pub(crate) fn reply(ip_repr: &IpRepr, repr: &TcpRepr) -> (IpRepr, TcpRepr<'static>) {
         let reply_repr = TcpRepr {
             src_port: repr.dst_port,
             dst_port: repr.src_port,
             control: TcpControl::None,
+            ece: false,
+            cwr: false,
             seq_number: TcpSeqNumber(0),
             ack_number: None,
             window_len: 0,
@@ -1193,6 +2107,8 @@ impl<'a> Socket<'a> {
             max_seg_size: None,
             sack_permitted: false,
             sack_ranges: [None, None, None],
+            timestamp: None,
+            md5_signature: None,
             payload: &[],
         };
         let ip_reply_repr = IpRepr::new(
@@ -1221,9 +2137,26 @@ impl<'a> Socket<'a> {
         (ip_reply_repr, reply_repr)
     }
 
-    fn ack_reply(&mut self, ip_repr: &IpRepr, repr: &TcpRepr) -> (IpRepr, TcpRepr<'static>) {
+    /// Return the value of the timestamp clock, which ticks every millisecond.
+    fn tsval(cx: &Context) -> u32 {
+        cx.now().total_millis() as u32
+    }
+
+    fn ack_reply(
+        &mut self,
+        cx: &mut Context,
+        ip_repr: &IpRepr,
+        repr: &TcpRepr,
+    ) -> (IpRepr, TcpRepr<'static>) {
         let (mut ip_reply_repr, mut reply_repr) = Self::reply(ip_repr, repr);
 
+        if self.remote_has_timestamp {
+            reply_repr.timestamp = Some((Self::tsval(cx), self.remote_last_tsval));
+        }
+        if self.remote_has_ecn {
+            reply_repr.ece = self.ecn_echo;
+        }
+
         // From RFC 793:
         // [...] an empty acknowledgment segment containing the current send-sequence number
         // and an acknowledgment indicating the next sequence number expected
@@ -1291,7 +2224,7 @@ impl<'a> Socket<'a> {
         // Rate-limit to 1 per second max.
         self.challenge_ack_timer = cx.now() + Duration::from_secs(1);
 
-        return Some(self.ack_reply(ip_repr, repr));
+        return Some(self.ack_reply(cx, ip_repr, repr));
     }
 
     pub(crate) fn accepts(&self, _cx: &mut Context, ip_repr: &IpRepr, repr: &TcpRepr) -> bool {
@@ -1314,22 +2247,134 @@ impl<'a> Socket<'a> {
                 && repr.src_port == tuple.remote.port
         } else {
             // We're listening, reject packets not matching the listen endpoint.
-            let addr_ok = match self.listen_endpoint.addr {
-                Some(addr) => ip_repr.dst_addr() == addr,
-                None => true,
-            };
-            addr_ok && repr.dst_port != 0 && repr.dst_port == self.listen_endpoint.port
+            let local = IpEndpoint::new(ip_repr.dst_addr(), repr.dst_port);
+            self.listen_endpoint.matches(&local)
+        }
+    }
+
+    /// Query whether the socket listened on the local endpoint of a packet, even if it
+    /// has accepted a connection since.
+    pub(crate) fn listens_on(&self, ip_repr: &IpRepr, repr: &TcpRepr) -> bool {
+        let local = IpEndpoint::new(ip_repr.dst_addr(), repr.dst_port);
+        self.listen_endpoint.port != 0 && self.listen_endpoint.matches(&local)
+    }
+
+    /// Return how specific the endpoint the socket accepts packets on is, for choosing
+    /// between several sockets accepting the same packet, the higher the better.
+    ///
+    /// A connection is more specific than any listening endpoint.
+    pub(crate) fn specificity(&self) -> u8 {
+        match self.tuple {
+            Some(_) => u8::MAX,
+            None => self.listen_endpoint.specificity(),
+        }
+    }
+
+    /// Query whether the MD5 signature of a packet is the one expected with the key
+    /// of the socket, if any.
+    #[cfg(feature = "socket-tcp-md5")]
+    pub(crate) fn md5_verify(
+        &self,
+        ip_repr: &IpRepr,
+        repr: &TcpRepr,
+        packet: &TcpPacket<&[u8]>,
+    ) -> bool {
+        match self.md5_key {
+            Some(key) => {
+                let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
+                repr.md5_signature == Some(packet.compute_md5_signature(&src_addr, &dst_addr, key))
+            }
+            None => true,
         }
     }
 
+    /// Add the MD5 signature option to a segment, if the socket has a key.
+    #[cfg(feature = "socket-tcp-md5")]
+    fn md5_sign(&self, ip_repr: &IpRepr, repr: &mut TcpRepr) {
+        let key = match self.md5_key {
+            Some(key) => key,
+            None => return,
+        };
+        repr.md5_signature = Some([0; 16]);
+        // Drop the SACK blocks that no longer fit in the 40 octets of options.
+        while repr.header_len() > TCP_HEADER_LEN + 40 {
+            match repr
+                .sack_ranges
+                .iter_mut()
+                .rev()
+                .find(|range| range.is_some())
+            {
+                Some(range) => *range = None,
+                None => break,
+            }
+        }
+        let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
+        repr.md5_signature = Some(repr.compute_md5_signature(&src_addr, &dst_addr, key));
+    }
+
+    /// Return the length of the options added to every segment, which is taken from
+    /// the data that fits in a segment.
+    fn options_overhead(&self) -> usize {
+        #[cfg(feature = "socket-tcp-md5")]
+        if self.md5_key.is_some() {
+            // The signature option, padded to a multiple of 4 octets.
+            return 20;
+        }
+        0
+    }
+
+    #[allow(clippy::let_and_return)]
     pub(crate) fn process(
         &mut self,
         cx: &mut Context,
         ip_repr: &IpRepr,
         repr: &TcpRepr,
+    ) -> Option<(IpRepr, TcpRepr<'static>)> {
+        let reply = self.process_segment(cx, ip_repr, repr);
+        #[cfg(feature = "socket-tcp-md5")]
+        let reply = reply.map(|(ip_repr, mut repr)| {
+            self.md5_sign(&ip_repr, &mut repr);
+            (ip_repr, repr)
+        });
+        reply
+    }
+
+    fn process_segment(
+        &mut self,
+        cx: &mut Context,
+        ip_repr: &IpRepr,
+        repr: &TcpRepr,
     ) -> Option<(IpRepr, TcpRepr<'static>)> {
         debug_assert!(self.accepts(cx, ip_repr, repr));
 
+        #[cfg(feature = "socket-tcp-history")]
+        {
+            self.history_now = cx.now();
+        }
+
+        let _span = net_span!(
+            "tcp",
+            local = %self.listen_endpoint,
+            state = %self.state,
+            local_seq = %self.local_seq_no,
+            remote_seq = %self.remote_seq_no,
+            remote_win = self.remote_win_len
+        );
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            src = %ip_repr.src_addr(),
+            src_port = repr.src_port,
+            control = ?repr.control,
+            seq = %repr.seq_number,
+            ack = ?repr.ack_number.map(|ack| ack.0),
+            window = repr.window_len,
+            len = repr.payload.len(),
+            "received segment"
+        );
+
+        #[cfg(feature = "stats")]
+        self.stats.record_rx(repr.payload.len());
+
         // Consider how much the sequence number space differs from the transmit buffer space.
         let (sent_syn, sent_fin) = match self.state {
             // In SYN-SENT or SYN-RECEIVED, we've just sent a SYN.
@@ -1362,6 +2407,17 @@ impl<'a> Socket<'a> {
             (State::Listen, _, None) => (),
             // This case is handled in `accepts()`.
             (State::Listen, _, Some(_)) => unreachable!(),
+            // A retransmitted SYN in the SYN-RECEIVED state means that our SYN|ACK was lost,
+            // so send it again right away instead of waiting for the retransmit timer.
+            (State::SynReceived, TcpControl::Syn, None) => {
+                if repr.seq_number + 1 == self.remote_seq_no {
+                    net_debug!("duplicate SYN, retransmitting SYN|ACK");
+                    self.remote_last_seq = self.local_seq_no;
+                } else {
+                    net_debug!("unacceptable SYN with a different initial sequence number");
+                }
+                return None;
+            }
             // Every packet after the initial SYN must be an acknowledgement.
             (_, _, None) => {
                 net_debug!("expecting an ACK");
@@ -1439,6 +2495,22 @@ impl<'a> Socket<'a> {
             }
         }
 
+        // RFC 7323 § 5.3: A segment with a timestamp older than the most recent one is
+        // an old duplicate, which could otherwise be mistaken for new data once the sequence
+        // numbers have wrapped around.
+        if self.remote_has_timestamp && repr.control != TcpControl::Rst {
+            if let Some((tsval, _)) = repr.timestamp {
+                if (tsval.wrapping_sub(self.remote_last_tsval) as i32) < 0 {
+                    net_debug!(
+                        "segment failed PAWS check (timestamp {} older than {})",
+                        tsval,
+                        self.remote_last_tsval
+                    );
+                    return self.challenge_ack_reply(cx, ip_repr, repr);
+                }
+            }
+        }
+
         let window_start = self.remote_seq_no + self.rx_buffer.len();
         let window_end = self.remote_seq_no + self.rx_buffer.capacity();
         let segment_start = repr.seq_number;
@@ -1479,6 +2551,17 @@ impl<'a> Socket<'a> {
                     // We've checked that segment_start >= window_start above.
                     payload_offset = segment_start - window_start;
                     self.local_rx_last_seq = Some(repr.seq_number);
+
+                    // Echo the timestamp of the segment acknowledged next, as opposed to
+                    // that of a later out-of-order segment.
+                    if let Some((tsval, _)) = repr.timestamp {
+                        let covers_last_ack = self
+                            .remote_last_ack
+                            .map_or(true, |last_ack| segment_start <= last_ack);
+                        if self.remote_has_timestamp && covers_last_ack {
+                            self.remote_last_tsval = tsval;
+                        }
+                    }
                 } else {
                     // If we're in the TIME-WAIT state, restart the TIME-WAIT timeout, since
                     // the remote end may not have realized we've closed the connection.
@@ -1517,7 +2600,23 @@ impl<'a> Socket<'a> {
                     ack_all = self.remote_last_seq == ack_number
                 }
 
-                self.rtte.on_ack(cx.now(), ack_number);
+                match repr.timestamp {
+                    // RFC 7323 § 4: With timestamps, any acknowledgement of new data yields
+                    // a round-trip time sample, even for a retransmitted segment.
+                    Some((_, tsecr)) if self.remote_has_timestamp && ack_len > 0 => {
+                        let rtt = Self::tsval(cx).wrapping_sub(tsecr);
+                        if (rtt as i32) >= 0 {
+                            self.rtte.sample(rtt);
+                        }
+                    }
+                    _ => self.rtte.on_ack(cx.now(), ack_number),
+                }
+
+                if ack_len > 0 {
+                    self.congestion_controller
+                        .inner_mut()
+                        .on_ack(cx.now(), ack_len, &self.rtte);
+                }
             }
         }
 
@@ -1555,6 +2654,15 @@ impl<'a> Socket<'a> {
             // SYN packets in the LISTEN state change it to SYN-RECEIVED.
             (State::Listen, TcpControl::Syn) => {
                 tcp_trace!("received SYN");
+                if self.syn_rate_exceeded(cx.now()) {
+                    net_debug!("SYN rate limit exceeded");
+                    return match self.syn_rate_limit {
+                        Some(SynRateLimit { reset: true, .. }) => {
+                            Some(Self::rst_reply(ip_repr, repr))
+                        }
+                        _ => None,
+                    };
+                }
                 if let Some(max_seg_size) = repr.max_seg_size {
                     if max_seg_size == 0 {
                         tcp_trace!("received SYNACK with zero MSS, ignoring");
@@ -1563,27 +2671,43 @@ impl<'a> Socket<'a> {
                     self.remote_mss = max_seg_size as usize
                 }
 
-                self.tuple = Some(Tuple {
-                    local: IpEndpoint::new(ip_repr.dst_addr(), repr.dst_port),
-                    remote: IpEndpoint::new(ip_repr.src_addr(), repr.src_port),
-                });
-                self.local_seq_no = Self::random_seq_no(cx);
+                let local = IpEndpoint::new(ip_repr.dst_addr(), repr.dst_port);
+                let remote = IpEndpoint::new(ip_repr.src_addr(), repr.src_port);
+                self.tuple = Some(Tuple { local, remote });
+                self.local_seq_no = Self::initial_seq_no(cx, local, remote);
                 self.remote_seq_no = repr.seq_number + 1;
                 self.remote_last_seq = self.local_seq_no;
                 self.remote_has_sack = repr.sack_permitted;
+                self.remote_has_timestamp = self.timestamps && repr.timestamp.is_some();
+                self.remote_last_tsval = repr.timestamp.map_or(0, |(tsval, _)| tsval);
+                // RFC 3168 § 6.1.1: An ECN-setup SYN carries both the ECE and CWR flags.
+                self.remote_has_ecn = self.ecn && repr.ece && repr.cwr;
+                self.congestion_controller
+                    .inner_mut()
+                    .set_mss(self.remote_mss);
                 self.remote_win_scale = repr.window_scale;
                 // Remote doesn't support window scaling, don't do it.
                 if self.remote_win_scale.is_none() {
                     self.remote_win_shift = 0;
                 }
+                self.syn_ack_retries_sent = 0;
                 self.set_state(State::SynReceived);
-                self.timer.set_for_idle(cx.now(), self.keep_alive);
+                self.timer.set_for_idle(cx.now(), self.keep_alive_first());
+            }
+
+            // ACK packets without data in the SYN-RECEIVED state are ignored while
+            // accepting is deferred.
+            (State::SynReceived, TcpControl::None)
+                if repr.payload.is_empty() && self.accept_deferred(cx.now()) =>
+            {
+                tcp_trace!("deferring accept until data arrives");
+                return None;
             }
 
             // ACK packets in the SYN-RECEIVED state change it to ESTABLISHED.
             (State::SynReceived, TcpControl::None) => {
                 self.set_state(State::Established);
-                self.timer.set_for_idle(cx.now(), self.keep_alive);
+                self.timer.set_for_idle(cx.now(), self.keep_alive_first());
             }
 
             // FIN packets in the SYN-RECEIVED state change it to CLOSE-WAIT.
@@ -1593,7 +2717,7 @@ impl<'a> Socket<'a> {
                 self.remote_seq_no += 1;
                 self.rx_fin_received = true;
                 self.set_state(State::CloseWait);
-                self.timer.set_for_idle(cx.now(), self.keep_alive);
+                self.timer.set_for_idle(cx.now(), self.keep_alive_first());
             }
 
             // SYN|ACK packets in the SYN-SENT state change it to ESTABLISHED.
@@ -1611,20 +2735,27 @@ impl<'a> Socket<'a> {
                 self.remote_last_seq = self.local_seq_no + 1;
                 self.remote_last_ack = Some(repr.seq_number);
                 self.remote_win_scale = repr.window_scale;
+                self.remote_has_timestamp = self.timestamps && repr.timestamp.is_some();
+                self.remote_last_tsval = repr.timestamp.map_or(0, |(tsval, _)| tsval);
+                // RFC 3168 § 6.1.1: An ECN-setup SYN|ACK only carries the ECE flag.
+                self.remote_has_ecn = self.ecn && repr.ece && !repr.cwr;
+                self.congestion_controller
+                    .inner_mut()
+                    .set_mss(self.remote_mss);
                 // Remote doesn't support window scaling, don't do it.
                 if self.remote_win_scale.is_none() {
                     self.remote_win_shift = 0;
                 }
 
                 self.set_state(State::Established);
-                self.timer.set_for_idle(cx.now(), self.keep_alive);
+                self.timer.set_for_idle(cx.now(), self.keep_alive_first());
             }
 
             // ACK packets in ESTABLISHED state reset the retransmit timer,
             // except for duplicate ACK packets which preserve it.
             (State::Established, TcpControl::None) => {
                 if !self.timer.is_retransmit() || ack_all {
-                    self.timer.set_for_idle(cx.now(), self.keep_alive);
+                    self.timer.set_for_idle(cx.now(), self.keep_alive_first());
                 }
             }
 
@@ -1633,7 +2764,7 @@ impl<'a> Socket<'a> {
                 self.remote_seq_no += 1;
                 self.rx_fin_received = true;
                 self.set_state(State::CloseWait);
-                self.timer.set_for_idle(cx.now(), self.keep_alive);
+                self.timer.set_for_idle(cx.now(), self.keep_alive_first());
             }
 
             // ACK packets in FIN-WAIT-1 state change it to FIN-WAIT-2, if we've already
@@ -1643,7 +2774,7 @@ impl<'a> Socket<'a> {
                     self.set_state(State::FinWait2);
                 }
                 if ack_all {
-                    self.timer.set_for_idle(cx.now(), self.keep_alive);
+                    self.timer.set_for_idle(cx.now(), self.keep_alive_first());
                 }
             }
 
@@ -1657,13 +2788,13 @@ impl<'a> Socket<'a> {
                     self.timer.set_for_close(cx.now());
                 } else {
                     self.set_state(State::Closing);
-                    self.timer.set_for_idle(cx.now(), self.keep_alive);
+                    self.timer.set_for_idle(cx.now(), self.keep_alive_first());
                 }
             }
 
             // Data packets in FIN-WAIT-2 reset the idle timer.
             (State::FinWait2, TcpControl::None) => {
-                self.timer.set_for_idle(cx.now(), self.keep_alive);
+                self.timer.set_for_idle(cx.now(), self.keep_alive_first());
             }
 
             // FIN packets in FIN-WAIT-2 state change it to TIME-WAIT.
@@ -1680,13 +2811,13 @@ impl<'a> Socket<'a> {
                     self.set_state(State::TimeWait);
                     self.timer.set_for_close(cx.now());
                 } else {
-                    self.timer.set_for_idle(cx.now(), self.keep_alive);
+                    self.timer.set_for_idle(cx.now(), self.keep_alive_first());
                 }
             }
 
             // ACK packets in CLOSE-WAIT state reset the retransmit timer.
             (State::CloseWait, TcpControl::None) => {
-                self.timer.set_for_idle(cx.now(), self.keep_alive);
+                self.timer.set_for_idle(cx.now(), self.keep_alive_first());
             }
 
             // ACK packets in LAST-ACK state change it to CLOSED.
@@ -1696,7 +2827,7 @@ impl<'a> Socket<'a> {
                     self.set_state(State::Closed);
                     self.tuple = None;
                 } else {
-                    self.timer.set_for_idle(cx.now(), self.keep_alive);
+                    self.timer.set_for_idle(cx.now(), self.keep_alive_first());
                 }
             }
 
@@ -1708,6 +2839,7 @@ impl<'a> Socket<'a> {
 
         // Update remote state.
         self.remote_last_ts = Some(cx.now());
+        self.keep_alive_probes_sent = 0;
 
         // RFC 1323: The window field (SEG.WND) in the header of every incoming segment, with the
         // exception of SYN segments, is left-shifted by Snd.Wind.Scale bits before updating SND.WND.
@@ -1715,7 +2847,17 @@ impl<'a> Socket<'a> {
             TcpControl::Syn => 0,
             _ => self.remote_win_scale.unwrap_or(0),
         };
-        self.remote_win_len = (repr.window_len as usize) << (scale as usize);
+        let remote_win_len = (repr.window_len as usize) << (scale as usize);
+        #[cfg(feature = "socket-tcp-history")]
+        if self.remote_win_len != remote_win_len {
+            self.record(HistoryEvent::RemoteWindow {
+                len: remote_win_len,
+            });
+        }
+        self.remote_win_len = remote_win_len;
+        self.congestion_controller
+            .inner_mut()
+            .set_remote_window(remote_win_len);
 
         if ack_len > 0 {
             // Dequeue acknowledged octets.
@@ -1727,9 +2869,18 @@ impl<'a> Socket<'a> {
             );
             self.tx_buffer.dequeue_allocated(ack_len);
 
-            // There's new room available in tx_buffer, wake the waiting task if any.
+            if let Some((low, _)) = self.tx_watermarks {
+                if self.tx_buffer.len() <= low {
+                    self.tx_throttled = false;
+                }
+            }
+
+            // There's new room available in tx_buffer, wake the waiting task if any,
+            // unless it has to wait for the low watermark.
             #[cfg(feature = "async")]
-            self.tx_waker.wake();
+            if !self.tx_throttled {
+                self.tx_waker.wake();
+            }
         }
 
         if let Some(ack_number) = repr.ack_number {
@@ -1763,6 +2914,10 @@ impl<'a> Socket<'a> {
                         }
                     );
 
+                    self.congestion_controller
+                        .inner_mut()
+                        .on_duplicate_ack(cx.now(), self.local_rx_dup_acks);
+
                     if self.local_rx_dup_acks == 3 {
                         self.timer.set_for_fast_retransmit();
                         net_debug!("started fast retransmit");
@@ -1788,6 +2943,14 @@ impl<'a> Socket<'a> {
             if self.remote_last_seq < self.local_seq_no {
                 self.remote_last_seq = self.local_seq_no
             }
+
+            if self.remote_has_sack {
+                self.process_sack_ranges(&repr.sack_ranges);
+            }
+        }
+
+        if self.remote_has_ecn && repr.control != TcpControl::Syn {
+            self.process_ecn(cx, ip_repr, repr);
         }
 
         let payload_len = repr.payload.len();
@@ -1831,6 +2994,7 @@ impl<'a> Socket<'a> {
                 self.rx_buffer.len() + contig_len
             );
             self.rx_buffer.enqueue_unallocated(contig_len);
+            self.autotune_rx_window(cx.now());
 
             // There's new data in rx_buffer, notify waiting task if any.
             #[cfg(feature = "async")]
@@ -1874,7 +3038,7 @@ impl<'a> Socket<'a> {
             // This is fine because smoltcp assumes that it can always transmit zero or one
             // packets for every packet it receives.
             tcp_trace!("ACKing incoming segment");
-            Some(self.ack_reply(ip_repr, repr))
+            Some(self.ack_reply(cx, ip_repr, repr))
         } else {
             None
         }
@@ -1887,6 +3051,121 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Return when a half-open connection is abandoned, if the current state has a timeout.
+    fn half_open_expires_at(&self) -> Option<Instant> {
+        let timeout = match self.state {
+            State::SynReceived => self.syn_received_timeout,
+            State::FinWait2 => self.fin_wait_2_timeout,
+            _ => None,
+        };
+        Some(self.remote_last_ts? + timeout?)
+    }
+
+    fn half_open_timed_out(&self, timestamp: Instant) -> bool {
+        match self.half_open_expires_at() {
+            Some(expires_at) => timestamp >= expires_at,
+            None => false,
+        }
+    }
+
+    fn syn_ack_retries_exhausted(&self, timestamp: Instant) -> bool {
+        match self.syn_ack_retries {
+            Some(retries) => {
+                self.state == State::SynReceived
+                    && self.timer.should_retransmit(timestamp).is_some()
+                    && self.syn_ack_retries_sent >= retries
+            }
+            None => false,
+        }
+    }
+
+    fn keep_alive_exhausted(&self, timestamp: Instant) -> bool {
+        match self.keep_alive_probes {
+            Some(probes) => {
+                self.timer.should_keep_alive(timestamp) && self.keep_alive_probes_sent >= probes
+            }
+            None => false,
+        }
+    }
+
+    /// Replace the selectively acknowledged ranges with the SACK blocks of the last
+    /// packet received, ignoring the parts outside of the transmit buffer.
+    fn process_sack_ranges(&mut self, sack_ranges: &[Option<(u32, u32)>; 3]) {
+        let buffer_end = self.local_seq_no + self.tx_buffer.len();
+        for (sacked, range) in self.remote_sacked.iter_mut().zip(sack_ranges.iter()) {
+            *sacked = range.and_then(|(left, right)| {
+                let (mut left, mut right) = (TcpSeqNumber(left as i32), TcpSeqNumber(right as i32));
+                if left < self.local_seq_no {
+                    left = self.local_seq_no;
+                }
+                if right > buffer_end {
+                    right = buffer_end;
+                }
+                (left < right).then_some((left, right))
+            });
+        }
+    }
+
+    /// React to the ECN codepoint and flags of a segment received on a connection using ECN,
+    /// as described in RFC 3168 § 6.1.
+    fn process_ecn(&mut self, cx: &Context, ip_repr: &IpRepr, repr: &TcpRepr) {
+        // Congestion experienced marks are echoed until the remote reacts to them.
+        if repr.cwr {
+            self.ecn_echo = false;
+        }
+        if ip_repr.traffic_class() & 0x03 == ECN_CE {
+            if !self.ecn_echo {
+                net_debug!("received congestion experienced mark");
+            }
+            self.ecn_echo = true;
+        }
+
+        let ack_number = match repr.ack_number {
+            Some(ack_number) if repr.ece => ack_number,
+            _ => return,
+        };
+        // The congestion window is reduced at most once per window of data.
+        if matches!(self.ecn_recover, Some(recover) if ack_number <= recover) {
+            return;
+        }
+        net_debug!("received ECN echo, reducing congestion window");
+        self.congestion_controller.inner_mut().on_ecn_echo(cx.now());
+        self.ecn_recover = Some(self.remote_last_seq);
+        self.ecn_cwr = true;
+    }
+
+    /// Return the first sequence number at or after `seq` that was not selectively
+    /// acknowledged, and how many octets starting from it can be sent before reaching
+    /// a range that was.
+    fn next_unsacked_seq(&self, mut seq: TcpSeqNumber) -> (TcpSeqNumber, usize) {
+        // The ranges are not sorted, and may be adjacent to each other.
+        while let Some(&(_, right)) = self
+            .remote_sacked
+            .iter()
+            .flatten()
+            .find(|(left, right)| *left <= seq && seq < *right)
+        {
+            seq = right;
+        }
+
+        let limit = self
+            .remote_sacked
+            .iter()
+            .flatten()
+            .filter(|(left, _)| *left > seq)
+            .map(|(left, _)| *left - seq)
+            .min()
+            .unwrap_or(usize::MAX);
+        (seq, limit)
+    }
+
+    /// Return the number of octets after local_seq_no we may send, limited by both
+    /// the remote window and the congestion window.
+    fn send_window(&self) -> usize {
+        let congestion_window = self.congestion_controller.inner().window();
+        cmp::min(self.remote_win_len, congestion_window)
+    }
+
     fn seq_to_transmit(&self, cx: &mut Context) -> bool {
         let ip_header_len = match self.tuple.unwrap().local.addr {
             #[cfg(feature = "proto-ipv4")]
@@ -1896,7 +3175,7 @@ impl<'a> Socket<'a> {
         };
 
         // Max segment size we're able to send due to MTU limitations.
-        let local_mss = cx.ip_mtu() - ip_header_len - TCP_HEADER_LEN;
+        let local_mss = cx.ip_mtu() - ip_header_len - TCP_HEADER_LEN - self.options_overhead();
 
         // The effective max segment size, taking into account our and remote's limits.
         let effective_mss = local_mss.min(self.remote_mss);
@@ -1911,11 +3190,14 @@ impl<'a> Socket<'a> {
 
         // max sequence number we can send.
         let max_send_seq =
-            self.local_seq_no + core::cmp::min(self.remote_win_len, self.tx_buffer.len());
+            self.local_seq_no + core::cmp::min(self.send_window(), self.tx_buffer.len());
+
+        // The next sequence number to send, skipping the selectively acknowledged octets.
+        let (next_seq, _) = self.next_unsacked_seq(self.remote_last_seq);
 
         // Max amount of octets we can send.
-        let max_send = if max_send_seq >= self.remote_last_seq {
-            max_send_seq - self.remote_last_seq
+        let max_send = if max_send_seq >= next_seq {
+            max_send_seq - next_seq
         } else {
             0
         };
@@ -1942,11 +3224,16 @@ impl<'a> Socket<'a> {
             can_send = false;
         }
 
+        // If we're corked, we don't send partial segments at all, unless we're closing send.
+        if self.cork && !can_send_full && !want_fin {
+            can_send = false;
+        }
+
         // Can we actually send the FIN? We can send it if:
         // 1. We have unsent data that fits in the remote window.
         // 2. We have no unsent data.
         // This condition matches only if #2, because #1 is already covered by can_data and we're ORing them.
-        let can_fin = want_fin && self.remote_last_seq == self.local_seq_no + self.tx_buffer.len();
+        let can_fin = want_fin && next_seq == self.local_seq_no + self.tx_buffer.len();
 
         can_send || can_fin
     }
@@ -1978,6 +3265,23 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Return whether the remote window is closed while we have data to send and none
+    /// in flight, in which case the window has to be probed.
+    fn window_to_probe(&self) -> bool {
+        match self.state {
+            State::Established
+            | State::FinWait1
+            | State::Closing
+            | State::CloseWait
+            | State::LastAck => {
+                self.remote_win_len == 0
+                    && self.remote_last_seq == self.local_seq_no
+                    && !self.tx_buffer.is_empty()
+            }
+            _ => false,
+        }
+    }
+
     pub(crate) fn dispatch<F, E>(&mut self, cx: &mut Context, emit: F) -> Result<(), E>
     where
         F: FnOnce(&mut Context, (IpRepr, TcpRepr)) -> Result<(), E>,
@@ -1986,6 +3290,20 @@ impl<'a> Socket<'a> {
             return Ok(());
         }
 
+        let _span = net_span!(
+            "tcp",
+            tuple = %self.tuple.unwrap(),
+            state = %self.state,
+            local_seq = %self.local_seq_no,
+            remote_seq = %self.remote_seq_no,
+            remote_win = self.remote_win_len
+        );
+
+        #[cfg(feature = "socket-tcp-history")]
+        {
+            self.history_now = cx.now();
+        }
+
         if self.remote_last_ts.is_none() {
             // We get here in exactly two cases:
             //  1) This socket just transitioned into SYN-SENT.
@@ -2001,28 +3319,84 @@ impl<'a> Socket<'a> {
         if self.timed_out(cx.now()) {
             // If a timeout expires, we should abort the connection.
             net_debug!("timeout exceeded");
+            #[cfg(feature = "socket-tcp-history")]
+            self.record(HistoryEvent::Timeout);
+            self.set_state(State::Closed);
+        } else if self.keep_alive_exhausted(cx.now()) {
+            // If the remote endpoint did not answer enough keep-alive packets, abort as well.
+            net_debug!("keep-alive probes unanswered");
+            #[cfg(feature = "socket-tcp-history")]
+            self.record(HistoryEvent::Timeout);
             self.set_state(State::Closed);
+        } else if self.half_open_timed_out(cx.now()) {
+            // If the remote endpoint vanished mid-handshake or mid-close, reap the connection.
+            net_debug!("half-open timeout exceeded in state {}", self.state);
+            #[cfg(feature = "socket-tcp-history")]
+            self.record(HistoryEvent::Timeout);
+            if self.state == State::SynReceived {
+                self.tuple = None;
+                self.set_state(State::Listen);
+                return Ok(());
+            }
+            self.set_state(State::Closed);
+        } else if self.syn_ack_retries_exhausted(cx.now()) {
+            // If the remote endpoint never acknowledged our SYN|ACK, give up on it.
+            net_debug!("SYN|ACK retries exhausted");
+            #[cfg(feature = "socket-tcp-history")]
+            self.record(HistoryEvent::Timeout);
+            self.tuple = None;
+            self.set_state(State::Listen);
+            return Ok(());
         } else if !self.seq_to_transmit(cx) {
             if let Some(retransmit_delta) = self.timer.should_retransmit(cx.now()) {
                 // If a retransmit timer expired, we should resend data starting at the last ACK.
                 net_debug!("retransmitting at t+{}", retransmit_delta);
+                #[cfg(feature = "stats")]
+                self.stats.record_retransmission();
+                #[cfg(feature = "socket-tcp-history")]
+                self.record(HistoryEvent::Retransmit {
+                    seq: self.local_seq_no,
+                });
 
                 // Rewind "last sequence number sent", as if we never
                 // had sent them. This will cause all data in the queue
-                // to be sent again.
+                // to be sent again, except for the octets that were selectively
+                // acknowledged if this is a fast retransmit.
                 self.remote_last_seq = self.local_seq_no;
 
+                // RFC 2018 § 8: After a retransmit timeout the data sender SHOULD ignore
+                // prior SACK information, since the receiver may have discarded it.
+                if !matches!(self.timer, Timer::FastRetransmit) {
+                    self.remote_sacked = [None; 3];
+                    self.congestion_controller
+                        .inner_mut()
+                        .on_retransmit(cx.now());
+                }
+
                 // Clear the `should_retransmit` state. If we can't retransmit right
                 // now for whatever reason (like zero window), this avoids an
                 // infinite polling loop where `poll_at` returns `Now` but `dispatch`
                 // can't actually do anything.
-                self.timer.set_for_idle(cx.now(), self.keep_alive);
+                self.timer.set_for_idle(cx.now(), self.keep_alive_first());
 
                 // Inform RTTE, so that it can avoid bogus measurements.
                 self.rtte.on_retransmit();
+
+                if self.state == State::SynReceived {
+                    self.syn_ack_retries_sent = self.syn_ack_retries_sent.saturating_add(1);
+                }
             }
         }
 
+        // Probe a closed remote window, so that a lost window update cannot stall us forever.
+        if self.window_to_probe() {
+            self.timer
+                .set_for_zero_window_probe(cx.now(), self.rtte.retransmission_timeout());
+        } else {
+            self.timer
+                .clear_zero_window_probe(cx.now(), self.keep_alive_first());
+        }
+
         // Decide whether we're sending a packet.
         if self.seq_to_transmit(cx) {
             // If we have data to transmit and it fits into partner's window, do it.
@@ -2036,6 +3410,9 @@ impl<'a> Socket<'a> {
         } else if self.state == State::Closed {
             // If we need to abort the connection, do it.
             tcp_trace!("outgoing segment will abort connection");
+        } else if self.timer.should_zero_window_probe(cx.now()) {
+            // If we need to probe the remote window, do it.
+            tcp_trace!("zero window probe timer expired");
         } else if self.timer.should_keep_alive(cx.now()) {
             // If we need to transmit a keep-alive packet, do it.
             tcp_trace!("keep-alive timer expired");
@@ -2067,6 +3444,8 @@ impl<'a> Socket<'a> {
             src_port: tuple.local.port,
             dst_port: tuple.remote.port,
             control: TcpControl::None,
+            ece: false,
+            cwr: false,
             seq_number: self.remote_last_seq,
             ack_number: Some(self.remote_seq_no + self.rx_buffer.len()),
             window_len: self.scaled_window(),
@@ -2074,6 +3453,8 @@ impl<'a> Socket<'a> {
             max_seg_size: None,
             sack_permitted: false,
             sack_ranges: [None, None, None],
+            timestamp: None,
+            md5_signature: None,
             payload: &[],
         };
 
@@ -2092,14 +3473,17 @@ impl<'a> Socket<'a> {
             State::SynSent | State::SynReceived => {
                 repr.control = TcpControl::Syn;
                 // window len must NOT be scaled in SYNs.
-                repr.window_len = self.rx_buffer.window().min((1 << 16) - 1) as u16;
+                repr.window_len = self.rx_window().min((1 << 16) - 1) as u16;
                 if self.state == State::SynSent {
                     repr.ack_number = None;
                     repr.window_scale = Some(self.remote_win_shift);
                     repr.sack_permitted = true;
+                    repr.ece = self.ecn;
+                    repr.cwr = self.ecn;
                 } else {
                     repr.sack_permitted = self.remote_has_sack;
                     repr.window_scale = self.remote_win_scale.map(|_| self.remote_win_shift);
+                    repr.ece = self.remote_has_ecn;
                 }
             }
 
@@ -2113,12 +3497,16 @@ impl<'a> Socket<'a> {
                 // Extract as much data as the remote side can receive in this packet
                 // from the transmit buffer.
 
+                // Skip the octets the remote has selectively acknowledged.
+                let (seq_number, sack_limit) = self.next_unsacked_seq(self.remote_last_seq);
+                repr.seq_number = seq_number;
+
                 // Right edge of window, ie the max sequence number we're allowed to send.
-                let win_right_edge = self.local_seq_no + self.remote_win_len;
+                let win_right_edge = self.local_seq_no + self.send_window();
 
                 // Max amount of octets we're allowed to send according to the remote window.
-                let win_limit = if win_right_edge >= self.remote_last_seq {
-                    win_right_edge - self.remote_last_seq
+                let win_limit = if win_right_edge >= seq_number {
+                    win_right_edge - seq_number
                 } else {
                     // This can happen if we've sent some data and later the remote side
                     // has shrunk its window so that data is no longer inside the window.
@@ -2134,9 +3522,15 @@ impl<'a> Socket<'a> {
                 // 3. MSS we can send, determined by our MTU.
                 let size = win_limit
                     .min(self.remote_mss)
-                    .min(cx.ip_mtu() - ip_repr.header_len() - TCP_HEADER_LEN);
-
-                let offset = self.remote_last_seq - self.local_seq_no;
+                    .min(
+                        cx.ip_mtu()
+                            - ip_repr.header_len()
+                            - TCP_HEADER_LEN
+                            - self.options_overhead(),
+                    )
+                    .min(sack_limit);
+
+                let offset = seq_number - self.local_seq_no;
                 repr.payload = self.tx_buffer.get_allocated(offset, size);
 
                 // If we've sent everything we had in the buffer, follow it with the PSH or FIN
@@ -2158,6 +3552,14 @@ impl<'a> Socket<'a> {
             State::FinWait2 | State::TimeWait => {}
         }
 
+        // RFC 7323 § 3.2: The timestamps option is offered in the initial SYN, and once
+        // agreed upon, sent in every segment except for RSTs.
+        if self.state == State::SynSent && self.timestamps {
+            repr.timestamp = Some((Self::tsval(cx), 0));
+        } else if self.remote_has_timestamp && repr.control != TcpControl::Rst {
+            repr.timestamp = Some((Self::tsval(cx), self.remote_last_tsval));
+        }
+
         // There might be more than one reason to send a packet. E.g. the keep-alive timer
         // has expired, and we also have data in transmit buffer. Since any packet that occupies
         // sequence space will elicit an ACK, we only need to send an explicit packet if we
@@ -2171,14 +3573,43 @@ impl<'a> Socket<'a> {
             is_keep_alive = false;
         }
 
+        // A zero window probe carries the next octet beyond the window. The remote endpoint
+        // either accepts it, or answers with an ACK carrying its current window.
+        let is_zero_window_probe;
+        if self.timer.should_zero_window_probe(cx.now()) && repr.is_empty() {
+            repr.payload = self.tx_buffer.get_allocated(0, 1);
+            is_zero_window_probe = true;
+        } else {
+            is_zero_window_probe = false;
+        }
+
+        // RFC 3168 § 6.1: Once ECN is agreed upon, segments echo congestion experienced
+        // marks, and new data is sent ECN-capable, with the CWR flag after a reduction of
+        // the congestion window. Pure ACKs, retransmissions and probes are not ECN-capable.
+        if self.remote_has_ecn && !matches!(repr.control, TcpControl::Syn | TcpControl::Rst) {
+            repr.ece = self.ecn_echo;
+            let is_new_data = !repr.payload.is_empty()
+                && !is_keep_alive
+                && !is_zero_window_probe
+                && self
+                    .ecn_high_seq
+                    .map_or(true, |high| repr.seq_number >= high);
+            if is_new_data {
+                ip_repr.set_traffic_class(ECN_ECT0);
+                repr.cwr = self.ecn_cwr;
+            }
+        }
+
         // Trace a summary of what will be sent.
         if is_keep_alive {
             tcp_trace!("sending a keep-alive");
+        } else if is_zero_window_probe {
+            tcp_trace!("sending a zero window probe");
         } else if !repr.payload.is_empty() {
             tcp_trace!(
                 "tx buffer: sending {} octets at offset {}",
                 repr.payload.len(),
-                self.remote_last_seq - self.local_seq_no
+                repr.seq_number - self.local_seq_no
             );
         }
         if repr.control != TcpControl::None || repr.payload.is_empty() {
@@ -2207,12 +3638,34 @@ impl<'a> Socket<'a> {
         // Bailing out if the packet isn't placed in the device buffer allows us
         // to not waste time waiting for the retransmit timer on packets that we know
         // for sure will not be successfully transmitted.
+        #[cfg(feature = "socket-tcp-md5")]
+        self.md5_sign(&ip_repr, &mut repr);
         ip_repr.set_payload_len(repr.buffer_len());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            control = ?repr.control,
+            seq = %repr.seq_number,
+            ack = ?repr.ack_number.map(|ack| ack.0),
+            window = repr.window_len,
+            len = repr.payload.len(),
+            "sending segment"
+        );
         emit(cx, (ip_repr, repr))?;
+        #[cfg(feature = "stats")]
+        self.stats.record_tx(repr.payload.len());
 
         // We've sent something, whether useful data or a keep-alive packet, so rewind
-        // the keep-alive timer.
-        self.timer.rewind_keep_alive(cx.now(), self.keep_alive);
+        // the keep-alive timer. Keep-alive packets are repeated at the keep-alive interval,
+        // while any other packet starts a new idle period.
+        if is_keep_alive {
+            self.keep_alive_probes_sent = self.keep_alive_probes_sent.saturating_add(1);
+            self.timer.rewind_keep_alive(cx.now(), self.keep_alive);
+        } else if is_zero_window_probe {
+            self.timer.rewind_zero_window_probe(cx.now());
+        } else {
+            self.timer
+                .rewind_keep_alive(cx.now(), self.keep_alive_first());
+        }
 
         // Reset delayed-ack timer
         match self.ack_delay_timer {
@@ -2227,8 +3680,8 @@ impl<'a> Socket<'a> {
         self.ack_delay_timer = AckDelayTimer::Idle;
 
         // Leave the rest of the state intact if sending a keep-alive packet, since those
-        // carry a fake segment.
-        if is_keep_alive {
+        // carry a fake segment, or a zero window probe, which is outside of the window.
+        if is_keep_alive || is_zero_window_probe {
             return Ok(());
         }
 
@@ -2236,6 +3689,15 @@ impl<'a> Socket<'a> {
         self.remote_last_seq = repr.seq_number + repr.segment_len();
         self.remote_last_ack = repr.ack_number;
         self.remote_last_win = repr.window_len;
+        if repr.cwr && repr.control != TcpControl::Syn {
+            self.ecn_cwr = false;
+        }
+        if self
+            .ecn_high_seq
+            .map_or(true, |high| self.remote_last_seq > high)
+        {
+            self.ecn_high_seq = Some(self.remote_last_seq);
+        }
 
         if repr.segment_len() > 0 {
             self.rtte
@@ -2290,11 +3752,21 @@ impl<'a> Socket<'a> {
                 (_, _) => PollAt::Ingress,
             };
 
+            let half_open_poll_at = match self.half_open_expires_at() {
+                Some(expires_at) => PollAt::Time(expires_at),
+                None => PollAt::Ingress,
+            };
+
             // We wait for the earliest of our timers to fire.
-            *[self.timer.poll_at(), timeout_poll_at, delayed_ack_poll_at]
-                .iter()
-                .min()
-                .unwrap_or(&PollAt::Ingress)
+            *[
+                self.timer.poll_at(),
+                timeout_poll_at,
+                delayed_ack_poll_at,
+                half_open_poll_at,
+            ]
+            .iter()
+            .min()
+            .unwrap_or(&PollAt::Ingress)
         }
     }
 }
@@ -2380,11 +3852,14 @@ mod test {
         next_header: IpProtocol::Tcp,
         payload_len: 20,
         hop_limit: 64,
+        traffic_class: 0,
     });
     const SEND_TEMPL: TcpRepr<'static> = TcpRepr {
         src_port: REMOTE_PORT,
         dst_port: LOCAL_PORT,
         control: TcpControl::None,
+        ece: false,
+        cwr: false,
         seq_number: TcpSeqNumber(0),
         ack_number: Some(TcpSeqNumber(0)),
         window_len: 256,
@@ -2392,6 +3867,8 @@ mod test {
         max_seg_size: None,
         sack_permitted: false,
         sack_ranges: [None, None, None],
+        timestamp: None,
+        md5_signature: None,
         payload: &[],
     };
     const _RECV_IP_TEMPL: IpRepr = IpReprIpvX(IpvXRepr {
@@ -2400,11 +3877,14 @@ mod test {
         next_header: IpProtocol::Tcp,
         payload_len: 20,
         hop_limit: 64,
+        traffic_class: 0,
     });
     const RECV_TEMPL: TcpRepr<'static> = TcpRepr {
         src_port: LOCAL_PORT,
         dst_port: REMOTE_PORT,
         control: TcpControl::None,
+        ece: false,
+        cwr: false,
         seq_number: TcpSeqNumber(0),
         ack_number: Some(TcpSeqNumber(0)),
         window_len: 64,
@@ -2412,6 +3892,8 @@ mod test {
         max_seg_size: None,
         sack_permitted: false,
         sack_ranges: [None, None, None],
+        timestamp: None,
+        md5_signature: None,
         payload: &[],
     };
 
@@ -2450,6 +3932,7 @@ mod test {
             next_header: IpProtocol::Tcp,
             payload_len: repr.buffer_len(),
             hop_limit: 64,
+            traffic_class: 0,
         });
         net_trace!("send: {}", repr);
 
@@ -2775,46 +4258,393 @@ mod test {
     }
 
     #[test]
-    fn test_listen_syn_win_scale_buffers() {
-        for (buffer_size, shift_amt) in &[
-            (64, 0),
-            (128, 0),
-            (1024, 0),
-            (65535, 0),
-            (65536, 1),
-            (65537, 1),
-            (131071, 1),
-            (131072, 2),
-            (524287, 3),
-            (524288, 4),
-            (655350, 4),
-            (1048576, 5),
-        ] {
-            let mut s = socket_with_buffer_sizes(64, *buffer_size);
-            s.state = State::Listen;
-            s.listen_endpoint = LISTEN_END;
-            assert_eq!(s.remote_win_shift, *shift_amt);
-            send!(
-                s,
-                TcpRepr {
-                    control: TcpControl::Syn,
-                    seq_number: REMOTE_SEQ,
-                    ack_number: None,
-                    window_scale: Some(0),
-                    ..SEND_TEMPL
-                }
-            );
-            assert_eq!(s.remote_win_shift, *shift_amt);
-            recv!(
-                s,
-                [TcpRepr {
-                    control: TcpControl::Syn,
-                    seq_number: LOCAL_SEQ,
-                    ack_number: Some(REMOTE_SEQ + 1),
-                    max_seg_size: Some(BASE_MSS),
-                    window_scale: Some(*shift_amt),
-                    window_len: cmp::min(*buffer_size, 65535) as u16,
-                    ..RECV_TEMPL
+    fn test_listen_timestamps_option() {
+        let mut s = socket_listen();
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ,
+                ack_number: None,
+                timestamp: Some((500, 0)),
+                ..SEND_TEMPL
+            }
+        );
+        assert!(!s.remote_has_timestamp);
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: LOCAL_SEQ,
+                ack_number: Some(REMOTE_SEQ + 1),
+                max_seg_size: Some(BASE_MSS),
+                ..RECV_TEMPL
+            }]
+        );
+
+        let mut s = socket_listen();
+        s.set_timestamps_enabled(true);
+        send!(
+            s,
+            time 1000,
+            TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ,
+                ack_number: None,
+                timestamp: Some((500, 0)),
+                ..SEND_TEMPL
+            }
+        );
+        assert!(s.remote_has_timestamp);
+        recv!(
+            s,
+            time 1000,
+            Ok(TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: LOCAL_SEQ,
+                ack_number: Some(REMOTE_SEQ + 1),
+                max_seg_size: Some(BASE_MSS),
+                timestamp: Some((1000, 500)),
+                ..RECV_TEMPL
+            })
+        );
+    }
+
+    #[test]
+    fn test_syn_sent_timestamps_option() {
+        let mut s = socket_syn_sent();
+        s.set_timestamps_enabled(true);
+        recv!(
+            s,
+            time 1000,
+            Ok(TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: LOCAL_SEQ,
+                ack_number: None,
+                max_seg_size: Some(BASE_MSS),
+                window_scale: Some(0),
+                sack_permitted: true,
+                timestamp: Some((1000, 0)),
+                ..RECV_TEMPL
+            })
+        );
+        send!(
+            s,
+            time 1050,
+            TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ,
+                ack_number: Some(LOCAL_SEQ + 1),
+                max_seg_size: Some(BASE_MSS),
+                timestamp: Some((700, 1000)),
+                ..SEND_TEMPL
+            }
+        );
+        assert!(s.remote_has_timestamp);
+        recv!(
+            s,
+            time 1050,
+            Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                timestamp: Some((1050, 700)),
+                ..RECV_TEMPL
+            })
+        );
+    }
+
+    #[test]
+    fn test_ecn_negotiation() {
+        let ecn_syn = TcpRepr {
+            control: TcpControl::Syn,
+            ece: true,
+            cwr: true,
+            seq_number: REMOTE_SEQ,
+            ack_number: None,
+            ..SEND_TEMPL
+        };
+        let syn_ack = TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            max_seg_size: Some(BASE_MSS),
+            ..RECV_TEMPL
+        };
+
+        let mut s = socket_listen();
+        send!(s, ecn_syn);
+        assert!(!s.remote_has_ecn);
+        recv!(s, [syn_ack]);
+
+        let mut s = socket_listen();
+        s.set_ecn_enabled(true);
+        send!(s, ecn_syn);
+        assert!(s.remote_has_ecn);
+        recv!(
+            s,
+            [TcpRepr {
+                ece: true,
+                ..syn_ack
+            }]
+        );
+
+        let mut s = socket_syn_sent();
+        s.set_ecn_enabled(true);
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Syn,
+                ece: true,
+                cwr: true,
+                seq_number: LOCAL_SEQ,
+                ack_number: None,
+                max_seg_size: Some(BASE_MSS),
+                window_scale: Some(0),
+                sack_permitted: true,
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Syn,
+                ece: true,
+                seq_number: REMOTE_SEQ,
+                ack_number: Some(LOCAL_SEQ + 1),
+                max_seg_size: Some(BASE_MSS),
+                ..SEND_TEMPL
+            }
+        );
+        assert!(s.remote_has_ecn);
+    }
+
+    #[test]
+    fn test_ecn_congestion() {
+        let mut s = socket_established();
+        s.set_congestion_control(CongestionControl::Reno);
+        s.set_nagle_enabled(false);
+        s.remote_has_ecn = true;
+
+        fn recv_ecn(s: &mut TestSocket, payload: &[u8], traffic_class: u8, ece: bool, cwr: bool) {
+            let mut sent = false;
+            let result = s.socket.dispatch(&mut s.cx, |_, (ip_repr, repr)| {
+                assert_eq!(repr.payload, payload);
+                assert_eq!(ip_repr.traffic_class(), traffic_class);
+                assert_eq!((repr.ece, repr.cwr), (ece, cwr));
+                sent = true;
+                Ok::<_, ()>(())
+            });
+            assert_eq!(result, Ok(()));
+            assert!(sent);
+        }
+
+        // New data is ECN-capable.
+        s.send_slice(b"abcdef").unwrap();
+        recv_ecn(&mut s, b"abcdef", ECN_ECT0, false, false);
+
+        // Congestion experienced marks are echoed until the remote signals that it reacted.
+        let ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: REMOTE_ADDR,
+            dst_addr: LOCAL_ADDR,
+            next_header: IpProtocol::Tcp,
+            payload_len: 23,
+            hop_limit: 64,
+            traffic_class: ECN_CE,
+        });
+        let repr = TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            payload: &b"xyz"[..],
+            ..SEND_TEMPL
+        };
+        assert_eq!(s.socket.process(&mut s.cx, &ip_repr, &repr), None);
+        recv_ecn(&mut s, b"", 0, true, false);
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 3,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"uvw"[..],
+                ..SEND_TEMPL
+            }
+        );
+        recv_ecn(&mut s, b"", 0, true, false);
+        send!(
+            s,
+            TcpRepr {
+                cwr: true,
+                seq_number: REMOTE_SEQ + 1 + 6,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"rst"[..],
+                ..SEND_TEMPL
+            }
+        );
+        recv_ecn(&mut s, b"", 0, false, false);
+
+        // An echo reduces the congestion window once, signalled with the next new data.
+        let window = s.congestion_controller.inner().window();
+        send!(
+            s,
+            TcpRepr {
+                ece: true,
+                seq_number: REMOTE_SEQ + 1 + 9,
+                ack_number: Some(LOCAL_SEQ + 1 + 6),
+                ..SEND_TEMPL
+            }
+        );
+        assert!(s.congestion_controller.inner().window() < window);
+        let window = s.congestion_controller.inner().window();
+        send!(
+            s,
+            TcpRepr {
+                ece: true,
+                seq_number: REMOTE_SEQ + 1 + 9,
+                ack_number: Some(LOCAL_SEQ + 1 + 6),
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.congestion_controller.inner().window(), window);
+
+        s.send_slice(b"ghi").unwrap();
+        recv_ecn(&mut s, b"ghi", ECN_ECT0, false, true);
+        s.send_slice(b"jkl").unwrap();
+        recv_ecn(&mut s, b"jkl", ECN_ECT0, false, false);
+    }
+
+    #[test]
+    #[cfg(feature = "socket-tcp-md5")]
+    fn test_md5_signature() {
+        let mut s = socket_established();
+        s.set_md5_key(Some(b"secret"));
+        s.send_slice(b"abcdef").unwrap();
+        recv(&mut s, Instant::from_millis(1000), |result| {
+            let repr = result.unwrap();
+            let unsigned = TcpRepr {
+                md5_signature: Some([0; 16]),
+                ..repr
+            };
+            let signature =
+                unsigned.compute_md5_signature(&LOCAL_ADDR.into(), &REMOTE_ADDR.into(), b"secret");
+            assert_eq!(repr.md5_signature, Some(signature));
+            assert_eq!(repr.payload, b"abcdef");
+        });
+
+        // The SACK blocks that do not fit along with the signature are dropped.
+        let mut repr = TcpRepr {
+            timestamp: Some((1, 2)),
+            sack_ranges: [Some((1, 2)), Some((3, 4)), Some((5, 6))],
+            ..RECV_TEMPL
+        };
+        s.md5_sign(&SEND_IP_TEMPL, &mut repr);
+        assert!(repr.header_len() <= TCP_HEADER_LEN + 40);
+        assert_eq!(repr.sack_ranges, [Some((1, 2)), None, None]);
+    }
+
+    #[test]
+    fn test_timestamps_rtt_measurement() {
+        let mut s = socket_established();
+        s.remote_has_timestamp = true;
+        s.remote_last_tsval = 500;
+
+        s.send_slice(b"abcdef").unwrap();
+        recv!(s, time 1000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abcdef"[..],
+            timestamp:  Some((1000, 500)),
+            ..RECV_TEMPL
+        }));
+        send!(s, time 1100, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            timestamp: Some((600, 1000)),
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.rtte.rtt, (RTTE_INITIAL_RTT * 7 + 100 + 7) / 8);
+        assert_eq!(s.remote_last_tsval, 600);
+    }
+
+    #[test]
+    fn test_timestamps_paws() {
+        let mut s = socket_established();
+        s.remote_has_timestamp = true;
+        s.remote_last_tsval = 500;
+
+        // An old duplicate segment is dropped.
+        send!(
+            s,
+            time 1000,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abc"[..],
+                timestamp: Some((400, 0)),
+                ..SEND_TEMPL
+            },
+            Some(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                timestamp: Some((1000, 500)),
+                ..RECV_TEMPL
+            })
+        );
+        assert_eq!(s.recv_queue(), 0);
+
+        send!(
+            s,
+            time 1000,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abc"[..],
+                timestamp: Some((500, 0)),
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.recv_queue(), 3);
+    }
+
+    #[test]
+    fn test_listen_syn_win_scale_buffers() {
+        for (buffer_size, shift_amt) in &[
+            (64, 0),
+            (128, 0),
+            (1024, 0),
+            (65535, 0),
+            (65536, 1),
+            (65537, 1),
+            (131071, 1),
+            (131072, 2),
+            (524287, 3),
+            (524288, 4),
+            (655350, 4),
+            (1048576, 5),
+        ] {
+            let mut s = socket_with_buffer_sizes(64, *buffer_size);
+            s.state = State::Listen;
+            s.listen_endpoint = LISTEN_END;
+            assert_eq!(s.remote_win_shift, *shift_amt);
+            send!(
+                s,
+                TcpRepr {
+                    control: TcpControl::Syn,
+                    seq_number: REMOTE_SEQ,
+                    ack_number: None,
+                    window_scale: Some(0),
+                    ..SEND_TEMPL
+                }
+            );
+            assert_eq!(s.remote_win_shift, *shift_amt);
+            recv!(
+                s,
+                [TcpRepr {
+                    control: TcpControl::Syn,
+                    seq_number: LOCAL_SEQ,
+                    ack_number: Some(REMOTE_SEQ + 1),
+                    max_seg_size: Some(BASE_MSS),
+                    window_scale: Some(*shift_amt),
+                    window_len: cmp::min(*buffer_size, 65535) as u16,
+                    ..RECV_TEMPL
                 }]
             );
         }
@@ -2921,6 +4751,52 @@ mod test {
         sanity!(s, socket_established());
     }
 
+    #[test]
+    fn test_syn_received_defer_accept() {
+        let mut s = socket_syn_received();
+        s.set_defer_accept(Some(Duration::from_millis(1000)));
+        recv!(s, time 0, Ok(TcpRepr {
+            control:    TcpControl::Syn,
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            max_seg_size: Some(BASE_MSS),
+            ..RECV_TEMPL
+        }));
+        send!(s, time 10, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.state, State::SynReceived);
+        send!(s, time 20, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            payload: &b"abc"[..],
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.state, State::Established);
+        assert_eq!(s.rx_buffer.dequeue_many(3), &b"abc"[..]);
+    }
+
+    #[test]
+    fn test_syn_received_defer_accept_expired() {
+        let mut s = socket_syn_received();
+        s.set_defer_accept(Some(Duration::from_millis(1000)));
+        recv!(s, time 0, Ok(TcpRepr {
+            control:    TcpControl::Syn,
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            max_seg_size: Some(BASE_MSS),
+            ..RECV_TEMPL
+        }));
+        send!(s, time 1000, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.state, State::Established);
+    }
+
     #[test]
     fn test_syn_received_ack_too_low() {
         let mut s = socket_syn_received();
@@ -3051,6 +4927,50 @@ mod test {
         assert_eq!(s.tuple, None);
     }
 
+    #[test]
+    fn test_listen_syn_rate_limit() {
+        let mut s = socket_listen();
+        s.set_syn_rate_limit(Some(SynRateLimit {
+            max_syns: 1,
+            interval: Duration::from_millis(1000),
+            reset: true,
+        }));
+        let syn = TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: REMOTE_SEQ,
+            ack_number: None,
+            ..SEND_TEMPL
+        };
+        send!(s, time 0, syn);
+        assert_eq!(s.state, State::SynReceived);
+        recv!(s, time 0, Ok(TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            max_seg_size: Some(BASE_MSS),
+            ..RECV_TEMPL
+        }));
+        send!(s, time 0, TcpRepr {
+            control: TcpControl::Rst,
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ),
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.state, State::Listen);
+
+        send!(s, time 500, syn, Some(TcpRepr {
+            control: TcpControl::Rst,
+            seq_number: TcpSeqNumber(0),
+            ack_number: Some(REMOTE_SEQ + 1),
+            window_len: 0,
+            ..RECV_TEMPL
+        }));
+        assert_eq!(s.state, State::Listen);
+
+        send!(s, time 1000, syn);
+        assert_eq!(s.state, State::SynReceived);
+    }
+
     #[test]
     fn test_syn_received_no_window_scaling() {
         let mut s = socket_listen();
@@ -3164,6 +5084,22 @@ mod test {
         assert_eq!(s.tuple, Some(TUPLE));
     }
 
+    #[test]
+    fn test_connect_iss_hook() {
+        fn iss(local: IpEndpoint, remote: IpEndpoint, _timestamp: Instant) -> TcpSeqNumber {
+            assert_eq!((local, remote), (LOCAL_END, REMOTE_END));
+            TcpSeqNumber(1234)
+        }
+
+        let mut s = socket();
+        s.cx.set_tcp_iss_hook(Some(iss));
+        s.socket
+            .connect(&mut s.cx, REMOTE_END, LOCAL_END.port)
+            .unwrap();
+        assert_eq!(s.local_seq_no, TcpSeqNumber(1234));
+        assert_eq!(s.remote_last_seq, TcpSeqNumber(1234));
+    }
+
     #[test]
     fn test_connect() {
         let mut s = socket();
@@ -3570,7 +5506,67 @@ mod test {
     // =========================================================================================//
 
     #[test]
-    fn test_established_recv() {
+    fn test_established_recv() {
+        let mut s = socket_established();
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abcdef"[..],
+                ..SEND_TEMPL
+            }
+        );
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1 + 6),
+                window_len: 58,
+                ..RECV_TEMPL
+            }]
+        );
+        assert_eq!(s.rx_buffer.dequeue_many(6), &b"abcdef"[..]);
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn test_stats() {
+        let mut s = socket_established();
+        s.reset_stats();
+        s.send_slice(b"abcdef").unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload: &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 1000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload: &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+        send!(s, time 1000, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            payload: &b"xyz"[..],
+            ..SEND_TEMPL
+        });
+        assert_eq!(
+            *s.stats(),
+            Stats {
+                rx_packets: 1,
+                rx_bytes: 3,
+                tx_packets: 2,
+                tx_bytes: 12,
+                retransmissions: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_established_recv_consume() {
         let mut s = socket_established();
         send!(
             s,
@@ -3581,16 +5577,39 @@ mod test {
                 ..SEND_TEMPL
             }
         );
-        recv!(
-            s,
-            [TcpRepr {
-                seq_number: LOCAL_SEQ + 1,
-                ack_number: Some(REMOTE_SEQ + 1 + 6),
-                window_len: 58,
-                ..RECV_TEMPL
-            }]
-        );
-        assert_eq!(s.rx_buffer.dequeue_many(6), &b"abcdef"[..]);
+        assert!(s.can_recv_len(6));
+        assert!(!s.can_recv_len(7));
+        assert_eq!(s.peek(4), Ok(&b"abcd"[..]));
+        assert_eq!(s.recv_consume(4), Ok(4));
+        assert_eq!(s.peek(4), Ok(&b"ef"[..]));
+        assert_eq!(s.recv_consume(4), Ok(2));
+        assert!(!s.can_recv_len(1));
+    }
+
+    #[test]
+    fn test_established_recv_window_autotune() {
+        let mut s = socket_established_with_buffer_sizes(4000, 4000);
+        s.rx_autotune = Some(RxAutotune::new(1000));
+        send!(s, time 0, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            payload: &[0; 600][..],
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.scaled_window(), 400);
+        assert_eq!(s.recv_slice(&mut [0; 600][..]), Ok(600));
+        assert_eq!(s.scaled_window(), 1000);
+
+        // More than half of the window was filled within a round-trip time.
+        send!(s, time 400, TcpRepr {
+            seq_number: REMOTE_SEQ + 1 + 600,
+            ack_number: Some(LOCAL_SEQ + 1),
+            payload: &[0; 600][..],
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.scaled_window(), 600);
+        assert_eq!(s.recv_slice(&mut [0; 600][..]), Ok(600));
+        assert_eq!(s.scaled_window(), 1200);
     }
 
     fn setup_rfc2018_cases() -> (TestSocket, Vec<u8>) {
@@ -3733,6 +5752,79 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_established_send_reserve() {
+        let mut s = socket_established();
+        s.send_slice(b"abcdef").unwrap();
+        s.send_reserve(6).unwrap().copy_from_slice(b"foobar");
+        assert_eq!(s.tx_buffer.len(), 6);
+        // An uncommitted reservation is overwritten by the next one.
+        s.send_reserve(3).unwrap().copy_from_slice(b"123");
+        assert_eq!(s.send_commit(3), Ok(3));
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abcdef123"[..],
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 9),
+                ..SEND_TEMPL
+            }
+        );
+        // The empty transmit buffer is rewound, so the whole capacity is contiguous.
+        assert_eq!(s.send_reserve(64).unwrap().len(), 64);
+        // A reservation is cancelled by enqueueing octets otherwise.
+        s.send_slice(b"abc").unwrap();
+        assert_eq!(s.send_commit(3), Ok(0));
+        assert_eq!(s.tx_buffer.len(), 3);
+    }
+
+    #[test]
+    fn test_established_send_reserve_wraparound() {
+        let mut s = socket_established();
+        s.set_nagle_enabled(false);
+        s.send_slice(&[b'x'; 60]).unwrap();
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &[b'x'; 60][..],
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 50),
+                ..SEND_TEMPL
+            }
+        );
+        // The free space wraps around the end of the buffer, so the reservation is
+        // cut short...
+        s.send_reserve(20).unwrap().copy_from_slice(b"abcd");
+        // ...and committing more than was reserved only enqueues the reserved octets.
+        assert_eq!(s.send_commit(20), Ok(4));
+        assert_eq!(s.tx_buffer.len(), 14);
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 60,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abcd"[..],
+                ..RECV_TEMPL
+            }]
+        );
+    }
+
     #[test]
     fn test_established_send() {
         let mut s = socket_established();
@@ -3805,6 +5897,63 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_established_send_watermarks() {
+        let mut s = socket_established();
+        s.set_send_watermarks(Some((4, 12)));
+        s.send_slice(b"abcdef").unwrap();
+        assert!(s.can_send());
+        s.send_slice(b"123456").unwrap();
+        assert!(!s.can_send());
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abcdef123456"[..],
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 6),
+                ..SEND_TEMPL
+            }
+        );
+        assert!(!s.can_send());
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 8),
+                ..SEND_TEMPL
+            }
+        );
+        assert!(s.can_send());
+    }
+
+    #[test]
+    fn test_established_send_corked() {
+        let mut s = socket_established();
+        s.set_corked(true);
+        s.send_slice(b"abcdef").unwrap();
+        recv_nothing!(s);
+        s.send_slice(b"foobar").unwrap();
+        recv_nothing!(s);
+        s.set_corked(false);
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abcdeffoobar"[..],
+                ..RECV_TEMPL
+            }]
+        );
+    }
+
     #[test]
     fn test_established_send_buf_gt_win() {
         let mut data = [0; 32];
@@ -4128,6 +6277,35 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_established_rebind_abort() {
+        let mut s = socket_established();
+        assert!(!s.socket.rebind(&mut s.cx, RebindPolicy::Abort));
+        assert_eq!(s.state, State::Established);
+
+        s.tuple = Some(Tuple {
+            local: IpEndpoint::new(OTHER_ADDR.into(), LOCAL_PORT),
+            remote: REMOTE_END,
+        });
+        assert!(s.socket.rebind(&mut s.cx, RebindPolicy::Abort));
+        assert_eq!(s.state, State::Closed);
+        assert!(s.is_address_lost());
+        recv_nothing!(s);
+    }
+
+    #[test]
+    fn test_established_rebind_reconnect() {
+        let mut s = socket_established();
+        s.tuple = Some(Tuple {
+            local: IpEndpoint::new(OTHER_ADDR.into(), LOCAL_PORT),
+            remote: REMOTE_END,
+        });
+        assert!(s.socket.rebind(&mut s.cx, RebindPolicy::Reconnect));
+        assert_eq!(s.state, State::SynSent);
+        assert_eq!(s.tuple, Some(TUPLE));
+        assert!(!s.is_address_lost());
+    }
+
     #[test]
     fn test_established_rst_bad_seq() {
         let mut s = socket_established();
@@ -4440,8 +6618,10 @@ mod test {
             }]
         );
         assert_eq!(s.state, State::TimeWait);
+        assert_eq!(s.local_port(), Some(LOCAL_PORT));
         recv_nothing!(s, time 60_000);
         assert_eq!(s.state, State::Closed);
+        assert_eq!(s.local_port(), None);
     }
 
     // =========================================================================================//
@@ -5414,26 +7594,173 @@ mod test {
             payload:    &b"wwwwww"[..],
             ..RECV_TEMPL
         }));
-        recv!(s, time 1115, Ok(TcpRepr {
-            seq_number: LOCAL_SEQ + 1 + (6 * 3),
+        recv!(s, time 1115, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + (6 * 3),
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"zzzzzz"[..],
+            ..RECV_TEMPL
+        }));
+
+        // After all was send out, enter *normal* retransmission,
+        // don't stay in fast retransmission.
+        assert!(match s.timer {
+            Timer::Retransmit { expires_at, .. } => expires_at > Instant::from_millis(1115),
+            _ => false,
+        });
+
+        // ACK all received segments
+        send!(s, time 1120, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + (6 * 4)),
+            ..SEND_TEMPL
+        });
+    }
+
+    #[test]
+    fn test_fast_retransmit_with_sack() {
+        let mut s = socket_established();
+        s.remote_has_sack = true;
+        s.remote_mss = 6;
+
+        send!(s, time 0, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        });
+
+        s.send_slice(b"xxxxxxyyyyyywwwwwwzzzzzz").unwrap();
+        for (i, payload) in [b"xxxxxx", b"yyyyyy", b"wwwwww", b"zzzzzz"]
+            .iter()
+            .enumerate()
+        {
+            recv!(s, time 1000, Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 6 * i,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload:    &payload[..],
+                ..RECV_TEMPL
+            }));
+        }
+
+        // The first and third segments are lost, the others are selectively acknowledged.
+        let sack = |seq: TcpSeqNumber| Some((seq.0 as u32, (seq + 6).0 as u32));
+        for at in [1050, 1055, 1060] {
+            send!(s, time at, TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                sack_ranges: [sack(LOCAL_SEQ + 1 + 6), sack(LOCAL_SEQ + 1 + 18), None],
+                ..SEND_TEMPL
+            });
+        }
+
+        // Only the lost segments are retransmitted.
+        recv!(s, time 1100, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"xxxxxx"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 1105, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 12,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"wwwwww"[..],
+            ..RECV_TEMPL
+        }));
+        recv_nothing!(s, time 1110);
+
+        send!(s, time 1120, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 24),
+            ..SEND_TEMPL
+        });
+        assert_eq!(s.remote_sacked, [None; 3]);
+    }
+
+    #[test]
+    fn test_congestion_window() {
+        let mut s = socket_established();
+        s.remote_mss = 6;
+        s.set_congestion_control(CongestionControl::Reno);
+        assert_eq!(s.congestion_control(), CongestionControl::Reno);
+
+        s.send_slice(b"xxxxxxyyyyyywwwwwwzzzzzzaaaaaabbbbbb")
+            .unwrap();
+        for (i, payload) in [b"xxxxxx", b"yyyyyy", b"wwwwww", b"zzzzzz"]
+            .iter()
+            .enumerate()
+        {
+            recv!(s, time 1000, Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 6 * i,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload:    &payload[..],
+                ..RECV_TEMPL
+            }));
+        }
+        // The initial window is four segments.
+        recv_nothing!(s, time 1000);
+
+        // In slow start, the window grows by one segment for each segment acknowledged.
+        send!(s, time 1100, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            ..SEND_TEMPL
+        });
+        recv!(s, time 1100, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 24,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"aaaaaa"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 1100, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 30,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"bbbbbb"[..],
+            ..RECV_TEMPL
+        }));
+    }
+
+    #[test]
+    fn test_retransmit_timeout_ignores_sack() {
+        let mut s = socket_established();
+        s.remote_has_sack = true;
+        s.remote_mss = 6;
+
+        s.send_slice(b"xxxxxxyyyyyy").unwrap();
+        recv!(s, time 1000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"xxxxxx"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 1000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"yyyyyy"[..],
+            ..RECV_TEMPL
+        }));
+        send!(s, time 1050, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            sack_ranges: [
+                Some(((LOCAL_SEQ + 1 + 6).0 as u32, (LOCAL_SEQ + 1 + 12).0 as u32)),
+                None,
+                None,
+            ],
+            ..SEND_TEMPL
+        });
+
+        // The receiver may have discarded the selectively acknowledged data by now.
+        recv!(s, time 2000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"xxxxxx"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 2000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6,
             ack_number: Some(REMOTE_SEQ + 1),
-            payload:    &b"zzzzzz"[..],
+            payload:    &b"yyyyyy"[..],
             ..RECV_TEMPL
         }));
-
-        // After all was send out, enter *normal* retransmission,
-        // don't stay in fast retransmission.
-        assert!(match s.timer {
-            Timer::Retransmit { expires_at, .. } => expires_at > Instant::from_millis(1115),
-            _ => false,
-        });
-
-        // ACK all received segments
-        send!(s, time 1120, TcpRepr {
-            seq_number: REMOTE_SEQ + 1,
-            ack_number: Some(LOCAL_SEQ + 1 + (6 * 4)),
-            ..SEND_TEMPL
-        });
     }
 
     #[test]
@@ -5885,6 +8212,66 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_zero_window_probe() {
+        let mut s = socket_established();
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                window_len: 0,
+                ..SEND_TEMPL
+            }
+        );
+        s.send_slice(b"abcdef").unwrap();
+        recv_nothing!(s, time 0);
+        recv_nothing!(s, time 600);
+        recv!(s, time 700, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload: &b"a"[..],
+            ..RECV_TEMPL
+        }));
+
+        // The window is still closed, probe again after twice the delay.
+        send!(
+            s,
+            time 710,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                window_len: 0,
+                ..SEND_TEMPL
+            }
+        );
+        recv_nothing!(s, time 2000);
+        recv!(s, time 2100, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload: &b"a"[..],
+            ..RECV_TEMPL
+        }));
+
+        // The window opens, send the data.
+        send!(
+            s,
+            time 2110,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                window_len: 6,
+                ..SEND_TEMPL
+            }
+        );
+        recv!(s, time 2110, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload: &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+    }
+
     #[test]
     fn test_zero_window_ack_on_window_growth() {
         let mut s = socket_established();
@@ -6167,6 +8554,106 @@ mod test {
         assert_eq!(s.state, State::Closed);
     }
 
+    #[test]
+    fn test_syn_received_timeout() {
+        let mut s = socket_syn_received();
+        s.set_syn_received_timeout(Some(Duration::from_millis(500)));
+        recv!(s, time 0, Ok(TcpRepr {
+            control:    TcpControl::Syn,
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            max_seg_size: Some(BASE_MSS),
+            ..RECV_TEMPL
+        }));
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(500))
+        );
+        recv_nothing!(s, time 495);
+        assert_eq!(s.state, State::SynReceived);
+        recv_nothing!(s, time 500);
+        assert_eq!(s.state, State::Listen);
+        assert_eq!(s.tuple, None);
+    }
+
+    #[test]
+    fn test_syn_received_duplicate_syn() {
+        let mut s = socket_syn_received();
+        recv!(s, time 0, Ok(TcpRepr {
+            control:    TcpControl::Syn,
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            max_seg_size: Some(BASE_MSS),
+            ..RECV_TEMPL
+        }));
+        send!(s, time 100, TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: REMOTE_SEQ,
+            ack_number: None,
+            ..SEND_TEMPL
+        });
+        recv!(s, time 100, Ok(TcpRepr {
+            control:    TcpControl::Syn,
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            max_seg_size: Some(BASE_MSS),
+            ..RECV_TEMPL
+        }));
+        send!(s, time 200, TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: REMOTE_SEQ + 10,
+            ack_number: None,
+            ..SEND_TEMPL
+        });
+        recv_nothing!(s, time 200);
+        assert_eq!(s.state, State::SynReceived);
+        assert_eq!(s.tuple, Some(TUPLE));
+    }
+
+    #[test]
+    fn test_syn_received_syn_ack_retries() {
+        let mut s = socket_syn_received();
+        s.set_syn_ack_retries(Some(1));
+        recv!(s, time 0, Ok(TcpRepr {
+            control:    TcpControl::Syn,
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            max_seg_size: Some(BASE_MSS),
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 700, Ok(TcpRepr { // retransmit
+            control:    TcpControl::Syn,
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            max_seg_size: Some(BASE_MSS),
+            ..RECV_TEMPL
+        }));
+        recv_nothing!(s, time 1399);
+        assert_eq!(s.state, State::SynReceived);
+        recv_nothing!(s, time 1400);
+        assert_eq!(s.state, State::Listen);
+        assert_eq!(s.tuple, None);
+    }
+
+    #[test]
+    fn test_fin_wait_2_timeout() {
+        let mut s = socket_fin_wait_2();
+        s.set_fin_wait_2_timeout(Some(Duration::from_millis(1000)));
+        recv_nothing!(s, time 100);
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(1100))
+        );
+        recv_nothing!(s, time 1095);
+        recv!(s, time 1100, Ok(TcpRepr {
+            control:    TcpControl::Rst,
+            seq_number: LOCAL_SEQ + 1 + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            ..RECV_TEMPL
+        }));
+        assert_eq!(s.state, State::Closed);
+    }
+
     #[test]
     fn test_fin_wait_1_timeout() {
         let mut s = socket_fin_wait_1();
@@ -6299,6 +8786,77 @@ mod test {
         }));
     }
 
+    #[test]
+    fn test_sends_keep_alive_after_idle() {
+        let mut s = socket_established();
+        s.set_keep_alive(Some(Duration::from_millis(100)));
+        s.set_keep_alive_idle(Some(Duration::from_millis(1000)));
+
+        send!(s, time 0, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        });
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(1000))
+        );
+        recv_nothing!(s, time 995);
+        recv!(s, time 1000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &[0],
+            ..RECV_TEMPL
+        }));
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(1100))
+        );
+
+        send!(s, time 1050, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        });
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(2050))
+        );
+    }
+
+    #[test]
+    fn test_keep_alive_probes_unanswered() {
+        let mut s = socket_established();
+        s.set_keep_alive(Some(Duration::from_millis(100)));
+        s.set_keep_alive_probes(Some(2));
+
+        send!(s, time 0, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1),
+            ..SEND_TEMPL
+        });
+        recv!(s, time 100, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &[0],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 200, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &[0],
+            ..RECV_TEMPL
+        }));
+        recv_nothing!(s, time 295);
+        recv!(s, time 300, Ok(TcpRepr {
+            control:    TcpControl::Rst,
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            ..RECV_TEMPL
+        }));
+        assert_eq!(s.state, State::Closed);
+    }
+
     // =========================================================================================//
     // Tests for time-to-live configuration.
     // =========================================================================================//
@@ -6954,6 +9512,7 @@ mod test {
             next_header: IpProtocol::Tcp,
             payload_len: tcp_repr.buffer_len(),
             hop_limit: 64,
+            traffic_class: 0,
         });
         assert!(s.socket.accepts(&mut s.cx, &ip_repr, &tcp_repr));
 
@@ -6963,6 +9522,7 @@ mod test {
             next_header: IpProtocol::Tcp,
             payload_len: tcp_repr.buffer_len(),
             hop_limit: 64,
+            traffic_class: 0,
         });
         assert!(!s.socket.accepts(&mut s.cx, &ip_repr_wrong_src, &tcp_repr));
 
@@ -6972,10 +9532,88 @@ mod test {
             next_header: IpProtocol::Tcp,
             payload_len: tcp_repr.buffer_len(),
             hop_limit: 64,
+            traffic_class: 0,
         });
         assert!(!s.socket.accepts(&mut s.cx, &ip_repr_wrong_dst, &tcp_repr));
     }
 
+    // =========================================================================================//
+    // Tests for event history
+    // =========================================================================================//
+
+    #[test]
+    #[cfg(feature = "socket-tcp-history")]
+    fn test_history() {
+        let mut s = socket_established();
+        s.clear_history();
+        assert_eq!(s.history().count(), 0);
+
+        s.send_slice(b"abc").unwrap();
+        recv!(s, time 1000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abc"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 4000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abc"[..],
+            ..RECV_TEMPL
+        }));
+        send!(
+            s,
+            time 4100,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 3),
+                window_len: 128,
+                ..SEND_TEMPL
+            }
+        );
+        s.abort();
+
+        let history: Vec<_> = s.history().copied().collect();
+        assert_eq!(
+            history,
+            vec![
+                HistoryEntry {
+                    timestamp: Instant::from_millis(4000),
+                    event: HistoryEvent::Retransmit { seq: LOCAL_SEQ + 1 },
+                },
+                HistoryEntry {
+                    timestamp: Instant::from_millis(4100),
+                    event: HistoryEvent::RemoteWindow { len: 128 },
+                },
+                HistoryEntry {
+                    timestamp: Instant::from_millis(4100),
+                    event: HistoryEvent::State {
+                        from: State::Established,
+                        to: State::Closed
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "socket-tcp-history")]
+    fn test_history_wraps() {
+        let mut s = socket();
+        for _ in 0..HISTORY_LEN {
+            s.listen(LOCAL_PORT).unwrap();
+            s.close();
+        }
+        assert_eq!(s.history().count(), HISTORY_LEN);
+        assert_eq!(
+            s.history().last().unwrap().event,
+            HistoryEvent::State {
+                from: State::Listen,
+                to: State::Closed
+            }
+        );
+    }
+
     // =========================================================================================//
     // Timer tests
     // =========================================================================================//
@@ -0,0 +1,449 @@
+// Heads up! Before working on this file you should read, at least, RFC 5681,
+// RFC 3168 § 6.1.2 for ECN, and RFC 8312 for CUBIC.
+
+use crate::time::Instant;
+
+use super::{CongestionControl, RttEstimator, DEFAULT_MSS};
+
+/// A congestion control algorithm, limiting the amount of data in flight.
+pub(super) trait Controller {
+    /// Return the congestion window, in octets.
+    fn window(&self) -> usize;
+
+    /// Set the maximum segment size, and start over with the initial window.
+    fn set_mss(&mut self, mss: usize);
+
+    /// Set the window advertised by the remote, which the congestion window
+    /// need not exceed.
+    fn set_remote_window(&mut self, remote_window: usize);
+
+    /// Process an acknowledgement of `len` new octets.
+    fn on_ack(&mut self, now: Instant, len: usize, rtte: &RttEstimator);
+
+    /// Process the `count`-th duplicate acknowledgement in a row.
+    fn on_duplicate_ack(&mut self, now: Instant, count: u8);
+
+    /// Process a retransmission timeout.
+    fn on_retransmit(&mut self, now: Instant);
+
+    /// Process congestion signalled by the remote with an ECN echo, without any loss.
+    /// This happens at most once per round trip.
+    fn on_ecn_echo(&mut self, now: Instant);
+}
+
+/// Return the initial window for segments of `mss` octets, see RFC 5681 § 3.1.
+fn initial_window(mss: usize) -> usize {
+    if mss > 2190 {
+        2 * mss
+    } else if mss > 1095 {
+        3 * mss
+    } else {
+        4 * mss
+    }
+}
+
+/// No congestion control; the amount of data in flight is only limited by the remote window.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct NoControl;
+
+impl Controller for NoControl {
+    fn window(&self) -> usize {
+        usize::MAX
+    }
+
+    fn set_mss(&mut self, _mss: usize) {}
+
+    fn set_remote_window(&mut self, _remote_window: usize) {}
+
+    fn on_ack(&mut self, _now: Instant, _len: usize, _rtte: &RttEstimator) {}
+
+    fn on_duplicate_ack(&mut self, _now: Instant, _count: u8) {}
+
+    fn on_retransmit(&mut self, _now: Instant) {}
+
+    fn on_ecn_echo(&mut self, _now: Instant) {}
+}
+
+/// The Reno algorithm: slow start, congestion avoidance, and fast retransmit
+/// and recovery, as described in RFC 5681.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Reno {
+    mss: usize,
+    cwnd: usize,
+    ssthresh: usize,
+    remote_window: usize,
+    recovery: bool,
+}
+
+impl Default for Reno {
+    fn default() -> Reno {
+        Reno {
+            mss: DEFAULT_MSS,
+            cwnd: initial_window(DEFAULT_MSS),
+            ssthresh: usize::MAX,
+            remote_window: usize::MAX,
+            recovery: false,
+        }
+    }
+}
+
+impl Controller for Reno {
+    fn window(&self) -> usize {
+        self.cwnd
+    }
+
+    fn set_mss(&mut self, mss: usize) {
+        *self = Reno {
+            mss,
+            cwnd: initial_window(mss),
+            ..Reno::default()
+        }
+    }
+
+    fn set_remote_window(&mut self, remote_window: usize) {
+        self.remote_window = remote_window;
+    }
+
+    fn on_ack(&mut self, _now: Instant, len: usize, _rtte: &RttEstimator) {
+        if self.recovery {
+            // Deflate the window inflated by the duplicate ACKs.
+            self.recovery = false;
+            self.cwnd = self.ssthresh;
+            return;
+        }
+        if self.cwnd >= self.remote_window {
+            return;
+        }
+
+        if self.cwnd < self.ssthresh {
+            // Slow start.
+            self.cwnd += len.min(self.mss);
+        } else {
+            // Congestion avoidance, growing by about one segment per round trip.
+            self.cwnd += (self.mss * self.mss / self.cwnd).max(1);
+        }
+    }
+
+    fn on_duplicate_ack(&mut self, _now: Instant, count: u8) {
+        if count == 3 {
+            // Fast retransmit; the three segments that left the network are accounted for.
+            self.ssthresh = (self.cwnd / 2).max(2 * self.mss);
+            self.cwnd = self.ssthresh + 3 * self.mss;
+            self.recovery = true;
+        } else if count > 3 && self.recovery {
+            self.cwnd += self.mss;
+        }
+    }
+
+    fn on_retransmit(&mut self, _now: Instant) {
+        self.ssthresh = (self.cwnd / 2).max(2 * self.mss);
+        self.cwnd = self.mss;
+        self.recovery = false;
+    }
+
+    fn on_ecn_echo(&mut self, _now: Instant) {
+        // Halve the window as for a loss, but without retransmitting anything.
+        self.ssthresh = (self.cwnd / 2).max(2 * self.mss);
+        self.cwnd = self.ssthresh;
+        self.recovery = false;
+    }
+}
+
+/// The CUBIC algorithm, as described in RFC 8312, which grows the window faster than Reno
+/// on paths with a large bandwidth-delay product.
+#[cfg(feature = "socket-tcp-cubic")]
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Cubic {
+    mss: usize,
+    cwnd: usize,
+    ssthresh: usize,
+    remote_window: usize,
+    /// The window before the last congestion event, in segments.
+    w_max: f64,
+    /// The window estimated for Reno, in segments.
+    w_est: f64,
+    /// The start of the current congestion avoidance epoch.
+    epoch_start: Option<Instant>,
+}
+
+#[cfg(feature = "socket-tcp-cubic")]
+impl Cubic {
+    const C: f64 = 0.4;
+    const BETA: f64 = 0.7;
+
+    fn on_congestion(&mut self) {
+        let cwnd = self.cwnd as f64 / self.mss as f64;
+        // Fast convergence, releasing bandwidth to new flows.
+        self.w_max = if cwnd < self.w_max {
+            cwnd * (1.0 + Self::BETA) / 2.0
+        } else {
+            cwnd
+        };
+        // Multiplicative decrease by BETA.
+        self.ssthresh = (self.cwnd * 7 / 10).max(2 * self.mss);
+        self.epoch_start = None;
+    }
+}
+
+#[cfg(feature = "socket-tcp-cubic")]
+impl Default for Cubic {
+    fn default() -> Cubic {
+        Cubic {
+            mss: DEFAULT_MSS,
+            cwnd: initial_window(DEFAULT_MSS),
+            ssthresh: usize::MAX,
+            remote_window: usize::MAX,
+            w_max: 0.0,
+            w_est: 0.0,
+            epoch_start: None,
+        }
+    }
+}
+
+#[cfg(feature = "socket-tcp-cubic")]
+impl Controller for Cubic {
+    fn window(&self) -> usize {
+        self.cwnd
+    }
+
+    fn set_mss(&mut self, mss: usize) {
+        *self = Cubic {
+            mss,
+            cwnd: initial_window(mss),
+            ..Cubic::default()
+        }
+    }
+
+    fn set_remote_window(&mut self, remote_window: usize) {
+        self.remote_window = remote_window;
+    }
+
+    fn on_ack(&mut self, now: Instant, len: usize, rtte: &RttEstimator) {
+        if self.cwnd >= self.remote_window {
+            return;
+        }
+        if self.cwnd < self.ssthresh {
+            self.cwnd += len.min(self.mss);
+            return;
+        }
+
+        let mss = self.mss as f64;
+        let cwnd = self.cwnd as f64 / mss;
+        let epoch_start = match self.epoch_start {
+            Some(epoch_start) => epoch_start,
+            None => {
+                if self.w_max < cwnd {
+                    self.w_max = cwnd;
+                }
+                self.w_est = cwnd;
+                self.epoch_start = Some(now);
+                now
+            }
+        };
+
+        // RFC 8312 § 4.1: W_cubic(t) = C * (t - K)^3 + W_max, aiming for the window
+        // one round trip from now.
+        let k = cbrt(self.w_max * (1.0 - Self::BETA) / Self::C);
+        let t = (now - epoch_start).total_millis() as f64 / 1000.0 + rtte.rtt as f64 / 1000.0;
+        let target = Self::C * (t - k) * (t - k) * (t - k) + self.w_max;
+
+        // RFC 8312 § 4.2: Do not grow slower than Reno would.
+        self.w_est += 3.0 * (1.0 - Self::BETA) / (1.0 + Self::BETA) * (len as f64 / mss) / cwnd;
+
+        let increment = if target > cwnd {
+            (target - cwnd) / cwnd * (len as f64 / mss)
+        } else {
+            0.0
+        };
+        let new_cwnd = (cwnd + increment).max(self.w_est);
+        self.cwnd = ((new_cwnd * mss) as usize).max(self.cwnd);
+    }
+
+    fn on_duplicate_ack(&mut self, _now: Instant, count: u8) {
+        if count == 3 {
+            self.on_congestion();
+            self.cwnd = self.ssthresh;
+        }
+    }
+
+    fn on_retransmit(&mut self, _now: Instant) {
+        self.on_congestion();
+        self.cwnd = self.mss;
+    }
+
+    fn on_ecn_echo(&mut self, _now: Instant) {
+        self.on_congestion();
+        self.cwnd = self.ssthresh;
+    }
+}
+
+/// Return the cube root of a non-negative `x`, using Newton's method, since `f64::cbrt`
+/// is not available without the standard library.
+#[cfg(feature = "socket-tcp-cubic")]
+fn cbrt(x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let mut y = if x > 1.0 { x / 3.0 } else { 1.0 };
+    for _ in 0..32 {
+        let next = y - (y * y * y - x) / (3.0 * y * y);
+        if next == y {
+            break;
+        }
+        y = next;
+    }
+    y
+}
+
+/// Any of the congestion control algorithms, stored inline.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum AnyController {
+    None(NoControl),
+    Reno(Reno),
+    #[cfg(feature = "socket-tcp-cubic")]
+    Cubic(Cubic),
+}
+
+impl AnyController {
+    pub(super) fn new(algorithm: CongestionControl) -> AnyController {
+        match algorithm {
+            CongestionControl::None => AnyController::None(NoControl),
+            CongestionControl::Reno => AnyController::Reno(Reno::default()),
+            #[cfg(feature = "socket-tcp-cubic")]
+            CongestionControl::Cubic => AnyController::Cubic(Cubic::default()),
+        }
+    }
+
+    pub(super) fn algorithm(&self) -> CongestionControl {
+        match self {
+            AnyController::None(_) => CongestionControl::None,
+            AnyController::Reno(_) => CongestionControl::Reno,
+            #[cfg(feature = "socket-tcp-cubic")]
+            AnyController::Cubic(_) => CongestionControl::Cubic,
+        }
+    }
+
+    pub(super) fn inner(&self) -> &dyn Controller {
+        match self {
+            AnyController::None(controller) => controller,
+            AnyController::Reno(controller) => controller,
+            #[cfg(feature = "socket-tcp-cubic")]
+            AnyController::Cubic(controller) => controller,
+        }
+    }
+
+    pub(super) fn inner_mut(&mut self) -> &mut dyn Controller {
+        match self {
+            AnyController::None(controller) => controller,
+            AnyController::Reno(controller) => controller,
+            #[cfg(feature = "socket-tcp-cubic")]
+            AnyController::Cubic(controller) => controller,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "socket-tcp-cubic")]
+    use crate::time::Duration;
+
+    const MSS: usize = 1000;
+
+    fn rtte() -> RttEstimator {
+        RttEstimator::default()
+    }
+
+    #[test]
+    fn test_reno_slow_start() {
+        let mut reno = Reno::default();
+        reno.set_mss(MSS);
+        assert_eq!(reno.window(), 4 * MSS);
+        reno.on_ack(Instant::ZERO, MSS, &rtte());
+        reno.on_ack(Instant::ZERO, 2 * MSS, &rtte());
+        assert_eq!(reno.window(), 6 * MSS);
+    }
+
+    #[test]
+    fn test_reno_congestion_avoidance() {
+        let mut reno = Reno::default();
+        reno.set_mss(MSS);
+        reno.on_retransmit(Instant::ZERO);
+        assert_eq!(reno.window(), MSS);
+        assert_eq!(reno.ssthresh, 2 * MSS);
+
+        reno.on_ack(Instant::ZERO, MSS, &rtte());
+        assert_eq!(reno.window(), 2 * MSS);
+        reno.on_ack(Instant::ZERO, MSS, &rtte());
+        assert_eq!(reno.window(), 2 * MSS + MSS / 2);
+    }
+
+    #[test]
+    fn test_reno_fast_recovery() {
+        let mut reno = Reno::default();
+        reno.set_mss(MSS);
+        for count in 1..=4 {
+            reno.on_duplicate_ack(Instant::ZERO, count);
+        }
+        assert_eq!(reno.window(), 2 * MSS + 3 * MSS + MSS);
+        reno.on_ack(Instant::ZERO, MSS, &rtte());
+        assert_eq!(reno.window(), 2 * MSS);
+    }
+
+    #[test]
+    fn test_reno_ecn_echo() {
+        let mut reno = Reno::default();
+        reno.set_mss(MSS);
+        for _ in 0..4 {
+            reno.on_ack(Instant::ZERO, MSS, &rtte());
+        }
+        assert_eq!(reno.window(), 8 * MSS);
+        reno.on_ecn_echo(Instant::ZERO);
+        assert_eq!(reno.window(), 4 * MSS);
+        assert_eq!(reno.ssthresh, 4 * MSS);
+    }
+
+    #[test]
+    fn test_reno_remote_window() {
+        let mut reno = Reno::default();
+        reno.set_mss(MSS);
+        reno.set_remote_window(4 * MSS);
+        reno.on_ack(Instant::ZERO, MSS, &rtte());
+        assert_eq!(reno.window(), 4 * MSS);
+    }
+
+    #[cfg(feature = "socket-tcp-cubic")]
+    #[test]
+    fn test_cbrt() {
+        assert_eq!(cbrt(0.0), 0.0);
+        assert!((cbrt(27.0) - 3.0) < 1e-9 && (3.0 - cbrt(27.0)) < 1e-9);
+        assert!((cbrt(0.125) - 0.5) < 1e-9 && (0.5 - cbrt(0.125)) < 1e-9);
+    }
+
+    #[cfg(feature = "socket-tcp-cubic")]
+    #[test]
+    fn test_cubic_recovery() {
+        let mut cubic = Cubic::default();
+        cubic.set_mss(MSS);
+        for _ in 0..16 {
+            cubic.on_ack(Instant::ZERO, MSS, &rtte());
+        }
+        assert_eq!(cubic.window(), 20 * MSS);
+
+        for count in 1..=3 {
+            cubic.on_duplicate_ack(Instant::ZERO, count);
+        }
+        assert_eq!(cubic.window(), 14 * MSS);
+
+        // The window grows back towards its previous maximum, and then beyond it.
+        let mut now = Instant::ZERO;
+        let mut last = cubic.window();
+        for _ in 0..100 {
+            now += Duration::from_millis(100);
+            cubic.on_ack(now, MSS, &rtte());
+            assert!(cubic.window() >= last);
+            last = cubic.window();
+        }
+        assert!(cubic.window() > 20 * MSS);
+    }
+}
@@ -1,19 +1,59 @@
 use core::cmp::min;
+use core::fmt;
+use core::mem;
 #[cfg(feature = "async")]
 use core::task::Waker;
 
-use crate::iface::Context;
+use crate::iface::{Context, RebindPolicy};
 use crate::socket::PollAt;
+#[cfg(feature = "stats")]
+use crate::socket::Stats;
 #[cfg(feature = "async")]
 use crate::socket::WakerRegistration;
-use crate::storage::Empty;
-use crate::wire::{IpEndpoint, IpListenEndpoint, IpProtocol, IpRepr, UdpRepr};
+use crate::storage::{Empty, MemoryUsage};
+use crate::wire::{IpAddress, IpEndpoint, IpListenEndpoint, IpProtocol, IpRepr, UdpRepr};
+
+/// Metadata of a UDP packet.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct UdpMetadata {
+    /// The remote endpoint the packet was received from or is sent to.
+    pub endpoint: IpEndpoint,
+    /// The time-to-live (IPv4) or hop limit (IPv6) of a received packet, or to send
+    /// the packet with.
+    ///
+    /// A packet to be sent with a hop limit of 0 uses the hop limit of the socket.
+    pub hop_limit: u8,
+    /// The Differentiated Services Code Point of a received packet, or to send the packet with.
+    pub dscp: u8,
+    /// The Explicit Congestion Notification field of a received packet, or to send
+    /// the packet with.
+    pub ecn: u8,
+    /// The destination address of a received packet, which is either one of the addresses
+    /// of the interface, a broadcast address, or a multicast group joined by the interface.
+    ///
+    /// This is `None` for packets to be sent.
+    pub local_address: Option<IpAddress>,
+}
+
+impl From<IpEndpoint> for UdpMetadata {
+    fn from(endpoint: IpEndpoint) -> UdpMetadata {
+        UdpMetadata {
+            endpoint,
+            hop_limit: 0,
+            dscp: 0,
+            ecn: 0,
+            local_address: None,
+        }
+    }
+}
 
 /// A UDP packet metadata.
-pub type PacketMetadata = crate::storage::PacketMetadata<IpEndpoint>;
+pub type PacketMetadata = crate::storage::PacketMetadata<UdpMetadata>;
 
 /// A UDP packet ring buffer.
-pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, IpEndpoint>;
+pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, UdpMetadata>;
 
 /// Error returned by [`Socket::bind`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -23,6 +63,27 @@ pub enum BindError {
     Unaddressable,
 }
 
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BindError::InvalidState => write!(f, "invalid state"),
+            BindError::Unaddressable => write!(f, "unaddressable destination"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BindError {}
+
+impl From<BindError> for crate::Error {
+    fn from(err: BindError) -> Self {
+        match err {
+            BindError::InvalidState => crate::Error::Illegal(crate::Layer::Transport),
+            BindError::Unaddressable => crate::Error::Unaddressable(crate::Layer::Transport),
+        }
+    }
+}
+
 /// Error returned by [`Socket::send`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -31,6 +92,27 @@ pub enum SendError {
     BufferFull,
 }
 
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendError::Unaddressable => write!(f, "unaddressable destination"),
+            SendError::BufferFull => write!(f, "buffer full"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SendError {}
+
+impl From<SendError> for crate::Error {
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::Unaddressable => crate::Error::Unaddressable(crate::Layer::Transport),
+            SendError::BufferFull => crate::Error::Exhausted(crate::Layer::Transport),
+        }
+    }
+}
+
 /// Error returned by [`Socket::recv`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -38,6 +120,25 @@ pub enum RecvError {
     Exhausted,
 }
 
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecvError::Exhausted => write!(f, "buffer empty"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RecvError {}
+
+impl From<RecvError> for crate::Error {
+    fn from(err: RecvError) -> Self {
+        match err {
+            RecvError::Exhausted => crate::Error::Exhausted(crate::Layer::Transport),
+        }
+    }
+}
+
 /// A User Datagram Protocol socket.
 ///
 /// A UDP socket is bound to a specific endpoint, and owns transmit and receive
@@ -49,6 +150,16 @@ pub struct Socket<'a> {
     tx_buffer: PacketBuffer<'a>,
     /// The time-to-live (IPv4) or hop limit (IPv6) value used in outgoing packets.
     hop_limit: Option<u8>,
+    /// The time-to-live (IPv4) or hop limit (IPv6) value used in outgoing multicast packets.
+    multicast_hop_limit: Option<u8>,
+    /// The source address of outgoing multicast packets.
+    multicast_source_addr: Option<IpAddress>,
+    multicast_loop: bool,
+    /// The datagram at the head of the transmit buffer was already looped back.
+    looped_back: bool,
+    broadcast: bool,
+    #[cfg(feature = "stats")]
+    stats: Stats,
     #[cfg(feature = "async")]
     rx_waker: WakerRegistration,
     #[cfg(feature = "async")]
@@ -63,6 +174,13 @@ impl<'a> Socket<'a> {
             rx_buffer,
             tx_buffer,
             hop_limit: None,
+            multicast_hop_limit: None,
+            multicast_source_addr: None,
+            multicast_loop: false,
+            looped_back: false,
+            broadcast: true,
+            #[cfg(feature = "stats")]
+            stats: Stats::default(),
             #[cfg(feature = "async")]
             rx_waker: WakerRegistration::new(),
             #[cfg(feature = "async")]
@@ -138,8 +256,93 @@ impl<'a> Socket<'a> {
         self.hop_limit = hop_limit
     }
 
+    /// Return the time-to-live (IPv4) or hop limit (IPv6) value used in outgoing
+    /// multicast packets.
+    ///
+    /// See also the [set_multicast_hop_limit](#method.set_multicast_hop_limit) method
+    pub fn multicast_hop_limit(&self) -> Option<u8> {
+        self.multicast_hop_limit
+    }
+
+    /// Set the time-to-live (IPv4) or hop limit (IPv6) value used in outgoing multicast
+    /// packets, which often should not leave the local network.
+    ///
+    /// A socket without an explicitly set multicast hop limit value uses its
+    /// [hop limit](#method.set_hop_limit) for multicast packets too. Either is overridden by
+    /// the hop limit in the metadata of a packet, if any.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if a hop limit value of 0 is given. See [RFC 1122 § 3.2.1.7].
+    ///
+    /// [RFC 1122 § 3.2.1.7]: https://tools.ietf.org/html/rfc1122#section-3.2.1.7
+    pub fn set_multicast_hop_limit(&mut self, hop_limit: Option<u8>) {
+        // A host MUST NOT send a datagram with a hop limit value of 0
+        if let Some(0) = hop_limit {
+            panic!("the time-to-live value of a packet must not be zero")
+        }
+
+        self.multicast_hop_limit = hop_limit
+    }
+
+    /// Return the source address of outgoing multicast packets.
+    ///
+    /// See also the [set_multicast_source_addr](#method.set_multicast_source_addr) method
+    pub fn multicast_source_addr(&self) -> Option<IpAddress> {
+        self.multicast_source_addr
+    }
+
+    /// Set the source address of outgoing multicast packets, i.e. the address of the
+    /// interface they are sent from.
+    ///
+    /// By default, and when the address is not assigned to the interface, multicast packets
+    /// are sent from the address the socket is bound to, or if it is not bound to a unicast
+    /// address, from the first address of the interface with the right IP version, like any
+    /// other packet.
+    pub fn set_multicast_source_addr(&mut self, addr: Option<IpAddress>) {
+        self.multicast_source_addr = addr
+    }
+
+    /// Return whether multicast datagrams sent by the socket are looped back.
+    ///
+    /// See also the [set_multicast_loop](#method.set_multicast_loop) method
+    pub fn multicast_loop(&self) -> bool {
+        self.multicast_loop
+    }
+
+    /// Set whether multicast datagrams sent by the socket are looped back.
+    ///
+    /// When enabled, a datagram sent to a multicast group the interface is a member of is
+    /// also received by the UDP socket in the same socket set that would receive it from
+    /// the network, which may be this one. It is disabled by default.
+    pub fn set_multicast_loop(&mut self, enabled: bool) {
+        self.multicast_loop = enabled
+    }
+
+    /// Return whether the socket receives broadcast datagrams.
+    ///
+    /// See also the [set_broadcast](#method.set_broadcast) method
+    pub fn broadcast(&self) -> bool {
+        self.broadcast
+    }
+
+    /// Set whether the socket receives broadcast datagrams.
+    ///
+    /// Both datagrams sent to the limited broadcast address and to the broadcast address
+    /// of a subnet the interface has an address in, e.g. Wake-on-LAN packets, are delivered
+    /// to a socket bound to a matching port, regardless of the address it is bound to,
+    /// unless this is disabled. It is enabled by default.
+    pub fn set_broadcast(&mut self, broadcast: bool) {
+        self.broadcast = broadcast
+    }
+
     /// Bind the socket to the given endpoint.
     ///
+    /// A socket bound to a unicast address only receives the datagrams sent to that address,
+    /// as well as multicast and broadcast datagrams, and sends datagrams from it. A socket
+    /// bound to a multicast address only receives the datagrams sent to that group, and
+    /// broadcast datagrams; the interface must be a member of the group.
+    ///
     /// This function returns `Err(Error::Illegal)` if the socket was open
     /// (see [is_open](#method.is_open)), and `Err(Error::Unaddressable)`
     /// if the port in the given endpoint is zero.
@@ -180,6 +383,22 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Handle the removal of the bound address from the interface, and return whether
+    /// the socket was affected.
+    pub(crate) fn rebind(&mut self, cx: &Context, policy: RebindPolicy) -> bool {
+        match self.endpoint.addr {
+            Some(addr) if addr.is_unicast() && !cx.has_ip_addr(addr) => {
+                net_debug!("bound address {} was removed", addr);
+            }
+            _ => return false,
+        }
+        match policy {
+            RebindPolicy::Abort => self.close(),
+            RebindPolicy::Reconnect => self.endpoint.addr = None,
+        }
+        true
+    }
+
     /// Check whether the socket is open.
     #[inline]
     pub fn is_open(&self) -> bool {
@@ -222,6 +441,30 @@ impl<'a> Socket<'a> {
         self.tx_buffer.payload_capacity()
     }
 
+    /// Return the number of bytes of receive buffer storage used and committed by the socket.
+    #[inline]
+    pub fn recv_memory_usage(&self) -> MemoryUsage {
+        self.rx_buffer.memory_usage()
+    }
+
+    /// Return the number of bytes of transmit buffer storage used and committed by the socket.
+    #[inline]
+    pub fn send_memory_usage(&self) -> MemoryUsage {
+        self.tx_buffer.memory_usage()
+    }
+
+    /// Return the counters of the traffic of the socket.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Reset the counters of the traffic of the socket.
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+
     /// Enqueue a packet to be sent to a given remote endpoint, and return a pointer
     /// to its payload.
     ///
@@ -234,6 +477,21 @@ impl<'a> Socket<'a> {
         size: usize,
         remote_endpoint: IpEndpoint,
     ) -> Result<&mut [u8], SendError> {
+        self.send_with_metadata(size, remote_endpoint.into())
+    }
+
+    /// Enqueue a packet to be sent as described by `metadata`, and return a pointer
+    /// to its payload.
+    ///
+    /// Besides the remote endpoint, the metadata sets the hop limit, overriding the one of
+    /// the socket unless it is 0, as well as the DSCP and ECN fields the packet is sent with.
+    /// The local address of the metadata is ignored. See also [send](#method.send).
+    pub fn send_with_metadata(
+        &mut self,
+        size: usize,
+        metadata: UdpMetadata,
+    ) -> Result<&mut [u8], SendError> {
+        let remote_endpoint = metadata.endpoint;
         if self.endpoint.port == 0 {
             return Err(SendError::Unaddressable);
         }
@@ -246,7 +504,7 @@ impl<'a> Socket<'a> {
 
         let payload_buf = self
             .tx_buffer
-            .enqueue(size, remote_endpoint)
+            .enqueue(size, metadata)
             .map_err(|_| SendError::BufferFull)?;
 
         net_trace!(
@@ -284,7 +542,7 @@ impl<'a> Socket<'a> {
 
         let size = self
             .tx_buffer
-            .enqueue_with_infallible(max_size, remote_endpoint, f)
+            .enqueue_with_infallible(max_size, remote_endpoint.into(), f)
             .map_err(|_| SendError::BufferFull)?;
 
         net_trace!(
@@ -309,21 +567,50 @@ impl<'a> Socket<'a> {
         Ok(())
     }
 
+    /// Enqueue several packets to be sent, each to its own remote endpoint, and fill them
+    /// from slices. Return the number of packets enqueued.
+    ///
+    /// Packets are enqueued in order until one cannot be; that packet and the following ones
+    /// are left to the caller. An error is only returned if not even the first packet could
+    /// be enqueued, for the same reasons as [send](#method.send).
+    pub fn send_batch(&mut self, datagrams: &[(&[u8], IpEndpoint)]) -> Result<usize, SendError> {
+        for (index, &(data, remote_endpoint)) in datagrams.iter().enumerate() {
+            match self.send_slice(data, remote_endpoint) {
+                Ok(()) => (),
+                Err(err) if index == 0 => return Err(err),
+                Err(_) => return Ok(index),
+            }
+        }
+        Ok(datagrams.len())
+    }
+
     /// Dequeue a packet received from a remote endpoint, and return the endpoint as well
     /// as a pointer to the payload.
     ///
     /// This function returns `Err(Error::Exhausted)` if the receive buffer is empty.
     pub fn recv(&mut self) -> Result<(&[u8], IpEndpoint), RecvError> {
-        let (remote_endpoint, payload_buf) =
-            self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
+        let (payload_buf, metadata) = self.recv_with_metadata()?;
+        Ok((payload_buf, metadata.endpoint))
+    }
+
+    /// Dequeue a packet received from a remote endpoint, and return its metadata as well
+    /// as a pointer to the payload.
+    ///
+    /// Besides the remote endpoint, the metadata includes the hop limit the packet was
+    /// received with, which allows implementing the Generalized TTL Security Mechanism
+    /// (RFC 5082), and the destination address of the packet, which allows telling apart
+    /// unicast, broadcast and multicast packets, or finding out which address of
+    /// a multihomed interface the packet was sent to. See also [recv](#method.recv).
+    pub fn recv_with_metadata(&mut self) -> Result<(&[u8], UdpMetadata), RecvError> {
+        let (metadata, payload_buf) = self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
 
         net_trace!(
             "udp:{}:{}: receive {} buffered octets",
             self.endpoint,
-            remote_endpoint,
+            metadata.endpoint,
             payload_buf.len()
         );
-        Ok((payload_buf, remote_endpoint))
+        Ok((payload_buf, metadata))
     }
 
     /// Dequeue a packet received from a remote endpoint, copy the payload into the given slice,
@@ -337,6 +624,39 @@ impl<'a> Socket<'a> {
         Ok((length, endpoint))
     }
 
+    /// Dequeue several packets received from remote endpoints, and copy them into the given
+    /// slices. Return the number of packets dequeued.
+    ///
+    /// Each packet is copied into the next slice of `buffers`, which is then shortened to
+    /// the amount of octets copied, and its metadata is stored into the next element of
+    /// `metadata`. Packets larger than their slice are truncated, as with
+    /// [recv_slice](#method.recv_slice). At most as many packets as there are slices
+    /// and metadata elements are dequeued.
+    ///
+    /// This function returns `Err(Error::Exhausted)` if the receive buffer is empty.
+    pub fn recv_batch(
+        &mut self,
+        buffers: &mut [&mut [u8]],
+        metadata: &mut [Option<UdpMetadata>],
+    ) -> Result<usize, RecvError> {
+        let mut count = 0;
+        for (buffer, metadata) in buffers.iter_mut().zip(metadata.iter_mut()) {
+            let (payload_buf, packet_metadata) = match self.recv_with_metadata() {
+                Ok(result) => result,
+                Err(_) => break,
+            };
+            let length = min(buffer.len(), payload_buf.len());
+            buffer[..length].copy_from_slice(&payload_buf[..length]);
+            *buffer = &mut mem::take(buffer)[..length];
+            *metadata = Some(packet_metadata);
+            count += 1;
+        }
+        if count == 0 {
+            return Err(RecvError::Exhausted);
+        }
+        Ok(count)
+    }
+
     /// Peek at a packet received from a remote endpoint, and return the endpoint as well
     /// as a pointer to the payload without removing the packet from the receive buffer.
     /// This function otherwise behaves identically to [recv](#method.recv).
@@ -344,17 +664,18 @@ impl<'a> Socket<'a> {
     /// It returns `Err(Error::Exhausted)` if the receive buffer is empty.
     pub fn peek(&mut self) -> Result<(&[u8], &IpEndpoint), RecvError> {
         let endpoint = self.endpoint;
-        self.rx_buffer.peek().map_err(|_| RecvError::Exhausted).map(
-            |(remote_endpoint, payload_buf)| {
+        self.rx_buffer
+            .peek()
+            .map_err(|_| RecvError::Exhausted)
+            .map(|(metadata, payload_buf)| {
                 net_trace!(
                     "udp:{}:{}: peek {} buffered octets",
                     endpoint,
-                    remote_endpoint,
+                    metadata.endpoint,
                     payload_buf.len()
                 );
-                (payload_buf, remote_endpoint)
-            },
-        )
+                (payload_buf, &metadata.endpoint)
+            })
     }
 
     /// Peek at a packet received from a remote endpoint, copy the payload into the given slice,
@@ -370,19 +691,22 @@ impl<'a> Socket<'a> {
         Ok((length, endpoint))
     }
 
-    pub(crate) fn accepts(&self, _cx: &mut Context, ip_repr: &IpRepr, repr: &UdpRepr) -> bool {
+    pub(crate) fn accepts(&self, cx: &mut Context, ip_repr: &IpRepr, repr: &UdpRepr) -> bool {
         if self.endpoint.port != repr.dst_port {
             return false;
         }
-        if self.endpoint.addr.is_some()
-            && self.endpoint.addr != Some(ip_repr.dst_addr())
-            && !ip_repr.dst_addr().is_broadcast()
-            && !ip_repr.dst_addr().is_multicast()
-        {
-            return false;
+        if cx.is_broadcast(&ip_repr.dst_addr()) {
+            return self.broadcast;
+        }
+        match self.endpoint.addr {
+            Some(addr)
+                if addr != ip_repr.dst_addr()
+                    && (addr.is_multicast() || !ip_repr.dst_addr().is_multicast()) =>
+            {
+                false
+            }
+            _ => true,
         }
-
-        true
     }
 
     pub(crate) fn process(
@@ -408,7 +732,12 @@ impl<'a> Socket<'a> {
             size
         );
 
-        match self.rx_buffer.enqueue(size, remote_endpoint) {
+        let metadata = Self::received_metadata(ip_repr, repr);
+
+        #[cfg(feature = "stats")]
+        self.stats.record_rx(size);
+
+        match self.rx_buffer.enqueue(size, metadata) {
             Ok(buf) => buf.copy_from_slice(payload),
             Err(_) => net_trace!(
                 "udp:{}:{}: buffer full, dropped incoming packet",
@@ -421,27 +750,138 @@ impl<'a> Socket<'a> {
         self.rx_waker.wake();
     }
 
+    fn received_metadata(ip_repr: &IpRepr, repr: &UdpRepr) -> UdpMetadata {
+        UdpMetadata {
+            endpoint: IpEndpoint::new(ip_repr.src_addr(), repr.src_port),
+            hop_limit: ip_repr.hop_limit(),
+            dscp: ip_repr.traffic_class() >> 2,
+            ecn: ip_repr.traffic_class() & 0x03,
+            local_address: Some(ip_repr.dst_addr()),
+        }
+    }
+
+    /// Return the headers of a datagram to be sent with the given metadata, or `None`
+    /// if there is no suitable source address.
+    fn send_reprs(
+        &self,
+        cx: &mut Context,
+        metadata: &UdpMetadata,
+        payload_len: usize,
+    ) -> Option<(IpRepr, UdpRepr)> {
+        let remote_endpoint = metadata.endpoint;
+        let multicast = remote_endpoint.addr.is_multicast();
+        let src_addr = match (self.endpoint.addr, self.multicast_source_addr) {
+            (Some(addr), _) if !addr.is_multicast() => addr,
+            (_, Some(addr)) if multicast && cx.has_ip_addr(addr) => addr,
+            _ => cx.get_source_address(remote_endpoint.addr)?,
+        };
+        let hop_limit = match metadata.hop_limit {
+            0 if multicast => self.multicast_hop_limit.or(self.hop_limit).unwrap_or(64),
+            0 => self.hop_limit.unwrap_or(64),
+            hop_limit => hop_limit,
+        };
+
+        let repr = UdpRepr {
+            src_port: self.endpoint.port,
+            dst_port: remote_endpoint.port,
+        };
+        let mut ip_repr = IpRepr::new(
+            src_addr,
+            remote_endpoint.addr,
+            IpProtocol::Udp,
+            repr.header_len() + payload_len,
+            hop_limit,
+        );
+        ip_repr.set_traffic_class((metadata.dscp << 2) | (metadata.ecn & 0x03));
+        Some((ip_repr, repr))
+    }
+
+    /// Loop back the datagram about to be sent, if it is sent to a multicast group the
+    /// interface is a member of and the socket loops multicast datagrams back. Each datagram
+    /// is only looped back once.
+    ///
+    /// The datagram is passed to `f`, along with the specificity of the socket endpoint if
+    /// the socket accepts it, and `f` returns whether another socket received it. Otherwise,
+    /// the socket receives it if it accepts it.
+    pub(crate) fn loop_back<F>(&mut self, cx: &mut Context, f: F)
+    where
+        F: FnOnce(&mut Context, &IpRepr, &UdpRepr, &[u8], Option<u8>) -> bool,
+    {
+        if !self.multicast_loop || self.looped_back {
+            return;
+        }
+        let (metadata, payload_len) = match self.tx_buffer.peek() {
+            Ok((metadata, payload)) => (*metadata, payload.len()),
+            Err(Empty) => return,
+        };
+        let dst_addr = metadata.endpoint.addr;
+        if !dst_addr.is_multicast() || !cx.has_multicast_group(dst_addr) {
+            return;
+        }
+        let (ip_repr, repr) = match self.send_reprs(cx, &metadata, payload_len) {
+            Some(reprs) => reprs,
+            None => return,
+        };
+        let specificity = self
+            .accepts(cx, &ip_repr, &repr)
+            .then_some(self.endpoint.specificity());
+        self.looped_back = true;
+
+        net_trace!(
+            "udp:{}:{}: looping back {} octets",
+            self.endpoint,
+            metadata.endpoint,
+            payload_len
+        );
+        let payload = match self.tx_buffer.peek() {
+            Ok((_, payload)) => payload,
+            Err(Empty) => return,
+        };
+        if f(cx, &ip_repr, &repr, payload, specificity) || specificity.is_none() {
+            return;
+        }
+
+        #[cfg(feature = "stats")]
+        self.stats.record_rx(payload_len);
+        match self
+            .rx_buffer
+            .enqueue(payload_len, Self::received_metadata(&ip_repr, &repr))
+        {
+            Ok(buf) => buf.copy_from_slice(payload),
+            Err(_) => net_trace!(
+                "udp:{}:{}: buffer full, dropped looped back packet",
+                self.endpoint,
+                metadata.endpoint
+            ),
+        }
+
+        #[cfg(feature = "async")]
+        self.rx_waker.wake();
+    }
+
     pub(crate) fn dispatch<F, E>(&mut self, cx: &mut Context, emit: F) -> Result<(), E>
     where
         F: FnOnce(&mut Context, (IpRepr, UdpRepr, &[u8])) -> Result<(), E>,
     {
         let endpoint = self.endpoint;
-        let hop_limit = self.hop_limit.unwrap_or(64);
-
-        let res = self.tx_buffer.dequeue_with(|remote_endpoint, payload_buf| {
-            let src_addr = match endpoint.addr {
-                Some(addr) => addr,
-                None => match cx.get_source_address(remote_endpoint.addr) {
-                    Some(addr) => addr,
-                    None => {
-                        net_trace!(
-                            "udp:{}:{}: cannot find suitable source address, dropping.",
-                            endpoint,
-                            remote_endpoint
-                        );
-                        return Ok(());
-                    }
-                },
+        let (metadata, payload_len) = match self.tx_buffer.peek() {
+            Ok((metadata, payload)) => (*metadata, payload.len()),
+            Err(Empty) => return Ok(()),
+        };
+        let remote_endpoint = metadata.endpoint;
+        let reprs = self.send_reprs(cx, &metadata, payload_len);
+
+        let res = self.tx_buffer.dequeue_with(|_, payload_buf| {
+            let (ip_repr, repr) = match reprs {
+                Some(reprs) => reprs,
+                None => {
+                    net_trace!(
+                        "udp:{}:{}: cannot find suitable source address, dropping.",
+                        endpoint,
+                        remote_endpoint
+                    );
+                    return Ok(());
+                }
             };
 
             net_trace!(
@@ -451,23 +891,16 @@ impl<'a> Socket<'a> {
                 payload_buf.len()
             );
 
-            let repr = UdpRepr {
-                src_port: endpoint.port,
-                dst_port: remote_endpoint.port,
-            };
-            let ip_repr = IpRepr::new(
-                src_addr,
-                remote_endpoint.addr,
-                IpProtocol::Udp,
-                repr.header_len() + payload_buf.len(),
-                hop_limit,
-            );
-            emit(cx, (ip_repr, repr, payload_buf))
+            emit(cx, (ip_repr, repr, payload_buf))?;
+            #[cfg(feature = "stats")]
+            self.stats.record_tx(payload_buf.len());
+            Ok(())
         });
         match res {
             Err(Empty) => Ok(()),
             Ok(Err(e)) => Err(e),
             Ok(Ok(())) => {
+                self.looped_back = false;
                 #[cfg(feature = "async")]
                 self.tx_waker.wake();
                 Ok(())
@@ -488,7 +921,7 @@ impl<'a> Socket<'a> {
 mod test {
     use super::*;
     use crate::wire::{IpRepr, UdpRepr};
-    use crate::Error;
+    use crate::{Error, Layer};
 
     fn buffer(packets: usize) -> PacketBuffer<'static> {
         PacketBuffer::new(vec![PacketMetadata::EMPTY; packets], vec![0; 16 * packets])
@@ -545,6 +978,7 @@ mod test {
         next_header: IpProtocol::Udp,
         payload_len: 8 + 6,
         hop_limit: 64,
+        traffic_class: 0,
     });
 
     pub const REMOTE_IP_REPR: IpRepr = IpReprIpvX(IpvXRepr {
@@ -553,6 +987,7 @@ mod test {
         next_header: IpProtocol::Udp,
         payload_len: 8 + 6,
         hop_limit: 64,
+        traffic_class: 0,
     });
 
     pub const BAD_IP_REPR: IpRepr = IpReprIpvX(IpvXRepr {
@@ -561,6 +996,7 @@ mod test {
         next_header: IpProtocol::Udp,
         payload_len: 8 + 6,
         hop_limit: 64,
+        traffic_class: 0,
     });
 
     const LOCAL_UDP_REPR: UdpRepr = UdpRepr {
@@ -652,9 +1088,9 @@ mod test {
                 assert_eq!(ip_repr, LOCAL_IP_REPR);
                 assert_eq!(udp_repr, LOCAL_UDP_REPR);
                 assert_eq!(payload, PAYLOAD);
-                Err(Error::Unaddressable)
+                Err(Error::Unaddressable(Layer::Link))
             }),
-            Err(Error::Unaddressable)
+            Err(Error::Unaddressable(Layer::Link))
         );
         assert!(!socket.can_send());
 
@@ -691,6 +1127,104 @@ mod test {
         assert!(!socket.can_recv());
     }
 
+    #[test]
+    fn test_recv_with_metadata() {
+        let mut socket = socket(buffer(1), buffer(0));
+        let mut cx = Context::mock();
+
+        assert_eq!(socket.bind(LOCAL_PORT), Ok(()));
+
+        socket.process(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR, PAYLOAD);
+        let (payload, metadata) = socket.recv_with_metadata().unwrap();
+        assert_eq!(payload, PAYLOAD);
+        assert_eq!(metadata.endpoint, REMOTE_END);
+        assert_eq!(metadata.hop_limit, 64);
+        assert_eq!(metadata.dscp, 0);
+        assert_eq!(metadata.local_address, Some(LOCAL_ADDR.into()));
+
+        let mut ip_repr = REMOTE_IP_REPR;
+        ip_repr.set_traffic_class((46 << 2) | 0x01);
+        socket.process(&mut cx, &ip_repr, &REMOTE_UDP_REPR, PAYLOAD);
+        let (_, metadata) = socket.recv_with_metadata().unwrap();
+        assert_eq!(metadata.dscp, 46);
+        assert_eq!(metadata.ecn, 0x01);
+    }
+
+    #[test]
+    fn test_send_with_metadata() {
+        let mut s = socket(buffer(0), buffer(2));
+        let mut cx = Context::mock();
+
+        assert_eq!(s.bind(LOCAL_END), Ok(()));
+
+        let mut metadata = UdpMetadata::from(REMOTE_END);
+        metadata.hop_limit = 255;
+        metadata.dscp = 46;
+        s.send_with_metadata(6, metadata)
+            .unwrap()
+            .copy_from_slice(PAYLOAD);
+        assert_eq!(s.send_slice(PAYLOAD, REMOTE_END), Ok(()));
+
+        assert_eq!(
+            s.dispatch(&mut cx, |_, (ip_repr, _, _)| {
+                assert_eq!(ip_repr.hop_limit(), 255);
+                assert_eq!(ip_repr.traffic_class(), 46 << 2);
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+        assert_eq!(
+            s.dispatch(&mut cx, |_, (ip_repr, _, _)| {
+                assert_eq!(ip_repr.hop_limit(), 64);
+                assert_eq!(ip_repr.traffic_class(), 0);
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_send_batch() {
+        let mut socket = socket(buffer(0), buffer(2));
+        assert_eq!(socket.bind(LOCAL_END), Ok(()));
+
+        let datagrams = [
+            (PAYLOAD, REMOTE_END),
+            (&b"123"[..], REMOTE_END),
+            (PAYLOAD, REMOTE_END),
+        ];
+        assert_eq!(socket.send_batch(&datagrams), Ok(2));
+        assert_eq!(socket.send_batch(&datagrams), Err(SendError::BufferFull));
+        assert_eq!(socket.send_batch(&[]), Ok(0));
+    }
+
+    #[test]
+    fn test_recv_batch() {
+        let mut socket = socket(buffer(3), buffer(0));
+        let mut cx = Context::mock();
+
+        assert_eq!(socket.bind(LOCAL_PORT), Ok(()));
+
+        let (mut buf1, mut buf2) = ([0; 16], [0; 4]);
+        let mut buffers = [&mut buf1[..], &mut buf2[..]];
+        let mut metadata = [None; 2];
+        assert_eq!(
+            socket.recv_batch(&mut buffers, &mut metadata),
+            Err(RecvError::Exhausted)
+        );
+
+        for _ in 0..3 {
+            socket.process(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR, PAYLOAD);
+        }
+        assert_eq!(socket.recv_batch(&mut buffers, &mut metadata), Ok(2));
+        assert_eq!(buffers, [&b"abcdef"[..], &b"abcd"[..]]);
+        assert_eq!(
+            metadata.map(|m| m.map(|m| m.endpoint)),
+            [Some(REMOTE_END); 2]
+        );
+        assert!(socket.can_recv());
+    }
+
     #[test]
     fn test_peek_process() {
         let mut socket = socket(buffer(1), buffer(0));
@@ -757,6 +1291,7 @@ mod test {
                         next_header: IpProtocol::Udp,
                         payload_len: 8 + 6,
                         hop_limit: 0x2a,
+                        traffic_class: 0,
                     })
                 );
                 Ok::<_, Error>(())
@@ -791,6 +1326,139 @@ mod test {
         assert!(!ip_bound_socket.accepts(&mut cx, &BAD_IP_REPR, &REMOTE_UDP_REPR));
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_accept_broadcast() {
+        let mut cx = Context::mock();
+
+        let mut socket = socket(buffer(1), buffer(0));
+        assert_eq!(socket.bind(LOCAL_END), Ok(()));
+
+        let ip_repr = |dst_addr| {
+            IpReprIpvX(IpvXRepr {
+                src_addr: REMOTE_ADDR,
+                dst_addr,
+                next_header: IpProtocol::Udp,
+                payload_len: 8 + 6,
+                hop_limit: 64,
+                traffic_class: 0,
+            })
+        };
+        let subnet_broadcast = ip_repr(IpvXAddress([192, 168, 1, 255]));
+        let broadcast = ip_repr(IpvXAddress::BROADCAST);
+        assert!(socket.accepts(&mut cx, &subnet_broadcast, &REMOTE_UDP_REPR));
+        assert!(socket.accepts(&mut cx, &broadcast, &REMOTE_UDP_REPR));
+
+        socket.set_broadcast(false);
+        assert!(!socket.accepts(&mut cx, &subnet_broadcast, &REMOTE_UDP_REPR));
+        assert!(!socket.accepts(&mut cx, &broadcast, &REMOTE_UDP_REPR));
+        assert!(socket.accepts(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_accept_multicast_group() {
+        let mut cx = Context::mock();
+
+        let group = IpvXAddress([224, 0, 0, 251]);
+        let ip_repr = |dst_addr| {
+            IpReprIpvX(IpvXRepr {
+                src_addr: REMOTE_ADDR,
+                dst_addr,
+                next_header: IpProtocol::Udp,
+                payload_len: 8 + 6,
+                hop_limit: 64,
+                traffic_class: 0,
+            })
+        };
+
+        // A socket bound to a unicast address receives multicast datagrams...
+        let mut ip_bound_socket = socket(buffer(1), buffer(0));
+        assert_eq!(ip_bound_socket.bind(LOCAL_END), Ok(()));
+        assert!(ip_bound_socket.accepts(&mut cx, &ip_repr(group), &REMOTE_UDP_REPR));
+
+        // ...but a socket bound to a group only receives the datagrams sent to it.
+        let mut group_bound_socket = socket(buffer(1), buffer(0));
+        assert_eq!(
+            group_bound_socket.bind((IpAddress::from(group), LOCAL_PORT)),
+            Ok(())
+        );
+        assert!(group_bound_socket.accepts(&mut cx, &ip_repr(group), &REMOTE_UDP_REPR));
+        assert!(!group_bound_socket.accepts(
+            &mut cx,
+            &ip_repr(IpvXAddress([224, 0, 0, 252])),
+            &REMOTE_UDP_REPR
+        ));
+        assert!(!group_bound_socket.accepts(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_send_multicast() {
+        let mut s = socket(buffer(0), buffer(3));
+        let mut cx = Context::mock();
+
+        assert_eq!(s.bind(LOCAL_PORT), Ok(()));
+        s.set_hop_limit(Some(0x2a));
+        s.set_multicast_hop_limit(Some(1));
+
+        let group = IpEndpoint::new(IpvXAddress([224, 0, 0, 251]).into(), REMOTE_PORT);
+        assert_eq!(s.send_slice(b"abcdef", group), Ok(()));
+        assert_eq!(s.send_slice(b"abcdef", REMOTE_END), Ok(()));
+        s.set_multicast_source_addr(Some(OTHER_ADDR.into()));
+        assert_eq!(s.send_slice(b"abcdef", group), Ok(()));
+
+        let mut dispatch = |hop_limit| {
+            s.dispatch(&mut cx, |_, (ip_repr, _, _)| {
+                assert_eq!(ip_repr.src_addr(), LOCAL_ADDR.into());
+                assert_eq!(ip_repr.hop_limit(), hop_limit);
+                Ok::<_, Error>(())
+            })
+        };
+        assert_eq!(dispatch(1), Ok(()));
+        assert_eq!(dispatch(0x2a), Ok(()));
+        // The source address is not assigned to the interface.
+        assert_eq!(dispatch(1), Ok(()));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-igmp")]
+    fn test_multicast_loop() {
+        let mut s = socket(buffer(1), buffer(1));
+        let mut cx = Context::mock();
+
+        assert_eq!(s.bind(LOCAL_PORT), Ok(()));
+        let group = IpEndpoint::new(IpvXAddress::MULTICAST_ALL_SYSTEMS.into(), LOCAL_PORT);
+        assert_eq!(s.send_slice(b"abcdef", group), Ok(()));
+
+        // Nothing is looped back by default.
+        s.loop_back(&mut cx, |_, _, _, _, _| panic!());
+
+        s.set_multicast_loop(true);
+        s.loop_back(&mut cx, |_, ip_repr, repr, payload, specificity| {
+            assert_eq!(ip_repr.dst_addr(), group.addr);
+            assert_eq!(repr.dst_port, LOCAL_PORT);
+            assert_eq!(payload, b"abcdef");
+            assert_eq!(specificity, Some(0));
+            false
+        });
+        // Each datagram is looped back once, even if it is not sent yet.
+        s.loop_back(&mut cx, |_, _, _, _, _| panic!());
+        assert_eq!(
+            s.recv(),
+            Ok((
+                &b"abcdef"[..],
+                IpEndpoint::new(LOCAL_ADDR.into(), LOCAL_PORT)
+            ))
+        );
+
+        assert_eq!(s.dispatch(&mut cx, |_, _| Ok::<_, Error>(())), Ok(()));
+        assert_eq!(s.send_slice(b"abcdef", group), Ok(()));
+        // The datagram is not received again if another socket receives it.
+        s.loop_back(&mut cx, |_, _, _, _, _| true);
+        assert_eq!(s.recv(), Err(RecvError::Exhausted));
+    }
+
     #[test]
     fn test_send_large_packet() {
         // buffer(4) creates a payload buffer of size 16*4
@@ -3,15 +3,23 @@
 The `storage` module provides containers for use in other modules.
 The containers support both pre-allocated memory, without the `std`
 or `alloc` crates being available, and heap-allocated memory.
+Pre-allocated memory can be declared as a `static` value with
+[StaticBuffer].
 */
 
+use core::ops::{Add, AddAssign};
+
 mod assembler;
 mod packet_buffer;
 mod ring_buffer;
+#[cfg(target_has_atomic = "8")]
+mod static_buffer;
 
 pub use self::assembler::Assembler;
 pub use self::packet_buffer::{PacketBuffer, PacketMetadata};
 pub use self::ring_buffer::RingBuffer;
+#[cfg(target_has_atomic = "8")]
+pub use self::static_buffer::StaticBuffer;
 
 /// A trait for setting a value to a known state.
 ///
@@ -29,3 +37,33 @@ pub struct Full;
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Empty;
+
+/// Memory usage of a container backed by user-provided storage, in bytes.
+///
+/// `used` is the amount of storage currently holding data, `capacity` is the total amount
+/// of storage, and `high_water` is the largest value `used` has reached.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MemoryUsage {
+    pub used: usize,
+    pub capacity: usize,
+    pub high_water: usize,
+}
+
+impl Add for MemoryUsage {
+    type Output = MemoryUsage;
+
+    fn add(self, rhs: MemoryUsage) -> MemoryUsage {
+        MemoryUsage {
+            used: self.used + rhs.used,
+            capacity: self.capacity + rhs.capacity,
+            high_water: self.high_water + rhs.high_water,
+        }
+    }
+}
+
+impl AddAssign for MemoryUsage {
+    fn add_assign(&mut self, rhs: MemoryUsage) {
+        *self = *self + rhs;
+    }
+}
@@ -1,6 +1,6 @@
 use managed::ManagedSlice;
 
-use crate::storage::{Full, RingBuffer};
+use crate::storage::{Full, MemoryUsage, RingBuffer};
 
 use super::Empty;
 
@@ -254,6 +254,12 @@ impl<'a, H> PacketBuffer<'a, H> {
         self.payload_ring.capacity()
     }
 
+    /// Return the number of bytes of metadata and payload storage used and committed
+    /// by the packet buffer.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.metadata_ring.memory_usage() + self.payload_ring.memory_usage()
+    }
+
     /// Reset the packet buffer and clear any staged.
     #[allow(unused)]
     pub(crate) fn reset(&mut self) {
@@ -414,4 +420,18 @@ mod test {
         buffer.reset();
         assert!(buffer.is_empty());
     }
+
+    #[test]
+    fn test_memory_usage() {
+        let mut buffer = buffer();
+        let metadata_size = core::mem::size_of::<PacketMetadata<()>>();
+        assert_eq!(buffer.memory_usage().capacity, 4 * metadata_size + 16);
+
+        buffer.enqueue(6, ()).unwrap();
+        buffer.enqueue(4, ()).unwrap();
+        buffer.dequeue().unwrap();
+        let usage = buffer.memory_usage();
+        assert_eq!(usage.used, metadata_size + 4);
+        assert_eq!(usage.high_water, 2 * metadata_size + 10);
+    }
 }
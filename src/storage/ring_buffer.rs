@@ -2,10 +2,10 @@
 // these functions may have side effects, and it's implemented by [RFC 1940].
 // [RFC 1940]: https://github.com/rust-lang/rust/issues/43302
 
-use core::cmp;
+use core::{cmp, mem};
 use managed::ManagedSlice;
 
-use crate::storage::Resettable;
+use crate::storage::{MemoryUsage, Resettable};
 
 use super::{Empty, Full};
 
@@ -28,6 +28,7 @@ pub struct RingBuffer<'a, T: 'a> {
     storage: ManagedSlice<'a, T>,
     read_at: usize,
     length: usize,
+    high_water: usize,
 }
 
 impl<'a, T: 'a> RingBuffer<'a, T> {
@@ -42,10 +43,13 @@ impl<'a, T: 'a> RingBuffer<'a, T> {
             storage: storage.into(),
             read_at: 0,
             length: 0,
+            high_water: 0,
         }
     }
 
     /// Clear the ring buffer.
+    ///
+    /// The high-water mark is kept; use [reset_high_water](#method.reset_high_water) to clear it.
     pub fn clear(&mut self) {
         self.read_at = 0;
         self.length = 0;
@@ -72,6 +76,27 @@ impl<'a, T: 'a> RingBuffer<'a, T> {
         self.length
     }
 
+    /// Return the largest number of elements that were in the ring buffer at once
+    /// since it was created, or since the high-water mark was last reset.
+    pub fn high_water(&self) -> usize {
+        self.high_water
+    }
+
+    /// Reset the high-water mark to the current number of elements.
+    pub fn reset_high_water(&mut self) {
+        self.high_water = self.length;
+    }
+
+    /// Return the number of bytes of storage used and committed by the ring buffer.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let size = mem::size_of::<T>();
+        MemoryUsage {
+            used: self.length * size,
+            capacity: self.capacity() * size,
+            high_water: self.high_water * size,
+        }
+    }
+
     /// Return the number of elements that can be added to the ring buffer.
     pub fn window(&self) -> usize {
         self.capacity() - self.len()
@@ -128,6 +153,7 @@ impl<'a, T: 'a> RingBuffer<'a, T> {
         let res = f(&mut self.storage[index]);
         if res.is_ok() {
             self.length += 1;
+            self.high_water = cmp::max(self.high_water, self.length);
         }
         Ok(res)
     }
@@ -193,6 +219,7 @@ impl<'a, T: 'a> RingBuffer<'a, T> {
         let (size, result) = f(&mut self.storage[write_at..write_at + max_size]);
         assert!(size <= max_size);
         self.length += size;
+        self.high_water = cmp::max(self.high_water, self.length);
         (size, result)
     }
 
@@ -344,6 +371,7 @@ impl<'a, T: 'a> RingBuffer<'a, T> {
     pub fn enqueue_unallocated(&mut self, count: usize) {
         assert!(count <= self.window());
         self.length += count;
+        self.high_water = cmp::max(self.high_water, self.length);
     }
 
     /// Return the largest contiguous slice of allocated buffer elements starting
@@ -390,6 +418,16 @@ impl<'a, T: 'a> RingBuffer<'a, T> {
         size_1 + size_2
     }
 
+    /// Return all allocated buffer elements, in order, as two slices.
+    ///
+    /// The second slice is only non-empty if the allocated elements wrap around
+    /// the end of the storage.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let first = self.get_allocated(0, self.length);
+        let second = self.get_allocated(first.len(), self.length - first.len());
+        (first, second)
+    }
+
     /// Dequeue the given number of allocated buffer elements.
     ///
     /// # Panics
@@ -770,6 +808,22 @@ mod test {
         assert_eq!(&data[..], b"mno\x00\x00\x00");
     }
 
+    #[test]
+    fn test_buffer_as_slices() {
+        let mut ring = RingBuffer::new(vec![b'.'; 12]);
+        assert_eq!(ring.as_slices(), (&b""[..], &b""[..]));
+
+        ring.enqueue_many(12).copy_from_slice(b"abcdefghijkl");
+        assert_eq!(ring.as_slices(), (&b"abcdefghijkl"[..], &b""[..]));
+
+        ring.dequeue_many(6).copy_from_slice(b"ABCDEF");
+        ring.enqueue_many(3).copy_from_slice(b"mno");
+        assert_eq!(ring.as_slices(), (&b"ghijkl"[..], &b"mno"[..]));
+
+        ring.dequeue_many(6).copy_from_slice(b"GHIJKL");
+        assert_eq!(ring.as_slices(), (&b"mno"[..], &b""[..]));
+    }
+
     #[test]
     fn test_buffer_with_no_capacity() {
         let mut no_capacity: RingBuffer<u8> = RingBuffer::new(vec![]);
@@ -800,4 +854,30 @@ mod test {
         let large = ring.enqueue_many(8);
         assert_eq!(large.len(), 8);
     }
+
+    #[test]
+    fn test_buffer_high_water() {
+        let mut storage = [0u32; 8];
+        let mut ring = RingBuffer::new(&mut storage[..]);
+        assert_eq!(ring.high_water(), 0);
+
+        assert_eq!(ring.enqueue_slice(&[1, 2, 3, 4, 5]), 5);
+        assert_eq!(ring.dequeue_slice(&mut [0; 3]), 3);
+        *ring.enqueue_one().unwrap() = 6;
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.high_water(), 5);
+        assert_eq!(
+            ring.memory_usage(),
+            MemoryUsage {
+                used: 12,
+                capacity: 32,
+                high_water: 20,
+            }
+        );
+
+        ring.clear();
+        assert_eq!(ring.high_water(), 5);
+        ring.reset_high_water();
+        assert_eq!(ring.high_water(), 0);
+    }
 }
@@ -0,0 +1,90 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Storage for a buffer of `N` elements that can be declared as a `static` value, and is
+/// handed out once as a `'static` slice.
+///
+/// All the containers of smoltcp borrow their storage, so that storage must outlive them.
+/// Taking it from a `StaticBuffer` gives them, and the sockets, socket sets and interfaces
+/// using them, the `'static` lifetime without `static mut` or `unsafe` code, so that they
+/// can e.g. be moved into an RTOS task. The storage of socket buffers, socket sets,
+/// reassembly buffers and packet metadata can all be declared this way.
+///
+/// ```rust
+/// use smoltcp::storage::{RingBuffer, StaticBuffer};
+///
+/// static STORAGE: StaticBuffer<u8, 1024> = StaticBuffer::new([0; 1024]);
+///
+/// let buffer: RingBuffer<'static, u8> = RingBuffer::new(STORAGE.take().unwrap());
+/// assert_eq!(buffer.capacity(), 1024);
+///
+/// // The storage is only handed out once.
+/// assert!(STORAGE.take().is_none());
+/// ```
+pub struct StaticBuffer<T, const N: usize> {
+    taken: AtomicBool,
+    storage: UnsafeCell<[T; N]>,
+}
+
+// SAFETY: the storage is only accessed through the slice handed out by `take`, which
+// happens at most once, so sharing the buffer between threads amounts to sending
+// the elements to the thread taking it.
+#[allow(unsafe_code)]
+unsafe impl<T: Send, const N: usize> Sync for StaticBuffer<T, N> {}
+
+impl<T, const N: usize> StaticBuffer<T, N> {
+    /// Create a buffer holding the given elements.
+    pub const fn new(storage: [T; N]) -> StaticBuffer<T, N> {
+        StaticBuffer {
+            taken: AtomicBool::new(false),
+            storage: UnsafeCell::new(storage),
+        }
+    }
+
+    /// Return the storage of the buffer, or `None` if it was already taken.
+    // Handing out a mutable reference from a shared one is sound here, because the flag
+    // lets only the first call do it; see below.
+    #[allow(unsafe_code, clippy::mut_from_ref)]
+    pub fn take(&'static self) -> Option<&'static mut [T]> {
+        if self.taken.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+        // SAFETY: the swap is a single atomic read-modify-write, so it returns `false` to
+        // exactly one caller, which is the only one to ever get a reference to the storage;
+        // nothing else in this type touches the storage. Since `self` is `'static`,
+        // the storage is never moved nor dropped while that reference is alive.
+        Some(unsafe { &mut *self.storage.get() })
+    }
+
+    /// Query whether the storage of the buffer was taken.
+    pub fn is_taken(&self) -> bool {
+        self.taken.load(Ordering::Acquire)
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for StaticBuffer<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StaticBuffer")
+            .field("len", &N)
+            .field("taken", &self.is_taken())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_take_once() {
+        static BUFFER: StaticBuffer<u8, 4> = StaticBuffer::new([1, 2, 3, 4]);
+
+        assert!(!BUFFER.is_taken());
+        let storage = BUFFER.take().unwrap();
+        assert_eq!(storage, &[1, 2, 3, 4]);
+        storage[0] = 5;
+        assert!(BUFFER.is_taken());
+        assert!(BUFFER.take().is_none());
+    }
+}
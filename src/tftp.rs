@@ -0,0 +1,837 @@
+/*! A TFTP client and server, on top of UDP sockets.
+
+TFTP, specified in [RFC 1350], transfers a file in lock-step: every block of data is
+acknowledged before the next one is sent, and retransmitted until it is. A block shorter
+than the others ends the transfer. The size of the blocks can be negotiated as described
+in [RFC 2347] and [RFC 2348], which speeds up transfers of large files such as firmware
+images.
+
+A [Transfer] drives a single transfer over a UDP socket of its own, reading the data it sends
+from, or writing the data it receives to, a [Handler]. Clients start one with [Transfer::read]
+or [Transfer::write]. Servers receive requests with [recv_request] on a socket bound to
+[SERVER_PORT], and either [reject] them or accept them with [Transfer::accept]; since TFTP
+identifies a transfer by the ports of both ends, every accepted transfer uses another socket,
+bound to an unused port.
+
+```rust,ignore
+let mut transfer = tftp::Transfer::read(
+    (IpAddress::v4(192, 168, 69, 100), tftp::SERVER_PORT).into(),
+    "firmware.bin",
+    tftp::Config::new(),
+);
+while transfer.status() == tftp::Status::InProgress {
+    iface.poll(clock.now(), &mut device, &mut sockets)?;
+    transfer.poll(sockets.get_mut(handle), &mut flash, clock.now());
+    // Sleep until `transfer.poll_at()` or `iface.poll_at()`.
+}
+```
+
+The data is transferred as is, even in the `netascii` mode.
+
+[RFC 1350]: https://www.rfc-editor.org/rfc/rfc1350
+[RFC 2347]: https://www.rfc-editor.org/rfc/rfc2347
+[RFC 2348]: https://www.rfc-editor.org/rfc/rfc2348
+*/
+
+use core::fmt;
+
+use crate::socket::udp;
+use crate::time::{Duration, Instant};
+use crate::wire::{
+    IpEndpoint, TftpRepr, TFTP_DEFAULT_BLOCK_SIZE, TFTP_MAX_BLOCK_SIZE, TFTP_MIN_BLOCK_SIZE,
+};
+
+pub use crate::wire::{
+    TftpErrorCode as ErrorCode, TftpMode as Mode, TFTP_SERVER_PORT as SERVER_PORT,
+};
+
+/// The file a [Transfer] reads the data it sends from, or writes the data it receives to.
+///
+/// An error returned by the handler aborts the transfer, and is sent to the remote endpoint.
+pub trait Handler {
+    /// Read the data of the file at `offset` into `buffer`, and return its length, which
+    /// is shorter than the buffer only at the end of the file.
+    ///
+    /// The same data is read again if it has to be retransmitted. By default, reading
+    /// is an access violation.
+    fn read(&mut self, offset: usize, buffer: &mut [u8]) -> Result<usize, ErrorCode> {
+        let _ = (offset, buffer);
+        Err(ErrorCode::AccessViolation)
+    }
+
+    /// Write the data received at `offset` into the file.
+    ///
+    /// The data is written in order, and only once. By default, writing is an access
+    /// violation.
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), ErrorCode> {
+        let _ = (offset, data);
+        Err(ErrorCode::AccessViolation)
+    }
+}
+
+/// The parameters of a [Transfer].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// The block size a client asks for, or the largest one a server agrees to, between
+    /// 8 and 65464 octets. The blocks have to fit into the buffers of the socket.
+    pub block_size: u16,
+    /// How long to wait for an answer before retransmitting a packet.
+    pub timeout: Duration,
+    /// How many times a packet is retransmitted before the transfer times out.
+    pub max_retries: u8,
+}
+
+impl Config {
+    /// Create a configuration with the default block size of 512 octets, retransmitting
+    /// packets up to 5 times every second.
+    pub const fn new() -> Config {
+        Config {
+            block_size: TFTP_DEFAULT_BLOCK_SIZE,
+            timeout: Duration::from_secs(1),
+            max_retries: 5,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+/// Why a [Transfer] failed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The remote endpoint sent an error.
+    Remote(ErrorCode),
+    /// The transfer was aborted because of an error of the handler, or because the remote
+    /// endpoint does not follow the protocol. The error was sent to the remote endpoint.
+    Aborted(ErrorCode),
+    /// The remote endpoint stopped answering.
+    TimedOut,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Remote(code) => write!(f, "remote error: {code:?}"),
+            Error::Aborted(code) => write!(f, "aborted: {code:?}"),
+            Error::TimedOut => write!(f, "timed out"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// The state of a [Transfer].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Status {
+    InProgress,
+    /// The whole file was transferred.
+    Done,
+    Failed(Error),
+}
+
+/// What a client requests.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Operation {
+    /// The client reads a file from the server.
+    Read,
+    /// The client writes a file to the server.
+    Write,
+}
+
+/// A request received by a server.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Request<'a> {
+    /// The endpoint of the client, which the transfer has to be sent to.
+    pub remote: IpEndpoint,
+    pub operation: Operation,
+    pub filename: &'a str,
+    pub mode: Mode,
+    /// The block size the client asks for, if any.
+    pub block_size: Option<u16>,
+}
+
+/// Dequeue the next request received by a server on `socket`, dropping the packets
+/// received before it that are not requests.
+pub fn recv_request<'s>(socket: &'s mut udp::Socket) -> Option<Request<'s>> {
+    loop {
+        let is_request = match socket.peek() {
+            Ok((data, _)) => matches!(
+                TftpRepr::parse(data),
+                Ok(TftpRepr::ReadRequest { .. } | TftpRepr::WriteRequest { .. })
+            ),
+            Err(_) => return None,
+        };
+        if is_request {
+            break;
+        }
+        if let Ok((_, remote)) = socket.recv() {
+            net_debug!(
+                "tftp: dropping a packet from {} that is not a request",
+                remote
+            );
+        }
+    }
+
+    let (data, remote) = socket.recv().ok()?;
+    match TftpRepr::parse(data) {
+        Ok(TftpRepr::ReadRequest {
+            filename,
+            mode,
+            block_size,
+        }) => Some(Request {
+            remote,
+            operation: Operation::Read,
+            filename,
+            mode,
+            block_size,
+        }),
+        Ok(TftpRepr::WriteRequest {
+            filename,
+            mode,
+            block_size,
+        }) => Some(Request {
+            remote,
+            operation: Operation::Write,
+            filename,
+            mode,
+            block_size,
+        }),
+        _ => unreachable!(),
+    }
+}
+
+/// Reject the request of a client at `remote`, e.g. because the file does not exist.
+pub fn reject(socket: &mut udp::Socket, remote: IpEndpoint, code: ErrorCode) {
+    send_error(socket, remote, code)
+}
+
+fn send_error(socket: &mut udp::Socket, remote: IpEndpoint, code: ErrorCode) {
+    let repr = TftpRepr::Error {
+        code,
+        message: code.message(),
+    };
+    match socket.send(repr.buffer_len(), remote) {
+        Ok(buffer) => repr.emit(buffer),
+        Err(err) => net_debug!("tftp: cannot send an error to {}: {}", remote, err),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Direction {
+    Send,
+    Receive,
+}
+
+/// The kind of the packet sent last, which is retransmitted until it is answered.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Last {
+    Request,
+    OptionAck,
+    Data,
+    Ack,
+}
+
+/// A file transfer, in either direction.
+#[derive(Debug)]
+pub struct Transfer<'a> {
+    config: Config,
+    /// The file requested by a client, to retransmit the request.
+    filename: &'a str,
+    remote: IpEndpoint,
+    /// Whether the port of the remote endpoint is known. Servers answer requests from
+    /// the port of the transfer, rather than from the one requests are sent to.
+    remote_known: bool,
+    direction: Direction,
+    block_size: u16,
+    /// The number of the block sent or acknowledged last.
+    block_number: u16,
+    /// The number of octets sent and acknowledged, or received.
+    offset: usize,
+    /// The length of the block sent last.
+    block_len: usize,
+    last: Last,
+    /// Whether the packet sent last changed, and has to be sent right away.
+    pending: bool,
+    /// An error to send to the remote endpoint, or to an endpoint unrelated to the transfer.
+    error: Option<(IpEndpoint, ErrorCode)>,
+    retransmit_at: Instant,
+    retries: u8,
+    status: Status,
+}
+
+impl<'a> Transfer<'a> {
+    /// Start reading `filename` from the server at `remote`, usually on [SERVER_PORT].
+    pub fn read(remote: IpEndpoint, filename: &'a str, config: Config) -> Transfer<'a> {
+        Transfer::new(remote, filename, Direction::Receive, config)
+    }
+
+    /// Start writing `filename` to the server at `remote`, usually on [SERVER_PORT].
+    pub fn write(remote: IpEndpoint, filename: &'a str, config: Config) -> Transfer<'a> {
+        Transfer::new(remote, filename, Direction::Send, config)
+    }
+
+    /// Accept a request received by a server.
+    ///
+    /// The transfer has to use another socket than the one the request was received on,
+    /// bound to an unused port, e.g. one allocated with
+    /// [Interface::ephemeral_port](crate::iface::Interface::ephemeral_port).
+    pub fn accept(request: &Request, config: Config) -> Transfer<'static> {
+        let direction = match request.operation {
+            Operation::Read => Direction::Send,
+            Operation::Write => Direction::Receive,
+        };
+        let mut transfer = Transfer::new(request.remote, "", direction, config);
+        transfer.remote_known = true;
+        match request.block_size {
+            Some(size) => {
+                transfer.block_size = size.min(transfer.config.block_size);
+                transfer.last = Last::OptionAck;
+            }
+            None if direction == Direction::Send => {
+                transfer.block_number = 1;
+                transfer.last = Last::Data;
+            }
+            None => transfer.last = Last::Ack,
+        }
+        transfer
+    }
+
+    fn new(remote: IpEndpoint, filename: &'a str, direction: Direction, config: Config) -> Self {
+        let block_size = config
+            .block_size
+            .clamp(TFTP_MIN_BLOCK_SIZE, TFTP_MAX_BLOCK_SIZE);
+        Transfer {
+            config: Config {
+                block_size,
+                ..config
+            },
+            filename,
+            remote,
+            remote_known: false,
+            direction,
+            block_size: TFTP_DEFAULT_BLOCK_SIZE,
+            block_number: 0,
+            offset: 0,
+            block_len: 0,
+            last: Last::Request,
+            pending: true,
+            error: None,
+            retransmit_at: Instant::ZERO,
+            retries: 0,
+            status: Status::InProgress,
+        }
+    }
+
+    /// Return the state of the transfer.
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Return the size of the data blocks, which may change until the first block was
+    /// transferred.
+    pub fn block_size(&self) -> u16 {
+        self.block_size
+    }
+
+    /// Return the number of octets transferred so far.
+    pub fn transferred(&self) -> usize {
+        self.offset
+    }
+
+    /// Return when [poll](#method.poll) should be called next, or `None` if the transfer
+    /// is over.
+    pub fn poll_at(&self) -> Option<Instant> {
+        if self.pending || self.error.is_some() {
+            Some(Instant::ZERO)
+        } else if self.status == Status::InProgress {
+            Some(self.retransmit_at)
+        } else {
+            None
+        }
+    }
+
+    /// Process the packets received on `socket`, and send the next packet of the transfer,
+    /// or retransmit the last one if it was not answered in time.
+    ///
+    /// The socket has to be bound, and not be used for anything else.
+    ///
+    /// Once done, a transfer receiving a file answers the retransmissions of the last
+    /// block as long as it is polled, in case its acknowledgment was lost.
+    pub fn poll<H>(&mut self, socket: &mut udp::Socket, handler: &mut H, timestamp: Instant)
+    where
+        H: Handler + ?Sized,
+    {
+        while let Ok((data, remote)) = socket.recv() {
+            self.process(handler, data, remote);
+        }
+
+        if let Some((remote, code)) = self.error.take() {
+            send_error(socket, remote, code);
+        }
+
+        if self.pending {
+            self.retries = 0;
+        } else if self.status == Status::InProgress && timestamp >= self.retransmit_at {
+            if self.retries == self.config.max_retries {
+                net_debug!("tftp: {} stopped answering", self.remote);
+                self.status = Status::Failed(Error::TimedOut);
+                return;
+            }
+            self.retries += 1;
+            net_debug!("tftp: retransmitting to {}", self.remote);
+        } else {
+            return;
+        }
+
+        match self.dispatch(socket, handler) {
+            Ok(()) => {
+                self.pending = false;
+                self.retransmit_at = timestamp + self.config.timeout;
+            }
+            Err(err) => {
+                net_debug!("tftp: cannot send to {}: {}", self.remote, err);
+                self.pending = true;
+            }
+        }
+    }
+
+    fn process<H>(&mut self, handler: &mut H, data: &[u8], remote: IpEndpoint)
+    where
+        H: Handler + ?Sized,
+    {
+        let is_remote = if self.remote_known {
+            remote == self.remote
+        } else {
+            remote.addr == self.remote.addr
+        };
+        if !is_remote {
+            net_debug!("tftp: packet from unknown endpoint {}", remote);
+            self.error = Some((remote, ErrorCode::UnknownTransferId));
+            return;
+        }
+
+        let repr = match TftpRepr::parse(data) {
+            Ok(repr) => repr,
+            Err(err) => {
+                net_debug!("tftp: cannot parse a packet from {}: {}", remote, err);
+                return;
+            }
+        };
+        net_trace!("tftp: received {}", repr);
+        if !self.remote_known {
+            self.remote = remote;
+            self.remote_known = true;
+        }
+
+        match repr {
+            TftpRepr::Error { code, .. } if self.status == Status::InProgress => {
+                self.status = Status::Failed(Error::Remote(code));
+                self.pending = false;
+            }
+            TftpRepr::OptionAck { block_size } if self.last == Last::Request => {
+                // The options left out were refused.
+                let block_size = block_size.unwrap_or(TFTP_DEFAULT_BLOCK_SIZE);
+                if block_size > self.config.block_size {
+                    self.abort(ErrorCode::OptionRefused);
+                    return;
+                }
+                self.block_size = block_size;
+                match self.direction {
+                    Direction::Send => self.send_block(1),
+                    Direction::Receive => self.acknowledge(0),
+                }
+            }
+            TftpRepr::OptionAck { .. } if self.last == Last::Ack && self.block_number == 0 => {
+                // The acknowledgment of the options was lost.
+                self.pending = true;
+            }
+            TftpRepr::Ack { block_number } if self.direction == Direction::Send => {
+                match self.last {
+                    Last::Request | Last::OptionAck if block_number == 0 => {
+                        if self.last == Last::Request {
+                            // The server ignored the options.
+                            self.block_size = TFTP_DEFAULT_BLOCK_SIZE;
+                        }
+                        self.send_block(1)
+                    }
+                    Last::Data
+                        if block_number == self.block_number
+                            && self.status == Status::InProgress =>
+                    {
+                        self.offset += self.block_len;
+                        if self.block_len < usize::from(self.block_size) {
+                            net_debug!("tftp: sent {} octets to {}", self.offset, self.remote);
+                            self.status = Status::Done;
+                        } else {
+                            self.send_block(block_number.wrapping_add(1))
+                        }
+                    }
+                    // Answering a duplicate acknowledgment with the next block again would
+                    // double every packet from then on.
+                    _ => (),
+                }
+            }
+            TftpRepr::Data { block_number, data } if self.direction == Direction::Receive => {
+                if self.last == Last::Request {
+                    // The server ignored the options.
+                    self.block_size = TFTP_DEFAULT_BLOCK_SIZE;
+                }
+                if block_number == self.block_number.wrapping_add(1)
+                    && self.status == Status::InProgress
+                {
+                    if data.len() > usize::from(self.block_size) {
+                        self.abort(ErrorCode::IllegalOperation);
+                        return;
+                    }
+                    if let Err(code) = handler.write(self.offset, data) {
+                        self.abort(code);
+                        return;
+                    }
+                    self.offset += data.len();
+                    self.acknowledge(block_number);
+                    if data.len() < usize::from(self.block_size) {
+                        net_debug!("tftp: received {} octets from {}", self.offset, self.remote);
+                        self.status = Status::Done;
+                    }
+                } else if block_number == self.block_number && self.last == Last::Ack {
+                    // The acknowledgment of the block was lost.
+                    self.pending = true;
+                }
+            }
+            _ => net_debug!("tftp: ignoring unexpected {}", repr),
+        }
+    }
+
+    fn send_block(&mut self, block_number: u16) {
+        self.block_number = block_number;
+        self.last = Last::Data;
+        self.pending = true;
+    }
+
+    fn acknowledge(&mut self, block_number: u16) {
+        self.block_number = block_number;
+        self.last = Last::Ack;
+        self.pending = true;
+    }
+
+    fn abort(&mut self, code: ErrorCode) {
+        net_debug!(
+            "tftp: aborting the transfer with {}: {:?}",
+            self.remote,
+            code
+        );
+        self.status = Status::Failed(Error::Aborted(code));
+        self.error = Some((self.remote, code));
+        self.pending = false;
+    }
+
+    fn dispatch<H>(
+        &mut self,
+        socket: &mut udp::Socket,
+        handler: &mut H,
+    ) -> Result<(), udp::SendError>
+    where
+        H: Handler + ?Sized,
+    {
+        // Only ask for a block size when it is not the default one.
+        let block_size =
+            (self.config.block_size != TFTP_DEFAULT_BLOCK_SIZE).then_some(self.config.block_size);
+        let repr = match (self.last, self.direction) {
+            (Last::Request, Direction::Receive) => TftpRepr::ReadRequest {
+                filename: self.filename,
+                mode: Mode::Octet,
+                block_size,
+            },
+            (Last::Request, Direction::Send) => TftpRepr::WriteRequest {
+                filename: self.filename,
+                mode: Mode::Octet,
+                block_size,
+            },
+            (Last::OptionAck, _) => TftpRepr::OptionAck {
+                block_size: Some(self.block_size),
+            },
+            (Last::Ack, _) => TftpRepr::Ack {
+                block_number: self.block_number,
+            },
+            (Last::Data, _) => return self.dispatch_data(socket, handler),
+        };
+        net_trace!("tftp: sending {}", repr);
+        let buffer = socket.send(repr.buffer_len(), self.remote)?;
+        repr.emit(buffer);
+        Ok(())
+    }
+
+    fn dispatch_data<H>(
+        &mut self,
+        socket: &mut udp::Socket,
+        handler: &mut H,
+    ) -> Result<(), udp::SendError>
+    where
+        H: Handler + ?Sized,
+    {
+        let (offset, block_number) = (self.offset, self.block_number);
+        let block_size = usize::from(self.block_size);
+        let mut result = Ok(0);
+        socket.send_with(4 + block_size, self.remote, |buffer| {
+            result = handler
+                .read(offset, &mut buffer[4..])
+                .map(|len| len.min(block_size));
+            let repr = match result {
+                Ok(_) => TftpRepr::Data {
+                    block_number,
+                    data: &[],
+                },
+                // The error takes the place of the block, with its message if it fits.
+                Err(code) if 5 + code.message().len() <= buffer.len() => TftpRepr::Error {
+                    code,
+                    message: code.message(),
+                },
+                Err(code) => TftpRepr::Error { code, message: "" },
+            };
+            // Only the header of the block is emitted, as its data is already in place.
+            repr.emit(buffer);
+            match result {
+                Ok(len) => 4 + len,
+                Err(_) => repr.buffer_len(),
+            }
+        })?;
+
+        match result {
+            Ok(len) => {
+                net_trace!("tftp: sent block {} of {} octets", block_number, len);
+                self.block_len = len;
+            }
+            Err(code) => {
+                net_debug!(
+                    "tftp: aborting the transfer with {}: {:?}",
+                    self.remote,
+                    code
+                );
+                self.status = Status::Failed(Error::Aborted(code));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "medium-ip", feature = "proto-ipv4"))]
+mod test {
+    use super::*;
+    use crate::iface::{Interface, InterfaceBuilder, SocketHandle, SocketSet};
+    use crate::phy::{Loopback, Medium};
+    use crate::wire::{IpAddress, IpCidr};
+
+    const CLIENT_PORT: u16 = 49152;
+    const SERVER_TRANSFER_PORT: u16 = 49153;
+
+    struct File(Vec<u8>);
+
+    impl Handler for File {
+        fn read(&mut self, offset: usize, buffer: &mut [u8]) -> Result<usize, ErrorCode> {
+            let data = &self.0[offset.min(self.0.len())..];
+            let len = data.len().min(buffer.len());
+            buffer[..len].copy_from_slice(&data[..len]);
+            Ok(len)
+        }
+
+        fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), ErrorCode> {
+            assert_eq!(offset, self.0.len());
+            self.0.extend_from_slice(data);
+            Ok(())
+        }
+    }
+
+    struct Network {
+        iface: Interface<'static>,
+        device: Loopback,
+        sockets: SocketSet<'static>,
+        client: SocketHandle,
+        listener: SocketHandle,
+        server: SocketHandle,
+    }
+
+    impl Network {
+        fn new() -> Network {
+            let mut device = Loopback::new(Medium::Ip);
+            let mut ip_addrs = heapless::Vec::<IpCidr, 5>::new();
+            ip_addrs
+                .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
+                .unwrap();
+            let iface = InterfaceBuilder::new()
+                .ip_addrs(ip_addrs)
+                .finalize(&mut device);
+            let mut sockets = SocketSet::new(vec![]);
+            let mut socket = |port: u16| {
+                let mut socket = udp::Socket::new(
+                    udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 4], vec![0; 4096]),
+                    udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 4], vec![0; 4096]),
+                );
+                socket.bind(port).unwrap();
+                sockets.add(socket)
+            };
+            let client = socket(CLIENT_PORT);
+            let listener = socket(SERVER_PORT);
+            let server = socket(SERVER_TRANSFER_PORT);
+            Network {
+                iface,
+                device,
+                sockets,
+                client,
+                listener,
+                server,
+            }
+        }
+
+        fn poll(&mut self, timestamp: Instant) {
+            self.iface
+                .poll(timestamp, &mut self.device, &mut self.sockets)
+                .unwrap();
+        }
+    }
+
+    fn server_endpoint() -> IpEndpoint {
+        IpEndpoint::new(IpAddress::v4(127, 0, 0, 1), SERVER_PORT)
+    }
+
+    /// Run a transfer between a client and a server, until both are over.
+    fn run(
+        network: &mut Network,
+        client: &mut Transfer,
+        client_file: &mut File,
+        server_config: Config,
+        server_file: &mut File,
+    ) -> Transfer<'static> {
+        let mut server = None;
+        for _ in 0..20 {
+            let timestamp = Instant::ZERO;
+            client.poll(
+                network.sockets.get_mut(network.client),
+                client_file,
+                timestamp,
+            );
+            network.poll(timestamp);
+            if let Some(request) = recv_request(network.sockets.get_mut(network.listener)) {
+                assert_eq!(request.filename, "fw.bin");
+                server = Some(Transfer::accept(&request, server_config));
+            }
+            if let Some(server) = server.as_mut() {
+                server.poll(
+                    network.sockets.get_mut(network.server),
+                    server_file,
+                    timestamp,
+                );
+            }
+            network.poll(timestamp);
+        }
+        server.unwrap()
+    }
+
+    #[test]
+    fn test_read() {
+        let mut network = Network::new();
+        let config = Config {
+            block_size: 1024,
+            ..Config::new()
+        };
+        let mut client = Transfer::read(server_endpoint(), "fw.bin", config);
+        let mut received = File(vec![]);
+        let mut file = File((0..1500).map(|i| i as u8).collect());
+        let server = run(&mut network, &mut client, &mut received, config, &mut file);
+
+        assert_eq!(client.status(), Status::Done);
+        assert_eq!(server.status(), Status::Done);
+        assert_eq!(client.block_size(), 1024);
+        assert_eq!(client.transferred(), 1500);
+        assert_eq!(received.0, file.0);
+    }
+
+    #[test]
+    fn test_write() {
+        let mut network = Network::new();
+        let mut client = Transfer::write(server_endpoint(), "fw.bin", Config::new());
+        let mut file = File((0..1024).map(|i| i as u8).collect());
+        let mut received = File(vec![]);
+        let server = run(
+            &mut network,
+            &mut client,
+            &mut file,
+            Config::new(),
+            &mut received,
+        );
+
+        // The file ends with an empty block.
+        assert_eq!(client.status(), Status::Done);
+        assert_eq!(server.status(), Status::Done);
+        assert_eq!(server.transferred(), 1024);
+        assert_eq!(received.0, file.0);
+    }
+
+    #[test]
+    fn test_reject() {
+        let mut network = Network::new();
+        let mut client = Transfer::read(server_endpoint(), "missing.bin", Config::new());
+        let mut received = File(vec![]);
+        client.poll(
+            network.sockets.get_mut(network.client),
+            &mut received,
+            Instant::ZERO,
+        );
+        network.poll(Instant::ZERO);
+
+        let listener = network.sockets.get_mut::<udp::Socket>(network.listener);
+        let request = recv_request(listener).unwrap();
+        assert_eq!(request.operation, Operation::Read);
+        assert_eq!(request.filename, "missing.bin");
+        let remote = request.remote;
+        reject(listener, remote, ErrorCode::FileNotFound);
+        network.poll(Instant::ZERO);
+
+        client.poll(
+            network.sockets.get_mut(network.client),
+            &mut received,
+            Instant::ZERO,
+        );
+        assert_eq!(
+            client.status(),
+            Status::Failed(Error::Remote(ErrorCode::FileNotFound))
+        );
+        assert_eq!(client.poll_at(), None);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut network = Network::new();
+        let mut client = Transfer::read(server_endpoint(), "fw.bin", Config::new());
+        let mut received = File(vec![]);
+
+        // The request is retransmitted every second, as nobody answers it.
+        for secs in 0..=5 {
+            let timestamp = Instant::from_secs(secs);
+            client.poll(
+                network.sockets.get_mut(network.client),
+                &mut received,
+                timestamp,
+            );
+            network.poll(timestamp);
+            assert_eq!(client.status(), Status::InProgress);
+            assert_eq!(client.poll_at(), Some(timestamp + Duration::from_secs(1)));
+            let _ = recv_request(network.sockets.get_mut(network.listener)).unwrap();
+        }
+
+        client.poll(
+            network.sockets.get_mut(network.client),
+            &mut received,
+            Instant::from_secs(6),
+        );
+        assert_eq!(client.status(), Status::Failed(Error::TimedOut));
+    }
+}
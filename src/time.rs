@@ -333,6 +333,51 @@ impl From<Duration> for ::core::time::Duration {
     }
 }
 
+/// A counter limiting the rate of events, such as packets sent or accepted, to a maximum
+/// number within each fixed-length window of time.
+///
+/// A window starts with the first event counted after the previous one ended.
+#[cfg(any(
+    feature = "medium-ethernet",
+    feature = "medium-ip",
+    feature = "medium-ieee802154"
+))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct RateLimiter {
+    /// The start of the current window.
+    start: Instant,
+    /// The number of events counted within the current window.
+    count: u16,
+}
+
+#[cfg(any(
+    feature = "medium-ethernet",
+    feature = "medium-ip",
+    feature = "medium-ieee802154"
+))]
+impl RateLimiter {
+    pub(crate) const fn new() -> RateLimiter {
+        RateLimiter {
+            start: Instant::ZERO,
+            count: 0,
+        }
+    }
+
+    /// Return whether an event happening at `now` exceeds `max` events per `interval`,
+    /// and count it otherwise.
+    pub(crate) fn exceeded(&mut self, now: Instant, max: u16, interval: Duration) -> bool {
+        if now >= self.start + interval {
+            self.start = now;
+            self.count = 0;
+        }
+        if self.count >= max {
+            return true;
+        }
+        self.count += 1;
+        false
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -443,4 +488,22 @@ mod test {
         std_duration = duration.into();
         assert_eq!(std_duration, ::core::time::Duration::from_millis(4934));
     }
+
+    #[test]
+    #[cfg(any(
+        feature = "medium-ethernet",
+        feature = "medium-ip",
+        feature = "medium-ieee802154"
+    ))]
+    fn test_rate_limiter() {
+        let interval = Duration::from_secs(1);
+        let mut limiter = RateLimiter::new();
+        assert!(!limiter.exceeded(Instant::from_millis(0), 2, interval));
+        assert!(!limiter.exceeded(Instant::from_millis(500), 2, interval));
+        assert!(limiter.exceeded(Instant::from_millis(999), 2, interval));
+        // The excess event is not counted, and the next window starts afresh.
+        assert!(!limiter.exceeded(Instant::from_millis(1000), 2, interval));
+        assert!(!limiter.exceeded(Instant::from_millis(1999), 2, interval));
+        assert!(limiter.exceeded(Instant::from_millis(1999), 2, interval));
+    }
 }
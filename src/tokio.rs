@@ -0,0 +1,489 @@
+/*! Integration with the [tokio] runtime.
+
+This module drives an [Interface] from a tokio task, waking it up whenever the
+underlying device becomes readable, a socket timer expires, or a socket is used,
+and exposes TCP sockets as [AsyncRead] and [AsyncWrite] streams.
+
+The interface, the device and the socket set are moved into a [Stack], which is
+shared between a [Driver] and any number of [TcpStream]s. Since most devices are
+not `Send`, the driver must be run on the current thread, e.g. with
+[`tokio::task::spawn_local`]:
+
+```rust,no_run
+# use smoltcp::iface::{InterfaceBuilder, SocketSet};
+# use smoltcp::phy::{Medium, TunTapInterface};
+# use smoltcp::socket::tcp;
+# use smoltcp::wire::{IpAddress, IpCidr};
+use smoltcp::tokio::Stack;
+
+# async fn f() -> std::io::Result<()> {
+let mut device = TunTapInterface::new("tun0", Medium::Ip)?;
+let mut ip_addrs = heapless::Vec::<IpCidr, 5>::new();
+ip_addrs
+    .push(IpCidr::new(IpAddress::v4(192, 168, 69, 1), 24))
+    .unwrap();
+let iface = InterfaceBuilder::new()
+    .ip_addrs(ip_addrs)
+    .finalize(&mut device);
+let mut sockets = SocketSet::new(vec![]);
+let tcp_socket = tcp::Socket::new(
+    tcp::SocketBuffer::new(vec![0; 1024]),
+    tcp::SocketBuffer::new(vec![0; 1024]),
+);
+let handle = sockets.add(tcp_socket);
+
+let (stack, driver) = Stack::new(iface, device, sockets)?;
+tokio::task::spawn_local(driver.run());
+
+stack.with(|iface, sockets| {
+    let socket = sockets.get_mut::<tcp::Socket>(handle);
+    socket.connect(iface.context(), (IpAddress::v4(192, 168, 69, 100), 80), 49500)
+}).unwrap();
+let stream = stack.tcp_stream(handle);
+# Ok(())
+# }
+```
+
+[tokio]: https://tokio.rs
+*/
+
+use std::cell::RefCell;
+use std::future;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncRead, AsyncWrite, Interest, ReadBuf};
+use tokio::sync::Notify;
+
+use crate::iface::{Interface, SocketHandle, SocketSet};
+use crate::phy::Device;
+use crate::socket::tcp;
+use crate::time::Instant;
+
+struct Inner<D> {
+    iface: Interface<'static>,
+    device: D,
+    sockets: SocketSet<'static>,
+}
+
+struct Shared<D> {
+    inner: RefCell<Inner<D>>,
+    notify: Notify,
+}
+
+/// A file descriptor registered with the tokio reactor, without taking ownership of it.
+struct Fd(RawFd);
+
+impl AsRawFd for Fd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+/// A network stack shared with a [Driver].
+///
+/// Cloning a `Stack` returns another handle to the same interface and sockets.
+pub struct Stack<D> {
+    shared: Rc<Shared<D>>,
+}
+
+impl<D> Stack<D>
+where
+    D: Device + AsRawFd + 'static,
+{
+    /// Create a stack out of an interface, the device it uses and a socket set,
+    /// and return it together with the driver that polls it.
+    ///
+    /// The file descriptor of the device must be in non-blocking mode, which is the
+    /// case for [TunTapInterface](crate::phy::TunTapInterface) and
+    /// [RawSocket](crate::phy::RawSocket).
+    ///
+    /// # Panics
+    /// This function panics if it is called outside of a tokio runtime.
+    pub fn new(
+        iface: Interface<'static>,
+        device: D,
+        sockets: SocketSet<'static>,
+    ) -> io::Result<(Stack<D>, Driver<D>)> {
+        let fd = AsyncFd::with_interest(Fd(device.as_raw_fd()), Interest::READABLE)?;
+        let shared = Rc::new(Shared {
+            inner: RefCell::new(Inner {
+                iface,
+                device,
+                sockets,
+            }),
+            notify: Notify::new(),
+        });
+        let driver = Driver {
+            shared: shared.clone(),
+            fd,
+        };
+        Ok((Stack { shared }, driver))
+    }
+}
+
+impl<D> Stack<D> {
+    /// Call `f` with the interface and the socket set, and wake up the driver once it returns.
+    ///
+    /// # Panics
+    /// This function panics if it is called from within `f`.
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Interface<'static>, &mut SocketSet<'static>) -> R,
+    {
+        let result = {
+            let mut inner = self.shared.inner.borrow_mut();
+            let Inner { iface, sockets, .. } = &mut *inner;
+            f(iface, sockets)
+        };
+        self.shared.notify.notify_one();
+        result
+    }
+
+    /// Return a stream reading from and writing to the TCP socket with the given handle.
+    ///
+    /// The socket must be connected, or connecting, or listening; the stream does not
+    /// open it.
+    pub fn tcp_stream(&self, handle: SocketHandle) -> TcpStream<D> {
+        TcpStream {
+            stack: self.clone(),
+            handle,
+        }
+    }
+}
+
+impl<D> Clone for Stack<D> {
+    fn clone(&self) -> Self {
+        Stack {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<D> Drop for Stack<D> {
+    fn drop(&mut self) {
+        // Let the driver notice if this was the last handle.
+        self.shared.notify.notify_one();
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<D> crate::socket::framed::SharedSockets<'static> for Stack<D> {
+    fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut SocketSet<'static>) -> R,
+    {
+        Stack::with(self, |_, sockets| f(sockets))
+    }
+}
+
+/// The task polling the interface of a [Stack].
+pub struct Driver<D> {
+    shared: Rc<Shared<D>>,
+    fd: AsyncFd<Fd>,
+}
+
+impl<D> Driver<D>
+where
+    D: Device + AsRawFd + 'static,
+{
+    /// Poll the interface until every handle to the stack has been dropped, or
+    /// waiting for the device fails.
+    pub async fn run(self) -> io::Result<()> {
+        while Rc::strong_count(&self.shared) > 1 {
+            let delay = {
+                let mut inner = self.shared.inner.borrow_mut();
+                let Inner {
+                    iface,
+                    device,
+                    sockets,
+                } = &mut *inner;
+                let timestamp = Instant::now();
+                if let Err(err) = iface.poll(timestamp, device, sockets) {
+                    net_debug!("poll error: {}", err);
+                }
+                iface.poll_delay(timestamp, sockets)
+            };
+
+            let timeout = async {
+                match delay {
+                    Some(delay) => tokio::time::sleep(delay.into()).await,
+                    None => future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                guard = self.fd.readable() => guard?.clear_ready(),
+                _ = timeout => (),
+                _ = self.shared.notify.notified() => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A TCP socket of a [Stack], usable as an [AsyncRead] and [AsyncWrite] stream.
+///
+/// Reading returns end of file once the remote endpoint closes the connection,
+/// and shutting down the stream closes the transmit half of the connection.
+pub struct TcpStream<D> {
+    stack: Stack<D>,
+    handle: SocketHandle,
+}
+
+impl<D> TcpStream<D> {
+    /// Return the handle of the underlying socket.
+    pub fn handle(&self) -> SocketHandle {
+        self.handle
+    }
+
+    fn with_socket<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut tcp::Socket<'static>) -> R,
+    {
+        self.stack
+            .with(|_, sockets| f(sockets.get_mut::<tcp::Socket>(self.handle)))
+    }
+}
+
+/// Return whether a socket in this state may still be able to transfer data later.
+fn is_opening(state: tcp::State) -> bool {
+    matches!(
+        state,
+        tcp::State::Listen | tcp::State::SynSent | tcp::State::SynReceived
+    )
+}
+
+impl<D> AsyncRead for TcpStream<D> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.with_socket(|socket| {
+            if socket.can_recv() {
+                match socket.recv_slice(buf.initialize_unfilled()) {
+                    Ok(size) => {
+                        buf.advance(size);
+                        Poll::Ready(Ok(()))
+                    }
+                    Err(tcp::RecvError::Finished) => Poll::Ready(Ok(())),
+                    Err(tcp::RecvError::InvalidState) => {
+                        Poll::Ready(Err(io::ErrorKind::NotConnected.into()))
+                    }
+                }
+            } else if socket.may_recv() || is_opening(socket.state()) {
+                socket.register_recv_waker(cx.waker());
+                Poll::Pending
+            } else {
+                // Either the remote endpoint has closed the connection, or it was reset.
+                Poll::Ready(Ok(()))
+            }
+        })
+    }
+}
+
+impl<D> AsyncWrite for TcpStream<D> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.with_socket(|socket| {
+            if socket.can_send() {
+                match socket.send_slice(buf) {
+                    Ok(size) => Poll::Ready(Ok(size)),
+                    Err(tcp::SendError::InvalidState) => {
+                        Poll::Ready(Err(io::ErrorKind::NotConnected.into()))
+                    }
+                }
+            } else if socket.may_send() || is_opening(socket.state()) {
+                socket.register_send_waker(cx.waker());
+                Poll::Pending
+            } else {
+                Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+            }
+        })
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // Data is handed to the interface as soon as it is written.
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.with_socket(|socket| socket.close());
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::os::unix::net::UnixDatagram;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::iface::InterfaceBuilder;
+    use crate::phy::{self, DeviceCapabilities, Medium};
+    use crate::wire::{IpAddress, IpCidr};
+
+    /// An IP device looping packets back through a pair of datagram sockets.
+    struct SocketPair {
+        rx: UnixDatagram,
+        tx: UnixDatagram,
+    }
+
+    impl SocketPair {
+        fn new() -> SocketPair {
+            let (rx, tx) = UnixDatagram::pair().unwrap();
+            rx.set_nonblocking(true).unwrap();
+            SocketPair { rx, tx }
+        }
+    }
+
+    impl AsRawFd for SocketPair {
+        fn as_raw_fd(&self) -> RawFd {
+            self.rx.as_raw_fd()
+        }
+    }
+
+    struct RxToken(Vec<u8>);
+
+    impl phy::RxToken for RxToken {
+        fn consume<R, F>(mut self, _timestamp: Instant, f: F) -> crate::Result<R>
+        where
+            F: FnOnce(&mut [u8]) -> crate::Result<R>,
+        {
+            f(&mut self.0)
+        }
+    }
+
+    struct TxToken<'a>(&'a UnixDatagram);
+
+    impl<'a> phy::TxToken for TxToken<'a> {
+        fn consume<R, F>(self, _timestamp: Instant, len: usize, f: F) -> crate::Result<R>
+        where
+            F: FnOnce(&mut [u8]) -> crate::Result<R>,
+        {
+            let mut buffer = vec![0; len];
+            let result = f(&mut buffer);
+            self.0.send(&buffer).unwrap();
+            result
+        }
+    }
+
+    impl Device for SocketPair {
+        type RxToken<'a> = RxToken;
+        type TxToken<'a> = TxToken<'a>;
+
+        fn receive(&mut self) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+            let mut buffer = vec![0; 1500];
+            match self.rx.recv(&mut buffer) {
+                Ok(size) => {
+                    buffer.truncate(size);
+                    Some((RxToken(buffer), TxToken(&self.tx)))
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => None,
+                Err(err) => panic!("{}", err),
+            }
+        }
+
+        fn transmit(&mut self) -> Option<Self::TxToken<'_>> {
+            Some(TxToken(&self.tx))
+        }
+
+        fn capabilities(&self) -> DeviceCapabilities {
+            DeviceCapabilities {
+                medium: Medium::Ip,
+                max_transmission_unit: 1500,
+                ..DeviceCapabilities::default()
+            }
+        }
+    }
+
+    fn tcp_socket() -> tcp::Socket<'static> {
+        tcp::Socket::new(
+            tcp::SocketBuffer::new(vec![0; 64]),
+            tcp::SocketBuffer::new(vec![0; 64]),
+        )
+    }
+
+    #[test]
+    fn test_tcp_stream() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let local = tokio::task::LocalSet::new();
+
+        local.block_on(&runtime, async {
+            let mut device = SocketPair::new();
+            let mut ip_addrs = heapless::Vec::<IpCidr, 5>::new();
+            ip_addrs
+                .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
+                .unwrap();
+            let iface = InterfaceBuilder::new()
+                .ip_addrs(ip_addrs)
+                .finalize(&mut device);
+            let mut sockets = SocketSet::new(vec![]);
+            let server = sockets.add(tcp_socket());
+            let client = sockets.add(tcp_socket());
+
+            let (stack, driver) = Stack::new(iface, device, sockets).unwrap();
+            let driver = tokio::task::spawn_local(driver.run());
+
+            stack.with(|iface, sockets| {
+                sockets.get_mut::<tcp::Socket>(server).listen(1234).unwrap();
+                sockets
+                    .get_mut::<tcp::Socket>(client)
+                    .connect(iface.context(), (IpAddress::v4(127, 0, 0, 1), 1234), 65000)
+                    .unwrap();
+            });
+
+            // More data than fits in the socket buffers at once.
+            let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+
+            let mut client = stack.tcp_stream(client);
+            let data_ref = &data;
+            let writer = async move {
+                let mut sent = 0;
+                while sent < data_ref.len() {
+                    sent += future::poll_fn(|cx| {
+                        Pin::new(&mut client).poll_write(cx, &data_ref[sent..])
+                    })
+                    .await
+                    .unwrap();
+                }
+                future::poll_fn(|cx| Pin::new(&mut client).poll_shutdown(cx))
+                    .await
+                    .unwrap();
+            };
+
+            let mut server = stack.tcp_stream(server);
+            let reader = async move {
+                let mut received = Vec::new();
+                loop {
+                    let mut buffer = [0; 16];
+                    let mut buf = ReadBuf::new(&mut buffer);
+                    future::poll_fn(|cx| Pin::new(&mut server).poll_read(cx, &mut buf))
+                        .await
+                        .unwrap();
+                    if buf.filled().is_empty() {
+                        break received;
+                    }
+                    received.extend_from_slice(buf.filled());
+                }
+            };
+
+            let ((), received) = tokio::join!(writer, reader);
+            assert_eq!(received, data);
+
+            drop(stack);
+            driver.await.unwrap().unwrap();
+        });
+    }
+}
@@ -73,12 +73,12 @@ impl<'a> DhcpOptionWriter<'a> {
     /// Emit a  [`DhcpOption`] into a [`DhcpOptionWriter`].
     pub fn emit(&mut self, option: DhcpOption<'_>) -> Result<()> {
         if option.data.len() > u8::MAX as _ {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
 
         let total_len = 2 + option.data.len();
         if self.buffer.len() < total_len {
-            return Err(Error);
+            return Err(Error::Truncated);
         }
 
         let (buf, rest) = core::mem::take(&mut self.buffer).split_at_mut(total_len);
@@ -93,7 +93,7 @@ impl<'a> DhcpOptionWriter<'a> {
 
     pub fn end(&mut self) -> Result<()> {
         if self.buffer.is_empty() {
-            return Err(Error);
+            return Err(Error::Truncated);
         }
 
         self.buffer[0] = field::OPT_END;
@@ -248,13 +248,13 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     /// Ensure that no accessor method will panic if called.
-    /// Returns `Err(Error)` if the buffer is too short.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
     ///
     /// [set_header_len]: #method.set_header_len
     pub fn check_len(&self) -> Result<()> {
         let len = self.buffer.as_ref().len();
         if len < field::MAGIC_NUMBER.end {
-            Err(Error)
+            Err(Error::Truncated)
         } else {
             Ok(())
         }
@@ -397,22 +397,22 @@ impl<T: AsRef<[u8]>> Packet<T> {
 
     pub fn get_sname(&self) -> Result<&str> {
         let data = &self.buffer.as_ref()[field::SNAME];
-        let len = data.iter().position(|&x| x == 0).ok_or(Error)?;
+        let len = data.iter().position(|&x| x == 0).ok_or(Error::Malformed)?;
         if len == 0 {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
 
-        let data = core::str::from_utf8(&data[..len]).map_err(|_| Error)?;
+        let data = core::str::from_utf8(&data[..len]).map_err(|_| Error::Malformed)?;
         Ok(data)
     }
 
     pub fn get_boot_file(&self) -> Result<&str> {
         let data = &self.buffer.as_ref()[field::FILE];
-        let len = data.iter().position(|&x| x == 0).ok_or(Error)?;
+        let len = data.iter().position(|&x| x == 0).ok_or(Error::Malformed)?;
         if len == 0 {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
-        let data = core::str::from_utf8(&data[..len]).map_err(|_| Error)?;
+        let data = core::str::from_utf8(&data[..len]).map_err(|_| Error::Malformed)?;
         Ok(data)
     }
 }
@@ -719,17 +719,17 @@ impl<'a> Repr<'a> {
         match packet.hardware_type() {
             Hardware::Ethernet => {
                 if packet.hardware_len() != 6 {
-                    return Err(Error);
+                    return Err(Error::Malformed);
                 }
             }
-            Hardware::Unknown(_) => return Err(Error), // unimplemented
+            Hardware::Unknown(_) => return Err(Error::Malformed), // unimplemented
         }
 
         if packet.magic_number() != DHCP_MAGIC_NUMBER {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
 
-        let mut message_type = Err(Error);
+        let mut message_type = Err(Error::Malformed);
         let mut requested_ip = None;
         let mut client_identifier = None;
         let mut server_identifier = None;
@@ -757,7 +757,7 @@ impl<'a> Repr<'a> {
                 (field::OPT_CLIENT_ID, 7) => {
                     let hardware_type = Hardware::from(u16::from(data[0]));
                     if hardware_type != Hardware::Ethernet {
-                        return Err(Error);
+                        return Err(Error::Malformed);
                     }
                     client_identifier = Some(EthernetAddress::from_bytes(&data[1..]));
                 }
@@ -792,7 +792,10 @@ impl<'a> Repr<'a> {
                         // We ignore push failures because that will only happen
                         // if we attempt to push more than 4 addresses, and the only
                         // solution to that is to support more addresses.
-                        servers.push(Ipv4Address::from_bytes(chunk)).ok();
+                        let server = Ipv4Address::from_bytes(chunk);
+                        if servers.push(server).is_err() {
+                            net_trace!("dhcp: too many DNS servers, ignoring {}", server);
+                        }
                     }
                     dns_servers = Some(servers);
                 }
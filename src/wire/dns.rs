@@ -42,7 +42,11 @@ enum_with_unknown! {
         Ns    = 0x0002,
         Cname = 0x0005,
         Soa   = 0x0006,
+        Ptr   = 0x000c,
+        Txt   = 0x0010,
         Aaaa  = 0x001c,
+        Srv   = 0x0021,
+        Any   = 0x00ff,
     }
 }
 
@@ -72,8 +76,15 @@ mod field {
     pub const HEADER_END: usize = 12;
 }
 
+/// The length of a DNS header.
+pub const HEADER_LEN: usize = field::HEADER_END;
+
 // DNS class IN (Internet)
-const CLASS_IN: u16 = 1;
+pub const CLASS_IN: u16 = 1;
+
+// Multicast DNS uses the top bit of the class as the unicast-response flag of questions,
+// and as the cache-flush flag of records, see RFC 6762 § 18.12 and § 18.13.
+pub const CLASS_MDNS_FLAG: u16 = 0x8000;
 
 /// A read/write wrapper around a DNS packet buffer.
 #[derive(Debug, PartialEq, Eq)]
@@ -98,12 +109,12 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     /// Ensure that no accessor method will panic if called.
-    /// Returns `Err(Error)` if the buffer is smaller than
+    /// Returns `Err(Error::Truncated)` if the buffer is smaller than
     /// the header length.
     pub fn check_len(&self) -> Result<()> {
         let len = self.buffer.as_ref().len();
         if len < field::HEADER_END {
-            Err(Error)
+            Err(Error::Truncated)
         } else {
             Ok(())
         }
@@ -166,14 +177,14 @@ impl<T: AsRef<[u8]>> Packet<T> {
 
         iter::from_fn(move || loop {
             if bytes.is_empty() {
-                return Some(Err(Error));
+                return Some(Err(Error::Truncated));
             }
             match bytes[0] {
                 0x00 => return None,
                 x if x & 0xC0 == 0x00 => {
                     let len = (x & 0x3F) as usize;
                     if bytes.len() < 1 + len {
-                        return Some(Err(Error));
+                        return Some(Err(Error::Truncated));
                     }
                     let label = &bytes[1..1 + len];
                     bytes = &bytes[1 + len..];
@@ -181,12 +192,12 @@ impl<T: AsRef<[u8]>> Packet<T> {
                 }
                 x if x & 0xC0 == 0xC0 => {
                     if bytes.len() < 2 {
-                        return Some(Err(Error));
+                        return Some(Err(Error::Truncated));
                     }
                     let y = bytes[1];
                     let ptr = ((x & 0x3F) as usize) << 8 | (y as usize);
                     if packet.len() <= ptr {
-                        return Some(Err(Error));
+                        return Some(Err(Error::Malformed));
                     }
 
                     // RFC1035 says: "In this scheme, an entire domain name or a list of labels at
@@ -204,7 +215,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
                     bytes = &packet[ptr..];
                     packet = &packet[..ptr];
                 }
-                _ => return Some(Err(Error)),
+                _ => return Some(Err(Error::Malformed)),
             }
         })
     }
@@ -262,24 +273,24 @@ fn parse_name_part<'a>(
     mut f: impl FnMut(&'a [u8]),
 ) -> Result<(&'a [u8], Option<usize>)> {
     loop {
-        let x = *bytes.first().ok_or(Error)?;
+        let x = *bytes.first().ok_or(Error::Truncated)?;
         bytes = &bytes[1..];
         match x {
             0x00 => return Ok((bytes, None)),
             x if x & 0xC0 == 0x00 => {
                 let len = (x & 0x3F) as usize;
-                let label = bytes.get(..len).ok_or(Error)?;
+                let label = bytes.get(..len).ok_or(Error::Truncated)?;
                 bytes = &bytes[len..];
                 f(label);
             }
             x if x & 0xC0 == 0xC0 => {
-                let y = *bytes.first().ok_or(Error)?;
+                let y = *bytes.first().ok_or(Error::Truncated)?;
                 bytes = &bytes[1..];
 
                 let ptr = ((x & 0x3F) as usize) << 8 | (y as usize);
                 return Ok((bytes, Some(ptr)));
             }
-            _ => return Err(Error),
+            _ => return Err(Error::Malformed),
         }
     }
 }
@@ -297,14 +308,14 @@ impl<'a> Question<'a> {
         let name = &buffer[..buffer.len() - rest.len()];
 
         if rest.len() < 4 {
-            return Err(Error);
+            return Err(Error::Truncated);
         }
         let type_ = NetworkEndian::read_u16(&rest[0..2]).into();
-        let class = NetworkEndian::read_u16(&rest[2..4]);
+        let class = NetworkEndian::read_u16(&rest[2..4]) & !CLASS_MDNS_FLAG;
         let rest = &rest[4..];
 
         if class != CLASS_IN {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
 
         Ok((rest, Question { name, type_ }))
@@ -338,14 +349,14 @@ impl<'a> RecordData<'a> {
             #[cfg(feature = "proto-ipv4")]
             Type::A => {
                 if data.len() != 4 {
-                    return Err(Error);
+                    return Err(Error::Malformed);
                 }
                 Ok(RecordData::A(Ipv4Address::from_bytes(data)))
             }
             #[cfg(feature = "proto-ipv6")]
             Type::Aaaa => {
                 if data.len() != 16 {
-                    return Err(Error);
+                    return Err(Error::Malformed);
                 }
                 Ok(RecordData::Aaaa(Ipv6Address::from_bytes(data)))
             }
@@ -372,19 +383,19 @@ impl<'a> Record<'a> {
         let name = &buffer[..buffer.len() - rest.len()];
 
         if rest.len() < 10 {
-            return Err(Error);
+            return Err(Error::Truncated);
         }
         let type_ = NetworkEndian::read_u16(&rest[0..2]).into();
-        let class = NetworkEndian::read_u16(&rest[2..4]);
+        let class = NetworkEndian::read_u16(&rest[2..4]) & !CLASS_MDNS_FLAG;
         let ttl = NetworkEndian::read_u32(&rest[4..8]);
         let len = NetworkEndian::read_u16(&rest[8..10]) as usize;
         let rest = &rest[10..];
 
         if class != CLASS_IN {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
 
-        let data = rest.get(..len).ok_or(Error)?;
+        let data = rest.get(..len).ok_or(Error::Truncated)?;
         let rest = &rest[len..];
 
         Ok((
@@ -790,4 +801,21 @@ mod test {
         ];
         assert_eq!(&buf, want);
     }
+
+    #[test]
+    fn test_parse_mdns_flags() {
+        let p = Parsed::parse(&[
+            0x00, 0x00, 0x84, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x61,
+            0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x00, 0x00, 0x01, 0x80, 0x01, 0xc0, 0x0c, 0x00,
+            0x01, 0x80, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x04, 0xc0, 0xa8, 0x01, 0x01,
+        ])
+        .unwrap();
+
+        assert_eq!(p.questions[0].type_, Type::A);
+        assert_eq!(p.answers[0].ttl, 120);
+        assert_eq!(
+            p.answers[0].data,
+            RecordData::A(Ipv4Address::new(192, 168, 1, 1))
+        );
+    }
 }
@@ -1,6 +1,10 @@
 use byteorder::{ByteOrder, NetworkEndian};
 use core::fmt;
 
+#[cfg(feature = "proto-ipv4")]
+use super::Ipv4Address;
+#[cfg(feature = "proto-ipv6")]
+use super::Ipv6Address;
 use super::{Error, Result};
 
 enum_with_unknown! {
@@ -8,7 +12,9 @@ enum_with_unknown! {
     pub enum EtherType(u16) {
         Ipv4 = 0x0800,
         Arp  = 0x0806,
-        Ipv6 = 0x86DD
+        Vlan = 0x8100,
+        Ipv6 = 0x86DD,
+        Lldp = 0x88CC
     }
 }
 
@@ -18,13 +24,15 @@ impl fmt::Display for EtherType {
             EtherType::Ipv4 => write!(f, "IPv4"),
             EtherType::Ipv6 => write!(f, "IPv6"),
             EtherType::Arp => write!(f, "ARP"),
+            EtherType::Vlan => write!(f, "802.1Q"),
+            EtherType::Lldp => write!(f, "LLDP"),
             EtherType::Unknown(id) => write!(f, "0x{id:04x}"),
         }
     }
 }
 
 /// A six-octet Ethernet II address.
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Address(pub [u8; 6]);
 
@@ -66,6 +74,38 @@ impl Address {
     pub const fn is_local(&self) -> bool {
         self.0[0] & 0x02 != 0
     }
+
+    /// Return the multicast Ethernet address an IPv4 multicast group is mapped to.
+    ///
+    /// See [RFC 1112 § 6.4](https://tools.ietf.org/html/rfc1112#section-6.4).
+    #[cfg(feature = "proto-ipv4")]
+    pub const fn from_ipv4_multicast(addr: Ipv4Address) -> Address {
+        let b = addr.0;
+        Address([0x01, 0x00, 0x5e, b[1] & 0x7f, b[2], b[3]])
+    }
+
+    /// Return the multicast Ethernet address an IPv6 multicast group is mapped to.
+    ///
+    /// See [RFC 2464 § 7](https://tools.ietf.org/html/rfc2464#section-7).
+    #[cfg(feature = "proto-ipv6")]
+    pub const fn from_ipv6_multicast(addr: Ipv6Address) -> Address {
+        let b = addr.0;
+        Address([0x33, 0x33, b[12], b[13], b[14], b[15]])
+    }
+
+    /// Convert the address to a modified EUI-64 interface identifier.
+    ///
+    /// See [RFC 2464 § 4](https://tools.ietf.org/html/rfc2464#section-4).
+    pub const fn as_eui_64(&self) -> [u8; 8] {
+        let b = self.0;
+        [b[0] ^ 0x02, b[1], b[2], 0xff, 0xfe, b[3], b[4], b[5]]
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
 }
 
 impl fmt::Display for Address {
@@ -79,6 +119,9 @@ impl fmt::Display for Address {
     }
 }
 
+#[cfg(feature = "serde")]
+serde_via_str!(Address, "an Ethernet address");
+
 /// A read/write wrapper around an Ethernet II frame buffer.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -115,11 +158,11 @@ impl<T: AsRef<[u8]>> Frame<T> {
     }
 
     /// Ensure that no accessor method will panic if called.
-    /// Returns `Err(Error)` if the buffer is too short.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
     pub fn check_len(&self) -> Result<()> {
         let len = self.buffer.as_ref().len();
         if len < HEADER_LEN {
-            Err(Error)
+            Err(Error::Truncated)
         } else {
             Ok(())
         }
@@ -251,6 +294,18 @@ impl<T: AsRef<[u8]>> PrettyPrint for Frame<T> {
                 indent.increase(f)?;
                 super::Ipv6Packet::<&[u8]>::pretty_print(&frame.payload(), f, indent)
             }
+            EtherType::Vlan => {
+                indent.increase(f)?;
+                super::VlanPacket::<&[u8]>::pretty_print(&frame.payload(), f, indent)
+            }
+            #[cfg(feature = "proto-lldp")]
+            EtherType::Lldp => {
+                indent.increase(f)?;
+                match super::LldpRepr::parse(frame.payload()) {
+                    Err(err) => write!(f, "{indent}({err})"),
+                    Ok(repr) => write!(f, "{indent}{repr}"),
+                }
+            }
             _ => Ok(()),
         }
     }
@@ -259,6 +314,7 @@ impl<T: AsRef<[u8]>> PrettyPrint for Frame<T> {
 /// A high-level representation of an Internet Protocol version 4 packet header.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Repr {
     pub src_addr: Address,
     pub dst_addr: Address,
@@ -302,6 +358,32 @@ mod test {
         assert!(Address::BROADCAST.is_multicast());
         assert!(Address::BROADCAST.is_local());
     }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_from_ipv4_multicast() {
+        let addr = Address::from_ipv4_multicast(Ipv4Address::new(239, 129, 2, 3));
+        assert_eq!(addr, Address([0x01, 0x00, 0x5e, 0x01, 0x02, 0x03]));
+        assert!(addr.is_multicast());
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6")]
+    fn test_from_ipv6_multicast() {
+        let addr =
+            Address::from_ipv6_multicast(Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff12, 0x3456));
+        assert_eq!(addr, Address([0x33, 0x33, 0xff, 0x12, 0x34, 0x56]));
+        assert!(addr.is_multicast());
+    }
+
+    #[test]
+    fn test_as_eui_64() {
+        let addr = Address([0x02, 0x00, 0x00, 0x12, 0x34, 0x56]);
+        assert_eq!(
+            addr.as_eui_64(),
+            [0x00, 0x00, 0x00, 0xff, 0xfe, 0x12, 0x34, 0x56]
+        );
+    }
 }
 
 #[cfg(test)]
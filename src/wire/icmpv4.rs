@@ -199,7 +199,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     /// Ensure that no accessor method will panic if called.
-    /// Returns `Err(Error)` if the buffer is too short.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
     ///
     /// The result of this check is invalidated by calling [set_header_len].
     ///
@@ -207,7 +207,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     pub fn check_len(&self) -> Result<()> {
         let len = self.buffer.as_ref().len();
         if len < field::HEADER_END {
-            Err(Error)
+            Err(Error::Truncated)
         } else {
             Ok(())
         }
@@ -365,6 +365,7 @@ impl<T: AsRef<[u8]>> AsRef<[u8]> for Packet<T> {
 /// A high-level representation of an Internet Control Message Protocol version 4 packet header.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum Repr<'a> {
     EchoRequest {
@@ -401,7 +402,7 @@ impl<'a> Repr<'a> {
     {
         // Valid checksum is expected.
         if checksum_caps.icmpv4.rx() && !packet.verify_checksum() {
-            return Err(Error);
+            return Err(Error::Checksum);
         }
 
         match (packet.msg_type(), packet.msg_code()) {
@@ -424,7 +425,7 @@ impl<'a> Repr<'a> {
                 // RFC 792 requires exactly eight bytes to be returned.
                 // We allow more, since there isn't a reason not to, but require at least eight.
                 if payload.len() < 8 {
-                    return Err(Error);
+                    return Err(Error::Truncated);
                 }
 
                 Ok(Repr::DstUnreachable {
@@ -435,6 +436,7 @@ impl<'a> Repr<'a> {
                         next_header: ip_packet.next_header(),
                         payload_len: payload.len(),
                         hop_limit: ip_packet.hop_limit(),
+                        traffic_class: 0,
                     },
                     data: payload,
                 })
@@ -447,7 +449,7 @@ impl<'a> Repr<'a> {
                 // RFC 792 requires exactly eight bytes to be returned.
                 // We allow more, since there isn't a reason not to, but require at least eight.
                 if payload.len() < 8 {
-                    return Err(Error);
+                    return Err(Error::Truncated);
                 }
 
                 Ok(Repr::TimeExceeded {
@@ -458,12 +460,13 @@ impl<'a> Repr<'a> {
                         next_header: ip_packet.next_header(),
                         payload_len: payload.len(),
                         hop_limit: ip_packet.hop_limit(),
+                        traffic_class: 0,
                     },
                     data: payload,
                 })
             }
 
-            _ => Err(Error),
+            _ => Err(Error::Malformed),
         }
     }
 
@@ -695,8 +698,8 @@ mod test {
     #[test]
     fn test_check_len() {
         let bytes = [0x0b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
-        assert_eq!(Packet::new_checked(&[]), Err(Error));
-        assert_eq!(Packet::new_checked(&bytes[..4]), Err(Error));
+        assert_eq!(Packet::new_checked(&[]), Err(Error::Truncated));
+        assert_eq!(Packet::new_checked(&bytes[..4]), Err(Error::Truncated));
         assert!(Packet::new_checked(&bytes[..]).is_ok());
     }
 }
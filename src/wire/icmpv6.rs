@@ -262,11 +262,11 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     /// Ensure that no accessor method will panic if called.
-    /// Returns `Err(Error)` if the buffer is too short.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
     pub fn check_len(&self) -> Result<()> {
         let len = self.buffer.as_ref().len();
         if len < field::HEADER_END || len < self.header_len() {
-            Err(Error)
+            Err(Error::Truncated)
         } else {
             Ok(())
         }
@@ -557,7 +557,7 @@ impl<'a> Repr<'a> {
 
             let payload = &packet.payload()[ip_packet.header_len()..];
             if payload.len() < 8 {
-                return Err(Error);
+                return Err(Error::Truncated);
             }
             let repr = Ipv6Repr {
                 src_addr: ip_packet.src_addr(),
@@ -565,12 +565,13 @@ impl<'a> Repr<'a> {
                 next_header: ip_packet.next_header(),
                 payload_len: payload.len(),
                 hop_limit: ip_packet.hop_limit(),
+                traffic_class: 0,
             };
             Ok((payload, repr))
         }
         // Valid checksum is expected.
         if checksum_caps.icmpv6.rx() && !packet.verify_checksum(src_addr, dst_addr) {
-            return Err(Error);
+            return Err(Error::Checksum);
         }
 
         match (packet.msg_type(), packet.msg_code()) {
@@ -620,7 +621,7 @@ impl<'a> Repr<'a> {
             #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
             (msg_type, 0) if msg_type.is_ndisc() => NdiscRepr::parse(packet).map(Repr::Ndisc),
             (msg_type, 0) if msg_type.is_mld() => MldRepr::parse(packet).map(Repr::Mld),
-            _ => Err(Error),
+            _ => Err(Error::Malformed),
         }
     }
 
@@ -798,6 +799,7 @@ mod test {
                 next_header: IpProtocol::Udp,
                 payload_len: 12,
                 hop_limit: 0x40,
+                traffic_class: 0,
             },
             data: &PKT_TOO_BIG_UDP_PAYLOAD,
         }
@@ -11,6 +11,7 @@ use crate::wire::{Ipv6Address, Ipv6Cidr, Ipv6Packet, Ipv6Repr};
 /// Internet protocol version.
 #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Version {
     #[cfg(feature = "proto-ipv4")]
     Ipv4,
@@ -22,14 +23,14 @@ impl Version {
     /// Return the version of an IP packet stored in the provided buffer.
     ///
     /// This function never returns `Ok(IpVersion::Unspecified)`; instead,
-    /// unknown versions result in `Err(Error)`.
+    /// unknown versions result in `Err(Error::Malformed)`.
     pub const fn of_packet(data: &[u8]) -> Result<Version> {
         match data[0] >> 4 {
             #[cfg(feature = "proto-ipv4")]
             4 => Ok(Version::Ipv4),
             #[cfg(feature = "proto-ipv6")]
             6 => Ok(Version::Ipv6),
-            _ => Err(Error),
+            _ => Err(Error::Malformed),
         }
     }
 }
@@ -80,7 +81,7 @@ impl fmt::Display for Protocol {
 }
 
 /// An internetworking address.
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum Address {
     /// An IPv4 address.
     #[cfg(feature = "proto-ipv4")]
@@ -241,6 +242,12 @@ impl From<Ipv6Address> for Address {
     }
 }
 
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -252,6 +259,9 @@ impl fmt::Display for Address {
     }
 }
 
+#[cfg(feature = "serde")]
+serde_via_str!(Address, "an IP address");
+
 #[cfg(feature = "defmt")]
 impl defmt::Format for Address {
     fn format(&self, f: defmt::Formatter) {
@@ -308,6 +318,16 @@ impl Cidr {
         }
     }
 
+    /// Return the network block of this CIDR block.
+    pub fn network(&self) -> Cidr {
+        match *self {
+            #[cfg(feature = "proto-ipv4")]
+            Cidr::Ipv4(cidr) => Cidr::Ipv4(cidr.network()),
+            #[cfg(feature = "proto-ipv6")]
+            Cidr::Ipv6(cidr) => Cidr::Ipv6(cidr.network()),
+        }
+    }
+
     /// Query whether the subnetwork described by this CIDR block contains
     /// the given address.
     pub fn contains_addr(&self, addr: &Address) -> bool {
@@ -360,6 +380,9 @@ impl fmt::Display for Cidr {
     }
 }
 
+#[cfg(feature = "serde")]
+serde_via_str!(Cidr, "an IP CIDR block");
+
 #[cfg(feature = "defmt")]
 impl defmt::Format for Cidr {
     fn format(&self, f: defmt::Formatter) {
@@ -378,7 +401,7 @@ impl defmt::Format for Cidr {
 ///
 /// See also ['ListenEndpoint'], which allows not specifying the address
 /// in order to listen on a given port on any address.
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct Endpoint {
     pub addr: Address,
     pub port: u16,
@@ -389,6 +412,11 @@ impl Endpoint {
     pub const fn new(addr: Address, port: u16) -> Endpoint {
         Endpoint { addr: addr, port }
     }
+
+    /// Query whether the endpoint has a specified address and port.
+    pub fn is_specified(&self) -> bool {
+        !self.addr.is_unspecified() && self.port != 0
+    }
 }
 
 #[cfg(all(feature = "std", feature = "proto-ipv4", feature = "proto-ipv6"))]
@@ -421,12 +449,26 @@ impl From<::std::net::SocketAddrV6> for Endpoint {
     }
 }
 
+impl fmt::Debug for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 impl fmt::Display for Endpoint {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}:{}", self.addr, self.port)
+        match self.addr {
+            #[cfg(feature = "proto-ipv6")]
+            Address::Ipv6(addr) => write!(f, "[{}]:{}", addr, self.port),
+            #[allow(unreachable_patterns)]
+            addr => write!(f, "{}:{}", addr, self.port),
+        }
     }
 }
 
+#[cfg(feature = "serde")]
+serde_via_str!(Endpoint, "an IP endpoint");
+
 #[cfg(feature = "defmt")]
 impl defmt::Format for Endpoint {
     fn format(&self, f: defmt::Formatter) {
@@ -449,7 +491,7 @@ impl<T: Into<Address>> From<(T, u16)> for Endpoint {
 /// in order to listen on a given port at all our addresses.
 ///
 /// An endpoint can be constructed from a port, in which case the address is unspecified.
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
 pub struct ListenEndpoint {
     pub addr: Option<Address>,
     pub port: u16,
@@ -460,6 +502,25 @@ impl ListenEndpoint {
     pub const fn is_specified(&self) -> bool {
         self.addr.is_some() && self.port != 0
     }
+
+    /// Query whether a packet sent to the given local endpoint is accepted by a socket
+    /// listening on this endpoint.
+    ///
+    /// An endpoint without an address accepts packets sent to any address, and an endpoint
+    /// without a port accepts none.
+    pub fn matches(&self, endpoint: &Endpoint) -> bool {
+        self.port != 0
+            && self.port == endpoint.port
+            && self.addr.map_or(true, |addr| addr == endpoint.addr)
+    }
+
+    /// Return how specific the endpoint is, for choosing between several endpoints
+    /// matching the same packet, the higher the better.
+    ///
+    /// An endpoint with an address is more specific than an endpoint with only a port.
+    pub const fn specificity(&self) -> u8 {
+        self.addr.is_some() as u8
+    }
 }
 
 #[cfg(all(feature = "std", feature = "proto-ipv4", feature = "proto-ipv6"))]
@@ -492,16 +553,25 @@ impl From<::std::net::SocketAddrV6> for ListenEndpoint {
     }
 }
 
+impl fmt::Debug for ListenEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 impl fmt::Display for ListenEndpoint {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if let Some(addr) = self.addr {
-            write!(f, "{}:{}", addr, self.port)
+            write!(f, "{}", Endpoint::new(addr, self.port))
         } else {
             write!(f, "*:{}", self.port)
         }
     }
 }
 
+#[cfg(feature = "serde")]
+serde_via_str!(ListenEndpoint, "an IP listen endpoint");
+
 #[cfg(feature = "defmt")]
 impl defmt::Format for ListenEndpoint {
     fn format(&self, f: defmt::Formatter) {
@@ -539,6 +609,7 @@ impl<T: Into<Address>> From<(T, u16)> for ListenEndpoint {
 /// or IPv6 concrete high-level representation.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Repr {
     #[cfg(feature = "proto-ipv4")]
     Ipv4(Ipv4Repr),
@@ -581,6 +652,7 @@ impl Repr {
                 next_header,
                 payload_len,
                 hop_limit,
+                traffic_class: 0,
             }),
             #[cfg(feature = "proto-ipv6")]
             (Address::Ipv6(src_addr), Address::Ipv6(dst_addr)) => Self::Ipv6(Ipv6Repr {
@@ -589,6 +661,7 @@ impl Repr {
                 next_header,
                 payload_len,
                 hop_limit,
+                traffic_class: 0,
             }),
             #[allow(unreachable_patterns)]
             _ => panic!("IP version mismatch: src={src_addr:?} dst={dst_addr:?}"),
@@ -665,6 +738,27 @@ impl Repr {
         }
     }
 
+    /// Return the traffic class, i.e. the DSCP in the upper six bits and the ECN field
+    /// in the lower two bits.
+    pub const fn traffic_class(&self) -> u8 {
+        match *self {
+            #[cfg(feature = "proto-ipv4")]
+            Repr::Ipv4(Ipv4Repr { traffic_class, .. }) => traffic_class,
+            #[cfg(feature = "proto-ipv6")]
+            Repr::Ipv6(Ipv6Repr { traffic_class, .. }) => traffic_class,
+        }
+    }
+
+    /// Set the traffic class.
+    pub fn set_traffic_class(&mut self, value: u8) {
+        match self {
+            #[cfg(feature = "proto-ipv4")]
+            Repr::Ipv4(Ipv4Repr { traffic_class, .. }) => *traffic_class = value,
+            #[cfg(feature = "proto-ipv6")]
+            Repr::Ipv6(Ipv6Repr { traffic_class, .. }) => *traffic_class = value,
+        }
+    }
+
     /// Return the length of a header that will be emitted from this high-level representation.
     pub const fn header_len(&self) -> usize {
         match *self {
@@ -991,4 +1085,19 @@ pub(crate) mod test {
             .prefix_len()
         );
     }
+
+    #[test]
+    fn listen_endpoint_matches() {
+        let endpoint = Endpoint::new(MOCK_IP_ADDR_1, 80);
+
+        let any = ListenEndpoint::from(80);
+        let specific = ListenEndpoint::from((MOCK_IP_ADDR_1, 80));
+        assert!(any.matches(&endpoint));
+        assert!(specific.matches(&endpoint));
+        assert!(specific.specificity() > any.specificity());
+
+        assert!(!ListenEndpoint::from((MOCK_IP_ADDR_2, 80)).matches(&endpoint));
+        assert!(!ListenEndpoint::from(81).matches(&endpoint));
+        assert!(!ListenEndpoint::from(0).matches(&Endpoint::new(MOCK_IP_ADDR_1, 0)));
+    }
 }
@@ -29,13 +29,13 @@ pub const ADDR_SIZE: usize = 4;
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
 pub struct Key {
     id: u16,
-    src_addr: Address,
-    dst_addr: Address,
-    protocol: Protocol,
+    pub(crate) src_addr: Address,
+    pub(crate) dst_addr: Address,
+    pub(crate) protocol: Protocol,
 }
 
 /// A four-octet IPv4 address.
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
 pub struct Address(pub [u8; ADDR_SIZE]);
 
 impl Address {
@@ -123,6 +123,12 @@ impl From<Address> for ::std::net::Ipv4Addr {
     }
 }
 
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let bytes = self.0;
@@ -130,6 +136,9 @@ impl fmt::Display for Address {
     }
 }
 
+#[cfg(feature = "serde")]
+serde_via_str!(Address, "an IPv4 address");
+
 #[cfg(feature = "defmt")]
 impl defmt::Format for Address {
     fn format(&self, f: defmt::Formatter) {
@@ -177,7 +186,7 @@ impl Cidr {
                 prefix_len: netmask.count_ones() as u8,
             })
         } else {
-            Err(Error)
+            Err(Error::Malformed)
         }
     }
 
@@ -243,6 +252,26 @@ impl Cidr {
         }
     }
 
+    /// Return an iterator over the host addresses in this IPv4 CIDR block.
+    ///
+    /// The network and broadcast addresses are skipped, except in /31 and /32 blocks,
+    /// which have neither; see [RFC 3021].
+    ///
+    /// [RFC 3021]: https://tools.ietf.org/html/rfc3021
+    pub fn hosts(&self) -> impl Iterator<Item = Address> {
+        let first = NetworkEndian::read_u32(&self.network().address.0[..]);
+        let last = first
+            | 0xffffffffu32
+                .checked_shr(self.prefix_len as u32)
+                .unwrap_or(0);
+        let range = if self.prefix_len >= 31 {
+            first..=last
+        } else {
+            first + 1..=last - 1
+        };
+        range.map(|number| Address(number.to_be_bytes()))
+    }
+
     /// Query whether the subnetwork described by this IPv4 CIDR block contains
     /// the given address.
     pub fn contains_addr(&self, addr: &Address) -> bool {
@@ -270,6 +299,9 @@ impl fmt::Display for Cidr {
     }
 }
 
+#[cfg(feature = "serde")]
+serde_via_str!(Cidr, "an IPv4 CIDR block");
+
 #[cfg(feature = "defmt")]
 impl defmt::Format for Cidr {
     fn format(&self, f: defmt::Formatter) {
@@ -318,8 +350,8 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     /// Ensure that no accessor method will panic if called.
-    /// Returns `Err(Error)` if the buffer is too short.
-    /// Returns `Err(Error)` if the header length is greater
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
+    /// Returns `Err(Error::Malformed)` if the header length is greater
     /// than total length.
     ///
     /// The result of this check is invalidated by calling [set_header_len]
@@ -331,13 +363,13 @@ impl<T: AsRef<[u8]>> Packet<T> {
     pub fn check_len(&self) -> Result<()> {
         let len = self.buffer.as_ref().len();
         if len < field::DST_ADDR.end {
-            Err(Error)
+            Err(Error::Truncated)
         } else if len < self.header_len() as usize {
-            Err(Error)
+            Err(Error::Truncated)
         } else if self.header_len() as u16 > self.total_len() {
-            Err(Error)
+            Err(Error::Malformed)
         } else if len < self.total_len() as usize {
-            Err(Error)
+            Err(Error::Truncated)
         } else {
             Ok(())
         }
@@ -469,6 +501,14 @@ impl<T: AsRef<[u8]>> Packet<T> {
 }
 
 impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the options.
+    #[inline]
+    pub fn options(&self) -> &'a [u8] {
+        let range = field::DST_ADDR.end..self.header_len() as usize;
+        let data = self.buffer.as_ref();
+        &data[range]
+    }
+
     /// Return a pointer to the payload.
     #[inline]
     pub fn payload(&self) -> &'a [u8] {
@@ -618,12 +658,16 @@ impl<T: AsRef<[u8]>> AsRef<[u8]> for Packet<T> {
 /// A high-level representation of an Internet Protocol version 4 packet header.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Repr {
     pub src_addr: Address,
     pub dst_addr: Address,
     pub next_header: Protocol,
     pub payload_len: usize,
     pub hop_limit: u8,
+    /// The type of service field, i.e. the DSCP in the upper six bits and the ECN field
+    /// in the lower two bits.
+    pub traffic_class: u8,
 }
 
 impl Repr {
@@ -634,17 +678,17 @@ impl Repr {
     ) -> Result<Repr> {
         // Version 4 is expected.
         if packet.version() != 4 {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
         // Valid checksum is expected.
         if checksum_caps.ipv4.rx() && !packet.verify_checksum() {
-            return Err(Error);
+            return Err(Error::Checksum);
         }
 
         #[cfg(not(feature = "proto-ipv4-fragmentation"))]
         // We do not support fragmentation.
         if packet.more_frags() || packet.frag_offset() != 0 {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
 
         let payload_len = packet.total_len() as usize - packet.header_len() as usize;
@@ -658,6 +702,7 @@ impl Repr {
             next_header: packet.next_header(),
             payload_len,
             hop_limit: packet.hop_limit(),
+            traffic_class: (packet.dscp() << 2) | packet.ecn(),
         })
     }
 
@@ -675,8 +720,8 @@ impl Repr {
     ) {
         packet.set_version(4);
         packet.set_header_len(field::DST_ADDR.end as u8);
-        packet.set_dscp(0);
-        packet.set_ecn(0);
+        packet.set_dscp(self.traffic_class >> 2);
+        packet.set_ecn(self.traffic_class & 0x03);
         let total_len = packet.header_len() as u16 + self.payload_len as u16;
         packet.set_total_len(total_len);
         packet.set_ident(0);
@@ -870,7 +915,7 @@ mod test {
         bytes.extend(&PACKET_BYTES[..]);
         Packet::new_unchecked(&mut bytes).set_total_len(128);
 
-        assert_eq!(Packet::new_checked(&bytes).unwrap_err(), Error);
+        assert_eq!(Packet::new_checked(&bytes).unwrap_err(), Error::Truncated);
     }
 
     static REPR_PACKET_BYTES: [u8; 24] = [
@@ -887,6 +932,7 @@ mod test {
             next_header: Protocol::Icmp,
             payload_len: 4,
             hop_limit: 64,
+            traffic_class: 0,
         }
     }
 
@@ -907,7 +953,7 @@ mod test {
         let packet = Packet::new_unchecked(&*packet.into_inner());
         assert_eq!(
             Repr::parse(&packet, &ChecksumCapabilities::default()),
-            Err(Error)
+            Err(Error::Malformed)
         );
     }
 
@@ -915,7 +961,7 @@ mod test {
     fn test_parse_total_len_less_than_header_len() {
         let mut bytes = vec![0; 40];
         bytes[0] = 0x09;
-        assert_eq!(Packet::new_checked(&mut bytes), Err(Error));
+        assert_eq!(Packet::new_checked(&mut bytes), Err(Error::Malformed));
     }
 
     #[test]
@@ -928,6 +974,22 @@ mod test {
         assert_eq!(&*packet.into_inner(), &REPR_PACKET_BYTES[..]);
     }
 
+    #[test]
+    fn test_traffic_class() {
+        let mut repr = packet_repr();
+        repr.traffic_class = (46 << 2) | 0x02;
+        let mut bytes = vec![0xa5; repr.buffer_len() + REPR_PAYLOAD_BYTES.len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(&mut packet, &ChecksumCapabilities::default());
+        assert_eq!(packet.dscp(), 46);
+        assert_eq!(packet.ecn(), 0x02);
+        let packet = Packet::new_unchecked(&*packet.into_inner());
+        assert_eq!(
+            Repr::parse(&packet, &ChecksumCapabilities::default()),
+            Ok(repr)
+        );
+    }
+
     #[test]
     fn test_unspecified() {
         assert!(Address::UNSPECIFIED.is_unspecified());
@@ -1131,6 +1193,30 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_cidr_hosts() {
+        let mut hosts = Cidr::new(Address([192, 168, 1, 77]), 30).hosts();
+        assert_eq!(hosts.next(), Some(Address([192, 168, 1, 77])));
+        assert_eq!(hosts.next(), Some(Address([192, 168, 1, 78])));
+        assert_eq!(hosts.next(), None);
+
+        let mut hosts = Cidr::new(Address([192, 168, 1, 0]), 31).hosts();
+        assert_eq!(hosts.next(), Some(Address([192, 168, 1, 0])));
+        assert_eq!(hosts.next(), Some(Address([192, 168, 1, 1])));
+        assert_eq!(hosts.next(), None);
+
+        let mut hosts = Cidr::new(Address([192, 168, 1, 1]), 32).hosts();
+        assert_eq!(hosts.next(), Some(Address([192, 168, 1, 1])));
+        assert_eq!(hosts.next(), None);
+
+        assert_eq!(
+            Cidr::new(Address([172, 16, 0, 0]), 16).hosts().count(),
+            0xfffe
+        );
+        let mut hosts = Cidr::new(Address([0, 0, 0, 0]), 0).hosts();
+        assert_eq!(hosts.next(), Some(Address([0, 0, 0, 1])));
+    }
+
     #[test]
     fn test_cidr_network() {
         assert_eq!(
@@ -26,7 +26,7 @@ pub const ADDR_SIZE: usize = 16;
 pub const IPV4_MAPPED_PREFIX_SIZE: usize = ADDR_SIZE - 4; // 4 == ipv4::ADDR_SIZE , cannot DRY here because of dependency on a IPv4 module which is behind the feature
 
 /// A sixteen-octet IPv6 address.
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Address(pub [u8; ADDR_SIZE]);
 
@@ -244,6 +244,12 @@ impl From<Address> for ::std::net::Ipv6Addr {
     }
 }
 
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.is_ipv4_mapped() {
@@ -257,54 +263,47 @@ impl fmt::Display for Address {
             );
         }
 
-        // The string representation of an IPv6 address should
-        // collapse a series of 16 bit sections that evaluate
-        // to 0 to "::"
+        // The longest run of at least two 16 bit sections that evaluate
+        // to 0 is collapsed to "::", the first one if there is a tie.
         //
-        // See https://tools.ietf.org/html/rfc4291#section-2.2
+        // See https://tools.ietf.org/html/rfc5952#section-4.2
         // for details.
-        enum State {
-            Head,
-            HeadBody,
-            Tail,
-            TailBody,
-        }
         let mut words = [0u16; 8];
         self.write_parts(&mut words);
-        let mut state = State::Head;
-        for word in words.iter() {
-            state = match (*word, &state) {
-                // Once a u16 equal to zero write a double colon and
-                // skip to the next non-zero u16.
-                (0, &State::Head) | (0, &State::HeadBody) => {
+        let (mut longest, mut longest_len, mut run_start) = (0, 0, 0);
+        for (i, word) in words.iter().enumerate() {
+            if *word != 0 {
+                run_start = i + 1;
+            } else if i + 1 - run_start > longest_len {
+                longest = run_start;
+                longest_len = i + 1 - run_start;
+            }
+        }
+        let collapsed = if longest_len >= 2 {
+            longest..longest + longest_len
+        } else {
+            words.len()..words.len()
+        };
+
+        for (i, word) in words.iter().enumerate() {
+            if collapsed.contains(&i) {
+                if i == collapsed.start {
                     write!(f, "::")?;
-                    State::Tail
-                }
-                // Continue iterating without writing any characters until
-                // we hit a non-zero value.
-                (0, &State::Tail) => State::Tail,
-                // When the state is Head or Tail write a u16 in hexadecimal
-                // without the leading colon if the value is not 0.
-                (_, &State::Head) => {
-                    write!(f, "{word:x}")?;
-                    State::HeadBody
-                }
-                (_, &State::Tail) => {
-                    write!(f, "{word:x}")?;
-                    State::TailBody
-                }
-                // Write the u16 with a leading colon when parsing a value
-                // that isn't the first in a section
-                (_, &State::HeadBody) | (_, &State::TailBody) => {
-                    write!(f, ":{word:x}")?;
-                    state
                 }
+                continue;
+            }
+            if i != 0 && i != collapsed.end {
+                write!(f, ":")?;
             }
+            write!(f, "{word:x}")?;
         }
         Ok(())
     }
 }
 
+#[cfg(feature = "serde")]
+serde_via_str!(Address, "an IPv6 address");
+
 #[cfg(feature = "proto-ipv4")]
 /// Convert the given IPv4 address into a IPv4-mapped IPv6 address
 impl From<ipv4::Address> for Address {
@@ -359,6 +358,14 @@ impl Cidr {
         self.prefix_len
     }
 
+    /// Return the network block of this IPv6 CIDR.
+    pub fn network(&self) -> Cidr {
+        Cidr {
+            address: Address(self.address.mask(self.prefix_len)),
+            prefix_len: self.prefix_len,
+        }
+    }
+
     /// Query whether the subnetwork described by this IPv6 CIDR block contains
     /// the given address.
     pub fn contains_addr(&self, addr: &Address) -> bool {
@@ -384,6 +391,9 @@ impl fmt::Display for Cidr {
     }
 }
 
+#[cfg(feature = "serde")]
+serde_via_str!(Cidr, "an IPv6 CIDR block");
+
 /// A read/write wrapper around an Internet Protocol version 6 packet buffer.
 #[derive(Debug, PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -458,7 +468,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     /// Ensure that no accessor method will panic if called.
-    /// Returns `Err(Error)` if the buffer is too short.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
     ///
     /// The result of this check is invalidated by calling [set_payload_len].
     ///
@@ -467,7 +477,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     pub fn check_len(&self) -> Result<()> {
         let len = self.buffer.as_ref().len();
         if len < field::DST_ADDR.end || len < self.total_len() {
-            Err(Error)
+            Err(Error::Truncated)
         } else {
             Ok(())
         }
@@ -656,6 +666,7 @@ impl<T: AsRef<[u8]>> AsRef<[u8]> for Packet<T> {
 /// A high-level representation of an Internet Protocol version 6 packet header.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Repr {
     /// IPv6 address of the source node.
     pub src_addr: Address,
@@ -667,6 +678,9 @@ pub struct Repr {
     pub payload_len: usize,
     /// The 8-bit hop limit field.
     pub hop_limit: u8,
+    /// The 8-bit traffic class field, i.e. the DSCP in the upper six bits and the ECN field
+    /// in the lower two bits.
+    pub traffic_class: u8,
 }
 
 impl Repr {
@@ -675,7 +689,7 @@ impl Repr {
         // Ensure basic accessors will work
         packet.check_len()?;
         if packet.version() != 6 {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
         Ok(Repr {
             src_addr: packet.src_addr(),
@@ -683,6 +697,7 @@ impl Repr {
             next_header: packet.next_header(),
             payload_len: packet.payload_len() as usize,
             hop_limit: packet.hop_limit(),
+            traffic_class: packet.traffic_class(),
         })
     }
 
@@ -697,7 +712,7 @@ impl Repr {
         // Make no assumptions about the original state of the packet buffer.
         // Make sure to set every byte.
         packet.set_version(6);
-        packet.set_traffic_class(0);
+        packet.set_traffic_class(self.traffic_class);
         packet.set_flow_label(0);
         packet.set_payload_len(self.payload_len as u16);
         packet.set_hop_limit(self.hop_limit);
@@ -797,6 +812,20 @@ mod test {
         );
         assert_eq!("::", format!("{}", Address::UNSPECIFIED));
         assert_eq!("::1", format!("{}", Address::LOOPBACK));
+        assert_eq!(
+            "2001:db8:0:1::1",
+            format!("{}", Address::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 1))
+        );
+        assert_eq!(
+            "2001:db8::1:0:0:1",
+            format!("{}", Address::new(0x2001, 0xdb8, 0, 0, 1, 0, 0, 1))
+        );
+        assert_eq!(
+            "2001:db8:0:1:1:1:1:1",
+            format!("{}", Address::new(0x2001, 0xdb8, 0, 1, 1, 1, 1, 1))
+        );
+        assert_eq!("1::", format!("{}", Address::new(1, 0, 0, 0, 0, 0, 0, 0)));
+        assert_eq!("fe80::1", format!("{LINK_LOCAL_ADDR:?}"));
 
         #[cfg(feature = "proto-ipv4")]
         assert_eq!(
@@ -915,6 +944,26 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_cidr_network() {
+        assert_eq!(
+            Cidr::new(LINK_LOCAL_ADDR, 64).network(),
+            Cidr::new(Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 0), 64)
+        );
+        assert_eq!(
+            Cidr::new(Address::new(0xfdbe, 0x1234, 0x5678, 0x9abc, 0, 0, 0, 1), 36).network(),
+            Cidr::new(Address::new(0xfdbe, 0x1234, 0x5000, 0, 0, 0, 0, 0), 36)
+        );
+        assert_eq!(
+            Cidr::new(LINK_LOCAL_ADDR, 128).network(),
+            Cidr::new(LINK_LOCAL_ADDR, 128)
+        );
+        assert_eq!(
+            Cidr::new(LINK_LOCAL_ADDR, 0).network(),
+            Cidr::new(Address::UNSPECIFIED, 0)
+        );
+    }
+
     #[test]
     fn test_cidr() {
         // fe80::1/56
@@ -1082,6 +1131,7 @@ mod test {
             next_header: Protocol::Udp,
             payload_len: 12,
             hop_limit: 64,
+            traffic_class: 0,
         }
     }
 
@@ -1164,7 +1214,7 @@ mod test {
         bytes.extend(&REPR_PACKET_BYTES[..]);
         Packet::new_unchecked(&mut bytes).set_payload_len(0x80);
 
-        assert_eq!(Packet::new_checked(&bytes).unwrap_err(), Error);
+        assert_eq!(Packet::new_checked(&bytes).unwrap_err(), Error::Truncated);
     }
 
     #[test]
@@ -1181,7 +1231,7 @@ mod test {
         packet.set_version(4);
         packet.set_payload_len(0);
         let packet = Packet::new_unchecked(&*packet.into_inner());
-        assert_eq!(Repr::parse(&packet), Err(Error));
+        assert_eq!(Repr::parse(&packet), Err(Error::Malformed));
     }
 
     #[test]
@@ -1191,7 +1241,7 @@ mod test {
         packet.set_version(6);
         packet.set_payload_len(39);
         let packet = Packet::new_unchecked(&*packet.into_inner());
-        assert_eq!(Repr::parse(&packet), Err(Error));
+        assert_eq!(Repr::parse(&packet), Err(Error::Truncated));
     }
 
     #[test]
@@ -1201,7 +1251,7 @@ mod test {
         packet.set_version(6);
         packet.set_payload_len(1);
         let packet = Packet::new_unchecked(&*packet.into_inner());
-        assert_eq!(Repr::parse(&packet), Err(Error));
+        assert_eq!(Repr::parse(&packet), Err(Error::Truncated));
     }
 
     #[test]
@@ -0,0 +1,554 @@
+/*! Structured (JSON) rendering of packet representation.
+
+The `json` module renders high-level packet representations as JSON objects,
+with one member per field, so that captured packets can be processed by scripts
+or compared against expected traces.
+
+Representations are rendered with the [ToJson] trait. Raw packets can be rendered
+together with everything they encapsulate with [ethernet_frame] and [ip_packet];
+each encapsulated packet is nested in the `payload` member of its parent.
+
+# Example
+
+```rust
+# #[cfg(feature = "proto-ipv4")]
+# {
+use smoltcp::wire::json::ToJson;
+use smoltcp::wire::{IpProtocol, Ipv4Address, Ipv4Repr};
+
+let repr = Ipv4Repr {
+    src_addr: Ipv4Address::new(10, 0, 0, 1),
+    dst_addr: Ipv4Address::new(10, 0, 0, 2),
+    next_header: IpProtocol::Udp,
+    payload_len: 8,
+    hop_limit: 64,
+    traffic_class: 0,
+};
+assert_eq!(
+    repr.to_json(),
+    r#"{"type":"ipv4","src_addr":"10.0.0.1","dst_addr":"10.0.0.2","next_header":"UDP","payload_len":8,"hop_limit":64,"traffic_class":0}"#
+);
+# }
+```
+*/
+
+use core::fmt::{Display, Write};
+use std::string::String;
+
+use super::*;
+use crate::phy::ChecksumCapabilities;
+
+/// A value that can be rendered as JSON.
+pub trait ToJson {
+    /// Append the JSON rendering of this value to `out`.
+    fn write_json(&self, out: &mut String);
+
+    /// Return the JSON rendering of this value.
+    fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+}
+
+/// A JSON object being written.
+struct Object<'a> {
+    out: &'a mut String,
+    empty: bool,
+}
+
+impl<'a> Object<'a> {
+    fn new(out: &'a mut String, type_: &str) -> Object<'a> {
+        out.push('{');
+        let mut object = Object { out, empty: true };
+        object.field("type", type_);
+        object
+    }
+
+    fn key(&mut self, name: &str) -> &mut String {
+        if !self.empty {
+            self.out.push(',');
+        }
+        self.empty = false;
+        name.write_json(self.out);
+        self.out.push(':');
+        self.out
+    }
+
+    fn field<T: ToJson + ?Sized>(&mut self, name: &str, value: &T) -> &mut Self {
+        value.write_json(self.key(name));
+        self
+    }
+
+    fn display<T: Display>(&mut self, name: &str, value: T) -> &mut Self {
+        let mut s = String::new();
+        let _ = write!(s, "{}", value);
+        self.field(name, s.as_str())
+    }
+
+    fn finish(&mut self) {
+        self.out.push('}');
+    }
+}
+
+impl ToJson for str {
+    fn write_json(&self, out: &mut String) {
+        out.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if (c as u32) < 0x20 => {
+                    let _ = write!(out, "\\u{:04x}", c as u32);
+                }
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+}
+
+impl ToJson for bool {
+    fn write_json(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" })
+    }
+}
+
+macro_rules! impl_to_json_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl ToJson for $ty {
+                fn write_json(&self, out: &mut String) {
+                    let _ = write!(out, "{}", self);
+                }
+            }
+        )*
+    };
+}
+
+impl_to_json_for_int!(u8, u16, u32, u64, usize, i32);
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn write_json(&self, out: &mut String) {
+        match self {
+            Some(value) => value.write_json(out),
+            None => out.push_str("null"),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn write_json(&self, out: &mut String) {
+        out.push('[');
+        for (i, value) in self.iter().enumerate() {
+            if i != 0 {
+                out.push(',');
+            }
+            value.write_json(out);
+        }
+        out.push(']');
+    }
+}
+
+impl ToJson for TcpSeqNumber {
+    fn write_json(&self, out: &mut String) {
+        self.0.write_json(out)
+    }
+}
+
+#[cfg(feature = "medium-ethernet")]
+impl ToJson for EthernetRepr {
+    fn write_json(&self, out: &mut String) {
+        Object::new(out, "ethernet")
+            .display("src_addr", self.src_addr)
+            .display("dst_addr", self.dst_addr)
+            .display("ethertype", self.ethertype)
+            .finish()
+    }
+}
+
+#[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+impl ToJson for ArpRepr {
+    fn write_json(&self, out: &mut String) {
+        match *self {
+            ArpRepr::EthernetIpv4 {
+                operation,
+                source_hardware_addr,
+                source_protocol_addr,
+                target_hardware_addr,
+                target_protocol_addr,
+            } => Object::new(out, "arp")
+                .display("operation", format_args!("{:?}", operation))
+                .display("source_hardware_addr", source_hardware_addr)
+                .display("source_protocol_addr", source_protocol_addr)
+                .display("target_hardware_addr", target_hardware_addr)
+                .display("target_protocol_addr", target_protocol_addr)
+                .finish(),
+        }
+    }
+}
+
+#[cfg(feature = "proto-ipv4")]
+impl ToJson for Ipv4Repr {
+    fn write_json(&self, out: &mut String) {
+        Object::new(out, "ipv4")
+            .display("src_addr", self.src_addr)
+            .display("dst_addr", self.dst_addr)
+            .display("next_header", self.next_header)
+            .field("payload_len", &self.payload_len)
+            .field("hop_limit", &self.hop_limit)
+            .field("traffic_class", &self.traffic_class)
+            .finish()
+    }
+}
+
+#[cfg(feature = "proto-ipv6")]
+impl ToJson for Ipv6Repr {
+    fn write_json(&self, out: &mut String) {
+        Object::new(out, "ipv6")
+            .display("src_addr", self.src_addr)
+            .display("dst_addr", self.dst_addr)
+            .display("next_header", self.next_header)
+            .field("payload_len", &self.payload_len)
+            .field("hop_limit", &self.hop_limit)
+            .field("traffic_class", &self.traffic_class)
+            .finish()
+    }
+}
+
+impl ToJson for IpRepr {
+    fn write_json(&self, out: &mut String) {
+        match self {
+            #[cfg(feature = "proto-ipv4")]
+            IpRepr::Ipv4(repr) => repr.write_json(out),
+            #[cfg(feature = "proto-ipv6")]
+            IpRepr::Ipv6(repr) => repr.write_json(out),
+        }
+    }
+}
+
+impl ToJson for UdpRepr {
+    fn write_json(&self, out: &mut String) {
+        Object::new(out, "udp")
+            .field("src_port", &self.src_port)
+            .field("dst_port", &self.dst_port)
+            .finish()
+    }
+}
+
+impl<'a> ToJson for TcpRepr<'a> {
+    fn write_json(&self, out: &mut String) {
+        let mut sack_ranges = [(0, 0); 3];
+        let mut sack_count = 0;
+        for (start, end) in self.sack_ranges.iter().flatten() {
+            sack_ranges[sack_count] = (*start, *end);
+            sack_count += 1;
+        }
+
+        let mut object = Object::new(out, "tcp");
+        object
+            .field("src_port", &self.src_port)
+            .field("dst_port", &self.dst_port)
+            .display("control", format_args!("{:?}", self.control))
+            .field("seq_number", &self.seq_number)
+            .field("ack_number", &self.ack_number)
+            .field("window_len", &self.window_len)
+            .field("window_scale", &self.window_scale)
+            .field("max_seg_size", &self.max_seg_size)
+            .field("sack_permitted", &self.sack_permitted);
+        let out = object.key("sack_ranges");
+        out.push('[');
+        for (i, (start, end)) in sack_ranges[..sack_count].iter().enumerate() {
+            if i != 0 {
+                out.push(',');
+            }
+            [*start, *end].write_json(out);
+        }
+        out.push(']');
+        object.field("payload_len", &self.payload.len()).finish()
+    }
+}
+
+#[cfg(feature = "proto-ipv4")]
+impl<'a> ToJson for Icmpv4Repr<'a> {
+    fn write_json(&self, out: &mut String) {
+        match self {
+            Icmpv4Repr::EchoRequest {
+                ident,
+                seq_no,
+                data,
+            } => Object::new(out, "icmpv4_echo_request")
+                .field("ident", ident)
+                .field("seq_no", seq_no)
+                .field("data_len", &data.len())
+                .finish(),
+            Icmpv4Repr::EchoReply {
+                ident,
+                seq_no,
+                data,
+            } => Object::new(out, "icmpv4_echo_reply")
+                .field("ident", ident)
+                .field("seq_no", seq_no)
+                .field("data_len", &data.len())
+                .finish(),
+            Icmpv4Repr::DstUnreachable {
+                reason,
+                header,
+                data,
+            } => Object::new(out, "icmpv4_dst_unreachable")
+                .display("reason", reason)
+                .field("header", header)
+                .field("data_len", &data.len())
+                .finish(),
+            Icmpv4Repr::TimeExceeded {
+                reason,
+                header,
+                data,
+            } => Object::new(out, "icmpv4_time_exceeded")
+                .display("reason", reason)
+                .field("header", header)
+                .field("data_len", &data.len())
+                .finish(),
+        }
+    }
+}
+
+#[cfg(feature = "proto-ipv6")]
+impl<'a> ToJson for Icmpv6Repr<'a> {
+    fn write_json(&self, out: &mut String) {
+        match self {
+            Icmpv6Repr::EchoRequest {
+                ident,
+                seq_no,
+                data,
+            } => Object::new(out, "icmpv6_echo_request")
+                .field("ident", ident)
+                .field("seq_no", seq_no)
+                .field("data_len", &data.len())
+                .finish(),
+            Icmpv6Repr::EchoReply {
+                ident,
+                seq_no,
+                data,
+            } => Object::new(out, "icmpv6_echo_reply")
+                .field("ident", ident)
+                .field("seq_no", seq_no)
+                .field("data_len", &data.len())
+                .finish(),
+            Icmpv6Repr::DstUnreachable {
+                reason,
+                header,
+                data,
+            } => Object::new(out, "icmpv6_dst_unreachable")
+                .display("reason", reason)
+                .field("header", header)
+                .field("data_len", &data.len())
+                .finish(),
+            Icmpv6Repr::PktTooBig { mtu, header, data } => Object::new(out, "icmpv6_pkt_too_big")
+                .field("mtu", mtu)
+                .field("header", header)
+                .field("data_len", &data.len())
+                .finish(),
+            Icmpv6Repr::TimeExceeded {
+                reason,
+                header,
+                data,
+            } => Object::new(out, "icmpv6_time_exceeded")
+                .display("reason", reason)
+                .field("header", header)
+                .field("data_len", &data.len())
+                .finish(),
+            Icmpv6Repr::ParamProblem {
+                reason,
+                pointer,
+                header,
+                data,
+            } => Object::new(out, "icmpv6_param_problem")
+                .display("reason", reason)
+                .field("pointer", pointer)
+                .field("header", header)
+                .field("data_len", &data.len())
+                .finish(),
+            #[allow(unreachable_patterns)]
+            repr => Object::new(out, "icmpv6")
+                .display("repr", format_args!("{:?}", repr))
+                .finish(),
+        }
+    }
+}
+
+fn write_error(out: &mut String, type_: &str) {
+    Object::new(out, type_).field("error", "malformed").finish()
+}
+
+/// Render an Ethernet II frame, and everything it encapsulates, as JSON.
+#[cfg(feature = "medium-ethernet")]
+pub fn ethernet_frame(buffer: &[u8]) -> String {
+    let mut out = String::new();
+    let frame = match EthernetFrame::new_checked(buffer) {
+        Ok(frame) => frame,
+        Err(_) => {
+            write_error(&mut out, "ethernet");
+            return out;
+        }
+    };
+    let repr = match EthernetRepr::parse(&frame) {
+        Ok(repr) => repr,
+        Err(_) => {
+            write_error(&mut out, "ethernet");
+            return out;
+        }
+    };
+
+    repr.write_json(&mut out);
+    out.pop();
+    out.push_str(",\"payload\":");
+    match repr.ethertype {
+        #[cfg(feature = "proto-ipv4")]
+        EthernetProtocol::Arp => {
+            match ArpPacket::new_checked(frame.payload()).and_then(|p| ArpRepr::parse(&p)) {
+                Ok(repr) => repr.write_json(&mut out),
+                Err(_) => write_error(&mut out, "arp"),
+            }
+        }
+        EthernetProtocol::Ipv4 | EthernetProtocol::Ipv6 => {
+            out.push_str(&ip_packet(frame.payload()))
+        }
+        _ => out.push_str("null"),
+    }
+    out.push('}');
+    out
+}
+
+/// Render an IPv4 or IPv6 packet, and everything it encapsulates, as JSON.
+///
+/// Checksums are not verified.
+pub fn ip_packet(buffer: &[u8]) -> String {
+    let mut out = String::new();
+    let checksum_caps = ChecksumCapabilities::ignored();
+
+    let (ip_repr, payload): (IpRepr, &[u8]) = match IpVersion::of_packet(buffer) {
+        #[cfg(feature = "proto-ipv4")]
+        Ok(IpVersion::Ipv4) => {
+            match Ipv4Packet::new_checked(buffer)
+                .and_then(|p| Ipv4Repr::parse(&p, &checksum_caps).map(|r| (r, p)))
+            {
+                Ok((repr, packet)) => (IpRepr::Ipv4(repr), packet.payload()),
+                Err(_) => {
+                    write_error(&mut out, "ipv4");
+                    return out;
+                }
+            }
+        }
+        #[cfg(feature = "proto-ipv6")]
+        Ok(IpVersion::Ipv6) => match Ipv6Packet::new_checked(buffer)
+            .and_then(|p| Ipv6Repr::parse(&p).map(|r| (r, p)))
+        {
+            Ok((repr, packet)) => (IpRepr::Ipv6(repr), packet.payload()),
+            Err(_) => {
+                write_error(&mut out, "ipv6");
+                return out;
+            }
+        },
+        _ => {
+            write_error(&mut out, "ip");
+            return out;
+        }
+    };
+
+    ip_repr.write_json(&mut out);
+    out.pop();
+    out.push_str(",\"payload\":");
+    let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
+    match ip_repr.next_header() {
+        IpProtocol::Udp => match UdpPacket::new_checked(payload)
+            .and_then(|p| UdpRepr::parse(&p, &src_addr, &dst_addr, &checksum_caps))
+        {
+            Ok(repr) => repr.write_json(&mut out),
+            Err(_) => write_error(&mut out, "udp"),
+        },
+        IpProtocol::Tcp => {
+            match TcpPacket::new_checked(payload).and_then(|p| {
+                TcpRepr::parse(&p, &src_addr, &dst_addr, &checksum_caps).map(|repr| repr.to_json())
+            }) {
+                Ok(json) => out.push_str(&json),
+                Err(_) => write_error(&mut out, "tcp"),
+            }
+        }
+        #[cfg(feature = "proto-ipv4")]
+        IpProtocol::Icmp => match Icmpv4Packet::new_checked(payload)
+            .and_then(|p| Icmpv4Repr::parse(&p, &checksum_caps).map(|repr| repr.to_json()))
+        {
+            Ok(json) => out.push_str(&json),
+            Err(_) => write_error(&mut out, "icmpv4"),
+        },
+        #[cfg(feature = "proto-ipv6")]
+        IpProtocol::Icmpv6 => match Icmpv6Packet::new_checked(payload).and_then(|p| {
+            Icmpv6Repr::parse(&src_addr, &dst_addr, &p, &checksum_caps).map(|repr| repr.to_json())
+        }) {
+            Ok(json) => out.push_str(&json),
+            Err(_) => write_error(&mut out, "icmpv6"),
+        },
+        _ => out.push_str("null"),
+    }
+    out.push('}');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!("a\"b\\c\n\u{1}".to_json(), r#""a\"b\\c\n\u0001""#);
+    }
+
+    #[test]
+    fn test_udp_repr() {
+        let repr = UdpRepr {
+            src_port: 1234,
+            dst_port: 53,
+        };
+        assert_eq!(
+            repr.to_json(),
+            r#"{"type":"udp","src_port":1234,"dst_port":53}"#
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_ethernet_frame() {
+        static FRAME: [u8; 42] = [
+            // Ethernet II
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x08, 0x00,
+            // IPv4
+            0x45, 0x00, 0x00, 0x1c, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0x0a, 0x00,
+            0x00, 0x01, 0x0a, 0x00, 0x00, 0x02, // UDP
+            0x04, 0xd2, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00,
+        ];
+        assert_eq!(
+            ethernet_frame(&FRAME),
+            concat!(
+                r#"{"type":"ethernet","src_addr":"11-12-13-14-15-16","#,
+                r#""dst_addr":"01-02-03-04-05-06","ethertype":"IPv4","payload":"#,
+                r#"{"type":"ipv4","src_addr":"10.0.0.1","dst_addr":"10.0.0.2","#,
+                r#""next_header":"UDP","payload_len":8,"hop_limit":64,"traffic_class":0,"#,
+                r#""payload":"#,
+                r#"{"type":"udp","src_port":1234,"dst_port":53}}}"#
+            )
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_ip_packet_truncated() {
+        assert_eq!(
+            ip_packet(&[0x45, 0x00]),
+            r#"{"type":"ipv4","error":"malformed"}"#
+        );
+    }
+}
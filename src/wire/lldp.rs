@@ -0,0 +1,383 @@
+// Heads up! Before working on this file you should read IEEE 802.1AB § 8 and 9,
+// which define the LLDPDU format and its mandatory TLVs.
+
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, Result};
+use crate::wire::EthernetAddress;
+
+/// The nearest bridge group address, which LLDP frames are sent to and which
+/// bridges never forward.
+pub const MULTICAST_ADDR: EthernetAddress = EthernetAddress([0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e]);
+
+/// The largest value a TLV can hold.
+pub const MAX_TLV_VALUE_LEN: usize = 511;
+
+enum_with_unknown! {
+    /// The type of an LLDP TLV.
+    pub enum TlvType(u8) {
+        End = 0,
+        ChassisId = 1,
+        PortId = 2,
+        Ttl = 3,
+        PortDescription = 4,
+        SystemName = 5,
+        SystemDescription = 6,
+        SystemCapabilities = 7,
+        ManagementAddress = 8,
+        OrganizationallySpecific = 127,
+    }
+}
+
+enum_with_unknown! {
+    /// The meaning of the value of a Chassis ID TLV.
+    pub enum ChassisIdSubtype(u8) {
+        ChassisComponent = 1,
+        InterfaceAlias = 2,
+        PortComponent = 3,
+        MacAddress = 4,
+        NetworkAddress = 5,
+        InterfaceName = 6,
+        Local = 7,
+    }
+}
+
+enum_with_unknown! {
+    /// The meaning of the value of a Port ID TLV.
+    pub enum PortIdSubtype(u8) {
+        InterfaceAlias = 1,
+        PortComponent = 2,
+        MacAddress = 3,
+        NetworkAddress = 4,
+        InterfaceName = 5,
+        AgentCircuitId = 6,
+        Local = 7,
+    }
+}
+
+/// A TLV of an LLDPDU.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Tlv<'a> {
+    pub kind: TlvType,
+    pub data: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    /// Parse the TLV at the start of `buffer`, and return it together with the rest
+    /// of the buffer.
+    pub fn parse(buffer: &'a [u8]) -> Result<(&'a [u8], Tlv<'a>)> {
+        if buffer.len() < 2 {
+            return Err(Error::Truncated);
+        }
+        let header = NetworkEndian::read_u16(&buffer[..2]);
+        let length = 2 + (header & 0x01ff) as usize;
+        if buffer.len() < length {
+            return Err(Error::Truncated);
+        }
+        let tlv = Tlv {
+            kind: TlvType::from((header >> 9) as u8),
+            data: &buffer[2..length],
+        };
+        Ok((&buffer[length..], tlv))
+    }
+
+    /// Return the length of the TLV, including its header.
+    pub const fn buffer_len(&self) -> usize {
+        2 + self.data.len()
+    }
+
+    /// Emit the TLV at the start of `buffer`, and return the rest of the buffer.
+    ///
+    /// # Panics
+    /// This function panics if the data is longer than 511 octets, or does not fit
+    /// into `buffer`.
+    pub fn emit<'b>(&self, buffer: &'b mut [u8]) -> &'b mut [u8] {
+        assert!(self.data.len() <= MAX_TLV_VALUE_LEN);
+        let header = (u16::from(u8::from(self.kind)) << 9) | self.data.len() as u16;
+        NetworkEndian::write_u16(&mut buffer[..2], header);
+        let length = self.buffer_len();
+        buffer[2..length].copy_from_slice(self.data);
+        &mut buffer[length..]
+    }
+}
+
+/// An iterator over the TLVs of an LLDPDU.
+///
+/// The iteration stops at the End of LLDPDU TLV, which is not returned. A malformed TLV
+/// ends the iteration with an error.
+#[derive(Debug, Clone)]
+pub struct TlvsIterator<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> TlvsIterator<'a> {
+    /// Create an iterator over the TLVs in `data`.
+    pub const fn new(data: &'a [u8]) -> TlvsIterator<'a> {
+        TlvsIterator { data }
+    }
+}
+
+impl<'a> Iterator for TlvsIterator<'a> {
+    type Item = Result<Tlv<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        match Tlv::parse(self.data) {
+            Ok((_, tlv)) if tlv.kind == TlvType::End => {
+                self.data = &[];
+                None
+            }
+            Ok((rest, tlv)) => {
+                self.data = rest;
+                Some(Ok(tlv))
+            }
+            Err(err) => {
+                self.data = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// A high-level representation of an LLDPDU.
+///
+/// Only the mandatory TLVs, and the port description, system name and system description
+/// TLVs are represented; the other TLVs are skipped when parsing.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Repr<'a> {
+    pub chassis_id_subtype: ChassisIdSubtype,
+    pub chassis_id: &'a [u8],
+    pub port_id_subtype: PortIdSubtype,
+    pub port_id: &'a [u8],
+    /// How long the information is valid, in seconds. Zero means the information
+    /// has to be removed right away.
+    pub ttl: u16,
+    pub port_description: Option<&'a str>,
+    pub system_name: Option<&'a str>,
+    pub system_description: Option<&'a str>,
+}
+
+impl<'a> Repr<'a> {
+    /// Parse an LLDPDU, i.e. the payload of an LLDP frame, and return a high-level
+    /// representation.
+    ///
+    /// Optional TLVs whose value is not valid UTF-8 are ignored.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let mut tlvs = TlvsIterator::new(data);
+        let mut mandatory = |kind: TlvType, min_len: usize| match tlvs.next() {
+            Some(Ok(tlv)) if tlv.kind == kind && tlv.data.len() >= min_len => Ok(tlv.data),
+            Some(Err(err)) => Err(err),
+            _ => Err(Error::Malformed),
+        };
+
+        // The chassis ID, port ID and TTL TLVs come first, in this order.
+        let chassis_id = mandatory(TlvType::ChassisId, 2)?;
+        let port_id = mandatory(TlvType::PortId, 2)?;
+        let ttl = mandatory(TlvType::Ttl, 2)?;
+
+        let mut repr = Repr {
+            chassis_id_subtype: ChassisIdSubtype::from(chassis_id[0]),
+            chassis_id: &chassis_id[1..],
+            port_id_subtype: PortIdSubtype::from(port_id[0]),
+            port_id: &port_id[1..],
+            ttl: NetworkEndian::read_u16(&ttl[..2]),
+            port_description: None,
+            system_name: None,
+            system_description: None,
+        };
+
+        for tlv in tlvs {
+            let tlv = tlv?;
+            let text = core::str::from_utf8(tlv.data).ok();
+            match tlv.kind {
+                TlvType::PortDescription => repr.port_description = text,
+                TlvType::SystemName => repr.system_name = text,
+                TlvType::SystemDescription => repr.system_description = text,
+                _ => (),
+            }
+        }
+
+        Ok(repr)
+    }
+
+    fn optional_tlvs(&self) -> impl Iterator<Item = Tlv<'a>> {
+        let port_description = self.port_description.map(|text| Tlv {
+            kind: TlvType::PortDescription,
+            data: text.as_bytes(),
+        });
+        let system_name = self.system_name.map(|text| Tlv {
+            kind: TlvType::SystemName,
+            data: text.as_bytes(),
+        });
+        let system_description = self.system_description.map(|text| Tlv {
+            kind: TlvType::SystemDescription,
+            data: text.as_bytes(),
+        });
+        port_description
+            .into_iter()
+            .chain(system_name)
+            .chain(system_description)
+    }
+
+    /// Return the length of an LLDPDU that will be emitted from this high-level
+    /// representation.
+    pub fn buffer_len(&self) -> usize {
+        // Chassis ID, port ID, TTL and End of LLDPDU.
+        let mandatory_len = (3 + self.chassis_id.len()) + (3 + self.port_id.len()) + 4 + 2;
+        mandatory_len
+            + self
+                .optional_tlvs()
+                .map(|tlv| tlv.buffer_len())
+                .sum::<usize>()
+    }
+
+    /// Emit a high-level representation into an LLDPDU.
+    ///
+    /// # Panics
+    /// This function panics if an ID is longer than 255 octets, a text is longer than
+    /// 511 octets, or the LLDPDU does not fit into `buffer`.
+    pub fn emit(&self, buffer: &mut [u8]) {
+        let buffer = emit_id(
+            buffer,
+            TlvType::ChassisId,
+            self.chassis_id_subtype.into(),
+            self.chassis_id,
+        );
+        let buffer = emit_id(
+            buffer,
+            TlvType::PortId,
+            self.port_id_subtype.into(),
+            self.port_id,
+        );
+
+        let mut ttl = [0; 2];
+        NetworkEndian::write_u16(&mut ttl, self.ttl);
+        let mut buffer = Tlv {
+            kind: TlvType::Ttl,
+            data: &ttl,
+        }
+        .emit(buffer);
+        for tlv in self.optional_tlvs() {
+            buffer = tlv.emit(buffer);
+        }
+        Tlv {
+            kind: TlvType::End,
+            data: &[],
+        }
+        .emit(buffer);
+    }
+}
+
+/// Emit a chassis ID or port ID TLV at the start of `buffer`, and return the rest of
+/// the buffer.
+fn emit_id<'b>(buffer: &'b mut [u8], kind: TlvType, subtype: u8, id: &[u8]) -> &'b mut [u8] {
+    assert!(id.len() <= 255);
+    let length = 1 + id.len();
+    NetworkEndian::write_u16(
+        &mut buffer[..2],
+        (u16::from(u8::from(kind)) << 9) | length as u16,
+    );
+    buffer[2] = subtype;
+    buffer[3..2 + length].copy_from_slice(id);
+    &mut buffer[2 + length..]
+}
+
+impl<'a> fmt::Display for Repr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LLDP chassis_id={:?}:{:02x?} port_id={:?}:{:02x?} ttl={}",
+            self.chassis_id_subtype, self.chassis_id, self.port_id_subtype, self.port_id, self.ttl
+        )?;
+        if let Some(system_name) = self.system_name {
+            write!(f, " system_name={system_name:?}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static LLDPDU_BYTES: [u8; 43] = [
+        0x02, 0x07, 0x04, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x04, 0x05, 0x05, 0x65, 0x74, 0x68,
+        0x30, 0x06, 0x02, 0x00, 0x78, 0x08, 0x05, 0x70, 0x6f, 0x72, 0x74, 0x31, 0x0a, 0x06, 0x64,
+        0x65, 0x76, 0x69, 0x63, 0x65, 0xfe, 0x04, 0x00, 0x80, 0xc2, 0x01, 0x00, 0x00,
+    ];
+
+    fn lldpdu_repr() -> Repr<'static> {
+        Repr {
+            chassis_id_subtype: ChassisIdSubtype::MacAddress,
+            chassis_id: &[0x02, 0x02, 0x02, 0x02, 0x02, 0x02],
+            port_id_subtype: PortIdSubtype::InterfaceName,
+            port_id: b"eth0",
+            ttl: 120,
+            port_description: Some("port1"),
+            system_name: Some("device"),
+            system_description: None,
+        }
+    }
+
+    #[test]
+    fn test_tlvs() {
+        let mut tlvs = TlvsIterator::new(&LLDPDU_BYTES[..]);
+        assert_eq!(
+            tlvs.next(),
+            Some(Ok(Tlv {
+                kind: TlvType::ChassisId,
+                data: &LLDPDU_BYTES[2..9],
+            }))
+        );
+        assert_eq!(
+            tlvs.nth(4),
+            Some(Ok(Tlv {
+                kind: TlvType::OrganizationallySpecific,
+                data: &LLDPDU_BYTES[37..41],
+            }))
+        );
+        assert_eq!(tlvs.next(), None);
+    }
+
+    #[test]
+    fn test_tlvs_malformed() {
+        let mut tlvs = TlvsIterator::new(&LLDPDU_BYTES[..12]);
+        assert!(matches!(tlvs.next(), Some(Ok(_))));
+        assert_eq!(tlvs.next(), Some(Err(Error::Truncated)));
+        assert_eq!(tlvs.next(), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Repr::parse(&LLDPDU_BYTES[..]), Ok(lldpdu_repr()));
+    }
+
+    #[test]
+    fn test_parse_missing_mandatory() {
+        // The port ID TLV is missing.
+        let mut bytes = LLDPDU_BYTES[..9].to_vec();
+        bytes.extend_from_slice(&LLDPDU_BYTES[16..]);
+        assert_eq!(Repr::parse(&bytes[..]), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn test_emit() {
+        let repr = Repr {
+            system_description: None,
+            ..lldpdu_repr()
+        };
+        // The organizationally specific TLV is not emitted, but an End TLV is.
+        let mut expected = LLDPDU_BYTES[..35].to_vec();
+        expected.extend_from_slice(&[0x00, 0x00]);
+
+        assert_eq!(repr.buffer_len(), expected.len());
+        let mut bytes = vec![0xa5; expected.len()];
+        repr.emit(&mut bytes[..]);
+        assert_eq!(bytes, expected);
+    }
+}
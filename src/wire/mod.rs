@@ -53,6 +53,7 @@ let repr = Ipv4Repr {
     next_header: IpProtocol::Tcp,
     payload_len: 10,
     hop_limit:   64,
+    traffic_class: 0,
 };
 let mut buffer = vec![0; repr.buffer_len() + repr.payload_len];
 { // emission
@@ -75,6 +76,8 @@ mod field {
     pub type Rest = ::core::ops::RangeFrom<usize>;
 }
 
+#[cfg(feature = "json")]
+pub mod json;
 pub mod pretty_print;
 
 #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
@@ -108,6 +111,10 @@ mod ipv6hopbyhop;
 mod ipv6option;
 #[cfg(feature = "proto-ipv6")]
 mod ipv6routing;
+#[cfg(feature = "proto-lldp")]
+mod lldp;
+#[cfg(feature = "socket-tcp-md5")]
+mod md5;
 #[cfg(feature = "proto-ipv6")]
 mod mld;
 #[cfg(all(
@@ -120,10 +127,18 @@ mod ndisc;
     any(feature = "medium-ethernet", feature = "medium-ieee802154")
 ))]
 mod ndiscoption;
+#[cfg(feature = "medium-ppp")]
+mod ppp;
 #[cfg(all(feature = "proto-sixlowpan", feature = "medium-ieee802154"))]
 mod sixlowpan;
+#[cfg(feature = "proto-sntp")]
+mod sntp;
 mod tcp;
+#[cfg(feature = "proto-tftp")]
+mod tftp;
 mod udp;
+#[cfg(feature = "medium-ethernet")]
+mod vlan;
 
 use core::fmt;
 
@@ -137,6 +152,25 @@ pub use self::ethernet::{
     Repr as EthernetRepr, HEADER_LEN as ETHERNET_HEADER_LEN,
 };
 
+#[cfg(feature = "medium-ethernet")]
+pub use self::vlan::{Packet as VlanPacket, Tag as VlanTag, HEADER_LEN as VLAN_HEADER_LEN};
+
+#[cfg(feature = "proto-lldp")]
+pub use self::lldp::{
+    ChassisIdSubtype as LldpChassisIdSubtype, PortIdSubtype as LldpPortIdSubtype, Repr as LldpRepr,
+    Tlv as LldpTlv, TlvType as LldpTlvType, TlvsIterator as LldpTlvsIterator,
+    MAX_TLV_VALUE_LEN as LLDP_MAX_TLV_VALUE_LEN, MULTICAST_ADDR as LLDP_MULTICAST_ADDR,
+};
+
+#[cfg(feature = "medium-ppp")]
+pub use self::ppp::{
+    ControlCode as PppControlCode, ControlOption as PppControlOption,
+    ControlOptionsIterator as PppControlOptionsIterator, ControlPacket as PppControlPacket,
+    ControlRepr as PppControlRepr, Frame as PppFrame, IpcpOption as PppIpcpOption,
+    LcpOption as PppLcpOption, Protocol as PppProtocol,
+    CONTROL_HEADER_LEN as PPP_CONTROL_HEADER_LEN, HEADER_LEN as PPP_HEADER_LEN,
+};
+
 #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
 pub use self::arp::{
     Hardware as ArpHardware, Operation as ArpOperation, Packet as ArpPacket, Repr as ArpRepr,
@@ -253,19 +287,41 @@ pub use self::dhcpv4::{
 #[cfg(feature = "proto-dns")]
 pub use self::dns::{Packet as DnsPacket, Repr as DnsRepr, Type as DnsQueryType};
 
+#[cfg(feature = "proto-sntp")]
+pub use self::sntp::{
+    LeapIndicator as SntpLeapIndicator, Mode as SntpMode, Packet as SntpPacket, Repr as SntpRepr,
+    Timestamp as NtpTimestamp, HEADER_LEN as SNTP_HEADER_LEN, SERVER_PORT as SNTP_SERVER_PORT,
+};
+
+#[cfg(feature = "proto-tftp")]
+pub use self::tftp::{
+    ErrorCode as TftpErrorCode, Mode as TftpMode, OpCode as TftpOpCode, Repr as TftpRepr,
+    DEFAULT_BLOCK_SIZE as TFTP_DEFAULT_BLOCK_SIZE, MAX_BLOCK_SIZE as TFTP_MAX_BLOCK_SIZE,
+    MIN_BLOCK_SIZE as TFTP_MIN_BLOCK_SIZE, SERVER_PORT as TFTP_SERVER_PORT,
+};
+
 /// Parsing a packet failed.
-///
-/// Either it is malformed, or it is not supported by smoltcp.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct Error;
+pub enum Error {
+    /// The buffer is shorter than the packet, or than one of its headers.
+    Truncated,
+    /// The checksum of the packet does not match its contents.
+    Checksum,
+    /// A field of the packet has an invalid value, or one not supported by smoltcp.
+    Malformed,
+}
 
 #[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "wire::Error")
+        match self {
+            Error::Truncated => write!(f, "truncated packet"),
+            Error::Checksum => write!(f, "checksum error"),
+            Error::Malformed => write!(f, "malformed packet"),
+        }
     }
 }
 
@@ -385,7 +441,7 @@ impl RawHardwareAddress {
             #[cfg(feature = "medium-ethernet")]
             Medium::Ethernet => {
                 if self.len() < 6 {
-                    return Err(Error);
+                    return Err(Error::Truncated);
                 }
                 Ok(HardwareAddress::Ethernet(EthernetAddress::from_bytes(
                     self.as_bytes(),
@@ -394,7 +450,7 @@ impl RawHardwareAddress {
             #[cfg(feature = "medium-ieee802154")]
             Medium::Ieee802154 => {
                 if self.len() < 8 {
-                    return Err(Error);
+                    return Err(Error::Truncated);
                 }
                 Ok(HardwareAddress::Ieee802154(Ieee802154Address::from_bytes(
                     self.as_bytes(),
@@ -402,6 +458,8 @@ impl RawHardwareAddress {
             }
             #[cfg(feature = "medium-ip")]
             Medium::Ip => unreachable!(),
+            #[cfg(feature = "medium-ppp")]
+            Medium::Ppp => unreachable!(),
         }
     }
 }
@@ -237,7 +237,7 @@ impl<'a> Repr<'a> {
                     match opt.option_type() {
                         NdiscOptionType::SourceLinkLayerAddr => Some(opt.link_layer_addr()),
                         _ => {
-                            return Err(Error);
+                            return Err(Error::Malformed);
                         }
                     }
                 } else {
@@ -256,7 +256,7 @@ impl<'a> Repr<'a> {
                         NdiscOptionRepr::Mtu(val) => mtu = Some(val),
                         NdiscOptionRepr::PrefixInformation(info) => prefix_info = Some(info),
                         _ => {
-                            return Err(Error);
+                            return Err(Error::Malformed);
                         }
                     }
                     offset += opt.buffer_len();
@@ -278,7 +278,7 @@ impl<'a> Repr<'a> {
                     match opt.option_type() {
                         NdiscOptionType::SourceLinkLayerAddr => Some(opt.link_layer_addr()),
                         _ => {
-                            return Err(Error);
+                            return Err(Error::Malformed);
                         }
                     }
                 } else {
@@ -295,7 +295,7 @@ impl<'a> Repr<'a> {
                     match opt.option_type() {
                         NdiscOptionType::TargetLinkLayerAddr => Some(opt.link_layer_addr()),
                         _ => {
-                            return Err(Error);
+                            return Err(Error::Malformed);
                         }
                     }
                 } else {
@@ -322,7 +322,7 @@ impl<'a> Repr<'a> {
                             let opt_data = opt.data();
 
                             if opt.data_len() < 6 || opt_data.len() < offset + 8 {
-                                return Err(Error);
+                                return Err(Error::Truncated);
                             };
 
                             let ip_packet = Ipv6Packet::new_checked(&opt_data[offset + 8..])?;
@@ -336,7 +336,7 @@ impl<'a> Repr<'a> {
                             redirected_hdr = Some(redirected);
                         }
                         _ => {
-                            return Err(Error);
+                            return Err(Error::Malformed);
                         }
                     }
                 }
@@ -347,7 +347,7 @@ impl<'a> Repr<'a> {
                     redirected_hdr,
                 })
             }
-            _ => Err(Error),
+            _ => Err(Error::Malformed),
         }
     }
 
@@ -159,14 +159,14 @@ impl<T: AsRef<[u8]>> NdiscOption<T> {
 
         // A data length field of 0 is invalid.
         if opt.data_len() == 0 {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
 
         Ok(opt)
     }
 
     /// Ensure that no accessor method will panic if called.
-    /// Returns `Err(Error)` if the buffer is too short.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
     ///
     /// The result of this check is invalidated by calling [set_data_len].
     ///
@@ -176,18 +176,18 @@ impl<T: AsRef<[u8]>> NdiscOption<T> {
         let len = data.len();
 
         if len < field::MIN_OPT_LEN {
-            Err(Error)
+            Err(Error::Truncated)
         } else {
             let data_range = field::DATA(data[field::LENGTH]);
             if len < data_range.end {
-                Err(Error)
+                Err(Error::Truncated)
             } else {
                 match self.option_type() {
                     Type::SourceLinkLayerAddr | Type::TargetLinkLayerAddr | Type::Mtu => Ok(()),
                     Type::PrefixInformation if data_range.end >= field::PREFIX.end => Ok(()),
                     Type::RedirectedHeader if data_range.end >= field::REDIR_MIN_SZ => Ok(()),
                     Type::Unknown(_) => Ok(()),
-                    _ => Err(Error),
+                    _ => Err(Error::Malformed),
                 }
             }
         }
@@ -436,14 +436,14 @@ impl<'a> Repr<'a> {
                 if opt.data_len() == 1 {
                     Ok(Repr::SourceLinkLayerAddr(opt.link_layer_addr()))
                 } else {
-                    Err(Error)
+                    Err(Error::Malformed)
                 }
             }
             Type::TargetLinkLayerAddr => {
                 if opt.data_len() == 1 {
                     Ok(Repr::TargetLinkLayerAddr(opt.link_layer_addr()))
                 } else {
-                    Err(Error)
+                    Err(Error::Malformed)
                 }
             }
             Type::PrefixInformation => {
@@ -456,7 +456,7 @@ impl<'a> Repr<'a> {
                         prefix: opt.prefix(),
                     }))
                 } else {
-                    Err(Error)
+                    Err(Error::Malformed)
                 }
             }
             Type::RedirectedHeader => {
@@ -464,7 +464,7 @@ impl<'a> Repr<'a> {
                 // does not have enough data to fill out the IP header
                 // and common option fields.
                 if opt.data_len() < 6 {
-                    Err(Error)
+                    Err(Error::Malformed)
                 } else {
                     let ip_packet =
                         Ipv6Packet::new_unchecked(&opt.data()[field::REDIRECTED_RESERVED.len()..]);
@@ -480,7 +480,7 @@ impl<'a> Repr<'a> {
                 if opt.data_len() == 1 {
                     Ok(Repr::Mtu(opt.mtu()))
                 } else {
-                    Err(Error)
+                    Err(Error::Malformed)
                 }
             }
             Type::Unknown(id) => {
@@ -492,7 +492,7 @@ impl<'a> Repr<'a> {
                         data: opt.data(),
                     })
                 } else {
-                    Err(Error)
+                    Err(Error::Malformed)
                 }
             }
         }
@@ -673,9 +673,12 @@ mod test {
 
     #[test]
     fn test_short_packet() {
-        assert_eq!(NdiscOption::new_checked(&[0x00, 0x00]), Err(Error));
+        assert_eq!(
+            NdiscOption::new_checked(&[0x00, 0x00]),
+            Err(Error::Truncated)
+        );
         let bytes = [0x03, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
-        assert_eq!(NdiscOption::new_checked(&bytes), Err(Error));
+        assert_eq!(NdiscOption::new_checked(&bytes), Err(Error::Malformed));
     }
 
     #[test]
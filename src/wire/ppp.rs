@@ -0,0 +1,613 @@
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, Result};
+
+enum_with_unknown! {
+    /// The protocol of a PPP frame.
+    pub enum Protocol(u16) {
+        Ipv4 = 0x0021,
+        Ipv6 = 0x0057,
+        /// IP Control Protocol
+        Ipcp = 0x8021,
+        /// IPv6 Control Protocol
+        Ipv6cp = 0x8057,
+        /// Link Control Protocol
+        Lcp = 0xc021,
+        /// Password Authentication Protocol
+        Pap = 0xc023,
+        /// Challenge Handshake Authentication Protocol
+        Chap = 0xc223,
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Protocol::Ipv4 => write!(f, "IPv4"),
+            Protocol::Ipv6 => write!(f, "IPv6"),
+            Protocol::Ipcp => write!(f, "IPCP"),
+            Protocol::Ipv6cp => write!(f, "IPV6CP"),
+            Protocol::Lcp => write!(f, "LCP"),
+            Protocol::Pap => write!(f, "PAP"),
+            Protocol::Chap => write!(f, "CHAP"),
+            Protocol::Unknown(id) => write!(f, "0x{id:04x}"),
+        }
+    }
+}
+
+enum_with_unknown! {
+    /// The code of an LCP or IPCP packet.
+    pub enum ControlCode(u8) {
+        ConfigureRequest = 1,
+        ConfigureAck = 2,
+        ConfigureNak = 3,
+        ConfigureReject = 4,
+        TerminateRequest = 5,
+        TerminateAck = 6,
+        CodeReject = 7,
+        /// Only used by LCP.
+        ProtocolReject = 8,
+        /// Only used by LCP.
+        EchoRequest = 9,
+        /// Only used by LCP.
+        EchoReply = 10,
+        /// Only used by LCP.
+        DiscardRequest = 11,
+    }
+}
+
+enum_with_unknown! {
+    /// The type of an LCP configuration option.
+    pub enum LcpOption(u8) {
+        /// Maximum-Receive-Unit
+        Mru = 1,
+        /// Async-Control-Character-Map
+        Accm = 2,
+        AuthProtocol = 3,
+        QualityProtocol = 4,
+        MagicNumber = 5,
+        /// Protocol-Field-Compression
+        Pfc = 7,
+        /// Address-and-Control-Field-Compression
+        Acfc = 8,
+    }
+}
+
+enum_with_unknown! {
+    /// The type of an IPCP configuration option.
+    pub enum IpcpOption(u8) {
+        IpCompressionProtocol = 2,
+        IpAddress = 3,
+        /// Primary DNS server address, see RFC 1877.
+        PrimaryDns = 129,
+        /// Secondary DNS server address, see RFC 1877.
+        SecondaryDns = 131,
+    }
+}
+
+/// A read/write wrapper around a PPP frame.
+///
+/// The frame consists of the protocol field and the information field. Devices using
+/// [Medium::Ppp](../phy/enum.Medium.html#variant.Ppp) have to remove the HDLC-like framing
+/// of RFC 1662, i.e. the flags, escapes and frame check sequence, from received frames,
+/// and add it to transmitted ones. The protocol field is never compressed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Frame<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod field {
+    use crate::wire::field::*;
+
+    pub const PROTOCOL: Field = 0..2;
+    pub const PAYLOAD: Rest = 2..;
+
+    pub const CODE: usize = 0;
+    pub const IDENTIFIER: usize = 1;
+    pub const LENGTH: Field = 2..4;
+    pub const DATA: Rest = 4..;
+}
+
+/// The PPP header length, i.e. the length of the protocol field.
+pub const HEADER_LEN: usize = field::PAYLOAD.start;
+
+/// The length of the header of LCP and IPCP packets.
+pub const CONTROL_HEADER_LEN: usize = field::DATA.start;
+
+impl<T: AsRef<[u8]>> Frame<T> {
+    /// Imbue a raw octet buffer with PPP frame structure.
+    pub const fn new_unchecked(buffer: T) -> Frame<T> {
+        Frame { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Frame<T>> {
+        let frame = Self::new_unchecked(buffer);
+        frame.check_len()?;
+        Ok(frame)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < HEADER_LEN {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the frame, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the protocol field.
+    #[inline]
+    pub fn protocol(&self) -> Protocol {
+        let data = self.buffer.as_ref();
+        Protocol::from(NetworkEndian::read_u16(&data[field::PROTOCOL]))
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Frame<&'a T> {
+    /// Return a pointer to the payload.
+    #[inline]
+    pub fn payload(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::PAYLOAD]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Frame<T> {
+    /// Set the protocol field.
+    #[inline]
+    pub fn set_protocol(&mut self, value: Protocol) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::PROTOCOL], value.into())
+    }
+
+    /// Return a mutable pointer to the payload.
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        &mut data[field::PAYLOAD]
+    }
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for Frame<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Display for Frame<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PPP proto={}", self.protocol())
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Frame<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        let frame = match Frame::new_checked(buffer) {
+            Err(err) => return write!(f, "{indent}({err})"),
+            Ok(frame) => frame,
+        };
+        write!(f, "{indent}{frame}")?;
+
+        match frame.protocol() {
+            #[cfg(feature = "proto-ipv4")]
+            Protocol::Ipv4 => {
+                indent.increase(f)?;
+                super::Ipv4Packet::<&[u8]>::pretty_print(&frame.payload(), f, indent)
+            }
+            #[cfg(feature = "proto-ipv6")]
+            Protocol::Ipv6 => {
+                indent.increase(f)?;
+                super::Ipv6Packet::<&[u8]>::pretty_print(&frame.payload(), f, indent)
+            }
+            Protocol::Lcp | Protocol::Ipcp | Protocol::Ipv6cp => {
+                indent.increase(f)?;
+                match ControlPacket::new_checked(frame.payload()) {
+                    Err(err) => write!(f, "{indent}({err})"),
+                    Ok(packet) => write!(f, "{indent}{packet}"),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A read/write wrapper around an LCP or IPCP packet.
+///
+/// The control protocols of PPP share the packet format of RFC 1661 § 5.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ControlPacket<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> ControlPacket<T> {
+    /// Imbue a raw octet buffer with LCP packet structure.
+    pub const fn new_unchecked(buffer: T) -> ControlPacket<T> {
+        ControlPacket { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<ControlPacket<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error::Truncated)` if the buffer is shorter than the length field,
+    /// and `Err(Error::Malformed)` if the length field is shorter than the header.
+    ///
+    /// The octets following the length field are padding, and are ignored.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < CONTROL_HEADER_LEN {
+            return Err(Error::Truncated);
+        }
+        let length = self.length() as usize;
+        if length < CONTROL_HEADER_LEN {
+            Err(Error::Malformed)
+        } else if length > len {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the code field.
+    #[inline]
+    pub fn code(&self) -> ControlCode {
+        let data = self.buffer.as_ref();
+        ControlCode::from(data[field::CODE])
+    }
+
+    /// Return the identifier field.
+    #[inline]
+    pub fn identifier(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::IDENTIFIER]
+    }
+
+    /// Return the length field, which includes the header.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::LENGTH])
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> ControlPacket<&'a T> {
+    /// Return a pointer to the data, i.e. the options of Configure packets.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::DATA.start..self.length() as usize]
+    }
+
+    /// Return an iterator over the options of a Configure packet.
+    pub fn options(&self) -> ControlOptionsIterator<'a> {
+        ControlOptionsIterator::new(self.data())
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> ControlPacket<T> {
+    /// Set the code field.
+    #[inline]
+    pub fn set_code(&mut self, value: ControlCode) {
+        let data = self.buffer.as_mut();
+        data[field::CODE] = value.into()
+    }
+
+    /// Set the identifier field.
+    #[inline]
+    pub fn set_identifier(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::IDENTIFIER] = value
+    }
+
+    /// Set the length field.
+    #[inline]
+    pub fn set_length(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::LENGTH], value)
+    }
+
+    /// Return a mutable pointer to the data.
+    #[inline]
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        let length = self.length() as usize;
+        let data = self.buffer.as_mut();
+        &mut data[field::DATA.start..length]
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Display for ControlPacket<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PPP control code={:?} id={} len={}",
+            self.code(),
+            self.identifier(),
+            self.length()
+        )
+    }
+}
+
+/// A configuration option of an LCP or IPCP packet.
+///
+/// The meaning of `kind` depends on the protocol, see [LcpOption] and [IpcpOption].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ControlOption<'a> {
+    pub kind: u8,
+    pub data: &'a [u8],
+}
+
+impl<'a> ControlOption<'a> {
+    /// Parse the option at the start of `buffer`, and return it together with the rest
+    /// of the buffer.
+    pub fn parse(buffer: &'a [u8]) -> Result<(&'a [u8], ControlOption<'a>)> {
+        if buffer.len() < 2 {
+            return Err(Error::Truncated);
+        }
+        let length = buffer[1] as usize;
+        if length < 2 {
+            return Err(Error::Malformed);
+        }
+        if buffer.len() < length {
+            return Err(Error::Truncated);
+        }
+        let option = ControlOption {
+            kind: buffer[0],
+            data: &buffer[2..length],
+        };
+        Ok((&buffer[length..], option))
+    }
+
+    /// Return the length of the option, including its type and length fields.
+    pub const fn buffer_len(&self) -> usize {
+        2 + self.data.len()
+    }
+
+    /// Emit the option at the start of `buffer`, and return the rest of the buffer.
+    ///
+    /// # Panics
+    /// This function panics if the data is longer than 253 octets, or does not fit
+    /// into `buffer`.
+    pub fn emit<'b>(&self, buffer: &'b mut [u8]) -> &'b mut [u8] {
+        let length = self.buffer_len();
+        assert!(length <= 0xff);
+        buffer[0] = self.kind;
+        buffer[1] = length as u8;
+        buffer[2..length].copy_from_slice(self.data);
+        &mut buffer[length..]
+    }
+}
+
+/// An iterator over the options of an LCP or IPCP Configure packet.
+///
+/// A malformed option ends the iteration with an error.
+#[derive(Debug, Clone)]
+pub struct ControlOptionsIterator<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ControlOptionsIterator<'a> {
+    /// Create an iterator over the options in `data`.
+    pub const fn new(data: &'a [u8]) -> ControlOptionsIterator<'a> {
+        ControlOptionsIterator { data }
+    }
+}
+
+impl<'a> Iterator for ControlOptionsIterator<'a> {
+    type Item = Result<ControlOption<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        match ControlOption::parse(self.data) {
+            Ok((rest, option)) => {
+                self.data = rest;
+                Some(Ok(option))
+            }
+            Err(err) => {
+                self.data = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// A high-level representation of an LCP or IPCP packet.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ControlRepr<'a> {
+    pub code: ControlCode,
+    pub identifier: u8,
+    /// The options of Configure packets, and the data of other packets.
+    pub data: &'a [u8],
+}
+
+impl<'a> ControlRepr<'a> {
+    /// Parse an LCP or IPCP packet and return a high-level representation.
+    pub fn parse<T: AsRef<[u8]> + ?Sized>(packet: &ControlPacket<&'a T>) -> Result<Self> {
+        packet.check_len()?;
+        Ok(ControlRepr {
+            code: packet.code(),
+            identifier: packet.identifier(),
+            data: packet.data(),
+        })
+    }
+
+    /// Return the length of a packet that will be emitted from this high-level representation.
+    pub const fn buffer_len(&self) -> usize {
+        CONTROL_HEADER_LEN + self.data.len()
+    }
+
+    /// Emit a high-level representation into an LCP or IPCP packet.
+    pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut ControlPacket<T>) {
+        packet.set_code(self.code);
+        packet.set_identifier(self.identifier);
+        packet.set_length(self.buffer_len() as u16);
+        packet.data_mut().copy_from_slice(self.data);
+    }
+}
+
+impl<'a> fmt::Display for ControlRepr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PPP control code={:?} id={} len={}",
+            self.code,
+            self.identifier,
+            self.buffer_len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static FRAME_BYTES: [u8; 16] = [
+        0xc0, 0x21, 0x01, 0x2a, 0x00, 0x0e, 0x01, 0x04, 0x05, 0xdc, 0x05, 0x06, 0x12, 0x34, 0x56,
+        0x78,
+    ];
+
+    static OPTIONS_BYTES: [u8; 10] = [0x01, 0x04, 0x05, 0xdc, 0x05, 0x06, 0x12, 0x34, 0x56, 0x78];
+
+    #[test]
+    fn test_deconstruct() {
+        let frame = Frame::new_checked(&FRAME_BYTES[..]).unwrap();
+        assert_eq!(frame.protocol(), Protocol::Lcp);
+        let packet = ControlPacket::new_checked(frame.payload()).unwrap();
+        assert_eq!(packet.code(), ControlCode::ConfigureRequest);
+        assert_eq!(packet.identifier(), 42);
+        assert_eq!(packet.length(), 14);
+        assert_eq!(packet.data(), &OPTIONS_BYTES[..]);
+
+        let mut options = packet.options();
+        assert_eq!(
+            options.next(),
+            Some(Ok(ControlOption {
+                kind: LcpOption::Mru.into(),
+                data: &[0x05, 0xdc],
+            }))
+        );
+        assert_eq!(
+            options.next(),
+            Some(Ok(ControlOption {
+                kind: LcpOption::MagicNumber.into(),
+                data: &[0x12, 0x34, 0x56, 0x78],
+            }))
+        );
+        assert_eq!(options.next(), None);
+    }
+
+    #[test]
+    fn test_construct() {
+        let mut bytes = vec![0xa5; 16];
+        let mut frame = Frame::new_unchecked(&mut bytes);
+        frame.set_protocol(Protocol::Lcp);
+        let mut packet = ControlPacket::new_unchecked(frame.payload_mut());
+        packet.set_code(ControlCode::ConfigureRequest);
+        packet.set_identifier(42);
+        packet.set_length(14);
+        packet.data_mut().copy_from_slice(&OPTIONS_BYTES[..]);
+        assert_eq!(&bytes[..], &FRAME_BYTES[..]);
+    }
+
+    #[test]
+    fn test_padding() {
+        let mut bytes = FRAME_BYTES[2..].to_vec();
+        bytes.extend_from_slice(&[0, 0]);
+        let packet = ControlPacket::new_checked(&bytes[..]).unwrap();
+        assert_eq!(packet.data(), &OPTIONS_BYTES[..]);
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert_eq!(
+            ControlPacket::new_checked(&FRAME_BYTES[2..15]),
+            Err(Error::Truncated)
+        );
+        let bytes = [0x01, 0x01, 0x00, 0x03];
+        assert_eq!(
+            ControlPacket::new_checked(&bytes[..]),
+            Err(Error::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_options_malformed() {
+        let bytes = [0x01, 0x04, 0x05, 0xdc, 0x05, 0x01, 0x12];
+        let mut options = ControlOptionsIterator::new(&bytes[..]);
+        assert!(matches!(options.next(), Some(Ok(_))));
+        assert_eq!(options.next(), Some(Err(Error::Malformed)));
+        assert_eq!(options.next(), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = ControlPacket::new_unchecked(&FRAME_BYTES[2..]);
+        assert_eq!(
+            ControlRepr::parse(&packet),
+            Ok(ControlRepr {
+                code: ControlCode::ConfigureRequest,
+                identifier: 42,
+                data: &OPTIONS_BYTES[..],
+            })
+        );
+    }
+
+    #[test]
+    fn test_emit() {
+        let mut options = [0; 10];
+        let rest = ControlOption {
+            kind: LcpOption::Mru.into(),
+            data: &[0x05, 0xdc],
+        }
+        .emit(&mut options[..]);
+        ControlOption {
+            kind: LcpOption::MagicNumber.into(),
+            data: &[0x12, 0x34, 0x56, 0x78],
+        }
+        .emit(rest);
+
+        let repr = ControlRepr {
+            code: ControlCode::ConfigureRequest,
+            identifier: 42,
+            data: &options[..],
+        };
+        assert_eq!(repr.buffer_len(), 14);
+        let mut bytes = [0; 14];
+        repr.emit(&mut ControlPacket::new_unchecked(&mut bytes[..]));
+        assert_eq!(&bytes[..], &FRAME_BYTES[2..]);
+    }
+}
@@ -74,14 +74,14 @@ impl<'a> UnresolvedAddress<'a> {
 
         let copy_context = |index: usize, bytes: &mut [u8]| -> Result<()> {
             if index >= addr_context.len() {
-                return Err(Error);
+                return Err(Error::Malformed);
             }
 
             let context = addr_context[index];
             let len = context.len();
 
             if len > 8 {
-                return Err(Error);
+                return Err(Error::Malformed);
             }
 
             bytes[..len].copy_from_slice(&context);
@@ -112,10 +112,10 @@ impl<'a> UnresolvedAddress<'a> {
                         }
                         Some(addr @ LlAddress::Extended(_)) => match addr.as_eui_64() {
                             Some(addr) => bytes[8..].copy_from_slice(&addr),
-                            None => return Err(Error),
+                            None => return Err(Error::Malformed),
                         },
-                        Some(LlAddress::Absent) => return Err(Error),
-                        None => return Err(Error),
+                        Some(LlAddress::Absent) => return Err(Error::Malformed),
+                        None => return Err(Error::Malformed),
                     }
                     Ok(ipv6::Address::from_bytes(&bytes[..]))
                 }
@@ -137,7 +137,7 @@ impl<'a> UnresolvedAddress<'a> {
                     bytes[15] = inline[0];
                     Ok(ipv6::Address::from_bytes(&bytes[..]))
                 }
-                _ => Err(Error),
+                _ => Err(Error::Malformed),
             },
             UnresolvedAddress::WithContext(mode) => match mode {
                 (_, AddressMode::Unspecified) => Ok(ipv6::Address::UNSPECIFIED),
@@ -159,19 +159,19 @@ impl<'a> UnresolvedAddress<'a> {
                         }
                         Some(addr @ LlAddress::Extended(_)) => match addr.as_eui_64() {
                             Some(addr) => bytes[8..].copy_from_slice(&addr),
-                            None => return Err(Error),
+                            None => return Err(Error::Malformed),
                         },
-                        Some(LlAddress::Absent) => return Err(Error),
-                        None => return Err(Error),
+                        Some(LlAddress::Absent) => return Err(Error::Malformed),
+                        None => return Err(Error::Malformed),
                     }
 
                     copy_context(index, &mut bytes[..])?;
 
                     Ok(ipv6::Address::from_bytes(&bytes[..]))
                 }
-                _ => Err(Error),
+                _ => Err(Error::Malformed),
             },
-            UnresolvedAddress::Reserved => Err(Error),
+            UnresolvedAddress::Reserved => Err(Error::Malformed),
         }
     }
 }
@@ -194,13 +194,13 @@ impl SixlowpanPacket {
     /// This can either be a fragment header or an IPHC header.
     ///
     /// # Errors
-    /// Returns `[Error::Unrecognized]` when neither the Fragment Header dispatch or the IPHC
+    /// Returns `[Error::Malformed]` when neither the Fragment Header dispatch or the IPHC
     /// dispatch is recognized.
     pub fn dispatch(buffer: impl AsRef<[u8]>) -> Result<Self> {
         let raw = buffer.as_ref();
 
         if raw.is_empty() {
-            return Err(Error);
+            return Err(Error::Truncated);
         }
 
         if raw[0] >> 3 == DISPATCH_FIRST_FRAGMENT_HEADER || raw[0] >> 3 == DISPATCH_FRAGMENT_HEADER
@@ -209,7 +209,7 @@ impl SixlowpanPacket {
         } else if raw[0] >> 5 == DISPATCH_IPHC_HEADER {
             Ok(Self::IphcHeader)
         } else {
-            Err(Error)
+            Err(Error::Malformed)
         }
     }
 }
@@ -219,11 +219,8 @@ pub mod frag {
     //!
     //! [RFC 4944 § 5.3]: https://datatracker.ietf.org/doc/html/rfc4944#section-5.3
 
-    use super::{DISPATCH_FIRST_FRAGMENT_HEADER, DISPATCH_FRAGMENT_HEADER};
-    use crate::{
-        wire::{Ieee802154Address, Ieee802154Repr},
-        Error, Result,
-    };
+    use super::{Error, Result, DISPATCH_FIRST_FRAGMENT_HEADER, DISPATCH_FRAGMENT_HEADER};
+    use crate::wire::{Ieee802154Address, Ieee802154Repr};
     use byteorder::{ByteOrder, NetworkEndian};
 
     /// Key used for identifying all the link fragments that belong to the same packet.
@@ -323,7 +320,7 @@ pub mod frag {
                 DISPATCH_FRAGMENT_HEADER if buffer.len() < NEXT_FRAGMENT_HEADER_SIZE => {
                     Err(Error::Truncated)
                 }
-                _ => Err(Error::Unrecognized),
+                _ => Err(Error::Malformed),
             }
         }
 
@@ -574,11 +571,11 @@ pub mod iphc {
         }
 
         /// Ensure that no accessor method will panic if called.
-        /// Returns `Err(Error)` if the buffer is too short.
+        /// Returns `Err(Error::Truncated)` if the buffer is too short.
         pub fn check_len(&self) -> Result<()> {
             let buffer = self.buffer.as_ref();
             if buffer.len() < 2 {
-                return Err(Error);
+                return Err(Error::Truncated);
             }
 
             let mut offset = self.ip_fields_start()
@@ -589,7 +586,7 @@ pub mod iphc {
             offset += self.dst_address_size();
 
             if offset as usize > buffer.len() {
-                return Err(Error);
+                return Err(Error::Truncated);
             }
 
             Ok(())
@@ -730,7 +727,7 @@ pub mod iphc {
                             AddressMode::InLine64bits(&data[start..][..8]),
                         )))
                     } else {
-                        Err(Error)
+                        Err(Error::Malformed)
                     }
                 }
                 (1, 0b10) => {
@@ -740,7 +737,7 @@ pub mod iphc {
                             AddressMode::InLine16bits(&data[start..][..2]),
                         )))
                     } else {
-                        Err(Error)
+                        Err(Error::Malformed)
                     }
                 }
                 (1, 0b11) => {
@@ -750,10 +747,10 @@ pub mod iphc {
                             AddressMode::FullyElided,
                         )))
                     } else {
-                        Err(Error)
+                        Err(Error::Malformed)
                     }
                 }
-                _ => Err(Error),
+                _ => Err(Error::Malformed),
             }
         }
 
@@ -785,7 +782,7 @@ pub mod iphc {
                             AddressMode::InLine64bits(&data[start..][..8]),
                         )))
                     } else {
-                        Err(Error)
+                        Err(Error::Malformed)
                     }
                 }
                 (0, 1, 0b10) => {
@@ -795,7 +792,7 @@ pub mod iphc {
                             AddressMode::InLine16bits(&data[start..][..2]),
                         )))
                     } else {
-                        Err(Error)
+                        Err(Error::Malformed)
                     }
                 }
                 (0, 1, 0b11) => {
@@ -805,7 +802,7 @@ pub mod iphc {
                             AddressMode::FullyElided,
                         )))
                     } else {
-                        Err(Error)
+                        Err(Error::Malformed)
                     }
                 }
                 (1, 0, 0b00) => Ok(UnresolvedAddress::WithoutContext(AddressMode::FullInline(
@@ -825,7 +822,7 @@ pub mod iphc {
                     AddressMode::NotSupported,
                 ))),
                 (1, 1, 0b01 | 0b10 | 0b11) => Ok(UnresolvedAddress::Reserved),
-                _ => Err(Error),
+                _ => Err(Error::Malformed),
             }
         }
 
@@ -1192,7 +1189,7 @@ pub mod iphc {
 
             if packet.dispatch_field() != DISPATCH_IPHC_HEADER {
                 // This is not an LOWPAN_IPHC packet.
-                return Err(Error);
+                return Err(Error::Malformed);
             }
 
             let src_addr = packet.src_addr()?.resolve(ll_src_addr, addr_context)?;
@@ -1474,12 +1471,12 @@ pub mod nhc {
         /// This can either be an Extenstion header or an 6LoWPAN Udp header.
         ///
         /// # Errors
-        /// Returns `[Error::Unrecognized]` when neither the Extension Header dispatch or the Udp
+        /// Returns `[Error::Malformed]` when neither the Extension Header dispatch or the Udp
         /// dispatch is recognized.
         pub fn dispatch(buffer: impl AsRef<[u8]>) -> Result<Self> {
             let raw = buffer.as_ref();
             if raw.is_empty() {
-                return Err(Error);
+                return Err(Error::Truncated);
             }
 
             if raw[0] >> 4 == DISPATCH_EXT_HEADER {
@@ -1489,7 +1486,7 @@ pub mod nhc {
                 // We have a compressed UDP header.
                 Ok(Self::UdpHeader)
             } else {
-                Err(Error)
+                Err(Error::Malformed)
             }
         }
     }
@@ -1542,19 +1539,19 @@ pub mod nhc {
             packet.check_len()?;
 
             if packet.eid_field() > 7 {
-                return Err(Error);
+                return Err(Error::Malformed);
             }
 
             Ok(packet)
         }
 
         /// Ensure that no accessor method will panic if called.
-        /// Returns `Err(Error)` if the buffer is too short.
+        /// Returns `Err(Error::Truncated)` if the buffer is too short.
         pub fn check_len(&self) -> Result<()> {
             let buffer = self.buffer.as_ref();
 
             if buffer.is_empty() {
-                return Err(Error);
+                return Err(Error::Truncated);
             }
 
             let mut len = 1;
@@ -1563,7 +1560,7 @@ pub mod nhc {
             if len <= buffer.len() {
                 Ok(())
             } else {
-                Err(Error)
+                Err(Error::Truncated)
             }
         }
 
@@ -1693,7 +1690,7 @@ pub mod nhc {
             packet.check_len()?;
 
             if packet.dispatch_field() != DISPATCH_EXT_HEADER {
-                return Err(Error);
+                return Err(Error::Malformed);
             }
 
             Ok(Self {
@@ -1768,12 +1765,12 @@ pub mod nhc {
             let buffer = self.buffer.as_ref();
 
             if buffer.is_empty() {
-                return Err(Error);
+                return Err(Error::Truncated);
             }
 
             let index = 1 + self.ports_size() + self.checksum_size();
             if index > buffer.len() {
-                return Err(Error);
+                return Err(Error::Truncated);
             }
 
             Ok(())
@@ -1976,7 +1973,7 @@ pub mod nhc {
             packet.check_len()?;
 
             if packet.dispatch_field() != DISPATCH_UDP_HEADER {
-                return Err(Error);
+                return Err(Error::Malformed);
             }
 
             if checksum_caps.udp.rx() {
@@ -1996,7 +1993,7 @@ pub mod nhc {
 
                 if let Some(checksum) = packet.checksum() {
                     if chk_sum != checksum {
-                        return Err(Error);
+                        return Err(Error::Checksum);
                     }
                 }
             }
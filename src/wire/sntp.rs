@@ -0,0 +1,509 @@
+// See https://tools.ietf.org/html/rfc4330 for the SNTP specification, and
+// https://tools.ietf.org/html/rfc5905 for the NTP packet format.
+
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, Result};
+
+/// The UDP port of NTP servers.
+pub const SERVER_PORT: u16 = 123;
+
+/// The number of seconds from the NTP epoch (1900) to the Unix epoch (1970).
+const UNIX_EPOCH_SECS: i64 = 2_208_988_800;
+
+enum_with_unknown! {
+    /// The leap indicator of an NTP packet.
+    pub enum LeapIndicator(u8) {
+        NoWarning = 0,
+        /// The last minute of the day has 61 seconds.
+        InsertSecond = 1,
+        /// The last minute of the day has 59 seconds.
+        DeleteSecond = 2,
+        /// The clock of the sender is not synchronized.
+        Unsynchronized = 3,
+    }
+}
+
+enum_with_unknown! {
+    /// The association mode of an NTP packet.
+    pub enum Mode(u8) {
+        SymmetricActive = 1,
+        SymmetricPassive = 2,
+        Client = 3,
+        Server = 4,
+        Broadcast = 5,
+    }
+}
+
+/// An NTP timestamp, the time elapsed since 1900 in seconds, as a 32.32 bit fixed point number.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timestamp {
+    pub seconds: u32,
+    pub fraction: u32,
+}
+
+impl Timestamp {
+    /// The zero timestamp, which stands for an unknown time.
+    pub const ZERO: Timestamp = Timestamp {
+        seconds: 0,
+        fraction: 0,
+    };
+
+    /// Create a timestamp from the time elapsed since the Unix epoch, in microseconds.
+    pub fn from_unix_micros(micros: i64) -> Timestamp {
+        let micros = micros + UNIX_EPOCH_SECS * 1_000_000;
+        let fraction = ((micros.rem_euclid(1_000_000) << 32) + 500_000) / 1_000_000;
+        Timestamp {
+            // The seconds wrap around in 2036.
+            seconds: (micros.div_euclid(1_000_000) + (fraction >> 32)) as u32,
+            fraction: fraction as u32,
+        }
+    }
+
+    /// Return the time elapsed since the Unix epoch, in microseconds.
+    ///
+    /// Timestamps with the top bit of the seconds clear are taken to be after 2036,
+    /// as described in RFC 4330 § 3.
+    pub fn unix_micros(&self) -> i64 {
+        let mut seconds = self.seconds as i64;
+        if self.seconds & 0x8000_0000 == 0 {
+            seconds += 1 << 32;
+        }
+        let micros = ((self.fraction as i64 * 1_000_000) + (1 << 31)) >> 32;
+        (seconds - UNIX_EPOCH_SECS) * 1_000_000 + micros
+    }
+
+    fn read(data: &[u8]) -> Timestamp {
+        Timestamp {
+            seconds: NetworkEndian::read_u32(&data[..4]),
+            fraction: NetworkEndian::read_u32(&data[4..8]),
+        }
+    }
+
+    fn write(&self, data: &mut [u8]) {
+        NetworkEndian::write_u32(&mut data[..4], self.seconds);
+        NetworkEndian::write_u32(&mut data[4..8], self.fraction);
+    }
+}
+
+/// A read/write wrapper around an NTP packet buffer.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod field {
+    use crate::wire::field::*;
+
+    pub const LI_VN_MODE: usize = 0;
+    pub const STRATUM: usize = 1;
+    pub const POLL: usize = 2;
+    pub const PRECISION: usize = 3;
+    pub const ROOT_DELAY: Field = 4..8;
+    pub const ROOT_DISPERSION: Field = 8..12;
+    pub const REF_ID: Field = 12..16;
+    pub const REF_TIMESTAMP: Field = 16..24;
+    pub const ORIG_TIMESTAMP: Field = 24..32;
+    pub const RECV_TIMESTAMP: Field = 32..40;
+    pub const XMIT_TIMESTAMP: Field = 40..48;
+}
+
+/// The length of an NTP packet without extension fields and authenticator.
+pub const HEADER_LEN: usize = field::XMIT_TIMESTAMP.end;
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with NTP packet structure.
+    pub const fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < HEADER_LEN {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the leap indicator field.
+    #[inline]
+    pub fn leap_indicator(&self) -> LeapIndicator {
+        let data = self.buffer.as_ref();
+        LeapIndicator::from(data[field::LI_VN_MODE] >> 6)
+    }
+
+    /// Return the version field.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        (data[field::LI_VN_MODE] >> 3) & 0x07
+    }
+
+    /// Return the mode field.
+    #[inline]
+    pub fn mode(&self) -> Mode {
+        let data = self.buffer.as_ref();
+        Mode::from(data[field::LI_VN_MODE] & 0x07)
+    }
+
+    /// Return the stratum field.
+    #[inline]
+    pub fn stratum(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::STRATUM]
+    }
+
+    /// Return the poll interval field, as the base-2 logarithm of seconds.
+    #[inline]
+    pub fn poll(&self) -> i8 {
+        let data = self.buffer.as_ref();
+        data[field::POLL] as i8
+    }
+
+    /// Return the precision field, as the base-2 logarithm of seconds.
+    #[inline]
+    pub fn precision(&self) -> i8 {
+        let data = self.buffer.as_ref();
+        data[field::PRECISION] as i8
+    }
+
+    /// Return the root delay field, in seconds as a 16.16 bit fixed point number.
+    #[inline]
+    pub fn root_delay(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::ROOT_DELAY])
+    }
+
+    /// Return the root dispersion field, in seconds as a 16.16 bit fixed point number.
+    #[inline]
+    pub fn root_dispersion(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::ROOT_DISPERSION])
+    }
+
+    /// Return the reference identifier field.
+    #[inline]
+    pub fn ref_id(&self) -> [u8; 4] {
+        let data = self.buffer.as_ref();
+        let mut ref_id = [0; 4];
+        ref_id.copy_from_slice(&data[field::REF_ID]);
+        ref_id
+    }
+
+    /// Return the reference timestamp field.
+    #[inline]
+    pub fn ref_timestamp(&self) -> Timestamp {
+        Timestamp::read(&self.buffer.as_ref()[field::REF_TIMESTAMP])
+    }
+
+    /// Return the originate timestamp field.
+    #[inline]
+    pub fn orig_timestamp(&self) -> Timestamp {
+        Timestamp::read(&self.buffer.as_ref()[field::ORIG_TIMESTAMP])
+    }
+
+    /// Return the receive timestamp field.
+    #[inline]
+    pub fn recv_timestamp(&self) -> Timestamp {
+        Timestamp::read(&self.buffer.as_ref()[field::RECV_TIMESTAMP])
+    }
+
+    /// Return the transmit timestamp field.
+    #[inline]
+    pub fn xmit_timestamp(&self) -> Timestamp {
+        Timestamp::read(&self.buffer.as_ref()[field::XMIT_TIMESTAMP])
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the leap indicator field.
+    #[inline]
+    pub fn set_leap_indicator(&mut self, value: LeapIndicator) {
+        let data = self.buffer.as_mut();
+        let raw = data[field::LI_VN_MODE];
+        data[field::LI_VN_MODE] = (raw & !0xc0) | (u8::from(value) << 6);
+    }
+
+    /// Set the version field.
+    #[inline]
+    pub fn set_version(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        let raw = data[field::LI_VN_MODE];
+        data[field::LI_VN_MODE] = (raw & !0x38) | ((value & 0x07) << 3);
+    }
+
+    /// Set the mode field.
+    #[inline]
+    pub fn set_mode(&mut self, value: Mode) {
+        let data = self.buffer.as_mut();
+        let raw = data[field::LI_VN_MODE];
+        data[field::LI_VN_MODE] = (raw & !0x07) | (u8::from(value) & 0x07);
+    }
+
+    /// Set the stratum field.
+    #[inline]
+    pub fn set_stratum(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::STRATUM] = value
+    }
+
+    /// Set the poll interval field.
+    #[inline]
+    pub fn set_poll(&mut self, value: i8) {
+        let data = self.buffer.as_mut();
+        data[field::POLL] = value as u8
+    }
+
+    /// Set the precision field.
+    #[inline]
+    pub fn set_precision(&mut self, value: i8) {
+        let data = self.buffer.as_mut();
+        data[field::PRECISION] = value as u8
+    }
+
+    /// Set the root delay field.
+    #[inline]
+    pub fn set_root_delay(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::ROOT_DELAY], value)
+    }
+
+    /// Set the root dispersion field.
+    #[inline]
+    pub fn set_root_dispersion(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::ROOT_DISPERSION], value)
+    }
+
+    /// Set the reference identifier field.
+    #[inline]
+    pub fn set_ref_id(&mut self, value: [u8; 4]) {
+        let data = self.buffer.as_mut();
+        data[field::REF_ID].copy_from_slice(&value)
+    }
+
+    /// Set the reference timestamp field.
+    #[inline]
+    pub fn set_ref_timestamp(&mut self, value: Timestamp) {
+        value.write(&mut self.buffer.as_mut()[field::REF_TIMESTAMP])
+    }
+
+    /// Set the originate timestamp field.
+    #[inline]
+    pub fn set_orig_timestamp(&mut self, value: Timestamp) {
+        value.write(&mut self.buffer.as_mut()[field::ORIG_TIMESTAMP])
+    }
+
+    /// Set the receive timestamp field.
+    #[inline]
+    pub fn set_recv_timestamp(&mut self, value: Timestamp) {
+        value.write(&mut self.buffer.as_mut()[field::RECV_TIMESTAMP])
+    }
+
+    /// Set the transmit timestamp field.
+    #[inline]
+    pub fn set_xmit_timestamp(&mut self, value: Timestamp) {
+        value.write(&mut self.buffer.as_mut()[field::XMIT_TIMESTAMP])
+    }
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for Packet<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+}
+
+/// A high-level representation of an NTP packet.
+///
+/// Extension fields and the authenticator are not supported, and ignored when parsing.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Repr {
+    pub leap_indicator: LeapIndicator,
+    pub version: u8,
+    pub mode: Mode,
+    pub stratum: u8,
+    pub poll: i8,
+    pub precision: i8,
+    pub root_delay: u32,
+    pub root_dispersion: u32,
+    pub ref_id: [u8; 4],
+    pub ref_timestamp: Timestamp,
+    pub orig_timestamp: Timestamp,
+    pub recv_timestamp: Timestamp,
+    pub xmit_timestamp: Timestamp,
+}
+
+impl Repr {
+    /// Parse an NTP packet and return a high-level representation.
+    pub fn parse<T: AsRef<[u8]> + ?Sized>(packet: &Packet<&T>) -> Result<Repr> {
+        packet.check_len()?;
+        Ok(Repr {
+            leap_indicator: packet.leap_indicator(),
+            version: packet.version(),
+            mode: packet.mode(),
+            stratum: packet.stratum(),
+            poll: packet.poll(),
+            precision: packet.precision(),
+            root_delay: packet.root_delay(),
+            root_dispersion: packet.root_dispersion(),
+            ref_id: packet.ref_id(),
+            ref_timestamp: packet.ref_timestamp(),
+            orig_timestamp: packet.orig_timestamp(),
+            recv_timestamp: packet.recv_timestamp(),
+            xmit_timestamp: packet.xmit_timestamp(),
+        })
+    }
+
+    /// Return the length of a packet that will be emitted from this high-level representation.
+    pub const fn buffer_len(&self) -> usize {
+        HEADER_LEN
+    }
+
+    /// Emit a high-level representation into an NTP packet.
+    pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut Packet<T>) {
+        packet.set_leap_indicator(self.leap_indicator);
+        packet.set_version(self.version);
+        packet.set_mode(self.mode);
+        packet.set_stratum(self.stratum);
+        packet.set_poll(self.poll);
+        packet.set_precision(self.precision);
+        packet.set_root_delay(self.root_delay);
+        packet.set_root_dispersion(self.root_dispersion);
+        packet.set_ref_id(self.ref_id);
+        packet.set_ref_timestamp(self.ref_timestamp);
+        packet.set_orig_timestamp(self.orig_timestamp);
+        packet.set_recv_timestamp(self.recv_timestamp);
+        packet.set_xmit_timestamp(self.xmit_timestamp);
+    }
+}
+
+impl fmt::Display for Repr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NTPv{} mode={:?} stratum={} xmit={}.{:08x}",
+            self.version,
+            self.mode,
+            self.stratum,
+            self.xmit_timestamp.seconds,
+            self.xmit_timestamp.fraction
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static PACKET_BYTES: [u8; 48] = [
+        0x24, 0x02, 0x06, 0xec, 0x00, 0x00, 0x01, 0x23, 0x00, 0x00, 0x04, 0x56, 0xc0, 0xa8, 0x01,
+        0x01, 0xe7, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xe7, 0x00, 0x00, 0x01, 0x80, 0x00,
+        0x00, 0x00, 0xe7, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0xe7, 0x00, 0x00, 0x02, 0x40,
+        0x00, 0x00, 0x00,
+    ];
+
+    fn repr() -> Repr {
+        Repr {
+            leap_indicator: LeapIndicator::NoWarning,
+            version: 4,
+            mode: Mode::Server,
+            stratum: 2,
+            poll: 6,
+            precision: -20,
+            root_delay: 0x123,
+            root_dispersion: 0x456,
+            ref_id: [192, 168, 1, 1],
+            ref_timestamp: Timestamp {
+                seconds: 0xe700_0000,
+                fraction: 0,
+            },
+            orig_timestamp: Timestamp {
+                seconds: 0xe700_0001,
+                fraction: 0x8000_0000,
+            },
+            recv_timestamp: Timestamp {
+                seconds: 0xe700_0002,
+                fraction: 0,
+            },
+            xmit_timestamp: Timestamp {
+                seconds: 0xe700_0002,
+                fraction: 0x4000_0000,
+            },
+        }
+    }
+
+    #[test]
+    fn test_deconstruct() {
+        let packet = Packet::new_unchecked(&PACKET_BYTES[..]);
+        assert_eq!(packet.leap_indicator(), LeapIndicator::NoWarning);
+        assert_eq!(packet.version(), 4);
+        assert_eq!(packet.mode(), Mode::Server);
+        assert_eq!(packet.stratum(), 2);
+        assert_eq!(packet.poll(), 6);
+        assert_eq!(packet.precision(), -20);
+        assert_eq!(packet.root_delay(), 0x123);
+        assert_eq!(packet.root_dispersion(), 0x456);
+        assert_eq!(packet.ref_id(), [192, 168, 1, 1]);
+        assert_eq!(packet.xmit_timestamp(), repr().xmit_timestamp);
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = Packet::new_unchecked(&PACKET_BYTES[..]);
+        assert_eq!(Repr::parse(&packet), Ok(repr()));
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        let packet = Packet::new_unchecked(&PACKET_BYTES[..47]);
+        assert_eq!(Repr::parse(&packet), Err(Error::Truncated));
+    }
+
+    #[test]
+    fn test_emit() {
+        let mut bytes = [0xff; 48];
+        let mut packet = Packet::new_unchecked(&mut bytes[..]);
+        repr().emit(&mut packet);
+        assert_eq!(&packet.into_inner()[..], &PACKET_BYTES[..]);
+    }
+
+    #[test]
+    fn test_timestamp_unix() {
+        // 2022-12-01T00:00:00.5Z
+        let micros = 1_669_852_800_500_000;
+        let timestamp = Timestamp::from_unix_micros(micros);
+        assert_eq!(
+            timestamp,
+            Timestamp {
+                seconds: 0xe732_6d00,
+                fraction: 0x8000_0000,
+            }
+        );
+        assert_eq!(timestamp.unix_micros(), micros);
+
+        // After the seconds wrapped around in 2036.
+        let micros = 2_100_000_000_000_001;
+        assert_eq!(Timestamp::from_unix_micros(micros).unix_micros(), micros);
+    }
+}
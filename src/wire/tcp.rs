@@ -1,6 +1,8 @@
 use byteorder::{ByteOrder, NetworkEndian};
 use core::{cmp, fmt, i32, ops};
 
+#[cfg(feature = "socket-tcp-md5")]
+use super::md5::Md5;
 use super::{Error, Result};
 use crate::phy::ChecksumCapabilities;
 use crate::wire::ip::checksum;
@@ -12,6 +14,7 @@ use crate::wire::{IpAddress, IpProtocol};
 /// Sequence numbers do not have a discontiguity when compared pairwise across a signed overflow.
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SeqNumber(pub i32);
 
 impl fmt::Display for SeqNumber {
@@ -107,6 +110,8 @@ mod field {
     pub const OPT_WS: u8 = 0x03;
     pub const OPT_SACKPERM: u8 = 0x04;
     pub const OPT_SACKRNG: u8 = 0x05;
+    pub const OPT_TSTAMP: u8 = 0x08;
+    pub const OPT_MD5: u8 = 0x13;
 }
 
 pub const HEADER_LEN: usize = field::URGENT.end;
@@ -128,8 +133,8 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     /// Ensure that no accessor method will panic if called.
-    /// Returns `Err(Error)` if the buffer is too short.
-    /// Returns `Err(Error)` if the header length field has a value smaller
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
+    /// Returns `Err(Error::Malformed)` if the header length field has a value smaller
     /// than the minimal header length.
     ///
     /// The result of this check is invalidated by calling [set_header_len].
@@ -138,11 +143,13 @@ impl<T: AsRef<[u8]>> Packet<T> {
     pub fn check_len(&self) -> Result<()> {
         let len = self.buffer.as_ref().len();
         if len < field::URGENT.end {
-            Err(Error)
+            Err(Error::Truncated)
         } else {
             let header_len = self.header_len() as usize;
-            if len < header_len || header_len < field::URGENT.end {
-                Err(Error)
+            if header_len < field::URGENT.end {
+                Err(Error::Malformed)
+            } else if len < header_len {
+                Err(Error::Truncated)
             } else {
                 Ok(())
             }
@@ -345,6 +352,74 @@ impl<T: AsRef<[u8]>> Packet<T> {
             checksum::data(data),
         ]) == !0
     }
+
+    /// Compute the MD5 signature of the packet with the given key, as specified
+    /// in RFC 2385, to compare it with the signature option of the packet.
+    ///
+    /// # Panics
+    /// This function panics unless `src_addr` and `dst_addr` belong to the same family,
+    /// and that family is IPv4 or IPv6.
+    #[cfg(feature = "socket-tcp-md5")]
+    pub fn compute_md5_signature(
+        &self,
+        src_addr: &IpAddress,
+        dst_addr: &IpAddress,
+        key: &[u8],
+    ) -> [u8; 16] {
+        let data = self.buffer.as_ref();
+        let mut header = [0; HEADER_LEN];
+        header.copy_from_slice(&data[..HEADER_LEN]);
+        md5_signature(
+            src_addr,
+            dst_addr,
+            &mut header,
+            &data[self.header_len() as usize..],
+            key,
+        )
+    }
+}
+
+/// Compute the MD5 signature of a segment from its header without options, and its payload.
+///
+/// The signature covers the pseudo-header, the header with a zero checksum, the payload
+/// and the key, in that order.
+#[cfg(feature = "socket-tcp-md5")]
+fn md5_signature(
+    src_addr: &IpAddress,
+    dst_addr: &IpAddress,
+    header: &mut [u8; HEADER_LEN],
+    payload: &[u8],
+    key: &[u8],
+) -> [u8; 16] {
+    let segment_len = Packet::new_unchecked(&header[..]).header_len() as usize + payload.len();
+    let mut md5 = Md5::new();
+    match (src_addr, dst_addr) {
+        #[cfg(feature = "proto-ipv4")]
+        (&IpAddress::Ipv4(src_addr), &IpAddress::Ipv4(dst_addr)) => {
+            let mut proto_len = [0u8; 4];
+            proto_len[1] = IpProtocol::Tcp.into();
+            NetworkEndian::write_u16(&mut proto_len[2..4], segment_len as u16);
+            md5.update(src_addr.as_bytes());
+            md5.update(dst_addr.as_bytes());
+            md5.update(&proto_len);
+        }
+        #[cfg(feature = "proto-ipv6")]
+        (&IpAddress::Ipv6(src_addr), &IpAddress::Ipv6(dst_addr)) => {
+            let mut proto_len = [0u8; 8];
+            proto_len[7] = IpProtocol::Tcp.into();
+            NetworkEndian::write_u32(&mut proto_len[0..4], segment_len as u32);
+            md5.update(src_addr.as_bytes());
+            md5.update(dst_addr.as_bytes());
+            md5.update(&proto_len);
+        }
+        #[allow(unreachable_patterns)]
+        _ => panic!("Unexpected pseudo header addresses: {src_addr}, {dst_addr}"),
+    }
+    Packet::new_unchecked(&mut header[..]).set_checksum(0);
+    md5.update(&header[..]);
+    md5.update(payload);
+    md5.update(key);
+    md5.finalize()
 }
 
 impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
@@ -600,13 +675,15 @@ pub enum TcpOption<'a> {
     WindowScale(u8),
     SackPermitted,
     SackRange([Option<(u32, u32)>; 3]),
+    Timestamp { tsval: u32, tsecr: u32 },
+    Md5Signature([u8; 16]),
     Unknown { kind: u8, data: &'a [u8] },
 }
 
 impl<'a> TcpOption<'a> {
     pub fn parse(buffer: &'a [u8]) -> Result<(&'a [u8], TcpOption<'a>)> {
         let (length, option);
-        match *buffer.first().ok_or(Error)? {
+        match *buffer.first().ok_or(Error::Truncated)? {
             field::OPT_END => {
                 length = 1;
                 option = TcpOption::EndOfList;
@@ -616,21 +693,21 @@ impl<'a> TcpOption<'a> {
                 option = TcpOption::NoOperation;
             }
             kind => {
-                length = *buffer.get(1).ok_or(Error)? as usize;
-                let data = buffer.get(2..length).ok_or(Error)?;
+                length = *buffer.get(1).ok_or(Error::Truncated)? as usize;
+                let data = buffer.get(2..length).ok_or(Error::Truncated)?;
                 match (kind, length) {
                     (field::OPT_END, _) | (field::OPT_NOP, _) => unreachable!(),
                     (field::OPT_MSS, 4) => {
                         option = TcpOption::MaxSegmentSize(NetworkEndian::read_u16(data))
                     }
-                    (field::OPT_MSS, _) => return Err(Error),
+                    (field::OPT_MSS, _) => return Err(Error::Malformed),
                     (field::OPT_WS, 3) => option = TcpOption::WindowScale(data[0]),
-                    (field::OPT_WS, _) => return Err(Error),
+                    (field::OPT_WS, _) => return Err(Error::Malformed),
                     (field::OPT_SACKPERM, 2) => option = TcpOption::SackPermitted,
-                    (field::OPT_SACKPERM, _) => return Err(Error),
+                    (field::OPT_SACKPERM, _) => return Err(Error::Malformed),
                     (field::OPT_SACKRNG, n) => {
                         if n < 10 || (n - 2) % 8 != 0 {
-                            return Err(Error);
+                            return Err(Error::Malformed);
                         }
                         if n > 26 {
                             // It's possible for a remote to send 4 SACK blocks, but extremely rare.
@@ -663,6 +740,19 @@ impl<'a> TcpOption<'a> {
                         });
                         option = TcpOption::SackRange(sack_ranges);
                     }
+                    (field::OPT_TSTAMP, 10) => {
+                        option = TcpOption::Timestamp {
+                            tsval: NetworkEndian::read_u32(&data[0..4]),
+                            tsecr: NetworkEndian::read_u32(&data[4..8]),
+                        }
+                    }
+                    (field::OPT_TSTAMP, _) => return Err(Error::Malformed),
+                    (field::OPT_MD5, 18) => {
+                        let mut signature = [0; 16];
+                        signature.copy_from_slice(data);
+                        option = TcpOption::Md5Signature(signature)
+                    }
+                    (field::OPT_MD5, _) => return Err(Error::Malformed),
                     (_, _) => option = TcpOption::Unknown { kind, data },
                 }
             }
@@ -678,6 +768,8 @@ impl<'a> TcpOption<'a> {
             TcpOption::WindowScale(_) => 3,
             TcpOption::SackPermitted => 2,
             TcpOption::SackRange(s) => s.iter().filter(|s| s.is_some()).count() * 8 + 2,
+            TcpOption::Timestamp { .. } => 10,
+            TcpOption::Md5Signature(_) => 18,
             TcpOption::Unknown { data, .. } => 2 + data.len(),
         }
     }
@@ -725,6 +817,15 @@ impl<'a> TcpOption<'a> {
                                 NetworkEndian::write_u32(&mut buffer[pos + 4..], second);
                             });
                     }
+                    &TcpOption::Timestamp { tsval, tsecr } => {
+                        buffer[0] = field::OPT_TSTAMP;
+                        NetworkEndian::write_u32(&mut buffer[2..], tsval);
+                        NetworkEndian::write_u32(&mut buffer[6..], tsecr);
+                    }
+                    &TcpOption::Md5Signature(signature) => {
+                        buffer[0] = field::OPT_MD5;
+                        buffer[2..18].copy_from_slice(&signature);
+                    }
                     &TcpOption::Unknown {
                         kind,
                         data: provided,
@@ -742,6 +843,7 @@ impl<'a> TcpOption<'a> {
 /// The possible control flags of a Transmission Control Protocol packet.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Control {
     None,
     Psh,
@@ -772,10 +874,15 @@ impl Control {
 /// A high-level representation of a Transmission Control Protocol packet.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Repr<'a> {
     pub src_port: u16,
     pub dst_port: u16,
     pub control: Control,
+    /// The ECN-Echo flag of RFC 3168.
+    pub ece: bool,
+    /// The Congestion Window Reduced flag of RFC 3168.
+    pub cwr: bool,
     pub seq_number: SeqNumber,
     pub ack_number: Option<SeqNumber>,
     pub window_len: u16,
@@ -783,6 +890,9 @@ pub struct Repr<'a> {
     pub max_seg_size: Option<u16>,
     pub sack_permitted: bool,
     pub sack_ranges: [Option<(u32, u32)>; 3],
+    pub timestamp: Option<(u32, u32)>,
+    /// The MD5 signature option of RFC 2385.
+    pub md5_signature: Option<[u8; 16]>,
     pub payload: &'a [u8],
 }
 
@@ -799,14 +909,14 @@ impl<'a> Repr<'a> {
     {
         // Source and destination ports must be present.
         if packet.src_port() == 0 {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
         if packet.dst_port() == 0 {
-            return Err(Error);
+            return Err(Error::Malformed);
         }
         // Valid checksum is expected.
         if checksum_caps.tcp.rx() && !packet.verify_checksum(src_addr, dst_addr) {
-            return Err(Error);
+            return Err(Error::Checksum);
         }
 
         let control = match (packet.syn(), packet.fin(), packet.rst(), packet.psh()) {
@@ -815,7 +925,7 @@ impl<'a> Repr<'a> {
             (true, false, false, _) => Control::Syn,
             (false, true, false, _) => Control::Fin,
             (false, false, true, _) => Control::Rst,
-            _ => return Err(Error),
+            _ => return Err(Error::Malformed),
         };
         let ack_number = match packet.ack() {
             true => Some(packet.ack_number()),
@@ -831,6 +941,8 @@ impl<'a> Repr<'a> {
         let mut options = packet.options();
         let mut sack_permitted = false;
         let mut sack_ranges = [None, None, None];
+        let mut timestamp = None;
+        let mut md5_signature = None;
         while !options.is_empty() {
             let (next_options, option) = TcpOption::parse(options)?;
             match option {
@@ -857,7 +969,11 @@ impl<'a> Repr<'a> {
                 }
                 TcpOption::SackPermitted => sack_permitted = true,
                 TcpOption::SackRange(slice) => sack_ranges = slice,
-                _ => (),
+                TcpOption::Timestamp { tsval, tsecr } => timestamp = Some((tsval, tsecr)),
+                TcpOption::Md5Signature(signature) => md5_signature = Some(signature),
+                TcpOption::Unknown { kind, .. } => {
+                    net_trace!("tcp: ignoring unknown option {}", kind)
+                }
             }
             options = next_options;
         }
@@ -866,6 +982,8 @@ impl<'a> Repr<'a> {
             src_port: packet.src_port(),
             dst_port: packet.dst_port(),
             control: control,
+            ece: packet.ece(),
+            cwr: packet.cwr(),
             seq_number: packet.seq_number(),
             ack_number: ack_number,
             window_len: packet.window_len(),
@@ -873,14 +991,25 @@ impl<'a> Repr<'a> {
             max_seg_size: max_seg_size,
             sack_permitted: sack_permitted,
             sack_ranges: sack_ranges,
+            timestamp: timestamp,
+            md5_signature: md5_signature,
             payload: packet.payload(),
         })
     }
 
+    /// Query whether the SACK ranges are emitted, which is only the case if they are
+    /// acknowledging data, and there is no SACK-permitted option taking their place.
+    fn emits_sack_ranges(&self) -> bool {
+        !self.sack_permitted
+            && self.ack_number.is_some()
+            && self.sack_ranges.iter().any(|s| s.is_some())
+    }
+
     /// Return the length of a header that will be emitted from this high-level representation.
     ///
     /// This should be used for buffer space calculations.
-    /// The TCP header length is a multiple of 4.
+    /// The TCP header length is a multiple of 4, the options being padded with
+    /// end-of-list options.
     pub fn header_len(&self) -> usize {
         let mut length = field::URGENT.end;
         if self.max_seg_size.is_some() {
@@ -891,14 +1020,14 @@ impl<'a> Repr<'a> {
         }
         if self.sack_permitted {
             length += 2;
+        } else if self.emits_sack_ranges() {
+            length += TcpOption::SackRange(self.sack_ranges).buffer_len();
         }
-        let sack_range_len: usize = self
-            .sack_ranges
-            .iter()
-            .map(|o| o.map(|_| 8).unwrap_or(0))
-            .sum();
-        if sack_range_len > 0 {
-            length += sack_range_len + 2;
+        if self.timestamp.is_some() {
+            length += 10;
+        }
+        if self.md5_signature.is_some() {
+            length += 18;
         }
         if length % 4 != 0 {
             length += 4 - length % 4;
@@ -921,21 +1050,7 @@ impl<'a> Repr<'a> {
     ) where
         T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
     {
-        packet.set_src_port(self.src_port);
-        packet.set_dst_port(self.dst_port);
-        packet.set_seq_number(self.seq_number);
-        packet.set_ack_number(self.ack_number.unwrap_or(SeqNumber(0)));
-        packet.set_window_len(self.window_len);
-        packet.set_header_len(self.header_len() as u8);
-        packet.clear_flags();
-        match self.control {
-            Control::None => (),
-            Control::Psh => packet.set_psh(true),
-            Control::Syn => packet.set_syn(true),
-            Control::Fin => packet.set_fin(true),
-            Control::Rst => packet.set_rst(true),
-        }
-        packet.set_ack(self.ack_number.is_some());
+        self.emit_header(packet);
         {
             let mut options = packet.options_mut();
             if let Some(value) = self.max_seg_size {
@@ -949,16 +1064,23 @@ impl<'a> Repr<'a> {
             if self.sack_permitted {
                 let tmp = options;
                 options = TcpOption::SackPermitted.emit(tmp);
-            } else if self.ack_number.is_some() && self.sack_ranges.iter().any(|s| s.is_some()) {
+            } else if self.emits_sack_ranges() {
                 let tmp = options;
                 options = TcpOption::SackRange(self.sack_ranges).emit(tmp);
             }
+            if let Some((tsval, tsecr)) = self.timestamp {
+                let tmp = options;
+                options = TcpOption::Timestamp { tsval, tsecr }.emit(tmp);
+            }
+            if let Some(signature) = self.md5_signature {
+                let tmp = options;
+                options = TcpOption::Md5Signature(signature).emit(tmp);
+            }
 
             if !options.is_empty() {
                 TcpOption::EndOfList.emit(options);
             }
         }
-        packet.set_urgent_at(0);
         packet.payload_mut()[..self.payload.len()].copy_from_slice(self.payload);
 
         if checksum_caps.tcp.tx() {
@@ -970,6 +1092,53 @@ impl<'a> Repr<'a> {
         }
     }
 
+    /// Emit the header without options, except for the checksum.
+    fn emit_header<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        packet.set_src_port(self.src_port);
+        packet.set_dst_port(self.dst_port);
+        packet.set_seq_number(self.seq_number);
+        packet.set_ack_number(self.ack_number.unwrap_or(SeqNumber(0)));
+        packet.set_window_len(self.window_len);
+        packet.set_header_len(self.header_len() as u8);
+        packet.clear_flags();
+        match self.control {
+            Control::None => (),
+            Control::Psh => packet.set_psh(true),
+            Control::Syn => packet.set_syn(true),
+            Control::Fin => packet.set_fin(true),
+            Control::Rst => packet.set_rst(true),
+        }
+        packet.set_ece(self.ece);
+        packet.set_cwr(self.cwr);
+        packet.set_ack(self.ack_number.is_some());
+        packet.set_urgent_at(0);
+    }
+
+    /// Compute the MD5 signature of the packet that will be emitted from this high-level
+    /// representation with the given key, as specified in RFC 2385.
+    ///
+    /// The signature depends on the length of the options, so `md5_signature` must
+    /// already be set, e.g. to zeros.
+    ///
+    /// # Panics
+    /// This function panics unless `src_addr` and `dst_addr` belong to the same family,
+    /// and that family is IPv4 or IPv6.
+    #[cfg(feature = "socket-tcp-md5")]
+    pub fn compute_md5_signature(
+        &self,
+        src_addr: &IpAddress,
+        dst_addr: &IpAddress,
+        key: &[u8],
+    ) -> [u8; 16] {
+        debug_assert!(self.md5_signature.is_some());
+        let mut header = [0; HEADER_LEN];
+        self.emit_header(&mut Packet::new_unchecked(&mut header[..]));
+        md5_signature(src_addr, dst_addr, &mut header, self.payload, key)
+    }
+
     /// Return the length of the segment, in terms of sequence space.
     pub const fn segment_len(&self) -> usize {
         self.payload.len() + self.control.len()
@@ -1033,6 +1202,8 @@ impl<'a, T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&'a T> {
                 TcpOption::WindowScale(value) => write!(f, " ws={value}")?,
                 TcpOption::SackPermitted => write!(f, " sACK")?,
                 TcpOption::SackRange(slice) => write!(f, " sACKr{slice:?}")?, // debug print conveniently includes the []s
+                TcpOption::Timestamp { tsval, tsecr } => write!(f, " ts={tsval},{tsecr}")?,
+                TcpOption::Md5Signature(_) => write!(f, " md5")?,
                 TcpOption::Unknown { kind, .. } => write!(f, " opt({kind})")?,
             }
             options = next_options;
@@ -1051,6 +1222,12 @@ impl<'a> fmt::Display for Repr<'a> {
             Control::Psh => write!(f, " psh")?,
             Control::None => (),
         }
+        if self.ece {
+            write!(f, " ece")?;
+        }
+        if self.cwr {
+            write!(f, " cwr")?;
+        }
         write!(f, " seq={}", self.seq_number)?;
         if let Some(ack_number) = self.ack_number {
             write!(f, " ack={ack_number}")?;
@@ -1060,6 +1237,9 @@ impl<'a> fmt::Display for Repr<'a> {
         if let Some(max_seg_size) = self.max_seg_size {
             write!(f, " mss={max_seg_size}")?;
         }
+        if let Some((tsval, tsecr)) = self.timestamp {
+            write!(f, " ts={tsval},{tsecr}")?;
+        }
         Ok(())
     }
 }
@@ -1155,7 +1335,7 @@ mod test {
     #[cfg(feature = "proto-ipv4")]
     fn test_truncated() {
         let packet = Packet::new_unchecked(&PACKET_BYTES[..23]);
-        assert_eq!(packet.check_len(), Err(Error));
+        assert_eq!(packet.check_len(), Err(Error::Truncated));
     }
 
     #[test]
@@ -1163,7 +1343,7 @@ mod test {
         let mut bytes = vec![0; 20];
         let mut packet = Packet::new_unchecked(&mut bytes);
         packet.set_header_len(10);
-        assert_eq!(packet.check_len(), Err(Error));
+        assert_eq!(packet.check_len(), Err(Error::Malformed));
     }
 
     #[cfg(feature = "proto-ipv4")]
@@ -1182,9 +1362,13 @@ mod test {
             window_len: 0x0123,
             window_scale: None,
             control: Control::Syn,
+            ece: false,
+            cwr: false,
             max_seg_size: None,
             sack_permitted: false,
             sack_ranges: [None, None, None],
+            timestamp: None,
+            md5_signature: None,
             payload: &PAYLOAD_BYTES,
         }
     }
@@ -1226,6 +1410,158 @@ mod test {
         assert_eq!(repr.header_len() % 4, 0); // Should e.g. be 28 instead of 27.
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_ecn_flags_roundtrip() {
+        let mut repr = packet_repr();
+        repr.ece = true;
+        repr.cwr = true;
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(
+            &mut packet,
+            &SRC_ADDR.into(),
+            &DST_ADDR.into(),
+            &ChecksumCapabilities::default(),
+        );
+        assert!(packet.ece() && packet.cwr() && packet.syn());
+
+        let packet = Packet::new_unchecked(&*packet.into_inner());
+        let parsed = Repr::parse(
+            &packet,
+            &SRC_ADDR.into(),
+            &DST_ADDR.into(),
+            &ChecksumCapabilities::default(),
+        )
+        .unwrap();
+        assert_eq!(parsed, repr);
+    }
+
+    #[cfg(feature = "proto-ipv4")]
+    static SYN_OPTIONS_PACKET_BYTES: [u8; 40] = [
+        0xbf, 0x00, 0x00, 0x50, 0x01, 0x23, 0x45, 0x67, 0x00, 0x00, 0x00, 0x00, 0xa0, 0x02, 0x01,
+        0x23, 0xe5, 0xb0, 0x00, 0x00, 0x02, 0x04, 0x05, 0xb4, 0x03, 0x03, 0x07, 0x04, 0x02, 0x08,
+        0x0a, 0x00, 0x00, 0x04, 0xd2, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_syn_options_roundtrip() {
+        let packet = Packet::new_unchecked(&SYN_OPTIONS_PACKET_BYTES[..]);
+        let repr = Repr::parse(
+            &packet,
+            &SRC_ADDR.into(),
+            &DST_ADDR.into(),
+            &ChecksumCapabilities::ignored(),
+        )
+        .unwrap();
+        assert_eq!(repr.max_seg_size, Some(1460));
+        assert_eq!(repr.window_scale, Some(7));
+        assert!(repr.sack_permitted);
+        assert_eq!(repr.timestamp, Some((1234, 0)));
+        assert_eq!(repr.header_len(), 40);
+
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(
+            &mut packet,
+            &SRC_ADDR.into(),
+            &DST_ADDR.into(),
+            &ChecksumCapabilities::default(),
+        );
+        assert_eq!(&*packet.into_inner(), &SYN_OPTIONS_PACKET_BYTES[..]);
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_options_roundtrip() {
+        // Every combination of options parses back to the representation it was emitted from,
+        // and is emitted into exactly the length reported by header_len().
+        for combination in 0..32 {
+            let mut repr = packet_repr();
+            repr.payload = &[];
+            repr.ack_number = Some(SeqNumber(0x09abcdef));
+            if combination & 1 != 0 {
+                repr.max_seg_size = Some(536);
+            }
+            if combination & 2 != 0 {
+                repr.window_scale = Some(14);
+            }
+            if combination & 4 != 0 {
+                repr.sack_permitted = true;
+            }
+            if combination & 8 != 0 && !repr.sack_permitted {
+                repr.sack_ranges = [Some((1, 2)), Some((3, 4)), None];
+            }
+            if combination & 16 != 0 {
+                repr.timestamp = Some((0xdeadbeef, 0x01020304));
+            }
+
+            let mut bytes = vec![0xa5; repr.buffer_len()];
+            let mut packet = Packet::new_unchecked(&mut bytes);
+            repr.emit(
+                &mut packet,
+                &SRC_ADDR.into(),
+                &DST_ADDR.into(),
+                &ChecksumCapabilities::default(),
+            );
+            assert_eq!(packet.header_len() as usize, repr.header_len());
+            assert_eq!(repr.header_len() % 4, 0);
+
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            let parsed = Repr::parse(
+                &packet,
+                &SRC_ADDR.into(),
+                &DST_ADDR.into(),
+                &ChecksumCapabilities::default(),
+            )
+            .unwrap();
+            assert_eq!(parsed, repr);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-tcp-md5"))]
+    fn test_md5_signature() {
+        let mut repr = packet_repr();
+        repr.md5_signature = Some([0; 16]);
+        let signature = repr.compute_md5_signature(&SRC_ADDR.into(), &DST_ADDR.into(), b"secret");
+        assert_eq!(
+            signature,
+            [
+                0x66, 0x4d, 0xc5, 0xac, 0xbc, 0x0f, 0x57, 0x47, 0x59, 0xa3, 0x81, 0x19, 0x6a, 0xde,
+                0xd3, 0x27
+            ]
+        );
+        repr.md5_signature = Some(signature);
+
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(
+            &mut packet,
+            &SRC_ADDR.into(),
+            &DST_ADDR.into(),
+            &ChecksumCapabilities::default(),
+        );
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        let parsed = Repr::parse(
+            &packet,
+            &SRC_ADDR.into(),
+            &DST_ADDR.into(),
+            &ChecksumCapabilities::default(),
+        )
+        .unwrap();
+        assert_eq!(parsed, repr);
+        assert_eq!(
+            packet.compute_md5_signature(&SRC_ADDR.into(), &DST_ADDR.into(), b"secret"),
+            signature
+        );
+        assert_ne!(
+            packet.compute_md5_signature(&SRC_ADDR.into(), &DST_ADDR.into(), b"wrong"),
+            signature
+        );
+    }
+
     macro_rules! assert_option_parses {
         ($opt:expr, $data:expr) => {{
             assert_eq!(TcpOption::parse($data), Ok((&[][..], $opt)));
@@ -1264,6 +1600,20 @@ mod test {
                 0x00, 0x26, 0x25, 0xa0, 0x34, 0x3e, 0xfc, 0xea, 0x34, 0x40, 0xae, 0xf0
             ]
         );
+        assert_option_parses!(
+            TcpOption::Timestamp {
+                tsval: 0x01020304,
+                tsecr: 0xa0b0c0d0
+            },
+            &[0x08, 0x0a, 0x01, 0x02, 0x03, 0x04, 0xa0, 0xb0, 0xc0, 0xd0]
+        );
+        assert_option_parses!(
+            TcpOption::Md5Signature([0x11; 16]),
+            &[
+                0x13, 0x12, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+                0x11, 0x11, 0x11, 0x11
+            ]
+        );
         assert_option_parses!(
             TcpOption::Unknown {
                 kind: 12,
@@ -1275,11 +1625,16 @@ mod test {
 
     #[test]
     fn test_malformed_tcp_options() {
-        assert_eq!(TcpOption::parse(&[]), Err(Error));
-        assert_eq!(TcpOption::parse(&[0xc]), Err(Error));
-        assert_eq!(TcpOption::parse(&[0xc, 0x05, 0x01, 0x02]), Err(Error));
-        assert_eq!(TcpOption::parse(&[0xc, 0x01]), Err(Error));
-        assert_eq!(TcpOption::parse(&[0x2, 0x02]), Err(Error));
-        assert_eq!(TcpOption::parse(&[0x3, 0x02]), Err(Error));
+        assert_eq!(TcpOption::parse(&[]), Err(Error::Truncated));
+        assert_eq!(TcpOption::parse(&[0xc]), Err(Error::Truncated));
+        assert_eq!(
+            TcpOption::parse(&[0xc, 0x05, 0x01, 0x02]),
+            Err(Error::Truncated)
+        );
+        assert_eq!(TcpOption::parse(&[0xc, 0x01]), Err(Error::Truncated));
+        assert_eq!(TcpOption::parse(&[0x2, 0x02]), Err(Error::Malformed));
+        assert_eq!(TcpOption::parse(&[0x3, 0x02]), Err(Error::Malformed));
+        assert_eq!(TcpOption::parse(&[0x8, 0x02]), Err(Error::Malformed));
+        assert_eq!(TcpOption::parse(&[0x13, 0x02]), Err(Error::Malformed));
     }
 }
@@ -0,0 +1,446 @@
+// Heads up! Before working on this file you should read RFC 1350, which specifies
+// TFTP, and RFC 2347 and 2348, which add option negotiation and the block size option.
+
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, Result};
+
+/// The UDP port of TFTP servers.
+pub const SERVER_PORT: u16 = 69;
+
+/// The size of the data blocks, unless another one was negotiated.
+pub const DEFAULT_BLOCK_SIZE: u16 = 512;
+
+/// The smallest block size that can be negotiated.
+pub const MIN_BLOCK_SIZE: u16 = 8;
+
+/// The largest block size that can be negotiated.
+pub const MAX_BLOCK_SIZE: u16 = 65464;
+
+/// The name of the block size option.
+const BLOCK_SIZE_OPTION: &str = "blksize";
+
+enum_with_unknown! {
+    /// The opcode of a TFTP packet.
+    pub enum OpCode(u16) {
+        Read = 1,
+        Write = 2,
+        Data = 3,
+        Ack = 4,
+        Error = 5,
+        OptionAck = 6,
+    }
+}
+
+enum_with_unknown! {
+    /// The error code of a TFTP error packet.
+    pub enum ErrorCode(u16) {
+        /// Not defined, see the error message.
+        Undefined = 0,
+        FileNotFound = 1,
+        AccessViolation = 2,
+        DiskFull = 3,
+        IllegalOperation = 4,
+        UnknownTransferId = 5,
+        FileExists = 6,
+        NoSuchUser = 7,
+        /// The options of a request were refused.
+        OptionRefused = 8,
+    }
+}
+
+impl ErrorCode {
+    /// Return the message sent along with the error.
+    pub const fn message(&self) -> &'static str {
+        match self {
+            ErrorCode::Undefined | ErrorCode::Unknown(_) => "",
+            ErrorCode::FileNotFound => "File not found",
+            ErrorCode::AccessViolation => "Access violation",
+            ErrorCode::DiskFull => "Disk full or allocation exceeded",
+            ErrorCode::IllegalOperation => "Illegal TFTP operation",
+            ErrorCode::UnknownTransferId => "Unknown transfer ID",
+            ErrorCode::FileExists => "File already exists",
+            ErrorCode::NoSuchUser => "No such user",
+            ErrorCode::OptionRefused => "Options refused",
+        }
+    }
+}
+
+/// The transfer mode of a request.
+///
+/// The obsolete `mail` mode is not supported.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Mode {
+    /// Text, with lines ending with CR LF.
+    NetAscii,
+    /// Raw octets.
+    Octet,
+}
+
+impl Mode {
+    /// Return the name of the mode, as sent in requests.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Mode::NetAscii => "netascii",
+            Mode::Octet => "octet",
+        }
+    }
+
+    fn parse(name: &str) -> Result<Mode> {
+        if name.eq_ignore_ascii_case(Mode::NetAscii.as_str()) {
+            Ok(Mode::NetAscii)
+        } else if name.eq_ignore_ascii_case(Mode::Octet.as_str()) {
+            Ok(Mode::Octet)
+        } else {
+            Err(Error::Malformed)
+        }
+    }
+}
+
+/// A high-level representation of a TFTP packet.
+///
+/// The block size is the only option represented; the other options are skipped when parsing.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Repr<'a> {
+    /// A request to read a file from the server.
+    ReadRequest {
+        filename: &'a str,
+        mode: Mode,
+        block_size: Option<u16>,
+    },
+    /// A request to write a file to the server.
+    WriteRequest {
+        filename: &'a str,
+        mode: Mode,
+        block_size: Option<u16>,
+    },
+    Data {
+        block_number: u16,
+        data: &'a [u8],
+    },
+    Ack {
+        block_number: u16,
+    },
+    Error {
+        code: ErrorCode,
+        message: &'a str,
+    },
+    /// The acknowledgment of the options of a request, listing those the server agreed to.
+    OptionAck {
+        block_size: Option<u16>,
+    },
+}
+
+impl<'a> Repr<'a> {
+    /// Parse a TFTP packet, i.e. the payload of a UDP datagram, and return a high-level
+    /// representation.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        if data.len() < 2 {
+            return Err(Error::Truncated);
+        }
+        let opcode = OpCode::from(NetworkEndian::read_u16(&data[..2]));
+        let data = &data[2..];
+
+        match opcode {
+            OpCode::Read | OpCode::Write => {
+                let (filename, data) = parse_str(data)?;
+                let (mode, data) = parse_str(data)?;
+                let mode = Mode::parse(mode)?;
+                let block_size = parse_options(data)?;
+                if opcode == OpCode::Read {
+                    Ok(Repr::ReadRequest {
+                        filename,
+                        mode,
+                        block_size,
+                    })
+                } else {
+                    Ok(Repr::WriteRequest {
+                        filename,
+                        mode,
+                        block_size,
+                    })
+                }
+            }
+            OpCode::Data | OpCode::Ack | OpCode::Error if data.len() < 2 => Err(Error::Truncated),
+            OpCode::Data => Ok(Repr::Data {
+                block_number: NetworkEndian::read_u16(&data[..2]),
+                data: &data[2..],
+            }),
+            OpCode::Ack => Ok(Repr::Ack {
+                block_number: NetworkEndian::read_u16(&data[..2]),
+            }),
+            OpCode::Error => Ok(Repr::Error {
+                code: ErrorCode::from(NetworkEndian::read_u16(&data[..2])),
+                message: parse_str(&data[2..])?.0,
+            }),
+            OpCode::OptionAck => Ok(Repr::OptionAck {
+                block_size: parse_options(data)?,
+            }),
+            OpCode::Unknown(_) => Err(Error::Malformed),
+        }
+    }
+
+    /// Return the opcode of the packet.
+    pub const fn opcode(&self) -> OpCode {
+        match self {
+            Repr::ReadRequest { .. } => OpCode::Read,
+            Repr::WriteRequest { .. } => OpCode::Write,
+            Repr::Data { .. } => OpCode::Data,
+            Repr::Ack { .. } => OpCode::Ack,
+            Repr::Error { .. } => OpCode::Error,
+            Repr::OptionAck { .. } => OpCode::OptionAck,
+        }
+    }
+
+    /// Return the length of a packet that will be emitted from this high-level representation.
+    pub fn buffer_len(&self) -> usize {
+        2 + match *self {
+            Repr::ReadRequest {
+                filename,
+                mode,
+                block_size,
+            }
+            | Repr::WriteRequest {
+                filename,
+                mode,
+                block_size,
+            } => filename.len() + 1 + mode.as_str().len() + 1 + options_len(block_size),
+            Repr::Data { data, .. } => 2 + data.len(),
+            Repr::Ack { .. } => 2,
+            Repr::Error { message, .. } => 2 + message.len() + 1,
+            Repr::OptionAck { block_size } => options_len(block_size),
+        }
+    }
+
+    /// Emit a high-level representation into a TFTP packet.
+    ///
+    /// # Panics
+    /// This function panics if the packet does not fit into `buffer`.
+    pub fn emit(&self, buffer: &mut [u8]) {
+        NetworkEndian::write_u16(&mut buffer[..2], self.opcode().into());
+        let buffer = &mut buffer[2..];
+
+        match *self {
+            Repr::ReadRequest {
+                filename,
+                mode,
+                block_size,
+            }
+            | Repr::WriteRequest {
+                filename,
+                mode,
+                block_size,
+            } => {
+                let buffer = emit_str(buffer, filename.as_bytes());
+                let buffer = emit_str(buffer, mode.as_str().as_bytes());
+                emit_options(buffer, block_size);
+            }
+            Repr::Data { block_number, data } => {
+                NetworkEndian::write_u16(&mut buffer[..2], block_number);
+                buffer[2..2 + data.len()].copy_from_slice(data);
+            }
+            Repr::Ack { block_number } => NetworkEndian::write_u16(&mut buffer[..2], block_number),
+            Repr::Error { code, message } => {
+                NetworkEndian::write_u16(&mut buffer[..2], code.into());
+                emit_str(&mut buffer[2..], message.as_bytes());
+            }
+            Repr::OptionAck { block_size } => emit_options(buffer, block_size),
+        }
+    }
+}
+
+/// Parse a zero-terminated string at the start of `data`, and return it along with the
+/// rest of `data`.
+fn parse_str(data: &[u8]) -> Result<(&str, &[u8])> {
+    let len = data
+        .iter()
+        .position(|&octet| octet == 0)
+        .ok_or(Error::Truncated)?;
+    let text = core::str::from_utf8(&data[..len]).map_err(|_| Error::Malformed)?;
+    Ok((text, &data[len + 1..]))
+}
+
+/// Parse the name and value pairs of the options of a request or option acknowledgment,
+/// and return the block size.
+fn parse_options(mut data: &[u8]) -> Result<Option<u16>> {
+    let mut block_size = None;
+    while !data.is_empty() {
+        let (name, rest) = parse_str(data)?;
+        let (value, rest) = parse_str(rest)?;
+        if name.eq_ignore_ascii_case(BLOCK_SIZE_OPTION) {
+            match value.parse() {
+                Ok(size) if (MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&size) => {
+                    block_size = Some(size)
+                }
+                _ => return Err(Error::Malformed),
+            }
+        } else {
+            net_trace!("tftp: ignoring option {}", name);
+        }
+        data = rest;
+    }
+    Ok(block_size)
+}
+
+/// Format `value` in decimal at the end of `buffer`, and return the digits.
+fn decimal(mut value: u16, buffer: &mut [u8; 5]) -> &[u8] {
+    let mut start = buffer.len();
+    loop {
+        start -= 1;
+        buffer[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    &buffer[start..]
+}
+
+fn options_len(block_size: Option<u16>) -> usize {
+    match block_size {
+        Some(size) => BLOCK_SIZE_OPTION.len() + 1 + decimal(size, &mut [0; 5]).len() + 1,
+        None => 0,
+    }
+}
+
+/// Emit a zero-terminated string at the start of `buffer`, and return the rest of the buffer.
+fn emit_str<'b>(buffer: &'b mut [u8], text: &[u8]) -> &'b mut [u8] {
+    buffer[..text.len()].copy_from_slice(text);
+    buffer[text.len()] = 0;
+    &mut buffer[text.len() + 1..]
+}
+
+fn emit_options(buffer: &mut [u8], block_size: Option<u16>) {
+    if let Some(size) = block_size {
+        let buffer = emit_str(buffer, BLOCK_SIZE_OPTION.as_bytes());
+        emit_str(buffer, decimal(size, &mut [0; 5]));
+    }
+}
+
+impl<'a> fmt::Display for Repr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Repr::ReadRequest {
+                filename,
+                mode,
+                block_size,
+            } => write!(
+                f,
+                "TFTP RRQ filename={filename:?} mode={} blksize={block_size:?}",
+                mode.as_str()
+            ),
+            Repr::WriteRequest {
+                filename,
+                mode,
+                block_size,
+            } => write!(
+                f,
+                "TFTP WRQ filename={filename:?} mode={} blksize={block_size:?}",
+                mode.as_str()
+            ),
+            Repr::Data { block_number, data } => {
+                write!(f, "TFTP DATA block={block_number} len={}", data.len())
+            }
+            Repr::Ack { block_number } => write!(f, "TFTP ACK block={block_number}"),
+            Repr::Error { code, message } => {
+                write!(f, "TFTP ERROR code={code:?} message={message:?}")
+            }
+            Repr::OptionAck { block_size } => write!(f, "TFTP OACK blksize={block_size:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static RRQ_BYTES: [u8; 28] = [
+        0x00, 0x01, 0x66, 0x77, 0x2e, 0x62, 0x69, 0x6e, 0x00, 0x6f, 0x63, 0x74, 0x65, 0x74, 0x00,
+        0x62, 0x6c, 0x6b, 0x73, 0x69, 0x7a, 0x65, 0x00, 0x31, 0x34, 0x36, 0x38, 0x00,
+    ];
+
+    fn rrq() -> Repr<'static> {
+        Repr::ReadRequest {
+            filename: "fw.bin",
+            mode: Mode::Octet,
+            block_size: Some(1468),
+        }
+    }
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(Repr::parse(&RRQ_BYTES), Ok(rrq()));
+        assert_eq!(
+            Repr::parse(b"\x00\x02fw.bin\x00NetAscii\x00tsize\x000\x00"),
+            Ok(Repr::WriteRequest {
+                filename: "fw.bin",
+                mode: Mode::NetAscii,
+                block_size: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        assert_eq!(Repr::parse(&RRQ_BYTES[..27]), Err(Error::Truncated));
+        assert_eq!(Repr::parse(&[0x00]), Err(Error::Truncated));
+        assert_eq!(Repr::parse(&[0x00, 0x04, 0x00]), Err(Error::Truncated));
+        assert_eq!(
+            Repr::parse(b"\x00\x01fw.bin\x00mail\x00"),
+            Err(Error::Malformed)
+        );
+        assert_eq!(
+            Repr::parse(b"\x00\x06blksize\x0070000\x00"),
+            Err(Error::Malformed)
+        );
+        assert_eq!(
+            Repr::parse(&[0x00, 0x07, 0x00, 0x00]),
+            Err(Error::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_emit_request() {
+        let repr = rrq();
+        assert_eq!(repr.buffer_len(), 28);
+        let mut bytes = [0xff; 28];
+        repr.emit(&mut bytes);
+        assert_eq!(bytes, RRQ_BYTES);
+    }
+
+    #[test]
+    fn test_data_ack_error() {
+        for repr in [
+            Repr::Data {
+                block_number: 0x1234,
+                data: &[0xaa, 0xbb, 0xcc],
+            },
+            Repr::Ack {
+                block_number: 0x1234,
+            },
+            Repr::Error {
+                code: ErrorCode::FileNotFound,
+                message: ErrorCode::FileNotFound.message(),
+            },
+            Repr::OptionAck {
+                block_size: Some(MIN_BLOCK_SIZE),
+            },
+        ] {
+            let mut bytes = [0xff; 64];
+            let bytes = &mut bytes[..repr.buffer_len()];
+            repr.emit(bytes);
+            assert_eq!(Repr::parse(bytes), Ok(repr));
+        }
+
+        let mut bytes = [0xff; 7];
+        Repr::Data {
+            block_number: 0x1234,
+            data: &[0xaa, 0xbb, 0xcc],
+        }
+        .emit(&mut bytes);
+        assert_eq!(bytes, [0x00, 0x03, 0x12, 0x34, 0xaa, 0xbb, 0xcc]);
+    }
+}
@@ -0,0 +1,308 @@
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, EthernetProtocol, Result};
+
+/// A read/write wrapper around an IEEE 802.1Q header.
+///
+/// The header follows the source address of an Ethernet frame with the
+/// [Vlan](enum.EthernetProtocol.html#variant.Vlan) EtherType, and consists of the
+/// tag control information and the EtherType of the payload.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod field {
+    use crate::wire::field::*;
+
+    pub const TCI: Field = 0..2;
+    pub const ETHERTYPE: Field = 2..4;
+    pub const PAYLOAD: Rest = 4..;
+}
+
+/// The 802.1Q header length.
+pub const HEADER_LEN: usize = field::PAYLOAD.start;
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with 802.1Q header structure.
+    pub const fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < HEADER_LEN {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the priority code point field.
+    #[inline]
+    pub fn priority(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::TCI.start] >> 5
+    }
+
+    /// Return the drop eligible indicator field.
+    #[inline]
+    pub fn drop_eligible(&self) -> bool {
+        let data = self.buffer.as_ref();
+        data[field::TCI.start] & 0x10 != 0
+    }
+
+    /// Return the VLAN identifier field.
+    #[inline]
+    pub fn vlan_id(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::TCI]) & 0x0fff
+    }
+
+    /// Return the EtherType of the payload.
+    #[inline]
+    pub fn ethertype(&self) -> EthernetProtocol {
+        let data = self.buffer.as_ref();
+        EthernetProtocol::from(NetworkEndian::read_u16(&data[field::ETHERTYPE]))
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the payload.
+    #[inline]
+    pub fn payload(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::PAYLOAD]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the priority code point field.
+    #[inline]
+    pub fn set_priority(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::TCI.start] = (data[field::TCI.start] & !0xe0) | ((value & 0x07) << 5);
+    }
+
+    /// Set the drop eligible indicator field.
+    #[inline]
+    pub fn set_drop_eligible(&mut self, value: bool) {
+        let data = self.buffer.as_mut();
+        let raw = data[field::TCI.start];
+        data[field::TCI.start] = if value { raw | 0x10 } else { raw & !0x10 };
+    }
+
+    /// Set the VLAN identifier field.
+    #[inline]
+    pub fn set_vlan_id(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        let raw = NetworkEndian::read_u16(&data[field::TCI]);
+        NetworkEndian::write_u16(&mut data[field::TCI], (raw & !0x0fff) | (value & 0x0fff))
+    }
+
+    /// Set the EtherType of the payload.
+    #[inline]
+    pub fn set_ethertype(&mut self, value: EthernetProtocol) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::ETHERTYPE], value.into())
+    }
+
+    /// Return a mutable pointer to the payload.
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        &mut data[field::PAYLOAD]
+    }
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for Packet<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Display for Packet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "802.1Q vid={} pcp={} type={}",
+            self.vlan_id(),
+            self.priority(),
+            self.ethertype()
+        )
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        let packet = match Packet::new_checked(buffer) {
+            Err(err) => return write!(f, "{indent}({err})"),
+            Ok(packet) => packet,
+        };
+        write!(f, "{indent}{packet}")?;
+
+        match packet.ethertype() {
+            #[cfg(feature = "proto-ipv4")]
+            EthernetProtocol::Arp => {
+                indent.increase(f)?;
+                super::ArpPacket::<&[u8]>::pretty_print(&packet.payload(), f, indent)
+            }
+            #[cfg(feature = "proto-ipv4")]
+            EthernetProtocol::Ipv4 => {
+                indent.increase(f)?;
+                super::Ipv4Packet::<&[u8]>::pretty_print(&packet.payload(), f, indent)
+            }
+            #[cfg(feature = "proto-ipv6")]
+            EthernetProtocol::Ipv6 => {
+                indent.increase(f)?;
+                super::Ipv6Packet::<&[u8]>::pretty_print(&packet.payload(), f, indent)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A high-level representation of an IEEE 802.1Q VLAN tag.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Tag {
+    /// The priority code point, from 0 to 7.
+    pub priority: u8,
+    pub drop_eligible: bool,
+    /// The VLAN identifier, from 1 to 4094.
+    pub vlan_id: u16,
+}
+
+impl Tag {
+    /// Create a tag for the VLAN `vlan_id`, with the default priority.
+    pub const fn new(vlan_id: u16) -> Tag {
+        Tag {
+            priority: 0,
+            drop_eligible: false,
+            vlan_id,
+        }
+    }
+
+    /// Parse an 802.1Q header and return a high-level representation of its tag.
+    ///
+    /// Returns `Err(Error::Malformed)` for the reserved VLAN identifier 0xfff. The identifier 0,
+    /// used by priority tagged frames, is accepted.
+    pub fn parse<T: AsRef<[u8]> + ?Sized>(packet: &Packet<&T>) -> Result<Tag> {
+        packet.check_len()?;
+        if packet.vlan_id() == 0xfff {
+            return Err(Error::Malformed);
+        }
+        Ok(Tag {
+            priority: packet.priority(),
+            drop_eligible: packet.drop_eligible(),
+            vlan_id: packet.vlan_id(),
+        })
+    }
+
+    /// Return the length of a header that will be emitted from this high-level representation.
+    pub const fn buffer_len(&self) -> usize {
+        HEADER_LEN
+    }
+
+    /// Emit a high-level representation of the tag into an 802.1Q header.
+    pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut Packet<T>) {
+        packet.set_priority(self.priority);
+        packet.set_drop_eligible(self.drop_eligible);
+        packet.set_vlan_id(self.vlan_id);
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "802.1Q vid={} pcp={}", self.vlan_id, self.priority)?;
+        if self.drop_eligible {
+            write!(f, " dei")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static PACKET_BYTES: [u8; 8] = [0xa0, 0x64, 0x08, 0x00, 0xaa, 0x00, 0x00, 0xff];
+
+    static PAYLOAD_BYTES: [u8; 4] = [0xaa, 0x00, 0x00, 0xff];
+
+    fn tag() -> Tag {
+        Tag {
+            priority: 5,
+            drop_eligible: false,
+            vlan_id: 100,
+        }
+    }
+
+    #[test]
+    fn test_deconstruct() {
+        let packet = Packet::new_unchecked(&PACKET_BYTES[..]);
+        assert_eq!(packet.priority(), 5);
+        assert!(!packet.drop_eligible());
+        assert_eq!(packet.vlan_id(), 100);
+        assert_eq!(packet.ethertype(), EthernetProtocol::Ipv4);
+        assert_eq!(packet.payload(), &PAYLOAD_BYTES[..]);
+    }
+
+    #[test]
+    fn test_construct() {
+        let mut bytes = vec![0xff; 8];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        packet.set_priority(5);
+        packet.set_drop_eligible(false);
+        packet.set_vlan_id(100);
+        packet.set_ethertype(EthernetProtocol::Ipv4);
+        packet.payload_mut().copy_from_slice(&PAYLOAD_BYTES[..]);
+        assert_eq!(&packet.into_inner()[..], &PACKET_BYTES[..]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = Packet::new_unchecked(&PACKET_BYTES[..]);
+        assert_eq!(Tag::parse(&packet), Ok(tag()));
+    }
+
+    #[test]
+    fn test_parse_reserved() {
+        let bytes = [0x0f, 0xff, 0x08, 0x00];
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert_eq!(Tag::parse(&packet), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn test_emit() {
+        let mut bytes = PACKET_BYTES;
+        bytes[0] = 0xff;
+        let mut packet = Packet::new_unchecked(&mut bytes[..]);
+        tag().emit(&mut packet);
+        assert_eq!(&packet.into_inner()[..], &PACKET_BYTES[..]);
+    }
+}